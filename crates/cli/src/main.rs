@@ -235,6 +235,20 @@ impl Client {
         url: &str,
         body: Option<B>,
     ) -> Option<R> {
+        self.checked_http_request(method, url, body)
+            .await
+            .unwrap_or_else(|err| {
+                eprintln!("Request failed: {err}");
+                std::process::exit(1);
+            })
+    }
+
+    pub async fn checked_http_request<R: DeserializeOwned, B: Serialize>(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<B>,
+    ) -> Result<Option<R>, String> {
         let url = format!(
             "{}{}{}",
             self.url,
@@ -268,18 +282,14 @@ impl Client {
         match response.status() {
             StatusCode::OK => (),
             StatusCode::NOT_FOUND => {
-                return None;
+                return Ok(None);
             }
             StatusCode::UNAUTHORIZED => {
                 eprintln!("Authentication failed. Make sure the credentials are correct and that the account has administrator rights.");
                 std::process::exit(1);
             }
             _ => {
-                eprintln!(
-                    "Request failed: {}",
-                    response.text().await.unwrap_result("fetch text")
-                );
-                std::process::exit(1);
+                return Err(response.text().await.unwrap_result("fetch text"));
             }
         }
 
@@ -288,11 +298,8 @@ impl Client {
             "deserialize response {}",
             String::from_utf8_lossy(bytes.as_ref())
         )) {
-            Response::Data { data } => Some(data),
-            Response::Error(error) => {
-                eprintln!("Request failed: {error})");
-                std::process::exit(1);
-            }
+            Response::Data { data } => Ok(Some(data)),
+            Response::Error(error) => Err(error.to_string()),
         }
     }
 }
@@ -21,16 +21,17 @@
  * for more details.
 */
 
-use std::fmt::Display;
+use std::{fmt::Display, io::Write};
 
+use futures::future::join_all;
 use prettytable::{Attr, Cell, Row, Table};
 use pwhash::sha512_crypt;
 use reqwest::Method;
 use serde_json::Value;
 
 use super::{
-    cli::{AccountCommands, Client},
-    Principal, PrincipalField, PrincipalUpdate, PrincipalValue, Type,
+    cli::{AccountCommands, Client, PrincipalFormat},
+    read_file, Principal, PrincipalField, PrincipalUpdate, PrincipalValue, Type, UnwrapResult,
 };
 
 impl AccountCommands {
@@ -242,6 +243,24 @@ impl AccountCommands {
                     .list_principals("individual", "Account", filter, page, limit)
                     .await;
             }
+            AccountCommands::Import {
+                format,
+                batch_size,
+                path,
+            } => {
+                client
+                    .import_principals(format, batch_size.max(1), &path)
+                    .await;
+            }
+            AccountCommands::Export {
+                format,
+                batch_size,
+                path,
+            } => {
+                client
+                    .export_principals(format, batch_size.max(1), &path)
+                    .await;
+            }
         }
     }
 }
@@ -369,6 +388,269 @@ struct ListResponse {
     pub items: Vec<String>,
 }
 
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct PrincipalRecord {
+    #[serde(rename = "type")]
+    typ: Option<Type>,
+    name: String,
+    secrets: Option<String>,
+    description: Option<String>,
+    quota: Option<u32>,
+    emails: Option<String>,
+    #[serde(rename = "memberOf")]
+    member_of: Option<String>,
+    members: Option<String>,
+}
+
+impl Client {
+    pub async fn import_principals(&self, format: PrincipalFormat, batch_size: usize, path: &str) {
+        let contents = read_file(path);
+        let mut principals = Vec::new();
+        let mut failures = Vec::new();
+
+        match format {
+            PrincipalFormat::Csv => {
+                let mut reader = csv::Reader::from_reader(contents.as_slice());
+                let headers = reader.headers().unwrap_result("read CSV headers").clone();
+                let name_pos = headers.iter().position(|header| header == "name");
+
+                for record in reader.records() {
+                    match record {
+                        Ok(record) => {
+                            let row = record.position().map_or(0, |pos| pos.line() as usize);
+                            match record.deserialize::<PrincipalRecord>(Some(&headers)) {
+                                Ok(principal) => principals.push((row, Principal::from(principal))),
+                                Err(err) => failures.push((
+                                    row,
+                                    name_pos
+                                        .and_then(|pos| record.get(pos))
+                                        .unwrap_or_default()
+                                        .to_string(),
+                                    err.to_string(),
+                                )),
+                            }
+                        }
+                        Err(err) => failures.push((
+                            err.position().map_or(0, |pos| pos.line() as usize),
+                            String::new(),
+                            err.to_string(),
+                        )),
+                    }
+                }
+            }
+            PrincipalFormat::Json => {
+                principals = serde_json::from_slice::<Vec<Principal>>(&contents)
+                    .unwrap_result("parse JSON file")
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, principal)| (idx + 1, principal))
+                    .collect();
+            }
+        }
+
+        // Hash plain-text passwords and discard server-assigned fields
+        principals.retain_mut(|(row, principal)| {
+            if principal.name.as_ref().map_or(true, |name| name.is_empty()) {
+                failures.push((*row, String::new(), "Missing principal name.".to_string()));
+                return false;
+            }
+            principal.id = None;
+            principal.used_quota = None;
+            principal.typ.get_or_insert(Type::Individual);
+            for secret in &mut principal.secrets {
+                if !secret.starts_with('$') && !secret.starts_with('{') {
+                    *secret = sha512_crypt::hash(secret.as_str()).unwrap();
+                }
+            }
+            true
+        });
+
+        // Groups have to exist before their members are created, and lists
+        // are created last so their members can be resolved
+        principals.sort_by_key(|(_, principal)| principal.typ.map(Type::import_order));
+
+        let total = principals.len() + failures.len();
+        let mut imported = 0;
+        for (batch_num, batch) in principals.chunks(batch_size).enumerate() {
+            let results = join_all(batch.iter().map(|(_, principal)| {
+                self.checked_http_request::<u32, _>(Method::POST, "/api/principal", Some(principal))
+            }))
+            .await;
+
+            for ((row, principal), result) in batch.iter().zip(results) {
+                let name = principal.name.clone().unwrap_or_default();
+                match result {
+                    Ok(Some(_)) => {
+                        imported += 1;
+                    }
+                    Ok(None) => {
+                        failures.push((*row, name, "Not found.".to_string()));
+                    }
+                    Err(err) => {
+                        failures.push((*row, name, err));
+                    }
+                }
+            }
+
+            eprintln!(
+                "Processed batch {} ({} of {} principals).",
+                batch_num + 1,
+                (batch_num * batch_size + batch.len()).min(principals.len()),
+                principals.len()
+            );
+        }
+
+        if !failures.is_empty() {
+            failures.sort_by_key(|(row, _, _)| *row);
+            let mut table = Table::new();
+            table.add_row(Row::new(vec![
+                Cell::new("Row").with_style(Attr::Bold),
+                Cell::new("Name").with_style(Attr::Bold),
+                Cell::new("Error").with_style(Attr::Bold),
+            ]));
+            for (row, name, error) in &failures {
+                table.add_row(Row::new(vec![
+                    Cell::new(&row.to_string()),
+                    Cell::new(name),
+                    Cell::new(error),
+                ]));
+            }
+            eprintln!();
+            table.printstd();
+            eprintln!();
+        }
+
+        eprintln!(
+            "Successfully imported {imported} of {total} principal{}.",
+            if total == 1 { "" } else { "s" }
+        );
+
+        if !failures.is_empty() {
+            std::process::exit(1);
+        }
+    }
+
+    pub async fn export_principals(&self, format: PrincipalFormat, batch_size: usize, path: &str) {
+        let names = self
+            .http_request::<ListResponse, String>(Method::GET, "/api/principal", None)
+            .await
+            .items;
+
+        let mut principals = Vec::with_capacity(names.len());
+        for batch in names.chunks(batch_size) {
+            let results = join_all(batch.iter().map(|name| {
+                self.try_http_request::<Principal, String>(
+                    Method::GET,
+                    &format!("/api/principal/{name}"),
+                    None,
+                )
+            }))
+            .await;
+
+            for mut principal in results.into_iter().flatten() {
+                principal.id = None;
+                principal.used_quota = None;
+                principals.push(principal);
+            }
+        }
+        principals.sort_by(|a, b| {
+            a.typ
+                .map(Type::import_order)
+                .cmp(&b.typ.map(Type::import_order))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let writer: Box<dyn Write> = if path == "-" {
+            Box::new(std::io::stdout())
+        } else {
+            Box::new(std::fs::File::create(path).unwrap_result("create export file"))
+        };
+        match format {
+            PrincipalFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(writer);
+                for principal in &principals {
+                    writer
+                        .serialize(PrincipalRecord::from(principal.clone()))
+                        .unwrap_result("write CSV record");
+                }
+                writer.flush().unwrap_result("write CSV file");
+            }
+            PrincipalFormat::Json => {
+                serde_json::to_writer_pretty(writer, &principals).unwrap_result("write JSON file");
+            }
+        }
+
+        eprintln!(
+            "Exported {} principal{}.",
+            principals.len(),
+            if principals.len() == 1 { "" } else { "s" }
+        );
+    }
+}
+
+impl From<PrincipalRecord> for Principal {
+    fn from(record: PrincipalRecord) -> Self {
+        Principal {
+            typ: record.typ,
+            quota: record.quota,
+            name: Some(record.name),
+            secrets: split_list(record.secrets),
+            emails: split_list(record.emails),
+            member_of: split_list(record.member_of),
+            members: split_list(record.members),
+            description: record.description,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Principal> for PrincipalRecord {
+    fn from(principal: Principal) -> Self {
+        PrincipalRecord {
+            typ: principal.typ,
+            name: principal.name.unwrap_or_default(),
+            secrets: join_list(principal.secrets),
+            description: principal.description,
+            quota: principal.quota,
+            emails: join_list(principal.emails),
+            member_of: join_list(principal.member_of),
+            members: join_list(principal.members),
+        }
+    }
+}
+
+fn split_list(value: Option<String>) -> Vec<String> {
+    value
+        .map(|value| {
+            value
+                .split(';')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn join_list(items: Vec<String>) -> Option<String> {
+    if !items.is_empty() {
+        Some(items.join(";"))
+    } else {
+        None
+    }
+}
+
+impl Type {
+    fn import_order(self) -> u8 {
+        match self {
+            Type::Group => 0,
+            Type::List => 2,
+            _ => 1,
+        }
+    }
+}
+
 impl Display for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
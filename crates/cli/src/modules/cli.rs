@@ -197,6 +197,35 @@ pub enum AccountCommands {
         /// Page number
         page: Option<usize>,
     },
+
+    /// Bulk import accounts, groups and their aliases from a CSV or JSON file
+    Import {
+        #[clap(value_enum)]
+        #[clap(short, long, default_value = "csv")]
+        format: PrincipalFormat,
+
+        /// Number of principals to submit per batch
+        #[clap(short, long, default_value = "100")]
+        batch_size: usize,
+
+        /// Path to the file to import, or '-' for stdin
+        path: String,
+    },
+
+    /// Bulk export all accounts, groups and their aliases to a CSV or JSON file
+    Export {
+        #[clap(value_enum)]
+        #[clap(short, long, default_value = "csv")]
+        format: PrincipalFormat,
+
+        /// Number of principals to fetch per batch
+        #[clap(short, long, default_value = "100")]
+        batch_size: usize,
+
+        /// Path to export the principals to, or '-' for stdout
+        #[clap(default_value = "-")]
+        path: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -446,6 +475,14 @@ pub enum MailboxFormat {
     MaildirNested,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum PrincipalFormat {
+    /// Comma-separated values, one principal per row and lists separated by ';'
+    Csv,
+    /// JSON array of principals
+    Json,
+}
+
 #[derive(Subcommand)]
 pub enum QueueCommands {
     /// Shows messages queued for delivery
@@ -30,6 +30,7 @@ use crate::{
     config::smtp::session::AddressMapping,
     expr::{
         functions::ResolveVariable, if_block::IfBlock, tokenizer::TokenMap, Variable, V_RECIPIENT,
+        V_RECIPIENT_DOMAIN, V_RECIPIENT_LOCAL_PART,
     },
     Core,
 };
@@ -71,6 +72,16 @@ impl Core {
     }
 
     pub async fn rcpt(&self, directory: &Directory, email: &str) -> directory::Result<bool> {
+        self.rcpt_status(directory, email)
+            .await
+            .map(|status| matches!(status, RcptStatus::Found))
+    }
+
+    pub async fn rcpt_status(
+        &self,
+        directory: &Directory,
+        email: &str,
+    ) -> directory::Result<RcptStatus> {
         // Expand subaddress
         let mut address = self
             .smtp
@@ -82,22 +93,28 @@ impl Core {
 
         for _ in 0..2 {
             if directory.rcpt(address.as_ref()).await? {
-                return Ok(true);
-            } else if let Some(catch_all) = self
+                return Ok(RcptStatus::Found);
+            }
+
+            match self
                 .smtp
                 .session
                 .rcpt
                 .catch_all
-                .to_catch_all(self, email)
+                .resolve_catch_all(self, email)
                 .await
             {
-                address = catch_all;
-            } else {
-                break;
+                Some(CatchAll::Address(catch_all)) => {
+                    address = catch_all;
+                }
+                Some(CatchAll::Reject(reason)) => {
+                    return Ok(RcptStatus::Rejected(reason));
+                }
+                None => break,
             }
         }
 
-        Ok(false)
+        Ok(RcptStatus::NotFound)
     }
 
     pub async fn vrfy(
@@ -159,6 +176,8 @@ impl AddressMapping {
                 ("address", V_RECIPIENT),
                 ("email", V_RECIPIENT),
                 ("rcpt", V_RECIPIENT),
+                ("local_part", V_RECIPIENT_LOCAL_PART),
+                ("domain", V_RECIPIENT_DOMAIN),
             ]),
         ) {
             AddressMapping::Custom(if_block)
@@ -168,11 +187,35 @@ impl AddressMapping {
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum RcptStatus {
+    Found,
+    NotFound,
+    Rejected(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CatchAll<'x> {
+    Address(Cow<'x, str>),
+    Reject(String),
+}
+
 struct Address<'x>(&'x str);
 
 impl ResolveVariable for Address<'_> {
-    fn resolve_variable(&self, _: u32) -> crate::expr::Variable {
-        Variable::from(self.0)
+    fn resolve_variable(&self, variable: u32) -> crate::expr::Variable {
+        match variable {
+            V_RECIPIENT_LOCAL_PART => self
+                .0
+                .rsplit_once('@')
+                .map_or(self.0, |(local_part, _)| local_part),
+            V_RECIPIENT_DOMAIN => self
+                .0
+                .rsplit_once('@')
+                .map_or("", |(_, domain_part)| domain_part),
+            _ => self.0,
+        }
+        .into()
     }
 }
 
@@ -210,11 +253,21 @@ impl AddressMapping {
         core: &Core,
         address: &'y str,
     ) -> Option<Cow<'x, str>> {
+        match self.resolve_catch_all(core, address).await {
+            Some(CatchAll::Address(address)) => Some(address),
+            _ => None,
+        }
+    }
+
+    pub async fn resolve_catch_all<'x, 'y: 'x>(
+        &'x self,
+        core: &Core,
+        address: &'y str,
+    ) -> Option<CatchAll<'x>> {
         match self {
             AddressMapping::Enable => address
                 .rsplit_once('@')
-                .map(|(_, domain_part)| format!("@{}", domain_part))
-                .map(Cow::Owned),
+                .map(|(_, domain_part)| CatchAll::Address(format!("@{}", domain_part).into())),
 
             AddressMapping::Custom(if_block) => {
                 if let Ok(result) = String::try_from(
@@ -222,7 +275,12 @@ impl AddressMapping {
                         .eval(&Address(address), core, "session.rcpt.catch-all")
                         .await,
                 ) {
-                    Some(result.into())
+                    // Expressions may return an SMTP reply to reject the recipient
+                    if is_smtp_reply(&result) {
+                        Some(CatchAll::Reject(result))
+                    } else {
+                        Some(CatchAll::Address(result.into()))
+                    }
                 } else {
                     None
                 }
@@ -231,3 +289,12 @@ impl AddressMapping {
         }
     }
 }
+
+fn is_smtp_reply(value: &str) -> bool {
+    let value = value.as_bytes();
+    value.len() > 4
+        && matches!(value[0], b'4' | b'5')
+        && value[1].is_ascii_digit()
+        && value[2].is_ascii_digit()
+        && value[3] == b' '
+}
@@ -0,0 +1,34 @@
+use utils::config::Config;
+
+#[derive(Default, Clone)]
+pub struct DavConfig {
+    pub enable: bool,
+    pub max_request_size: usize,
+    pub max_resource_size: usize,
+    pub default_calendar_name: String,
+    pub default_addressbook_name: String,
+}
+
+impl DavConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        DavConfig {
+            enable: config
+                .property_or_default("dav.enable", "true")
+                .unwrap_or(true),
+            max_request_size: config
+                .property_or_default("dav.request.max-size", "4194304")
+                .unwrap_or(4 * 1024 * 1024),
+            max_resource_size: config
+                .property_or_default("dav.resource.max-size", "1048576")
+                .unwrap_or(1024 * 1024),
+            default_calendar_name: config
+                .value("dav.default.calendar-name")
+                .unwrap_or("Calendar")
+                .to_string(),
+            default_addressbook_name: config
+                .value("dav.default.addressbook-name")
+                .unwrap_or("Contacts")
+                .to_string(),
+        }
+    }
+}
@@ -2,12 +2,16 @@ use std::time::Duration;
 
 use utils::config::{Config, Rate};
 
+use crate::sasl::SaslMechanism;
+
 #[derive(Default, Clone)]
 pub struct ImapConfig {
     pub max_request_size: usize,
+    pub max_non_sync_literal: Option<u32>,
     pub max_auth_failures: u32,
     pub name_shared: String,
     pub allow_plain_auth: bool,
+    pub sasl_mechanisms: Vec<SaslMechanism>,
 
     pub timeout_auth: Duration,
     pub timeout_unauth: Duration,
@@ -15,6 +19,7 @@ pub struct ImapConfig {
 
     pub rate_requests: Option<Rate>,
     pub rate_concurrent: Option<u64>,
+    pub max_connections_per_ip: Option<u64>,
 }
 
 impl ImapConfig {
@@ -23,6 +28,9 @@ impl ImapConfig {
             max_request_size: config
                 .property_or_default("imap.request.max-size", "52428800")
                 .unwrap_or(52428800),
+            max_non_sync_literal: config
+                .property::<Option<u32>>("imap.request.max-non-sync-literal")
+                .unwrap_or_default(),
             max_auth_failures: config
                 .property_or_default("imap.auth.max-failures", "3")
                 .unwrap_or(3),
@@ -43,11 +51,24 @@ impl ImapConfig {
                 .property_or_default::<Option<Rate>>("imap.rate-limit.requests", "2000/1m")
                 .unwrap_or_default(),
             rate_concurrent: config
-                .property::<Option<u64>>("imap.rate-limit.concurrent")
+                .property::<Option<u64>>("imap.limits.concurrent-connections-per-user")
+                .unwrap_or_default()
+                .or_else(|| {
+                    config
+                        .property::<Option<u64>>("imap.rate-limit.concurrent")
+                        .unwrap_or_default()
+                }),
+            max_connections_per_ip: config
+                .property::<Option<u64>>("imap.limits.concurrent-connections-per-ip")
                 .unwrap_or_default(),
             allow_plain_auth: config
                 .property_or_default("imap.auth.allow-plain-text", "false")
                 .unwrap_or(false),
+            sasl_mechanisms: config
+                .properties::<SaslMechanism>("imap.auth.mechanisms")
+                .into_iter()
+                .map(|(_, mechanism)| mechanism)
+                .collect(),
         }
     }
 }
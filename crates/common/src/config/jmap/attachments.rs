@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use utils::config::Config;
+
+#[derive(Debug, Clone)]
+pub struct AttachmentExtraction {
+    pub max_size: usize,
+    pub max_text: usize,
+    pub timeout: Duration,
+    pub extractors: Vec<BuiltinExtractor>,
+    pub command: Option<ExtractCommand>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinExtractor {
+    Pdf,
+    Docx,
+    Odf,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtractCommand {
+    pub command: String,
+    pub arguments: Vec<String>,
+    pub content_types: Vec<String>,
+}
+
+impl AttachmentExtraction {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("storage.full-text.attachments.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        let mut extractors = Vec::new();
+        let values = config
+            .values("storage.full-text.attachments.extractors")
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>();
+        let has_values = !values.is_empty();
+        for (key, value) in values {
+            let extractor = match value.as_str() {
+                "pdf" => BuiltinExtractor::Pdf,
+                "docx" => BuiltinExtractor::Docx,
+                "odf" => BuiltinExtractor::Odf,
+                _ => {
+                    config.new_parse_error(
+                        key,
+                        format!("Invalid extractor {value:?}, expected 'pdf', 'docx' or 'odf'"),
+                    );
+                    continue;
+                }
+            };
+            if !extractors.contains(&extractor) {
+                extractors.push(extractor);
+            }
+        }
+        if !has_values {
+            extractors = vec![
+                BuiltinExtractor::Pdf,
+                BuiltinExtractor::Docx,
+                BuiltinExtractor::Odf,
+            ];
+        }
+
+        let command = config
+            .value("storage.full-text.attachments.command.path")
+            .map(|command| ExtractCommand {
+                command: command.to_string(),
+                arguments: config
+                    .values("storage.full-text.attachments.command.arguments")
+                    .map(|(_, v)| v.to_string())
+                    .collect(),
+                content_types: config
+                    .values("storage.full-text.attachments.command.content-types")
+                    .map(|(_, v)| v.to_ascii_lowercase())
+                    .collect(),
+            });
+
+        Some(AttachmentExtraction {
+            max_size: config
+                .property("storage.full-text.attachments.max-size")
+                .unwrap_or(10 * 1024 * 1024),
+            max_text: config
+                .property("storage.full-text.attachments.max-text")
+                .unwrap_or(1024 * 1024),
+            timeout: config
+                .property_or_default("storage.full-text.attachments.timeout", "30s")
+                .unwrap_or_else(|| Duration::from_secs(30)),
+            extractors,
+            command,
+        })
+    }
+}
+
+impl BuiltinExtractor {
+    // Attachments are matched by content type first, falling back to the file name
+    // since many clients send office documents as application/octet-stream.
+    pub fn matches(&self, content_type: &str, name: &str) -> bool {
+        match self {
+            BuiltinExtractor::Pdf => content_type == "application/pdf" || name.ends_with(".pdf"),
+            BuiltinExtractor::Docx => {
+                content_type
+                    == "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+                    || name.ends_with(".docx")
+            }
+            BuiltinExtractor::Odf => {
+                content_type.starts_with("application/vnd.oasis.opendocument.")
+                    || [".odt", ".ods", ".odp"]
+                        .iter()
+                        .any(|ext| name.ends_with(ext))
+            }
+        }
+    }
+}
+
+impl ExtractCommand {
+    pub fn matches(&self, content_type: &str) -> bool {
+        self.content_types
+            .iter()
+            .any(|ct| ct == "*" || ct == content_type)
+    }
+}
@@ -29,6 +29,11 @@ impl JmapConfig {
                     "i;ascii-casemap".to_string(),
                     "i;unicode-casemap".to_string(),
                 ],
+                max_request_cost: self.request_cost.as_ref().map(|cost| cost.budget.requests),
+                request_cost_period: self
+                    .request_cost
+                    .as_ref()
+                    .map(|cost| cost.budget.period.as_secs()),
             }),
         );
 
@@ -168,5 +173,22 @@ impl JmapConfig {
             Capability::Quota,
             Capabilities::Empty(EmptyCapabilities::default()),
         );
+
+        // Add calendars and contacts capabilities
+        if config
+            .property_or_default::<bool>("dav.enable", "true")
+            .unwrap_or(true)
+        {
+            for capability in [Capability::Calendars, Capability::Contacts] {
+                self.capabilities.session.append(
+                    capability,
+                    Capabilities::Empty(EmptyCapabilities::default()),
+                );
+                self.capabilities.account.append(
+                    capability,
+                    Capabilities::Empty(EmptyCapabilities::default()),
+                );
+            }
+        }
     }
 }
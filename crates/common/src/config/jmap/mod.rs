@@ -1,2 +1,4 @@
+pub mod attachments;
 pub mod capabilities;
+pub mod retention;
 pub mod settings;
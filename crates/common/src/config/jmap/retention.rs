@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use utils::config::Config;
+
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    pub id: String,
+    pub role: String,
+    pub action: RetentionAction,
+    pub after: Duration,
+    pub scope: RetentionScope,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionAction {
+    Delete,
+    Archive,
+    Keep,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetentionScope {
+    Server,
+    Domain(String),
+    Account(String),
+}
+
+impl RetentionPolicy {
+    pub fn parse_all(config: &mut Config) -> Vec<RetentionPolicy> {
+        let mut policies = Vec::new();
+
+        for id in config
+            .sub_keys("jmap.retention", ".role")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+        {
+            let role = config
+                .value(("jmap.retention", id.as_str(), "role"))
+                .unwrap()
+                .to_lowercase();
+            let action = match config
+                .value(("jmap.retention", id.as_str(), "action"))
+                .unwrap_or("delete")
+            {
+                "delete" => RetentionAction::Delete,
+                "archive" => RetentionAction::Archive,
+                "keep" => RetentionAction::Keep,
+                action => {
+                    config.new_parse_error(
+                        ("jmap.retention", id.as_str(), "action"),
+                        format!(
+                            "Invalid retention action {action:?}, expected 'delete', 'archive' or 'keep'"
+                        ),
+                    );
+                    continue;
+                }
+            };
+            let after = if action != RetentionAction::Keep {
+                if let Some(after) =
+                    config.property_require::<Duration>(("jmap.retention", id.as_str(), "after"))
+                {
+                    after
+                } else {
+                    continue;
+                }
+            } else {
+                Duration::ZERO
+            };
+            let scope = match (
+                config.value(("jmap.retention", id.as_str(), "account")),
+                config.value(("jmap.retention", id.as_str(), "domain")),
+            ) {
+                (Some(account), None) => RetentionScope::Account(account.to_lowercase()),
+                (None, Some(domain)) => RetentionScope::Domain(domain.to_lowercase()),
+                (None, None) => RetentionScope::Server,
+                (Some(_), Some(_)) => {
+                    config.new_build_error(
+                        ("jmap.retention", id.as_str()),
+                        "A retention policy can be scoped to either an account or a domain",
+                    );
+                    continue;
+                }
+            };
+
+            policies.push(RetentionPolicy {
+                id,
+                role,
+                action,
+                after,
+                scope,
+            });
+        }
+
+        policies
+    }
+
+    // Returns the policy for each role that applies to an account, account policies
+    // take precedence over domain policies, which take precedence over server ones.
+    pub fn effective<'x>(
+        policies: &'x [RetentionPolicy],
+        names: &[&str],
+    ) -> Vec<&'x RetentionPolicy> {
+        let mut effective: Vec<&RetentionPolicy> = Vec::new();
+        for policy in policies {
+            let precedence = match policy.precedence(names) {
+                Some(precedence) => precedence,
+                None => continue,
+            };
+            if let Some(current) = effective.iter_mut().find(|p| p.role == policy.role) {
+                if current.precedence(names).unwrap_or_default() < precedence {
+                    *current = policy;
+                }
+            } else {
+                effective.push(policy);
+            }
+        }
+        effective
+    }
+
+    fn precedence(&self, names: &[&str]) -> Option<u32> {
+        match &self.scope {
+            RetentionScope::Server => Some(0),
+            RetentionScope::Domain(domain) => names
+                .iter()
+                .any(|name| {
+                    name.rsplit_once('@')
+                        .map_or(false, |(_, d)| d.eq_ignore_ascii_case(domain))
+                })
+                .then_some(1),
+            RetentionScope::Account(account) => names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(account))
+                .then_some(2),
+        }
+    }
+
+    pub fn is_scoped(&self) -> bool {
+        self.scope != RetentionScope::Server
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{RetentionAction, RetentionPolicy, RetentionScope};
+
+    #[test]
+    fn retention_precedence() {
+        let policy = |id: &str, role: &str, scope: RetentionScope| RetentionPolicy {
+            id: id.to_string(),
+            role: role.to_string(),
+            action: RetentionAction::Delete,
+            after: Duration::from_secs(86400),
+            scope,
+        };
+        let policies = vec![
+            policy(
+                "account",
+                "trash",
+                RetentionScope::Account("jdoe@example.org".into()),
+            ),
+            policy("server", "trash", RetentionScope::Server),
+            policy(
+                "domain",
+                "trash",
+                RetentionScope::Domain("example.org".into()),
+            ),
+            policy("junk", "junk", RetentionScope::Server),
+            policy(
+                "other",
+                "sent",
+                RetentionScope::Domain("example.com".into()),
+            ),
+        ];
+
+        for (names, expected) in [
+            (vec!["jdoe", "jdoe@example.org"], vec!["account", "junk"]),
+            (vec!["jane@example.org"], vec!["domain", "junk"]),
+            (vec!["bill@foobar.org"], vec!["server", "junk"]),
+        ] {
+            let mut effective = RetentionPolicy::effective(&policies, &names)
+                .into_iter()
+                .map(|policy| policy.id.as_str())
+                .collect::<Vec<_>>();
+            effective.sort_unstable();
+            assert_eq!(effective, expected, "{names:?}");
+        }
+    }
+}
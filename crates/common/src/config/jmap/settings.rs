@@ -1,14 +1,54 @@
 use std::{str::FromStr, time::Duration};
 
+use base64::{engine::general_purpose, Engine};
+
 use jmap_proto::request::capability::BaseCapabilities;
 use mail_parser::HeaderName;
-use nlp::language::Language;
+use nlp::{bayes::BayesClassifier, language::Language};
 use store::rand::{distributions::Alphanumeric, thread_rng, Rng};
 use utils::config::{cron::SimpleCron, utils::ParseValue, Config, Rate};
 
+use super::{attachments::AttachmentExtraction, retention::RetentionPolicy};
+
+#[derive(Clone)]
+pub struct VapidKey {
+    pub private_key: Vec<u8>,
+    pub subject: String,
+}
+
+#[derive(Clone)]
+pub struct AccountBayes {
+    pub classifier: BayesClassifier,
+    pub lookup: Option<String>,
+    pub weight: f64,
+    pub spam_threshold: f64,
+    pub ham_threshold: f64,
+}
+
+#[derive(Clone)]
+pub struct RequestCost {
+    pub budget: Rate,
+    pub get: u64,
+    pub set: u64,
+    pub changes: u64,
+    pub query: u64,
+    pub query_changes: u64,
+    pub copy: u64,
+    pub import: u64,
+    pub parse: u64,
+    pub validate: u64,
+    pub blob: u64,
+    pub search_snippet: u64,
+    pub echo: u64,
+    pub filter_condition: u64,
+    pub filter_full_text: u64,
+}
+
 #[derive(Default, Clone)]
 pub struct JmapConfig {
     pub default_language: Language,
+    pub language_overrides: Vec<(String, Language)>,
+    pub fts_attachments: Option<AttachmentExtraction>,
     pub query_max_results: usize,
     pub snippet_max_results: usize,
 
@@ -34,15 +74,24 @@ pub struct JmapConfig {
     pub mail_attachments_max_size: usize,
     pub mail_parse_max_items: usize,
     pub mail_max_size: usize,
+    pub mail_max_messages: u64,
+    pub mail_max_hops: usize,
     pub mail_autoexpunge_after: Option<Duration>,
+    pub mail_retention: Vec<RetentionPolicy>,
+    pub mail_snooze_interval: Duration,
+    pub mailbox_defaults: Vec<(&'static str, String)>,
 
     pub sieve_max_script_name: usize,
     pub sieve_max_scripts: usize,
+    pub sieve_max_versions: usize,
+
+    pub quota_thresholds: Vec<u64>,
 
     pub session_cache_ttl: Duration,
     pub rate_authenticated: Option<Rate>,
     pub rate_authenticate_req: Option<Rate>,
     pub rate_anonymous: Option<Rate>,
+    pub request_cost: Option<RequestCost>,
 
     pub event_source_throttle: Duration,
     pub push_max_total: usize,
@@ -52,6 +101,7 @@ pub struct JmapConfig {
     pub push_timeout: Duration,
     pub push_verify_timeout: Duration,
     pub push_throttle: Duration,
+    pub push_vapid: Option<VapidKey>,
 
     pub web_socket_throttle: Duration,
     pub web_socket_timeout: Duration,
@@ -68,9 +118,12 @@ pub struct JmapConfig {
     pub master_user: Option<(String, String)>,
 
     pub spam_header: Option<(HeaderName<'static>, String)>,
+    pub spam_bayes_account: Option<AccountBayes>,
 
     pub http_headers: Vec<(hyper::header::HeaderName, hyper::header::HeaderValue)>,
     pub http_use_forwarded: bool,
+    pub audit_retention: Option<Duration>,
+    pub metrics_prometheus: bool,
 
     pub encrypt: bool,
     pub encrypt_append: bool,
@@ -144,6 +197,27 @@ impl JmapConfig {
                     .unwrap_or("en"),
             )
             .unwrap_or(Language::English),
+            fts_attachments: AttachmentExtraction::parse(config),
+            language_overrides: {
+                let overrides = config
+                    .iterate_prefix("storage.full-text.language-override")
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect::<Vec<_>>();
+                overrides
+                    .into_iter()
+                    .filter_map(|(key, value)| {
+                        if let Some(language) = Language::from_iso_639(&value) {
+                            Some((key, language))
+                        } else {
+                            config.new_parse_error(
+                                ("storage.full-text.language-override", key.as_str()),
+                                format!("Invalid language code {value:?}"),
+                            );
+                            None
+                        }
+                    })
+                    .collect()
+            },
             query_max_results: config
                 .property("jmap.protocol.query.max-results")
                 .unwrap_or(5000),
@@ -195,16 +269,72 @@ impl JmapConfig {
                 .property("jmap.email.max-attachment-size")
                 .unwrap_or(50000000),
             mail_max_size: config.property("jmap.email.max-size").unwrap_or(75000000),
+            mail_max_messages: config.property("jmap.email.max-messages").unwrap_or(0),
+            mail_max_hops: config.property("jmap.email.max-hops").unwrap_or(10),
             mail_parse_max_items: config.property("jmap.email.parse.max-items").unwrap_or(10),
             mail_autoexpunge_after: config
                 .property_or_default::<Option<Duration>>("jmap.email.auto-expunge", "30d")
                 .unwrap_or_default(),
+            mail_retention: RetentionPolicy::parse_all(config),
+            mail_snooze_interval: config
+                .property_or_default("jmap.email.snooze.interval", "1m")
+                .unwrap_or_else(|| Duration::from_secs(60)),
+            mailbox_defaults: [
+                ("trash", "Deleted Items"),
+                ("junk", "Junk Mail"),
+                ("drafts", "Drafts"),
+                ("sent", "Sent Items"),
+                ("archive", "Archive"),
+            ]
+            .into_iter()
+            .filter_map(|(role, name)| {
+                let create = config
+                    .property_or_default(("jmap.folders", role, "create"), "true")
+                    .unwrap_or(true);
+                let is_required = matches!(role, "trash" | "junk");
+                if !create && is_required {
+                    // Messages are moved to these folders by id
+                    config.new_parse_error(
+                        ("jmap.folders", role, "create"),
+                        "The trash and junk folders cannot be disabled",
+                    );
+                }
+
+                if create || is_required {
+                    Some((
+                        role,
+                        config
+                            .value(("jmap.folders", role, "name"))
+                            .unwrap_or(name)
+                            .to_string(),
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect(),
             sieve_max_script_name: config
                 .property("sieve.untrusted.limits.name-length")
                 .unwrap_or(512),
             sieve_max_scripts: config
                 .property("sieve.untrusted.limits.max-scripts")
                 .unwrap_or(256),
+            sieve_max_versions: config
+                .property("sieve.untrusted.limits.max-versions")
+                .unwrap_or(5),
+            quota_thresholds: {
+                let mut thresholds = config
+                    .properties::<u64>("jmap.quota.thresholds")
+                    .into_iter()
+                    .map(|(_, v)| v.clamp(1, 100))
+                    .collect::<Vec<_>>();
+                if thresholds.is_empty() {
+                    thresholds = vec![80, 90, 100];
+                }
+                thresholds.sort_unstable();
+                thresholds.dedup();
+                thresholds
+            },
             capabilities: BaseCapabilities::default(),
             session_cache_ttl: config
                 .property("cache.session.ttl")
@@ -218,6 +348,7 @@ impl JmapConfig {
             rate_anonymous: config
                 .property_or_default::<Option<Rate>>("jmap.rate-limit.anonymous", "100/1m")
                 .unwrap_or_default(),
+            request_cost: RequestCost::parse(config),
             oauth_key: config
                 .value("oauth.key")
                 .map(|s| s.to_string())
@@ -286,9 +417,16 @@ impl JmapConfig {
                         )
                     })
                 }),
+            spam_bayes_account: AccountBayes::parse(config),
             http_use_forwarded: config
                 .property("server.http.use-x-forwarded")
                 .unwrap_or(false),
+            audit_retention: config
+                .property_or_default::<Option<Duration>>("server.http.audit.retention", "90d")
+                .unwrap_or_default(),
+            metrics_prometheus: config
+                .property_or_default("metrics.prometheus.enable", "false")
+                .unwrap_or(false),
             http_headers,
             push_attempt_interval: config
                 .property_or_default("jmap.push.attempts.interval", "1m")
@@ -308,6 +446,7 @@ impl JmapConfig {
             push_throttle: config
                 .property_or_default("jmap.push.throttle", "1s")
                 .unwrap_or_else(|| Duration::from_secs(1)),
+            push_vapid: VapidKey::parse(config),
             session_purge_frequency: config
                 .property_or_default::<SimpleCron>("jmap.session.purge.frequency", "15 * *")
                 .unwrap_or_else(|| SimpleCron::parse_value("15 * *").unwrap()),
@@ -333,3 +472,121 @@ impl JmapConfig {
         jmap
     }
 }
+
+impl AccountBayes {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default::<bool>("spam.bayes.account.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        Some(AccountBayes {
+            classifier: BayesClassifier {
+                min_token_hits: config
+                    .property_or_default("spam.bayes.account.min-token-hits", "2")
+                    .unwrap_or(2),
+                min_tokens: config
+                    .property_or_default("spam.bayes.account.min-tokens", "11")
+                    .unwrap_or(11),
+                min_prob_strength: config
+                    .property_or_default("spam.bayes.account.min-prob-strength", "0.05")
+                    .unwrap_or(0.05),
+                min_learns: config
+                    .property_or_default("spam.bayes.account.min-learns", "20")
+                    .unwrap_or(20),
+            },
+            lookup: config
+                .value("spam.bayes.account.lookup")
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string()),
+            weight: config
+                .property_or_default::<f64>("spam.bayes.account.weight", "0.5")
+                .unwrap_or(0.5)
+                .clamp(0.0, 1.0),
+            spam_threshold: config
+                .property_or_default("spam.bayes.account.threshold.spam", "0.7")
+                .unwrap_or(0.7),
+            ham_threshold: config
+                .property_or_default("spam.bayes.account.threshold.ham", "0.3")
+                .unwrap_or(0.3),
+        })
+    }
+}
+
+impl RequestCost {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        let budget = config
+            .property::<Option<Rate>>("jmap.rate-limit.cost.budget")
+            .flatten()?;
+
+        Some(RequestCost {
+            budget,
+            get: config
+                .property_or_default("jmap.rate-limit.cost.method.get", "1")
+                .unwrap_or(1),
+            set: config
+                .property_or_default("jmap.rate-limit.cost.method.set", "2")
+                .unwrap_or(2),
+            changes: config
+                .property_or_default("jmap.rate-limit.cost.method.changes", "1")
+                .unwrap_or(1),
+            query: config
+                .property_or_default("jmap.rate-limit.cost.method.query", "5")
+                .unwrap_or(5),
+            query_changes: config
+                .property_or_default("jmap.rate-limit.cost.method.query-changes", "5")
+                .unwrap_or(5),
+            copy: config
+                .property_or_default("jmap.rate-limit.cost.method.copy", "5")
+                .unwrap_or(5),
+            import: config
+                .property_or_default("jmap.rate-limit.cost.method.import", "5")
+                .unwrap_or(5),
+            parse: config
+                .property_or_default("jmap.rate-limit.cost.method.parse", "2")
+                .unwrap_or(2),
+            validate: config
+                .property_or_default("jmap.rate-limit.cost.method.validate", "2")
+                .unwrap_or(2),
+            blob: config
+                .property_or_default("jmap.rate-limit.cost.method.blob", "2")
+                .unwrap_or(2),
+            search_snippet: config
+                .property_or_default("jmap.rate-limit.cost.method.search-snippet", "5")
+                .unwrap_or(5),
+            echo: config
+                .property_or_default("jmap.rate-limit.cost.method.echo", "1")
+                .unwrap_or(1),
+            filter_condition: config
+                .property_or_default("jmap.rate-limit.cost.filter.condition", "1")
+                .unwrap_or(1),
+            filter_full_text: config
+                .property_or_default("jmap.rate-limit.cost.filter.full-text", "10")
+                .unwrap_or(10),
+        })
+    }
+}
+
+impl VapidKey {
+    fn parse(config: &mut Config) -> Option<Self> {
+        let private_key = config
+            .value("jmap.push.vapid.private-key")?
+            .trim()
+            .to_string();
+        match general_purpose::URL_SAFE_NO_PAD.decode(private_key.trim_end_matches('=')) {
+            Ok(private_key) if private_key.len() == 32 => Some(VapidKey {
+                private_key,
+                subject: config.value_require("jmap.push.vapid.subject")?.to_string(),
+            }),
+            _ => {
+                config.new_parse_error(
+                    "jmap.push.vapid.private-key",
+                    "Expected a base64url encoded P-256 private key",
+                );
+                None
+            }
+        }
+    }
+}
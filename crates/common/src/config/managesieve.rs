@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use utils::config::Config;
+
+#[derive(Default, Clone)]
+pub struct ManageSieveConfig {
+    pub timeout: Duration,
+    pub max_script_size: usize,
+}
+
+impl ManageSieveConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        ManageSieveConfig {
+            timeout: config
+                .property_or_default("server.managesieve.timeout", "10m")
+                .unwrap_or_else(|| Duration::from_secs(600)),
+            max_script_size: config
+                .property_or_default("sieve.untrusted.limits.script-size", "1048576")
+                .unwrap_or(1024 * 1024),
+        }
+    }
+}
@@ -8,12 +8,14 @@ use utils::config::Config;
 use crate::{expr::*, listener::tls::TlsManager, manager::config::ConfigManager, Core, Network};
 
 use self::{
-    imap::ImapConfig, jmap::settings::JmapConfig, scripts::Scripting, smtp::SmtpConfig,
-    storage::Storage,
+    dav::DavConfig, imap::ImapConfig, jmap::settings::JmapConfig, managesieve::ManageSieveConfig,
+    scripts::Scripting, smtp::SmtpConfig, storage::Storage,
 };
 
+pub mod dav;
 pub mod imap;
 pub mod jmap;
+pub mod managesieve;
 pub mod network;
 pub mod scripts;
 pub mod server;
@@ -21,7 +23,7 @@ pub mod smtp;
 pub mod storage;
 pub mod tracers;
 
-pub(crate) const CONNECTION_VARS: &[u32; 7] = &[
+pub(crate) const CONNECTION_VARS: &[u32; 8] = &[
     V_LISTENER,
     V_REMOTE_IP,
     V_REMOTE_PORT,
@@ -29,6 +31,7 @@ pub(crate) const CONNECTION_VARS: &[u32; 7] = &[
     V_LOCAL_PORT,
     V_PROTOCOL,
     V_TLS,
+    V_TLS_SNI,
 ];
 
 impl Core {
@@ -87,6 +90,21 @@ impl Core {
                 }
             })
             .unwrap_or_default();
+        let pubsub = config
+            .value("storage.pubsub")
+            .map(|id| id.to_string())
+            .and_then(|id| {
+                if let Some(store) = stores.pubsub_stores.get(&id) {
+                    store.clone().into()
+                } else {
+                    config.new_parse_error(
+                        "storage.pubsub",
+                        format!("Pub/sub store {id:?} not found"),
+                    );
+                    None
+                }
+            })
+            .unwrap_or_default();
         let mut directories = Directories::parse(config, &stores, data.clone()).await;
         let directory = config
             .value_require("storage.directory")
@@ -129,12 +147,15 @@ impl Core {
             smtp: SmtpConfig::parse(config).await,
             jmap: JmapConfig::parse(config),
             imap: ImapConfig::parse(config),
+            managesieve: ManageSieveConfig::parse(config),
+            dav: DavConfig::parse(config),
             tls: TlsManager::parse(config),
             storage: Storage {
                 data,
                 blob,
                 fts,
                 lookup,
+                pubsub,
                 directory,
                 directories: directories.directories,
                 purge_schedules: stores.purge_schedules,
@@ -143,6 +164,7 @@ impl Core {
                 lookups: stores.lookup_stores,
                 blobs: stores.blob_stores,
                 ftss: stores.fts_stores,
+                pubsubs: stores.pubsub_stores,
             },
         }
     }
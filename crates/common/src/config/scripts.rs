@@ -4,17 +4,19 @@ use std::{
     time::{Duration, Instant},
 };
 
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use nlp::bayes::cache::BayesTokenCache;
 use parking_lot::RwLock;
 use sieve::{compiler::grammar::Capability, Compiler, Runtime, Sieve};
 use store::Stores;
-use utils::config::Config;
+use utils::config::{Config, Rate};
 
 use crate::scripts::{functions::register_functions, plugins::RegisterSievePlugins};
 
 use super::{if_block::IfBlock, smtp::SMTP_RCPT_TO_VARS, tokenizer::TokenMap};
 
+pub const ADDRESS_BOOK_LIST: &str = "urn:ietf:params:sieve:addrbook:default";
+
 pub struct Scripting {
     pub untrusted_compiler: Compiler,
     pub untrusted_runtime: Runtime,
@@ -26,6 +28,24 @@ pub struct Scripting {
     pub scripts: AHashMap<String, Arc<Sieve>>,
     pub bayes_cache: BayesTokenCache,
     pub remote_lists: RwLock<AHashMap<String, RemoteList>>,
+    pub untrusted_lists: AHashSet<String>,
+    pub untrusted_notify_rate: Option<Rate>,
+    pub untrusted_notify_timeout: Duration,
+    pub untrusted_global: Vec<GlobalScript>,
+}
+
+#[derive(Clone)]
+pub struct GlobalScript {
+    pub id: String,
+    pub phase: ScriptPhase,
+    pub domain: Option<String>,
+    pub script: Arc<Sieve>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptPhase {
+    Before,
+    After,
 }
 
 #[derive(Clone)]
@@ -89,6 +109,66 @@ impl Scripting {
                     .unwrap_or(3),
             );
 
+        // Parse lookup lists available to untrusted scripts
+        let mut untrusted_lists = AHashSet::new();
+        for (key, list) in config
+            .values("sieve.untrusted.lists")
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+        {
+            if stores.lookup_stores.contains_key(&list) {
+                untrusted_lists.insert(list);
+            } else {
+                config.new_build_error(key, format!("Lookup store {list:?} not found"));
+            }
+        }
+
+        // Parse admin scripts that run before and after the user's active script
+        let mut untrusted_global = Vec::new();
+        for id in config
+            .sub_keys("sieve.untrusted.global", ".contents")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+        {
+            let phase = match config
+                .value(("sieve.untrusted.global", id.as_str(), "phase"))
+                .unwrap_or("before")
+            {
+                "before" => ScriptPhase::Before,
+                "after" => ScriptPhase::After,
+                phase => {
+                    config.new_parse_error(
+                        ("sieve.untrusted.global", id.as_str(), "phase"),
+                        format!("Invalid script phase {phase:?}, expected 'before' or 'after'"),
+                    );
+                    continue;
+                }
+            };
+            let domain = config
+                .value(("sieve.untrusted.global", id.as_str(), "domain"))
+                .map(|domain| domain.to_lowercase());
+
+            match untrusted_compiler.compile(
+                config
+                    .value(("sieve.untrusted.global", id.as_str(), "contents"))
+                    .unwrap()
+                    .as_bytes(),
+            ) {
+                Ok(compiled) => {
+                    untrusted_global.push(GlobalScript {
+                        id,
+                        phase,
+                        domain,
+                        script: compiled.into(),
+                    });
+                }
+                Err(err) => config.new_build_error(
+                    ("sieve.untrusted.global", id.as_str(), "contents"),
+                    format!("Failed to compile Sieve script: {err}"),
+                ),
+            }
+        }
+
         // Parse untrusted runtime
         let untrusted_runtime = Runtime::new()
             .with_max_nested_includes(
@@ -151,9 +231,15 @@ impl Scripting {
                 if !values.is_empty() {
                     values
                 } else {
-                    vec!["mailto".to_string()]
+                    vec!["mailto".to_string(), "https".to_string()]
                 }
             })
+            .with_valid_ext_lists(
+                untrusted_lists
+                    .iter()
+                    .cloned()
+                    .chain([ADDRESS_BOOK_LIST.to_string()]),
+            )
             .with_protected_headers({
                 let values = config
                     .values("sieve.untrusted.protected-headers")
@@ -342,6 +428,17 @@ impl Scripting {
                     .unwrap_or_else(|| Duration::from_secs(3600)),
             ),
             remote_lists: Default::default(),
+            untrusted_lists,
+            untrusted_notify_rate: config
+                .property_or_default::<Option<Rate>>(
+                    "sieve.untrusted.notify.rate-limit",
+                    "20/1h",
+                )
+                .unwrap_or_default(),
+            untrusted_notify_timeout: config
+                .property_or_default("sieve.untrusted.notify.timeout", "10s")
+                .unwrap_or_else(|| Duration::from_secs(10)),
+            untrusted_global,
         }
     }
 }
@@ -371,6 +468,10 @@ impl Default for Scripting {
                 Duration::from_secs(3600),
             ),
             remote_lists: Default::default(),
+            untrusted_lists: AHashSet::new(),
+            untrusted_notify_rate: None,
+            untrusted_notify_timeout: Duration::from_secs(10),
+            untrusted_global: Vec::new(),
         }
     }
 }
@@ -388,6 +489,37 @@ impl Clone for Scripting {
             scripts: self.scripts.clone(),
             bayes_cache: self.bayes_cache.clone(),
             remote_lists: RwLock::new(self.remote_lists.read().clone()),
+            untrusted_lists: self.untrusted_lists.clone(),
+            untrusted_notify_rate: self.untrusted_notify_rate.clone(),
+            untrusted_notify_timeout: self.untrusted_notify_timeout,
+            untrusted_global: self.untrusted_global.clone(),
         }
     }
 }
+
+impl Scripting {
+    // Returns the admin scripts that apply to a recipient domain, in execution order:
+    // server-wide "before" scripts run first and server-wide "after" scripts run last.
+    pub fn global_scripts(&self, domain: &str) -> (Vec<&GlobalScript>, Vec<&GlobalScript>) {
+        let scripts = |phase: ScriptPhase, is_server_wide: bool| {
+            self.untrusted_global.iter().filter(move |script| {
+                script.phase == phase
+                    && match &script.domain {
+                        Some(script_domain) => {
+                            !is_server_wide && script_domain.eq_ignore_ascii_case(domain)
+                        }
+                        None => is_server_wide,
+                    }
+            })
+        };
+
+        (
+            scripts(ScriptPhase::Before, true)
+                .chain(scripts(ScriptPhase::Before, false))
+                .collect(),
+            scripts(ScriptPhase::After, false)
+                .chain(scripts(ScriptPhase::After, true))
+                .collect(),
+        )
+    }
+}
@@ -36,7 +36,9 @@ use utils::config::{
 };
 
 use crate::{
-    listener::{tls::CertificateResolver, TcpAcceptor},
+    listener::{
+        limiter::ConcurrencyLimiter, qos::QosLimiter, tls::CertificateResolver, TcpAcceptor,
+    },
     SharedCore,
 };
 
@@ -50,18 +52,29 @@ impl Servers {
         // Parse ACME managers
         let mut servers = Servers::default();
 
+        // Parse the worker pool shared by all listeners
+        let workers = config
+            .property::<Option<u64>>("server.qos.max-workers")
+            .unwrap_or_default()
+            .map(ConcurrencyLimiter::new);
+
         // Parse servers
         for id in config
             .sub_keys("server.listener", ".protocol")
             .map(|s| s.to_string())
             .collect::<Vec<_>>()
         {
-            servers.parse_server(config, id);
+            servers.parse_server(config, id, workers.clone());
         }
         servers
     }
 
-    fn parse_server(&mut self, config: &mut Config, id_: String) {
+    fn parse_server(
+        &mut self,
+        config: &mut Config,
+        id_: String,
+        workers: Option<ConcurrencyLimiter>,
+    ) {
         // Parse protocol
         let id = id_.as_str();
         let protocol =
@@ -179,7 +192,15 @@ impl Servers {
             });
         }
 
-        if listeners.is_empty() {
+        // Build UNIX listeners
+        #[cfg(unix)]
+        let unix_listeners = super::UnixListener::parse(config, id);
+        #[cfg(unix)]
+        let has_listeners = !listeners.is_empty() || !unix_listeners.is_empty();
+        #[cfg(not(unix))]
+        let has_listeners = !listeners.is_empty();
+
+        if !has_listeners {
             config.new_build_error(
                 ("server.listener", id),
                 "No 'bind' directive found for listener",
@@ -198,6 +219,34 @@ impl Servers {
             proxy_networks.push(network);
         }
 
+        // Parse connection classes
+        let mut qos = QosLimiter {
+            workers,
+            ..Default::default()
+        };
+        let trusted_keys = if config.has_prefix(("server.listener", id, "qos.trusted-networks")) {
+            ("server.listener", id, "qos.trusted-networks").as_key()
+        } else {
+            "server.qos.trusted-networks".as_key()
+        };
+        for (_, network) in config.properties(trusted_keys) {
+            qos.trusted_networks.push(network);
+        }
+        for (class, limiter) in [
+            ("trusted", &mut qos.trusted),
+            ("authenticated", &mut qos.authenticated),
+            ("unknown", &mut qos.unknown),
+        ] {
+            *limiter = config
+                .property_or_else::<Option<u64>>(
+                    ("server.listener", id, "qos.max-in-flight", class),
+                    ("server.qos.max-in-flight", class),
+                    "false",
+                )
+                .unwrap_or_default()
+                .map(ConcurrencyLimiter::new);
+        }
+
         self.servers.push(Server {
             max_connections: config
                 .property_or_else(
@@ -209,7 +258,10 @@ impl Servers {
             id: id_,
             protocol,
             listeners,
+            #[cfg(unix)]
+            unix_listeners,
             proxy_networks,
+            qos,
         });
     }
 
@@ -4,10 +4,12 @@ use ahash::AHashMap;
 use tokio::net::TcpSocket;
 use utils::config::ipmask::IpAddrMask;
 
-use crate::listener::TcpAcceptor;
+use crate::listener::{qos::QosLimiter, TcpAcceptor};
 
 pub mod listener;
 pub mod tls;
+#[cfg(unix)]
+pub mod unix;
 
 #[derive(Default)]
 pub struct Servers {
@@ -20,8 +22,11 @@ pub struct Server {
     pub id: String,
     pub protocol: ServerProtocol,
     pub listeners: Vec<Listener>,
+    #[cfg(unix)]
+    pub unix_listeners: Vec<UnixListener>,
     pub proxy_networks: Vec<IpAddrMask>,
     pub max_connections: u64,
+    pub qos: QosLimiter,
 }
 
 #[derive(Debug)]
@@ -36,6 +41,19 @@ pub struct Listener {
     pub nodelay: bool,
 }
 
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixListener {
+    pub path: std::path::PathBuf,
+    pub socket: Option<std::os::unix::net::UnixListener>,
+
+    // Socket file options
+    pub mode: Option<u32>,
+    pub owner: Option<u32>,
+    pub group: Option<u32>,
+    pub proxy: bool,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
 pub enum ServerProtocol {
     #[default]
@@ -22,8 +22,11 @@
 */
 
 use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
     io::Cursor,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
     sync::Arc,
     time::Duration,
 };
@@ -49,8 +52,12 @@ use x509_parser::{
 };
 
 use crate::listener::{
-    acme::{directory::LETS_ENCRYPT_PRODUCTION_DIRECTORY, AcmeProvider, ChallengeSettings},
-    tls::TlsManager,
+    acme::{
+        directory::LETS_ENCRYPT_PRODUCTION_DIRECTORY,
+        route53::{Credentials, Route53},
+        AcmeProvider, ChallengeSettings, DnsProvider,
+    },
+    tls::{CertificateDirectory, TlsManager},
 };
 
 pub static TLS13_VERSION: &[&SupportedProtocolVersion] = &[&TLS13];
@@ -63,6 +70,8 @@ impl TlsManager {
         let mut subject_names = AHashSet::new();
 
         // Parse certificates
+        let certificate_dir =
+            parse_certificate_directory(config, &mut certificates, &mut subject_names);
         parse_certificates(config, &mut certificates, &mut subject_names);
 
         // Parse ACME providers
@@ -186,6 +195,7 @@ impl TlsManager {
 
         TlsManager {
             certificates: ArcSwap::from_pointee(certificates),
+            certificate_dir,
             acme_providers,
             self_signed_cert: build_self_signed_cert(subject_names.into_iter().collect::<Vec<_>>())
                 .or_else(|err| {
@@ -199,7 +209,7 @@ impl TlsManager {
 }
 
 #[allow(clippy::unnecessary_to_owned)]
-fn build_dns_updater(config: &mut Config, acme_id: &str) -> Option<DnsUpdater> {
+fn build_dns_updater(config: &mut Config, acme_id: &str) -> Option<DnsProvider> {
     match config.value_require(("acme", acme_id, "provider"))? {
         "rfc2136-tsig" => {
             let algorithm: TsigAlgorithm = config
@@ -244,6 +254,7 @@ fn build_dns_updater(config: &mut Config, acme_id: &str) -> Option<DnsUpdater> {
                 )
             })
             .ok()
+            .map(DnsProvider::Update)
         }
         "cloudflare" => {
             let timeout = config
@@ -265,6 +276,40 @@ fn build_dns_updater(config: &mut Config, acme_id: &str) -> Option<DnsUpdater> {
                 )
             })
             .ok()
+            .map(DnsProvider::Update)
+        }
+        "route53" => {
+            let timeout = config
+                .property_or_default(("acme", acme_id, "timeout"), "30s")
+                .unwrap_or_else(|| Duration::from_secs(30));
+
+            Route53::new(
+                Credentials {
+                    access_key: config
+                        .value_require(("acme", acme_id, "user"))?
+                        .trim()
+                        .to_string(),
+                    secret_key: config
+                        .value_require(("acme", acme_id, "secret"))?
+                        .trim()
+                        .to_string(),
+                    session_token: config
+                        .value(("acme", acme_id, "session-token"))
+                        .map(|s| s.trim().to_string()),
+                },
+                config
+                    .value(("acme", acme_id, "zone-id"))
+                    .map(|s| s.trim().to_string()),
+                timeout,
+            )
+            .map_err(|err| {
+                config.new_build_error(
+                    ("acme", acme_id, "provider"),
+                    format!("Failed to create Route 53 DNS updater: {err}"),
+                )
+            })
+            .ok()
+            .map(DnsProvider::Route53)
         }
         _ => {
             config.new_parse_error(("acme", acme_id, "provider"), "Unsupported provider");
@@ -294,80 +339,29 @@ pub(crate) fn parse_certificates(
         let pk = config.value_require(key_pk).map(|s| s.as_bytes().to_vec());
 
         if let (Some(cert), Some(pk)) = (cert, pk) {
-            match build_certified_key(cert, pk) {
-                Ok(cert) => {
-                    match cert
-                        .end_entity_cert()
-                        .map_err(|err| format!("Failed to obtain end entity cert: {err}"))
-                        .and_then(|cert| {
-                            X509Certificate::from_der(cert.as_ref())
-                                .map_err(|err| format!("Failed to parse end entity cert: {err}"))
-                        }) {
-                        Ok((_, parsed)) => {
-                            // Add CNs and SANs to the list of names
-                            let mut names = AHashSet::new();
-                            for name in parsed.subject().iter_common_name() {
-                                if let Ok(name) = name.as_str() {
-                                    names.insert(name.to_string());
-                                }
-                            }
-                            for ext in parsed.extensions() {
-                                if let ParsedExtension::SubjectAlternativeName(san) =
-                                    ext.parsed_extension()
-                                {
-                                    for name in &san.general_names {
-                                        let name = match name {
-                                            GeneralName::DNSName(name) => name.to_string(),
-                                            GeneralName::IPAddress(ip) => match ip.len() {
-                                                4 => Ipv4Addr::from(
-                                                    <[u8; 4]>::try_from(*ip).unwrap(),
-                                                )
-                                                .to_string(),
-                                                16 => Ipv6Addr::from(
-                                                    <[u8; 16]>::try_from(*ip).unwrap(),
-                                                )
-                                                .to_string(),
-                                                _ => continue,
-                                            },
-                                            _ => {
-                                                continue;
-                                            }
-                                        };
-                                        names.insert(name);
-                                    }
-                                }
-                            }
-
-                            // Add custom SNIs
-                            names.extend(
-                                config
-                                    .values(("certificate", cert_id, "subjects"))
-                                    .map(|(_, v)| v.trim().to_string()),
-                            );
-
-                            // Add domain names
-                            subject_names.extend(names.iter().cloned());
-
-                            // Add certificates
-                            let cert = Arc::new(cert);
-                            for name in names {
-                                certificates.insert(
-                                    name.strip_prefix("*.")
-                                        .map(|name| name.to_string())
-                                        .unwrap_or(name),
-                                    cert.clone(),
-                                );
-                            }
-
-                            // Add default certificate
-                            if config
-                                .property::<bool>(("certificate", cert_id, "default"))
-                                .unwrap_or_default()
-                            {
-                                certificates.insert("*".to_string(), cert.clone());
-                            }
-                        }
-                        Err(err) => config.new_build_error(format!("certificate.{cert_id}"), err),
+            match build_certified_key(cert, pk)
+                .and_then(|cert| certificate_names(&cert).map(|names| (Arc::new(cert), names)))
+            {
+                Ok((cert, mut names)) => {
+                    // Add custom SNIs
+                    names.extend(
+                        config
+                            .values(("certificate", cert_id, "subjects"))
+                            .map(|(_, v)| v.trim().to_string()),
+                    );
+
+                    // Add domain names
+                    subject_names.extend(names.iter().cloned());
+
+                    // Add certificates
+                    insert_certificate(certificates, names, &cert);
+
+                    // Add default certificate
+                    if config
+                        .property::<bool>(("certificate", cert_id, "default"))
+                        .unwrap_or_default()
+                    {
+                        certificates.insert("*".to_string(), cert.clone());
                     }
                 }
                 Err(err) => config.new_build_error(format!("certificate.{cert_id}"), err),
@@ -376,6 +370,152 @@ pub(crate) fn parse_certificates(
     }
 }
 
+/// Loads all PEM certificates found in the certificate directory. The private
+/// key is read from a `.key` file with the same name or, if missing, from the
+/// certificate file itself. A certificate named `default` is used when the
+/// client does not send an SNI hostname.
+pub(crate) fn parse_certificate_directory(
+    config: &mut Config,
+    certificates: &mut AHashMap<String, Arc<CertifiedKey>>,
+    subject_names: &mut AHashSet<String>,
+) -> Option<CertificateDirectory> {
+    let path = PathBuf::from(config.value("certificate.directory.path")?);
+    let poll_interval = config
+        .property_or_default("certificate.directory.poll-interval", "30s")
+        .unwrap_or_else(|| Duration::from_secs(30));
+
+    let entries = match std::fs::read_dir(&path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            config.new_build_error(
+                "certificate.directory.path",
+                format!("Failed to read directory {}: {err}", path.display()),
+            );
+            return None;
+        }
+    };
+    let mut files = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map_or(false, |ext| matches!(ext, "pem" | "crt" | "cer" | "cert"))
+        })
+        .collect::<Vec<_>>();
+    files.sort_unstable();
+
+    for cert_path in files {
+        let key_path = cert_path.with_extension("key");
+        let key_path = if key_path.is_file() {
+            key_path
+        } else {
+            cert_path.clone()
+        };
+
+        match std::fs::read(&cert_path)
+            .and_then(|cert| std::fs::read(&key_path).map(|pk| (cert, pk)))
+            .map_err(|err| format!("Failed to read {}: {err}", cert_path.display()))
+            .and_then(|(cert, pk)| build_certified_key(cert, pk))
+            .and_then(|cert| certificate_names(&cert).map(|names| (Arc::new(cert), names)))
+        {
+            Ok((cert, names)) => {
+                subject_names.extend(names.iter().cloned());
+                insert_certificate(certificates, names, &cert);
+                if cert_path
+                    .file_stem()
+                    .map_or(false, |stem| stem == "default")
+                {
+                    certificates.insert("*".to_string(), cert);
+                }
+            }
+            Err(err) => config.new_build_error("certificate.directory.path", err),
+        }
+    }
+
+    Some(CertificateDirectory {
+        path,
+        poll_interval,
+    })
+}
+
+/// Returns the CNs and SANs of a certificate.
+fn certificate_names(cert: &CertifiedKey) -> utils::config::Result<AHashSet<String>> {
+    let (_, parsed) = cert
+        .end_entity_cert()
+        .map_err(|err| format!("Failed to obtain end entity cert: {err}"))
+        .and_then(|cert| {
+            X509Certificate::from_der(cert.as_ref())
+                .map_err(|err| format!("Failed to parse end entity cert: {err}"))
+        })?;
+
+    let mut names = AHashSet::new();
+    for name in parsed.subject().iter_common_name() {
+        if let Ok(name) = name.as_str() {
+            names.insert(name.to_string());
+        }
+    }
+    for ext in parsed.extensions() {
+        if let ParsedExtension::SubjectAlternativeName(san) = ext.parsed_extension() {
+            for name in &san.general_names {
+                let name = match name {
+                    GeneralName::DNSName(name) => name.to_string(),
+                    GeneralName::IPAddress(ip) => match ip.len() {
+                        4 => Ipv4Addr::from(<[u8; 4]>::try_from(*ip).unwrap()).to_string(),
+                        16 => Ipv6Addr::from(<[u8; 16]>::try_from(*ip).unwrap()).to_string(),
+                        _ => continue,
+                    },
+                    _ => {
+                        continue;
+                    }
+                };
+                names.insert(name);
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+fn insert_certificate(
+    certificates: &mut AHashMap<String, Arc<CertifiedKey>>,
+    names: AHashSet<String>,
+    cert: &Arc<CertifiedKey>,
+) {
+    for name in names {
+        certificates.insert(
+            name.strip_prefix("*.")
+                .map(|name| name.to_string())
+                .unwrap_or(name),
+            cert.clone(),
+        );
+    }
+}
+
+impl CertificateDirectory {
+    /// Hashes the names, sizes and modification times of the files in the
+    /// directory, used to detect changes.
+    pub fn fingerprint(path: &Path) -> u64 {
+        let mut entries = std::fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| {
+                        let entry = entry.ok()?;
+                        let metadata = entry.metadata().ok()?;
+                        Some((entry.file_name(), metadata.len(), metadata.modified().ok()))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        entries.sort_unstable();
+
+        let mut hasher = DefaultHasher::new();
+        entries.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
 pub(crate) fn build_certified_key(
     cert: Vec<u8>,
     pk: Vec<u8>,
@@ -386,16 +526,17 @@ pub(crate) fn build_certified_key(
     if cert.is_empty() {
         return Err("No certificates found.".to_string());
     }
-    let pk = match read_one(&mut Cursor::new(pk))
-        .map_err(|err| format!("Failed to read private keys.: {err}",))?
-        .into_iter()
-        .next()
-    {
-        Some(Item::Pkcs8Key(key)) => PrivateKeyDer::Pkcs8(key),
-        Some(Item::Pkcs1Key(key)) => PrivateKeyDer::Pkcs1(key),
-        Some(Item::Sec1Key(key)) => PrivateKeyDer::Sec1(key),
-        Some(_) => return Err("Unsupported private keys found.".to_string()),
-        None => return Err("No private keys found.".to_string()),
+    let mut pk = Cursor::new(pk);
+    let pk = loop {
+        match read_one(&mut pk).map_err(|err| format!("Failed to read private keys.: {err}",))? {
+            Some(Item::Pkcs8Key(key)) => break PrivateKeyDer::Pkcs8(key),
+            Some(Item::Pkcs1Key(key)) => break PrivateKeyDer::Pkcs1(key),
+            Some(Item::Sec1Key(key)) => break PrivateKeyDer::Sec1(key),
+            // Skip certificates when the key is stored in the same file
+            Some(Item::X509Certificate(_)) => continue,
+            Some(_) => return Err("Unsupported private keys found.".to_string()),
+            None => return Err("No private keys found.".to_string()),
+        }
     };
 
     Ok(CertifiedKey {
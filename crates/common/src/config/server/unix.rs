@@ -0,0 +1,125 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::PathBuf,
+};
+
+use utils::config::Config;
+
+use super::UnixListener;
+
+impl UnixListener {
+    pub fn parse(config: &mut Config, id: &str) -> Vec<UnixListener> {
+        let paths = config
+            .values(("server.listener", id, "bind-unix"))
+            .map(|(_, path)| PathBuf::from(path))
+            .collect::<Vec<_>>();
+        if paths.is_empty() {
+            return Vec::new();
+        }
+
+        // Parse socket file permissions
+        let mode = config
+            .value(("server.listener", id, "unix.mode"))
+            .map(|mode| mode.to_string())
+            .and_then(
+                |mode| match u32::from_str_radix(mode.trim_start_matches("0o"), 8) {
+                    Ok(mode) => Some(mode),
+                    Err(_) => {
+                        config.new_parse_error(
+                            ("server.listener", id, "unix.mode"),
+                            format!("Invalid octal file mode {mode:?}"),
+                        );
+                        None
+                    }
+                },
+            );
+        let owner = config.property::<u32>(("server.listener", id, "unix.owner"));
+        let group = config.property::<u32>(("server.listener", id, "unix.group"));
+        let proxy = config
+            .property_or_default(("server.listener", id, "unix.proxy-protocol"), "false")
+            .unwrap_or(false);
+
+        paths
+            .into_iter()
+            .map(|path| UnixListener {
+                path,
+                mode,
+                owner,
+                group,
+                proxy,
+                socket: None,
+            })
+            .collect()
+    }
+
+    pub fn bind(&mut self) -> Result<(), String> {
+        // Remove stale socket left behind by a previous instance
+        if std::fs::symlink_metadata(&self.path)
+            .map_or(false, |metadata| metadata.file_type().is_socket())
+        {
+            std::fs::remove_file(&self.path).map_err(|err| {
+                format!(
+                    "Failed to remove stale socket {}: {}",
+                    self.path.display(),
+                    err
+                )
+            })?;
+        }
+
+        let socket = std::os::unix::net::UnixListener::bind(&self.path)
+            .and_then(|socket| socket.set_nonblocking(true).map(|_| socket))
+            .map_err(|err| format!("Failed to bind to {}: {}", self.path.display(), err))?;
+
+        if let Some(mode) = self.mode {
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(mode)).map_err(
+                |err| {
+                    format!(
+                        "Failed to set permissions on {}: {}",
+                        self.path.display(),
+                        err
+                    )
+                },
+            )?;
+        }
+        if self.owner.is_some() || self.group.is_some() {
+            std::os::unix::fs::chown(&self.path, self.owner, self.group).map_err(|err| {
+                format!("Failed to change owner of {}: {}", self.path.display(), err)
+            })?;
+        }
+
+        self.socket = Some(socket);
+        Ok(())
+    }
+
+    pub fn listen(self) -> Result<tokio::net::UnixListener, String> {
+        self.socket
+            .ok_or_else(|| format!("Socket {} is not bound", self.path.display()))
+            .and_then(|socket| {
+                tokio::net::UnixListener::from_std(socket)
+                    .map_err(|err| format!("Failed to listen on {}: {}", self.path.display(), err))
+            })
+    }
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use utils::config::Config;
+
+#[derive(Debug, Clone, Default)]
+pub struct DnsblConfig {
+    pub lists: Vec<DnsList>,
+    pub ttl: Duration,
+    pub url_blocklist: Option<String>,
+    pub url_blocklist_weight: f64,
+    pub url_max_hosts: usize,
+    pub url_add_header: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DnsList {
+    pub id: String,
+    pub zone: String,
+    pub target: DnsListTarget,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsListTarget {
+    Ip,
+    Domain,
+    Url,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DnsblResult {
+    pub score: f64,
+    pub hits: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UrlReputation {
+    pub score: f64,
+    pub hits: Vec<UrlHit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlHit {
+    pub list: String,
+    pub host: String,
+}
+
+impl DnsblConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut lists = Vec::new();
+        for id in config
+            .sub_keys("spam.dnsbl.list", ".zone")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+        {
+            let id = id.as_str();
+            if !config
+                .property_or_default::<bool>(("spam.dnsbl.list", id, "enable"), "true")
+                .unwrap_or(true)
+            {
+                continue;
+            }
+            let zone = if let Some(zone) = config.value_require(("spam.dnsbl.list", id, "zone")) {
+                zone.trim_matches('.').to_lowercase()
+            } else {
+                continue;
+            };
+            let target = match config
+                .value(("spam.dnsbl.list", id, "type"))
+                .unwrap_or("ip")
+            {
+                "ip" => DnsListTarget::Ip,
+                "domain" => DnsListTarget::Domain,
+                "url" => DnsListTarget::Url,
+                other => {
+                    let err = format!("Invalid DNS list type {other:?}");
+                    config.new_parse_error(("spam.dnsbl.list", id, "type"), err);
+                    continue;
+                }
+            };
+
+            // Allow lists (DNSWL) are configured with a negative weight
+            lists.push(DnsList {
+                id: id.to_string(),
+                zone,
+                target,
+                weight: config
+                    .property_or_default(("spam.dnsbl.list", id, "weight"), "1.0")
+                    .unwrap_or(1.0),
+            });
+        }
+
+        DnsblConfig {
+            lists,
+            ttl: config
+                .property_or_default("spam.dnsbl.cache.ttl", "1h")
+                .unwrap_or_else(|| Duration::from_secs(3600)),
+            url_blocklist: config
+                .value("spam.url.blocklist")
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string()),
+            url_blocklist_weight: config
+                .property_or_default("spam.url.blocklist-weight", "5.0")
+                .unwrap_or(5.0),
+            url_max_hosts: config
+                .property_or_default("spam.url.max-hosts", "20")
+                .unwrap_or(20),
+            url_add_header: config
+                .property_or_default("spam.url.add-header", "true")
+                .unwrap_or(true),
+        }
+    }
+
+    pub fn has_lists(&self, target: DnsListTarget) -> bool {
+        self.lists.iter().any(|list| list.target == target)
+    }
+
+    pub fn has_url_checks(&self) -> bool {
+        self.url_blocklist.is_some() || self.has_lists(DnsListTarget::Url)
+    }
+}
@@ -1,6 +1,7 @@
 use utils::config::{Config, Rate};
 
 pub mod auth;
+pub mod dnsbl;
 pub mod queue;
 pub mod report;
 pub mod resolver;
@@ -10,8 +11,8 @@ pub mod throttle;
 use crate::expr::{tokenizer::TokenMap, Expression};
 
 use self::{
-    auth::MailAuthConfig, queue::QueueConfig, report::ReportConfig, resolver::Resolvers,
-    session::SessionConfig,
+    auth::MailAuthConfig, dnsbl::DnsblConfig, queue::QueueConfig, report::ReportConfig,
+    resolver::Resolvers, session::SessionConfig,
 };
 
 use super::*;
@@ -23,6 +24,7 @@ pub struct SmtpConfig {
     pub resolvers: Resolvers,
     pub mail_auth: MailAuthConfig,
     pub report: ReportConfig,
+    pub dnsbl: DnsblConfig,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -47,7 +49,7 @@ pub const THROTTLE_HELO_DOMAIN: u16 = 1 << 9;
 
 pub(crate) const RCPT_DOMAIN_VARS: &[u32; 1] = &[V_RECIPIENT_DOMAIN];
 
-pub(crate) const SMTP_EHLO_VARS: &[u32; 8] = &[
+pub(crate) const SMTP_EHLO_VARS: &[u32; 9] = &[
     V_LISTENER,
     V_REMOTE_IP,
     V_REMOTE_PORT,
@@ -55,9 +57,10 @@ pub(crate) const SMTP_EHLO_VARS: &[u32; 8] = &[
     V_LOCAL_PORT,
     V_PROTOCOL,
     V_TLS,
+    V_TLS_SNI,
     V_HELO_DOMAIN,
 ];
-pub(crate) const SMTP_MAIL_FROM_VARS: &[u32; 10] = &[
+pub(crate) const SMTP_MAIL_FROM_VARS: &[u32; 11] = &[
     V_LISTENER,
     V_REMOTE_IP,
     V_REMOTE_PORT,
@@ -65,11 +68,12 @@ pub(crate) const SMTP_MAIL_FROM_VARS: &[u32; 10] = &[
     V_LOCAL_PORT,
     V_PROTOCOL,
     V_TLS,
+    V_TLS_SNI,
     V_SENDER,
     V_SENDER_DOMAIN,
     V_AUTHENTICATED_AS,
 ];
-pub(crate) const SMTP_RCPT_TO_VARS: &[u32; 15] = &[
+pub(crate) const SMTP_RCPT_TO_VARS: &[u32; 16] = &[
     V_SENDER,
     V_SENDER_DOMAIN,
     V_RECIPIENTS,
@@ -83,10 +87,11 @@ pub(crate) const SMTP_RCPT_TO_VARS: &[u32; 15] = &[
     V_LOCAL_PORT,
     V_PROTOCOL,
     V_TLS,
+    V_TLS_SNI,
     V_PRIORITY,
     V_HELO_DOMAIN,
 ];
-pub(crate) const SMTP_QUEUE_HOST_VARS: &[u32; 14] = &[
+pub(crate) const SMTP_QUEUE_HOST_VARS: &[u32; 15] = &[
     V_SENDER,
     V_SENDER_DOMAIN,
     V_RECIPIENT_DOMAIN,
@@ -101,8 +106,9 @@ pub(crate) const SMTP_QUEUE_HOST_VARS: &[u32; 14] = &[
     V_QUEUE_EXPIRES_IN,
     V_QUEUE_LAST_STATUS,
     V_QUEUE_LAST_ERROR,
+    V_QUEUE_SIZE,
 ];
-pub(crate) const SMTP_QUEUE_RCPT_VARS: &[u32; 10] = &[
+pub(crate) const SMTP_QUEUE_RCPT_VARS: &[u32; 11] = &[
     V_RECIPIENT_DOMAIN,
     V_RECIPIENTS,
     V_SENDER,
@@ -113,8 +119,9 @@ pub(crate) const SMTP_QUEUE_RCPT_VARS: &[u32; 10] = &[
     V_QUEUE_EXPIRES_IN,
     V_QUEUE_LAST_STATUS,
     V_QUEUE_LAST_ERROR,
+    V_QUEUE_SIZE,
 ];
-pub(crate) const SMTP_QUEUE_SENDER_VARS: &[u32; 8] = &[
+pub(crate) const SMTP_QUEUE_SENDER_VARS: &[u32; 9] = &[
     V_SENDER,
     V_SENDER_DOMAIN,
     V_PRIORITY,
@@ -123,8 +130,9 @@ pub(crate) const SMTP_QUEUE_SENDER_VARS: &[u32; 8] = &[
     V_QUEUE_EXPIRES_IN,
     V_QUEUE_LAST_STATUS,
     V_QUEUE_LAST_ERROR,
+    V_QUEUE_SIZE,
 ];
-pub(crate) const SMTP_QUEUE_MX_VARS: &[u32; 11] = &[
+pub(crate) const SMTP_QUEUE_MX_VARS: &[u32; 12] = &[
     V_RECIPIENT_DOMAIN,
     V_RECIPIENTS,
     V_SENDER,
@@ -136,6 +144,7 @@ pub(crate) const SMTP_QUEUE_MX_VARS: &[u32; 11] = &[
     V_QUEUE_EXPIRES_IN,
     V_QUEUE_LAST_STATUS,
     V_QUEUE_LAST_ERROR,
+    V_QUEUE_SIZE,
 ];
 
 impl SmtpConfig {
@@ -146,6 +155,7 @@ impl SmtpConfig {
             resolvers: Resolvers::parse(config).await,
             mail_auth: MailAuthConfig::parse(config),
             report: ReportConfig::parse(config),
+            dnsbl: DnsblConfig::parse(config),
         }
     }
 }
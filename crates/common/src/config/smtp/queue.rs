@@ -1,9 +1,12 @@
+use std::{net::IpAddr, time::Duration};
+
 use ahash::AHashMap;
 use mail_auth::IpLookupStrategy;
+use mail_parser::DateTime;
 use mail_send::Credentials;
 use utils::config::{
     utils::{AsKey, ParseValue},
-    Config,
+    Config, Rate,
 };
 
 use crate::{
@@ -35,12 +38,21 @@ pub struct QueueConfig {
     // Timeouts
     pub timeout: QueueOutboundTimeout,
 
+    // Connection pool
+    pub pool: QueueOutboundPool,
+
     // Throttle and Quotas
     pub throttle: QueueThrottle,
     pub quota: QueueQuotas,
 
     // Relay hosts
     pub relay_hosts: AHashMap<String, RelayHost>,
+
+    // Dead letters
+    pub dead_letter: IfBlock,
+
+    // Delivery timeline
+    pub timeline_retention: Option<Duration>,
 }
 
 #[derive(Clone)]
@@ -76,11 +88,30 @@ pub struct QueueOutboundTimeout {
     pub mta_sts: IfBlock,
 }
 
+#[derive(Clone)]
+pub struct QueueOutboundPool {
+    pub max_idle: usize,
+    pub max_connections: usize,
+    pub idle_timeout: Duration,
+}
+
 #[derive(Debug, Clone)]
 pub struct QueueThrottle {
     pub sender: Vec<Throttle>,
     pub rcpt: Vec<Throttle>,
     pub host: Vec<Throttle>,
+    pub warmup: Vec<IpWarmup>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IpWarmup {
+    pub id: String,
+    pub source_ips: Vec<IpAddr>,
+    pub start: u64,
+    pub step: Duration,
+    pub rate: Rate,
+    pub growth: f64,
+    pub max_requests: u64,
 }
 
 #[derive(Clone)]
@@ -197,10 +228,16 @@ impl Default for QueueConfig {
                 data: IfBlock::new::<()>("queue.outbound.timeouts.data", [], "10m"),
                 mta_sts: IfBlock::new::<()>("queue.outbound.timeouts.mta-sts", [], "10m"),
             },
+            pool: QueueOutboundPool {
+                max_idle: 4,
+                max_connections: 256,
+                idle_timeout: Duration::from_secs(30),
+            },
             throttle: QueueThrottle {
                 sender: Default::default(),
                 rcpt: Default::default(),
                 host: Default::default(),
+                warmup: Default::default(),
             },
             quota: QueueQuotas {
                 sender: Default::default(),
@@ -208,6 +245,8 @@ impl Default for QueueConfig {
                 rcpt_domain: Default::default(),
             },
             relay_hosts: Default::default(),
+            dead_letter: IfBlock::new::<()>("queue.dead-letter.enable", [], "false"),
+            timeline_retention: None,
         }
     }
 }
@@ -228,6 +267,11 @@ impl QueueConfig {
             (&mut queue.notify, "queue.schedule.notify", &rcpt_vars),
             (&mut queue.expire, "queue.schedule.expire", &rcpt_vars),
             (&mut queue.hostname, "queue.outbound.hostname", &sender_vars),
+            (
+                &mut queue.dead_letter,
+                "queue.dead-letter.enable",
+                &sender_vars,
+            ),
             (&mut queue.max_mx, "queue.outbound.limits.mx", &rcpt_vars),
             (
                 &mut queue.max_multihomed,
@@ -319,6 +363,24 @@ impl QueueConfig {
             }
         }
 
+        // Parse connection pool settings
+        queue.pool = QueueOutboundPool {
+            max_idle: config
+                .property_or_default("queue.outbound.pool.max-idle", "4")
+                .unwrap_or(4),
+            max_connections: config
+                .property_or_default("queue.outbound.pool.max-connections", "256")
+                .unwrap_or(256),
+            idle_timeout: config
+                .property_or_default("queue.outbound.pool.idle-timeout", "30s")
+                .unwrap_or_else(|| Duration::from_secs(30)),
+        };
+
+        // Parse delivery timeline retention
+        queue.timeline_retention = config
+            .property_or_default::<Option<Duration>>("queue.timeline.retention", "false")
+            .unwrap_or_default();
+
         // Parse queue quotas and throttles
         queue.throttle = parse_queue_throttle(config);
         queue.quota = parse_queue_quota(config);
@@ -381,6 +443,7 @@ fn parse_queue_throttle(config: &mut Config) -> QueueThrottle {
         sender: Vec::new(),
         rcpt: Vec::new(),
         host: Vec::new(),
+        warmup: Vec::new(),
     };
 
     let all_throttles = parse_throttle(
@@ -414,9 +477,96 @@ fn parse_queue_throttle(config: &mut Config) -> QueueThrottle {
         }
     }
 
+    // Parse source IP warm-up schedules
+    for id in config
+        .sub_keys("queue.warmup", ".rate")
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+    {
+        if let Some(warmup) = parse_ip_warmup(config, &id) {
+            throttle.warmup.push(warmup);
+        }
+    }
+
     throttle
 }
 
+fn parse_ip_warmup(config: &mut Config, id: &str) -> Option<IpWarmup> {
+    if !config
+        .property_or_default::<bool>(("queue.warmup", id, "enable"), "true")
+        .unwrap_or(true)
+    {
+        return None;
+    }
+
+    let source_ips = config
+        .values(("queue.warmup", id, "source-ip"))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .filter_map(|(key, value)| match value.parse::<IpAddr>() {
+            Ok(ip) => Some(ip),
+            Err(_) => {
+                config.new_parse_error(key, format!("Invalid IP address {value:?}"));
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    if source_ips.is_empty() {
+        config.new_build_error(
+            ("queue.warmup", id, "source-ip"),
+            "At least one source IP is required",
+        );
+        return None;
+    }
+
+    let start = config.value_require(("queue.warmup", id, "start"))?;
+    let start = match DateTime::parse_rfc3339(start) {
+        Some(start) => start.to_timestamp() as u64,
+        None => {
+            let err = format!("Invalid RFC3339 date {start:?}");
+            config.new_parse_error(("queue.warmup", id, "start"), err);
+            return None;
+        }
+    };
+    let rate = config.property_require::<Rate>(("queue.warmup", id, "rate"))?;
+
+    Some(IpWarmup {
+        id: id.to_string(),
+        source_ips,
+        start,
+        step: config
+            .property_or_default(("queue.warmup", id, "step"), "1d")
+            .unwrap_or_else(|| Duration::from_secs(86400)),
+        growth: config
+            .property_or_default::<f64>(("queue.warmup", id, "growth"), "1.5")
+            .unwrap_or(1.5)
+            .max(1.0),
+        max_requests: config
+            .property_or_default(("queue.warmup", id, "max-requests"), "0")
+            .unwrap_or(0),
+        rate,
+    })
+}
+
+impl IpWarmup {
+    /// Returns the rate limit in effect at the given time, or `None` once
+    /// the warm-up has reached its maximum.
+    pub fn rate_at(&self, now: u64) -> Option<Rate> {
+        let steps = now.saturating_sub(self.start) / self.step.as_secs().max(1);
+        let requests = (self.rate.requests as f64 * self.growth.powi(steps.min(1024) as i32))
+            .min(u64::MAX as f64) as u64;
+        if self.max_requests == 0 || requests < self.max_requests {
+            Some(Rate {
+                requests,
+                period: self.rate.period,
+            })
+        } else {
+            None
+        }
+    }
+}
+
 fn parse_queue_quota(config: &mut Config) -> QueueQuotas {
     let mut capacities = QueueQuotas {
         sender: Vec::new(),
@@ -23,6 +23,8 @@ use utils::{
 
 use crate::Core;
 
+use super::dnsbl::DnsblResult;
+
 pub struct Resolvers {
     pub dns: Resolver,
     pub dnssec: DnssecResolver,
@@ -38,6 +40,7 @@ pub struct DnssecResolver {
 pub struct DnsRecordCache {
     pub tlsa: LruCache<String, Arc<Tlsa>>,
     pub mta_sts: LruCache<String, Arc<Policy>>,
+    pub dnsbl: LruCache<String, Arc<DnsblResult>>,
 }
 
 #[derive(Debug, Hash, PartialEq, Eq)]
@@ -230,6 +233,9 @@ impl Resolvers {
                         .property("cache.resolver.mta-sts.size")
                         .unwrap_or(1024),
                 ),
+                dnsbl: LruCache::with_capacity(
+                    config.property("cache.resolver.dnsbl.size").unwrap_or(1024),
+                ),
             },
             psl: PublicSuffix::parse(config, "resolver.public-suffix").await,
         }
@@ -347,6 +353,7 @@ impl Default for Resolvers {
             cache: DnsRecordCache {
                 tlsa: LruCache::with_capacity(1024),
                 mta_sts: LruCache::with_capacity(1024),
+                dnsbl: LruCache::with_capacity(1024),
             },
             psl: PublicSuffix::default(),
         }
@@ -399,6 +406,7 @@ impl Clone for DnsRecordCache {
         Self {
             tlsa: Mutex::new(self.tlsa.lock().clone()),
             mta_sts: Mutex::new(self.mta_sts.lock().clone()),
+            dnsbl: Mutex::new(self.dnsbl.lock().clone()),
         }
     }
 }
@@ -30,6 +30,7 @@ pub struct SessionConfig {
     pub data: Data,
     pub extensions: Extensions,
     pub mta_sts_policy: Option<Policy>,
+    pub srs: Option<Srs>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -100,6 +101,18 @@ pub struct Rcpt {
     // Catch-all and sub-adressing
     pub catch_all: AddressMapping,
     pub subaddressing: AddressMapping,
+
+    // Greylisting
+    pub greylist: Greylist,
+}
+
+#[derive(Clone)]
+pub struct Greylist {
+    pub enable: IfBlock,
+    pub delay: Duration,
+    pub expire: Duration,
+    pub whitelist_retries: u64,
+    pub whitelist_expire: Duration,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -115,11 +128,17 @@ pub struct Data {
     pub script: IfBlock,
     pub pipe_commands: Vec<Pipe>,
     pub milters: Vec<Milter>,
+    pub rspamd: Option<Rspamd>,
 
     // Limits
     pub max_messages: IfBlock,
     pub max_message_size: IfBlock,
+    pub max_header_size: IfBlock,
     pub max_received_headers: IfBlock,
+    pub sending_limits: Vec<SendingLimit>,
+
+    // Undo send
+    pub undo_window: IfBlock,
 
     // Headers
     pub add_received: IfBlock,
@@ -130,6 +149,21 @@ pub struct Data {
     pub add_date: IfBlock,
 }
 
+#[derive(Debug, Clone)]
+pub struct SendingLimit {
+    pub id: String,
+    pub messages: Option<Rate>,
+    pub recipients: Option<Rate>,
+    pub scope: SendingLimitScope,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendingLimitScope {
+    Server,
+    Domain(String),
+    Account(String),
+}
+
 // Ceci n'est pas une pipe
 #[derive(Clone)]
 pub struct Pipe {
@@ -162,6 +196,26 @@ pub enum MilterVersion {
     V6,
 }
 
+#[derive(Clone)]
+pub struct Srs {
+    pub secret: Vec<u8>,
+    pub domain: String,
+    pub max_age: Duration,
+}
+
+#[derive(Clone)]
+pub struct Rspamd {
+    pub enable: IfBlock,
+    pub url: String,
+    pub learn: bool,
+    pub learn_url: Option<String>,
+    pub password: Option<String>,
+    pub timeout: Duration,
+    pub max_size: usize,
+    pub tempfail_on_error: bool,
+    pub tls_allow_invalid_certs: bool,
+}
+
 impl SessionConfig {
     pub fn parse(config: &mut Config) -> Self {
         let has_conn_vars = TokenMap::default().with_variables(CONNECTION_VARS);
@@ -174,6 +228,7 @@ impl SessionConfig {
         let mut session = SessionConfig::default();
         session.rcpt.catch_all = AddressMapping::parse(config, "session.rcpt.catch-all");
         session.rcpt.subaddressing = AddressMapping::parse(config, "session.rcpt.sub-addressing");
+        session.rcpt.greylist.parse(config);
         session.data.milters = config
             .sub_keys("session.data.milter", "")
             .map(|s| s.to_string())
@@ -181,6 +236,7 @@ impl SessionConfig {
             .into_iter()
             .filter_map(|id| parse_milter(config, &id, &has_rcpt_vars))
             .collect();
+        session.data.rspamd = parse_rspamd(config, &has_rcpt_vars);
         session.data.pipe_commands = config
             .sub_keys("session.data.pipe", "")
             .map(|s| s.to_string())
@@ -188,8 +244,16 @@ impl SessionConfig {
             .into_iter()
             .filter_map(|id| parse_pipe(config, &id, &has_rcpt_vars))
             .collect();
+        session.data.sending_limits = config
+            .sub_keys("session.data.sending-limit", "")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|id| parse_sending_limit(config, id))
+            .collect();
         session.throttle = SessionThrottle::parse(config);
         session.mta_sts_policy = Policy::try_parse(config);
+        session.srs = parse_srs(config);
 
         for (value, key, token_map) in [
             (&mut session.duration, "session.duration", &has_conn_vars),
@@ -354,6 +418,11 @@ impl SessionConfig {
                 "session.rcpt.rewrite",
                 &has_rcpt_vars,
             ),
+            (
+                &mut session.rcpt.greylist.enable,
+                "session.rcpt.greylist.enable",
+                &has_rcpt_vars,
+            ),
             (
                 &mut session.data.script,
                 "session.data.script",
@@ -369,11 +438,21 @@ impl SessionConfig {
                 "session.data.limits.size",
                 &has_rcpt_vars,
             ),
+            (
+                &mut session.data.max_header_size,
+                "session.data.limits.header-size",
+                &has_rcpt_vars,
+            ),
             (
                 &mut session.data.max_received_headers,
                 "session.data.limits.received-headers",
                 &has_rcpt_vars,
             ),
+            (
+                &mut session.data.undo_window,
+                "session.data.undo-window",
+                &has_rcpt_vars,
+            ),
             (
                 &mut session.data.add_received,
                 "session.data.add-headers.received",
@@ -466,6 +545,23 @@ impl SessionThrottle {
     }
 }
 
+impl Greylist {
+    fn parse(&mut self, config: &mut Config) {
+        if let Some(delay) = config.property("session.rcpt.greylist.delay") {
+            self.delay = delay;
+        }
+        if let Some(expire) = config.property("session.rcpt.greylist.expire") {
+            self.expire = expire;
+        }
+        if let Some(retries) = config.property("session.rcpt.greylist.whitelist.retries") {
+            self.whitelist_retries = retries;
+        }
+        if let Some(expire) = config.property("session.rcpt.greylist.whitelist.expire") {
+            self.whitelist_expire = expire;
+        }
+    }
+}
+
 fn parse_pipe(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<Pipe> {
     Some(Pipe {
         command: IfBlock::try_parse(config, ("session.data.pipe", id, "command"), token_map)?,
@@ -545,6 +641,120 @@ fn parse_milter(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<M
     })
 }
 
+fn parse_rspamd(config: &mut Config, token_map: &TokenMap) -> Option<Rspamd> {
+    let url = config
+        .value("session.data.rspamd.url")?
+        .trim_end_matches('/')
+        .to_string();
+    Some(Rspamd {
+        enable: IfBlock::try_parse(config, "session.data.rspamd.enable", token_map)
+            .unwrap_or_else(|| IfBlock::new::<()>("session.data.rspamd.enable", [], "false")),
+        url,
+        learn: config
+            .property_or_default("session.data.rspamd.learn.enable", "false")
+            .unwrap_or(false),
+        learn_url: config
+            .value("session.data.rspamd.learn.url")
+            .map(|url| url.trim_end_matches('/').to_string()),
+        password: config
+            .value("session.data.rspamd.password")
+            .map(|password| password.to_string()),
+        timeout: config
+            .property_or_default("session.data.rspamd.timeout", "30s")
+            .unwrap_or_else(|| Duration::from_secs(30)),
+        max_size: config
+            .property_or_default("session.data.rspamd.max-size", "52428800")
+            .unwrap_or(52428800),
+        tempfail_on_error: config
+            .property_or_default("session.data.rspamd.options.tempfail-on-error", "false")
+            .unwrap_or(false),
+        tls_allow_invalid_certs: config
+            .property_or_default("session.data.rspamd.allow-invalid-certs", "false")
+            .unwrap_or_default(),
+    })
+}
+
+fn parse_sending_limit(config: &mut Config, id: String) -> Option<SendingLimit> {
+    let messages = config.property::<Rate>(("session.data.sending-limit", id.as_str(), "messages"));
+    let recipients =
+        config.property::<Rate>(("session.data.sending-limit", id.as_str(), "recipients"));
+    if messages.is_none() && recipients.is_none() {
+        config.new_build_error(
+            ("session.data.sending-limit", id.as_str()),
+            "A sending limit requires a 'messages' or 'recipients' rate",
+        );
+        return None;
+    }
+    let scope = match (
+        config.value(("session.data.sending-limit", id.as_str(), "account")),
+        config.value(("session.data.sending-limit", id.as_str(), "domain")),
+    ) {
+        (Some(account), None) => SendingLimitScope::Account(account.to_lowercase()),
+        (None, Some(domain)) => SendingLimitScope::Domain(domain.to_lowercase()),
+        (None, None) => SendingLimitScope::Server,
+        (Some(_), Some(_)) => {
+            config.new_build_error(
+                ("session.data.sending-limit", id.as_str()),
+                "A sending limit can be scoped to either an account or a domain",
+            );
+            return None;
+        }
+    };
+
+    Some(SendingLimit {
+        id,
+        messages,
+        recipients,
+        scope,
+    })
+}
+
+impl SendingLimit {
+    // Limits scoped to an account replace those of its domain, which in turn
+    // replace the server-wide limits.
+    pub fn applicable<'x>(limits: &'x [SendingLimit], account: &str) -> Vec<&'x SendingLimit> {
+        let domain = account.rsplit_once('@').map(|(_, domain)| domain);
+        let mut applicable = Vec::new();
+        let mut precedence = 0;
+
+        for limit in limits {
+            let limit_precedence = match &limit.scope {
+                SendingLimitScope::Server => 1,
+                SendingLimitScope::Domain(d)
+                    if domain.map_or(false, |domain| domain.eq_ignore_ascii_case(d)) =>
+                {
+                    2
+                }
+                SendingLimitScope::Account(a) if account.eq_ignore_ascii_case(a) => 3,
+                _ => continue,
+            };
+            if limit_precedence > precedence {
+                precedence = limit_precedence;
+                applicable.clear();
+            }
+            if limit_precedence == precedence {
+                applicable.push(limit);
+            }
+        }
+
+        applicable
+    }
+}
+
+fn parse_srs(config: &mut Config) -> Option<Srs> {
+    let secret = config.value("session.srs.secret")?.as_bytes().to_vec();
+    Some(Srs {
+        secret,
+        domain: config
+            .value_require("session.srs.domain")?
+            .trim()
+            .to_lowercase(),
+        max_age: config
+            .property_or_default("session.srs.max-age", "21d")
+            .unwrap_or_else(|| Duration::from_secs(21 * 86400)),
+    })
+}
+
 impl Default for SessionConfig {
     fn default() -> Self {
         Self {
@@ -629,6 +839,13 @@ impl Default for SessionConfig {
                 max_recipients: IfBlock::new::<()>("session.rcpt.max-recipients", [], "100"),
                 catch_all: AddressMapping::Enable,
                 subaddressing: AddressMapping::Enable,
+                greylist: Greylist {
+                    enable: IfBlock::new::<()>("session.rcpt.greylist.enable", [], "false"),
+                    delay: Duration::from_secs(5 * 60),
+                    expire: Duration::from_secs(24 * 60 * 60),
+                    whitelist_retries: 5,
+                    whitelist_expire: Duration::from_secs(35 * 24 * 60 * 60),
+                },
             },
             data: Data {
                 #[cfg(feature = "test_mode")]
@@ -641,13 +858,21 @@ impl Default for SessionConfig {
                 ),
                 pipe_commands: Default::default(),
                 milters: Default::default(),
+                rspamd: None,
                 max_messages: IfBlock::new::<()>("session.data.limits.messages", [], "10"),
                 max_message_size: IfBlock::new::<()>("session.data.limits.size", [], "104857600"),
+                max_header_size: IfBlock::new::<()>(
+                    "session.data.limits.header-size",
+                    [],
+                    "524288",
+                ),
                 max_received_headers: IfBlock::new::<()>(
                     "session.data.limits.received-headers",
                     [],
                     "50",
                 ),
+                sending_limits: Default::default(),
+                undo_window: IfBlock::new::<()>("session.data.undo-window", [], "false"),
                 add_received: IfBlock::new::<()>(
                     "session.data.add-headers.received",
                     [("local_port == 25", "true")],
@@ -716,6 +941,7 @@ impl Default for SessionConfig {
                 ),
             },
             mta_sts_policy: None,
+            srs: None,
         }
     }
 }
@@ -730,9 +956,10 @@ impl ParseValue for Mechanism {
             "PLAIN" => AUTH_PLAIN,
             "XOAUTH2" => AUTH_XOAUTH2,
             "OAUTHBEARER" => AUTH_OAUTHBEARER,
-            /*"SCRAM-SHA-256-PLUS" => AUTH_SCRAM_SHA_256_PLUS,
+            "SCRAM-SHA-256-PLUS" => AUTH_SCRAM_SHA_256_PLUS,
             "SCRAM-SHA-256" => AUTH_SCRAM_SHA_256,
-            "SCRAM-SHA-1-PLUS" => AUTH_SCRAM_SHA_1_PLUS,
+            "CRAM-MD5" => AUTH_CRAM_MD5,
+            /*"SCRAM-SHA-1-PLUS" => AUTH_SCRAM_SHA_1_PLUS,
             "SCRAM-SHA-1" => AUTH_SCRAM_SHA_1,
             "XOAUTH" => AUTH_XOAUTH,
             "9798-M-DSA-SHA1" => AUTH_9798_M_DSA_SHA1,
@@ -765,7 +992,6 @@ impl ParseValue for Mechanism {
             "SPNEGO" => AUTH_SPNEGO,
             "SPNEGO-PLUS" => AUTH_SPNEGO_PLUS,
             "SXOVER-PLUS" => AUTH_SXOVER_PLUS,
-            "CRAM-MD5" => AUTH_CRAM_MD5,
             "DIGEST-MD5" => AUTH_DIGEST_MD5,
             "ANONYMOUS" => AUTH_ANONYMOUS,*/
             _ => return Err(format!("Unsupported mechanism {:?}.", value)),
@@ -808,7 +1034,10 @@ impl ConstantValue for Mechanism {
             .add_constant("login", Mechanism(AUTH_LOGIN))
             .add_constant("plain", Mechanism(AUTH_PLAIN))
             .add_constant("xoauth2", Mechanism(AUTH_XOAUTH2))
-            .add_constant("oauthbearer", Mechanism(AUTH_OAUTHBEARER));
+            .add_constant("oauthbearer", Mechanism(AUTH_OAUTHBEARER))
+            .add_constant("scram_sha_256", Mechanism(AUTH_SCRAM_SHA_256))
+            .add_constant("scram_sha_256_plus", Mechanism(AUTH_SCRAM_SHA_256_PLUS))
+            .add_constant("cram_md5", Mechanism(AUTH_CRAM_MD5));
     }
 }
 
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use ahash::AHashMap;
 use directory::Directory;
-use store::{write::purge::PurgeSchedule, BlobStore, FtsStore, LookupStore, Store};
+use store::{write::purge::PurgeSchedule, BlobStore, FtsStore, LookupStore, PubSubStore, Store};
 
 use crate::manager::config::ConfigManager;
 
@@ -12,6 +12,7 @@ pub struct Storage {
     pub blob: BlobStore,
     pub fts: FtsStore,
     pub lookup: LookupStore,
+    pub pubsub: PubSubStore,
     pub directory: Arc<Directory>,
     pub directories: AHashMap<String, Arc<Directory>>,
     pub purge_schedules: Vec<PurgeSchedule>,
@@ -21,4 +22,5 @@ pub struct Storage {
     pub blobs: AHashMap<String, BlobStore>,
     pub lookups: AHashMap<String, LookupStore>,
     pub ftss: AHashMap<String, FtsStore>,
+    pub pubsubs: AHashMap<String, PubSubStore>,
 }
@@ -1,20 +1,27 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, str::FromStr, time::Duration};
 
 use opentelemetry_otlp::{HttpExporterBuilder, TonicExporterBuilder, WithExportConfig};
 use tracing::Level;
 use tracing_appender::rolling::RollingFileAppender;
 use utils::config::Config;
 
+use crate::{
+    history::{EventFilter, HistoryConfig},
+    redact::Redactor,
+};
+
 #[derive(Debug)]
 pub enum Tracer {
     Stdout {
         level: Level,
         ansi: bool,
+        redact: Option<Redactor>,
     },
     Log {
         level: Level,
         appender: RollingFileAppender,
         ansi: bool,
+        redact: Option<Redactor>,
     },
     Journal {
         level: Level,
@@ -23,6 +30,10 @@ pub enum Tracer {
         level: Level,
         tracer: OtelTracer,
     },
+    History {
+        level: Level,
+        config: HistoryConfig,
+    },
 }
 
 #[derive(Debug)]
@@ -95,6 +106,7 @@ impl Tracers {
                             ansi: config
                                 .property_or_default(("tracer", id, "ansi"), "false")
                                 .unwrap_or(false),
+                            redact: Redactor::parse(config, id),
                         });
                     }
                 }
@@ -104,15 +116,27 @@ impl Tracers {
                         ansi: config
                             .property_or_default(("tracer", id, "ansi"), "true")
                             .unwrap_or(true),
+                        redact: Redactor::parse(config, id),
                     });
                 }
                 "otel" | "open-telemetry" => {
+                    if config.has_prefix(("tracer", id, "redact")) {
+                        config.new_build_error(
+                            ("tracer", id, "redact"),
+                            "Redaction is not supported by OpenTelemetry tracers".to_string(),
+                        );
+                    }
+                    let timeout = config
+                        .property_or_default::<Duration>(("tracer", id, "timeout"), "10s")
+                        .unwrap_or(Duration::from_secs(10));
                     match config
                         .value_require(("tracer", id, "transport"))
                         .unwrap_or_default()
                     {
-                        "gprc" => {
-                            let mut exporter = opentelemetry_otlp::new_exporter().tonic();
+                        "grpc" | "gprc" => {
+                            let mut exporter = opentelemetry_otlp::new_exporter()
+                                .tonic()
+                                .with_timeout(timeout);
                             if let Some(endpoint) = config.value(("tracer", id, "endpoint")) {
                                 exporter = exporter.with_endpoint(endpoint);
                             }
@@ -146,7 +170,8 @@ impl Tracers {
 
                                 let mut exporter = opentelemetry_otlp::new_exporter()
                                     .http()
-                                    .with_endpoint(endpoint);
+                                    .with_endpoint(endpoint)
+                                    .with_timeout(timeout);
                                 if !headers.is_empty() {
                                     exporter = exporter.with_headers(headers);
                                 }
@@ -164,7 +189,58 @@ impl Tracers {
                         }
                     }
                 }
+                "history" => {
+                    if tracers.iter().any(|t| matches!(t, Tracer::History { .. })) {
+                        config.new_build_error(
+                            ("tracer", id, "type"),
+                            "Only one history tracer is allowed".to_string(),
+                        );
+                        continue;
+                    }
+
+                    let mut events = Vec::new();
+                    let mut invalid = Vec::new();
+                    for (_, value) in config.values(("tracer", id, "events")) {
+                        if let Some(filter) = EventFilter::parse(value) {
+                            events.push(filter);
+                        } else {
+                            invalid.push(format!("Invalid event filter {value:?}"));
+                        }
+                    }
+                    for err in invalid {
+                        config.new_parse_error(("tracer", id, "events"), err);
+                    }
+                    if events.is_empty() {
+                        events = DEFAULT_HISTORY_EVENTS
+                            .iter()
+                            .filter_map(|value| EventFilter::parse(value))
+                            .collect();
+                    }
+
+                    tracers.push(Tracer::History {
+                        level,
+                        config: HistoryConfig {
+                            events,
+                            redact: Redactor::parse(config, id),
+                            capacity: config
+                                .property_or_default(("tracer", id, "capacity"), "10000")
+                                .unwrap_or(10000),
+                            retention: config
+                                .property_or_default::<Option<Duration>>(
+                                    ("tracer", id, "retention"),
+                                    "30d",
+                                )
+                                .unwrap_or_default(),
+                        },
+                    });
+                }
                 "journal" => {
+                    if config.has_prefix(("tracer", id, "redact")) {
+                        config.new_build_error(
+                            ("tracer", id, "redact"),
+                            "Redaction is not supported by journal tracers".to_string(),
+                        );
+                    }
                     if !tracers.iter().any(|t| matches!(t, Tracer::Journal { .. })) {
                         tracers.push(Tracer::Journal { level });
                     } else {
@@ -186,3 +262,22 @@ impl Tracers {
         Tracers { tracers }
     }
 }
+
+static DEFAULT_HISTORY_EVENTS: &[&str] = &[
+    "auth.failed",
+    "directory.invalid_password",
+    "directory.fail2ban",
+    "authenticate.authz-denied",
+    "rcpt.failed",
+    "rcpt.rejected",
+    "queue.error",
+    "queue.quota-exceeded",
+    "queue.double-bounce",
+    "deliver_local.rejected",
+    "deliver_local.error",
+    "message.rejected",
+    "sieve.reject",
+    "milter.reject",
+    "dmarc.auth-failed",
+    "throttle.rate-limit-exceeded",
+];
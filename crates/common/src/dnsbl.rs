@@ -0,0 +1,211 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::Arc,
+    time::Instant,
+};
+
+use futures::future::join_all;
+use mail_auth::common::{lru::DnsCache, resolver::ToReverseName};
+use mail_parser::MessageParser;
+use nlp::tokenizers::types::{TokenType, TypesTokenizer};
+use utils::suffixlist::DomainPart;
+
+use crate::{
+    config::smtp::dnsbl::{DnsListTarget, DnsblResult, UrlHit, UrlReputation},
+    Core,
+};
+
+impl Core {
+    pub async fn dnsbl_check_ip(&self, ip: IpAddr) -> Arc<DnsblResult> {
+        self.dnsbl_check(DnsListTarget::Ip, &ip.to_reverse_name())
+            .await
+    }
+
+    pub async fn dnsbl_check_domain(&self, domain: &str) -> Arc<DnsblResult> {
+        self.dnsbl_check(DnsListTarget::Domain, domain).await
+    }
+
+    /// Checks the domains of the URLs found in the message bodies against the
+    /// local blocklist and the URL DNS lists.
+    pub async fn url_reputation(&self, raw_message: &[u8]) -> UrlReputation {
+        let config = &self.smtp.dnsbl;
+        let mut result = UrlReputation::default();
+        let blocklist = config
+            .url_blocklist
+            .as_ref()
+            .and_then(|id| self.storage.lookups.get(id));
+        let has_lists = config.has_lists(DnsListTarget::Url);
+
+        for host in self.url_hosts(raw_message, config.url_max_hosts) {
+            if let Some(blocklist) = blocklist {
+                if blocklist
+                    .key_exists(host.as_bytes().to_vec())
+                    .await
+                    .unwrap_or(false)
+                {
+                    result.score += config.url_blocklist_weight;
+                    result.hits.push(UrlHit {
+                        list: "blocklist".to_string(),
+                        host: host.clone(),
+                    });
+                }
+            }
+
+            if has_lists {
+                let lookup = self.dnsbl_check(DnsListTarget::Url, &host).await;
+                result.score += lookup.score;
+                result.hits.extend(lookup.hits.iter().map(|list| UrlHit {
+                    list: list.clone(),
+                    host: host.clone(),
+                }));
+            }
+        }
+
+        result
+    }
+
+    fn url_hosts(&self, raw_message: &[u8], max_hosts: usize) -> Vec<String> {
+        let mut hosts = Vec::new();
+        let message = if let Some(message) = MessageParser::new().parse(raw_message) {
+            message
+        } else {
+            return hosts;
+        };
+
+        for text in message
+            .text_body
+            .iter()
+            .chain(message.html_body.iter())
+            .filter_map(|part_id| message.parts.get(*part_id)?.text_contents())
+        {
+            for token in TypesTokenizer::new(text, &self.smtp.resolvers.psl)
+                .tokenize_numbers(false)
+                .tokenize_urls(true)
+                .tokenize_urls_without_scheme(false)
+                .tokenize_emails(false)
+            {
+                let url = if let TokenType::Url(url) = token.word {
+                    url
+                } else {
+                    continue;
+                };
+
+                // Obtain the registered domain of the URL host
+                let host = url
+                    .split_once("://")
+                    .map_or(url, |(_, rest)| rest)
+                    .split(['/', '?', '#'])
+                    .next()
+                    .unwrap_or_default();
+                let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+                let host = host.split(':').next().unwrap_or_default().to_lowercase();
+                if host.parse::<IpAddr>().is_ok() {
+                    continue;
+                }
+                if let Some(domain) = self.smtp.resolvers.psl.domain_part(&host, DomainPart::Sld) {
+                    if !hosts.contains(&domain) {
+                        hosts.push(domain);
+                        if hosts.len() >= max_hosts {
+                            return hosts;
+                        }
+                    }
+                }
+            }
+        }
+
+        hosts
+    }
+
+    /// Queries all lists of the given type concurrently, returning the combined
+    /// weight and the ids of the lists where the name is listed.
+    async fn dnsbl_check(&self, target: DnsListTarget, name: &str) -> Arc<DnsblResult> {
+        // URL hosts are cached separately as the same domain may be listed
+        // on both sender and URL lists
+        let cache = &self.smtp.resolvers.cache.dnsbl;
+        let cache_key = if target == DnsListTarget::Url {
+            format!("url:{name}")
+        } else {
+            name.to_string()
+        };
+        if let Some(result) = cache.get(cache_key.as_str()) {
+            return result;
+        }
+
+        let lists = self
+            .smtp
+            .dnsbl
+            .lists
+            .iter()
+            .filter(|list| list.target == target)
+            .collect::<Vec<_>>();
+        let responses = join_all(lists.iter().map(|list| {
+            let query = format!("{name}.{}", list.zone);
+            async move { self.smtp.resolvers.dns.ipv4_lookup(query.as_str()).await }
+        }))
+        .await;
+
+        let mut result = DnsblResult::default();
+        let mut is_cacheable = true;
+        for (list, response) in lists.into_iter().zip(responses) {
+            match response {
+                Ok(addrs) => {
+                    if addrs.iter().any(is_listed) {
+                        result.score += list.weight;
+                        result.hits.push(list.id.clone());
+                    }
+                }
+                Err(mail_auth::Error::DnsRecordNotFound(_)) => (),
+                Err(err) => {
+                    is_cacheable = false;
+                    tracing::debug!(
+                        context = "dnsbl",
+                        event = "error",
+                        zone = list.zone,
+                        name = name,
+                        reason = %err,
+                        "DNS list lookup failed."
+                    );
+                }
+            }
+        }
+
+        if is_cacheable {
+            cache.insert(
+                cache_key,
+                Arc::new(result),
+                Instant::now() + self.smtp.dnsbl.ttl,
+            )
+        } else {
+            Arc::new(result)
+        }
+    }
+}
+
+fn is_listed(addr: &Ipv4Addr) -> bool {
+    // 127.255.255.0/24 is used by some lists to report query errors
+    let octets = addr.octets();
+    octets[0] == 127 && !(octets[1] == 255 && octets[2] == 255)
+}
@@ -85,6 +85,7 @@ pub(crate) const FUNCTIONS: &[(&str, fn(Vec<Variable>) -> Variable, u32)] = &[
     ("eq_ignore_case", text::fn_eq_ignore_case, 2),
     ("starts_with", text::fn_starts_with, 2),
     ("ends_with", text::fn_ends_with, 2),
+    ("matches_glob", text::fn_matches_glob, 2),
     ("lines", text::fn_lines, 1),
     ("substring", text::fn_substring, 3),
     ("strip_prefix", text::fn_strip_prefix, 2),
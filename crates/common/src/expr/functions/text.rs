@@ -23,6 +23,8 @@
 
 use std::borrow::Cow;
 
+use utils::glob::GlobPattern;
+
 use crate::expr::Variable;
 
 pub(crate) fn fn_trim(mut v: Vec<Variable>) -> Variable {
@@ -164,6 +166,12 @@ pub(crate) fn fn_ends_with(v: Vec<Variable>) -> Variable {
     v[0].to_string().ends_with(v[1].to_string().as_ref()).into()
 }
 
+pub(crate) fn fn_matches_glob(v: Vec<Variable>) -> Variable {
+    GlobPattern::compile(v[0].to_string().as_ref(), true)
+        .matches(v[1].to_string().as_ref())
+        .into()
+}
+
 pub(crate) fn fn_lines(mut v: Vec<Variable>) -> Variable {
     match v.remove(0) {
         Variable::String(s) => s
@@ -48,6 +48,9 @@ pub const V_QUEUE_NOTIFY_NUM: u32 = 17;
 pub const V_QUEUE_EXPIRES_IN: u32 = 18;
 pub const V_QUEUE_LAST_STATUS: u32 = 19;
 pub const V_QUEUE_LAST_ERROR: u32 = 20;
+pub const V_QUEUE_SIZE: u32 = 21;
+pub const V_RECIPIENT_LOCAL_PART: u32 = 22;
+pub const V_TLS_SNI: u32 = 23;
 
 pub const VARIABLES_MAP: &[(&str, u32)] = &[
     ("rcpt", V_RECIPIENT),
@@ -65,12 +68,14 @@ pub const VARIABLES_MAP: &[(&str, u32)] = &[
     ("remote_port", V_REMOTE_PORT),
     ("protocol", V_PROTOCOL),
     ("is_tls", V_TLS),
+    ("tls_sni", V_TLS_SNI),
     ("recipients", V_RECIPIENTS),
     ("retry_num", V_QUEUE_RETRY_NUM),
     ("notify_num", V_QUEUE_NOTIFY_NUM),
     ("expires_in", V_QUEUE_EXPIRES_IN),
     ("last_status", V_QUEUE_LAST_STATUS),
     ("last_error", V_QUEUE_LAST_ERROR),
+    ("size", V_QUEUE_SIZE),
 ];
 
 use regex::Regex;
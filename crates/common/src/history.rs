@@ -0,0 +1,216 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{fmt::Debug, sync::Mutex, time::Duration};
+
+use store::write::now;
+use tokio::sync::mpsc;
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::redact::Redactor;
+
+const HISTORY_CHANNEL_BUFFER: usize = 8192;
+
+static HISTORY_RX: Mutex<Option<HistoryReceiver>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEvent {
+    pub timestamp: u64,
+    pub level: String,
+    pub context: String,
+    pub event: String,
+    pub message: String,
+    pub fields: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryConfig {
+    pub events: Vec<EventFilter>,
+    pub redact: Option<Redactor>,
+    pub capacity: usize,
+    pub retention: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventFilter {
+    pub context: String,
+    pub event: Option<String>,
+}
+
+pub struct HistoryReceiver {
+    pub rx: mpsc::Receiver<HistoryEvent>,
+    pub capacity: usize,
+    pub retention: Option<Duration>,
+}
+
+pub struct HistoryLayer {
+    events: Vec<EventFilter>,
+    redact: Option<Redactor>,
+    tx: mpsc::Sender<HistoryEvent>,
+}
+
+struct SpanFields(Vec<(String, String)>);
+
+#[derive(Default)]
+struct FieldVisitor {
+    context: String,
+    event: String,
+    message: String,
+    fields: Vec<(String, String)>,
+}
+
+impl HistoryLayer {
+    pub fn new(config: HistoryConfig) -> Self {
+        let (tx, rx) = mpsc::channel(HISTORY_CHANNEL_BUFFER);
+        *HISTORY_RX.lock().unwrap() = Some(HistoryReceiver {
+            rx,
+            capacity: config.capacity,
+            retention: config.retention,
+        });
+        HistoryLayer {
+            events: config.events,
+            redact: config.redact,
+            tx,
+        }
+    }
+
+    /// Returns the receiving end of the history channel, if an event history
+    /// tracer has been configured. Can only be obtained once.
+    pub fn take_receiver() -> Option<HistoryReceiver> {
+        HISTORY_RX.lock().unwrap().take()
+    }
+
+    fn is_recorded(&self, context: &str, event: &str) -> bool {
+        self.events.iter().any(|filter| {
+            filter.context == context && filter.event.as_ref().map_or(true, |e| e == event)
+        })
+    }
+}
+
+impl<S> Layer<S> for HistoryLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut visitor = FieldVisitor::default();
+            attrs.record(&mut visitor);
+            span.extensions_mut().insert(SpanFields(visitor.fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut visitor = FieldVisitor::default();
+            values.record(&mut visitor);
+            if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+                fields.0.extend(visitor.fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        if !self.is_recorded(&visitor.context, &visitor.event) {
+            return;
+        }
+
+        // Include the fields of the enclosing spans (remote IP, protocol, etc.)
+        let mut fields = Vec::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+                    fields.extend(span_fields.0.iter().cloned());
+                }
+            }
+        }
+        fields.extend(visitor.fields);
+
+        // Apply the redaction policy before the event is stored
+        let mut message = visitor.message;
+        if let Some(redact) = &self.redact {
+            for (name, value) in &mut fields {
+                *value = redact.redact_field(name, value).into_owned();
+            }
+            message = redact.redact_emails(&message).into_owned();
+        }
+
+        let _ = self.tx.try_send(HistoryEvent {
+            timestamp: now(),
+            level: event.metadata().level().to_string(),
+            context: visitor.context,
+            event: visitor.event,
+            message,
+            fields,
+        });
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "context" => self.context = value.to_string(),
+            "event" => self.event = value.to_string(),
+            "message" => self.message = value.to_string(),
+            name => self.fields.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let value = format!("{value:?}");
+        match field.name() {
+            "message" => self.message = value,
+            name => self
+                .fields
+                .push((name.to_string(), value.trim_matches('"').to_string())),
+        }
+    }
+}
+
+impl HistoryEvent {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find_map(|(k, v)| if k == name { Some(v.as_str()) } else { None })
+    }
+}
+
+impl EventFilter {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (context, event) = value.split_once('.').unwrap_or((value, "*"));
+        if !context.is_empty() && !event.is_empty() {
+            Some(EventFilter {
+                context: context.to_string(),
+                event: (event != "*").then(|| event.to_string()),
+            })
+        } else {
+            None
+        }
+    }
+}
@@ -25,8 +25,10 @@ use std::{borrow::Cow, net::IpAddr, sync::Arc};
 
 use arc_swap::ArcSwap;
 use config::{
+    dav::DavConfig,
     imap::ImapConfig,
     jmap::settings::JmapConfig,
+    managesieve::ManageSieveConfig,
     scripts::Scripting,
     smtp::{
         auth::{ArcSealer, DkimSigner},
@@ -38,6 +40,7 @@ use config::{
 };
 use directory::{core::secret::verify_secret_hash, Directory, Principal, QueryBy};
 use expr::if_block::IfBlock;
+use history::HistoryLayer;
 use listener::{
     blocked::{AllowedIps, BlockedIps},
     tls::TlsManager,
@@ -56,14 +59,19 @@ use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{
     layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer, Registry,
 };
-use utils::{config::Config, BlobHash};
+use utils::{config::Config, metrics::AUTH_TOTAL, BlobHash};
 
 pub mod addresses;
 pub mod config;
+pub mod dnsbl;
 pub mod expr;
+pub mod history;
 pub mod listener;
 pub mod manager;
+pub mod redact;
+pub mod sasl;
 pub mod scripts;
+pub mod srs;
 
 pub static USER_AGENT: &str = concat!("StalwartMail/", env!("CARGO_PKG_VERSION"),);
 pub static DAEMON_NAME: &str = concat!("Stalwart Mail Server v", env!("CARGO_PKG_VERSION"),);
@@ -79,6 +87,8 @@ pub struct Core {
     pub smtp: SmtpConfig,
     pub jmap: JmapConfig,
     pub imap: ImapConfig,
+    pub managesieve: ManageSieveConfig,
+    pub dav: DavConfig,
 }
 
 #[derive(Clone)]
@@ -233,6 +243,25 @@ impl Core {
         credentials: &Credentials<String>,
         remote_ip: IpAddr,
         return_member_of: bool,
+    ) -> directory::Result<AuthResult<Principal<u32>>> {
+        let result = self
+            .authenticate_credentials(directory, credentials, remote_ip, return_member_of)
+            .await;
+        AUTH_TOTAL.increment(match &result {
+            Ok(AuthResult::Success(_)) => "success",
+            Ok(AuthResult::Failure) => "failure",
+            Ok(AuthResult::Banned) => "banned",
+            Err(_) => "error",
+        });
+        result
+    }
+
+    async fn authenticate_credentials(
+        &self,
+        directory: &Directory,
+        credentials: &Credentials<String>,
+        remote_ip: IpAddr,
+        return_member_of: bool,
     ) -> directory::Result<AuthResult<Principal<u32>>> {
         // First try to authenticate the user against the default directory
         let result = match directory
@@ -282,12 +311,22 @@ impl Core {
 
         if let Err(err) = result {
             Err(err)
-        } else if self.has_fail2ban() {
+        } else {
             let login = match credentials {
                 Credentials::Plain { username, .. }
                 | Credentials::XOauth2 { username, .. }
                 | Credentials::OAuthBearer { token: username } => username,
             };
+            self.auth_failure(remote_ip, login).await
+        }
+    }
+
+    pub async fn auth_failure<T>(
+        &self,
+        remote_ip: IpAddr,
+        login: &str,
+    ) -> directory::Result<AuthResult<T>> {
+        if self.has_fail2ban() {
             if self.is_fail2banned(remote_ip, login.to_string()).await? {
                 tracing::info!(
                     context = "directory",
@@ -316,10 +355,11 @@ impl Tracers {
             let (Tracer::Stdout { level, .. }
             | Tracer::Log { level, .. }
             | Tracer::Journal { level }
-            | Tracer::Otel { level, .. }) = tracer;
+            | Tracer::Otel { level, .. }
+            | Tracer::History { level, .. }) = tracer;
 
             let filter = match EnvFilter::builder().parse(format!(
-                "smtp={level},imap={level},jmap={level},pop3={level},store={level},common={level},utils={level},directory={level}"
+                "smtp={level},imap={level},jmap={level},pop3={level},managesieve={level},store={level},common={level},utils={level},directory={level}"
             )) {
                 Ok(filter) => {
                     filter
@@ -331,18 +371,30 @@ impl Tracers {
             };
 
             let layer = match tracer {
-                Tracer::Stdout { ansi, .. } => tracing_subscriber::fmt::layer()
-                    .with_ansi(ansi)
-                    .with_filter(filter)
-                    .boxed(),
-                Tracer::Log { appender, ansi, .. } => {
+                Tracer::Stdout { ansi, redact, .. } => {
+                    let layer = tracing_subscriber::fmt::layer().with_ansi(ansi);
+                    if let Some(redact) = redact {
+                        layer.fmt_fields(redact).with_filter(filter).boxed()
+                    } else {
+                        layer.with_filter(filter).boxed()
+                    }
+                }
+                Tracer::Log {
+                    appender,
+                    ansi,
+                    redact,
+                    ..
+                } => {
                     let (non_blocking, guard) = tracing_appender::non_blocking(appender);
                     guards.push(guard);
-                    tracing_subscriber::fmt::layer()
+                    let layer = tracing_subscriber::fmt::layer()
                         .with_writer(non_blocking)
-                        .with_ansi(ansi)
-                        .with_filter(filter)
-                        .boxed()
+                        .with_ansi(ansi);
+                    if let Some(redact) = redact {
+                        layer.fmt_fields(redact).with_filter(filter).boxed()
+                    } else {
+                        layer.with_filter(filter).boxed()
+                    }
                 }
                 Tracer::Otel { tracer, .. } => {
                     let tracer = match tracer {
@@ -380,6 +432,9 @@ impl Tracers {
                         }
                     }
                 }
+                Tracer::History { config, .. } => {
+                    HistoryLayer::new(config).with_filter(filter).boxed()
+                }
                 Tracer::Journal { .. } => {
                     #[cfg(unix)]
                     {
@@ -26,11 +26,12 @@ pub mod directory;
 pub mod jose;
 pub mod order;
 pub mod resolver;
+pub mod route53;
 
 use std::{fmt::Debug, sync::Arc, time::Duration};
 
 use arc_swap::ArcSwap;
-use dns_update::DnsUpdater;
+use dns_update::{DnsRecord, DnsUpdater};
 use rustls::sign::CertifiedKey;
 
 use crate::Core;
@@ -38,6 +39,7 @@ use crate::Core;
 use self::{
     directory::{Account, ChallengeType},
     order::{CertParseError, OrderError},
+    route53::Route53,
 };
 
 pub struct AcmeProvider {
@@ -56,7 +58,7 @@ pub enum ChallengeSettings {
     Http01,
     TlsAlpn01,
     Dns01 {
-        updater: DnsUpdater,
+        updater: DnsProvider,
         origin: Option<String>,
         polling_interval: Duration,
         propagation_timeout: Duration,
@@ -64,6 +66,18 @@ pub enum ChallengeSettings {
     },
 }
 
+#[derive(Clone)]
+pub enum DnsProvider {
+    Update(DnsUpdater),
+    Route53(Route53),
+}
+
+#[derive(Debug)]
+pub enum DnsError {
+    Update(dns_update::Error),
+    Route53(String),
+}
+
 pub struct StaticResolver {
     pub key: Option<Arc<CertifiedKey>>,
 }
@@ -155,6 +169,39 @@ impl ChallengeSettings {
     }
 }
 
+impl DnsProvider {
+    pub async fn create(
+        &self,
+        name: &str,
+        content: String,
+        ttl: u32,
+        origin: &str,
+    ) -> Result<(), DnsError> {
+        match self {
+            DnsProvider::Update(updater) => updater
+                .create(name, DnsRecord::TXT { content }, ttl, origin)
+                .await
+                .map_err(DnsError::Update),
+            DnsProvider::Route53(route53) => route53
+                .create(name, &content, ttl, origin)
+                .await
+                .map_err(DnsError::Route53),
+        }
+    }
+
+    pub async fn delete(&self, name: &str, origin: &str) -> Result<(), DnsError> {
+        match self {
+            DnsProvider::Update(updater) => {
+                updater.delete(name, origin).await.map_err(DnsError::Update)
+            }
+            DnsProvider::Route53(route53) => route53
+                .delete(name, origin)
+                .await
+                .map_err(DnsError::Route53),
+        }
+    }
+}
+
 impl Debug for StaticResolver {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("StaticResolver").finish()
@@ -1,7 +1,6 @@
 // Adapted from rustls-acme (https://github.com/FlorianUekermann/rustls-acme), licensed under MIT/Apache-2.0.
 
 use chrono::{DateTime, TimeZone, Utc};
-use dns_update::DnsRecord;
 use futures::future::try_join_all;
 use rcgen::{CertificateParams, DistinguishedName, PKCS_ECDSA_P256_SHA256};
 use rustls::crypto::ring::sign::any_ecdsa_type;
@@ -19,7 +18,7 @@ use crate::Core;
 
 use super::directory::{Account, Auth, AuthStatus, Directory, DirectoryError, Order, OrderStatus};
 use super::jose::JoseError;
-use super::{AcmeError, AcmeProvider};
+use super::{AcmeError, AcmeProvider, DnsError};
 
 #[derive(Debug)]
 pub enum OrderError {
@@ -30,7 +29,7 @@ pub enum OrderError {
     TooManyAttemptsAuth(String),
     ProcessingTimeout(Order),
     Store(store::Error),
-    Dns(dns_update::Error),
+    Dns(DnsError),
 }
 
 #[derive(Debug)]
@@ -272,14 +271,7 @@ impl Core {
 
                         // Create the record
                         if let Err(err) = updater
-                            .create(
-                                &name,
-                                DnsRecord::TXT {
-                                    content: dns_proof.clone(),
-                                },
-                                *ttl,
-                                &origin,
-                            )
+                            .create(&name, dns_proof.clone(), *ttl, &origin)
                             .await
                         {
                             tracing::warn!(
@@ -0,0 +1,398 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{fmt::Write, time::Duration};
+
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use ring::{
+    digest::{digest, SHA256},
+    hmac,
+};
+
+use crate::USER_AGENT;
+
+const ENDPOINT: &str = "route53.amazonaws.com";
+const REGION: &str = "us-east-1";
+const SERVICE: &str = "route53";
+const API_VERSION: &str = "2013-04-01";
+
+/// Minimal Amazon Route 53 client used to publish DNS-01 challenge records.
+#[derive(Clone)]
+pub struct Route53 {
+    client: reqwest::Client,
+    credentials: Credentials,
+    hosted_zone_id: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+}
+
+pub struct SignedRequest<'x> {
+    pub method: &'x str,
+    pub path: &'x str,
+    pub query: &'x str,
+    pub headers: &'x [(&'x str, &'x str)],
+    pub payload: &'x [u8],
+}
+
+impl Route53 {
+    pub fn new(
+        credentials: Credentials,
+        hosted_zone_id: Option<String>,
+        timeout: Duration,
+    ) -> Result<Self, String> {
+        Ok(Route53 {
+            client: reqwest::Client::builder()
+                .user_agent(USER_AGENT)
+                .timeout(timeout)
+                .build()
+                .map_err(|err| err.to_string())?,
+            credentials,
+            hosted_zone_id,
+        })
+    }
+
+    /// Creates or replaces a TXT record.
+    pub async fn create(
+        &self,
+        name: &str,
+        content: &str,
+        ttl: u32,
+        origin: &str,
+    ) -> Result<(), String> {
+        let zone_id = self.hosted_zone_id(origin).await?;
+        let record_set = format!(
+            concat!(
+                "<ResourceRecordSet><Name>{}.</Name><Type>TXT</Type><TTL>{}</TTL>",
+                "<ResourceRecords><ResourceRecord><Value>\"{}\"</Value></ResourceRecord>",
+                "</ResourceRecords></ResourceRecordSet>"
+            ),
+            name,
+            ttl,
+            xml_escape(content)
+        );
+        self.change(&zone_id, "UPSERT", &record_set).await
+    }
+
+    /// Deletes the TXT record set with the given name, if it exists.
+    pub async fn delete(&self, name: &str, origin: &str) -> Result<(), String> {
+        // Route 53 requires the exact record set to delete, so fetch it first
+        let zone_id = self.hosted_zone_id(origin).await?;
+        let fqdn = format!("{name}.");
+        let response = self
+            .request(
+                Method::GET,
+                &format!("/{API_VERSION}/hostedzone/{zone_id}/rrset"),
+                &[("maxitems", "1"), ("name", &fqdn), ("type", "TXT")],
+                None,
+            )
+            .await?;
+
+        match xml_elements(&response, "ResourceRecordSet").next() {
+            Some(record_set)
+                if xml_value(record_set, "Name") == Some(fqdn.as_str())
+                    && xml_value(record_set, "Type") == Some("TXT") =>
+            {
+                self.change(&zone_id, "DELETE", record_set).await
+            }
+            _ => Err(format!("TXT record {name} not found")),
+        }
+    }
+
+    async fn change(&self, zone_id: &str, action: &str, record_set: &str) -> Result<(), String> {
+        let body = format!(
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>",
+                "<ChangeResourceRecordSetsRequest xmlns=\"https://route53.amazonaws.com/doc/{}/\">",
+                "<ChangeBatch><Changes><Change><Action>{}</Action>{}</Change></Changes>",
+                "</ChangeBatch></ChangeResourceRecordSetsRequest>"
+            ),
+            API_VERSION, action, record_set
+        );
+        self.request(
+            Method::POST,
+            &format!("/{API_VERSION}/hostedzone/{zone_id}/rrset/"),
+            &[],
+            body.into_bytes().into(),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn hosted_zone_id(&self, origin: &str) -> Result<String, String> {
+        if let Some(zone_id) = &self.hosted_zone_id {
+            return Ok(zone_id.clone());
+        }
+
+        // Zones are listed in lexicographic order starting at the requested
+        // name, so make sure the first result is an exact match
+        let origin = format!("{}.", origin.trim_end_matches('.'));
+        let response = self
+            .request(
+                Method::GET,
+                &format!("/{API_VERSION}/hostedzonesbyname"),
+                &[("dnsname", &origin), ("maxitems", "1")],
+                None,
+            )
+            .await?;
+        xml_elements(&response, "HostedZone")
+            .next()
+            .filter(|zone| xml_value(zone, "Name") == Some(origin.as_str()))
+            .and_then(|zone| xml_value(zone, "Id"))
+            .map(|id| id.trim_start_matches("/hostedzone/").to_string())
+            .ok_or_else(|| format!("Hosted zone for {origin} not found"))
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<Vec<u8>>,
+    ) -> Result<String, String> {
+        // Parameters have to be sorted by name
+        let query = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", uri_encode(name), uri_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let payload = body.unwrap_or_default();
+        let timestamp = Utc::now();
+        let amz_date = timestamp.format("%Y%m%dT%H%M%SZ").to_string();
+        let mut headers = vec![("host", ENDPOINT), ("x-amz-date", amz_date.as_str())];
+        if let Some(session_token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", session_token.as_str()));
+        }
+        let authorization = self.credentials.authorization(
+            REGION,
+            SERVICE,
+            &SignedRequest {
+                method: method.as_str(),
+                path,
+                query: &query,
+                headers: &headers,
+                payload: &payload,
+            },
+            timestamp,
+        );
+
+        let url = if query.is_empty() {
+            format!("https://{ENDPOINT}{path}")
+        } else {
+            format!("https://{ENDPOINT}{path}?{query}")
+        };
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("Authorization", authorization);
+        // The host header is added by the client
+        for (name, value) in headers.into_iter().skip(1) {
+            request = request.header(name, value);
+        }
+        if !payload.is_empty() {
+            request = request
+                .header("Content-Type", "application/xml")
+                .body(payload);
+        }
+
+        let response = request.send().await.map_err(|err| err.to_string())?;
+        let status = response.status();
+        let response = response.text().await.map_err(|err| err.to_string())?;
+        if status.is_success() {
+            Ok(response)
+        } else {
+            Err(format!(
+                "Route 53 request failed with status {status}: {}",
+                xml_value(&response, "Message").unwrap_or(&response)
+            ))
+        }
+    }
+}
+
+impl Credentials {
+    /// Builds an AWS Signature Version 4 `Authorization` header. Headers
+    /// must be lowercase and sorted by name.
+    pub fn authorization(
+        &self,
+        region: &str,
+        service: &str,
+        request: &SignedRequest<'_>,
+        timestamp: DateTime<Utc>,
+    ) -> String {
+        let amz_date = timestamp.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = timestamp.format("%Y%m%d").to_string();
+        let scope = format!("{date}/{region}/{service}/aws4_request");
+
+        let mut canonical_request =
+            format!("{}\n{}\n{}\n", request.method, request.path, request.query);
+        let mut signed_headers = String::new();
+        for (name, value) in request.headers {
+            let _ = writeln!(&mut canonical_request, "{name}:{}", value.trim());
+            if !signed_headers.is_empty() {
+                signed_headers.push(';');
+            }
+            signed_headers.push_str(name);
+        }
+        let _ = write!(
+            &mut canonical_request,
+            "\n{signed_headers}\n{}",
+            hex(digest(&SHA256, request.payload).as_ref())
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(digest(&SHA256, canonical_request.as_bytes()).as_ref())
+        );
+
+        let mut key = format!("AWS4{}", self.secret_key).into_bytes();
+        for value in [date.as_str(), region, service, "aws4_request"] {
+            key = hmac_sha256(&key, value.as_bytes());
+        }
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={}",
+            self.access_key,
+            hex(&hmac_sha256(&key, string_to_sign.as_bytes()))
+        )
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data)
+        .as_ref()
+        .to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(&mut result, "{byte:02x}");
+    }
+    result
+}
+
+fn uri_encode(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            result.push(byte as char);
+        } else {
+            let _ = write!(&mut result, "%{byte:02X}");
+        }
+    }
+    result
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Returns the raw contents of all elements with the given name, including
+/// the enclosing tags.
+fn xml_elements<'x>(xml: &'x str, tag: &str) -> impl Iterator<Item = &'x str> + 'x {
+    let start_tag = format!("<{tag}>");
+    let end_tag = format!("</{tag}>");
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        let start = pos + xml.get(pos..)?.find(&start_tag)?;
+        let end = start + xml[start..].find(&end_tag)? + end_tag.len();
+        pos = end;
+        Some(&xml[start..end])
+    })
+}
+
+/// Returns the text of the first element with the given name.
+fn xml_value<'x>(xml: &'x str, tag: &str) -> Option<&'x str> {
+    let start_tag = format!("<{tag}>");
+    let start = xml.find(&start_tag)? + start_tag.len();
+    let end = start + xml[start..].find(&format!("</{tag}>"))?;
+    Some(&xml[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{xml_elements, xml_value, Credentials, SignedRequest};
+
+    #[test]
+    fn sigv4_signature() {
+        // Example from the AWS Signature Version 4 documentation
+        let credentials = Credentials {
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        };
+        assert_eq!(
+            credentials.authorization(
+                "us-east-1",
+                "iam",
+                &SignedRequest {
+                    method: "GET",
+                    path: "/",
+                    query: "Action=ListUsers&Version=2010-05-08",
+                    headers: &[
+                        (
+                            "content-type",
+                            "application/x-www-form-urlencoded; charset=utf-8"
+                        ),
+                        ("host", "iam.amazonaws.com"),
+                        ("x-amz-date", "20150830T123600Z"),
+                    ],
+                    payload: b"",
+                },
+                Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap(),
+            ),
+            concat!(
+                "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, ",
+                "SignedHeaders=content-type;host;x-amz-date, ",
+                "Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+            )
+        );
+    }
+
+    #[test]
+    fn xml_extract() {
+        let xml = concat!(
+            "<ListResourceRecordSetsResponse><ResourceRecordSets>",
+            "<ResourceRecordSet><Name>_acme-challenge.example.org.</Name><Type>TXT</Type></ResourceRecordSet>",
+            "<ResourceRecordSet><Name>example.org.</Name><Type>MX</Type></ResourceRecordSet>",
+            "</ResourceRecordSets></ListResourceRecordSetsResponse>"
+        );
+        let sets = xml_elements(xml, "ResourceRecordSet").collect::<Vec<_>>();
+        assert_eq!(sets.len(), 2);
+        assert_eq!(
+            xml_value(sets[0], "Name"),
+            Some("_acme-challenge.example.org.")
+        );
+        assert_eq!(xml_value(sets[1], "Type"), Some("MX"));
+        assert_eq!(xml_value(xml, "Id"), None);
+    }
+}
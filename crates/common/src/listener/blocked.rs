@@ -25,10 +25,12 @@ use std::{
     fmt::Debug,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::atomic::AtomicU8,
+    time::Duration,
 };
 
-use ahash::AHashSet;
+use ahash::{AHashMap, AHashSet};
 use parking_lot::RwLock;
+use store::write::now;
 use utils::config::{
     ipmask::{IpAddrMask, IpAddrOrMask},
     utils::ParseValue,
@@ -39,10 +41,14 @@ use crate::Core;
 
 pub struct BlockedIps {
     pub ip_addresses: RwLock<AHashSet<IpAddr>>,
+    pub ip_bans: RwLock<AHashMap<IpAddr, u64>>,
     pub version: AtomicU8,
     ip_networks: Vec<IpAddrMask>,
     has_networks: bool,
     limiter_rate: Option<Rate>,
+    abuse_rate: Option<Rate>,
+    ban_duration: Option<Duration>,
+    ban_max_duration: Duration,
 }
 
 #[derive(Clone)]
@@ -59,37 +65,65 @@ pub const ALLOWED_IP_PREFIX: &str = "server.allowed-ip.";
 
 impl BlockedIps {
     pub fn parse(config: &mut Config) -> Self {
-        let mut ip_addresses = AHashSet::new();
-        let mut ip_networks = Vec::new();
-
-        for ip in config
-            .set_values(BLOCKED_IP_KEY)
-            .map(IpAddrOrMask::parse_value)
-            .collect::<Vec<_>>()
-        {
-            match ip {
-                Ok(IpAddrOrMask::Ip(ip)) => {
-                    ip_addresses.insert(ip);
-                }
-                Ok(IpAddrOrMask::Mask(ip)) => {
-                    ip_networks.push(ip);
-                }
-                Err(err) => {
-                    config.new_parse_error(BLOCKED_IP_KEY, err);
-                }
-            }
-        }
+        let (ip_addresses, ip_bans, ip_networks) = parse_blocked_ips(config);
 
         BlockedIps {
             ip_addresses: RwLock::new(ip_addresses),
+            ip_bans: RwLock::new(ip_bans),
             has_networks: !ip_networks.is_empty(),
             ip_networks,
             limiter_rate: config.property_or_default::<Rate>("authentication.fail2ban", "100/1d"),
+            abuse_rate: config.property::<Rate>("server.auto-ban.abuse.rate"),
+            ban_duration: config.property::<Duration>("server.auto-ban.duration"),
+            ban_max_duration: config
+                .property_or_default("server.auto-ban.max-duration", "30d")
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400)),
             version: 0.into(),
         }
     }
 }
 
+// Blocked IPs with an expiration timestamp as value are temporary bans,
+// expired bans are skipped and removed later by the housekeeper.
+pub(crate) fn parse_blocked_ips(
+    config: &mut Config,
+) -> (AHashSet<IpAddr>, AHashMap<IpAddr, u64>, Vec<IpAddrMask>) {
+    let mut ip_addresses = AHashSet::new();
+    let mut ip_bans = AHashMap::new();
+    let mut ip_networks = Vec::new();
+    let now = now();
+
+    for (ip, expires) in config
+        .iterate_prefix(BLOCKED_IP_KEY)
+        .map(|(ip, expires)| {
+            (
+                IpAddrOrMask::parse_value(ip),
+                expires.parse::<u64>().unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>()
+    {
+        match ip {
+            Ok(IpAddrOrMask::Ip(ip)) if expires == 0 => {
+                ip_addresses.insert(ip);
+            }
+            Ok(IpAddrOrMask::Ip(ip)) => {
+                if expires > now {
+                    ip_bans.insert(ip, expires);
+                }
+            }
+            Ok(IpAddrOrMask::Mask(ip)) => {
+                ip_networks.push(ip);
+            }
+            Err(err) => {
+                config.new_parse_error(BLOCKED_IP_KEY, err);
+            }
+        }
+    }
+
+    (ip_addresses, ip_bans, ip_networks)
+}
+
 impl AllowedIps {
     pub fn parse(config: &mut Config) -> Self {
         let mut ip_addresses = AHashSet::new();
@@ -142,21 +176,27 @@ impl Core {
                         .await?
                         .is_none());
             if !is_allowed {
-                // Add IP to blocked list
-                self.network.blocked_ips.ip_addresses.write().insert(ip);
-
-                // Write blocked IP to config
-                self.storage
-                    .config
-                    .set([ConfigKey {
-                        key: format!("{}.{}", BLOCKED_IP_KEY, ip),
-                        value: String::new(),
-                    }])
-                    .await?;
+                self.block_ip(ip).await?;
+                return Ok(true);
+            }
+        }
 
-                // Increment version
-                self.network.blocked_ips.increment_version();
+        Ok(false)
+    }
 
+    // Counts bad commands and protocol violations, banning the IP address
+    // once the abuse rate is exceeded.
+    pub async fn is_abuse_banned(&self, ip: IpAddr) -> store::Result<bool> {
+        if let Some(rate) = &self.network.blocked_ips.abuse_rate {
+            if !self.is_ip_allowed(&ip)
+                && self
+                    .storage
+                    .lookup
+                    .is_rate_allowed(format!("a:{}", ip).as_bytes(), rate, false)
+                    .await?
+                    .is_some()
+            {
+                self.block_ip(ip).await?;
                 return Ok(true);
             }
         }
@@ -164,12 +204,144 @@ impl Core {
         Ok(false)
     }
 
+    pub async fn block_ip(&self, ip: IpAddr) -> store::Result<()> {
+        let blocked_ips = &self.network.blocked_ips;
+        let value = if let Some(ban_duration) = blocked_ips.ban_duration {
+            // Repeat offenders are banned for exponentially longer periods
+            let max_duration = blocked_ips.ban_max_duration.as_secs();
+            let num_bans = self
+                .storage
+                .lookup
+                .counter_incr(
+                    format!("bn:{}", ip).into_bytes(),
+                    1,
+                    max_duration.into(),
+                    true,
+                )
+                .await?;
+            let duration = ban_duration
+                .as_secs()
+                .saturating_mul(1 << (num_bans - 1).clamp(0, 32))
+                .min(max_duration);
+            let expires = now() + duration;
+
+            tracing::info!(
+                context = "fail2ban",
+                event = "ban",
+                remote_ip = ?ip,
+                duration = duration,
+                "IP address temporarily banned",
+            );
+
+            blocked_ips.ip_bans.write().insert(ip, expires);
+            expires.to_string()
+        } else {
+            blocked_ips.ip_addresses.write().insert(ip);
+            String::new()
+        };
+
+        // Write blocked IP to config
+        self.storage
+            .config
+            .set([ConfigKey {
+                key: format!("{}.{}", BLOCKED_IP_KEY, ip),
+                value,
+            }])
+            .await?;
+
+        // Increment version
+        blocked_ips.increment_version();
+
+        Ok(())
+    }
+
+    pub async fn unblock_ip(&self, ip: IpAddr) -> store::Result<bool> {
+        let blocked_ips = &self.network.blocked_ips;
+        let was_blocked = blocked_ips.ip_addresses.write().remove(&ip)
+            | blocked_ips.ip_bans.write().remove(&ip).is_some();
+        if was_blocked {
+            self.storage
+                .config
+                .clear(format!("{}.{}", BLOCKED_IP_KEY, ip))
+                .await?;
+
+            // Reset failure counters so the IP is not banned again right away
+            for (prefix, rate) in [
+                ("b", &blocked_ips.limiter_rate),
+                ("a", &blocked_ips.abuse_rate),
+            ] {
+                if let Some(rate) = rate {
+                    self.storage
+                        .lookup
+                        .reset_rate(format!("{prefix}:{ip}").as_bytes(), rate)
+                        .await?;
+                }
+            }
+
+            blocked_ips.increment_version();
+        }
+
+        Ok(was_blocked)
+    }
+
+    pub fn list_blocked_ips(&self) -> Vec<(IpAddr, Option<u64>)> {
+        let now = now();
+        let blocked_ips = &self.network.blocked_ips;
+        let mut ips = blocked_ips
+            .ip_addresses
+            .read()
+            .iter()
+            .map(|ip| (*ip, None))
+            .collect::<Vec<_>>();
+        ips.extend(
+            blocked_ips
+                .ip_bans
+                .read()
+                .iter()
+                .filter(|(_, expires)| **expires > now)
+                .map(|(ip, expires)| (*ip, Some(*expires))),
+        );
+        ips
+    }
+
+    pub async fn purge_expired_bans(&self) -> store::Result<()> {
+        let now = now();
+        let expired = {
+            let mut ip_bans = self.network.blocked_ips.ip_bans.write();
+            let expired = ip_bans
+                .iter()
+                .filter(|(_, expires)| **expires <= now)
+                .map(|(ip, _)| *ip)
+                .collect::<Vec<_>>();
+            for ip in &expired {
+                ip_bans.remove(ip);
+            }
+            expired
+        };
+
+        for ip in expired {
+            self.storage
+                .config
+                .clear(format!("{}.{}", BLOCKED_IP_KEY, ip))
+                .await?;
+        }
+
+        Ok(())
+    }
+
     pub fn has_fail2ban(&self) -> bool {
         self.network.blocked_ips.limiter_rate.is_some()
     }
 
     pub fn is_ip_blocked(&self, ip: &IpAddr) -> bool {
         self.network.blocked_ips.ip_addresses.read().contains(ip)
+            || self
+                .network
+                .blocked_ips
+                .ip_bans
+                .read()
+                .get(ip)
+                .map_or(false, |expires| *expires > now())
             || (self.network.blocked_ips.has_networks
                 && self
                     .network
@@ -202,9 +374,13 @@ impl Default for BlockedIps {
     fn default() -> Self {
         Self {
             ip_addresses: RwLock::new(AHashSet::new()),
+            ip_bans: RwLock::new(AHashMap::new()),
             ip_networks: Default::default(),
             has_networks: Default::default(),
             limiter_rate: Default::default(),
+            abuse_rate: Default::default(),
+            ban_duration: Default::default(),
+            ban_max_duration: Duration::from_secs(30 * 86400),
             version: Default::default(),
         }
     }
@@ -228,9 +404,13 @@ impl Clone for BlockedIps {
     fn clone(&self) -> Self {
         Self {
             ip_addresses: RwLock::new(self.ip_addresses.read().clone()),
+            ip_bans: RwLock::new(self.ip_bans.read().clone()),
             ip_networks: self.ip_networks.clone(),
             has_networks: self.has_networks,
             limiter_rate: self.limiter_rate.clone(),
+            abuse_rate: self.abuse_rate.clone(),
+            ban_duration: self.ban_duration,
+            ban_max_duration: self.ban_max_duration,
             version: self
                 .version
                 .load(std::sync::atomic::Ordering::Relaxed)
@@ -243,8 +423,11 @@ impl Debug for BlockedIps {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("BlockedIps")
             .field("ip_addresses", &self.ip_addresses)
+            .field("ip_bans", &self.ip_bans)
             .field("ip_networks", &self.ip_networks)
             .field("limiter_rate", &self.limiter_rate)
+            .field("abuse_rate", &self.abuse_rate)
+            .field("ban_duration", &self.ban_duration)
             .finish()
     }
 }
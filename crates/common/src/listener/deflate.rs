@@ -0,0 +1,275 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    borrow::Cow,
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::SessionStream;
+
+const BUF_SIZE: usize = 8192;
+
+// Raw DEFLATE stream as used by IMAP COMPRESS (RFC 4978), each write is
+// followed by a sync flush so responses are never held back.
+pub struct DeflateStream<T> {
+    inner: T,
+    compress: Compress,
+    decompress: Decompress,
+    read_buf: Box<[u8]>,
+    read_pos: usize,
+    read_len: usize,
+    pending_output: bool,
+    write_buf: Vec<u8>,
+    write_pos: usize,
+}
+
+impl<T> DeflateStream<T> {
+    pub fn new(inner: T) -> Self {
+        DeflateStream {
+            inner,
+            compress: Compress::new(Compression::default(), false),
+            decompress: Decompress::new(false),
+            read_buf: vec![0; BUF_SIZE].into_boxed_slice(),
+            read_pos: 0,
+            read_len: 0,
+            pending_output: false,
+            write_buf: Vec::with_capacity(BUF_SIZE),
+            write_pos: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: AsyncWrite + Unpin> DeflateStream<T> {
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.write_pos < self.write_buf.len() {
+            let bytes_written = ready!(
+                Pin::new(&mut self.inner).poll_write(cx, &self.write_buf[self.write_pos..])
+            )?;
+            if bytes_written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_pos += bytes_written;
+        }
+        self.write_buf.clear();
+        self.write_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for DeflateStream<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            // Inflate any buffered input, or drain output still held by the inflater
+            // when the previous call filled the whole output buffer
+            if this.read_pos < this.read_len || this.pending_output {
+                let total_in = this.decompress.total_in();
+                let total_out = this.decompress.total_out();
+                let output = buf.initialize_unfilled();
+                let output_len = output.len();
+                this.decompress
+                    .decompress(
+                        &this.read_buf[this.read_pos..this.read_len],
+                        output,
+                        FlushDecompress::Sync,
+                    )
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                this.read_pos += (this.decompress.total_in() - total_in) as usize;
+                let bytes_out = (this.decompress.total_out() - total_out) as usize;
+                this.pending_output = bytes_out == output_len;
+                if bytes_out > 0 {
+                    buf.advance(bytes_out);
+                    return Poll::Ready(Ok(()));
+                }
+            }
+
+            // Read more compressed data
+            if this.read_pos > 0 {
+                this.read_buf.copy_within(this.read_pos..this.read_len, 0);
+                this.read_len -= this.read_pos;
+                this.read_pos = 0;
+            }
+            if this.read_len == this.read_buf.len() {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Failed to inflate compressed stream.",
+                )));
+            }
+            let mut read_buf = ReadBuf::new(&mut this.read_buf[this.read_len..]);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read_buf))?;
+            let bytes_read = read_buf.filled().len();
+            if bytes_read == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            this.read_len += bytes_read;
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for DeflateStream<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        ready!(this.poll_write_pending(cx))?;
+
+        let mut bytes_in = 0;
+        loop {
+            this.write_buf.reserve(BUF_SIZE);
+            let total_in = this.compress.total_in();
+            this.compress
+                .compress_vec(&data[bytes_in..], &mut this.write_buf, FlushCompress::Sync)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            bytes_in += (this.compress.total_in() - total_in) as usize;
+
+            // The flush is complete once the output no longer fills the buffer
+            if bytes_in == data.len() && this.write_buf.len() < this.write_buf.capacity() {
+                break;
+            }
+        }
+
+        // Start sending right away, anything left is sent on the next write or flush
+        if let Poll::Ready(Err(err)) = this.poll_write_pending(cx) {
+            return Poll::Ready(Err(err));
+        }
+
+        Poll::Ready(Ok(data.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+impl<T: SessionStream> SessionStream for DeflateStream<T> {
+    fn is_tls(&self) -> bool {
+        self.inner.is_tls()
+    }
+
+    fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>) {
+        self.inner.tls_version_and_cipher()
+    }
+
+    fn tls_server_name(&self) -> Option<&str> {
+        self.inner.tls_server_name()
+    }
+
+    fn tls_exporter(&self) -> Option<Vec<u8>> {
+        self.inner.tls_exporter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::DeflateStream;
+
+    #[tokio::test]
+    async fn deflate_stream() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = DeflateStream::new(client);
+        let mut server = DeflateStream::new(server);
+
+        let lines = (0..100)
+            .map(|num| format!("* {num} FETCH (FLAGS (\\Seen) UID {num})\r\n"))
+            .collect::<Vec<_>>();
+        let expected = lines.concat();
+
+        let writer = tokio::spawn(async move {
+            for line in lines {
+                server.write_all(line.as_bytes()).await.unwrap();
+                server.flush().await.unwrap();
+            }
+            server.shutdown().await.unwrap();
+        });
+
+        let mut received = vec![0u8; expected.len()];
+        client.read_exact(&mut received).await.unwrap();
+        assert_eq!(String::from_utf8(received).unwrap(), expected);
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn deflate_stream_small_reads() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = DeflateStream::new(client);
+        let mut server = DeflateStream::new(server);
+
+        // Highly compressible data inflates to far more than each read can hold
+        let expected = vec![b'A'; 64 * 1024];
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel::<()>();
+        let payload = expected.clone();
+        let writer = tokio::spawn(async move {
+            server.write_all(&payload).await.unwrap();
+            server.flush().await.unwrap();
+
+            // Keep the connection open so a stalled reader cannot rely on EOF
+            let _ = done_rx.await;
+            server.shutdown().await.unwrap();
+        });
+
+        let mut received = Vec::with_capacity(expected.len());
+        let mut chunk = [0u8; 16];
+        while received.len() < expected.len() {
+            let bytes_read =
+                tokio::time::timeout(std::time::Duration::from_secs(5), client.read(&mut chunk))
+                    .await
+                    .expect("Inflated output was not drained before reading more input")
+                    .unwrap();
+            assert!(bytes_read > 0);
+            received.extend_from_slice(&chunk[..bytes_read]);
+        }
+        assert_eq!(received, expected);
+        done_tx.send(()).unwrap();
+        writer.await.unwrap();
+    }
+}
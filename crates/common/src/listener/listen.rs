@@ -31,6 +31,7 @@ use arc_swap::ArcSwap;
 use proxy_header::io::ProxiedStream;
 use rustls::crypto::ring::cipher_suite::TLS13_AES_128_GCM_SHA256;
 use tokio::{
+    io::AsyncWriteExt,
     net::{TcpListener, TcpStream},
     sync::watch,
 };
@@ -44,8 +45,8 @@ use crate::{
 };
 
 use super::{
-    limiter::ConcurrencyLimiter, ServerInstance, SessionData, SessionManager, SessionStream,
-    TcpAcceptor,
+    limiter::ConcurrencyLimiter, qos::QosResult, ServerInstance, SessionData, SessionManager,
+    SessionStream, TcpAcceptor,
 };
 
 impl Server {
@@ -62,6 +63,7 @@ impl Server {
             protocol: self.protocol,
             proxy_networks: self.proxy_networks,
             limiter: ConcurrencyLimiter::new(self.max_connections),
+            qos: self.qos,
             acceptor,
             shutdown_rx,
         });
@@ -127,11 +129,29 @@ impl Server {
                                         tokio::spawn(async move {
                                             match ProxiedStream::create_from_tokio(stream, Default::default()).await {
                                                 Ok(stream) =>{
-                                                    let remote_addr = stream.proxy_header()
+                                                    let header = stream.proxy_header();
+                                                    let remote_addr = header
                                                                             .proxied_address()
                                                                             .map(|addr| addr.source)
                                                                             .unwrap_or(remote_addr);
-                                                    if let Some(session) = instance.build_session(stream, local_addr, remote_addr, &core) {
+                                                    tracing::trace!(context = "proxy",
+                                                                    event = "accept",
+                                                                    instance = instance.id,
+                                                                    protocol = ?instance.protocol,
+                                                                    remote.ip = remote_addr.ip().to_string(),
+                                                                    tls = stream.is_tls(),
+                                                                    tls.sni = header.authority(),
+                                                                    tls.alpn = header.alpn().map(String::from_utf8_lossy).as_deref(),
+                                                                    unique_id = header.unique_id().map(|id| id.iter().fold(String::with_capacity(id.len() * 2), |mut s, b| {
+                                                                        use std::fmt::Write;
+                                                                        let _ = write!(s, "{b:02x}");
+                                                                        s
+                                                                    })),
+                                                                    "Accepted proxied TCP connection");
+
+                                                    // Connections already terminated by the proxy over TLS skip the implicit TLS handshake
+                                                    let is_tls = is_tls && !stream.is_tls();
+                                                    if let Some(session) = instance.build_session(stream, local_addr, remote_addr, is_tls, &core) {
                                                         // Spawn session
                                                         manager.spawn(session, is_tls, enable_acme);
                                                     }
@@ -146,7 +166,7 @@ impl Server {
                                                 }
                                             }
                                         });
-                                    } else if let Some(session) = instance.build_session(stream, local_addr, remote_addr, &core) {
+                                    } else if let Some(session) = instance.build_session(stream, local_addr, remote_addr, is_tls, &core) {
                                         // Set socket options
                                         opts.apply(&session.stream);
 
@@ -176,6 +196,107 @@ impl Server {
                 }
             });
         }
+
+        // Spawn UNIX listeners
+        #[cfg(unix)]
+        for listener in self.unix_listeners {
+            tracing::info!(
+                id = instance.id,
+                protocol = ?instance.protocol,
+                bind.path = %listener.path.display(),
+                tls = is_tls,
+                "Starting UNIX listener"
+            );
+            let path = listener.path.clone();
+            let is_proxied = listener.proxy;
+
+            let listener = match listener.listen() {
+                Ok(listener) => listener,
+                Err(err) => {
+                    tracing::error!(
+                        event = "error",
+                        instance = instance.id,
+                        protocol = ?instance.protocol,
+                        reason = %err,
+                        "Failed to bind UNIX listener"
+                    );
+                    continue;
+                }
+            };
+
+            // UNIX peers have no address, sessions see them as loopback unless proxied
+            let local_addr = SocketAddr::from((std::net::Ipv4Addr::LOCALHOST, 0));
+
+            // Spawn listener
+            let mut shutdown_rx = instance.shutdown_rx.clone();
+            let manager = manager.clone();
+            let instance = instance.clone();
+            let core = core.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        stream = listener.accept() => {
+                            match stream {
+                                Ok((stream, _)) => {
+                                    let core = core.as_ref().load();
+                                    let enable_acme = (is_https && core.has_acme_tls_providers()).then_some(core.clone());
+
+                                    if is_proxied {
+                                        let instance = instance.clone();
+                                        let manager = manager.clone();
+
+                                        tokio::spawn(async move {
+                                            match ProxiedStream::create_from_tokio(stream, Default::default()).await {
+                                                Ok(stream) => {
+                                                    let remote_addr = stream
+                                                                        .proxy_header()
+                                                                        .proxied_address()
+                                                                        .map(|addr| addr.source)
+                                                                        .unwrap_or(local_addr);
+                                                    let is_tls = is_tls && !stream.is_tls();
+                                                    if let Some(session) = instance.build_session(stream, local_addr, remote_addr, is_tls, &core) {
+                                                        // Spawn session
+                                                        manager.spawn(session, is_tls, enable_acme);
+                                                    }
+                                                }
+                                                Err(err) => {
+                                                    tracing::trace!(context = "proxy",
+                                                                    event = "error",
+                                                                    instance = instance.id,
+                                                                    protocol = ?instance.protocol,
+                                                                    reason = %err,
+                                                                    "Failed to accept proxied UNIX connection");
+                                                }
+                                            }
+                                        });
+                                    } else if let Some(session) = instance.build_session(stream, local_addr, local_addr, is_tls, &core) {
+                                        // Spawn session
+                                        manager.spawn(session, is_tls, enable_acme);
+                                    }
+                                }
+                                Err(err) => {
+                                    tracing::trace!(context = "io",
+                                                    event = "error",
+                                                    instance = instance.id,
+                                                    protocol = ?instance.protocol,
+                                                    "Failed to accept UNIX connection: {}", err);
+                                }
+                            }
+                        },
+                        _ = shutdown_rx.changed() => {
+                            tracing::debug!(
+                                event = "shutdown",
+                                instance = instance.id,
+                                protocol = ?instance.protocol,
+                                "UNIX listener shutting down.");
+                            let _ = std::fs::remove_file(&path);
+                            manager.shutdown().await;
+                            break;
+                        }
+                    };
+                }
+            });
+        }
     }
 }
 
@@ -185,6 +306,7 @@ trait BuildSession {
         stream: T,
         local_addr: SocketAddr,
         remote_addr: SocketAddr,
+        is_tls: bool,
         core: &Core,
     ) -> Option<SessionData<T>>;
 }
@@ -195,6 +317,7 @@ impl BuildSession for Arc<ServerInstance> {
         stream: T,
         local_addr: SocketAddr,
         remote_addr: SocketAddr,
+        is_tls: bool,
         core: &Core,
     ) -> Option<SessionData<T>> {
         // Convert mapped IPv6 addresses to IPv4
@@ -220,16 +343,52 @@ impl BuildSession for Arc<ServerInstance> {
             );
             None
         } else if let Some(in_flight) = self.limiter.is_allowed() {
-            // Enforce concurrency
+            // Enforce per-class limits and shed load when the worker pool is saturated
+            let qos = match self.qos.is_allowed(&remote_ip) {
+                QosResult::Allowed(qos) => qos,
+                result => {
+                    tracing::info!(
+                        context = "throttle",
+                        event = "busy",
+                        instance = self.id,
+                        protocol = ?self.protocol,
+                        remote.ip = remote_ip.to_string(),
+                        remote.port = remote_port,
+                        class = match &result {
+                            QosResult::ClassFull(class) => class.as_str(),
+                            _ => "workers",
+                        },
+                        "Shedding connection, server is busy."
+                    );
+
+                    // Implicit TLS connections are closed without a handshake
+                    if !is_tls {
+                        let mut stream = stream;
+                        let response = self.protocol.busy_response();
+                        tokio::spawn(async move {
+                            let _ = tokio::time::timeout(
+                                Duration::from_secs(1),
+                                stream.write_all(response),
+                            )
+                            .await;
+                        });
+                    }
+                    return None;
+                }
+            };
+
             SessionData {
                 stream,
                 in_flight,
+                qos,
                 span: tracing::info_span!(
                     "session",
                     instance = self.id,
                     protocol = ?self.protocol,
                     remote.ip = remote_ip.to_string(),
                     remote.port = remote_port,
+                    authcid = tracing::field::Empty,
+                    authzid = tracing::field::Empty,
                 ),
                 local_ip: local_addr.ip(),
                 local_port: local_addr.port(),
@@ -296,9 +455,9 @@ impl SocketOpts {
 }
 
 impl Servers {
-    pub fn bind_and_drop_priv(&self, config: &mut Config) {
+    pub fn bind_and_drop_priv(&mut self, config: &mut Config) {
         // Bind as root
-        for server in &self.servers {
+        for server in &mut self.servers {
             for listener in &server.listeners {
                 if let Err(err) = listener.socket.bind(listener.addr) {
                     config.new_build_error(
@@ -307,6 +466,12 @@ impl Servers {
                     );
                 }
             }
+            #[cfg(unix)]
+            for listener in &mut server.unix_listeners {
+                if let Err(err) = listener.bind() {
+                    config.new_build_error(format!("server.listener.{}", server.id), err);
+                }
+            }
         }
 
         // Drop privileges
@@ -30,7 +30,10 @@ use tokio::{
     sync::watch,
 };
 use tokio_rustls::{Accept, TlsAcceptor};
-use utils::config::ipmask::IpAddrMask;
+use utils::{
+    config::ipmask::IpAddrMask,
+    metrics::{CONNECTIONS_ACTIVE, CONNECTIONS_TOTAL},
+};
 
 use crate::{
     config::server::ServerProtocol,
@@ -38,12 +41,17 @@ use crate::{
     Core,
 };
 
-use self::limiter::{ConcurrencyLimiter, InFlight};
+use self::{
+    limiter::{ConcurrencyLimiter, InFlight},
+    qos::{QosLimiter, QosPermit},
+};
 
 pub mod acme;
 pub mod blocked;
+pub mod deflate;
 pub mod limiter;
 pub mod listen;
+pub mod qos;
 pub mod stream;
 pub mod tls;
 
@@ -52,6 +60,7 @@ pub struct ServerInstance {
     pub protocol: ServerProtocol,
     pub acceptor: TcpAcceptor,
     pub limiter: ConcurrencyLimiter,
+    pub qos: QosLimiter,
     pub proxy_networks: Vec<IpAddrMask>,
     pub shutdown_rx: watch::Receiver<bool>,
 }
@@ -86,12 +95,21 @@ pub struct SessionData<T: SessionStream> {
     pub protocol: ServerProtocol,
     pub span: tracing::Span,
     pub in_flight: InFlight,
+    pub qos: QosPermit,
     pub instance: Arc<ServerInstance>,
 }
 
 pub trait SessionStream: AsyncRead + AsyncWrite + Unpin + 'static + Sync + Send {
     fn is_tls(&self) -> bool;
     fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>);
+    fn tls_server_name(&self) -> Option<&str> {
+        None
+    }
+
+    // Channel binding data as defined in RFC 9266, only available for TLS 1.3
+    fn tls_exporter(&self) -> Option<Vec<u8>> {
+        None
+    }
 }
 
 pub trait SessionManager: Sync + Send + 'static + Clone {
@@ -104,6 +122,10 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
         let manager = self.clone();
 
         tokio::spawn(async move {
+            let protocol = session.protocol.as_str();
+            CONNECTIONS_TOTAL.increment(protocol);
+            let _active = CONNECTIONS_ACTIVE.guard(protocol);
+
             if is_tls {
                 match session
                     .instance
@@ -122,6 +144,7 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
                                 protocol: session.protocol,
                                 span: session.span,
                                 in_flight: session.in_flight,
+                                qos: session.qos,
                                 instance: session.instance,
                             };
                             manager.handle(session).await;
@@ -168,6 +191,7 @@ impl<T: SessionStream> ResolveVariable for SessionData<T> {
             V_LISTENER => self.instance.id.as_str().into(),
             V_PROTOCOL => self.protocol.as_str().into(),
             V_TLS => self.stream.is_tls().into(),
+            V_TLS_SNI => self.stream.tls_server_name().unwrap_or_default().into(),
             _ => crate::expr::Variable::default(),
         }
     }
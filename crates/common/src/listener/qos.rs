@@ -0,0 +1,227 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of the Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::net::IpAddr;
+
+use utils::config::ipmask::IpAddrMask;
+
+use crate::config::server::ServerProtocol;
+
+use super::limiter::{ConcurrencyLimiter, InFlight};
+
+#[derive(Debug, Clone, Default)]
+pub struct QosLimiter {
+    pub trusted_networks: Vec<IpAddrMask>,
+    pub trusted: Option<ConcurrencyLimiter>,
+    pub authenticated: Option<ConcurrencyLimiter>,
+    pub unknown: Option<ConcurrencyLimiter>,
+    pub workers: Option<ConcurrencyLimiter>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QosClass {
+    Trusted,
+    Authenticated,
+    #[default]
+    Unknown,
+}
+
+#[derive(Default)]
+pub struct QosPermit {
+    class: QosClass,
+    class_slot: Option<InFlight>,
+    worker_slot: Option<InFlight>,
+    authenticated: Option<ConcurrencyLimiter>,
+}
+
+pub enum QosResult {
+    Allowed(QosPermit),
+    ClassFull(QosClass),
+    WorkersBusy,
+}
+
+impl QosLimiter {
+    pub fn classify(&self, remote_ip: &IpAddr) -> QosClass {
+        if self
+            .trusted_networks
+            .iter()
+            .any(|network| network.matches(remote_ip))
+        {
+            QosClass::Trusted
+        } else {
+            QosClass::Unknown
+        }
+    }
+
+    pub fn is_allowed(&self, remote_ip: &IpAddr) -> QosResult {
+        let class = self.classify(remote_ip);
+
+        // Reserve a slot in the connection class
+        let class_slot = match self.limiter(class) {
+            Some(limiter) => match limiter.is_allowed() {
+                Some(in_flight) => Some(in_flight),
+                None => return QosResult::ClassFull(class),
+            },
+            None => None,
+        };
+
+        // Reserve a worker, trusted networks are never shed
+        let worker_slot = match &self.workers {
+            Some(workers) => match workers.is_allowed() {
+                Some(in_flight) => Some(in_flight),
+                None if class == QosClass::Trusted => None,
+                None => return QosResult::WorkersBusy,
+            },
+            None => None,
+        };
+
+        QosResult::Allowed(QosPermit {
+            class,
+            class_slot,
+            worker_slot,
+            authenticated: self.authenticated.clone(),
+        })
+    }
+
+    fn limiter(&self, class: QosClass) -> Option<&ConcurrencyLimiter> {
+        match class {
+            QosClass::Trusted => self.trusted.as_ref(),
+            QosClass::Authenticated => self.authenticated.as_ref(),
+            QosClass::Unknown => self.unknown.as_ref(),
+        }
+    }
+}
+
+impl QosPermit {
+    pub fn class(&self) -> QosClass {
+        self.class
+    }
+
+    pub fn has_worker(&self) -> bool {
+        self.worker_slot.is_some()
+    }
+
+    // Moves an unknown connection to the authenticated class once the
+    // client has logged in. If the authenticated class is full the
+    // connection keeps its current slot rather than being dropped.
+    pub fn promote(&mut self) {
+        if self.class == QosClass::Unknown {
+            match &self.authenticated {
+                Some(limiter) => {
+                    if let Some(in_flight) = limiter.is_allowed() {
+                        self.class_slot = Some(in_flight);
+                        self.class = QosClass::Authenticated;
+                    }
+                }
+                None => {
+                    self.class_slot = None;
+                    self.class = QosClass::Authenticated;
+                }
+            }
+        }
+    }
+}
+
+impl QosClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QosClass::Trusted => "trusted",
+            QosClass::Authenticated => "authenticated",
+            QosClass::Unknown => "unknown",
+        }
+    }
+}
+
+impl ServerProtocol {
+    pub fn busy_response(&self) -> &'static [u8] {
+        match self {
+            ServerProtocol::Smtp | ServerProtocol::Lmtp => {
+                b"421 4.3.2 Service temporarily unavailable, try again later.\r\n"
+            }
+            ServerProtocol::Imap => b"* BYE [UNAVAILABLE] Server busy, try again later.\r\n",
+            ServerProtocol::Pop3 => b"-ERR [SYS/TEMP] Server busy, try again later.\r\n",
+            ServerProtocol::ManageSieve => b"BYE (TRYLATER) \"Server busy, try again later.\"\r\n",
+            ServerProtocol::Http => concat!(
+                "HTTP/1.1 503 Service Unavailable\r\n",
+                "Retry-After: 5\r\n",
+                "Content-Length: 0\r\n",
+                "Connection: close\r\n\r\n"
+            )
+            .as_bytes(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use utils::config::{ipmask::IpAddrMask, utils::ParseValue};
+
+    use crate::listener::limiter::ConcurrencyLimiter;
+
+    use super::{QosClass, QosLimiter, QosResult};
+
+    #[test]
+    fn qos_classes() {
+        let qos = QosLimiter {
+            trusted_networks: vec![IpAddrMask::parse_value("10.0.0.0/8").unwrap()],
+            trusted: None,
+            authenticated: Some(ConcurrencyLimiter::new(1)),
+            unknown: Some(ConcurrencyLimiter::new(2)),
+            workers: Some(ConcurrencyLimiter::new(2)),
+        };
+        let trusted: IpAddr = "10.0.0.1".parse().unwrap();
+        let unknown: IpAddr = "192.168.1.1".parse().unwrap();
+
+        // Unknown class is capped
+        let mut first = match qos.is_allowed(&unknown) {
+            QosResult::Allowed(permit) => permit,
+            _ => panic!("Expected permit"),
+        };
+        assert_eq!(first.class(), QosClass::Unknown);
+        let _second = match qos.is_allowed(&unknown) {
+            QosResult::Allowed(permit) => permit,
+            _ => panic!("Expected permit"),
+        };
+
+        // Worker pool is saturated, unknown clients are shed but trusted ones are not
+        assert!(matches!(
+            qos.is_allowed(&unknown),
+            QosResult::ClassFull(QosClass::Unknown)
+        ));
+        let trusted_permit = match qos.is_allowed(&trusted) {
+            QosResult::Allowed(permit) => permit,
+            _ => panic!("Expected permit"),
+        };
+        assert_eq!(trusted_permit.class(), QosClass::Trusted);
+        assert!(!trusted_permit.has_worker());
+
+        // Promotion frees the unknown slot
+        first.promote();
+        assert_eq!(first.class(), QosClass::Authenticated);
+        assert!(matches!(qos.is_allowed(&unknown), QosResult::WorkersBusy));
+        drop(first);
+        assert!(matches!(qos.is_allowed(&unknown), QosResult::Allowed(_)));
+    }
+}
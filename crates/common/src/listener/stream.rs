@@ -40,6 +40,10 @@ impl SessionStream for TcpStream {
     fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>) {
         (Cow::Borrowed(""), Cow::Borrowed(""))
     }
+
+    fn tls_server_name(&self) -> Option<&str> {
+        None
+    }
 }
 
 impl<T: SessionStream> SessionStream for TlsStream<T> {
@@ -79,9 +83,38 @@ impl<T: SessionStream> SessionStream for TlsStream<T> {
             .into(),
         )
     }
+
+    fn tls_server_name(&self) -> Option<&str> {
+        self.get_ref().1.server_name()
+    }
+
+    fn tls_exporter(&self) -> Option<Vec<u8>> {
+        let (_, conn) = self.get_ref();
+        if conn.protocol_version() == Some(rustls::ProtocolVersion::TLSv1_3) {
+            conn.export_keying_material(vec![0u8; 32], b"EXPORTER-Channel-Binding", None)
+                .ok()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+impl SessionStream for tokio::net::UnixStream {
+    fn is_tls(&self) -> bool {
+        false
+    }
+
+    fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>) {
+        (Cow::Borrowed(""), Cow::Borrowed(""))
+    }
+
+    fn tls_server_name(&self) -> Option<&str> {
+        None
+    }
 }
 
-impl SessionStream for ProxiedStream<TcpStream> {
+impl<T: SessionStream> SessionStream for ProxiedStream<T> {
     fn is_tls(&self) -> bool {
         self.proxy_header()
             .ssl()
@@ -99,6 +132,11 @@ impl SessionStream for ProxiedStream<TcpStream> {
             })
             .unwrap_or((Cow::Borrowed("unknown"), Cow::Borrowed("unknown")))
     }
+
+    fn tls_server_name(&self) -> Option<&str> {
+        // The authority TLV carries the SNI sent by the client to the proxy
+        self.proxy_header().authority()
+    }
 }
 
 #[derive(Default)]
@@ -157,4 +195,8 @@ impl SessionStream for NullIo {
             std::borrow::Cow::Borrowed(""),
         )
     }
+
+    fn tls_server_name(&self) -> Option<&str> {
+        None
+    }
 }
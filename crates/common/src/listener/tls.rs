@@ -24,7 +24,9 @@
 use std::{
     cmp::Ordering,
     fmt::{self, Formatter},
+    path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 
 use ahash::AHashMap;
@@ -54,10 +56,17 @@ pub static TLS12_VERSION: &[&SupportedProtocolVersion] = &[&TLS12];
 #[derive(Default)]
 pub struct TlsManager {
     pub certificates: ArcSwap<AHashMap<String, Arc<CertifiedKey>>>,
+    pub certificate_dir: Option<CertificateDirectory>,
     pub acme_providers: AHashMap<String, AcmeProvider>,
     pub self_signed_cert: Option<Arc<CertifiedKey>>,
 }
 
+#[derive(Debug, Clone)]
+pub struct CertificateDirectory {
+    pub path: PathBuf,
+    pub poll_interval: Duration,
+}
+
 #[derive(Clone)]
 pub struct CertificateResolver {
     pub core: SharedCore,
@@ -242,6 +251,7 @@ impl Clone for TlsManager {
     fn clone(&self) -> Self {
         Self {
             certificates: ArcSwap::from_pointee(self.certificates.load().as_ref().clone()),
+            certificate_dir: self.certificate_dir.clone(),
             acme_providers: self.acme_providers.clone(),
             self_signed_cert: self.self_signed_cert.clone(),
         }
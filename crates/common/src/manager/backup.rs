@@ -26,12 +26,17 @@ use std::{
     io::{BufWriter, Write},
     ops::Range,
     path::{Path, PathBuf},
-    sync::mpsc::{self, SyncSender},
+    sync::{
+        mpsc::{self, SyncSender},
+        Arc,
+    },
 };
 
 use ahash::{AHashMap, AHashSet};
 use jmap_proto::types::{collection::Collection, property::Property};
 use store::{
+    query::log::{Change, Changes},
+    roaring::RoaringBitmap,
     write::{
         key::DeserializeBigEndian, AnyKey, BitmapClass, BitmapHash, BlobOp, DirectoryClass,
         LookupClass, QueueClass, QueueEvent, TagValue, ValueClass,
@@ -48,7 +53,7 @@ use utils::{
 use crate::Core;
 
 pub(super) const MAGIC_MARKER: u8 = 123;
-pub(super) const FILE_VERSION: u8 = 2;
+pub(super) const FILE_VERSION: u8 = 3;
 
 #[derive(Debug)]
 pub(super) enum Op {
@@ -73,13 +78,25 @@ pub(super) enum Family {
     Index = 9,
     Bitmap = 10,
     Log = 11,
+    Deleted = 12,
     None = 255,
 }
 
 type TaskHandle = (tokio::task::JoinHandle<()>, std::thread::JoinHandle<()>);
 
+// Restricts an incremental backup to the documents changed after a change id
+#[derive(Clone, Default)]
+struct DocumentFilter(Option<Arc<ChangedDocuments>>);
+
+#[derive(Default)]
+struct ChangedDocuments {
+    since: u64,
+    updated: AHashMap<(u32, u8), RoaringBitmap>,
+    deleted: AHashMap<(u32, u8), RoaringBitmap>,
+}
+
 impl Core {
-    pub async fn backup(&self, dest: PathBuf) {
+    pub async fn backup(&self, dest: PathBuf, since: Option<u64>) {
         if !dest.exists() {
             std::fs::create_dir_all(&dest).failed("Failed to create backup directory");
         } else if !dest.is_dir() {
@@ -87,21 +104,34 @@ impl Core {
             std::process::exit(1);
         }
 
-        let mut sync_handles = Vec::new();
+        // Obtain the checkpoint before exporting any data, changes written
+        // during the export will be included in the next incremental backup
+        let last_change_id = self.last_change_id().await;
+        let filter = if let Some(since) = since {
+            DocumentFilter(Some(Arc::new(self.changed_documents(since).await)))
+        } else {
+            DocumentFilter::default()
+        };
 
-        for (async_handle, sync_handle) in [
-            self.backup_properties(&dest),
-            self.backup_fts_index(&dest),
-            self.backup_acl(&dest),
-            self.backup_blob(&dest),
+        let mut sync_handles = Vec::new();
+        let mut tasks = vec![
+            self.backup_properties(&dest, &filter),
+            self.backup_fts_index(&dest, &filter),
+            self.backup_acl(&dest, &filter),
+            self.backup_blob(&dest, &filter),
             self.backup_config(&dest),
             self.backup_lookup(&dest),
             self.backup_directory(&dest),
             self.backup_queue(&dest),
-            self.backup_index(&dest),
-            self.backup_bitmaps(&dest),
-            self.backup_logs(&dest),
-        ] {
+            self.backup_index(&dest, &filter),
+            self.backup_bitmaps(&dest, &filter),
+            self.backup_logs(&dest, &filter),
+        ];
+        if let Some(changes) = &filter.0 {
+            tasks.push(self.backup_deleted(&dest, changes.clone()));
+        }
+
+        for (async_handle, sync_handle) in tasks {
             async_handle.await.failed("Task failed");
             sync_handles.push(sync_handle);
         }
@@ -109,10 +139,125 @@ impl Core {
         for handle in sync_handles {
             handle.join().expect("Failed to join thread");
         }
+
+        if let Some(last_change_id) = last_change_id {
+            println!(
+                "Export complete, use '--since {last_change_id}' for the next incremental backup."
+            );
+        }
     }
 
-    fn backup_properties(&self, dest: &Path) -> TaskHandle {
+    async fn last_change_id(&self) -> Option<u64> {
+        let mut last_change_id = None;
+
+        self.storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    LogKey {
+                        account_id: 0,
+                        collection: 0,
+                        change_id: 0,
+                    },
+                    LogKey {
+                        account_id: u32::MAX,
+                        collection: u8::MAX,
+                        change_id: u64::MAX,
+                    },
+                )
+                .no_values(),
+                |key, _| {
+                    let change_id = key.deserialize_be_u64(key.len() - U64_LEN)?;
+                    if last_change_id.map_or(true, |last| change_id > last) {
+                        last_change_id = Some(change_id);
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .failed("Failed to iterate over data store");
+
+        last_change_id
+    }
+
+    async fn changed_documents(&self, since: u64) -> ChangedDocuments {
+        let mut changelog: AHashMap<(u32, u8), Changes> = AHashMap::new();
+
+        self.storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    LogKey {
+                        account_id: 0,
+                        collection: 0,
+                        change_id: 0,
+                    },
+                    LogKey {
+                        account_id: u32::MAX,
+                        collection: u8::MAX,
+                        change_id: u64::MAX,
+                    },
+                ),
+                |key, value| {
+                    let account_id = key.deserialize_be_u32(0)?;
+                    let collection = key.deserialize_u8(U32_LEN)?;
+                    let change_id = key.deserialize_be_u64(key.len() - U64_LEN)?;
+
+                    if change_id > since {
+                        changelog
+                            .entry((account_id, collection))
+                            .or_default()
+                            .deserialize(value)
+                            .ok_or_else(|| {
+                                store::Error::InternalError(format!(
+                                    "Failed to deserialize changelog for [{account_id}/{collection}/{change_id}]"
+                                ))
+                            })?;
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .failed("Failed to iterate over data store");
+
+        let mut changes = ChangedDocuments {
+            since,
+            ..Default::default()
+        };
+        for (key, changelog) in changelog {
+            let mut updated = RoaringBitmap::new();
+            let mut deleted = RoaringBitmap::new();
+
+            for change in changelog.changes {
+                match change {
+                    Change::Insert(id) | Change::Update(id) | Change::ChildUpdate(id) => {
+                        updated.insert((id & u32::MAX as u64) as u32);
+                    }
+                    Change::Delete(id) => {
+                        deleted.insert((id & u32::MAX as u64) as u32);
+                    }
+                }
+            }
+
+            // Document ids that were reused after a deletion are exported as updates
+            deleted -= &updated;
+
+            if !updated.is_empty() {
+                changes.updated.insert(key, updated);
+            }
+            if !deleted.is_empty() {
+                changes.deleted.insert(key, deleted);
+            }
+        }
+
+        changes
+    }
+
+    fn backup_properties(&self, dest: &Path, filter: &DocumentFilter) -> TaskHandle {
         let store = self.storage.data.clone();
+        let filter = filter.clone();
         let (handle, writer) = spawn_writer(dest.join("property"));
         (
             tokio::spawn(async move {
@@ -145,7 +290,9 @@ impl Core {
                             let field = key.deserialize_u8(U32_LEN + 1)?;
                             let document_id = key.deserialize_be_u32(U32_LEN + 2)?;
 
-                            keys.insert((account_id, collection, document_id, field));
+                            if filter.matches(account_id, collection, document_id) {
+                                keys.insert((account_id, collection, document_id, field));
+                            }
 
                             Ok(true)
                         },
@@ -223,8 +370,9 @@ impl Core {
         )
     }
 
-    fn backup_fts_index(&self, dest: &Path) -> TaskHandle {
+    fn backup_fts_index(&self, dest: &Path, filter: &DocumentFilter) -> TaskHandle {
         let store = self.storage.data.clone();
+        let filter = filter.clone();
         let (handle, writer) = spawn_writer(dest.join("fts_index"));
         (
             tokio::spawn(async move {
@@ -262,6 +410,10 @@ impl Core {
                             let collection = key.deserialize_u8(key.len() - U32_LEN - 1)?;
                             let document_id = key.deserialize_be_u32(key.len() - U32_LEN)?;
 
+                            if !filter.matches(account_id, collection, document_id) {
+                                return Ok(true);
+                            }
+
                             if account_id != last_account_id {
                                 writer
                                     .send(Op::AccountId(account_id))
@@ -297,8 +449,9 @@ impl Core {
         )
     }
 
-    fn backup_acl(&self, dest: &Path) -> TaskHandle {
+    fn backup_acl(&self, dest: &Path, filter: &DocumentFilter) -> TaskHandle {
         let store = self.storage.data.clone();
+        let filter = filter.clone();
         let (handle, writer) = spawn_writer(dest.join("acl"));
         (
             tokio::spawn(async move {
@@ -332,6 +485,10 @@ impl Core {
                             let collection = key.deserialize_u8(U32_LEN * 2)?;
                             let document_id = key.deserialize_be_u32((U32_LEN * 2) + 1)?;
 
+                            if !filter.matches(account_id, collection, document_id) {
+                                return Ok(true);
+                            }
+
                             if account_id != last_account_id {
                                 writer
                                     .send(Op::AccountId(account_id))
@@ -370,8 +527,9 @@ impl Core {
         )
     }
 
-    fn backup_blob(&self, dest: &Path) -> TaskHandle {
+    fn backup_blob(&self, dest: &Path, filter: &DocumentFilter) -> TaskHandle {
         let store = self.storage.data.clone();
+        let filter = filter.clone();
         let blob_store = self.storage.blob.clone();
         let (handle, writer) = spawn_writer(dest.join("blob"));
         (
@@ -381,6 +539,9 @@ impl Core {
                     .failed("Failed to send family");
 
                 let mut hashes = Vec::new();
+                let mut last_hash = Vec::new();
+                let mut is_linked = false;
+                let mut is_changed = false;
 
                 store
                     .iterate(
@@ -410,7 +571,20 @@ impl Core {
 
                             let hash = key.range(0..BLOB_HASH_LEN)?.to_vec();
 
+                            if hash != last_hash {
+                                last_hash = hash.clone();
+                                is_linked = false;
+                                is_changed = false;
+                            }
+
                             if account_id != u32::MAX && document_id != u32::MAX {
+                                // Links are sorted before the commit of the same hash
+                                is_linked = true;
+                                if !filter.matches(account_id, collection, document_id) {
+                                    return Ok(true);
+                                }
+                                is_changed = true;
+
                                 writer
                                     .send(Op::AccountId(account_id))
                                     .failed("Failed to send account id");
@@ -423,7 +597,7 @@ impl Core {
                                 writer
                                     .send(Op::KeyValue((hash, vec![])))
                                     .failed("Failed to send key value");
-                            } else {
+                            } else if is_changed || !is_linked {
                                 hashes.push(hash);
                             }
 
@@ -767,8 +941,9 @@ impl Core {
         )
     }
 
-    fn backup_index(&self, dest: &Path) -> TaskHandle {
+    fn backup_index(&self, dest: &Path, filter: &DocumentFilter) -> TaskHandle {
         let store = self.storage.data.clone();
+        let filter = filter.clone();
         let (handle, writer) = spawn_writer(dest.join("index"));
         (
             tokio::spawn(async move {
@@ -803,6 +978,10 @@ impl Core {
                             let collection = key.deserialize_u8(U32_LEN)?;
                             let document_id = key.deserialize_be_u32(key.len() - U32_LEN)?;
 
+                            if !filter.matches(account_id, collection, document_id) {
+                                return Ok(true);
+                            }
+
                             let key = key.range(U32_LEN + 1..key.len() - U32_LEN)?.to_vec();
 
                             if account_id != last_account_id {
@@ -837,8 +1016,9 @@ impl Core {
         )
     }
 
-    fn backup_bitmaps(&self, dest: &Path) -> TaskHandle {
+    fn backup_bitmaps(&self, dest: &Path, filter: &DocumentFilter) -> TaskHandle {
         let store = self.storage.data.clone();
+        let filter = filter.clone();
 
         let (handle, writer) = spawn_writer(dest.join("bitmap"));
         (
@@ -961,6 +1141,7 @@ impl Core {
                             })
                             .await
                             .failed("Failed to get bitmap")
+                            .and_then(|bitmap| filter.retain(account_id, collection, bitmap))
                         {
                             let key = match class {
                                 BitmapClass::DocumentIds => {
@@ -1008,8 +1189,9 @@ impl Core {
         )
     }
 
-    fn backup_logs(&self, dest: &Path) -> TaskHandle {
+    fn backup_logs(&self, dest: &Path, filter: &DocumentFilter) -> TaskHandle {
         let store = self.storage.data.clone();
+        let filter = filter.clone();
         let (handle, writer) = spawn_writer(dest.join("log"));
         (
             tokio::spawn(async move {
@@ -1041,6 +1223,8 @@ impl Core {
 
                             if key.len() != U64_LEN {
                                 failed(&format!("Found invalid log entry {key:?} {value:?}"));
+                            } else if !filter.is_new_change(key.as_slice().deserialize_be_u64(0)?) {
+                                return Ok(true);
                             }
 
                             if account_id != last_account_id {
@@ -1070,6 +1254,69 @@ impl Core {
             handle,
         )
     }
+
+    fn backup_deleted(&self, dest: &Path, changes: Arc<ChangedDocuments>) -> TaskHandle {
+        let (handle, writer) = spawn_writer(dest.join("deleted"));
+        (
+            tokio::spawn(async move {
+                writer
+                    .send(Op::Family(Family::Deleted))
+                    .failed("Failed to send family");
+
+                for ((account_id, collection), document_ids) in &changes.deleted {
+                    writer
+                        .send(Op::AccountId(*account_id))
+                        .failed("Failed to send account id");
+                    writer
+                        .send(Op::Collection(*collection))
+                        .failed("Failed to send collection");
+
+                    let mut bytes = Vec::with_capacity(document_ids.serialized_size());
+                    document_ids
+                        .serialize_into(&mut bytes)
+                        .failed("Failed to serialize bitmap");
+
+                    writer
+                        .send(Op::KeyValue((vec![], bytes)))
+                        .failed("Failed to send key value");
+                }
+            }),
+            handle,
+        )
+    }
+}
+
+impl DocumentFilter {
+    fn matches(&self, account_id: u32, collection: u8, document_id: u32) -> bool {
+        self.0.as_ref().map_or(true, |changes| {
+            changes
+                .updated
+                .get(&(account_id, collection))
+                .map_or(false, |document_ids| document_ids.contains(document_id))
+        })
+    }
+
+    fn retain(
+        &self,
+        account_id: u32,
+        collection: u8,
+        mut bitmap: RoaringBitmap,
+    ) -> Option<RoaringBitmap> {
+        if let Some(changes) = &self.0 {
+            bitmap &= changes.updated.get(&(account_id, collection))?;
+            if bitmap.is_empty() {
+                return None;
+            }
+        }
+
+        Some(bitmap)
+    }
+
+    fn is_new_change(&self, change_id: u64) -> bool {
+        self.0
+            .as_ref()
+            .map_or(true, |changes| change_id > changes.since)
+    }
 }
 
 fn spawn_writer(path: PathBuf) -> (std::thread::JoinHandle<()>, SyncSender<Op>) {
@@ -59,6 +59,7 @@ Usage: stalwart-mail [OPTIONS]
 Options:
   -c, --config <PATH>              Start server with the specified configuration file
   -e, --export <PATH>              Export all store data to a specific path
+  -s, --since <CHANGE-ID>          Only export changes made after the specified change id
   -i, --import <PATH>              Import store data from a specific path
   -I, --init <PATH>                Initialize a new server at a specific path
   -h, --help                       Print help
@@ -76,6 +77,7 @@ impl BootManager {
     pub async fn init() -> Self {
         let mut config_path = std::env::var("CONFIG_PATH").ok();
         let mut import_export = ImportExport::None;
+        let mut since = None;
 
         if config_path.is_none() {
             let mut args = std::env::args().skip(1);
@@ -110,6 +112,9 @@ impl BootManager {
                     ("export" | "e", Some(value)) => {
                         import_export = ImportExport::Export(value.into());
                     }
+                    ("since" | "s", Some(value)) => {
+                        since = Some(value.parse::<u64>().failed("Invalid change id"));
+                    }
                     ("import" | "i", Some(value)) => {
                         import_export = ImportExport::Import(value.into());
                     }
@@ -350,7 +355,7 @@ impl BootManager {
             ImportExport::Export(path) => {
                 Core::parse(&mut config, stores, manager)
                     .await
-                    .backup(path)
+                    .backup(path, since)
                     .await;
                 std::process::exit(0);
             }
@@ -0,0 +1,157 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use store::{
+    write::{
+        assert::HashedValue,
+        key::{DeserializeBigEndian, KeySerializer},
+        now, BatchBuilder, LookupClass, ValueClass,
+    },
+    Deserialize, ValueKey, U64_LEN,
+};
+
+use crate::Core;
+
+use super::node_id;
+
+/// A lease on a singleton task, stored in the shared data store so that only
+/// one node of the cluster runs the task at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    pub expires: u64,
+    pub holder: String,
+}
+
+impl Core {
+    /// Acquires or renews the lease on a task, returning `true` if this node
+    /// holds the lease for the given duration.
+    pub async fn try_lease(&self, task: &str, duration: Duration) -> bool {
+        let key = lease_key(task);
+        let holder = node_id();
+        let current_time = now();
+
+        let current = match self
+            .storage
+            .data
+            .get_value::<HashedValue<Lease>>(ValueKey::from(ValueClass::Lookup(LookupClass::Key(
+                key.clone(),
+            ))))
+            .await
+        {
+            Ok(current) => current,
+            Err(err) => {
+                tracing::error!(
+                    context = "lease",
+                    event = "error",
+                    task = task,
+                    reason = %err,
+                    "Failed to read lease."
+                );
+                return false;
+            }
+        };
+
+        // Leases are stored using the lookup key format, expired leases are
+        // removed when the lookup store is purged
+        let class = ValueClass::Lookup(LookupClass::Key(key));
+        let mut batch = BatchBuilder::new();
+        match &current {
+            Some(current)
+                if current.inner.expires > current_time && current.inner.holder != holder =>
+            {
+                tracing::debug!(
+                    context = "lease",
+                    event = "busy",
+                    task = task,
+                    holder = current.inner.holder,
+                    expires_in = current.inner.expires - current_time,
+                    "Task is leased by another node."
+                );
+                return false;
+            }
+            Some(current) => {
+                batch.assert_value(class.clone(), current);
+            }
+            None => {
+                batch.assert_value(class.clone(), ());
+            }
+        }
+        batch.set(
+            class,
+            KeySerializer::new(U64_LEN + holder.len())
+                .write(current_time + duration.as_secs())
+                .write(holder.as_bytes())
+                .finalize(),
+        );
+
+        match self.storage.data.write(batch.build()).await {
+            Ok(_) => {
+                tracing::debug!(
+                    context = "lease",
+                    event = "acquire",
+                    task = task,
+                    duration = duration.as_secs(),
+                    "Acquired task lease."
+                );
+                true
+            }
+            Err(store::Error::AssertValueFailed) => {
+                tracing::debug!(
+                    context = "lease",
+                    event = "busy",
+                    task = task,
+                    "Task lease was acquired by another node."
+                );
+                false
+            }
+            Err(err) => {
+                tracing::error!(
+                    context = "lease",
+                    event = "error",
+                    task = task,
+                    reason = %err,
+                    "Failed to acquire lease."
+                );
+                false
+            }
+        }
+    }
+}
+
+impl Deserialize for Lease {
+    fn deserialize(bytes: &[u8]) -> store::Result<Self> {
+        Ok(Lease {
+            expires: bytes.deserialize_be_u64(0)?,
+            holder: String::from_utf8_lossy(bytes.get(U64_LEN..).unwrap_or_default()).into_owned(),
+        })
+    }
+}
+
+fn lease_key(task: &str) -> Vec<u8> {
+    KeySerializer::new(task.len() + 6)
+        .write("lease:")
+        .write(task)
+        .finalize()
+}
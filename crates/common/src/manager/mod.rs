@@ -30,6 +30,7 @@ use self::config::ConfigManager;
 pub mod backup;
 pub mod boot;
 pub mod config;
+pub mod lease;
 pub mod reload;
 pub mod restore;
 pub mod webadmin;
@@ -59,6 +60,18 @@ impl ConfigManager {
     }
 }
 
+/// Identifies this node in the cluster. The process id is included so
+/// multiple instances can run on the same host.
+pub fn node_id() -> String {
+    format!(
+        "{}:{}",
+        hostname::get()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        std::process::id()
+    )
+}
+
 async fn fetch_resource(url: &str) -> Result<Vec<u8>, String> {
     if let Some(path) = url.strip_prefix("file://") {
         tokio::fs::read(path)
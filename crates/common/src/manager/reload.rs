@@ -21,18 +21,20 @@
  * for more details.
 */
 
-use ahash::AHashSet;
 use arc_swap::ArcSwap;
 use parking_lot::RwLock;
 use store::Stores;
-use utils::config::{ipmask::IpAddrOrMask, utils::ParseValue, Config};
+use utils::config::Config;
 
 use crate::{
     config::{
-        server::{tls::parse_certificates, Servers},
+        server::{
+            tls::{parse_certificate_directory, parse_certificates},
+            Servers,
+        },
         tracers::Tracers,
     },
-    listener::blocked::BLOCKED_IP_KEY,
+    listener::blocked::{parse_blocked_ips, BLOCKED_IP_KEY},
     Core,
 };
 
@@ -45,26 +47,11 @@ pub struct ReloadResult {
 
 impl Core {
     pub async fn reload_blocked_ips(&self) -> store::Result<ReloadResult> {
-        let mut ip_addresses = AHashSet::new();
         let mut config = self.storage.config.build_config(BLOCKED_IP_KEY).await?;
-
-        for ip in config
-            .set_values(BLOCKED_IP_KEY)
-            .map(IpAddrOrMask::parse_value)
-            .collect::<Vec<_>>()
-        {
-            match ip {
-                Ok(IpAddrOrMask::Ip(ip)) => {
-                    ip_addresses.insert(ip);
-                }
-                Ok(IpAddrOrMask::Mask(_)) => {}
-                Err(err) => {
-                    config.new_parse_error(BLOCKED_IP_KEY, err);
-                }
-            }
-        }
+        let (ip_addresses, ip_bans, _) = parse_blocked_ips(&mut config);
 
         *self.network.blocked_ips.ip_addresses.write() = ip_addresses;
+        *self.network.blocked_ips.ip_bans.write() = ip_bans;
 
         Ok(config.into())
     }
@@ -73,6 +60,7 @@ impl Core {
         let mut config = self.storage.config.build_config("certificate").await?;
         let mut certificates = self.tls.certificates.load().as_ref().clone();
 
+        parse_certificate_directory(&mut config, &mut certificates, &mut Default::default());
         parse_certificates(&mut config, &mut certificates, &mut Default::default());
 
         self.tls.certificates.store(certificates.into());
@@ -108,6 +96,7 @@ impl Core {
             blob_stores: self.storage.blobs.clone(),
             fts_stores: self.storage.ftss.clone(),
             lookup_stores: self.storage.lookups.clone(),
+            pubsub_stores: self.storage.pubsubs.clone(),
             purge_schedules: Default::default(),
         };
         stores.parse_stores(&mut config).await;
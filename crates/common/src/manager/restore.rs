@@ -35,7 +35,7 @@ use store::{
         FtsQueueClass, LookupClass, MaybeDynamicId, MaybeDynamicValue, Operation, TagValue,
         ValueClass,
     },
-    BlobStore, Serialize, Store, U32_LEN,
+    BlobStore, Serialize, Store, ValueKey, U32_LEN,
 };
 use store::{
     write::{QueueClass, QueueEvent},
@@ -117,10 +117,21 @@ async fn restore_file(store: Store, blob_store: BlobStore, path: &Path) {
                         if collection == u8::from(Collection::Mailbox)
                             && u8::from(Property::EmailIds) == field
                         {
+                            // Incremental snapshots contain the absolute counter value
+                            let current = store
+                                .get_counter(ValueKey {
+                                    account_id,
+                                    collection,
+                                    document_id,
+                                    class: ValueClass::Property(field),
+                                })
+                                .await
+                                .failed("Failed to get counter");
                             batch.add(
                                 ValueClass::Property(field),
                                 i64::deserialize(&value)
-                                    .expect("Failed to deserialize mailbox uidnext"),
+                                    .expect("Failed to deserialize mailbox uidnext")
+                                    - current,
                             );
                         } else {
                             batch.set(ValueClass::Property(field), value);
@@ -371,6 +382,25 @@ async fn restore_file(store: Store, blob_store: BlobStore, path: &Path) {
                             set: MaybeDynamicValue::Static(value),
                         });
                     }
+                    Family::Deleted => {
+                        let document_ids = RoaringBitmap::deserialize_from(&value[..])
+                            .expect("Failed to deserialize bitmap");
+
+                        for document_id in document_ids {
+                            batch.delete_document(document_id);
+
+                            if batch.ops.len() >= 1000 {
+                                store
+                                    .write(batch.build())
+                                    .await
+                                    .failed("Failed to write batch");
+                                batch = BatchBuilder::new();
+                                batch
+                                    .with_account_id(account_id)
+                                    .with_collection(collection);
+                            }
+                        }
+                    }
                     Family::None => failed("No family specified in file"),
                 }
             }
@@ -488,6 +518,7 @@ impl TryFrom<u8> for Family {
             9 => Ok(Self::Index),
             10 => Ok(Self::Bitmap),
             11 => Ok(Self::Log),
+            12 => Ok(Self::Deleted),
             other => Err(format!("Unknown family type {other}")),
         }
     }
@@ -0,0 +1,185 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    borrow::Cow,
+    fmt::{self, Debug, Write},
+};
+
+use ahash::AHashSet;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use tracing::field::{Field, Visit};
+use tracing_subscriber::{
+    field::RecordFields,
+    fmt::{format::Writer, FormatFields},
+};
+use utils::config::Config;
+
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    fields: AHashSet<String>,
+    method: RedactMethod,
+    salt: String,
+    emails: Option<Regex>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactMethod {
+    Hash,
+    Mask,
+}
+
+impl Redactor {
+    pub fn parse(config: &mut Config, id: &str) -> Option<Self> {
+        let fields = config
+            .values(("tracer", id, "redact.fields"))
+            .map(|(_, value)| value.to_string())
+            .collect::<AHashSet<_>>();
+        let emails = config
+            .property_or_default::<bool>(("tracer", id, "redact.emails"), "false")
+            .unwrap_or(false);
+        if fields.is_empty() && !emails {
+            return None;
+        }
+
+        let method = match config
+            .value(("tracer", id, "redact.method"))
+            .unwrap_or("hash")
+        {
+            "hash" => RedactMethod::Hash,
+            "mask" => RedactMethod::Mask,
+            method => {
+                let err = format!("Invalid redaction method: {method}");
+                config.new_parse_error(("tracer", id, "redact.method"), err);
+                RedactMethod::Hash
+            }
+        };
+
+        Some(Redactor {
+            fields,
+            method,
+            salt: config
+                .value(("tracer", id, "redact.salt"))
+                .unwrap_or_default()
+                .to_string(),
+            emails: emails.then(|| {
+                Regex::new(r"[A-Za-z0-9._%+\-]+@([A-Za-z0-9\-]+\.)+[A-Za-z0-9\-]+").unwrap()
+            }),
+        })
+    }
+
+    /// Redacts the value if the field is listed in the policy, otherwise
+    /// only e-mail addresses contained in the value are redacted.
+    pub fn redact_field<'x>(&self, name: &str, value: &'x str) -> Cow<'x, str> {
+        if self.fields.contains(name) {
+            Cow::Owned(self.redact(value))
+        } else {
+            self.redact_emails(value)
+        }
+    }
+
+    pub fn redact_emails<'x>(&self, value: &'x str) -> Cow<'x, str> {
+        if let Some(emails) = &self.emails {
+            emails.replace_all(value, |captures: &regex::Captures| {
+                let address = &captures[0];
+                let (local, domain) = address.rsplit_once('@').unwrap_or((address, ""));
+                format!("{}@{}", self.redact(local), domain)
+            })
+        } else {
+            Cow::Borrowed(value)
+        }
+    }
+
+    fn redact(&self, value: &str) -> String {
+        match self.method {
+            RedactMethod::Hash => {
+                // Keep a short digest so redacted values can still be correlated
+                let mut hasher = Sha256::new();
+                hasher.update(self.salt.as_bytes());
+                hasher.update(value.as_bytes());
+                hasher.finalize().iter().take(8).fold(
+                    String::with_capacity(16),
+                    |mut hash, byte| {
+                        let _ = write!(hash, "{byte:02x}");
+                        hash
+                    },
+                )
+            }
+            RedactMethod::Mask => "***".to_string(),
+        }
+    }
+}
+
+impl<'writer> FormatFields<'writer> for Redactor {
+    fn format_fields<R: RecordFields>(&self, writer: Writer<'writer>, fields: R) -> fmt::Result {
+        let mut visitor = RedactVisitor {
+            redactor: self,
+            writer,
+            is_empty: true,
+            result: Ok(()),
+        };
+        fields.record(&mut visitor);
+        visitor.result
+    }
+}
+
+struct RedactVisitor<'x, 'writer> {
+    redactor: &'x Redactor,
+    writer: Writer<'writer>,
+    is_empty: bool,
+    result: fmt::Result,
+}
+
+impl Visit for RedactVisitor<'_, '_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if self.result.is_err() {
+            return;
+        }
+
+        let delimiter = if self.is_empty { "" } else { " " };
+        self.is_empty = false;
+
+        let value = format!("{value:?}");
+        let name = field.name();
+        self.result = if name == "message" {
+            write!(
+                self.writer,
+                "{delimiter}{}",
+                self.redactor.redact_emails(&value)
+            )
+        } else if self.redactor.fields.contains(name) {
+            write!(
+                self.writer,
+                "{delimiter}{name}={}",
+                self.redactor.redact(value.trim_matches('"'))
+            )
+        } else {
+            write!(
+                self.writer,
+                "{delimiter}{name}={}",
+                self.redactor.redact_emails(&value)
+            )
+        };
+    }
+}
@@ -0,0 +1,213 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::net::IpAddr;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use directory::{core::scram::ScramExchange, Directory, Principal, QueryBy};
+use utils::{config::utils::ParseValue, metrics::AUTH_TOTAL};
+
+use crate::{AuthResult, Core};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaslMechanism {
+    ScramSha256,
+    ScramSha256Plus,
+    CramMd5,
+}
+
+// Challenge-response SASL exchange, these mechanisms are verified against the
+// SCRAM verifiers or clear text secrets of a principal.
+pub struct SaslExchange {
+    mechanism: SaslMechanism,
+    channel_binding: Option<Vec<u8>>,
+    state: SaslState,
+}
+
+enum SaslState {
+    Start,
+    CramMd5 {
+        challenge: String,
+    },
+    ScramFinal {
+        exchange: ScramExchange,
+        principal: Option<Principal<u32>>,
+    },
+    ScramAck {
+        principal: Principal<u32>,
+    },
+    Done,
+}
+
+// Challenges are returned base64 encoded
+pub enum SaslStep<T = directory::Result<AuthResult<Principal<u32>>>> {
+    Challenge(String),
+    Done(T),
+    Invalid(&'static str),
+}
+
+impl SaslExchange {
+    pub fn new(mechanism: SaslMechanism, channel_binding: Option<Vec<u8>>) -> Self {
+        SaslExchange {
+            mechanism,
+            channel_binding,
+            state: SaslState::Start,
+        }
+    }
+
+    pub fn mechanism(&self) -> SaslMechanism {
+        self.mechanism
+    }
+}
+
+impl Core {
+    // Runs the next step of a SASL exchange, the response is None when
+    // the client did not provide an initial response.
+    pub async fn sasl_step(
+        &self,
+        directory: &Directory,
+        exchange: &mut SaslExchange,
+        response: Option<&[u8]>,
+        remote_ip: IpAddr,
+    ) -> SaslStep {
+        let step = self
+            .sasl_next_step(directory, exchange, response, remote_ip)
+            .await;
+        if let SaslStep::Done(result) = &step {
+            AUTH_TOTAL.increment(match result {
+                Ok(AuthResult::Success(_)) => "success",
+                Ok(AuthResult::Failure) => "failure",
+                Ok(AuthResult::Banned) => "banned",
+                Err(_) => "error",
+            });
+        }
+        step
+    }
+
+    async fn sasl_next_step(
+        &self,
+        directory: &Directory,
+        exchange: &mut SaslExchange,
+        response: Option<&[u8]>,
+        remote_ip: IpAddr,
+    ) -> SaslStep {
+        match (
+            std::mem::replace(&mut exchange.state, SaslState::Done),
+            response,
+        ) {
+            (SaslState::Start, None) if exchange.mechanism == SaslMechanism::CramMd5 => {
+                let challenge = format!(
+                    "<{}.{}@stalwart>",
+                    std::process::id(),
+                    store::rand::random::<u64>()
+                );
+                let response = STANDARD.encode(&challenge);
+                exchange.state = SaslState::CramMd5 { challenge };
+                SaslStep::Challenge(response)
+            }
+            (SaslState::Start, None) => {
+                exchange.state = SaslState::Start;
+                SaslStep::Challenge(String::new())
+            }
+            (SaslState::Start, Some(response)) if exchange.mechanism != SaslMechanism::CramMd5 => {
+                let (channel_binding, is_plus_available) = match exchange.mechanism {
+                    SaslMechanism::ScramSha256Plus => match exchange.channel_binding.clone() {
+                        Some(channel_binding) => (Some(channel_binding), true),
+                        None => return SaslStep::Invalid("Channel binding is not available."),
+                    },
+                    _ => (None, exchange.channel_binding.is_some()),
+                };
+                let mut scram =
+                    match ScramExchange::parse(response, channel_binding, is_plus_available) {
+                        Ok(scram) => scram,
+                        Err(err) => return SaslStep::Invalid(err),
+                    };
+                let principal = match directory.query(QueryBy::Name(scram.username()), true).await {
+                    Ok(principal) => principal,
+                    Err(err) => return SaslStep::Done(Err(err)),
+                };
+                let response =
+                    scram.server_first(principal.as_ref().and_then(|p| p.scram_verifier()));
+                exchange.state = SaslState::ScramFinal {
+                    exchange: scram,
+                    principal,
+                };
+                SaslStep::Challenge(STANDARD.encode(response))
+            }
+            (SaslState::CramMd5 { challenge }, Some(response)) => {
+                let (username, digest) = match std::str::from_utf8(response)
+                    .ok()
+                    .and_then(|response| response.trim().rsplit_once(' '))
+                {
+                    Some(response) => response,
+                    None => return SaslStep::Invalid("Invalid CRAM-MD5 response."),
+                };
+                SaslStep::Done(match directory.query(QueryBy::Name(username), true).await {
+                    Ok(Some(principal)) if principal.verify_cram_md5(&challenge, digest) => {
+                        Ok(AuthResult::Success(principal))
+                    }
+                    Ok(_) => self.auth_failure(remote_ip, username).await,
+                    Err(err) => Err(err),
+                })
+            }
+            (
+                SaslState::ScramFinal {
+                    exchange: scram,
+                    principal,
+                },
+                Some(response),
+            ) => match (scram.server_final(response), principal) {
+                (Some(response), Some(principal)) => {
+                    exchange.state = SaslState::ScramAck { principal };
+                    SaslStep::Challenge(STANDARD.encode(response))
+                }
+                _ => SaslStep::Done(self.auth_failure(remote_ip, scram.username()).await),
+            },
+            (SaslState::ScramAck { principal }, None | Some(b"")) => {
+                SaslStep::Done(Ok(AuthResult::Success(principal)))
+            }
+            _ => SaslStep::Invalid("Invalid SASL response."),
+        }
+    }
+}
+
+impl SaslMechanism {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SaslMechanism::ScramSha256 => "SCRAM-SHA-256",
+            SaslMechanism::ScramSha256Plus => "SCRAM-SHA-256-PLUS",
+            SaslMechanism::CramMd5 => "CRAM-MD5",
+        }
+    }
+}
+
+impl ParseValue for SaslMechanism {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value.to_ascii_uppercase().as_str() {
+            "SCRAM-SHA-256" => Ok(SaslMechanism::ScramSha256),
+            "SCRAM-SHA-256-PLUS" => Ok(SaslMechanism::ScramSha256Plus),
+            "CRAM-MD5" => Ok(SaslMechanism::CramMd5),
+            _ => Err(format!("Unsupported SASL mechanism {value:?}.")),
+        }
+    }
+}
@@ -0,0 +1,276 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::SystemTime;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::hmac;
+
+use crate::{config::smtp::session::Srs, Core};
+
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const HASH_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SrsError {
+    Malformed,
+    InvalidHash,
+    Expired,
+}
+
+impl Core {
+    pub async fn srs_forward<'x>(
+        &self,
+        sender: &str,
+        rcpt_domains: impl IntoIterator<Item = &'x str>,
+    ) -> Option<String> {
+        let srs = self.smtp.session.srs.as_ref()?;
+        let directory = &self.storage.directory;
+
+        // Senders from local domains pass SPF on their own
+        let (_, sender_domain) = sender.rsplit_once('@')?;
+        if directory
+            .is_local_domain(&sender_domain.to_lowercase())
+            .await
+            .unwrap_or(true)
+        {
+            return None;
+        }
+
+        // Only rewrite when the message leaves this server
+        for domain in rcpt_domains {
+            if !directory.is_local_domain(domain).await.unwrap_or(true) {
+                return srs.forward(sender);
+            }
+        }
+
+        None
+    }
+}
+
+impl Srs {
+    pub fn forward(&self, sender: &str) -> Option<String> {
+        let (local, domain) = sender.rsplit_once('@')?;
+        if local.is_empty() || domain.is_empty() || domain.eq_ignore_ascii_case(&self.domain) {
+            return None;
+        }
+
+        Some(if srs_tail(local, "SRS0").is_some() {
+            // Forwarding an SRS0 address, keep the first hop and the original hash
+            let rest = &local[4..];
+            format!(
+                "SRS1={}={}={}@{}",
+                self.hash(&[domain, rest]),
+                domain,
+                rest,
+                self.domain
+            )
+        } else if let Some(rest) = srs_tail(local, "SRS1") {
+            // Forwarding an SRS1 address, only the hash is replaced
+            let (_, rest) = rest.split_once('=')?;
+            let (first_hop, rest) = rest.split_once('=')?;
+            format!(
+                "SRS1={}={}={}@{}",
+                self.hash(&[first_hop, rest]),
+                first_hop,
+                rest,
+                self.domain
+            )
+        } else {
+            let timestamp = timestamp();
+            format!(
+                "SRS0={}={}={}={}@{}",
+                self.hash(&[timestamp.as_str(), domain, local]),
+                timestamp,
+                domain,
+                local,
+                self.domain
+            )
+        })
+    }
+
+    pub fn reverse(&self, address: &str) -> Option<Result<String, SrsError>> {
+        let (local, domain) = address.rsplit_once('@')?;
+        if !domain.eq_ignore_ascii_case(&self.domain) {
+            return None;
+        }
+
+        if let Some(rest) = srs_tail(local, "SRS0") {
+            let mut parts = rest.splitn(4, '=');
+            let (hash, timestamp, domain, local) =
+                match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                    (Some(hash), Some(timestamp), Some(domain), Some(local))
+                        if !domain.is_empty() && !local.is_empty() =>
+                    {
+                        (hash, timestamp, domain, local)
+                    }
+                    _ => return Some(Err(SrsError::Malformed)),
+                };
+            if !self.verify_hash(hash, &[timestamp, domain, local]) {
+                Some(Err(SrsError::InvalidHash))
+            } else if !self.is_timestamp_valid(timestamp) {
+                Some(Err(SrsError::Expired))
+            } else {
+                Some(Ok(format!("{local}@{domain}")))
+            }
+        } else if let Some(rest) = srs_tail(local, "SRS1") {
+            let mut parts = rest.splitn(3, '=');
+            let (hash, first_hop, rest) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(hash), Some(first_hop), Some(rest))
+                    if !first_hop.is_empty() && !rest.is_empty() =>
+                {
+                    (hash, first_hop, rest)
+                }
+                _ => return Some(Err(SrsError::Malformed)),
+            };
+            if self.verify_hash(hash, &[first_hop, rest]) {
+                Some(Ok(format!("SRS0{rest}@{first_hop}")))
+            } else {
+                Some(Err(SrsError::InvalidHash))
+            }
+        } else {
+            None
+        }
+    }
+
+    fn hash(&self, items: &[&str]) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, &self.secret);
+        let mut ctx = hmac::Context::with_key(&key);
+        for item in items {
+            ctx.update(item.to_lowercase().as_bytes());
+        }
+        let mut hash = STANDARD.encode(ctx.sign().as_ref());
+        hash.truncate(HASH_LEN);
+        hash
+    }
+
+    fn verify_hash(&self, hash: &str, items: &[&str]) -> bool {
+        hash.len() == HASH_LEN && self.hash(items).eq_ignore_ascii_case(hash)
+    }
+
+    fn is_timestamp_valid(&self, timestamp: &str) -> bool {
+        let timestamp = timestamp.as_bytes();
+        if timestamp.len() != 2 {
+            return false;
+        }
+        let mut value = 0;
+        for ch in timestamp {
+            match BASE32.iter().position(|b| *b == ch.to_ascii_uppercase()) {
+                Some(pos) => value = (value << 5) | pos as u64,
+                None => return false,
+            }
+        }
+
+        (today() + 1024 - value) % 1024 <= self.max_age.as_secs() / 86400
+    }
+}
+
+fn srs_tail<'x>(local: &'x str, prefix: &str) -> Option<&'x str> {
+    // Accept the '=', '+' and '-' separators used by other implementations
+    local
+        .get(..5)
+        .filter(|head| {
+            head[..4].eq_ignore_ascii_case(prefix)
+                && matches!(head.as_bytes()[4], b'=' | b'+' | b'-')
+        })
+        .map(|_| &local[5..])
+}
+
+fn today() -> u64 {
+    SystemTime::UNIX_EPOCH
+        .elapsed()
+        .unwrap_or_default()
+        .as_secs()
+        / 86400
+        % 1024
+}
+
+fn timestamp() -> String {
+    let today = today() as usize;
+    String::from_utf8(vec![BASE32[(today >> 5) & 31], BASE32[today & 31]]).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::config::smtp::session::Srs;
+
+    use super::SrsError;
+
+    #[test]
+    fn srs_rewrite() {
+        let srs = Srs {
+            secret: b"secret".to_vec(),
+            domain: "forwarder.org".to_string(),
+            max_age: Duration::from_secs(21 * 86400),
+        };
+
+        // SRS0 round trip
+        let srs0 = srs.forward("john@example.org").unwrap();
+        assert!(srs0.starts_with("SRS0="), "{srs0}");
+        assert!(srs0.ends_with("=example.org=john@forwarder.org"), "{srs0}");
+        assert_eq!(srs.reverse(&srs0), Some(Ok("john@example.org".to_string())));
+        assert_eq!(
+            srs.reverse(&srs0.to_lowercase()),
+            Some(Ok("john@example.org".to_string()))
+        );
+
+        // Local senders are not rewritten
+        assert_eq!(srs.forward("jane@forwarder.org"), None);
+        assert_eq!(srs.reverse("jane@forwarder.org"), None);
+
+        // Tampered addresses are rejected
+        let tampered = srs0.replace("john", "jane");
+        assert_eq!(srs.reverse(&tampered), Some(Err(SrsError::InvalidHash)));
+        assert_eq!(
+            srs.reverse("SRS0=abc@forwarder.org"),
+            Some(Err(SrsError::Malformed))
+        );
+
+        // SRS1 rewriting of another forwarder's SRS0 address
+        let srs1 = srs
+            .forward("SRS0=HHHH=TT=example.org=john@other.net")
+            .unwrap();
+        assert!(
+            srs1.ends_with("=other.net==HHHH=TT=example.org=john@forwarder.org"),
+            "{srs1}"
+        );
+        assert_eq!(
+            srs.reverse(&srs1),
+            Some(Ok("SRS0=HHHH=TT=example.org=john@other.net".to_string()))
+        );
+
+        // Re-forwarding an SRS1 address keeps the first hop
+        let srs1_again = Srs {
+            domain: "third.net".to_string(),
+            ..srs.clone()
+        }
+        .forward(&srs1)
+        .unwrap();
+        assert!(
+            srs1_again.ends_with("=other.net==HHHH=TT=example.org=john@third.net"),
+            "{srs1_again}"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+pub mod object;
+pub mod request;
+pub mod response;
+
+use std::borrow::Cow;
+
+pub const NS_DAV: &str = "DAV:";
+pub const NS_CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+pub const NS_CARDDAV: &str = "urn:ietf:params:xml:ns:carddav";
+pub const NS_CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+
+pub const XML_CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DavResourceType {
+    Calendar,
+    AddressBook,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Depth {
+    Zero,
+    One,
+    Infinity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DavPath {
+    Root,
+    Principal(String),
+    Home {
+        resource_type: DavResourceType,
+        account: String,
+    },
+    Collection {
+        resource_type: DavResourceType,
+        account: String,
+        name: String,
+    },
+    Resource {
+        resource_type: DavResourceType,
+        account: String,
+        collection: String,
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preconditions {
+    pub if_match: Option<String>,
+    pub if_none_match: Option<String>,
+}
+
+impl DavResourceType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "cal" => Some(DavResourceType::Calendar),
+            "card" => Some(DavResourceType::AddressBook),
+            _ => None,
+        }
+    }
+
+    pub fn as_path(&self) -> &'static str {
+        match self {
+            DavResourceType::Calendar => "cal",
+            DavResourceType::AddressBook => "card",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            DavResourceType::Calendar => "text/calendar; charset=utf-8",
+            DavResourceType::AddressBook => "text/vcard; charset=utf-8",
+        }
+    }
+}
+
+impl Depth {
+    /// Parses the Depth header, defaulting to infinity as required by RFC 4918.
+    pub fn parse(value: Option<&str>) -> Self {
+        match value.map(|v| v.trim()) {
+            Some("0") => Depth::Zero,
+            Some("1") => Depth::One,
+            _ => Depth::Infinity,
+        }
+    }
+}
+
+impl Preconditions {
+    /// Evaluates If-Match and If-None-Match against the current ETag of a
+    /// resource, if it exists.
+    pub fn is_satisfied(&self, etag: Option<&str>) -> bool {
+        let matches = |value: &str| {
+            value
+                .split(',')
+                .map(|v| v.trim())
+                .any(|v| (v == "*" && etag.is_some()) || Some(v) == etag)
+        };
+        !matches!(&self.if_match, Some(value) if !matches(value))
+            && !matches!(&self.if_none_match, Some(value) if matches(value))
+    }
+}
+
+impl DavPath {
+    /// Parses a DAV path or href, such as `/dav/cal/john/default/event.ics`.
+    pub fn parse(href: &str) -> Option<Self> {
+        let path = match href.split_once("://") {
+            Some((_, rest)) => rest.find('/').map_or("/", |pos| &rest[pos..]),
+            None => href,
+        };
+        let mut segments = path
+            .strip_prefix("/dav")
+            .filter(|path| path.is_empty() || path.starts_with('/'))?
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| decode_path_segment(s).into_owned());
+
+        let path = match segments.next().as_deref() {
+            None => DavPath::Root,
+            Some("principal") => DavPath::Principal(segments.next()?),
+            Some(kind) => {
+                let resource_type = DavResourceType::parse(kind)?;
+                let account = segments.next()?;
+                match (segments.next(), segments.next()) {
+                    (None, _) => DavPath::Home {
+                        resource_type,
+                        account,
+                    },
+                    (Some(name), None) => DavPath::Collection {
+                        resource_type,
+                        account,
+                        name,
+                    },
+                    (Some(collection), Some(name)) => DavPath::Resource {
+                        resource_type,
+                        account,
+                        collection,
+                        name,
+                    },
+                }
+            }
+        };
+
+        if segments.next().is_none() {
+            Some(path)
+        } else {
+            None
+        }
+    }
+}
+
+/// Decodes a percent-encoded path segment.
+pub fn decode_path_segment(segment: &str) -> Cow<'_, str> {
+    if !segment.contains('%') {
+        return segment.into();
+    }
+
+    let bytes = segment.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if bytes[pos] == b'%' && pos + 2 < bytes.len() {
+            if let Some(byte) = std::str::from_utf8(&bytes[pos + 1..pos + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                result.push(byte);
+                pos += 3;
+                continue;
+            }
+        }
+        result.push(bytes[pos]);
+        pos += 1;
+    }
+
+    String::from_utf8(result)
+        .map(Cow::Owned)
+        .unwrap_or_else(|_| segment.into())
+}
+
+/// Percent-encodes a path segment, leaving unreserved characters as-is.
+pub fn encode_path_segment(segment: &str) -> Cow<'_, str> {
+    if segment
+        .bytes()
+        .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, b'-' | b'_' | b'.' | b'~' | b'@'))
+    {
+        return segment.into();
+    }
+
+    let mut result = String::with_capacity(segment.len() * 3);
+    for ch in segment.bytes() {
+        if ch.is_ascii_alphanumeric() || matches!(ch, b'-' | b'_' | b'.' | b'~' | b'@') {
+            result.push(ch as char);
+        } else {
+            result.push_str(&format!("%{ch:02X}"));
+        }
+    }
+    result.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DavPath, DavResourceType, Preconditions};
+
+    #[test]
+    fn parse_dav_path() {
+        for (href, expected) in [
+            ("/dav", Some(DavPath::Root)),
+            ("/dav/", Some(DavPath::Root)),
+            (
+                "/dav/principal/jane%40example.org/",
+                Some(DavPath::Principal("jane@example.org".to_string())),
+            ),
+            (
+                "https://example.org/dav/card/jane/",
+                Some(DavPath::Home {
+                    resource_type: DavResourceType::AddressBook,
+                    account: "jane".to_string(),
+                }),
+            ),
+            (
+                "/dav/cal/jane/work%20stuff/",
+                Some(DavPath::Collection {
+                    resource_type: DavResourceType::Calendar,
+                    account: "jane".to_string(),
+                    name: "work stuff".to_string(),
+                }),
+            ),
+            (
+                "/dav/cal/jane/default/1234.ics",
+                Some(DavPath::Resource {
+                    resource_type: DavResourceType::Calendar,
+                    account: "jane".to_string(),
+                    collection: "default".to_string(),
+                    name: "1234.ics".to_string(),
+                }),
+            ),
+            ("/dav/mail/jane/", None),
+            ("/dav/cal/jane/default/1234.ics/extra", None),
+            ("/jmap/", None),
+            ("/davcal/jane/", None),
+        ] {
+            assert_eq!(DavPath::parse(href), expected, "{href}");
+        }
+    }
+
+    #[test]
+    fn evaluate_preconditions() {
+        let preconditions = |if_match: Option<&str>, if_none_match: Option<&str>| Preconditions {
+            if_match: if_match.map(|v| v.to_string()),
+            if_none_match: if_none_match.map(|v| v.to_string()),
+        };
+
+        assert!(preconditions(None, None).is_satisfied(None));
+        assert!(preconditions(None, Some("*")).is_satisfied(None));
+        assert!(!preconditions(None, Some("*")).is_satisfied(Some("\"1\"")));
+        assert!(preconditions(Some("\"1\", \"2\""), None).is_satisfied(Some("\"2\"")));
+        assert!(!preconditions(Some("\"1\""), None).is_satisfied(Some("\"2\"")));
+        assert!(!preconditions(Some("*"), None).is_satisfied(None));
+    }
+}
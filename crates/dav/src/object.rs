@@ -0,0 +1,204 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::DavResourceType;
+
+/// A validated iCalendar or vCard object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DavObject {
+    pub uid: String,
+    pub component: Option<String>,
+}
+
+impl DavObject {
+    /// Validates an iCalendar (RFC 5545) or vCard (RFC 6350) object and
+    /// extracts the properties required for indexing. CalDAV requires each
+    /// calendar object resource to contain a single component type sharing
+    /// the same UID, with the exception of time zones.
+    pub fn parse(resource_type: DavResourceType, bytes: &[u8]) -> Result<Self, &'static str> {
+        let text = std::str::from_utf8(bytes).map_err(|_| "Object is not valid UTF-8.")?;
+        let root = match resource_type {
+            DavResourceType::Calendar => "VCALENDAR",
+            DavResourceType::AddressBook => "VCARD",
+        };
+
+        let mut stack: Vec<String> = Vec::new();
+        let mut has_root = false;
+        let mut uid: Option<String> = None;
+        let mut component: Option<String> = None;
+
+        for line in unfold(text) {
+            let (name, value) = match line.split_once(':') {
+                Some((name, value)) => (name, value.trim()),
+                None if line.trim().is_empty() => continue,
+                None => return Err("Invalid content line."),
+            };
+            // Strip parameters and group prefixes
+            let name = name.split(';').next().unwrap_or_default();
+            let name = name
+                .rsplit('.')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_uppercase();
+
+            match name.as_str() {
+                "BEGIN" => {
+                    let value = value.to_ascii_uppercase();
+                    if stack.is_empty() {
+                        if value != root || has_root {
+                            return Err("Invalid root component.");
+                        }
+                        has_root = true;
+                    } else if resource_type == DavResourceType::Calendar && stack.len() == 1 {
+                        match value.as_str() {
+                            "VTIMEZONE" => (),
+                            "VEVENT" | "VTODO" | "VJOURNAL" | "VFREEBUSY" => {
+                                if matches!(&component, Some(c) if c != &value) {
+                                    return Err(
+                                        "Calendar object contains multiple component types.",
+                                    );
+                                }
+                                component = Some(value.clone());
+                            }
+                            _ => return Err("Unsupported calendar component."),
+                        }
+                    }
+                    stack.push(value);
+                }
+                "END" => match stack.pop() {
+                    Some(component) if component.eq_ignore_ascii_case(value) => (),
+                    _ => return Err("Unbalanced component."),
+                },
+                "UID" => {
+                    let is_object_uid = match resource_type {
+                        DavResourceType::Calendar => stack.len() == 2 && stack[1] != "VTIMEZONE",
+                        DavResourceType::AddressBook => stack.len() == 1,
+                    };
+                    if is_object_uid {
+                        if matches!(&uid, Some(uid) if uid != value) {
+                            return Err("Calendar object contains multiple UIDs.");
+                        }
+                        uid = Some(value.to_string());
+                    }
+                }
+                _ if stack.is_empty() => return Err("Property found outside a component."),
+                _ => (),
+            }
+        }
+
+        if !has_root || !stack.is_empty() {
+            return Err("Incomplete object.");
+        }
+        if resource_type == DavResourceType::Calendar && component.is_none() {
+            return Err("Calendar object does not contain any components.");
+        }
+
+        Ok(DavObject {
+            uid: uid.filter(|uid| !uid.is_empty()).ok_or("Missing UID.")?,
+            component,
+        })
+    }
+}
+
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if let Some(rest) = line.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(rest);
+                continue;
+            }
+        }
+        lines.push(line.to_string());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{object::DavObject, DavResourceType};
+
+    #[test]
+    fn parse_objects() {
+        assert_eq!(
+            DavObject::parse(
+                DavResourceType::Calendar,
+                concat!(
+                    "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//Test//EN\r\n",
+                    "BEGIN:VTIMEZONE\r\nTZID:Europe/Madrid\r\nBEGIN:STANDARD\r\n",
+                    "DTSTART:19701025T030000\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\n",
+                    "BEGIN:VEVENT\r\nUID:event-1234@exam\r\n ple.org\r\nSUMMARY:Lunch\r\n",
+                    "BEGIN:VALARM\r\nACTION:DISPLAY\r\nEND:VALARM\r\nEND:VEVENT\r\n",
+                    "BEGIN:VEVENT\r\nUID:event-1234@example.org\r\nRECURRENCE-ID:20240101T120000Z\r\n",
+                    "END:VEVENT\r\nEND:VCALENDAR\r\n"
+                )
+                .as_bytes()
+            ),
+            Ok(DavObject {
+                uid: "event-1234@example.org".to_string(),
+                component: Some("VEVENT".to_string()),
+            })
+        );
+        assert_eq!(
+            DavObject::parse(
+                DavResourceType::AddressBook,
+                b"BEGIN:VCARD\nVERSION:4.0\nitem1.UID:urn:uuid:4fbe8971\nFN:Jane Doe\nEND:VCARD\n"
+            ),
+            Ok(DavObject {
+                uid: "urn:uuid:4fbe8971".to_string(),
+                component: None,
+            })
+        );
+
+        for (resource_type, object) in [
+            (DavResourceType::Calendar, "BEGIN:VCARD\nUID:1\nEND:VCARD\n"),
+            (
+                DavResourceType::Calendar,
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:1\nEND:VEVENT\n",
+            ),
+            (
+                DavResourceType::Calendar,
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\nEND:VEVENT\nEND:VCALENDAR\n",
+            ),
+            (
+                DavResourceType::Calendar,
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:1\nEND:VEVENT\nBEGIN:VTODO\nUID:1\nEND:VTODO\nEND:VCALENDAR\n",
+            ),
+            (
+                DavResourceType::Calendar,
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:1\nEND:VEVENT\nBEGIN:VEVENT\nUID:2\nEND:VEVENT\nEND:VCALENDAR\n",
+            ),
+            (DavResourceType::AddressBook, "BEGIN:VCARD\nFN:Jane\nEND:VCARD\n"),
+            (
+                DavResourceType::AddressBook,
+                "BEGIN:VCARD\nUID:1\nEND:VCARD\nBEGIN:VCARD\nUID:2\nEND:VCARD\n",
+            ),
+        ] {
+            assert!(
+                DavObject::parse(resource_type, object.as_bytes()).is_err(),
+                "{object}"
+            );
+        }
+    }
+}
@@ -0,0 +1,631 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use quick_xml::{
+    events::Event,
+    name::{Namespace, ResolveResult},
+    NsReader,
+};
+
+use crate::{DavResourceType, NS_CALDAV, NS_CALENDARSERVER, NS_CARDDAV, NS_DAV};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DavProperty {
+    // RFC 4918 and RFC 5397
+    ResourceType,
+    DisplayName,
+    GetETag,
+    GetContentType,
+    GetContentLength,
+    CurrentUserPrincipal,
+    PrincipalUrl,
+    Owner,
+    CurrentUserPrivilegeSet,
+    SupportedReportSet,
+    // RFC 6578
+    SyncToken,
+    // RFC 4791
+    CalendarHomeSet,
+    CalendarDescription,
+    CalendarData,
+    SupportedCalendarComponentSet,
+    // RFC 6352
+    AddressbookHomeSet,
+    AddressbookDescription,
+    AddressData,
+    // Calendar Server extensions
+    GetCTag,
+    Unknown { ns: String, name: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropFind {
+    AllProp,
+    PropName,
+    Prop(Vec<DavProperty>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PropPatch {
+    pub set: Vec<(DavProperty, String)>,
+    pub remove: Vec<DavProperty>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MkCol {
+    pub display_name: Option<String>,
+    pub resource_type: Option<DavResourceType>,
+}
+
+/// Access control entry of an ACL request (RFC 3744).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ace {
+    pub principal: String,
+    pub privileges: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Report {
+    SyncCollection {
+        sync_token: Option<String>,
+        properties: PropFind,
+    },
+    Multiget {
+        hrefs: Vec<String>,
+        properties: PropFind,
+    },
+    Query {
+        component: Option<String>,
+        properties: PropFind,
+    },
+}
+
+#[derive(Debug, Default)]
+struct XmlElement {
+    ns: String,
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    children: Vec<XmlElement>,
+}
+
+impl DavProperty {
+    pub fn from_xml(ns: &str, name: &str) -> Self {
+        match (ns, name) {
+            (NS_DAV, "resourcetype") => DavProperty::ResourceType,
+            (NS_DAV, "displayname") => DavProperty::DisplayName,
+            (NS_DAV, "getetag") => DavProperty::GetETag,
+            (NS_DAV, "getcontenttype") => DavProperty::GetContentType,
+            (NS_DAV, "getcontentlength") => DavProperty::GetContentLength,
+            (NS_DAV, "current-user-principal") => DavProperty::CurrentUserPrincipal,
+            (NS_DAV, "principal-URL") => DavProperty::PrincipalUrl,
+            (NS_DAV, "owner") => DavProperty::Owner,
+            (NS_DAV, "current-user-privilege-set") => DavProperty::CurrentUserPrivilegeSet,
+            (NS_DAV, "supported-report-set") => DavProperty::SupportedReportSet,
+            (NS_DAV, "sync-token") => DavProperty::SyncToken,
+            (NS_CALDAV, "calendar-home-set") => DavProperty::CalendarHomeSet,
+            (NS_CALDAV, "calendar-description") => DavProperty::CalendarDescription,
+            (NS_CALDAV, "calendar-data") => DavProperty::CalendarData,
+            (NS_CALDAV, "supported-calendar-component-set") => {
+                DavProperty::SupportedCalendarComponentSet
+            }
+            (NS_CARDDAV, "addressbook-home-set") => DavProperty::AddressbookHomeSet,
+            (NS_CARDDAV, "addressbook-description") => DavProperty::AddressbookDescription,
+            (NS_CARDDAV, "address-data") => DavProperty::AddressData,
+            (NS_CALENDARSERVER, "getctag") => DavProperty::GetCTag,
+            _ => DavProperty::Unknown {
+                ns: ns.to_string(),
+                name: name.to_string(),
+            },
+        }
+    }
+
+    pub fn namespace(&self) -> &str {
+        match self {
+            DavProperty::ResourceType
+            | DavProperty::DisplayName
+            | DavProperty::GetETag
+            | DavProperty::GetContentType
+            | DavProperty::GetContentLength
+            | DavProperty::CurrentUserPrincipal
+            | DavProperty::PrincipalUrl
+            | DavProperty::Owner
+            | DavProperty::CurrentUserPrivilegeSet
+            | DavProperty::SupportedReportSet
+            | DavProperty::SyncToken => NS_DAV,
+            DavProperty::CalendarHomeSet
+            | DavProperty::CalendarDescription
+            | DavProperty::CalendarData
+            | DavProperty::SupportedCalendarComponentSet => NS_CALDAV,
+            DavProperty::AddressbookHomeSet
+            | DavProperty::AddressbookDescription
+            | DavProperty::AddressData => NS_CARDDAV,
+            DavProperty::GetCTag => NS_CALENDARSERVER,
+            DavProperty::Unknown { ns, .. } => ns,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            DavProperty::ResourceType => "resourcetype",
+            DavProperty::DisplayName => "displayname",
+            DavProperty::GetETag => "getetag",
+            DavProperty::GetContentType => "getcontenttype",
+            DavProperty::GetContentLength => "getcontentlength",
+            DavProperty::CurrentUserPrincipal => "current-user-principal",
+            DavProperty::PrincipalUrl => "principal-URL",
+            DavProperty::Owner => "owner",
+            DavProperty::CurrentUserPrivilegeSet => "current-user-privilege-set",
+            DavProperty::SupportedReportSet => "supported-report-set",
+            DavProperty::SyncToken => "sync-token",
+            DavProperty::CalendarHomeSet => "calendar-home-set",
+            DavProperty::CalendarDescription => "calendar-description",
+            DavProperty::CalendarData => "calendar-data",
+            DavProperty::SupportedCalendarComponentSet => "supported-calendar-component-set",
+            DavProperty::AddressbookHomeSet => "addressbook-home-set",
+            DavProperty::AddressbookDescription => "addressbook-description",
+            DavProperty::AddressData => "address-data",
+            DavProperty::GetCTag => "getctag",
+            DavProperty::Unknown { name, .. } => name,
+        }
+    }
+}
+
+impl PropFind {
+    /// Parses a PROPFIND body. An empty body is treated as an allprop request.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.iter().all(|ch| ch.is_ascii_whitespace()) {
+            return Ok(PropFind::AllProp);
+        }
+
+        let root = XmlElement::parse(bytes)?;
+        if !root.is(NS_DAV, "propfind") {
+            return Err(format!("Expected propfind element, found {:?}.", root.name));
+        }
+        PropFind::from_children(&root).ok_or_else(|| "Missing prop element.".to_string())
+    }
+
+    fn from_children(element: &XmlElement) -> Option<Self> {
+        for child in &element.children {
+            if child.ns == NS_DAV {
+                match child.name.as_str() {
+                    "allprop" => return Some(PropFind::AllProp),
+                    "propname" => return Some(PropFind::PropName),
+                    "prop" => return Some(PropFind::Prop(child.properties())),
+                    _ => (),
+                }
+            }
+        }
+        None
+    }
+}
+
+impl PropPatch {
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let root = XmlElement::parse(bytes)?;
+        if !root.is(NS_DAV, "propertyupdate") {
+            return Err(format!(
+                "Expected propertyupdate element, found {:?}.",
+                root.name
+            ));
+        }
+
+        let mut patch = PropPatch::default();
+        for child in &root.children {
+            let is_set = match (child.ns.as_str(), child.name.as_str()) {
+                (NS_DAV, "set") => true,
+                (NS_DAV, "remove") => false,
+                _ => continue,
+            };
+            for prop in child.children.iter().filter(|e| e.is(NS_DAV, "prop")) {
+                for item in &prop.children {
+                    let property = DavProperty::from_xml(&item.ns, &item.name);
+                    if is_set {
+                        patch.set.push((property, item.text.clone()));
+                    } else {
+                        patch.remove.push(property);
+                    }
+                }
+            }
+        }
+
+        Ok(patch)
+    }
+}
+
+impl MkCol {
+    /// Parses an extended MKCOL (RFC 5689) or MKCALENDAR body.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut mkcol = MkCol::default();
+        if bytes.iter().all(|ch| ch.is_ascii_whitespace()) {
+            return Ok(mkcol);
+        }
+
+        let root = XmlElement::parse(bytes)?;
+        if !root.is(NS_DAV, "mkcol") && !root.is(NS_CALDAV, "mkcalendar") {
+            return Err(format!("Expected mkcol element, found {:?}.", root.name));
+        }
+
+        for prop in root
+            .children
+            .iter()
+            .filter(|e| e.is(NS_DAV, "set"))
+            .flat_map(|e| e.children.iter())
+            .filter(|e| e.is(NS_DAV, "prop"))
+            .flat_map(|e| e.children.iter())
+        {
+            match DavProperty::from_xml(&prop.ns, &prop.name) {
+                DavProperty::DisplayName => {
+                    mkcol.display_name = Some(prop.text.clone());
+                }
+                DavProperty::ResourceType => {
+                    for item in &prop.children {
+                        if item.is(NS_CALDAV, "calendar") {
+                            mkcol.resource_type = Some(DavResourceType::Calendar);
+                        } else if item.is(NS_CARDDAV, "addressbook") {
+                            mkcol.resource_type = Some(DavResourceType::AddressBook);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(mkcol)
+    }
+}
+
+impl Ace {
+    /// Parses the body of an ACL request. Only grants to principal hrefs
+    /// are supported, as privileges are mapped to per-account grants.
+    pub fn parse(bytes: &[u8]) -> Result<Vec<Self>, String> {
+        let root = XmlElement::parse(bytes)?;
+        if !root.is(NS_DAV, "acl") {
+            return Err(format!("Expected acl element, found {:?}.", root.name));
+        }
+
+        let mut aces = Vec::new();
+        for ace in root.children.iter().filter(|e| e.is(NS_DAV, "ace")) {
+            let mut principal = None;
+            let mut privileges = Vec::new();
+            for child in &ace.children {
+                match (child.ns.as_str(), child.name.as_str()) {
+                    (NS_DAV, "principal") => {
+                        principal = child
+                            .children
+                            .iter()
+                            .find(|e| e.is(NS_DAV, "href"))
+                            .map(|e| e.text.trim().to_string());
+                    }
+                    (NS_DAV, "grant") => {
+                        privileges.extend(
+                            child
+                                .children
+                                .iter()
+                                .filter(|e| e.is(NS_DAV, "privilege"))
+                                .flat_map(|e| e.children.iter())
+                                .map(|e| e.name.clone()),
+                        );
+                    }
+                    (NS_DAV, "deny") => {
+                        return Err("Deny ACEs are not supported.".to_string());
+                    }
+                    _ => (),
+                }
+            }
+
+            aces.push(Ace {
+                principal: principal
+                    .ok_or_else(|| "Only principal hrefs are supported.".to_string())?,
+                privileges,
+            });
+        }
+
+        Ok(aces)
+    }
+}
+
+impl Report {
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let root = XmlElement::parse(bytes)?;
+        let properties = PropFind::from_children(&root).unwrap_or(PropFind::AllProp);
+
+        match (root.ns.as_str(), root.name.as_str()) {
+            (NS_DAV, "sync-collection") => Ok(Report::SyncCollection {
+                sync_token: root
+                    .children
+                    .iter()
+                    .find(|e| e.is(NS_DAV, "sync-token"))
+                    .map(|e| e.text.trim().to_string())
+                    .filter(|token| !token.is_empty()),
+                properties,
+            }),
+            (NS_CALDAV, "calendar-multiget") | (NS_CARDDAV, "addressbook-multiget") => {
+                Ok(Report::Multiget {
+                    hrefs: root
+                        .children
+                        .iter()
+                        .filter(|e| e.is(NS_DAV, "href"))
+                        .map(|e| e.text.trim().to_string())
+                        .collect(),
+                    properties,
+                })
+            }
+            (NS_CALDAV, "calendar-query") => {
+                // Only the component type of the filter is evaluated,
+                // i.e. <comp-filter name="VCALENDAR"><comp-filter name="VEVENT">
+                let component = root
+                    .children
+                    .iter()
+                    .filter(|e| e.is(NS_CALDAV, "filter"))
+                    .flat_map(|e| e.children.iter())
+                    .filter(|e| e.is(NS_CALDAV, "comp-filter"))
+                    .flat_map(|e| e.children.iter())
+                    .find(|e| e.is(NS_CALDAV, "comp-filter"))
+                    .and_then(|e| e.attribute("name"))
+                    .map(|name| name.to_ascii_uppercase());
+                Ok(Report::Query {
+                    component,
+                    properties,
+                })
+            }
+            (NS_CARDDAV, "addressbook-query") => Ok(Report::Query {
+                component: None,
+                properties,
+            }),
+            _ => Err(format!("Unsupported report {:?}.", root.name)),
+        }
+    }
+}
+
+impl XmlElement {
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = NsReader::from_reader(bytes);
+        reader.trim_text(true);
+        let mut stack: Vec<XmlElement> = Vec::new();
+
+        loop {
+            let element = match reader.read_resolved_event() {
+                Ok((ns, Event::Start(tag))) => {
+                    stack.push(XmlElement::new(ns, &tag)?);
+                    continue;
+                }
+                Ok((ns, Event::Empty(tag))) => XmlElement::new(ns, &tag)?,
+                Ok((_, Event::End(_))) => stack
+                    .pop()
+                    .ok_or_else(|| "Unexpected closing tag.".to_string())?,
+                Ok((_, Event::Text(text))) => {
+                    if let Some(element) = stack.last_mut() {
+                        element
+                            .text
+                            .push_str(&text.unescape().map_err(|err| err.to_string())?);
+                    }
+                    continue;
+                }
+                Ok((_, Event::CData(data))) => {
+                    if let Some(element) = stack.last_mut() {
+                        element
+                            .text
+                            .push_str(&String::from_utf8_lossy(&data.into_inner()));
+                    }
+                    continue;
+                }
+                Ok((_, Event::Eof)) => return Err("Unexpected end of document.".to_string()),
+                Ok(_) => continue,
+                Err(err) => return Err(err.to_string()),
+            };
+
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(element);
+            } else {
+                return Ok(element);
+            }
+        }
+    }
+
+    fn new(ns: ResolveResult, tag: &quick_xml::events::BytesStart) -> Result<Self, String> {
+        let mut attributes = Vec::new();
+        for attribute in tag.attributes() {
+            let attribute = attribute.map_err(|err| err.to_string())?;
+            attributes.push((
+                String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(),
+                attribute
+                    .unescape_value()
+                    .map_err(|err| err.to_string())?
+                    .into_owned(),
+            ));
+        }
+
+        Ok(XmlElement {
+            ns: match ns {
+                ResolveResult::Bound(Namespace(ns)) => String::from_utf8_lossy(ns).into_owned(),
+                _ => String::new(),
+            },
+            name: String::from_utf8_lossy(tag.local_name().as_ref()).into_owned(),
+            attributes,
+            text: String::new(),
+            children: Vec::new(),
+        })
+    }
+
+    fn is(&self, ns: &str, name: &str) -> bool {
+        self.ns == ns && self.name == name
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn properties(&self) -> Vec<DavProperty> {
+        self.children
+            .iter()
+            .map(|e| DavProperty::from_xml(&e.ns, &e.name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        request::{Ace, DavProperty, MkCol, PropFind, PropPatch, Report},
+        DavResourceType,
+    };
+
+    #[test]
+    fn parse_propfind() {
+        assert_eq!(PropFind::parse(b"").unwrap(), PropFind::AllProp);
+        assert_eq!(
+            PropFind::parse(
+                br#"<?xml version="1.0" encoding="utf-8" ?>
+                <D:propfind xmlns:D="DAV:" xmlns:CS="http://calendarserver.org/ns/">
+                    <D:prop>
+                        <D:resourcetype/>
+                        <D:getetag/>
+                        <CS:getctag/>
+                        <X:color xmlns:X="http://apple.com/ns/ical/"/>
+                    </D:prop>
+                </D:propfind>"#
+            )
+            .unwrap(),
+            PropFind::Prop(vec![
+                DavProperty::ResourceType,
+                DavProperty::GetETag,
+                DavProperty::GetCTag,
+                DavProperty::Unknown {
+                    ns: "http://apple.com/ns/ical/".to_string(),
+                    name: "color".to_string()
+                },
+            ])
+        );
+        assert!(PropFind::parse(b"<propfind><prop>").is_err());
+    }
+
+    #[test]
+    fn parse_proppatch_and_mkcol() {
+        assert_eq!(
+            PropPatch::parse(
+                br#"<D:propertyupdate xmlns:D="DAV:">
+                    <D:set><D:prop><D:displayname>Work &amp; Play</D:displayname></D:prop></D:set>
+                    <D:remove><D:prop><D:owner/></D:prop></D:remove>
+                </D:propertyupdate>"#
+            )
+            .unwrap(),
+            PropPatch {
+                set: vec![(DavProperty::DisplayName, "Work & Play".to_string())],
+                remove: vec![DavProperty::Owner],
+            }
+        );
+        assert_eq!(
+            MkCol::parse(
+                br#"<C:mkcalendar xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+                    <D:set><D:prop>
+                        <D:resourcetype><D:collection/><C:calendar/></D:resourcetype>
+                        <D:displayname>Holidays</D:displayname>
+                    </D:prop></D:set>
+                </C:mkcalendar>"#
+            )
+            .unwrap(),
+            MkCol {
+                display_name: Some("Holidays".to_string()),
+                resource_type: Some(DavResourceType::Calendar),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_acl() {
+        assert_eq!(
+            Ace::parse(
+                br#"<D:acl xmlns:D="DAV:">
+                    <D:ace>
+                        <D:principal><D:href>/dav/principal/jane/</D:href></D:principal>
+                        <D:grant><D:privilege><D:read/></D:privilege><D:privilege><D:write/></D:privilege></D:grant>
+                    </D:ace>
+                </D:acl>"#
+            )
+            .unwrap(),
+            vec![Ace {
+                principal: "/dav/principal/jane/".to_string(),
+                privileges: vec!["read".to_string(), "write".to_string()],
+            }]
+        );
+        assert!(Ace::parse(
+            br#"<D:acl xmlns:D="DAV:"><D:ace><D:principal><D:all/></D:principal>
+                <D:grant><D:privilege><D:read/></D:privilege></D:grant></D:ace></D:acl>"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_report() {
+        assert_eq!(
+            Report::parse(
+                br#"<D:sync-collection xmlns:D="DAV:">
+                    <D:sync-token>urn:stalwart:sync:1234</D:sync-token>
+                    <D:sync-level>1</D:sync-level>
+                    <D:prop><D:getetag/></D:prop>
+                </D:sync-collection>"#
+            )
+            .unwrap(),
+            Report::SyncCollection {
+                sync_token: Some("urn:stalwart:sync:1234".to_string()),
+                properties: PropFind::Prop(vec![DavProperty::GetETag]),
+            }
+        );
+        assert_eq!(
+            Report::parse(
+                br#"<C:calendar-multiget xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+                    <D:prop><D:getetag/><C:calendar-data/></D:prop>
+                    <D:href>/dav/cal/john/default/a.ics</D:href>
+                    <D:href>/dav/cal/john/default/b.ics</D:href>
+                </C:calendar-multiget>"#
+            )
+            .unwrap(),
+            Report::Multiget {
+                hrefs: vec![
+                    "/dav/cal/john/default/a.ics".to_string(),
+                    "/dav/cal/john/default/b.ics".to_string()
+                ],
+                properties: PropFind::Prop(vec![DavProperty::GetETag, DavProperty::CalendarData]),
+            }
+        );
+        assert_eq!(
+            Report::parse(
+                br#"<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+                    <D:prop><D:getetag/></D:prop>
+                    <C:filter>
+                        <C:comp-filter name="VCALENDAR"><C:comp-filter name="vtodo"/></C:comp-filter>
+                    </C:filter>
+                </C:calendar-query>"#
+            )
+            .unwrap(),
+            Report::Query {
+                component: Some("VTODO".to_string()),
+                properties: PropFind::Prop(vec![DavProperty::GetETag]),
+            }
+        );
+    }
+}
@@ -0,0 +1,321 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::fmt::{self, Display};
+
+use quick_xml::escape::escape;
+
+use crate::{
+    request::DavProperty, DavResourceType, NS_CALDAV, NS_CALENDARSERVER, NS_CARDDAV, NS_DAV,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MultiStatus {
+    pub responses: Vec<Response>,
+    pub sync_token: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub href: String,
+    pub status: Option<u16>,
+    pub propstat: Vec<PropStat>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropStat {
+    pub status: u16,
+    pub properties: Vec<(DavProperty, PropValue)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropValue {
+    Empty,
+    Text(String),
+    Href(String),
+    ResourceType(Option<DavResourceType>),
+    Principal,
+    Components(Vec<&'static str>),
+    Privileges(Vec<&'static str>),
+    Reports(Vec<(&'static str, &'static str)>),
+}
+
+/// Precondition and postcondition codes returned in DAV:error bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    ValidSyncToken,
+    NoUidConflict(DavResourceType),
+    ValidData(DavResourceType),
+    MaxResourceSize(DavResourceType),
+    NotSupportedPrivilege,
+    RecognizedPrincipal,
+}
+
+impl MultiStatus {
+    pub fn new(responses: Vec<Response>) -> Self {
+        MultiStatus {
+            responses,
+            sync_token: None,
+        }
+    }
+
+    pub fn with_sync_token(mut self, sync_token: impl Into<String>) -> Self {
+        self.sync_token = Some(sync_token.into());
+        self
+    }
+}
+
+impl Response {
+    pub fn with_status(href: impl Into<String>, status: u16) -> Self {
+        Response {
+            href: href.into(),
+            status: status.into(),
+            propstat: Vec::new(),
+        }
+    }
+
+    pub fn with_propstat(href: impl Into<String>, propstat: Vec<PropStat>) -> Self {
+        Response {
+            href: href.into(),
+            status: None,
+            propstat,
+        }
+    }
+}
+
+impl PropStat {
+    pub fn new(status: u16) -> Self {
+        PropStat {
+            status,
+            properties: Vec::new(),
+        }
+    }
+}
+
+impl Display for MultiStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+                "<D:multistatus xmlns:D=\"{}\" xmlns:C=\"{}\" xmlns:CR=\"{}\" xmlns:CS=\"{}\">"
+            ),
+            NS_DAV, NS_CALDAV, NS_CARDDAV, NS_CALENDARSERVER
+        )?;
+        for response in &self.responses {
+            write!(f, "<D:response><D:href>{}</D:href>", escape(&response.href))?;
+            if let Some(status) = response.status {
+                write_status(f, status)?;
+            }
+            for propstat in &response.propstat {
+                f.write_str("<D:propstat><D:prop>")?;
+                for (property, value) in &propstat.properties {
+                    write_property(f, property, value)?;
+                }
+                f.write_str("</D:prop>")?;
+                write_status(f, propstat.status)?;
+                f.write_str("</D:propstat>")?;
+            }
+            f.write_str("</D:response>")?;
+        }
+        if let Some(sync_token) = &self.sync_token {
+            write!(f, "<D:sync-token>{}</D:sync-token>", escape(sync_token))?;
+        }
+        f.write_str("</D:multistatus>")
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (prefix, name) = match self {
+            Condition::ValidSyncToken => ("D", "valid-sync-token"),
+            Condition::NotSupportedPrivilege => ("D", "not-supported-privilege"),
+            Condition::RecognizedPrincipal => ("D", "recognized-principal"),
+            Condition::NoUidConflict(DavResourceType::Calendar) => ("C", "no-uid-conflict"),
+            Condition::NoUidConflict(DavResourceType::AddressBook) => ("CR", "no-uid-conflict"),
+            Condition::ValidData(DavResourceType::Calendar) => ("C", "valid-calendar-data"),
+            Condition::ValidData(DavResourceType::AddressBook) => ("CR", "valid-address-data"),
+            Condition::MaxResourceSize(DavResourceType::Calendar) => ("C", "max-resource-size"),
+            Condition::MaxResourceSize(DavResourceType::AddressBook) => ("CR", "max-resource-size"),
+        };
+        write!(
+            f,
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+                "<D:error xmlns:D=\"{}\" xmlns:C=\"{}\" xmlns:CR=\"{}\"><{}:{}/></D:error>"
+            ),
+            NS_DAV, NS_CALDAV, NS_CARDDAV, prefix, name
+        )
+    }
+}
+
+fn write_status(f: &mut fmt::Formatter<'_>, status: u16) -> fmt::Result {
+    write!(
+        f,
+        "<D:status>HTTP/1.1 {} {}</D:status>",
+        status,
+        reason(status)
+    )
+}
+
+fn write_property(
+    f: &mut fmt::Formatter<'_>,
+    property: &DavProperty,
+    value: &PropValue,
+) -> fmt::Result {
+    let prefix = match property.namespace() {
+        NS_DAV => "D",
+        NS_CALDAV => "C",
+        NS_CARDDAV => "CR",
+        NS_CALENDARSERVER => "CS",
+        ns => {
+            // Unknown properties are returned with their own namespace
+            f.write_str("<X:")?;
+            f.write_str(&escape(property.name()))?;
+            write!(f, " xmlns:X=\"{}\"", escape(ns))?;
+            return f.write_str("/>");
+        }
+    };
+    let name = property.name();
+
+    if matches!(value, PropValue::Empty) {
+        return write!(f, "<{prefix}:{name}/>");
+    }
+
+    write!(f, "<{prefix}:{name}>")?;
+    match value {
+        PropValue::Empty => (),
+        PropValue::Text(text) => f.write_str(&escape(text))?,
+        PropValue::Href(href) => write!(f, "<D:href>{}</D:href>", escape(href))?,
+        PropValue::ResourceType(resource_type) => {
+            f.write_str("<D:collection/>")?;
+            match resource_type {
+                Some(DavResourceType::Calendar) => f.write_str("<C:calendar/>")?,
+                Some(DavResourceType::AddressBook) => f.write_str("<CR:addressbook/>")?,
+                None => (),
+            }
+        }
+        PropValue::Principal => f.write_str("<D:principal/>")?,
+        PropValue::Components(components) => {
+            for component in components {
+                write!(f, "<C:comp name=\"{component}\"/>")?;
+            }
+        }
+        PropValue::Privileges(privileges) => {
+            for privilege in privileges {
+                write!(f, "<D:privilege><D:{privilege}/></D:privilege>")?;
+            }
+        }
+        PropValue::Reports(reports) => {
+            for (prefix, report) in reports {
+                write!(
+                    f,
+                    "<D:supported-report><D:report><{prefix}:{report}/></D:report></D:supported-report>"
+                )?;
+            }
+        }
+    }
+    write!(f, "</{prefix}:{name}>")
+}
+
+pub fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        207 => "Multi-Status",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        412 => "Precondition Failed",
+        424 => "Failed Dependency",
+        507 => "Insufficient Storage",
+        _ => "Unknown",
+    }
+}
+
+impl PropValue {
+    pub fn text(value: impl ToString) -> Self {
+        PropValue::Text(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        request::DavProperty,
+        response::{Condition, MultiStatus, PropStat, PropValue, Response},
+        DavResourceType,
+    };
+
+    #[test]
+    fn serialize_multistatus() {
+        let mut found = PropStat::new(200);
+        found.properties = vec![
+            (
+                DavProperty::ResourceType,
+                PropValue::ResourceType(Some(DavResourceType::Calendar)),
+            ),
+            (DavProperty::DisplayName, PropValue::text("Work & Play")),
+            (
+                DavProperty::SupportedCalendarComponentSet,
+                PropValue::Components(vec!["VEVENT"]),
+            ),
+        ];
+        let mut not_found = PropStat::new(404);
+        not_found.properties = vec![(
+            DavProperty::Unknown {
+                ns: "http://apple.com/ns/ical/".to_string(),
+                name: "calendar-color".to_string(),
+            },
+            PropValue::Empty,
+        )];
+
+        assert_eq!(
+            MultiStatus::new(vec![
+                Response::with_propstat("/dav/cal/john/default/", vec![found, not_found]),
+                Response::with_status("/dav/cal/john/default/old.ics", 404),
+            ])
+            .with_sync_token("urn:stalwart:sync:10")
+            .to_string(),
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?><D:multistatus xmlns:D=\"DAV:\" ",
+                "xmlns:C=\"urn:ietf:params:xml:ns:caldav\" xmlns:CR=\"urn:ietf:params:xml:ns:carddav\" ",
+                "xmlns:CS=\"http://calendarserver.org/ns/\"><D:response><D:href>/dav/cal/john/default/</D:href>",
+                "<D:propstat><D:prop><D:resourcetype><D:collection/><C:calendar/></D:resourcetype>",
+                "<D:displayname>Work &amp; Play</D:displayname><C:supported-calendar-component-set>",
+                "<C:comp name=\"VEVENT\"/></C:supported-calendar-component-set></D:prop>",
+                "<D:status>HTTP/1.1 200 OK</D:status></D:propstat><D:propstat><D:prop>",
+                "<X:calendar-color xmlns:X=\"http://apple.com/ns/ical/\"/></D:prop>",
+                "<D:status>HTTP/1.1 404 Not Found</D:status></D:propstat></D:response>",
+                "<D:response><D:href>/dav/cal/john/default/old.ics</D:href>",
+                "<D:status>HTTP/1.1 404 Not Found</D:status></D:response>",
+                "<D:sync-token>urn:stalwart:sync:10</D:sync-token></D:multistatus>"
+            )
+        );
+        assert!(Condition::NoUidConflict(DavResourceType::AddressBook)
+            .to_string()
+            .contains("<CR:no-uid-conflict/>"));
+    }
+}
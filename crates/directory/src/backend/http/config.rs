@@ -0,0 +1,76 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use store::Store;
+use utils::config::{utils::AsKey, Config};
+
+use super::{HttpAuth, HttpDirectory};
+
+impl HttpDirectory {
+    pub fn from_config(config: &mut Config, prefix: impl AsKey, data_store: Store) -> Option<Self> {
+        let prefix = prefix.as_key();
+        let url = config.value_require((&prefix, "url"))?.to_string();
+        let auth = if let Some(token) = config.value((&prefix, "auth.token")) {
+            HttpAuth::Bearer(token.to_string())
+        } else if let Some(username) = config.value((&prefix, "auth.username")) {
+            HttpAuth::Basic {
+                username: username.to_string(),
+                secret: config
+                    .value((&prefix, "auth.secret"))
+                    .unwrap_or_default()
+                    .to_string(),
+            }
+        } else {
+            HttpAuth::None
+        };
+
+        let client = reqwest::Client::builder()
+            .timeout(
+                config
+                    .property_or_default((&prefix, "timeout"), "15s")
+                    .unwrap_or_else(|| Duration::from_secs(15)),
+            )
+            .danger_accept_invalid_certs(
+                config
+                    .property_or_default((&prefix, "tls.allow-invalid-certs"), "false")
+                    .unwrap_or_default(),
+            )
+            .build()
+            .map_err(|err| {
+                config.new_parse_error(
+                    prefix.as_str(),
+                    format!("Failed to build HTTP client: {err}"),
+                )
+            })
+            .ok()?;
+
+        Some(HttpDirectory {
+            client,
+            url,
+            auth,
+            data_store,
+        })
+    }
+}
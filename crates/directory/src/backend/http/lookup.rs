@@ -0,0 +1,206 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use mail_send::Credentials;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use crate::{backend::internal::manage::ManageDirectory, DirectoryError, Principal, QueryBy};
+
+use super::{HttpAuth, HttpDirectory, HttpPrincipal};
+
+impl HttpDirectory {
+    pub async fn query(
+        &self,
+        by: QueryBy<'_>,
+        return_member_of: bool,
+    ) -> crate::Result<Option<Principal<u32>>> {
+        let (request, account_id) = match by {
+            QueryBy::Name(name) => (json!({"action": "lookup", "name": name}), None),
+            QueryBy::Id(account_id) => {
+                if let Some(name) = self.data_store.get_account_name(account_id).await? {
+                    (json!({"action": "lookup", "name": name}), Some(account_id))
+                } else {
+                    return Ok(None);
+                }
+            }
+            QueryBy::Credentials(
+                Credentials::Plain { username, secret } | Credentials::XOauth2 { username, secret },
+            ) => (
+                json!({"action": "authenticate", "username": username, "secret": secret}),
+                None,
+            ),
+            QueryBy::Credentials(Credentials::OAuthBearer { token }) => {
+                (json!({"action": "authenticate", "token": token}), None)
+            }
+        };
+
+        let result = if let Some(result) = self.send(&request).await? {
+            serde_json::from_value::<HttpPrincipal>(result).map_err(|err| {
+                DirectoryError::Http(format!("Failed to parse HTTP directory response: {err}"))
+            })?
+        } else {
+            return Ok(None);
+        };
+        if result.name.is_empty() {
+            tracing::debug!(
+                context = "directory",
+                event = "invalid_response",
+                protocol = "http",
+                "Principal name not found in response"
+            );
+            return Ok(None);
+        }
+
+        let mut principal = Principal {
+            id: match account_id {
+                Some(account_id) => account_id,
+                None => {
+                    self.data_store
+                        .get_or_create_account_id(&result.name)
+                        .await?
+                }
+            },
+            typ: result.typ,
+            quota: result.quota,
+            name: result.name,
+            secrets: vec![],
+            emails: result.emails,
+            member_of: result.member_of,
+            description: result.description,
+            protocols: result
+                .protocols
+                .into_iter()
+                .map(|v| v.to_lowercase())
+                .collect(),
+        };
+
+        if return_member_of {
+            self.data_store
+                .map_principal(principal, true)
+                .await
+                .map(Some)
+        } else {
+            principal.member_of.clear();
+            Ok(Some(principal.into()))
+        }
+    }
+
+    pub async fn email_to_ids(&self, address: &str) -> crate::Result<Vec<u32>> {
+        let mut ids = Vec::new();
+        for name in self
+            .send_list(
+                &json!({"action": "email_to_names", "address": address}),
+                "names",
+            )
+            .await?
+        {
+            ids.push(self.data_store.get_or_create_account_id(&name).await?);
+        }
+
+        Ok(ids)
+    }
+
+    pub async fn rcpt(&self, address: &str) -> crate::Result<bool> {
+        self.send_bool(&json!({"action": "rcpt", "address": address}))
+            .await
+    }
+
+    pub async fn vrfy(&self, address: &str) -> crate::Result<Vec<String>> {
+        self.send_list(&json!({"action": "vrfy", "address": address}), "addresses")
+            .await
+    }
+
+    pub async fn expn(&self, address: &str) -> crate::Result<Vec<String>> {
+        self.send_list(&json!({"action": "expn", "address": address}), "addresses")
+            .await
+    }
+
+    pub async fn is_local_domain(&self, domain: &str) -> crate::Result<bool> {
+        self.send_bool(&json!({"action": "is_local_domain", "domain": domain}))
+            .await
+    }
+
+    async fn send_bool(&self, request: &Value) -> crate::Result<bool> {
+        Ok(self
+            .send(request)
+            .await?
+            .and_then(|response| response.get("exists").and_then(|v| v.as_bool()))
+            .unwrap_or_default())
+    }
+
+    async fn send_list(&self, request: &Value, field: &str) -> crate::Result<Vec<String>> {
+        Ok(self
+            .send(request)
+            .await?
+            .and_then(|mut response| response.get_mut(field).map(|v| v.take()))
+            .and_then(|values| match values {
+                Value::Array(values) => values
+                    .into_iter()
+                    .map(|v| match v {
+                        Value::String(v) => Some(v),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>(),
+                _ => None,
+            })
+            .unwrap_or_default())
+    }
+
+    async fn send(&self, request: &Value) -> crate::Result<Option<Value>> {
+        let builder = self.client.post(&self.url).json(request);
+        let builder = match &self.auth {
+            HttpAuth::None => builder,
+            HttpAuth::Bearer(token) => builder.bearer_auth(token),
+            HttpAuth::Basic { username, secret } => builder.basic_auth(username, Some(secret)),
+        };
+
+        let response = builder.send().await?;
+        match response.status() {
+            StatusCode::NO_CONTENT | StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                tracing::debug!(
+                    context = "directory",
+                    event = "invalid_password",
+                    protocol = "http",
+                    status = response.status().as_u16(),
+                    "HTTP directory rejected credentials"
+                );
+                Ok(None)
+            }
+            status if status.is_success() => {
+                let bytes = response.bytes().await?;
+                serde_json::from_slice::<Value>(&bytes)
+                    .map(|value| Some(value).filter(|value| !value.is_null()))
+                    .map_err(|err| {
+                        DirectoryError::Http(format!(
+                            "Failed to parse HTTP directory response: {err}"
+                        ))
+                    })
+            }
+            status => Err(DirectoryError::Http(format!(
+                "Unexpected HTTP directory response status: {status}"
+            ))),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+// Lookups are sent as a JSON object with an "action" field to a single URL:
+//
+//   {"action": "lookup", "name": "john"}
+//   {"action": "authenticate", "username": "john", "secret": "..."}
+//   {"action": "authenticate", "token": "..."}
+//   {"action": "email_to_names", "address": "john@example.org"}
+//   {"action": "rcpt", "address": "john@example.org"}
+//   {"action": "is_local_domain", "domain": "example.org"}
+//   {"action": "vrfy", "address": "john"}
+//   {"action": "expn", "address": "sales@example.org"}
+//
+// Principal lookups return a Principal object or 404 when not found, while
+// the remaining actions return {"names": [...]}, {"exists": bool} or
+// {"addresses": [...]} respectively.
+
+pub mod config;
+pub mod lookup;
+
+use store::Store;
+
+pub struct HttpDirectory {
+    client: reqwest::Client,
+    url: String,
+    auth: HttpAuth,
+    pub(crate) data_store: Store,
+}
+
+#[derive(Debug, Default)]
+enum HttpAuth {
+    #[default]
+    None,
+    Bearer(String),
+    Basic {
+        username: String,
+        secret: String,
+    },
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct HttpPrincipal {
+    name: String,
+    #[serde(rename = "type")]
+    typ: crate::Type,
+    description: Option<String>,
+    quota: u64,
+    emails: Vec<String>,
+    #[serde(rename = "memberOf", alias = "member_of")]
+    member_of: Vec<String>,
+    protocols: Vec<String>,
+}
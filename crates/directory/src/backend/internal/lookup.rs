@@ -23,11 +23,11 @@
 
 use mail_send::Credentials;
 use store::{
-    write::{DirectoryClass, ValueClass},
+    write::{now, DirectoryClass, ValueClass},
     IterateParams, Store, ValueKey,
 };
 
-use crate::{Principal, QueryBy, Type};
+use crate::{core::app_password::APP_PASSWORD_USE_INTERVAL, Principal, QueryBy, Type};
 
 use super::{manage::ManageDirectory, PrincipalIdType};
 
@@ -76,7 +76,40 @@ impl DirectoryStore for Store {
                 .await?,
                 secret,
             ) {
-                (Some(mut principal), Some(secret)) if principal.verify_secret(secret).await => {
+                (Some(mut principal), Some(secret)) => {
+                    // Application passwords are cheaper to verify, try them first
+                    if let Some(app_password) = principal.app_password(secret) {
+                        if !principal.restrict_to(&app_password) {
+                            return Ok(None);
+                        }
+
+                        // Update the last used timestamp in the background
+                        let now = now();
+                        if app_password.last_used + APP_PASSWORD_USE_INTERVAL <= now {
+                            let store = self.clone();
+                            tokio::spawn(async move {
+                                if let Err(err) = store
+                                    .update_app_password_last_used(
+                                        account_id,
+                                        &app_password.name,
+                                        now,
+                                    )
+                                    .await
+                                {
+                                    tracing::warn!(
+                                        context = "directory",
+                                        event = "error",
+                                        account_id = account_id,
+                                        reason = ?err,
+                                        "Failed to update application password last use."
+                                    );
+                                }
+                            });
+                        }
+                    } else if !principal.verify_secret(secret).await {
+                        return Ok(None);
+                    }
+
                     if return_member_of {
                         principal.member_of = self.get_member_of(principal.id).await?;
                     }
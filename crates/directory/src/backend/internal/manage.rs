@@ -30,7 +30,14 @@ use store::{
     Deserialize, IterateParams, Serialize, Store, ValueKey, U32_LEN,
 };
 
-use crate::{DirectoryError, ManagementError, Principal, QueryBy, Type};
+use crate::{
+    core::{
+        app_password::{is_app_password, AppPassword},
+        scram::{is_scram_verifier, ScramVerifier},
+        secret::{hash_secret, is_weak_secret_hash, verify_secret_hash, SecretHash},
+    },
+    DirectoryError, ManagementError, Principal, QueryBy, Type,
+};
 
 use super::{
     lookup::DirectoryStore, PrincipalAction, PrincipalField, PrincipalIdType, PrincipalUpdate,
@@ -55,6 +62,26 @@ pub trait ManageDirectory: Sized {
         changes: Vec<PrincipalUpdate>,
     ) -> crate::Result<()>;
     async fn delete_account(&self, by: QueryBy<'_>) -> crate::Result<()>;
+    async fn update_secret_if_weaker(
+        &self,
+        account_id: u32,
+        secret: &str,
+        algorithm: SecretHash,
+    ) -> crate::Result<bool>;
+    async fn update_scram_verifier(&self, account_id: u32, secret: &str) -> crate::Result<bool>;
+    async fn add_app_password(
+        &self,
+        account_id: u32,
+        app_password: &AppPassword,
+    ) -> crate::Result<()>;
+    async fn remove_app_password(&self, account_id: u32, name: &str) -> crate::Result<()>;
+    async fn change_password(&self, account_id: u32, secret: &str) -> crate::Result<()>;
+    async fn update_app_password_last_used(
+        &self,
+        account_id: u32,
+        name: &str,
+        last_used: u64,
+    ) -> crate::Result<bool>;
     async fn list_accounts(
         &self,
         filter: Option<&str>,
@@ -427,8 +454,23 @@ impl ManageDirectory for Store {
                 (
                     PrincipalAction::Set,
                     PrincipalField::Secrets,
-                    PrincipalValue::StringList(secrets),
+                    PrincipalValue::StringList(mut secrets),
                 ) => {
+                    // SCRAM verifiers are derived from the other secrets, drop the
+                    // existing ones when those change.
+                    if secrets
+                        .iter()
+                        .filter(|s| !is_scram_verifier(s) && !is_app_password(s))
+                        .ne(principal
+                            .inner
+                            .secrets
+                            .iter()
+                            .filter(|s| !is_scram_verifier(s) && !is_app_password(s)))
+                    {
+                        secrets.retain(|s| {
+                            !is_scram_verifier(s) || !principal.inner.secrets.contains(s)
+                        });
+                    }
                     principal.inner.secrets = secrets;
                 }
                 (
@@ -538,6 +580,34 @@ impl ManageDirectory for Store {
                     }
                 }
 
+                // Protocols
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::Protocols,
+                    PrincipalValue::StringList(protocols),
+                ) => {
+                    principal.inner.protocols =
+                        protocols.into_iter().map(|v| v.to_lowercase()).collect();
+                }
+                (
+                    PrincipalAction::AddItem,
+                    PrincipalField::Protocols,
+                    PrincipalValue::String(protocol),
+                ) => {
+                    let protocol = protocol.to_lowercase();
+                    if !principal.inner.protocols.contains(&protocol) {
+                        principal.inner.protocols.push(protocol);
+                    }
+                }
+                (
+                    PrincipalAction::RemoveItem,
+                    PrincipalField::Protocols,
+                    PrincipalValue::String(protocol),
+                ) => {
+                    let protocol = protocol.to_lowercase();
+                    principal.inner.protocols.retain(|v| *v != protocol);
+                }
+
                 // MemberOf
                 (
                     PrincipalAction::Set,
@@ -741,6 +811,201 @@ impl ManageDirectory for Store {
         Ok(())
     }
 
+    async fn update_secret_if_weaker(
+        &self,
+        account_id: u32,
+        secret: &str,
+        algorithm: SecretHash,
+    ) -> crate::Result<bool> {
+        // Fetch principal
+        let mut principal = if let Some(principal) = self
+            .get_value::<HashedValue<Principal<u32>>>(ValueKey::from(ValueClass::Directory(
+                DirectoryClass::Principal(account_id),
+            )))
+            .await?
+        {
+            principal
+        } else {
+            return Ok(false);
+        };
+
+        // Find the weak secret matching the provided one
+        let mut weak_secret_pos = None;
+        for (pos, hashed_secret) in principal.inner.secrets.iter().enumerate() {
+            if is_weak_secret_hash(hashed_secret) && verify_secret_hash(hashed_secret, secret).await
+            {
+                weak_secret_pos = Some(pos);
+                break;
+            }
+        }
+        let (pos, hashed_secret) = match weak_secret_pos {
+            Some(pos) => match hash_secret(algorithm, secret).await {
+                Some(hashed_secret) => (pos, hashed_secret),
+                None => return Ok(false),
+            },
+            None => return Ok(false),
+        };
+
+        // Replace secret
+        let mut batch = BatchBuilder::new();
+        batch.assert_value(
+            ValueClass::Directory(DirectoryClass::Principal(MaybeDynamicId::Static(
+                account_id,
+            ))),
+            &principal,
+        );
+        principal.inner.secrets[pos] = hashed_secret;
+        batch.set(
+            ValueClass::Directory(DirectoryClass::Principal(MaybeDynamicId::Static(
+                account_id,
+            ))),
+            principal.inner.serialize(),
+        );
+
+        match self.write(batch.build()).await {
+            Ok(_) => Ok(true),
+            Err(store::Error::AssertValueFailed) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn update_scram_verifier(&self, account_id: u32, secret: &str) -> crate::Result<bool> {
+        // Fetch principal
+        let mut principal = if let Some(principal) = self
+            .get_value::<HashedValue<Principal<u32>>>(ValueKey::from(ValueClass::Directory(
+                DirectoryClass::Principal(account_id),
+            )))
+            .await?
+        {
+            principal
+        } else {
+            return Ok(false);
+        };
+
+        // Make sure the secret is valid and that there is no up to date verifier
+        let mut is_valid = false;
+        for hashed_secret in &principal.inner.secrets {
+            if is_scram_verifier(hashed_secret) {
+                if verify_secret_hash(hashed_secret, secret).await {
+                    return Ok(false);
+                }
+            } else if !is_valid && verify_secret_hash(hashed_secret, secret).await {
+                is_valid = true;
+            }
+        }
+        if !is_valid {
+            return Ok(false);
+        }
+
+        // Replace verifier
+        let mut batch = BatchBuilder::new();
+        batch.assert_value(
+            ValueClass::Directory(DirectoryClass::Principal(MaybeDynamicId::Static(
+                account_id,
+            ))),
+            &principal,
+        );
+        principal
+            .inner
+            .secrets
+            .retain(|secret| !is_scram_verifier(secret));
+        principal
+            .inner
+            .secrets
+            .push(ScramVerifier::generate(secret).to_string());
+        batch.set(
+            ValueClass::Directory(DirectoryClass::Principal(MaybeDynamicId::Static(
+                account_id,
+            ))),
+            principal.inner.serialize(),
+        );
+
+        match self.write(batch.build()).await {
+            Ok(_) => Ok(true),
+            Err(store::Error::AssertValueFailed) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn add_app_password(
+        &self,
+        account_id: u32,
+        app_password: &AppPassword,
+    ) -> crate::Result<()> {
+        update_secrets(self, account_id, |secrets| {
+            // Make sure the name is not taken
+            if secrets
+                .iter()
+                .filter_map(|secret| AppPassword::parse(secret))
+                .any(|existing| existing.name.eq_ignore_ascii_case(&app_password.name))
+            {
+                return Err(DirectoryError::Management(ManagementError::AlreadyExists {
+                    field: PrincipalField::Secrets,
+                    value: app_password.name.clone(),
+                }));
+            }
+
+            secrets.push(app_password.to_string());
+            Ok(true)
+        })
+        .await
+        .map(|_| ())
+    }
+
+    async fn remove_app_password(&self, account_id: u32, name: &str) -> crate::Result<()> {
+        update_secrets(self, account_id, |secrets| {
+            let pos = secrets
+                .iter()
+                .position(|secret| {
+                    AppPassword::parse(secret)
+                        .map_or(false, |app_password| app_password.name == name)
+                })
+                .ok_or_else(|| {
+                    DirectoryError::Management(ManagementError::NotFound(name.to_string()))
+                })?;
+            secrets.remove(pos);
+            Ok(true)
+        })
+        .await
+        .map(|_| ())
+    }
+
+    async fn change_password(&self, account_id: u32, secret: &str) -> crate::Result<()> {
+        update_secrets(self, account_id, |secrets| {
+            // Application passwords are kept, SCRAM verifiers are derived from
+            // the previous password and are regenerated on the next login.
+            secrets.retain(|secret| is_app_password(secret));
+            secrets.insert(0, secret.to_string());
+            Ok(true)
+        })
+        .await
+        .map(|_| ())
+    }
+
+    async fn update_app_password_last_used(
+        &self,
+        account_id: u32,
+        name: &str,
+        last_used: u64,
+    ) -> crate::Result<bool> {
+        update_secrets(self, account_id, |secrets| {
+            for secret in secrets.iter_mut() {
+                if let Some(mut app_password) =
+                    AppPassword::parse(secret).filter(|app_password| app_password.name == name)
+                {
+                    if app_password.last_used >= last_used {
+                        return Ok(false);
+                    }
+                    app_password.last_used = last_used;
+                    *secret = app_password.to_string();
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        })
+        .await
+    }
+
     async fn create_domain(&self, domain: &str) -> crate::Result<()> {
         if !domain.contains('.') {
             return Err(DirectoryError::Management(ManagementError::MissingField(
@@ -784,6 +1049,7 @@ impl ManageDirectory for Store {
             emails: principal.emails,
             member_of: Vec::with_capacity(principal.member_of.len()),
             description: principal.description,
+            protocols: principal.protocols,
         };
 
         for account_id in principal.member_of {
@@ -811,6 +1077,7 @@ impl ManageDirectory for Store {
                 .map_group_names(principal.member_of, create_if_missing)
                 .await?,
             description: principal.description,
+            protocols: principal.protocols,
         })
     }
 
@@ -1010,6 +1277,52 @@ impl From<Principal<String>> for Principal<u32> {
             emails: principal.emails,
             member_of: Vec::with_capacity(0),
             description: principal.description,
+            protocols: principal.protocols,
+        }
+    }
+}
+
+// Applies a change to the secrets of a principal, retrying on concurrent updates
+async fn update_secrets(
+    store: &Store,
+    account_id: u32,
+    mut update: impl FnMut(&mut Vec<String>) -> crate::Result<bool>,
+) -> crate::Result<bool> {
+    let mut try_count = 0;
+
+    loop {
+        let mut principal = store
+            .get_value::<HashedValue<Principal<u32>>>(ValueKey::from(ValueClass::Directory(
+                DirectoryClass::Principal(account_id),
+            )))
+            .await?
+            .ok_or_else(|| {
+                DirectoryError::Management(ManagementError::NotFound(account_id.to_string()))
+            })?;
+        if !update(&mut principal.inner.secrets)? {
+            return Ok(false);
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch.assert_value(
+            ValueClass::Directory(DirectoryClass::Principal(MaybeDynamicId::Static(
+                account_id,
+            ))),
+            &principal,
+        );
+        batch.set(
+            ValueClass::Directory(DirectoryClass::Principal(MaybeDynamicId::Static(
+                account_id,
+            ))),
+            principal.inner.serialize(),
+        );
+
+        match store.write(batch.build()).await {
+            Ok(_) => return Ok(true),
+            Err(store::Error::AssertValueFailed) if try_count < 3 => {
+                try_count += 1;
+            }
+            Err(err) => return Err(err.into()),
         }
     }
 }
@@ -50,6 +50,7 @@ impl Serialize for &Principal<u32> {
                 + self.name.len()
                 + self.emails.iter().map(|s| s.len()).sum::<usize>()
                 + self.secrets.iter().map(|s| s.len()).sum::<usize>()
+                + self.protocols.iter().map(|s| s.len()).sum::<usize>()
                 + self.description.as_ref().map(|s| s.len()).unwrap_or(0),
         )
         .write(1u8)
@@ -61,7 +62,7 @@ impl Serialize for &Principal<u32> {
         .write_leb128(self.description.as_ref().map_or(0, |s| s.len()))
         .write(self.description.as_deref().unwrap_or_default().as_bytes());
 
-        for list in [&self.secrets, &self.emails] {
+        for list in [&self.secrets, &self.emails, &self.protocols] {
             serializer = serializer.write_leb128(list.len());
             for value in list {
                 serializer = serializer.write_leb128(value.len()).write(value.as_bytes());
@@ -129,6 +130,7 @@ fn deserialize(bytes: &[u8]) -> Option<Principal<u32>> {
         secrets: deserialize_string_list(&mut bytes)?,
         emails: deserialize_string_list(&mut bytes)?,
         member_of: Vec::new(),
+        protocols: deserialize_string_list(&mut bytes).unwrap_or_default(),
     }
     .into()
 }
@@ -151,6 +153,8 @@ pub enum PrincipalField {
     MemberOf,
     #[serde(rename = "members")]
     Members,
+    #[serde(rename = "protocols")]
+    Protocols,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -215,6 +219,7 @@ impl Display for PrincipalField {
             PrincipalField::Emails => write!(f, "emails"),
             PrincipalField::MemberOf => write!(f, "memberOf"),
             PrincipalField::Members => write!(f, "members"),
+            PrincipalField::Protocols => write!(f, "protocols"),
         }
     }
 }
@@ -21,7 +21,10 @@
  * for more details.
 */
 
-use std::time::Duration;
+use std::{
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
 
 use ldap3::LdapConnSettings;
 use store::Store;
@@ -29,7 +32,10 @@ use utils::config::{utils::AsKey, Config};
 
 use crate::core::config::build_pool;
 
-use super::{Bind, LdapConnectionManager, LdapDirectory, LdapFilter, LdapMappings};
+use super::{
+    pool::spawn_health_check, Bind, LdapConnectionManager, LdapDirectory, LdapFilter, LdapMappings,
+    LdapPool,
+};
 
 impl LdapDirectory {
     pub fn from_config(config: &mut Config, prefix: impl AsKey, data_store: Store) -> Option<Self> {
@@ -44,26 +50,30 @@ impl LdapDirectory {
             None
         };
 
-        let manager = LdapConnectionManager::new(
-            config.value_require((&prefix, "url"))?.to_string(),
-            LdapConnSettings::new()
-                .set_conn_timeout(
-                    config
-                        .property_or_default((&prefix, "timeout"), "30s")
-                        .unwrap_or_else(|| Duration::from_secs(30)),
-                )
-                .set_starttls(
-                    config
-                        .property_or_default((&prefix, "tls.enable"), "false")
-                        .unwrap_or_default(),
-                )
-                .set_no_tls_verify(
-                    config
-                        .property_or_default((&prefix, "tls.allow-invalid-certs"), "false")
-                        .unwrap_or_default(),
-                ),
-            bind_dn,
-        );
+        let urls = config
+            .values((&prefix, "url"))
+            .map(|(_, v)| v.to_string())
+            .collect::<Vec<_>>();
+        if urls.is_empty() {
+            config.new_parse_error((&prefix, "url"), "Missing LDAP server URL");
+            return None;
+        }
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(
+                config
+                    .property_or_default((&prefix, "timeout"), "30s")
+                    .unwrap_or_else(|| Duration::from_secs(30)),
+            )
+            .set_starttls(
+                config
+                    .property_or_default((&prefix, "tls.enable"), "false")
+                    .unwrap_or_default(),
+            )
+            .set_no_tls_verify(
+                config
+                    .property_or_default((&prefix, "tls.allow-invalid-certs"), "false")
+                    .unwrap_or_default(),
+            );
 
         let mut mappings = LdapMappings {
             base_dn: config.value_require((&prefix, "base-dn"))?.to_string(),
@@ -104,7 +114,14 @@ impl LdapDirectory {
                 .values((&prefix, "attributes.email-alias"))
                 .map(|(_, v)| v.to_string())
                 .collect(),
+            attr_protocols: config
+                .values((&prefix, "attributes.protocols"))
+                .map(|(_, v)| v.to_string())
+                .collect(),
             attrs_principal: vec!["objectClass".to_string()],
+            max_group_depth: config
+                .property_or_default((&prefix, "member-of.max-depth"), "0")
+                .unwrap_or_default(),
         };
 
         for attr in [
@@ -116,6 +133,7 @@ impl LdapDirectory {
             &mappings.attr_groups,
             &mappings.attr_email_address,
             &mappings.attr_email_alias,
+            &mappings.attr_protocols,
         ] {
             mappings.attrs_principal.extend(attr.iter().cloned());
         }
@@ -129,13 +147,40 @@ impl LdapDirectory {
             None
         };
 
+        // Build a connection pool for each server, in order of preference
+        let mut pools = Vec::with_capacity(urls.len());
+        for url in urls {
+            let manager = LdapConnectionManager::new(url, settings.clone(), bind_dn.clone());
+            pools.push(LdapPool {
+                pool: build_pool(config, &prefix, manager)
+                    .map_err(|e| {
+                        config.new_parse_error(
+                            prefix.as_str(),
+                            format!("Failed to build LDAP pool: {e:?}"),
+                        )
+                    })
+                    .ok()?,
+                down_until: AtomicU64::new(0),
+            });
+        }
+
+        // Probe all servers in the background
+        let pools = Arc::new(pools);
+        let retry_interval = config
+            .property_or_default((&prefix, "pool.retry-interval"), "30s")
+            .unwrap_or_else(|| Duration::from_secs(30));
+        spawn_health_check(
+            Arc::downgrade(&pools),
+            config
+                .property_or_default((&prefix, "pool.health-check"), "30s")
+                .unwrap_or_else(|| Duration::from_secs(30)),
+            retry_interval,
+        );
+
         Some(LdapDirectory {
             mappings,
-            pool: build_pool(config, &prefix, manager)
-                .map_err(|e| {
-                    config.new_parse_error(prefix, format!("Failed to build LDAP pool: {e:?}"))
-                })
-                .ok()?,
+            pools,
+            retry_interval,
             auth_bind,
             data_store,
         })
@@ -21,12 +21,14 @@
  * for more details.
 */
 
+use ahash::AHashSet;
+use deadpool::managed::Object;
 use ldap3::{Ldap, LdapConnAsync, LdapError, Scope, SearchEntry};
 use mail_send::Credentials;
 
 use crate::{backend::internal::manage::ManageDirectory, DirectoryError, Principal, QueryBy, Type};
 
-use super::{LdapDirectory, LdapMappings};
+use super::{LdapConnectionManager, LdapDirectory, LdapMappings};
 
 impl LdapDirectory {
     pub async fn query(
@@ -34,7 +36,18 @@ impl LdapDirectory {
         by: QueryBy<'_>,
         return_member_of: bool,
     ) -> crate::Result<Option<Principal<u32>>> {
-        let mut conn = self.pool.get().await?;
+        let by = &by;
+        self.with_connection(|manager, conn| self.query_(manager, conn, by, return_member_of))
+            .await
+    }
+
+    async fn query_(
+        &self,
+        manager: &LdapConnectionManager,
+        mut conn: Object<LdapConnectionManager>,
+        by: &QueryBy<'_>,
+        return_member_of: bool,
+    ) -> crate::Result<Option<Principal<u32>>> {
         let mut account_id = None;
         let account_name;
 
@@ -52,12 +65,12 @@ impl LdapDirectory {
                 }
             }
             QueryBy::Id(uid) => {
-                if let Some(username) = self.data_store.get_account_name(uid).await? {
+                if let Some(username) = self.data_store.get_account_name(*uid).await? {
                     account_name = username;
                 } else {
                     return Ok(None);
                 }
-                account_id = Some(uid);
+                account_id = Some(*uid);
 
                 if let Some(principal) = self
                     .find_principal(&mut conn, &self.mappings.filter_name.build(&account_name))
@@ -77,11 +90,9 @@ impl LdapDirectory {
                 account_name = username.to_string();
 
                 if let Some(auth_bind) = &self.auth_bind {
-                    let (conn, mut ldap) = LdapConnAsync::with_settings(
-                        self.pool.manager().settings.clone(),
-                        &self.pool.manager().address,
-                    )
-                    .await?;
+                    let (conn, mut ldap) =
+                        LdapConnAsync::with_settings(manager.settings.clone(), &manager.address)
+                            .await?;
 
                     ldap3::drive!(conn);
 
@@ -136,30 +147,35 @@ impl LdapDirectory {
 
         // Obtain groups
         if return_member_of && !principal.member_of.is_empty() {
-            for member_of in principal.member_of.iter_mut() {
-                if member_of.contains('=') {
-                    let (rs, _res) = conn
-                        .search(
-                            member_of,
-                            Scope::Base,
-                            "objectClass=*",
-                            &self.mappings.attr_name,
-                        )
+            principal.member_of = self
+                .resolve_group_names(&mut conn, std::mem::take(&mut principal.member_of))
+                .await?;
+
+            // Follow nested groups up to the configured depth
+            let mut seen = AHashSet::from_iter(principal.member_of.iter().cloned());
+            seen.insert(principal.name.clone());
+            let mut names = principal.member_of.clone();
+            for _ in 0..self.mappings.max_group_depth {
+                let mut next_names = Vec::new();
+                for name in names {
+                    if let Some(group) = self
+                        .find_principal(&mut conn, &self.mappings.filter_name.build(&name))
                         .await?
-                        .success()?;
-                    for entry in rs {
-                        'outer: for (attr, value) in SearchEntry::construct(entry).attrs {
-                            if self.mappings.attr_name.contains(&attr) {
-                                if let Some(group) = value.into_iter().next() {
-                                    if !group.is_empty() {
-                                        *member_of = group;
-                                        break 'outer;
-                                    }
-                                }
+                    {
+                        for member_of in
+                            self.resolve_group_names(&mut conn, group.member_of).await?
+                        {
+                            if seen.insert(member_of.clone()) {
+                                principal.member_of.push(member_of.clone());
+                                next_names.push(member_of);
                             }
                         }
                     }
                 }
+                if next_names.is_empty() {
+                    break;
+                }
+                names = next_names;
             }
 
             // Map ids
@@ -175,18 +191,19 @@ impl LdapDirectory {
 
     pub async fn email_to_ids(&self, address: &str) -> crate::Result<Vec<u32>> {
         let rs = self
-            .pool
-            .get()
-            .await?
-            .search(
-                &self.mappings.base_dn,
-                Scope::Subtree,
-                &self.mappings.filter_email.build(address.as_ref()),
-                &self.mappings.attr_name,
-            )
-            .await?
-            .success()
-            .map(|(rs, _res)| rs)?;
+            .with_connection(|_, mut conn| async move {
+                conn.search(
+                    &self.mappings.base_dn,
+                    Scope::Subtree,
+                    &self.mappings.filter_email.build(address),
+                    &self.mappings.attr_name,
+                )
+                .await?
+                .success()
+                .map(|(rs, _res)| rs)
+                .map_err(Into::into)
+            })
+            .await?;
 
         let mut ids = Vec::with_capacity(rs.len());
         for entry in rs {
@@ -205,87 +222,85 @@ impl LdapDirectory {
     }
 
     pub async fn rcpt(&self, address: &str) -> crate::Result<bool> {
-        self.pool
-            .get()
-            .await?
-            .streaming_search(
+        self.with_connection(|_, mut conn| async move {
+            conn.streaming_search(
                 &self.mappings.base_dn,
                 Scope::Subtree,
-                &self.mappings.filter_email.build(address.as_ref()),
+                &self.mappings.filter_email.build(address),
                 &self.mappings.attr_email_address,
             )
             .await?
             .next()
             .await
             .map(|entry| entry.is_some())
-            .map_err(|e| e.into())
+            .map_err(Into::into)
+        })
+        .await
     }
 
     pub async fn vrfy(&self, address: &str) -> crate::Result<Vec<String>> {
-        let mut stream = self
-            .pool
-            .get()
-            .await?
-            .streaming_search(
-                &self.mappings.base_dn,
-                Scope::Subtree,
-                &self.mappings.filter_verify.build(address),
-                &self.mappings.attr_email_address,
-            )
-            .await?;
+        self.with_connection(|_, mut conn| async move {
+            let mut stream = conn
+                .streaming_search(
+                    &self.mappings.base_dn,
+                    Scope::Subtree,
+                    &self.mappings.filter_verify.build(address),
+                    &self.mappings.attr_email_address,
+                )
+                .await?;
 
-        let mut emails = Vec::new();
-        while let Some(entry) = stream.next().await? {
-            let entry = SearchEntry::construct(entry);
-            for attr in &self.mappings.attr_email_address {
-                if let Some(values) = entry.attrs.get(attr) {
-                    for email in values {
-                        if !email.is_empty() {
-                            emails.push(email.to_string());
+            let mut emails = Vec::new();
+            while let Some(entry) = stream.next().await? {
+                let entry = SearchEntry::construct(entry);
+                for attr in &self.mappings.attr_email_address {
+                    if let Some(values) = entry.attrs.get(attr) {
+                        for email in values {
+                            if !email.is_empty() {
+                                emails.push(email.to_string());
+                            }
                         }
                     }
                 }
             }
-        }
 
-        Ok(emails)
+            Ok(emails)
+        })
+        .await
     }
 
     pub async fn expn(&self, address: &str) -> crate::Result<Vec<String>> {
-        let mut stream = self
-            .pool
-            .get()
-            .await?
-            .streaming_search(
-                &self.mappings.base_dn,
-                Scope::Subtree,
-                &self.mappings.filter_expand.build(address),
-                &self.mappings.attr_email_address,
-            )
-            .await?;
+        self.with_connection(|_, mut conn| async move {
+            let mut stream = conn
+                .streaming_search(
+                    &self.mappings.base_dn,
+                    Scope::Subtree,
+                    &self.mappings.filter_expand.build(address),
+                    &self.mappings.attr_email_address,
+                )
+                .await?;
 
-        let mut emails = Vec::new();
-        while let Some(entry) = stream.next().await? {
-            let entry = SearchEntry::construct(entry);
-            for attr in &self.mappings.attr_email_address {
-                if let Some(values) = entry.attrs.get(attr) {
-                    for email in values {
-                        if !email.is_empty() {
-                            emails.push(email.to_string());
+            let mut emails = Vec::new();
+            while let Some(entry) = stream.next().await? {
+                let entry = SearchEntry::construct(entry);
+                for attr in &self.mappings.attr_email_address {
+                    if let Some(values) = entry.attrs.get(attr) {
+                        for email in values {
+                            if !email.is_empty() {
+                                emails.push(email.to_string());
+                            }
                         }
                     }
                 }
             }
-        }
 
-        Ok(emails)
+            Ok(emails)
+        })
+        .await
     }
 
     pub async fn is_local_domain(&self, domain: &str) -> crate::Result<bool> {
-        self.pool
-            .get()
-            .await?
-            .streaming_search(
+        self.with_connection(|_, mut conn| async move {
+            conn.streaming_search(
                 &self.mappings.base_dn,
                 Scope::Subtree,
                 &self.mappings.filter_domains.build(domain),
@@ -295,7 +310,9 @@ impl LdapDirectory {
             .next()
             .await
             .map(|entry| entry.is_some())
-            .map_err(|e| e.into())
+            .map_err(Into::into)
+        })
+        .await
     }
 }
 
@@ -321,6 +338,40 @@ impl LdapDirectory {
         })
         .map_err(Into::into)
     }
+
+    async fn resolve_group_names(
+        &self,
+        conn: &mut Ldap,
+        mut member_of: Vec<String>,
+    ) -> crate::Result<Vec<String>> {
+        for member_of in member_of.iter_mut() {
+            if member_of.contains('=') {
+                let (rs, _res) = conn
+                    .search(
+                        member_of,
+                        Scope::Base,
+                        "objectClass=*",
+                        &self.mappings.attr_name,
+                    )
+                    .await?
+                    .success()?;
+                for entry in rs {
+                    'outer: for (attr, value) in SearchEntry::construct(entry).attrs {
+                        if self.mappings.attr_name.contains(&attr) {
+                            if let Some(group) = value.into_iter().next() {
+                                if !group.is_empty() {
+                                    *member_of = group;
+                                    break 'outer;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(member_of)
+    }
 }
 
 impl LdapMappings {
@@ -355,6 +406,10 @@ impl LdapMappings {
                 }
             } else if self.attr_groups.contains(&attr) {
                 principal.member_of.extend(value);
+            } else if self.attr_protocols.contains(&attr) {
+                principal
+                    .protocols
+                    .extend(value.into_iter().map(|v| v.to_lowercase()));
             } else if self.attr_quota.contains(&attr) {
                 if let Ok(quota) = value.into_iter().next().unwrap_or_default().parse() {
                     principal.quota = quota;
@@ -21,6 +21,11 @@
  * for more details.
 */
 
+use std::{
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
+
 use deadpool::managed::Pool;
 use ldap3::{ldap_escape, LdapConnSettings};
 use store::Store;
@@ -30,12 +35,18 @@ pub mod lookup;
 pub mod pool;
 
 pub struct LdapDirectory {
-    pool: Pool<LdapConnectionManager>,
+    pools: Arc<Vec<LdapPool>>,
+    retry_interval: Duration,
     mappings: LdapMappings,
     auth_bind: Option<LdapFilter>,
     pub(crate) data_store: Store,
 }
 
+pub(crate) struct LdapPool {
+    pool: Pool<LdapConnectionManager>,
+    down_until: AtomicU64,
+}
+
 #[derive(Debug, Default)]
 pub struct LdapMappings {
     base_dn: String,
@@ -52,7 +63,9 @@ pub struct LdapMappings {
     attr_email_address: Vec<String>,
     attr_email_alias: Vec<String>,
     attr_quota: Vec<String>,
+    attr_protocols: Vec<String>,
     attrs_principal: Vec<String>,
+    max_group_depth: usize,
 }
 
 #[derive(Debug, Default)]
@@ -73,6 +86,7 @@ pub(crate) struct LdapConnectionManager {
     bind_dn: Option<Bind>,
 }
 
+#[derive(Clone)]
 pub(crate) struct Bind {
     dn: String,
     password: String,
@@ -21,11 +21,20 @@
  * for more details.
 */
 
+use std::{
+    future::Future,
+    sync::{atomic::Ordering, Weak},
+    time::Duration,
+};
+
 use async_trait::async_trait;
 use deadpool::managed;
 use ldap3::{exop::WhoAmI, Ldap, LdapConnAsync, LdapError};
+use store::write::now;
+
+use crate::DirectoryError;
 
-use super::LdapConnectionManager;
+use super::{LdapConnectionManager, LdapDirectory, LdapPool};
 
 #[async_trait]
 impl managed::Manager for LdapConnectionManager {
@@ -56,3 +65,127 @@ impl managed::Manager for LdapConnectionManager {
             .map_err(managed::RecycleError::Backend)
     }
 }
+
+impl LdapDirectory {
+    // Runs an operation on the first available server, the operation is retried
+    // on the next server when the connection fails before or while it runs.
+    // Servers that recently failed are only retried once all others have been exhausted.
+    pub(crate) async fn with_connection<'x, T, F, R>(&'x self, op: F) -> crate::Result<T>
+    where
+        F: Fn(&'x LdapConnectionManager, managed::Object<LdapConnectionManager>) -> R,
+        R: Future<Output = crate::Result<T>>,
+    {
+        let now = now();
+        let mut last_err = None;
+
+        for pool in self
+            .pools
+            .iter()
+            .filter(|pool| !pool.is_down(now))
+            .chain(self.pools.iter().filter(|pool| pool.is_down(now)))
+        {
+            let err = match pool.pool.get().await {
+                Ok(conn) => match op(pool.pool.manager(), conn).await {
+                    Err(err) if is_connection_error(&err) => err,
+                    result => {
+                        pool.down_until.store(0, Ordering::Relaxed);
+                        return result;
+                    }
+                },
+                Err(err) => err.into(),
+            };
+
+            tracing::warn!(
+                context = "directory",
+                event = "failover",
+                protocol = "ldap",
+                url = pool.pool.manager().address,
+                reason = ?err,
+                "LDAP server unavailable, trying next server"
+            );
+            pool.set_down(self.retry_interval);
+            last_err = Some(err);
+        }
+
+        Err(last_err.unwrap_or(DirectoryError::TimedOut))
+    }
+}
+
+impl LdapPool {
+    fn is_down(&self, now: u64) -> bool {
+        self.down_until.load(Ordering::Relaxed) > now
+    }
+
+    // Retires idle connections and skips this server until the retry interval expires
+    fn set_down(&self, retry_interval: Duration) {
+        self.pool.retain(|_, _| false);
+        self.down_until
+            .store(now() + retry_interval.as_secs(), Ordering::Relaxed);
+    }
+
+    async fn health_check(&self, retry_interval: Duration) {
+        let result = match self.pool.get().await {
+            Ok(mut conn) => conn.extended(WhoAmI).await.map(|_| ()).map_err(Into::into),
+            Err(err) => Err(DirectoryError::from(err)),
+        };
+
+        match result {
+            Ok(_) => {
+                if self.down_until.swap(0, Ordering::Relaxed) != 0 {
+                    tracing::info!(
+                        context = "directory",
+                        event = "recovered",
+                        protocol = "ldap",
+                        url = self.pool.manager().address,
+                        "LDAP server is available again"
+                    );
+                }
+            }
+            Err(err) => {
+                tracing::debug!(
+                    context = "directory",
+                    event = "health-check",
+                    protocol = "ldap",
+                    url = self.pool.manager().address,
+                    reason = ?err,
+                    "LDAP server failed health check"
+                );
+                self.set_down(retry_interval);
+            }
+        }
+    }
+}
+
+// Probes all servers periodically until the directory is dropped, retiring
+// broken connections and bringing recovered servers back into rotation.
+pub(crate) fn spawn_health_check(
+    pools: Weak<Vec<LdapPool>>,
+    interval: Duration,
+    retry_interval: Duration,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(pools) = pools.upgrade() else {
+                break;
+            };
+            for pool in pools.iter() {
+                pool.health_check(retry_interval).await;
+            }
+        }
+    });
+}
+
+fn is_connection_error(err: &DirectoryError) -> bool {
+    matches!(
+        err,
+        DirectoryError::Ldap(
+            LdapError::Io { .. }
+                | LdapError::OpSend { .. }
+                | LdapError::ResultRecv { .. }
+                | LdapError::Timeout { .. }
+                | LdapError::EndOfStream
+        ) | DirectoryError::TimedOut
+            | DirectoryError::Pool(_)
+    )
+}
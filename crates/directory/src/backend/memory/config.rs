@@ -152,6 +152,10 @@ impl MemoryDirectory {
                 member_of,
                 id,
                 emails,
+                protocols: config
+                    .values((prefix.as_str(), "principals", lookup_id, "protocols"))
+                    .map(|(_, v)| v.to_lowercase())
+                    .collect(),
             });
         }
 
@@ -21,9 +21,11 @@
  * for more details.
 */
 
+pub mod http;
 pub mod imap;
 pub mod internal;
 pub mod ldap;
 pub mod memory;
+pub mod oidc;
 pub mod smtp;
 pub mod sql;
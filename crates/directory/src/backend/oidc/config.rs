@@ -0,0 +1,119 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use store::Store;
+use utils::config::{utils::AsKey, Config};
+
+use super::{OidcDirectory, OidcEndpoints, OidcMappings};
+
+impl OidcDirectory {
+    pub fn from_config(config: &mut Config, prefix: impl AsKey, data_store: Store) -> Option<Self> {
+        let prefix = prefix.as_key();
+        let endpoints = OidcEndpoints {
+            token: config
+                .value((&prefix, "endpoint.token"))
+                .map(|v| v.to_string()),
+            userinfo: config
+                .value((&prefix, "endpoint.userinfo"))
+                .map(|v| v.to_string()),
+            introspect: config
+                .value((&prefix, "endpoint.introspect"))
+                .map(|v| v.to_string()),
+        };
+        if endpoints.introspect.is_none()
+            && (endpoints.token.is_none() || endpoints.userinfo.is_none())
+        {
+            config.new_parse_error(
+                prefix.as_str(),
+                "Either an introspection endpoint or both token and userinfo endpoints are required",
+            );
+            return None;
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(
+                config
+                    .property_or_default((&prefix, "timeout"), "15s")
+                    .unwrap_or_else(|| Duration::from_secs(15)),
+            )
+            .danger_accept_invalid_certs(
+                config
+                    .property_or_default((&prefix, "tls.allow-invalid-certs"), "false")
+                    .unwrap_or_default(),
+            )
+            .build()
+            .map_err(|err| {
+                config.new_parse_error(
+                    prefix.as_str(),
+                    format!("Failed to build HTTP client: {err}"),
+                )
+            })
+            .ok()?;
+
+        Some(OidcDirectory {
+            client,
+            endpoints,
+            client_id: config.value_require((&prefix, "client.id"))?.to_string(),
+            client_secret: config
+                .value((&prefix, "client.secret"))
+                .map(|v| v.to_string()),
+            scope: config
+                .value((&prefix, "scope"))
+                .unwrap_or("openid email profile")
+                .to_string(),
+            mappings: OidcMappings {
+                claim_name: config
+                    .value((&prefix, "claims.name"))
+                    .unwrap_or("preferred_username")
+                    .to_string(),
+                claim_email: config
+                    .value((&prefix, "claims.email"))
+                    .unwrap_or("email")
+                    .to_string(),
+                claim_description: config
+                    .value((&prefix, "claims.description"))
+                    .unwrap_or("name")
+                    .to_string(),
+                claim_quota: config
+                    .value((&prefix, "claims.quota"))
+                    .unwrap_or("quota")
+                    .to_string(),
+                claim_groups: config
+                    .value((&prefix, "claims.groups"))
+                    .unwrap_or("groups")
+                    .to_string(),
+                claim_protocols: config
+                    .value((&prefix, "claims.protocols"))
+                    .unwrap_or("protocols")
+                    .to_string(),
+            },
+            domains: config
+                .values((&prefix, "lookup.domains"))
+                .map(|(_, v)| v.to_lowercase())
+                .collect(),
+            data_store,
+        })
+    }
+}
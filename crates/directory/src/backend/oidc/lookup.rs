@@ -0,0 +1,333 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use mail_send::Credentials;
+use reqwest::StatusCode;
+use serde_json::{Map, Value};
+use store::{
+    write::{BatchBuilder, DirectoryClass, MaybeDynamicId, ValueClass},
+    Serialize, ValueKey,
+};
+
+use crate::{backend::internal::manage::ManageDirectory, DirectoryError, Principal, QueryBy, Type};
+
+use super::OidcDirectory;
+
+type Claims = Map<String, Value>;
+
+impl OidcDirectory {
+    pub async fn query(
+        &self,
+        by: QueryBy<'_>,
+        return_member_of: bool,
+    ) -> crate::Result<Option<Principal<u32>>> {
+        let (claims, account_name) = match by {
+            QueryBy::Name(name) => {
+                return match self.data_store.get_account_id(name).await? {
+                    Some(account_id) => self.stored_principal(account_id, return_member_of).await,
+                    None => Ok(None),
+                };
+            }
+            QueryBy::Id(account_id) => {
+                return self.stored_principal(account_id, return_member_of).await;
+            }
+            QueryBy::Credentials(Credentials::Plain { username, secret }) => {
+                let (token_url, userinfo_url) =
+                    match (&self.endpoints.token, &self.endpoints.userinfo) {
+                        (Some(token_url), Some(userinfo_url)) => (token_url, userinfo_url),
+                        _ => return Err(DirectoryError::unsupported("oidc", "password grant")),
+                    };
+
+                match self.password_grant(token_url, username, secret).await? {
+                    Some(access_token) => (
+                        self.userinfo(userinfo_url, &access_token).await?,
+                        Some(username.as_str()),
+                    ),
+                    None => return Ok(None),
+                }
+            }
+            QueryBy::Credentials(
+                Credentials::OAuthBearer { token } | Credentials::XOauth2 { secret: token, .. },
+            ) => {
+                if let Some(introspect_url) = &self.endpoints.introspect {
+                    (self.introspect(introspect_url, token).await?, None)
+                } else if let Some(userinfo_url) = &self.endpoints.userinfo {
+                    (self.userinfo(userinfo_url, token).await?, None)
+                } else {
+                    return Err(DirectoryError::unsupported("oidc", "token introspection"));
+                }
+            }
+        };
+        let claims = if let Some(claims) = claims {
+            claims
+        } else {
+            return Ok(None);
+        };
+
+        // Map claims to principal fields
+        let account_name = if let Some(account_name) = claims
+            .get(&self.mappings.claim_name)
+            .or_else(|| claims.get("username"))
+            .and_then(|v| v.as_str())
+            .or(account_name)
+        {
+            account_name.to_string()
+        } else {
+            tracing::debug!(
+                context = "directory",
+                event = "invalid_claims",
+                protocol = "oidc",
+                claim = self.mappings.claim_name,
+                "Account name claim not found"
+            );
+            return Ok(None);
+        };
+        let principal = Principal {
+            id: self
+                .data_store
+                .get_or_create_account_id(&account_name)
+                .await?,
+            typ: Type::Individual,
+            quota: match claims.get(&self.mappings.claim_quota) {
+                Some(Value::Number(quota)) => quota.as_u64().unwrap_or_default(),
+                Some(Value::String(quota)) => quota.parse().unwrap_or_default(),
+                _ => 0,
+            },
+            name: account_name,
+            secrets: vec![],
+            emails: claim_values(&claims, &self.mappings.claim_email),
+            member_of: claim_values(&claims, &self.mappings.claim_groups)
+                .into_iter()
+                .map(|group| group.trim_start_matches('/').to_string())
+                .filter(|group| !group.is_empty())
+                .collect(),
+            description: claims
+                .get(&self.mappings.claim_description)
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string()),
+            protocols: claim_values(&claims, &self.mappings.claim_protocols)
+                .into_iter()
+                .map(|v| v.to_lowercase())
+                .collect(),
+        };
+
+        // OIDC providers offer no lookup endpoint, keep a copy of the claims
+        // so the principal can later be obtained by id or name.
+        let mut principal = self.data_store.map_principal(principal, true).await?;
+        self.store_principal(&principal).await?;
+
+        if !return_member_of {
+            principal.member_of.clear();
+        }
+        Ok(Some(principal))
+    }
+
+    async fn stored_principal(
+        &self,
+        account_id: u32,
+        return_member_of: bool,
+    ) -> crate::Result<Option<Principal<u32>>> {
+        match self
+            .data_store
+            .get_value::<Principal<u32>>(ValueKey::from(ValueClass::Directory(
+                DirectoryClass::Principal(account_id),
+            )))
+            .await?
+        {
+            Some(mut principal) => {
+                principal.id = account_id;
+                if return_member_of {
+                    principal.member_of = self.data_store.get_member_of(account_id).await?;
+                }
+                Ok(Some(principal))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn store_principal(&self, principal: &Principal<u32>) -> crate::Result<()> {
+        let account_id = principal.id;
+        let mut batch = BatchBuilder::new();
+
+        // Update the principal only when the claims have changed
+        let claims = Principal {
+            member_of: vec![],
+            ..principal.clone()
+        };
+        if self
+            .stored_principal(account_id, false)
+            .await?
+            .map_or(true, |stored| stored != claims)
+        {
+            batch.set(
+                ValueClass::Directory(DirectoryClass::Principal(MaybeDynamicId::Static(
+                    account_id,
+                ))),
+                claims.serialize(),
+            );
+        }
+
+        // Synchronize group memberships
+        let member_of = self.data_store.get_member_of(account_id).await?;
+        for member_id in &member_of {
+            if !principal.member_of.contains(member_id) {
+                batch.clear(DirectoryClass::MemberOf {
+                    principal_id: MaybeDynamicId::Static(account_id),
+                    member_of: MaybeDynamicId::Static(*member_id),
+                });
+                batch.clear(DirectoryClass::Members {
+                    principal_id: MaybeDynamicId::Static(*member_id),
+                    has_member: MaybeDynamicId::Static(account_id),
+                });
+            }
+        }
+        for member_id in &principal.member_of {
+            if !member_of.contains(member_id) {
+                batch.set(
+                    ValueClass::Directory(DirectoryClass::MemberOf {
+                        principal_id: MaybeDynamicId::Static(account_id),
+                        member_of: MaybeDynamicId::Static(*member_id),
+                    }),
+                    vec![],
+                );
+                batch.set(
+                    ValueClass::Directory(DirectoryClass::Members {
+                        principal_id: MaybeDynamicId::Static(*member_id),
+                        has_member: MaybeDynamicId::Static(account_id),
+                    }),
+                    vec![],
+                );
+            }
+        }
+
+        if !batch.is_empty() {
+            self.data_store.write(batch.build()).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn email_to_ids(&self, _address: &str) -> crate::Result<Vec<u32>> {
+        Err(DirectoryError::unsupported("oidc", "email_to_ids"))
+    }
+
+    pub async fn rcpt(&self, _address: &str) -> crate::Result<bool> {
+        Err(DirectoryError::unsupported("oidc", "rcpt"))
+    }
+
+    pub async fn vrfy(&self, _address: &str) -> crate::Result<Vec<String>> {
+        Err(DirectoryError::unsupported("oidc", "vrfy"))
+    }
+
+    pub async fn expn(&self, _address: &str) -> crate::Result<Vec<String>> {
+        Err(DirectoryError::unsupported("oidc", "expn"))
+    }
+
+    pub async fn is_local_domain(&self, domain: &str) -> crate::Result<bool> {
+        Ok(self.domains.contains(domain))
+    }
+
+    async fn password_grant(
+        &self,
+        url: &str,
+        username: &str,
+        secret: &str,
+    ) -> crate::Result<Option<String>> {
+        let mut params = vec![
+            ("grant_type", "password"),
+            ("username", username),
+            ("password", secret),
+            ("client_id", self.client_id.as_str()),
+            ("scope", self.scope.as_str()),
+        ];
+        if let Some(client_secret) = &self.client_secret {
+            params.push(("client_secret", client_secret.as_str()));
+        }
+
+        Ok(self
+            .send(self.client.post(url).form(&params))
+            .await?
+            .and_then(|response| {
+                response
+                    .get("access_token")
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string())
+            }))
+    }
+
+    async fn userinfo(&self, url: &str, access_token: &str) -> crate::Result<Option<Claims>> {
+        self.send(self.client.get(url).bearer_auth(access_token))
+            .await
+    }
+
+    async fn introspect(&self, url: &str, token: &str) -> crate::Result<Option<Claims>> {
+        Ok(self
+            .send(
+                self.client
+                    .post(url)
+                    .basic_auth(&self.client_id, self.client_secret.as_ref())
+                    .form(&[("token", token), ("token_type_hint", "access_token")]),
+            )
+            .await?
+            .filter(|claims| claims.get("active").and_then(|v| v.as_bool()) == Some(true)))
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> crate::Result<Option<Claims>> {
+        let response = request.send().await?;
+        match response.status() {
+            status if status.is_success() => {
+                let bytes = response.bytes().await?;
+                serde_json::from_slice::<Claims>(&bytes)
+                    .map(Some)
+                    .map_err(|err| {
+                        DirectoryError::Http(format!("Failed to parse OIDC response: {err}"))
+                    })
+            }
+            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                tracing::debug!(
+                    context = "directory",
+                    event = "invalid_password",
+                    protocol = "oidc",
+                    status = response.status().as_u16(),
+                    "OIDC provider rejected credentials"
+                );
+                Ok(None)
+            }
+            status => Err(DirectoryError::Http(format!(
+                "Unexpected OIDC response status: {status}"
+            ))),
+        }
+    }
+}
+
+fn claim_values(claims: &Claims, claim: &str) -> Vec<String> {
+    match claims.get(claim) {
+        Some(Value::String(value)) => vec![value.to_string()],
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(|v| v.to_string())
+            .collect(),
+        _ => vec![],
+    }
+}
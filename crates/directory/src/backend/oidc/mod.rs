@@ -0,0 +1,56 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+pub mod config;
+pub mod lookup;
+
+use ahash::AHashSet;
+use store::Store;
+
+pub struct OidcDirectory {
+    client: reqwest::Client,
+    endpoints: OidcEndpoints,
+    client_id: String,
+    client_secret: Option<String>,
+    scope: String,
+    mappings: OidcMappings,
+    domains: AHashSet<String>,
+    pub(crate) data_store: Store,
+}
+
+#[derive(Debug, Default)]
+struct OidcEndpoints {
+    token: Option<String>,
+    userinfo: Option<String>,
+    introspect: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct OidcMappings {
+    claim_name: String,
+    claim_email: String,
+    claim_description: String,
+    claim_quota: String,
+    claim_groups: String,
+    claim_protocols: String,
+}
@@ -60,6 +60,13 @@ impl SqlDirectory {
                 .value((&prefix, "columns.class"))
                 .unwrap_or_default()
                 .to_string(),
+            column_protocols: config
+                .value((&prefix, "columns.protocols"))
+                .unwrap_or_default()
+                .to_string(),
+            max_group_depth: config
+                .property_or_default((&prefix, "member-of.max-depth"), "0")
+                .unwrap_or_default(),
             ..Default::default()
         };
 
@@ -21,6 +21,7 @@
  * for more details.
 */
 
+use ahash::AHashSet;
 use mail_send::Credentials;
 use store::{NamedRows, Rows, Value};
 
@@ -108,22 +109,34 @@ impl SqlDirectory {
         }
         principal.name = account_name;
 
-        // Obtain members
+        // Obtain members, following nested groups up to the configured depth
         if return_member_of && !self.mappings.query_members.is_empty() {
-            for row in self
-                .store
-                .query::<Rows>(
-                    &self.mappings.query_members,
-                    vec![principal.name.clone().into()],
-                )
-                .await?
-                .rows
-            {
-                if let Some(Value::Text(account_id)) = row.values.first() {
-                    principal
-                        .member_of
-                        .push(self.data_store.get_or_create_account_id(account_id).await?);
+            let mut names = vec![principal.name.clone()];
+            let mut seen = AHashSet::from_iter([principal.name.clone()]);
+
+            for _ in 0..=self.mappings.max_group_depth {
+                let mut next_names = Vec::new();
+                for name in names {
+                    for row in self
+                        .store
+                        .query::<Rows>(&self.mappings.query_members, vec![name.into()])
+                        .await?
+                        .rows
+                    {
+                        if let Some(Value::Text(group)) = row.values.first() {
+                            if seen.insert(group.to_string()) {
+                                principal
+                                    .member_of
+                                    .push(self.data_store.get_or_create_account_id(group).await?);
+                                next_names.push(group.to_string());
+                            }
+                        }
+                    }
                 }
+                if next_names.is_empty() {
+                    break;
+                }
+                names = next_names;
             }
         }
 
@@ -224,6 +237,14 @@ impl SqlMappings {
                     if let Value::Integer(quota) = value {
                         principal.quota = quota as u64;
                     }
+                } else if name.eq_ignore_ascii_case(&self.column_protocols) {
+                    if let Value::Text(protocols) = value {
+                        principal.protocols = protocols
+                            .split(|c: char| c == ',' || c.is_whitespace())
+                            .filter(|p| !p.is_empty())
+                            .map(|p| p.to_lowercase())
+                            .collect();
+                    }
                 }
             }
         }
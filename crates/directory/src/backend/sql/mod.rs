@@ -45,4 +45,6 @@ pub(crate) struct SqlMappings {
     column_secret: String,
     column_quota: String,
     column_type: String,
+    column_protocols: String,
+    max_group_depth: usize,
 }
@@ -0,0 +1,224 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::fmt::Display;
+
+use mail_builder::encoders::base64::base64_encode;
+use password_hash::rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::Principal;
+
+pub const APP_PASSWORD_PREFIX: &str = "$app$";
+pub const APP_PASSWORD_PROTOCOLS: &[&str] = &["imap", "pop3", "smtp", "managesieve", "dav"];
+
+// Last used timestamps are only updated once per interval
+pub const APP_PASSWORD_USE_INTERVAL: u64 = 3600;
+
+const ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+// Application password, stored as an additional principal secret using the format:
+// $app$<name>$<protocol>,<protocol>$<last used>$<hash>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppPassword {
+    pub name: String,
+    pub protocols: Vec<String>,
+    pub last_used: u64,
+    hash: String,
+}
+
+impl AppPassword {
+    // Returns the new application password along with its clear text value,
+    // which is only revealed once to the user.
+    pub fn generate(name: &str, protocols: &[String]) -> Result<(Self, String), &'static str> {
+        let name = name.trim();
+        if name.is_empty() || name.len() > 64 || name.contains(['$', ',']) {
+            return Err("Invalid application password name.");
+        }
+
+        let mut app_protocols: Vec<String> = Vec::with_capacity(protocols.len());
+        for protocol in protocols {
+            let protocol = protocol.to_lowercase();
+            if !APP_PASSWORD_PROTOCOLS.contains(&protocol.as_str()) {
+                return Err("Unsupported application password protocol.");
+            } else if !app_protocols.contains(&protocol) {
+                app_protocols.push(protocol);
+            }
+        }
+        if app_protocols.is_empty() {
+            app_protocols = APP_PASSWORD_PROTOCOLS
+                .iter()
+                .map(|p| p.to_string())
+                .collect();
+        }
+
+        let mut bytes = [0u8; 20];
+        OsRng.fill_bytes(&mut bytes);
+        let mut secret = String::with_capacity(24);
+        for (pos, byte) in bytes.iter().enumerate() {
+            if pos > 0 && pos % 5 == 0 {
+                secret.push('-');
+            }
+            secret.push(ALPHABET[*byte as usize % ALPHABET.len()] as char);
+        }
+
+        Ok((
+            AppPassword {
+                name: name.to_string(),
+                protocols: app_protocols,
+                last_used: 0,
+                hash: hash(&secret),
+            },
+            secret,
+        ))
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.strip_prefix(APP_PASSWORD_PREFIX)?.splitn(4, '$');
+        let name = parts.next().filter(|name| !name.is_empty())?;
+        let protocols = parts.next()?;
+        let last_used = parts.next()?.parse::<u64>().ok()?;
+        let hash = parts.next()?;
+
+        Some(AppPassword {
+            name: name.to_string(),
+            protocols: protocols
+                .split(',')
+                .filter(|p| !p.is_empty())
+                .map(|p| p.to_string())
+                .collect(),
+            last_used,
+            hash: hash.to_string(),
+        })
+    }
+
+    pub fn verify(&self, secret: &str) -> bool {
+        hash(secret) == self.hash
+    }
+
+    pub fn is_protocol_allowed(&self, protocol: &str) -> bool {
+        self.protocols
+            .iter()
+            .any(|p| p.eq_ignore_ascii_case(protocol))
+    }
+}
+
+impl Display for AppPassword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{APP_PASSWORD_PREFIX}{}${}${}${}",
+            self.name,
+            self.protocols.join(","),
+            self.last_used,
+            self.hash
+        )
+    }
+}
+
+pub fn is_app_password(secret: &str) -> bool {
+    secret.starts_with(APP_PASSWORD_PREFIX)
+}
+
+// Application passwords are random tokens, a salted slow hash is not needed
+fn hash(secret: &str) -> String {
+    format!(
+        "{{SHA256}}{}",
+        String::from_utf8(base64_encode(&Sha256::digest(secret.as_bytes())).unwrap_or_default())
+            .unwrap_or_default()
+    )
+}
+
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Principal<T> {
+    pub fn app_passwords(&self) -> impl Iterator<Item = AppPassword> + '_ {
+        self.secrets
+            .iter()
+            .filter_map(|secret| AppPassword::parse(secret))
+    }
+
+    pub fn app_password(&self, secret: &str) -> Option<AppPassword> {
+        self.app_passwords()
+            .find(|app_password| app_password.verify(secret))
+    }
+
+    // Limits the principal to the protocols allowed by an application password,
+    // returns false when none of them are allowed for the principal.
+    pub fn restrict_to(&mut self, app_password: &AppPassword) -> bool {
+        if self.protocols.is_empty() {
+            self.protocols = app_password.protocols.clone();
+        } else {
+            self.protocols
+                .retain(|p| app_password.is_protocol_allowed(p));
+        }
+        !self.protocols.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Principal, Type};
+
+    use super::AppPassword;
+
+    #[test]
+    fn app_passwords() {
+        let (app_password, secret) =
+            AppPassword::generate("Phone", &["IMAP".to_string(), "smtp".to_string()]).unwrap();
+        assert_eq!(secret.len(), 23);
+        assert_eq!(app_password.protocols, vec!["imap", "smtp"]);
+        assert!(app_password.verify(&secret));
+        assert!(!app_password.verify("wrong"));
+        assert_eq!(
+            AppPassword::parse(&app_password.to_string()).as_ref(),
+            Some(&app_password)
+        );
+
+        for (name, protocols) in [
+            ("", vec![]),
+            ("My$app", vec![]),
+            ("Laptop", vec!["jmap".to_string()]),
+        ] {
+            assert!(
+                AppPassword::generate(name, &protocols).is_err(),
+                "{name} {protocols:?}"
+            );
+        }
+        assert_eq!(
+            AppPassword::generate("Tablet", &[]).unwrap().0.protocols,
+            vec!["imap", "pop3", "smtp", "managesieve", "dav"]
+        );
+
+        let mut principal = Principal::<u32> {
+            typ: Type::Individual,
+            name: "jdoe".to_string(),
+            secrets: vec!["secret".to_string(), app_password.to_string()],
+            ..Default::default()
+        };
+        assert_eq!(principal.app_password(&secret), Some(app_password.clone()));
+        assert_eq!(principal.app_password("secret"), None);
+        assert!(principal.restrict_to(&app_password));
+        assert_eq!(principal.protocols, vec!["imap", "smtp"]);
+        principal.protocols = vec!["pop3".to_string()];
+        assert!(!principal.restrict_to(&app_password));
+    }
+}
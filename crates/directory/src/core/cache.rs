@@ -24,15 +24,33 @@
 use std::{
     borrow::Borrow,
     hash::Hash,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
+use ahash::AHashMap;
+use mail_send::Credentials;
 use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
 use utils::config::{utils::AsKey, Config};
 
+use crate::Principal;
+
+pub type CredentialsKey = [u8; 32];
+pub type PendingLookup = Arc<OnceCell<Option<Principal<u32>>>>;
+
 pub struct CachedDirectory {
     cached_domains: Mutex<LookupCache<String>>,
     cached_rcpts: Mutex<LookupCache<String>>,
+    cached_auth_failures: Mutex<LookupCache<CredentialsKey>>,
+    pending_auth: Mutex<AHashMap<(CredentialsKey, bool), PendingLookup>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
 }
 
 #[allow(clippy::type_complexity)]
@@ -42,6 +60,7 @@ pub struct LookupCache<T: Hash + Eq> {
     cache_neg: lru_cache::LruCache<T, Instant, ahash::RandomState>,
     ttl_pos: Duration,
     ttl_neg: Duration,
+    stats: CacheStats,
 }
 
 impl CachedDirectory {
@@ -54,6 +73,9 @@ impl CachedDirectory {
         let cache_ttl_negative = config
             .property((&prefix, "cache.ttl.negative"))
             .unwrap_or_else(|| Duration::from_secs(3600));
+        let cache_ttl_auth_failure = config
+            .property((&prefix, "cache.ttl.auth-failure"))
+            .unwrap_or_else(|| Duration::from_secs(30));
 
         Some(CachedDirectory {
             cached_domains: Mutex::new(LookupCache::new(
@@ -66,6 +88,12 @@ impl CachedDirectory {
                 cache_ttl_positive,
                 cache_ttl_negative,
             )),
+            cached_auth_failures: Mutex::new(LookupCache::new(
+                cached_entries,
+                cache_ttl_auth_failure,
+                cache_ttl_auth_failure,
+            )),
+            pending_auth: Mutex::new(AHashMap::new()),
         })
     }
 
@@ -92,6 +120,75 @@ impl CachedDirectory {
             self.cached_domains.lock().insert_neg(domain.to_string());
         }
     }
+
+    pub fn credentials_key(credentials: &Credentials<String>) -> CredentialsKey {
+        // Only a digest of the credentials is kept in memory
+        let mut hasher = Sha256::new();
+        match credentials {
+            Credentials::Plain { username, secret } => {
+                hasher.update(b"plain\0");
+                hasher.update(username.as_bytes());
+                hasher.update(b"\0");
+                hasher.update(secret.as_bytes());
+            }
+            Credentials::XOauth2 { username, secret } => {
+                hasher.update(b"xoauth2\0");
+                hasher.update(username.as_bytes());
+                hasher.update(b"\0");
+                hasher.update(secret.as_bytes());
+            }
+            Credentials::OAuthBearer { token } => {
+                hasher.update(b"oauthbearer\0");
+                hasher.update(token.as_bytes());
+            }
+        }
+        hasher.finalize().into()
+    }
+
+    pub fn is_auth_failure(&self, key: &CredentialsKey) -> bool {
+        self.cached_auth_failures.lock().get(key).is_some()
+    }
+
+    pub fn set_auth_failure(&self, key: CredentialsKey) {
+        self.cached_auth_failures.lock().insert_neg(key);
+    }
+
+    // Lookups are only shared between callers requesting the same group memberships
+    pub fn pending_auth(&self, key: CredentialsKey, return_member_of: bool) -> PendingLookup {
+        self.pending_auth
+            .lock()
+            .entry((key, return_member_of))
+            .or_default()
+            .clone()
+    }
+
+    pub fn remove_pending_auth(
+        &self,
+        key: CredentialsKey,
+        return_member_of: bool,
+        lookup: &PendingLookup,
+    ) {
+        let mut pending = self.pending_auth.lock();
+        let key = (key, return_member_of);
+        if pending
+            .get(&key)
+            .map_or(false, |current| Arc::ptr_eq(current, lookup))
+        {
+            pending.remove(&key);
+        }
+    }
+
+    pub fn rcpt_stats(&self) -> CacheStats {
+        self.cached_rcpts.lock().stats()
+    }
+
+    pub fn domain_stats(&self) -> CacheStats {
+        self.cached_domains.lock().stats()
+    }
+
+    pub fn auth_stats(&self) -> CacheStats {
+        self.cached_auth_failures.lock().stats()
+    }
 }
 
 impl<T: Hash + Eq> LookupCache<T> {
@@ -101,6 +198,7 @@ impl<T: Hash + Eq> LookupCache<T> {
             cache_neg: lru_cache::LruCache::with_hasher(capacity, ahash::RandomState::new()),
             ttl_pos,
             ttl_neg,
+            stats: CacheStats::default(),
         }
     }
 
@@ -112,6 +210,7 @@ impl<T: Hash + Eq> LookupCache<T> {
         // Check positive cache
         if let Some(valid_until) = self.cache_pos.get_mut(name) {
             if *valid_until >= Instant::now() {
+                self.stats.hits += 1;
                 return Some(true);
             } else {
                 self.cache_pos.remove(name);
@@ -119,13 +218,21 @@ impl<T: Hash + Eq> LookupCache<T> {
         }
 
         // Check negative cache
-        let valid_until = self.cache_neg.get_mut(name)?;
-        if *valid_until >= Instant::now() {
-            Some(false)
-        } else {
-            self.cache_pos.remove(name);
-            None
+        if let Some(valid_until) = self.cache_neg.get_mut(name) {
+            if *valid_until >= Instant::now() {
+                self.stats.hits += 1;
+                return Some(false);
+            } else {
+                self.cache_neg.remove(name);
+            }
         }
+
+        self.stats.misses += 1;
+        None
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
     }
 
     pub fn insert_pos(&mut self, item: T) {
@@ -141,3 +248,39 @@ impl<T: Hash + Eq> LookupCache<T> {
         self.cache_neg.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mail_send::Credentials;
+    use utils::config::Config;
+
+    use super::CachedDirectory;
+
+    #[test]
+    fn pending_auth_by_member_of() {
+        let cache = CachedDirectory::try_from_config(
+            &mut Config::new("directory.test.cache.entries = 10").unwrap(),
+            ("directory", "test"),
+        )
+        .unwrap();
+        let key = CachedDirectory::credentials_key(&Credentials::Plain {
+            username: "john".to_string(),
+            secret: "secret".to_string(),
+        });
+
+        // Lookups are only coalesced when the member_of flag matches
+        let with_groups = cache.pending_auth(key, true);
+        let without_groups = cache.pending_auth(key, false);
+        assert!(!Arc::ptr_eq(&with_groups, &without_groups));
+        assert!(Arc::ptr_eq(&with_groups, &cache.pending_auth(key, true)));
+
+        cache.remove_pending_auth(key, true, &with_groups);
+        assert!(!Arc::ptr_eq(&with_groups, &cache.pending_auth(key, true)));
+        assert!(Arc::ptr_eq(
+            &without_groups,
+            &cache.pending_auth(key, false)
+        ));
+    }
+}
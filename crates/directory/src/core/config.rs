@@ -33,13 +33,13 @@ use ahash::AHashMap;
 
 use crate::{
     backend::{
-        imap::ImapDirectory, ldap::LdapDirectory, memory::MemoryDirectory, smtp::SmtpDirectory,
-        sql::SqlDirectory,
+        http::HttpDirectory, imap::ImapDirectory, ldap::LdapDirectory, memory::MemoryDirectory,
+        oidc::OidcDirectory, smtp::SmtpDirectory, sql::SqlDirectory,
     },
     Directories, Directory, DirectoryInner,
 };
 
-use super::cache::CachedDirectory;
+use super::{cache::CachedDirectory, secret::SecretHash};
 
 impl Directories {
     pub async fn parse(config: &mut Config, stores: &Stores, data_store: Store) -> Self {
@@ -91,6 +91,10 @@ impl Directories {
                 "lmtp" => {
                     SmtpDirectory::from_config(config, prefix, true).map(DirectoryInner::Smtp)
                 }
+                "oidc" => OidcDirectory::from_config(config, prefix, data_store.clone())
+                    .map(DirectoryInner::Oidc),
+                "http" => HttpDirectory::from_config(config, prefix, data_store.clone())
+                    .map(DirectoryInner::Http),
                 "memory" => MemoryDirectory::from_config(config, prefix, data_store.clone())
                     .await
                     .map(DirectoryInner::Memory),
@@ -103,9 +107,24 @@ impl Directories {
 
             // Build directory
             if let Some(store) = store {
+                let (rehash_secrets, scram_verifiers) =
+                    if matches!(store, DirectoryInner::Internal(_)) {
+                        (
+                            config
+                                .property::<Option<SecretHash>>(("directory", id, "secret.rehash"))
+                                .flatten(),
+                            config
+                                .property_or_default(("directory", id, "secret.scram"), "true")
+                                .unwrap_or(true),
+                        )
+                    } else {
+                        (None, false)
+                    };
                 let directory = Arc::new(Directory {
                     store,
                     cache: CachedDirectory::try_from_config(config, ("directory", id)),
+                    rehash_secrets,
+                    scram_verifiers,
                 });
 
                 // Add directory
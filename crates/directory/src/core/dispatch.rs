@@ -21,22 +21,147 @@
  * for more details.
 */
 
+use mail_send::Credentials;
+
 use crate::{
-    backend::internal::lookup::DirectoryStore, Directory, DirectoryInner, Principal, QueryBy,
+    backend::internal::{lookup::DirectoryStore, manage::ManageDirectory},
+    Directory, DirectoryInner, Principal, QueryBy,
 };
 
+use super::{cache::CachedDirectory, scram::is_scram_verifier, secret::is_weak_secret_hash};
+
 impl Directory {
     pub async fn query(
         &self,
         by: QueryBy<'_>,
         return_member_of: bool,
+    ) -> crate::Result<Option<Principal<u32>>> {
+        match (&self.cache, by) {
+            (Some(cache), QueryBy::Credentials(credentials)) => {
+                // Check the failed credentials cache
+                let key = CachedDirectory::credentials_key(credentials);
+                if cache.is_auth_failure(&key) {
+                    tracing::debug!(
+                        context = "directory",
+                        event = "cache-hit",
+                        "Credentials found in the authentication failure cache."
+                    );
+                    return Ok(None);
+                }
+
+                // Coalesce concurrent lookups for the same credentials
+                let lookup = cache.pending_auth(key, return_member_of);
+                let result = lookup
+                    .get_or_try_init(|| {
+                        self.query_store(QueryBy::Credentials(credentials), return_member_of)
+                    })
+                    .await
+                    .cloned();
+                cache.remove_pending_auth(key, return_member_of, &lookup);
+
+                // Update cache
+                let result = result?;
+                if result.is_none() {
+                    cache.set_auth_failure(key);
+                }
+
+                Ok(result)
+            }
+            (_, by) => self.query_store(by, return_member_of).await,
+        }
+    }
+
+    async fn query_store(
+        &self,
+        by: QueryBy<'_>,
+        return_member_of: bool,
     ) -> crate::Result<Option<Principal<u32>>> {
         match &self.store {
-            DirectoryInner::Internal(store) => store.query(by, return_member_of).await,
+            DirectoryInner::Internal(store) => {
+                let secret = match &by {
+                    QueryBy::Credentials(Credentials::Plain { secret, .. })
+                        if self.rehash_secrets.is_some() || self.scram_verifiers =>
+                    {
+                        Some(secret.to_string())
+                    }
+                    _ => None,
+                };
+                let result = store.query(by, return_member_of).await;
+
+                // Upgrade weak password hashes and store SCRAM verifiers in the background,
+                // application passwords are left untouched.
+                if let (Ok(Some(principal)), Some(secret)) = (&result, secret) {
+                    let is_app_password = principal.app_password(&secret).is_some();
+                    let rehash = self.rehash_secrets.filter(|_| {
+                        !is_app_password && principal.secrets.iter().any(|s| is_weak_secret_hash(s))
+                    });
+                    let add_verifier = self.scram_verifiers
+                        && !is_app_password
+                        && !principal.secrets.iter().any(|s| is_scram_verifier(s));
+                    if rehash.is_some() || add_verifier {
+                        let store = store.clone();
+                        let account_id = principal.id;
+                        tokio::spawn(async move {
+                            if let Some(algorithm) = rehash {
+                                match store
+                                    .update_secret_if_weaker(account_id, &secret, algorithm)
+                                    .await
+                                {
+                                    Ok(true) => {
+                                        tracing::debug!(
+                                            context = "directory",
+                                            event = "rehash",
+                                            account_id = account_id,
+                                            "Upgraded weak password hash."
+                                        );
+                                    }
+                                    Ok(false) => (),
+                                    Err(err) => {
+                                        tracing::warn!(
+                                            context = "directory",
+                                            event = "error",
+                                            account_id = account_id,
+                                            reason = ?err,
+                                            "Failed to upgrade weak password hash."
+                                        );
+                                    }
+                                }
+                            }
+
+                            if add_verifier {
+                                match store.update_scram_verifier(account_id, &secret).await {
+                                    Ok(true) => {
+                                        tracing::debug!(
+                                            context = "directory",
+                                            event = "scram-verifier",
+                                            account_id = account_id,
+                                            "Stored SCRAM verifier."
+                                        );
+                                    }
+                                    Ok(false) => (),
+                                    Err(err) => {
+                                        tracing::warn!(
+                                            context = "directory",
+                                            event = "error",
+                                            account_id = account_id,
+                                            reason = ?err,
+                                            "Failed to store SCRAM verifier."
+                                        );
+                                    }
+                                }
+                            }
+                        });
+                    }
+                }
+
+                result
+            }
             DirectoryInner::Ldap(store) => store.query(by, return_member_of).await,
             DirectoryInner::Sql(store) => store.query(by, return_member_of).await,
             DirectoryInner::Imap(store) => store.query(by).await,
             DirectoryInner::Smtp(store) => store.query(by).await,
+            DirectoryInner::Oidc(store) => store.query(by, return_member_of).await,
+            DirectoryInner::Http(store) => store.query(by, return_member_of).await,
             DirectoryInner::Memory(store) => store.query(by).await,
         }
     }
@@ -48,6 +173,8 @@ impl Directory {
             DirectoryInner::Sql(store) => store.email_to_ids(email).await,
             DirectoryInner::Imap(store) => store.email_to_ids(email).await,
             DirectoryInner::Smtp(store) => store.email_to_ids(email).await,
+            DirectoryInner::Oidc(store) => store.email_to_ids(email).await,
+            DirectoryInner::Http(store) => store.email_to_ids(email).await,
             DirectoryInner::Memory(store) => store.email_to_ids(email).await,
         }
     }
@@ -66,6 +193,8 @@ impl Directory {
             DirectoryInner::Sql(store) => store.is_local_domain(domain).await,
             DirectoryInner::Imap(store) => store.is_local_domain(domain).await,
             DirectoryInner::Smtp(store) => store.is_local_domain(domain).await,
+            DirectoryInner::Oidc(store) => store.is_local_domain(domain).await,
+            DirectoryInner::Http(store) => store.is_local_domain(domain).await,
             DirectoryInner::Memory(store) => store.is_local_domain(domain).await,
         }?;
 
@@ -91,6 +220,8 @@ impl Directory {
             DirectoryInner::Sql(store) => store.rcpt(email).await,
             DirectoryInner::Imap(store) => store.rcpt(email).await,
             DirectoryInner::Smtp(store) => store.rcpt(email).await,
+            DirectoryInner::Oidc(store) => store.rcpt(email).await,
+            DirectoryInner::Http(store) => store.rcpt(email).await,
             DirectoryInner::Memory(store) => store.rcpt(email).await,
         }?;
 
@@ -109,6 +240,8 @@ impl Directory {
             DirectoryInner::Sql(store) => store.vrfy(address).await,
             DirectoryInner::Imap(store) => store.vrfy(address).await,
             DirectoryInner::Smtp(store) => store.vrfy(address).await,
+            DirectoryInner::Oidc(store) => store.vrfy(address).await,
+            DirectoryInner::Http(store) => store.vrfy(address).await,
             DirectoryInner::Memory(store) => store.vrfy(address).await,
         }
     }
@@ -120,6 +253,8 @@ impl Directory {
             DirectoryInner::Sql(store) => store.expn(address).await,
             DirectoryInner::Imap(store) => store.expn(address).await,
             DirectoryInner::Smtp(store) => store.expn(address).await,
+            DirectoryInner::Oidc(store) => store.expn(address).await,
+            DirectoryInner::Http(store) => store.expn(address).await,
             DirectoryInner::Memory(store) => store.expn(address).await,
         }
     }
@@ -21,7 +21,9 @@
  * for more details.
 */
 
+pub mod app_password;
 pub mod cache;
 pub mod config;
 pub mod dispatch;
+pub mod scram;
 pub mod secret;
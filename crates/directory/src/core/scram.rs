@@ -0,0 +1,356 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::fmt::Display;
+
+use hmac::{Hmac, Mac};
+use mail_builder::encoders::base64::base64_encode;
+use mail_parser::decoders::base64::base64_decode;
+use password_hash::rand_core::{OsRng, RngCore};
+use pbkdf2::pbkdf2_hmac;
+use sha2::{Digest, Sha256};
+
+pub const SCRAM_PREFIX: &str = "{SCRAM-SHA-256}";
+pub const SCRAM_ITERATIONS: u32 = 4096;
+
+// Salted SCRAM-SHA-256 verifier, stored using the Dovecot format:
+// {SCRAM-SHA-256}<iterations>,<salt>,<stored key>,<server key>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScramVerifier {
+    pub iterations: u32,
+    pub salt: Vec<u8>,
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+}
+
+pub struct ScramExchange {
+    username: String,
+    gs2_header: String,
+    client_first_bare: String,
+    nonce: String,
+    server_first: String,
+    channel_binding: Option<Vec<u8>>,
+    verifier: Option<ScramVerifier>,
+}
+
+impl ScramVerifier {
+    pub fn new(secret: &str, salt: Vec<u8>, iterations: u32) -> Self {
+        // Secrets are used as-is, SASLprep normalization is not applied
+        let mut salted_password = [0u8; 32];
+        pbkdf2_hmac::<Sha256>(secret.as_bytes(), &salt, iterations, &mut salted_password);
+        let client_key = hmac_sha256(&salted_password, b"Client Key");
+
+        ScramVerifier {
+            iterations,
+            salt,
+            stored_key: Sha256::digest(client_key).to_vec(),
+            server_key: hmac_sha256(&salted_password, b"Server Key"),
+        }
+    }
+
+    pub fn generate(secret: &str) -> Self {
+        let mut salt = vec![0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Self::new(secret, salt, SCRAM_ITERATIONS)
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.strip_prefix(SCRAM_PREFIX).unwrap_or(value).split(',');
+        let iterations = parts.next()?.parse::<u32>().ok().filter(|i| *i > 0)?;
+        let salt = base64_decode(parts.next()?.as_bytes())?;
+        let stored_key = base64_decode(parts.next()?.as_bytes())?;
+        let server_key = base64_decode(parts.next()?.as_bytes())?;
+
+        if parts.next().is_none() && stored_key.len() == 32 && server_key.len() == 32 {
+            Some(ScramVerifier {
+                iterations,
+                salt,
+                stored_key,
+                server_key,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn verify(&self, secret: &str) -> bool {
+        Self::new(secret, self.salt.clone(), self.iterations).stored_key == self.stored_key
+    }
+}
+
+pub fn is_scram_verifier(secret: &str) -> bool {
+    secret.starts_with(SCRAM_PREFIX)
+}
+
+impl ScramExchange {
+    // Parses a client-first message. The channel binding data is only provided
+    // when the client selected the -PLUS variant of the mechanism.
+    pub fn parse(
+        message: &[u8],
+        channel_binding: Option<Vec<u8>>,
+        is_plus_available: bool,
+    ) -> Result<Self, &'static str> {
+        let message = std::str::from_utf8(message).map_err(|_| "Invalid SCRAM message.")?;
+        let (cbind_flag, message) = message.split_once(',').ok_or("Invalid GS2 header.")?;
+        let (authzid, client_first_bare) = message.split_once(',').ok_or("Invalid GS2 header.")?;
+
+        match (cbind_flag, &channel_binding) {
+            ("n", None) | ("p=tls-exporter", Some(_)) => (),
+            ("y", None) if !is_plus_available => (),
+            ("y", None) => return Err("Channel binding downgrade detected."),
+            _ => return Err("Unsupported channel binding."),
+        }
+        if !authzid.is_empty() {
+            return Err("Authorization identities are not supported.");
+        }
+
+        let mut username = None;
+        let mut nonce = None;
+        for (pos, attribute) in client_first_bare.split(',').enumerate() {
+            match attribute.split_once('=') {
+                Some(("m", _)) if pos == 0 => return Err("Mandatory extensions not supported."),
+                Some(("n", value)) if pos == 0 => {
+                    username = decode_sasl_name(value);
+                }
+                Some(("r", value)) if pos == 1 && !value.is_empty() => {
+                    nonce = Some(value.to_string());
+                }
+                _ if pos > 1 => (),
+                _ => return Err("Invalid SCRAM message."),
+            }
+        }
+
+        match (username, nonce) {
+            (Some(username), Some(nonce)) if !username.is_empty() => Ok(ScramExchange {
+                username,
+                gs2_header: format!("{cbind_flag},{authzid},"),
+                client_first_bare: client_first_bare.to_string(),
+                nonce,
+                server_first: String::new(),
+                channel_binding,
+                verifier: None,
+            }),
+            _ => Err("Invalid SCRAM message."),
+        }
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn server_first(&mut self, verifier: Option<ScramVerifier>) -> Vec<u8> {
+        let mut server_nonce = [0u8; 18];
+        OsRng.fill_bytes(&mut server_nonce);
+        self.server_first_with_nonce(verifier, &b64(&server_nonce))
+    }
+
+    fn server_first_with_nonce(
+        &mut self,
+        verifier: Option<ScramVerifier>,
+        server_nonce: &str,
+    ) -> Vec<u8> {
+        // Unknown accounts get a verifier that can't be matched, so the exchange
+        // only fails at the proof check and does not reveal whether the account exists.
+        let verifier = verifier.unwrap_or_else(|| ScramVerifier {
+            iterations: SCRAM_ITERATIONS,
+            salt: Sha256::digest(self.username.as_bytes())[..16].to_vec(),
+            stored_key: vec![],
+            server_key: vec![],
+        });
+
+        self.nonce.push_str(server_nonce);
+        self.server_first = format!(
+            "r={},s={},i={}",
+            self.nonce,
+            b64(&verifier.salt),
+            verifier.iterations
+        );
+        self.verifier = Some(verifier);
+        self.server_first.as_bytes().to_vec()
+    }
+
+    // Verifies the client proof and returns the server-final message
+    pub fn server_final(&self, message: &[u8]) -> Option<Vec<u8>> {
+        let verifier = self.verifier.as_ref()?;
+        let message = std::str::from_utf8(message).ok()?;
+        let (client_final_bare, proof) = message.rsplit_once(",p=")?;
+
+        let mut cbind_input = None;
+        let mut nonce = None;
+        for attribute in client_final_bare.split(',') {
+            match attribute.split_once('=') {
+                Some(("c", value)) => cbind_input = base64_decode(value.as_bytes()),
+                Some(("r", value)) => nonce = Some(value),
+                _ => (),
+            }
+        }
+
+        // Validate nonce and channel binding
+        let mut expected_cbind_input = self.gs2_header.as_bytes().to_vec();
+        if let Some(channel_binding) = &self.channel_binding {
+            expected_cbind_input.extend_from_slice(channel_binding);
+        }
+        if nonce != Some(self.nonce.as_str()) || cbind_input? != expected_cbind_input {
+            return None;
+        }
+
+        // Recover the client key from the proof and compare it against the stored key
+        let auth_message = format!(
+            "{},{},{}",
+            self.client_first_bare, self.server_first, client_final_bare
+        );
+        let client_signature = hmac_sha256(&verifier.stored_key, auth_message.as_bytes());
+        let proof = base64_decode(proof.as_bytes())?;
+        if proof.len() != client_signature.len() {
+            return None;
+        }
+        let client_key = proof
+            .iter()
+            .zip(client_signature.iter())
+            .map(|(a, b)| a ^ b)
+            .collect::<Vec<_>>();
+        let stored_key = Sha256::digest(client_key);
+        if stored_key.len() != verifier.stored_key.len()
+            || stored_key
+                .iter()
+                .zip(verifier.stored_key.iter())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                != 0
+        {
+            return None;
+        }
+
+        let server_signature = hmac_sha256(&verifier.server_key, auth_message.as_bytes());
+        Some(format!("v={}", b64(&server_signature)).into_bytes())
+    }
+}
+
+impl Display for ScramVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{SCRAM_PREFIX}{},{},{},{}",
+            self.iterations,
+            b64(&self.salt),
+            b64(&self.stored_key),
+            b64(&self.server_key)
+        )
+    }
+}
+
+fn decode_sasl_name(value: &str) -> Option<String> {
+    let mut name = String::with_capacity(value.len());
+    let mut chars = value.split('=');
+    name.push_str(chars.next()?);
+    for part in chars {
+        if let Some(part) = part.strip_prefix("2C") {
+            name.push(',');
+            name.push_str(part);
+        } else if let Some(part) = part.strip_prefix("3D") {
+            name.push('=');
+            name.push_str(part);
+        } else {
+            return None;
+        }
+    }
+    Some(name)
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn b64(bytes: &[u8]) -> String {
+    String::from_utf8(base64_encode(bytes).unwrap_or_default()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use mail_parser::decoders::base64::base64_decode;
+
+    use super::{ScramExchange, ScramVerifier};
+
+    #[test]
+    fn scram_sha256_exchange() {
+        // Example from RFC 7677
+        let verifier = ScramVerifier::new(
+            "pencil",
+            base64_decode(b"W22ZaJ0SNY7soEsUEjb6gQ==").unwrap(),
+            4096,
+        );
+        assert_eq!(
+            ScramVerifier::parse(&verifier.to_string()).as_ref(),
+            Some(&verifier)
+        );
+        assert!(verifier.verify("pencil"));
+        assert!(!verifier.verify("pencils"));
+
+        let client_final = concat!(
+            "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,",
+            "p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+        );
+        for (verifier, expected) in [
+            (
+                Some(verifier.clone()),
+                Some("v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4="),
+            ),
+            (Some(ScramVerifier::generate("pencil")), None),
+            (None, None),
+        ] {
+            let mut exchange =
+                ScramExchange::parse(b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO", None, false).unwrap();
+            assert_eq!(exchange.username(), "user");
+            let server_first =
+                exchange.server_first_with_nonce(verifier, "%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0");
+            assert!(server_first
+                .starts_with(b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s="));
+            assert_eq!(
+                exchange.server_final(client_final.as_bytes()),
+                expected.map(|v| v.as_bytes().to_vec())
+            );
+        }
+
+        // Channel binding negotiation
+        for (message, channel_binding, is_plus_available, expected) in [
+            ("n,,n=user,r=abc", None, true, true),
+            ("y,,n=user,r=abc", None, false, true),
+            ("y,,n=user,r=abc", None, true, false),
+            ("p=tls-exporter,,n=user,r=abc", Some(vec![1u8]), true, true),
+            ("p=tls-unique,,n=user,r=abc", Some(vec![1u8]), true, false),
+            ("n,,n=user,r=abc", Some(vec![1u8]), true, false),
+            ("n,a=admin,n=user,r=abc", None, true, false),
+            ("n,,m=ext,n=user,r=abc", None, true, false),
+            ("n,,n=us=3Der,r=abc", None, true, true),
+            ("n,,n=us=er,r=abc", None, true, false),
+        ] {
+            assert_eq!(
+                ScramExchange::parse(message.as_bytes(), channel_binding, is_plus_available)
+                    .is_ok(),
+                expected,
+                "{message}"
+            );
+        }
+    }
+}
@@ -24,7 +24,7 @@
 use argon2::Argon2;
 use mail_builder::encoders::base64::base64_encode;
 use mail_parser::decoders::base64::base64_decode;
-use password_hash::PasswordHash;
+use password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, SaltString};
 use pbkdf2::Pbkdf2;
 use pwhash::{bcrypt, bsdi_crypt, md5_crypt, sha1_crypt, sha256_crypt, sha512_crypt, unix_crypt};
 use scrypt::Scrypt;
@@ -33,9 +33,21 @@ use sha1::Sha1;
 use sha2::Sha256;
 use sha2::Sha512;
 use tokio::sync::oneshot;
+use utils::config::utils::ParseValue;
 
 use crate::Principal;
 
+use super::{
+    app_password::is_app_password,
+    scram::{ScramVerifier, SCRAM_PREFIX},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretHash {
+    Argon2,
+    Bcrypt,
+}
+
 impl<T: serde::Serialize + serde::de::DeserializeOwned> Principal<T> {
     pub async fn verify_secret(&self, secret: &str) -> bool {
         for hashed_secret in &self.secrets {
@@ -45,6 +57,71 @@ impl<T: serde::Serialize + serde::de::DeserializeOwned> Principal<T> {
         }
         false
     }
+
+    pub fn verify_apop(&self, timestamp: &str, digest: &str) -> bool {
+        // APOP digests can only be verified against secrets stored in clear text
+        self.clear_text_secrets().any(|secret| {
+            let expected = md5::compute(format!("{timestamp}{secret}").as_bytes());
+            format!("{expected:x}").eq_ignore_ascii_case(digest)
+        })
+    }
+
+    pub fn verify_cram_md5(&self, challenge: &str, digest: &str) -> bool {
+        // CRAM-MD5 digests can only be verified against secrets stored in clear text
+        self.clear_text_secrets().any(|secret| {
+            hmac_md5(secret.as_bytes(), challenge.as_bytes())
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+                .eq_ignore_ascii_case(digest)
+        })
+    }
+
+    // Returns the stored SCRAM verifier or derives one from a clear text secret
+    pub fn scram_verifier(&self) -> Option<ScramVerifier> {
+        self.secrets
+            .iter()
+            .find_map(|secret| {
+                secret
+                    .strip_prefix(SCRAM_PREFIX)
+                    .and_then(ScramVerifier::parse)
+            })
+            .or_else(|| {
+                self.clear_text_secrets()
+                    .next()
+                    .map(ScramVerifier::generate)
+            })
+    }
+
+    fn clear_text_secrets(&self) -> impl Iterator<Item = &str> {
+        self.secrets
+            .iter()
+            .filter_map(|secret| match secret.strip_prefix('{') {
+                Some(secret) => match secret.split_once('}') {
+                    Some(("PLAIN" | "plain" | "CLEAR" | "clear", secret)) => Some(secret),
+                    _ => None,
+                },
+                None if !secret.starts_with(['$', '_']) => Some(secret.as_str()),
+                None => None,
+            })
+    }
+}
+
+fn hmac_md5(key: &[u8], data: &[u8]) -> [u8; 16] {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..16].copy_from_slice(&md5::compute(key).0);
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = md5::Context::new();
+    inner.consume(block.map(|byte| byte ^ 0x36));
+    inner.consume(data);
+    let mut outer = md5::Context::new();
+    outer.consume(block.map(|byte| byte ^ 0x5c));
+    outer.consume(inner.compute().0);
+    outer.compute().0
 }
 
 async fn verify_hash_prefix(hashed_secret: &str, secret: &str) -> bool {
@@ -97,6 +174,9 @@ async fn verify_hash_prefix(hashed_secret: &str, secret: &str) -> bool {
     } else if hashed_secret.starts_with("$1") {
         // MD5 based hash
         md5_crypt::verify(secret, hashed_secret)
+    } else if is_app_password(hashed_secret) {
+        // Application passwords are only accepted by the internal directory
+        false
     } else {
         // Unknown hash
         tracing::warn!(
@@ -190,6 +270,8 @@ pub async fn verify_secret_hash(hashed_secret: &str, secret: &str) -> bool {
                     }
                 }
                 "PLAIN" | "plain" | "CLEAR" | "clear" => hashed_secret == secret,
+                "SCRAM-SHA-256" => ScramVerifier::parse(hashed_secret)
+                    .map_or(false, |verifier| verifier.verify(secret)),
                 _ => {
                     tracing::warn!(
                         context = "directory",
@@ -213,3 +295,147 @@ pub async fn verify_secret_hash(hashed_secret: &str, secret: &str) -> bool {
         hashed_secret == secret
     }
 }
+
+// Clear text secrets are left untouched as they are required by APOP
+pub fn is_weak_secret_hash(hashed_secret: &str) -> bool {
+    if let Some(hashed_secret) = hashed_secret.strip_prefix('{') {
+        match hashed_secret.split_once('}') {
+            Some((algo, hashed_secret)) => match algo {
+                "SHA" | "SSHA" | "MD5" => true,
+                "CRYPT" | "crypt" => {
+                    !hashed_secret.starts_with('$') || is_weak_secret_hash(hashed_secret)
+                }
+                _ => false,
+            },
+            None => false,
+        }
+    } else {
+        // MD5-crypt, SHA1-crypt and DES-based hashes
+        hashed_secret.starts_with("$1$")
+            || hashed_secret.starts_with("$sha1")
+            || hashed_secret.starts_with('_')
+    }
+}
+
+pub async fn hash_secret(algorithm: SecretHash, secret: &str) -> Option<String> {
+    let (tx, rx) = oneshot::channel();
+    let secret = secret.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let result = match algorithm {
+            SecretHash::Argon2 => Argon2::default()
+                .hash_password(secret.as_bytes(), &SaltString::generate(&mut OsRng))
+                .map(|hash| hash.to_string())
+                .map_err(|err| err.to_string()),
+            SecretHash::Bcrypt => bcrypt::hash(&secret).map_err(|err| err.to_string()),
+        };
+        tx.send(result).ok();
+    });
+
+    match rx.await {
+        Ok(Ok(hash)) => Some(hash),
+        Ok(Err(err)) => {
+            tracing::warn!(
+                context = "directory",
+                event = "error",
+                reason = err,
+                "Failed to hash secret"
+            );
+            None
+        }
+        Err(_) => {
+            tracing::warn!(context = "directory", event = "error", "Thread join error");
+            None
+        }
+    }
+}
+
+impl ParseValue for SecretHash {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value {
+            "argon2" => Ok(SecretHash::Argon2),
+            "bcrypt" => Ok(SecretHash::Bcrypt),
+            _ => Err(format!("Invalid secret hash algorithm {value:?}.")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{scram::ScramVerifier, secret::verify_secret_hash},
+        Principal,
+    };
+
+    #[test]
+    fn verify_cram_md5() {
+        // Example from RFC 2195
+        let challenge = "<1896.697170952@postoffice.reston.mci.net>";
+        let digest = "b913a602c7eda7a495b4e6e7334d3890";
+
+        for (secret, expected) in [
+            ("tanstaaftanstaaf", true),
+            ("{CLEAR}tanstaaftanstaaf", true),
+            ("{SHA}tanstaaftanstaaf", false),
+            ("tanstaaf", false),
+        ] {
+            let principal = Principal::<u32> {
+                secrets: vec![secret.to_string()],
+                ..Default::default()
+            };
+            assert_eq!(
+                principal.verify_cram_md5(challenge, digest),
+                expected,
+                "{secret}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_scram_secret() {
+        let verifier = ScramVerifier::generate("secret").to_string();
+        assert!(verify_secret_hash(&verifier, "secret").await);
+        assert!(!verify_secret_hash(&verifier, "secrets").await);
+
+        let principal = Principal::<u32> {
+            secrets: vec![
+                "$2y$05$bvIG6Nmid91Mu9RcmmWZfO5HJIMCT8riNW0hEp8f6/FuA2/mHZFpe".to_string(),
+                verifier.clone(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            principal.scram_verifier().map(|v| v.to_string()),
+            Some(verifier)
+        );
+        let principal = Principal::<u32> {
+            secrets: vec!["secret".to_string()],
+            ..Default::default()
+        };
+        assert!(principal.scram_verifier().unwrap().verify("secret"));
+    }
+
+    #[test]
+    fn verify_apop() {
+        // Example from RFC 1939
+        let timestamp = "<1896.697170952@dbc.mtview.ca.us>";
+        let digest = "c4c9334bac560ecc979e58001b3e22fb";
+
+        for (secret, expected) in [
+            ("tanstaaf", true),
+            ("{PLAIN}tanstaaf", true),
+            ("{SHA}tanstaaf", false),
+            ("tanstaafl", false),
+        ] {
+            let principal = Principal::<u32> {
+                secrets: vec![secret.to_string()],
+                ..Default::default()
+            };
+            assert_eq!(
+                principal.verify_apop(timestamp, digest),
+                expected,
+                "{secret}"
+            );
+        }
+    }
+}
@@ -21,15 +21,17 @@
  * for more details.
 */
 
-use core::cache::CachedDirectory;
+use core::{cache::CachedDirectory, secret::SecretHash};
 use std::{fmt::Debug, sync::Arc};
 
 use ahash::AHashMap;
 use backend::{
+    http::HttpDirectory,
     imap::{ImapDirectory, ImapError},
     internal::PrincipalField,
     ldap::LdapDirectory,
     memory::MemoryDirectory,
+    oidc::OidcDirectory,
     smtp::SmtpDirectory,
     sql::SqlDirectory,
 };
@@ -44,6 +46,8 @@ pub mod core;
 pub struct Directory {
     pub store: DirectoryInner,
     pub cache: Option<CachedDirectory>,
+    pub rehash_secrets: Option<SecretHash>,
+    pub scram_verifiers: bool,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -64,6 +68,8 @@ pub struct Principal<T> {
     pub member_of: Vec<T>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocols: Vec<String>,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -91,6 +97,7 @@ pub enum DirectoryError {
     Store(store::Error),
     Imap(ImapError),
     Smtp(mail_send::Error),
+    Http(String),
     Pool(String),
     Management(ManagementError),
     TimedOut,
@@ -113,6 +120,8 @@ pub enum DirectoryInner {
     Sql(SqlDirectory),
     Imap(ImapDirectory),
     Smtp(SmtpDirectory),
+    Oidc(OidcDirectory),
+    Http(HttpDirectory),
     Memory(MemoryDirectory),
 }
 
@@ -134,6 +143,15 @@ impl<T: serde::Serialize + serde::de::DeserializeOwned> Principal<T> {
     pub fn description(&self) -> Option<&str> {
         self.description.as_deref()
     }
+
+    // An empty list means that the principal may authenticate using any protocol
+    pub fn is_protocol_allowed(&self, protocol: &str) -> bool {
+        self.protocols.is_empty()
+            || self
+                .protocols
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(protocol))
+    }
 }
 
 impl Default for Directory {
@@ -141,6 +159,8 @@ impl Default for Directory {
         Self {
             store: DirectoryInner::Internal(Store::None),
             cache: None,
+            rehash_secrets: None,
+            scram_verifiers: false,
         }
     }
 }
@@ -278,6 +298,24 @@ impl From<mail_send::Error> for DirectoryError {
     }
 }
 
+impl From<reqwest::Error> for DirectoryError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return DirectoryError::timeout("http");
+        }
+
+        tracing::warn!(
+            context = "directory",
+            event = "error",
+            protocol = "http",
+            reason = %error,
+            "HTTP directory error"
+        );
+
+        DirectoryError::Http(error.to_string())
+    }
+}
+
 impl DirectoryError {
     pub fn unsupported(protocol: &str, method: &str) -> Self {
         tracing::warn!(
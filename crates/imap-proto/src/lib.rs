@@ -89,6 +89,17 @@ pub enum Command {
 
     // RFC 2971
     Id,
+
+    // RFC 5465
+    Notify,
+
+    // RFC 4978
+    Compress,
+
+    // RFC 9208
+    GetQuota,
+    GetQuotaRoot,
+    SetQuota,
 }
 
 impl Command {
@@ -145,6 +156,7 @@ pub enum ResponseCode {
     ReadOnly,
     ReadWrite,
     ServerBug,
+    TooBig,
     TryCreate,
     UidNext,
     UidNotSticky,
@@ -167,6 +179,12 @@ pub enum ResponseCode {
 
     // USEATTR
     UseAttr,
+
+    // NOTIFY
+    BadEvent,
+
+    // COMPRESS
+    CompressionActive,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -32,6 +32,8 @@ pub mod fetch;
 pub mod list;
 pub mod login;
 pub mod lsub;
+pub mod notify;
+pub mod quota;
 pub mod rename;
 pub mod search;
 pub mod select;
@@ -75,6 +77,8 @@ impl CommandParser for Command {
             b"STATUS" => Some(Command::Status),
             b"APPEND" => Some(Command::Append),
             b"IDLE" => Some(Command::Idle),
+            b"NOTIFY" => Some(Command::Notify),
+            b"COMPRESS" => Some(Command::Compress),
             b"CLOSE" => Some(Command::Close),
             b"UNSELECT" => Some(Command::Unselect),
             b"EXPUNGE" => Some(Command::Expunge(uid)),
@@ -92,6 +96,9 @@ impl CommandParser for Command {
             b"GETACL" => Some(Command::GetAcl),
             b"LISTRIGHTS" => Some(Command::ListRights),
             b"MYRIGHTS" => Some(Command::MyRights),
+            b"GETQUOTA" => Some(Command::GetQuota),
+            b"GETQUOTAROOT" => Some(Command::GetQuotaRoot),
+            b"SETQUOTA" => Some(Command::SetQuota),
             b"UNAUTHENTICATE" => Some(Command::Unauthenticate),
             b"ID" => Some(Command::Id),
             _ => None,
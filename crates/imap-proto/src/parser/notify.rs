@@ -0,0 +1,308 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{iter::Peekable, vec::IntoIter};
+
+use crate::{
+    protocol::{
+        notify::{self, Event, EventGroup, MailboxFilter},
+        ProtocolVersion,
+    },
+    receiver::{Request, Token},
+    utf7::utf7_maybe_decode,
+    Command,
+};
+
+impl Request<Command> {
+    pub fn parse_notify(self, version: ProtocolVersion) -> crate::Result<notify::Arguments> {
+        let mut tokens = self.tokens.into_iter().peekable();
+        match tokens.next() {
+            Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"NONE") => {
+                return if tokens.next().is_none() {
+                    Ok(notify::Arguments {
+                        tag: self.tag,
+                        status: false,
+                        groups: vec![],
+                    })
+                } else {
+                    Err((self.tag.as_str(), "Unexpected arguments after NONE.").into())
+                };
+            }
+            Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"SET") => (),
+            _ => return Err((self.tag.as_str(), "Expected SET or NONE.").into()),
+        }
+
+        let mut status = false;
+        let mut groups = Vec::new();
+        while let Some(token) = tokens.next() {
+            match token {
+                Token::Argument(value)
+                    if value.eq_ignore_ascii_case(b"STATUS") && groups.is_empty() && !status =>
+                {
+                    status = true;
+                }
+                Token::ParenthesisOpen => {
+                    groups.push(
+                        parse_event_group(&mut tokens, version)
+                            .map_err(|v| (self.tag.as_str(), v))?,
+                    );
+                }
+                _ => return Err((self.tag.as_str(), "Invalid NOTIFY argument.").into()),
+            }
+        }
+
+        if !groups.is_empty() {
+            Ok(notify::Arguments {
+                tag: self.tag,
+                status,
+                groups,
+            })
+        } else {
+            Err((self.tag, "At least one event group is required.").into())
+        }
+    }
+}
+
+fn parse_event_group(
+    tokens: &mut Peekable<IntoIter<Token>>,
+    version: ProtocolVersion,
+) -> super::Result<EventGroup> {
+    let filter = match tokens.next() {
+        Some(Token::Argument(value)) => {
+            if value.eq_ignore_ascii_case(b"SELECTED") {
+                MailboxFilter::Selected
+            } else if value.eq_ignore_ascii_case(b"SELECTED-DELAYED") {
+                MailboxFilter::SelectedDelayed
+            } else if value.eq_ignore_ascii_case(b"PERSONAL") {
+                MailboxFilter::Personal
+            } else if value.eq_ignore_ascii_case(b"INBOXES") {
+                MailboxFilter::Inboxes
+            } else if value.eq_ignore_ascii_case(b"SUBSCRIBED") {
+                MailboxFilter::Subscribed
+            } else if value.eq_ignore_ascii_case(b"SUBTREE") {
+                MailboxFilter::Subtree(parse_mailboxes(tokens, version)?)
+            } else if value.eq_ignore_ascii_case(b"MAILBOXES") {
+                MailboxFilter::Mailboxes(parse_mailboxes(tokens, version)?)
+            } else {
+                return Err(format!(
+                    "Invalid mailbox filter '{}'.",
+                    String::from_utf8_lossy(&value)
+                )
+                .into());
+            }
+        }
+        _ => return Err("Expected mailbox filter.".into()),
+    };
+
+    let mut events = Vec::new();
+    match tokens.next() {
+        Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"NONE") => (),
+        Some(Token::ParenthesisOpen) => loop {
+            match tokens.next() {
+                Some(Token::Argument(value)) => {
+                    events.push(Event::parse(&value)?);
+                }
+                Some(Token::ParenthesisOpen) if events.last() == Some(&Event::MessageNew) => {
+                    // Fetch attributes for MessageNew are not returned, skip them
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match tokens.next() {
+                            Some(Token::ParenthesisOpen) => depth += 1,
+                            Some(Token::ParenthesisClose) => depth -= 1,
+                            Some(_) => (),
+                            None => return Err("Unterminated fetch attributes.".into()),
+                        }
+                    }
+                }
+                Some(Token::ParenthesisClose) if !events.is_empty() => break,
+                _ => return Err("Invalid event list.".into()),
+            }
+        },
+        _ => return Err("Expected event list.".into()),
+    }
+
+    if !matches!(tokens.next(), Some(Token::ParenthesisClose)) {
+        return Err("Expected closing parenthesis after event list.".into());
+    }
+
+    // MessageNew and MessageExpunge must be requested together, and
+    // flag or annotation changes require both.
+    let has_new = events.contains(&Event::MessageNew);
+    if has_new != events.contains(&Event::MessageExpunge)
+        || (!has_new
+            && (events.contains(&Event::FlagChange) || events.contains(&Event::AnnotationChange)))
+    {
+        return Err("MessageNew and MessageExpunge must be specified together.".into());
+    }
+
+    Ok(EventGroup { filter, events })
+}
+
+fn parse_mailboxes(
+    tokens: &mut Peekable<IntoIter<Token>>,
+    version: ProtocolVersion,
+) -> super::Result<Vec<String>> {
+    let mut mailboxes = Vec::new();
+    match tokens.next() {
+        Some(Token::ParenthesisOpen) => loop {
+            match tokens.next() {
+                Some(Token::ParenthesisClose) if !mailboxes.is_empty() => break,
+                Some(token @ Token::Argument(_)) => {
+                    mailboxes.push(utf7_maybe_decode(token.unwrap_string()?, version));
+                }
+                _ => return Err("Invalid mailbox list.".into()),
+            }
+        },
+        Some(token @ Token::Argument(_)) => {
+            mailboxes.push(utf7_maybe_decode(token.unwrap_string()?, version));
+        }
+        _ => return Err("Expected mailbox name.".into()),
+    }
+    Ok(mailboxes)
+}
+
+impl Event {
+    pub fn parse(value: &[u8]) -> super::Result<Self> {
+        if value.eq_ignore_ascii_case(b"MessageNew") {
+            Ok(Self::MessageNew)
+        } else if value.eq_ignore_ascii_case(b"MessageExpunge") {
+            Ok(Self::MessageExpunge)
+        } else if value.eq_ignore_ascii_case(b"FlagChange") {
+            Ok(Self::FlagChange)
+        } else if value.eq_ignore_ascii_case(b"AnnotationChange") {
+            Ok(Self::AnnotationChange)
+        } else if value.eq_ignore_ascii_case(b"MailboxName") {
+            Ok(Self::MailboxName)
+        } else if value.eq_ignore_ascii_case(b"SubscriptionChange") {
+            Ok(Self::SubscriptionChange)
+        } else if value.eq_ignore_ascii_case(b"MailboxMetadataChange") {
+            Ok(Self::MailboxMetadataChange)
+        } else if value.eq_ignore_ascii_case(b"ServerMetadataChange") {
+            Ok(Self::ServerMetadataChange)
+        } else {
+            Err(format!("Invalid event '{}'.", String::from_utf8_lossy(value)).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::{
+            notify::{self, Event, EventGroup, MailboxFilter},
+            ProtocolVersion,
+        },
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_notify() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A01 NOTIFY NONE\r\n",
+                notify::Arguments {
+                    tag: "A01".to_string(),
+                    status: false,
+                    groups: vec![],
+                },
+            ),
+            (
+                concat!(
+                    "A02 NOTIFY SET STATUS (SELECTED (MessageNew (UID FLAGS) MessageExpunge)) ",
+                    "(SUBTREE (\"Lists\" \"Archive\") (MessageNew MessageExpunge FlagChange)) ",
+                    "(PERSONAL (MailboxName SubscriptionChange))\r\n"
+                ),
+                notify::Arguments {
+                    tag: "A02".to_string(),
+                    status: true,
+                    groups: vec![
+                        EventGroup {
+                            filter: MailboxFilter::Selected,
+                            events: vec![Event::MessageNew, Event::MessageExpunge],
+                        },
+                        EventGroup {
+                            filter: MailboxFilter::Subtree(vec![
+                                "Lists".to_string(),
+                                "Archive".to_string(),
+                            ]),
+                            events: vec![
+                                Event::MessageNew,
+                                Event::MessageExpunge,
+                                Event::FlagChange,
+                            ],
+                        },
+                        EventGroup {
+                            filter: MailboxFilter::Personal,
+                            events: vec![Event::MailboxName, Event::SubscriptionChange],
+                        },
+                    ],
+                },
+            ),
+            (
+                "A03 NOTIFY SET (MAILBOXES INBOX NONE) (INBOXES (MessageNew MessageExpunge))\r\n",
+                notify::Arguments {
+                    tag: "A03".to_string(),
+                    status: false,
+                    groups: vec![
+                        EventGroup {
+                            filter: MailboxFilter::Mailboxes(vec!["INBOX".to_string()]),
+                            events: vec![],
+                        },
+                        EventGroup {
+                            filter: MailboxFilter::Inboxes,
+                            events: vec![Event::MessageNew, Event::MessageExpunge],
+                        },
+                    ],
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_notify(ProtocolVersion::Rev2)
+                    .unwrap(),
+                arguments,
+                "{command}"
+            );
+        }
+
+        for command in [
+            "A04 NOTIFY SET (SELECTED (MessageNew))\r\n",
+            "A05 NOTIFY SET (PERSONAL (FlagChange))\r\n",
+            "A06 NOTIFY SET\r\n",
+            "A07 NOTIFY SET (UNKNOWN (MailboxName))\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_notify(ProtocolVersion::Rev2)
+                    .is_err(),
+                "{command}"
+            );
+        }
+    }
+}
@@ -0,0 +1,201 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::{
+    protocol::{
+        quota::{self, QuotaResource},
+        ProtocolVersion,
+    },
+    receiver::{Request, Token},
+    utf7::utf7_maybe_decode,
+    Command,
+};
+
+use super::parse_number;
+
+/*
+
+   getquota        = "GETQUOTA" SP quota-root-name
+
+   getquotaroot    = "GETQUOTAROOT" SP mailbox
+
+   setquota        = "SETQUOTA" SP quota-root-name SP setquota-list
+
+   setquota-list   = "(" [setquota-resource *(SP setquota-resource)] ")"
+
+   setquota-resource = resource-name SP resource-limit
+
+*/
+
+impl Request<Command> {
+    pub fn parse_get_quota(self, version: ProtocolVersion) -> crate::Result<quota::Arguments> {
+        let mut tokens = self.tokens.into_iter();
+        let name = utf7_maybe_decode(
+            tokens
+                .next()
+                .ok_or((self.tag.as_str(), "Missing quota root or mailbox name."))?
+                .unwrap_string()
+                .map_err(|v| (self.tag.as_str(), v))?,
+            version,
+        );
+
+        Ok(quota::Arguments {
+            tag: self.tag,
+            name,
+        })
+    }
+
+    pub fn parse_set_quota(self, version: ProtocolVersion) -> crate::Result<quota::SetArguments> {
+        let mut tokens = self.tokens.into_iter();
+        let root = utf7_maybe_decode(
+            tokens
+                .next()
+                .ok_or((self.tag.as_str(), "Missing quota root name."))?
+                .unwrap_string()
+                .map_err(|v| (self.tag.as_str(), v))?,
+            version,
+        );
+
+        if tokens
+            .next()
+            .map_or(true, |token| !token.is_parenthesis_open())
+        {
+            return Err((self.tag.as_str(), "Expected parenthesis after quota root.").into());
+        }
+
+        let mut limits = Vec::new();
+        while let Some(token) = tokens.next() {
+            match token {
+                Token::ParenthesisClose => break,
+                Token::Argument(value) => {
+                    let resource =
+                        QuotaResource::parse(&value).map_err(|v| (self.tag.as_str(), v))?;
+                    let limit = parse_number::<u64>(
+                        &tokens
+                            .next()
+                            .ok_or((self.tag.as_str(), "Missing resource limit."))?
+                            .unwrap_bytes(),
+                    )
+                    .map_err(|v| (self.tag.as_str(), v))?;
+                    limits.push((resource, limit));
+                }
+                _ => {
+                    return Err((self.tag.as_str(), "Invalid quota resource argument.").into());
+                }
+            }
+        }
+
+        Ok(quota::SetArguments {
+            tag: self.tag,
+            root,
+            limits,
+        })
+    }
+}
+
+impl QuotaResource {
+    pub fn parse(value: &[u8]) -> super::Result<Self> {
+        if value.eq_ignore_ascii_case(b"storage") {
+            Ok(Self::Storage)
+        } else if value.eq_ignore_ascii_case(b"message") {
+            Ok(Self::Message)
+        } else {
+            Err(format!(
+                "Unsupported quota resource '{}'.",
+                String::from_utf8_lossy(value)
+            )
+            .into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::{
+            quota::{self, QuotaResource},
+            ProtocolVersion,
+        },
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_quota() {
+        let mut receiver = Receiver::new();
+
+        for (command, name) in [
+            ("A003 GETQUOTA \"\"\r\n", ""),
+            ("A003 GETQUOTAROOT INBOX\r\n", "INBOX"),
+            (
+                "A003 GETQUOTAROOT \"Shared Folders/jdoe\"\r\n",
+                "Shared Folders/jdoe",
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_get_quota(ProtocolVersion::Rev2)
+                    .unwrap(),
+                quota::Arguments {
+                    tag: "A003".to_string(),
+                    name: name.to_string(),
+                }
+            );
+        }
+
+        for (command, limits) in [
+            (
+                "A001 SETQUOTA \"\" (STORAGE 512)\r\n",
+                vec![(QuotaResource::Storage, 512)],
+            ),
+            (
+                "A001 SETQUOTA \"\" (storage 1024 MESSAGE 100)\r\n",
+                vec![
+                    (QuotaResource::Storage, 1024),
+                    (QuotaResource::Message, 100),
+                ],
+            ),
+            ("A001 SETQUOTA \"\" ()\r\n", vec![]),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_set_quota(ProtocolVersion::Rev2)
+                    .unwrap(),
+                quota::SetArguments {
+                    tag: "A001".to_string(),
+                    root: "".to_string(),
+                    limits,
+                }
+            );
+        }
+
+        assert!(receiver
+            .parse(&mut "A001 SETQUOTA \"\" (MAILBOX 10)\r\n".as_bytes().iter())
+            .unwrap()
+            .parse_set_quota(ProtocolVersion::Rev2)
+            .is_err());
+    }
+}
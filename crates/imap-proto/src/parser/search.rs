@@ -116,6 +116,7 @@ pub fn parse_filters(
     let mut filters_len = 0;
     let mut filters_stack = Vec::new();
     let mut operator = Filter::And;
+    let mut is_fuzzy = false;
 
     while let Some(token) = tokens.next() {
         let mut found_parenthesis = false;
@@ -172,6 +173,29 @@ pub fn parse_filters(
                             .ok_or_else(|| Cow::from("Expected date"))?
                             .unwrap_bytes(),
                     )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDBEFORE") {
+                    filters.push(Filter::SavedBefore(parse_date(
+                        &tokens
+                            .next()
+                            .ok_or_else(|| Cow::from("Expected date"))?
+                            .unwrap_bytes(),
+                    )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDON") {
+                    filters.push(Filter::SavedOn(parse_date(
+                        &tokens
+                            .next()
+                            .ok_or_else(|| Cow::from("Expected date"))?
+                            .unwrap_bytes(),
+                    )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDSINCE") {
+                    filters.push(Filter::SavedSince(parse_date(
+                        &tokens
+                            .next()
+                            .ok_or_else(|| Cow::from("Expected date"))?
+                            .unwrap_bytes(),
+                    )?));
+                } else if value.eq_ignore_ascii_case(b"SAVEDATESUPPORTED") {
+                    filters.push(Filter::SaveDateSupported);
                 } else if value.eq_ignore_ascii_case(b"SEEN") {
                     filters.push(Filter::Seen);
                 } else if value.eq_ignore_ascii_case(b"SENTBEFORE") {
@@ -338,6 +362,10 @@ pub fn parse_filters(
                     filters = Vec::with_capacity(2);
                     operator = Filter::Or;
                     continue;
+                } else if value.eq_ignore_ascii_case(b"FUZZY") {
+                    // RFC 6203: applies to the search key that follows
+                    is_fuzzy = true;
+                    continue;
                 } else if value.eq_ignore_ascii_case(b"NOT") {
                     if filters_stack.len() > 10 {
                         return Err(Cow::from("Too many nested filters"));
@@ -352,6 +380,13 @@ pub fn parse_filters(
                     filters.push(Filter::Sequence(parse_sequence_set(&value)?, false));
                 }
 
+                if is_fuzzy {
+                    is_fuzzy = false;
+                    if let Some(filter) = filters.pop() {
+                        filters.push(filter.into_fuzzy());
+                    }
+                }
+
                 filters_len += 1;
             }
             Token::ParenthesisOpen => {
@@ -435,6 +470,8 @@ impl ResultOption {
             Ok(Self::Save)
         } else if value.eq_ignore_ascii_case(b"context") {
             Ok(Self::Context)
+        } else if value.eq_ignore_ascii_case(b"relevancy") {
+            Ok(Self::Relevancy)
         } else {
             Err(format!("Invalid result option {:?}", String::from_utf8_lossy(value)).into())
         }
@@ -489,6 +526,40 @@ mod tests {
                     sort: None,
                 },
             ),
+            (
+                b"A284 SEARCH SAVEDATESUPPORTED SAVEDSINCE 1-Feb-1994 NOT SAVEDON 1-Feb-1994\r\n"
+                    .to_vec(),
+                search::Arguments {
+                    tag: "A284".to_string(),
+                    result_options: vec![],
+                    filter: vec![
+                        Filter::SaveDateSupported,
+                        Filter::SavedSince(760060800),
+                        Filter::Not,
+                        Filter::SavedOn(760060800),
+                        Filter::End,
+                    ],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
+            (
+                b"A285 SEARCH RETURN (RELEVANCY ALL) FUZZY TEXT \"invoice\" NOT FUZZY SUBJECT \"draft\" FLAGGED\r\n"
+                    .to_vec(),
+                search::Arguments {
+                    tag: "A285".to_string(),
+                    result_options: vec![ResultOption::Relevancy, ResultOption::All],
+                    filter: vec![
+                        Filter::Fuzzy(Box::new(Filter::Text("invoice".to_string()))),
+                        Filter::Not,
+                        Filter::Fuzzy(Box::new(Filter::Subject("draft".to_string()))),
+                        Filter::End,
+                        Filter::Flagged,
+                    ],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
             (
                 b"A301 SEARCH $ SMALLER 4096\r\n".to_vec(),
                 search::Arguments {
@@ -127,6 +127,8 @@ impl Sort {
             Ok(Self::DisplayFrom)
         } else if value.eq_ignore_ascii_case(b"DISPLAYTO") {
             Ok(Self::DisplayTo)
+        } else if value.eq_ignore_ascii_case(b"RELEVANCY") {
+            Ok(Self::Relevancy)
         } else {
             Err(format!("Invalid sort criteria {:?}", String::from_utf8_lossy(value)).into())
         }
@@ -58,12 +58,22 @@ pub enum Capability {
     Move,
     CondStore,
     QResync,
-    LiteralPlus, //LITERAL+
+    LiteralPlus,  //LITERAL+
+    LiteralMinus, //LITERAL-
     UnAuthenticate,
     StatusSize, //STATUS=SIZE
     ObjectId,
     Preview,
     Utf8Accept,
+    SaveDate,
+    SearchFuzzy, //SEARCH=FUZZY
+    Notify,
+    CompressDeflate, //COMPRESS=DEFLATE
+    Quota,
+    QuotaResStorage, //QUOTA=RES-STORAGE
+    QuotaResMessage, //QUOTA=RES-MESSAGE
+    QuotaSet,
+    AppendLimit(u64),
     Auth(Mechanism),
 }
 
@@ -75,6 +85,11 @@ impl Capability {
                 mechanism.serialize(buf);
                 return;
             }
+            Capability::AppendLimit(limit) => {
+                buf.extend_from_slice(b"APPENDLIMIT=");
+                buf.extend_from_slice(limit.to_string().as_bytes());
+                return;
+            }
             Capability::IMAP4rev2 => b"IMAP4rev2",
             Capability::IMAP4rev1 => b"IMAP4rev1",
             Capability::StartTLS => b"STARTTLS",
@@ -82,6 +97,7 @@ impl Capability {
             Capability::CondStore => b"CONDSTORE",
             Capability::QResync => b"QRESYNC",
             Capability::LiteralPlus => b"LITERAL+",
+            Capability::LiteralMinus => b"LITERAL-",
             Capability::UnAuthenticate => b"UNAUTHENTICATE",
             Capability::StatusSize => b"STATUS=SIZE",
             Capability::ObjectId => b"OBJECTID",
@@ -109,20 +125,42 @@ impl Capability {
             Capability::CreateSpecialUse => b"CREATE-SPECIAL-USE",
             Capability::Move => b"MOVE",
             Capability::Utf8Accept => b"UTF8=ACCEPT",
+            Capability::SaveDate => b"SAVEDATE",
+            Capability::SearchFuzzy => b"SEARCH=FUZZY",
+            Capability::Notify => b"NOTIFY",
+            Capability::CompressDeflate => b"COMPRESS=DEFLATE",
+            Capability::Quota => b"QUOTA",
+            Capability::QuotaResStorage => b"QUOTA=RES-STORAGE",
+            Capability::QuotaResMessage => b"QUOTA=RES-MESSAGE",
+            Capability::QuotaSet => b"QUOTASET",
         });
     }
 
-    pub fn all_capabilities(is_authenticated: bool, is_tls: bool) -> Vec<Capability> {
+    pub fn all_capabilities(
+        is_authenticated: bool,
+        is_tls: bool,
+        is_literal_minus: bool,
+        append_limit: Option<u64>,
+        auth_mechanisms: &[Mechanism],
+    ) -> Vec<Capability> {
         let mut capabilties = vec![
             Capability::IMAP4rev2,
             Capability::IMAP4rev1,
             Capability::Enable,
             Capability::SASLIR,
-            Capability::LiteralPlus,
+            if is_literal_minus {
+                Capability::LiteralMinus
+            } else {
+                Capability::LiteralPlus
+            },
             Capability::Id,
             Capability::Utf8Accept,
         ];
 
+        if let Some(append_limit) = append_limit {
+            capabilties.push(Capability::AppendLimit(append_limit));
+        }
+
         if is_authenticated {
             capabilties.extend([
                 Capability::Idle,
@@ -150,12 +188,21 @@ impl Capability {
                 Capability::StatusSize,
                 Capability::ObjectId,
                 Capability::Preview,
+                Capability::SaveDate,
+                Capability::SearchFuzzy,
+                Capability::Notify,
+                Capability::CompressDeflate,
+                Capability::Quota,
+                Capability::QuotaResStorage,
+                Capability::QuotaResMessage,
+                Capability::QuotaSet,
             ]);
         } else {
             capabilties.extend([
                 Capability::Auth(Mechanism::OAuthBearer),
                 Capability::Auth(Mechanism::Plain),
             ]);
+            capabilties.extend(auth_mechanisms.iter().cloned().map(Capability::Auth));
         }
         if !is_tls {
             capabilties.push(Capability::StartTLS);
@@ -192,11 +239,13 @@ mod tests {
                 capabilities: vec![
                     Capability::IMAP4rev2,
                     Capability::StartTLS,
-                    Capability::LoginDisabled
+                    Capability::LoginDisabled,
+                    Capability::AppendLimit(35651584),
                 ],
             }
             .serialize(),
-            concat!("* CAPABILITY IMAP4rev2 STARTTLS LOGINDISABLED\r\n",).as_bytes()
+            concat!("* CAPABILITY IMAP4rev2 STARTTLS LOGINDISABLED APPENDLIMIT=35651584\r\n",)
+                .as_bytes()
         );
     }
 }
@@ -42,6 +42,8 @@ pub mod fetch;
 pub mod list;
 pub mod login;
 pub mod namespace;
+pub mod notify;
+pub mod quota;
 pub mod rename;
 pub mod search;
 pub mod select;
@@ -266,7 +268,7 @@ impl Flag {
             Flag::Flagged => b"\\Flagged",
             Flag::Answered => b"\\Answered",
             Flag::Recent => b"\\Recent",
-            Flag::Important => b"\\Important",
+            Flag::Important => b"$Important",
             Flag::Phishing => b"$Phishing",
             Flag::Junk => b"$Junk",
             Flag::NotJunk => b"$NotJunk",
@@ -373,6 +375,11 @@ impl ResponseCode {
             ResponseCode::ReadOnly => b"READ-ONLY",
             ResponseCode::ReadWrite => b"READ-WRITE",
             ResponseCode::ServerBug => b"SERVERBUG",
+            ResponseCode::TooBig => b"TOOBIG",
+            ResponseCode::CompressionActive => b"COMPRESSIONACTIVE",
+            ResponseCode::BadEvent => {
+                b"BADEVENT (MessageNew MessageExpunge FlagChange MailboxName SubscriptionChange)"
+            }
             ResponseCode::TryCreate => b"TRYCREATE",
             ResponseCode::UidNext => b"UIDNEXT",
             ResponseCode::UidNotSticky => b"UIDNOTSTICKY",
@@ -497,6 +504,8 @@ impl Display for Command {
             Command::Status => write!(f, "STATUS"),
             Command::Append => write!(f, "APPEND"),
             Command::Idle => write!(f, "IDLE"),
+            Command::Notify => write!(f, "NOTIFY"),
+            Command::Compress => write!(f, "COMPRESS"),
             Command::Close => write!(f, "CLOSE"),
             Command::Unselect => write!(f, "UNSELECT"),
             Command::Expunge(false) => write!(f, "EXPUNGE"),
@@ -522,6 +531,9 @@ impl Display for Command {
             Command::GetAcl => write!(f, "GETACL"),
             Command::ListRights => write!(f, "LISTRIGHTS"),
             Command::MyRights => write!(f, "MYRIGHTS"),
+            Command::GetQuota => write!(f, "GETQUOTA"),
+            Command::GetQuotaRoot => write!(f, "GETQUOTAROOT"),
+            Command::SetQuota => write!(f, "SETQUOTA"),
             Command::Unauthenticate => write!(f, "UNAUTHENTICATE"),
             Command::Id => write!(f, "ID"),
         }
@@ -0,0 +1,106 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arguments {
+    pub tag: String,
+    pub status: bool,
+    pub groups: Vec<EventGroup>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventGroup {
+    pub filter: MailboxFilter,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MailboxFilter {
+    Selected,
+    SelectedDelayed,
+    Personal,
+    Inboxes,
+    Subscribed,
+    Subtree(Vec<String>),
+    Mailboxes(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    MessageNew,
+    MessageExpunge,
+    FlagChange,
+    AnnotationChange,
+    MailboxName,
+    SubscriptionChange,
+    MailboxMetadataChange,
+    ServerMetadataChange,
+}
+
+impl Event {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Event::MessageNew => "MessageNew",
+            Event::MessageExpunge => "MessageExpunge",
+            Event::FlagChange => "FlagChange",
+            Event::AnnotationChange => "AnnotationChange",
+            Event::MailboxName => "MailboxName",
+            Event::SubscriptionChange => "SubscriptionChange",
+            Event::MailboxMetadataChange => "MailboxMetadataChange",
+            Event::ServerMetadataChange => "ServerMetadataChange",
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        !matches!(
+            self,
+            Event::AnnotationChange | Event::MailboxMetadataChange | Event::ServerMetadataChange
+        )
+    }
+
+    pub fn is_message_event(&self) -> bool {
+        matches!(
+            self,
+            Event::MessageNew | Event::MessageExpunge | Event::FlagChange
+        )
+    }
+}
+
+impl MailboxFilter {
+    pub fn is_selected(&self) -> bool {
+        matches!(
+            self,
+            MailboxFilter::Selected | MailboxFilter::SelectedDelayed
+        )
+    }
+}
+
+impl EventGroup {
+    pub fn has_message_events(&self) -> bool {
+        self.events.iter().any(|event| event.is_message_event())
+    }
+
+    pub fn has_event(&self, event: Event) -> bool {
+        self.events.contains(&event)
+    }
+}
@@ -0,0 +1,156 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::utf7::utf7_encode;
+
+use super::quoted_string;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arguments {
+    pub tag: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetArguments {
+    pub tag: String,
+    pub root: String,
+    pub limits: Vec<(QuotaResource, u64)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaResource {
+    Storage,
+    Message,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaResponse {
+    pub root: String,
+    pub resources: Vec<(QuotaResource, u64, u64)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaRootResponse {
+    pub mailbox_name: String,
+    pub roots: Vec<QuotaResponse>,
+}
+
+impl QuotaResource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaResource::Storage => "STORAGE",
+            QuotaResource::Message => "MESSAGE",
+        }
+    }
+}
+
+impl QuotaResponse {
+    pub fn into_bytes(self, is_rev2: bool) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.root.len() + 16 + self.resources.len() * 32);
+        self.serialize(&mut buf, is_rev2);
+        buf
+    }
+
+    fn serialize(&self, buf: &mut Vec<u8>, is_rev2: bool) {
+        buf.extend_from_slice(b"* QUOTA ");
+        serialize_root(buf, &self.root, is_rev2);
+        buf.extend_from_slice(b" (");
+        for (pos, (resource, usage, limit)) in self.resources.iter().enumerate() {
+            if pos > 0 {
+                buf.push(b' ');
+            }
+            buf.extend_from_slice(resource.as_str().as_bytes());
+            buf.push(b' ');
+            buf.extend_from_slice(usage.to_string().as_bytes());
+            buf.push(b' ');
+            buf.extend_from_slice(limit.to_string().as_bytes());
+        }
+        buf.extend_from_slice(b")\r\n");
+    }
+}
+
+impl QuotaRootResponse {
+    pub fn into_bytes(self, is_rev2: bool) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.mailbox_name.len() + 16 + self.roots.len() * 64);
+        buf.extend_from_slice(b"* QUOTAROOT ");
+        serialize_root(&mut buf, &self.mailbox_name, is_rev2);
+        for root in &self.roots {
+            buf.push(b' ');
+            serialize_root(&mut buf, &root.root, is_rev2);
+        }
+        buf.extend_from_slice(b"\r\n");
+        for root in &self.roots {
+            root.serialize(&mut buf, is_rev2);
+        }
+        buf
+    }
+}
+
+fn serialize_root(buf: &mut Vec<u8>, name: &str, is_rev2: bool) {
+    if is_rev2 {
+        quoted_string(buf, name);
+    } else {
+        quoted_string(buf, &utf7_encode(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QuotaResource, QuotaResponse, QuotaRootResponse};
+
+    #[test]
+    fn serialize_quota() {
+        assert_eq!(
+            String::from_utf8(
+                QuotaRootResponse {
+                    mailbox_name: "INBOX".to_string(),
+                    roots: vec![QuotaResponse {
+                        root: "".to_string(),
+                        resources: vec![
+                            (QuotaResource::Storage, 10, 512),
+                            (QuotaResource::Message, 4, 100)
+                        ],
+                    }],
+                }
+                .into_bytes(true)
+            )
+            .unwrap(),
+            concat!(
+                "* QUOTAROOT \"INBOX\" \"\"\r\n",
+                "* QUOTA \"\" (STORAGE 10 512 MESSAGE 4 100)\r\n"
+            )
+        );
+        assert_eq!(
+            String::from_utf8(
+                QuotaRootResponse {
+                    mailbox_name: "Drafts".to_string(),
+                    roots: vec![],
+                }
+                .into_bytes(true)
+            )
+            .unwrap(),
+            "* QUOTAROOT \"Drafts\"\r\n"
+        );
+    }
+}
@@ -77,13 +77,21 @@ pub enum State {
     Literal { non_sync: bool },
     LiteralSeek { size: u32, non_sync: bool },
     LiteralData { remaining: u32 },
+    LiteralDiscard { remaining: u32 },
+    Discard,
+    DiscardLiteral { size: u32, non_sync: bool },
+    DiscardSeek { size: u32 },
 }
 
+// RFC 7888 limits LITERAL- non-synchronizing literals to 4096 bytes
+pub const MAX_LITERAL_MINUS_SIZE: u32 = 4096;
+
 pub struct Receiver<T: CommandParser> {
     buf: Vec<u8>,
     pub request: Request<T>,
     pub state: State,
     pub max_request_size: usize,
+    pub max_non_sync_literal: Option<u32>,
     pub current_request_size: usize,
     pub start_state: State,
 }
@@ -109,6 +117,12 @@ impl<T: CommandParser> Receiver<T> {
         }
     }
 
+    pub fn with_max_non_sync_literal(mut self, max_non_sync_literal: Option<u32>) -> Self {
+        self.max_non_sync_literal =
+            max_non_sync_literal.map(|max_size| max_size.min(MAX_LITERAL_MINUS_SIZE));
+        self
+    }
+
     pub fn error_reset(&mut self, message: impl Into<Cow<'static, str>>) -> Error {
         let request = std::mem::take(&mut self.request);
         let err = Error::err(
@@ -119,10 +133,32 @@ impl<T: CommandParser> Receiver<T> {
             },
             message,
         );
+        self.reset();
+        err
+    }
+
+    fn literal_limit_reset(&mut self) -> Error {
+        let request = std::mem::take(&mut self.request);
+        self.reset();
+        Error::Error {
+            response: StatusResponse {
+                tag: Some(request.tag).filter(|tag| !tag.is_empty()),
+                code: ResponseCode::Limit.into(),
+                message: format!(
+                    "Non-synchronizing literals are limited to {} bytes.",
+                    self.max_non_sync_literal.unwrap_or_default()
+                )
+                .into(),
+                rtype: ResponseType::No,
+            },
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.request = Request::default();
         self.buf = Vec::with_capacity(10);
         self.state = self.start_state;
         self.current_request_size = 0;
-        err
     }
 
     fn push_argument(&mut self, in_quote: bool) -> Result<(), Error> {
@@ -369,7 +405,19 @@ impl<T: CommandParser> Receiver<T> {
                 }
                 State::LiteralSeek { size, non_sync } => {
                     if ch == b'\n' {
-                        if size > 0 {
+                        if non_sync
+                            && self
+                                .max_non_sync_literal
+                                .map_or(false, |max_size| size > max_size)
+                        {
+                            // LITERAL-: the client did not wait for a continuation,
+                            // so the literal has to be discarded before rejecting the command
+                            self.state = if size > 0 {
+                                State::LiteralDiscard { remaining: size }
+                            } else {
+                                State::Discard
+                            };
+                        } else if size > 0 {
                             self.state = State::LiteralData { remaining: size };
                         } else {
                             self.state = State::Argument { last_ch: b' ' };
@@ -395,6 +443,66 @@ impl<T: CommandParser> Receiver<T> {
                         self.state = State::Argument { last_ch: b' ' };
                     }
                 }
+                State::LiteralDiscard { remaining } => {
+                    self.state = if remaining > 1 {
+                        State::LiteralDiscard {
+                            remaining: remaining - 1,
+                        }
+                    } else {
+                        State::Discard
+                    };
+                }
+                State::Discard => match ch {
+                    b'\n' => {
+                        return Err(self.literal_limit_reset());
+                    }
+                    b'{' => {
+                        self.state = State::DiscardLiteral {
+                            size: 0,
+                            non_sync: false,
+                        };
+                    }
+                    _ => (),
+                },
+                State::DiscardLiteral { size, non_sync } => {
+                    // Any further non-synchronizing literals sent along with
+                    // the rejected command have to be skipped as well
+                    self.state = match ch {
+                        b'0'..=b'9' if !non_sync => size
+                            .checked_mul(10)
+                            .and_then(|size| size.checked_add((ch - b'0') as u32))
+                            .map_or(State::Discard, |size| State::DiscardLiteral {
+                                size,
+                                non_sync,
+                            }),
+                        b'+' if !non_sync => State::DiscardLiteral {
+                            size,
+                            non_sync: true,
+                        },
+                        b'}' if non_sync => State::DiscardSeek { size },
+                        b'{' => State::DiscardLiteral {
+                            size: 0,
+                            non_sync: false,
+                        },
+                        b'\n' => {
+                            return Err(self.literal_limit_reset());
+                        }
+                        _ => State::Discard,
+                    };
+                }
+                State::DiscardSeek { size } => match ch {
+                    b'\n' => {
+                        self.state = if size > 0 {
+                            State::LiteralDiscard { remaining: size }
+                        } else {
+                            State::Discard
+                        };
+                    }
+                    b'\r' => (),
+                    _ => {
+                        self.state = State::Discard;
+                    }
+                },
             }
         }
 
@@ -499,6 +607,7 @@ impl<T: CommandParser> Default for Receiver<T> {
             state: State::Start,
             start_state: State::Start,
             max_request_size: 25 * 1024 * 1024,
+            max_non_sync_literal: None,
             current_request_size: 0,
         }
     }
@@ -611,7 +720,7 @@ DQUOTE         =  %x22 ; " (Double Quote)
 #[cfg(test)]
 mod tests {
 
-    use crate::Command;
+    use crate::{Command, ResponseCode, ResponseType};
 
     use super::{Error, Receiver, Request, Token};
 
@@ -1086,4 +1195,47 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn receiver_parse_literal_minus() {
+        let mut receiver = Receiver::<Command>::new().with_max_non_sync_literal(Some(5));
+
+        match receiver.parse(&mut b"a001 login {6+}\r\nabcdef {5+}\r\nabcde\r\n".iter()) {
+            Err(Error::Error { response }) => {
+                assert_eq!(response.tag.as_deref(), Some("a001"));
+                assert_eq!(response.rtype, ResponseType::No);
+                assert_eq!(response.code, Some(ResponseCode::Limit));
+            }
+            result => panic!("Expected error, got: {:?}", result),
+        }
+
+        assert!(matches!(
+            receiver.parse(&mut b"a002 login {5+}\r\nhello {5}\r\n".iter()),
+            Err(Error::NeedsLiteral { size: 5 })
+        ));
+
+        // Literals following a rejected one are discarded, even if they contain line breaks
+        let mut receiver = Receiver::<Command>::new().with_max_non_sync_literal(Some(5));
+        match receiver
+            .parse(&mut b"a003 login {7+}\r\nab\r\ncde {13+}\r\n\r\na004 noop\r\n\r\n".iter())
+        {
+            Err(Error::Error { response }) => {
+                assert_eq!(response.tag.as_deref(), Some("a003"));
+                assert_eq!(response.code, Some(ResponseCode::Limit));
+            }
+            result => panic!("Expected error, got: {:?}", result),
+        }
+        assert!(matches!(
+            receiver.parse(&mut b"a005 noop\r\n".iter()),
+            Ok(request) if request.tag == "a005"
+        ));
+
+        // LITERAL- limits non-synchronizing literals to 4096 bytes
+        assert_eq!(
+            Receiver::<Command>::new()
+                .with_max_non_sync_literal(Some(1024 * 1024))
+                .max_non_sync_literal,
+            Some(4096)
+        );
+    }
 }
@@ -30,10 +30,10 @@ use imap_proto::{
 };
 use jmap::auth::rate_limit::ConcurrencyLimiters;
 
-use super::{SelectedMailbox, Session, SessionData, State};
+use super::{SelectedMailbox, Session, SessionData, State, StreamUpgrade};
 
 impl<T: SessionStream> Session<T> {
-    pub async fn ingest(&mut self, bytes: &[u8]) -> crate::Result<bool> {
+    pub async fn ingest(&mut self, bytes: &[u8]) -> crate::Result<Option<StreamUpgrade>> {
         /*for line in String::from_utf8_lossy(bytes).split("\r\n") {
             let c = println!("{}", line);
         }*/
@@ -65,6 +65,19 @@ impl<T: SessionStream> Session<T> {
                     break;
                 }
                 Err(receiver::Error::Error { response }) => {
+                    if self
+                        .jmap
+                        .core
+                        .is_abuse_banned(self.remote_addr)
+                        .await
+                        .unwrap_or_default()
+                    {
+                        self.write_bytes(
+                            StatusResponse::bye("Too many invalid commands.").into_bytes(),
+                        )
+                        .await?;
+                        return Err(());
+                    }
                     self.write_bytes(response.into_bytes()).await?;
                     break;
                 }
@@ -130,6 +143,9 @@ impl<T: SessionStream> Session<T> {
                 Command::Idle => {
                     self.handle_idle(request).await?;
                 }
+                Command::Notify => {
+                    self.handle_notify(request).await?;
+                }
                 Command::Subscribe => {
                     self.handle_subscribe(request, true).await?;
                 }
@@ -159,7 +175,10 @@ impl<T: SessionStream> Session<T> {
                                 .into_bytes(),
                         )
                         .await
-                        .map(|_| true);
+                        .map(|_| Some(StreamUpgrade::Tls));
+                }
+                Command::Compress => {
+                    return self.handle_compress(request).await;
                 }
                 Command::Noop => {
                     self.handle_noop(request).await?;
@@ -185,6 +204,15 @@ impl<T: SessionStream> Session<T> {
                 Command::MyRights => {
                     self.handle_my_rights(request).await?;
                 }
+                Command::GetQuota => {
+                    self.handle_get_quota(request).await?;
+                }
+                Command::GetQuotaRoot => {
+                    self.handle_get_quota_root(request).await?;
+                }
+                Command::SetQuota => {
+                    self.handle_set_quota(request).await?;
+                }
                 Command::Unauthenticate => {
                     self.handle_unauthenticate(request).await?;
                 }
@@ -199,7 +227,7 @@ impl<T: SessionStream> Session<T> {
                 .await?;
         }
 
-        Ok(false)
+        Ok(None)
     }
 }
 
@@ -253,6 +281,17 @@ impl<T: SessionStream> Session<T> {
 
         match &request.command {
             Command::Capability | Command::Noop | Command::Logout | Command::Id => Ok(request),
+            Command::Compress => {
+                if self.is_compressed {
+                    Err(StatusResponse::no("Compression is already active.")
+                        .with_tag(request.tag)
+                        .with_code(ResponseCode::CompressionActive))
+                } else if let State::Authenticated { .. } | State::Selected { .. } = state {
+                    Ok(request)
+                } else {
+                    Err(StatusResponse::no("Not authenticated.").with_tag(request.tag))
+                }
+            }
             Command::StartTls => {
                 if !self.is_tls {
                     if self.instance.acceptor.is_tls() {
@@ -299,11 +338,15 @@ impl<T: SessionStream> Session<T> {
             | Command::Status
             | Command::Append
             | Command::Idle
+            | Command::Notify
             | Command::SetAcl
             | Command::DeleteAcl
             | Command::GetAcl
             | Command::ListRights
             | Command::MyRights
+            | Command::GetQuota
+            | Command::GetQuotaRoot
+            | Command::SetQuota
             | Command::Unauthenticate => {
                 if let State::Authenticated { .. } | State::Selected { .. } = state {
                     Ok(request)
@@ -347,19 +390,36 @@ impl<T: SessionStream> Session<T> {
 
     pub fn get_concurrency_limiter(&self, account_id: u32) -> Option<Arc<ConcurrencyLimiters>> {
         let rate = self.jmap.core.imap.rate_concurrent?;
-        self.imap
+        match self
+            .imap
             .rate_limiter
             .get(&account_id)
             .map(|limiter| limiter.clone())
-            .unwrap_or_else(|| {
-                let limiter = Arc::new(ConcurrencyLimiters {
-                    concurrent_requests: ConcurrencyLimiter::new(rate),
-                    concurrent_uploads: ConcurrencyLimiter::new(rate),
+        {
+            Some(limiter) if limiter.concurrent_requests.max_concurrent == rate => limiter,
+            limiter => {
+                // Keep the in-flight counters when the configured limit changed
+                let limiter = Arc::new(match limiter {
+                    Some(limiter) => ConcurrencyLimiters {
+                        concurrent_requests: ConcurrencyLimiter {
+                            max_concurrent: rate,
+                            concurrent: limiter.concurrent_requests.concurrent.clone(),
+                        },
+                        concurrent_uploads: ConcurrencyLimiter {
+                            max_concurrent: rate,
+                            concurrent: limiter.concurrent_uploads.concurrent.clone(),
+                        },
+                    },
+                    None => ConcurrencyLimiters {
+                        concurrent_requests: ConcurrencyLimiter::new(rate),
+                        concurrent_uploads: ConcurrencyLimiter::new(rate),
+                    },
                 });
                 self.imap.rate_limiter.insert(account_id, limiter.clone());
                 limiter
-            })
-            .into()
+            }
+        }
+        .into()
     }
 }
 
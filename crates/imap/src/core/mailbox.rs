@@ -607,4 +607,35 @@ impl<T: SessionStream> SessionData<T> {
                 .map(|mailbox| mailbox.effective_acl(&access_token).contains(item))
                 .ok_or_else(|| StatusResponse::no("Mailbox no longer exists."))?)
     }
+
+    /// Returns `None` if the user cannot read messages in the mailbox, otherwise
+    /// whether the mailbox can be opened read-write.
+    pub async fn check_mailbox_select(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> crate::op::Result<Option<bool>> {
+        let access_token = self.get_access_token().await?;
+        if access_token.is_member(account_id) {
+            return Ok(Some(true));
+        }
+
+        let acl = self
+            .jmap
+            .get_property::<Object<Value>>(
+                account_id,
+                Collection::Mailbox,
+                document_id,
+                Property::Value,
+            )
+            .await?
+            .map(|mailbox| mailbox.effective_acl(&access_token))
+            .ok_or_else(|| StatusResponse::no("Mailbox no longer exists."))?;
+
+        Ok(if acl.contains(Acl::ReadItems) {
+            Some(acl.contains(Acl::ModifyItems) || acl.contains(Acl::RemoveItems))
+        } else {
+            None
+        })
+    }
 }
@@ -350,6 +350,36 @@ impl SelectedMailbox {
         deleted_ids
     }
 
+    pub fn verify_seq_match(&self, seqnums: &Sequence, uids: &Sequence) -> u32 {
+        let state = self.state.lock();
+        let mut seqnums = seqnums
+            .expand(state.total_messages as u32)
+            .into_iter()
+            .collect::<Vec<_>>();
+        let mut uids = uids.expand(state.uid_max).into_iter().collect::<Vec<_>>();
+        if seqnums.len() != uids.len() {
+            return 0;
+        }
+        seqnums.sort_unstable();
+        uids.sort_unstable();
+
+        // Find the highest UID up to which the client's sequence mapping is correct
+        let mut verified_uid = 0;
+        for (seqnum, uid) in seqnums.into_iter().zip(uids) {
+            match state
+                .uid_to_id
+                .get(&uid)
+                .and_then(|id| state.id_to_imap.get(id))
+            {
+                Some(imap_id) if imap_id.seqnum == seqnum => {
+                    verified_uid = uid;
+                }
+                _ => break,
+            }
+        }
+        verified_uid
+    }
+
     pub fn append_messages(&self, ids: Vec<ImapUidToId>, modseq: Option<u64>) -> u32 {
         let mut mailbox = self.state.lock();
         if modseq.unwrap_or(0) > mailbox.modseq.unwrap_or(0) {
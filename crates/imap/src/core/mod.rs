@@ -24,14 +24,24 @@
 use std::{
     collections::BTreeMap,
     net::IpAddr,
-    sync::{atomic::AtomicU32, Arc},
+    sync::{
+        atomic::{AtomicU32, AtomicUsize},
+        Arc,
+    },
 };
 
 use ahash::AHashMap;
-use common::listener::{limiter::InFlight, ServerInstance, SessionStream};
+use common::{
+    listener::{
+        limiter::{ConcurrencyLimiter, InFlight},
+        qos::QosPermit,
+        ServerInstance, SessionStream,
+    },
+    sasl::SaslExchange,
+};
 use dashmap::DashMap;
 use imap_proto::{
-    protocol::{list::Attribute, ProtocolVersion},
+    protocol::{list::Attribute, notify::EventGroup, ProtocolVersion},
     receiver::Receiver,
     Command, ResponseCode, StatusResponse,
 };
@@ -39,9 +49,10 @@ use jmap::{
     auth::{rate_limit::ConcurrencyLimiters, AccessToken},
     JmapInstance, JMAP,
 };
+use jmap_proto::types::state::StateChange;
 use tokio::{
     io::{ReadHalf, WriteHalf},
-    sync::watch,
+    sync::{mpsc, watch},
 };
 use utils::lru_cache::LruCache;
 
@@ -72,6 +83,8 @@ pub struct Inner {
     pub greeting_tls: Vec<u8>,
 
     pub rate_limiter: DashMap<u32, Arc<ConcurrencyLimiters>>,
+    pub connection_limiter: DashMap<IpAddr, ConcurrencyLimiter>,
+    pub connection_limiter_purge: AtomicUsize,
 
     pub cache_account: LruCache<AccountId, Arc<Account>>,
     pub cache_mailbox: LruCache<MailboxId, Arc<MailboxState>>,
@@ -89,10 +102,16 @@ pub struct Session<T: SessionStream> {
     pub is_tls: bool,
     pub is_condstore: bool,
     pub is_qresync: bool,
+    pub is_compressed: bool,
     pub stream_rx: ReadHalf<T>,
     pub stream_tx: Arc<tokio::sync::Mutex<WriteHalf<T>>>,
     pub in_flight: InFlight,
+    pub qos: QosPermit,
+    pub in_flight_ip: Option<InFlight>,
     pub remote_addr: IpAddr,
+    pub notify: Option<Notifier>,
+    pub channel_binding: Option<Vec<u8>>,
+    pub sasl: Option<(String, SaslExchange)>,
     pub span: tracing::Span,
 }
 
@@ -175,6 +194,19 @@ pub struct MailboxSync {
     pub deleted: Vec<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamUpgrade {
+    Tls,
+    Compress,
+}
+
+pub struct Notifier {
+    pub groups: Vec<EventGroup>,
+    pub change_rx: mpsc::Receiver<StateChange>,
+    pub has_mailbox_changes: bool,
+    pub has_email_changes: bool,
+}
+
 pub enum SavedSearch {
     InFlight {
         rx: watch::Receiver<Arc<Vec<ImapId>>>,
@@ -21,15 +21,61 @@
  * for more details.
 */
 
-use std::sync::Arc;
+use std::{
+    net::IpAddr,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
 
-use common::listener::{stream::NullIo, SessionData, SessionManager, SessionStream};
-use imap_proto::{protocol::ProtocolVersion, receiver::Receiver};
+use common::listener::{
+    deflate::DeflateStream,
+    limiter::{ConcurrencyLimiter, InFlight},
+    stream::NullIo,
+    SessionData, SessionManager, SessionStream,
+};
+use imap_proto::{protocol::ProtocolVersion, receiver::Receiver, ResponseCode, StatusResponse};
 use jmap::JMAP;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    time::Instant,
+};
 use tokio_rustls::server::TlsStream;
 
-use super::{ImapSessionManager, Session, State};
+use crate::op::notify::next_notify_change;
+
+use super::{ImapSessionManager, Inner, Session, State, StreamUpgrade};
+
+const NOTIFY_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+pub(crate) const CONNECTION_LIMITER_PURGE: usize = 1024;
+
+impl Inner {
+    pub fn is_connection_allowed(
+        &self,
+        remote_ip: IpAddr,
+        max_concurrent: u64,
+    ) -> Option<InFlight> {
+        // Drop limiters of addresses without open connections before the map grows unbounded,
+        // the next purge threshold grows with the number of active addresses to keep purges rare
+        if self.connection_limiter.len() >= self.connection_limiter_purge.load(Ordering::Relaxed) {
+            self.connection_limiter
+                .retain(|_, limiter| limiter.is_active());
+            self.connection_limiter_purge.store(
+                std::cmp::max(CONNECTION_LIMITER_PURGE, self.connection_limiter.len() * 2),
+                Ordering::Relaxed,
+            );
+        }
+
+        let mut limiter = self
+            .connection_limiter
+            .entry(remote_ip)
+            .or_insert_with(|| ConcurrencyLimiter::new(max_concurrent));
+
+        // Pick up limit changes made after the limiter was created
+        limiter.max_concurrent = max_concurrent;
+
+        limiter.is_allowed()
+    }
+}
 
 impl SessionManager for ImapSessionManager {
     #[allow(clippy::manual_async_fn)]
@@ -39,10 +85,22 @@ impl SessionManager for ImapSessionManager {
     ) -> impl std::future::Future<Output = ()> + Send {
         async move {
             if let Ok(mut session) = Session::new(session, self).await {
-                if session.handle_conn().await && session.instance.acceptor.is_tls() {
-                    if let Ok(mut session) = session.into_tls().await {
-                        session.handle_conn().await;
+                match session.handle_conn().await {
+                    Some(StreamUpgrade::Tls) if session.instance.acceptor.is_tls() => {
+                        if let Ok(mut session) = session.into_tls().await {
+                            if session.handle_conn().await == Some(StreamUpgrade::Compress) {
+                                if let Ok(mut session) = session.into_compressed().await {
+                                    session.handle_conn().await;
+                                }
+                            }
+                        }
+                    }
+                    Some(StreamUpgrade::Compress) => {
+                        if let Ok(mut session) = session.into_compressed().await {
+                            session.handle_conn().await;
+                        }
                     }
+                    _ => (),
                 }
             }
         }
@@ -55,14 +113,15 @@ impl SessionManager for ImapSessionManager {
 }
 
 impl<T: SessionStream> Session<T> {
-    pub async fn handle_conn(&mut self) -> bool {
+    pub async fn handle_conn(&mut self) -> Option<StreamUpgrade> {
         let mut buf = vec![0; 8192];
         let mut shutdown_rx = self.instance.shutdown_rx.clone();
+        let mut last_read = Instant::now();
 
         loop {
             tokio::select! {
-                result = tokio::time::timeout(
-                    if !matches!(self.state, State::NotAuthenticated {..}) {
+                result = tokio::time::timeout_at(
+                    last_read + if !matches!(self.state, State::NotAuthenticated {..}) {
                         self.jmap.core.imap.timeout_auth
                     } else {
                         self.jmap.core.imap.timeout_unauth
@@ -71,16 +130,20 @@ impl<T: SessionStream> Session<T> {
                     match result {
                         Ok(Ok(bytes_read)) => {
                             if bytes_read > 0 {
+                                last_read = Instant::now();
                                 match self.ingest(&buf[..bytes_read]).await {
-                                    Ok(false) => (),
-                                    Ok(true) => {
-                                        return true;
+                                    Ok(None) => (),
+                                    Ok(Some(upgrade)) => {
+                                        return Some(upgrade);
                                     }
                                     Err(_) => {
                                         tracing::debug!(parent: &self.span, event = "disconnect", "Disconnecting client.");
                                         break;
                                     }
                                 }
+                                if self.flush_notify().await.is_err() {
+                                    break;
+                                }
                             } else {
                                 tracing::debug!(parent: &self.span, event = "close", "IMAP connection closed by client.");
                                 break;
@@ -97,6 +160,22 @@ impl<T: SessionStream> Session<T> {
                         }
                     }
                 },
+                state_change = next_notify_change(&mut self.notify) => {
+                    if let (Some(state_change), Some(notify)) = (state_change, &mut self.notify) {
+                        notify.queue(state_change);
+                        if self.flush_notify().await.is_err() {
+                            break;
+                        }
+                    } else {
+                        self.notify = None;
+                    }
+                },
+                _ = tokio::time::sleep(NOTIFY_RETRY_INTERVAL), if self.notify.as_ref().map_or(false, |notify| notify.has_changes()) => {
+                    // Retry once the commands running in the background have completed
+                    if self.flush_notify().await.is_err() {
+                        break;
+                    }
+                },
                 _ = shutdown_rx.changed() => {
                     self.write_bytes(&b"* BYE Server shutting down.\r\n"[..]).await.ok();
                     tracing::debug!(parent: &self.span, event = "shutdown", "IMAP server shutting down.");
@@ -105,13 +184,46 @@ impl<T: SessionStream> Session<T> {
             };
         }
 
-        false
+        None
     }
 
     pub async fn new(
         mut session: SessionData<T>,
         manager: ImapSessionManager,
     ) -> Result<Session<T>, ()> {
+        // Enforce per-IP connection limits
+        let jmap = JMAP::from(manager.imap.jmap_instance);
+        let in_flight_ip = if let Some(max_concurrent) = jmap.core.imap.max_connections_per_ip {
+            if let Some(in_flight) = manager
+                .imap
+                .imap_inner
+                .is_connection_allowed(session.remote_ip, max_concurrent)
+            {
+                Some(in_flight)
+            } else {
+                tracing::info!(
+                    parent: &session.span,
+                    context = "imap",
+                    event = "limit",
+                    remote_ip = %session.remote_ip,
+                    limit = max_concurrent,
+                    "Too many concurrent connections from IP address."
+                );
+                // The greeting cannot be NO, so the rejection is a BYE with the LIMIT code
+                let _ = session
+                    .stream
+                    .write_all(
+                        &StatusResponse::bye("Too many concurrent connections from this address.")
+                            .with_code(ResponseCode::Limit)
+                            .into_bytes(),
+                    )
+                    .await;
+                return Err(());
+            }
+        } else {
+            None
+        };
+
         // Write greeting
         let (is_tls, greeting) = if session.stream.is_tls() {
             (true, &manager.imap.imap_inner.greeting_tls)
@@ -125,22 +237,29 @@ impl<T: SessionStream> Session<T> {
         let _ = session.stream.flush().await;
 
         // Split stream into read and write halves
+        let channel_binding = session.stream.tls_exporter();
         let (stream_rx, stream_tx) = tokio::io::split(session.stream);
-        let jmap = JMAP::from(manager.imap.jmap_instance);
 
         Ok(Session {
-            receiver: Receiver::with_max_request_size(jmap.core.imap.max_request_size),
+            receiver: Receiver::with_max_request_size(jmap.core.imap.max_request_size)
+                .with_max_non_sync_literal(jmap.core.imap.max_non_sync_literal),
             version: ProtocolVersion::Rev1,
             state: State::NotAuthenticated { auth_failures: 0 },
             is_tls,
             is_condstore: false,
             is_qresync: false,
+            is_compressed: false,
             jmap,
             imap: manager.imap.imap_inner,
             instance: session.instance,
             span: session.span,
             in_flight: session.in_flight,
+            qos: session.qos,
+            in_flight_ip,
             remote_addr: session.remote_ip,
+            notify: None,
+            channel_binding,
+            sasl: None,
             stream_rx,
             stream_tx: Arc::new(tokio::sync::Mutex::new(stream_tx)),
         })
@@ -170,8 +289,9 @@ impl<T: SessionStream> Session<T> {
         };
 
         // Upgrade to TLS
-        let (stream_rx, stream_tx) =
-            tokio::io::split(self.instance.tls_accept(stream, &self.span).await?);
+        let stream = self.instance.tls_accept(stream, &self.span).await?;
+        let channel_binding = stream.tls_exporter();
+        let (stream_rx, stream_tx) = tokio::io::split(stream);
         let stream_tx = Arc::new(tokio::sync::Mutex::new(stream_tx));
 
         Ok(Session {
@@ -184,9 +304,66 @@ impl<T: SessionStream> Session<T> {
             is_tls: true,
             is_condstore: self.is_condstore,
             is_qresync: self.is_qresync,
+            is_compressed: false,
+            span: self.span,
+            in_flight: self.in_flight,
+            qos: self.qos,
+            in_flight_ip: self.in_flight_ip,
+            remote_addr: self.remote_addr,
+            notify: None,
+            channel_binding,
+            sasl: None,
+            stream_rx,
+            stream_tx,
+        })
+    }
+
+    pub async fn into_compressed(self) -> Result<Session<DeflateStream<T>>, ()> {
+        // Drop references to write half from state
+        let state = if let Some(state) =
+            self.state
+                .try_replace_stream_tx(Arc::new(tokio::sync::Mutex::new(
+                    tokio::io::split(NullIo::default()).1,
+                ))) {
+            state
+        } else {
+            tracing::debug!("Failed to obtain write half state.");
+            return Err(());
+        };
+
+        // Take ownership of WriteHalf and unsplit it from ReadHalf
+        let stream = if let Ok(stream_tx) =
+            Arc::try_unwrap(self.stream_tx).map(|mutex| mutex.into_inner())
+        {
+            self.stream_rx.unsplit(stream_tx)
+        } else {
+            tracing::debug!("Failed to take ownership of write half.");
+            return Err(());
+        };
+
+        // Enable compression
+        let (stream_rx, stream_tx) = tokio::io::split(DeflateStream::new(stream));
+        let stream_tx = Arc::new(tokio::sync::Mutex::new(stream_tx));
+
+        Ok(Session {
+            jmap: self.jmap,
+            imap: self.imap,
+            instance: self.instance,
+            receiver: self.receiver,
+            version: self.version,
+            state: state.try_replace_stream_tx(stream_tx.clone()).unwrap(),
+            is_tls: self.is_tls,
+            is_condstore: self.is_condstore,
+            is_qresync: self.is_qresync,
+            is_compressed: true,
             span: self.span,
             in_flight: self.in_flight,
+            qos: self.qos,
+            in_flight_ip: self.in_flight_ip,
             remote_addr: self.remote_addr,
+            notify: self.notify,
+            channel_binding: self.channel_binding,
+            sasl: None,
             stream_rx,
             stream_tx,
         })
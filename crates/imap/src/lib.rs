@@ -21,12 +21,13 @@
  * for more details.
 */
 
-use core::{ImapInstance, Inner, IMAP};
+use core::{session::CONNECTION_LIMITER_PURGE, ImapInstance, Inner, IMAP};
 use std::{collections::hash_map::RandomState, sync::Arc};
 
 use dashmap::DashMap;
 use imap_proto::{protocol::capability::Capability, ResponseCode, StatusResponse};
 use jmap::JmapInstance;
+use op::authenticate::sasl_mechanisms;
 use utils::{
     config::Config,
     lru_cache::{LruCache, LruCached},
@@ -44,16 +45,36 @@ impl IMAP {
             .unwrap_or(32)
             .next_power_of_two() as usize;
         let capacity = config.property("cache.capacity").unwrap_or(100);
+        let (is_literal_minus, append_limit, mechanisms) = {
+            let core = jmap_instance.core.load();
+            (
+                core.imap.max_non_sync_literal.is_some(),
+                Some(core.jmap.mail_max_size as u64),
+                core.imap.sasl_mechanisms.clone(),
+            )
+        };
 
         let inner = Inner {
             greeting_plain: StatusResponse::ok(SERVER_GREETING)
                 .with_code(ResponseCode::Capability {
-                    capabilities: Capability::all_capabilities(false, false),
+                    capabilities: Capability::all_capabilities(
+                        false,
+                        false,
+                        is_literal_minus,
+                        append_limit,
+                        &sasl_mechanisms(&mechanisms, false),
+                    ),
                 })
                 .into_bytes(),
             greeting_tls: StatusResponse::ok(SERVER_GREETING)
                 .with_code(ResponseCode::Capability {
-                    capabilities: Capability::all_capabilities(false, true),
+                    capabilities: Capability::all_capabilities(
+                        false,
+                        true,
+                        is_literal_minus,
+                        append_limit,
+                        &sasl_mechanisms(&mechanisms, true),
+                    ),
                 })
                 .into_bytes(),
             rate_limiter: DashMap::with_capacity_and_hasher_and_shard_amount(
@@ -61,6 +82,12 @@ impl IMAP {
                 RandomState::default(),
                 shard_amount,
             ),
+            connection_limiter: DashMap::with_capacity_and_hasher_and_shard_amount(
+                capacity,
+                RandomState::default(),
+                shard_amount,
+            ),
+            connection_limiter_purge: CONNECTION_LIMITER_PURGE.into(),
             cache_account: LruCache::with_capacity(
                 config.property("cache.account.size").unwrap_or(2048),
             ),
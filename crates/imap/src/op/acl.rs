@@ -404,7 +404,7 @@ impl<T: SessionStream> Session<T> {
                     }
 
                     // Invalidate ACLs
-                    data.jmap.inner.access_tokens.remove(&acl_account_id);
+                    data.jmap.invalidate_access_tokens(acl_account_id);
 
                     data.write_bytes(
                         StatusResponse::completed(command)
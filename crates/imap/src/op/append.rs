@@ -116,50 +116,83 @@ impl<T: SessionStream> SessionData<T> {
             .map_err(|r| r.with_tag(&arguments.tag))?
             .quota as i64;
 
-        // Append messages
-        let mut response = StatusResponse::completed(Command::Append);
-        let mut created_ids = Vec::with_capacity(arguments.messages.len());
-        let mut last_change_id = None;
-        for message in arguments.messages {
-            match self
-                .jmap
-                .email_ingest(IngestEmail {
-                    raw_message: &message.message,
-                    message: MessageParser::new().parse(&message.message),
-                    account_id,
-                    account_quota,
-                    mailbox_ids: vec![mailbox_id],
-                    keywords: message.flags.into_iter().map(Keyword::from).collect(),
-                    received_at: message.received_at.map(|d| d as u64),
-                    skip_duplicates: false,
-                    encrypt: self.jmap.core.jmap.encrypt && self.jmap.core.jmap.encrypt_append,
-                })
-                .await
+        // MULTIAPPEND is atomic, validate all messages before ingesting any of them
+        let max_size = self.jmap.core.jmap.mail_max_size;
+        if arguments
+            .messages
+            .iter()
+            .any(|message| message.message.len() > max_size)
+        {
+            return Ok(StatusResponse::no(format!(
+                "Message exceeds the maximum size of {max_size} bytes."
+            ))
+            .with_tag(arguments.tag)
+            .with_code(ResponseCode::TooBig));
+        }
+        if account_quota > 0 && arguments.messages.len() > 1 {
+            let total_size = arguments
+                .messages
+                .iter()
+                .map(|message| message.message.len() as i64)
+                .sum::<i64>();
+            if total_size
+                + self
+                    .jmap
+                    .get_used_quota(account_id)
+                    .await
+                    .map_err(|_| StatusResponse::database_failure().with_tag(&arguments.tag))?
+                > account_quota
             {
-                Ok(email) => {
-                    created_ids.push(ImapUidToId {
+                return Ok(StatusResponse::no("Disk quota exceeded.")
+                    .with_tag(arguments.tag)
+                    .with_code(ResponseCode::OverQuota));
+            }
+        }
+
+        // Append messages, all of them are written in a single batch
+        let mut response = StatusResponse::completed(Command::Append);
+        let encrypt = self.jmap.core.jmap.encrypt && self.jmap.core.jmap.encrypt_append;
+        let (created_ids, last_change_id) = match self
+            .jmap
+            .email_ingest_many(
+                arguments
+                    .messages
+                    .iter()
+                    .map(|message| IngestEmail {
+                        raw_message: &message.message,
+                        message: MessageParser::new().parse(&message.message),
+                        account_id,
+                        account_quota,
+                        mailbox_ids: vec![mailbox_id],
+                        keywords: message.flags.iter().cloned().map(Keyword::from).collect(),
+                        received_at: message.received_at.map(|d| d as u64),
+                        skip_duplicates: false,
+                        encrypt,
+                    })
+                    .collect(),
+            )
+            .await
+        {
+            Ok(emails) => (
+                emails
+                    .iter()
+                    .map(|email| ImapUidToId {
                         uid: email.imap_uids[0],
                         id: email.id.document_id(),
-                    });
-                    last_change_id = Some(email.change_id);
-                }
-                Err(err) => {
-                    match err {
-                        jmap::IngestError::Temporary => {
-                            response = StatusResponse::database_failure();
-                        }
-                        jmap::IngestError::OverQuota => {
-                            response = StatusResponse::no("Disk quota exceeded.")
-                                .with_code(ResponseCode::OverQuota);
-                        }
-                        jmap::IngestError::Permanent { reason, .. } => {
-                            response = StatusResponse::no(reason);
-                        }
-                    }
-                    break;
-                }
+                    })
+                    .collect::<Vec<_>>(),
+                emails.last().map(|email| email.change_id),
+            ),
+            Err(err) => {
+                response = match err {
+                    jmap::IngestError::Temporary => StatusResponse::database_failure(),
+                    jmap::IngestError::OverQuota => StatusResponse::no("Disk quota exceeded.")
+                        .with_code(ResponseCode::OverQuota),
+                    jmap::IngestError::Permanent { reason, .. } => StatusResponse::no(reason),
+                };
+                (Vec::new(), None)
             }
-        }
+        };
 
         // Broadcast changes
         if let Some(change_id) = last_change_id {
@@ -21,12 +21,20 @@
  * for more details.
 */
 
-use common::{listener::SessionStream, AuthResult};
+use common::{
+    listener::SessionStream,
+    sasl::{SaslExchange, SaslMechanism, SaslStep},
+    AuthResult,
+};
 use imap_proto::{
-    protocol::{authenticate::Mechanism, capability::Capability},
+    protocol::{
+        authenticate::{Arguments, Mechanism},
+        capability::Capability,
+    },
     receiver::{self, Request},
     Command, ResponseCode, StatusResponse,
 };
+use jmap::auth::AccessToken;
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use std::sync::Arc;
@@ -79,6 +87,9 @@ impl<T: SessionStream> Session<T> {
                         self.write_bytes(b"+ \"\"\r\n".to_vec()).await
                     }
                 }
+                Mechanism::ScramSha256 | Mechanism::ScramSha256Plus | Mechanism::CramMd5 => {
+                    self.handle_sasl_exchange(args).await
+                }
                 _ => {
                     self.write_bytes(
                         StatusResponse::no("Authentication mechanism not supported.")
@@ -93,11 +104,89 @@ impl<T: SessionStream> Session<T> {
         }
     }
 
-    pub async fn authenticate(
-        &mut self,
-        credentials: Credentials<String>,
-        tag: String,
-    ) -> crate::Result<()> {
+    async fn handle_sasl_exchange(&mut self, mut args: Arguments) -> crate::OpResult {
+        // Continue the exchange when this is a response to a previous challenge
+        let mut exchange = match self.sasl.take() {
+            Some((tag, exchange))
+                if tag == args.tag
+                    && Some(exchange.mechanism()) == sasl_mechanism(&args.mechanism) =>
+            {
+                exchange
+            }
+            _ => {
+                let mechanism = match sasl_mechanism(&args.mechanism)
+                    .filter(|mechanism| self.jmap.core.imap.sasl_mechanisms.contains(mechanism))
+                {
+                    Some(mechanism) => mechanism,
+                    None => {
+                        return self
+                            .write_bytes(
+                                StatusResponse::no("Authentication mechanism not supported.")
+                                    .with_tag(args.tag)
+                                    .with_code(ResponseCode::Cannot)
+                                    .into_bytes(),
+                            )
+                            .await;
+                    }
+                };
+                self.is_auth_allowed().await?;
+                SaslExchange::new(mechanism, self.channel_binding.clone())
+            }
+        };
+
+        let response = match args.params.pop() {
+            Some(response) => match base64_decode(response.as_bytes()) {
+                Some(response) => Some(response),
+                None if response.is_empty() => Some(vec![]),
+                None => {
+                    return self
+                        .write_bytes(
+                            StatusResponse::no("Failed to decode challenge.")
+                                .with_tag(args.tag)
+                                .with_code(ResponseCode::Parse)
+                                .into_bytes(),
+                        )
+                        .await;
+                }
+            },
+            None => None,
+        };
+
+        match self
+            .jmap
+            .authenticate_sasl(&mut exchange, response.as_deref(), self.remote_addr)
+            .await
+        {
+            SaslStep::Challenge(challenge) => {
+                self.sasl = Some((args.tag.clone(), exchange));
+                self.receiver.request = receiver::Request {
+                    tag: args.tag,
+                    command: Command::Authenticate,
+                    tokens: vec![receiver::Token::Argument(args.mechanism.into_bytes())],
+                };
+                self.receiver.state = receiver::State::Argument { last_ch: b' ' };
+                self.write_bytes(format!("+ {challenge}\r\n").into_bytes())
+                    .await
+            }
+            SaslStep::Done(AuthResult::Success(access_token)) => {
+                self.finish_authentication(Some(access_token), args.tag)
+                    .await
+            }
+            SaslStep::Done(AuthResult::Failure) => self.finish_authentication(None, args.tag).await,
+            SaslStep::Done(AuthResult::Banned) => Err(()),
+            SaslStep::Invalid(err) => {
+                self.write_bytes(
+                    StatusResponse::no(err)
+                        .with_tag(args.tag)
+                        .with_code(ResponseCode::Parse)
+                        .into_bytes(),
+                )
+                .await
+            }
+        }
+    }
+
+    async fn is_auth_allowed(&mut self) -> crate::Result<()> {
         // Throttle authentication requests
         if self
             .jmap
@@ -114,8 +203,18 @@ impl<T: SessionStream> Session<T> {
                 event = "disconnect",
                 "Too many authentication attempts, disconnecting.",
             );
-            return Err(());
+            Err(())
+        } else {
+            Ok(())
         }
+    }
+
+    pub async fn authenticate(
+        &mut self,
+        credentials: Credentials<String>,
+        tag: String,
+    ) -> crate::Result<()> {
+        self.is_auth_allowed().await?;
 
         // Authenticate
         let access_token = match credentials {
@@ -150,6 +249,29 @@ impl<T: SessionStream> Session<T> {
             }
         };
 
+        self.finish_authentication(access_token, tag).await
+    }
+
+    async fn finish_authentication(
+        &mut self,
+        access_token: Option<AccessToken>,
+        tag: String,
+    ) -> crate::Result<()> {
+        // Make sure the account is allowed to use this protocol
+        let access_token = access_token.filter(|access_token| {
+            if access_token.is_protocol_allowed("imap") {
+                true
+            } else {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "authenticate",
+                    account_id = access_token.primary_id(),
+                    "Account is not allowed to access IMAP."
+                );
+                false
+            }
+        });
+
         if let Some(access_token) = access_token {
             // Enforce concurrency limits
             let in_flight = match self
@@ -159,15 +281,21 @@ impl<T: SessionStream> Session<T> {
                 Some(Some(limiter)) => Some(limiter),
                 None => None,
                 Some(None) => {
-                    self.write_bytes(
-                        StatusResponse::bye("Too many concurrent IMAP connections.").into_bytes(),
-                    )
-                    .await?;
-                    tracing::debug!(parent: &self.span,
-                        event = "disconnect",
-                        "Too many concurrent connections, disconnecting.",
+                    tracing::info!(parent: &self.span,
+                        context = "imap",
+                        event = "limit",
+                        account_id = access_token.primary_id(),
+                        limit = self.jmap.core.imap.rate_concurrent.unwrap_or_default(),
+                        "Too many concurrent connections for account.",
                     );
-                    return Err(());
+                    return self
+                        .write_bytes(
+                            StatusResponse::no("Too many concurrent IMAP connections.")
+                                .with_tag(tag)
+                                .with_code(ResponseCode::Limit)
+                                .into_bytes(),
+                        )
+                        .await;
                 }
             };
 
@@ -175,6 +303,9 @@ impl<T: SessionStream> Session<T> {
             let access_token = Arc::new(access_token);
             self.jmap.cache_access_token(access_token.clone());
 
+            // Move the connection to the authenticated class
+            self.qos.promote();
+
             // Create session
             self.state = State::Authenticated {
                 data: Arc::new(SessionData::new(self, &access_token, in_flight).await?),
@@ -182,7 +313,13 @@ impl<T: SessionStream> Session<T> {
             self.write_bytes(
                 StatusResponse::ok("Authentication successful")
                     .with_code(ResponseCode::Capability {
-                        capabilities: Capability::all_capabilities(true, self.is_tls),
+                        capabilities: Capability::all_capabilities(
+                            true,
+                            self.is_tls,
+                            self.jmap.core.imap.max_non_sync_literal.is_some(),
+                            Some(self.jmap.core.jmap.mail_max_size as u64),
+                            &[],
+                        ),
                     })
                     .with_tag(tag)
                     .into_bytes(),
@@ -220,6 +357,7 @@ impl<T: SessionStream> Session<T> {
     }
 
     pub async fn handle_unauthenticate(&mut self, request: Request<Command>) -> crate::OpResult {
+        self.notify = None;
         self.state = State::NotAuthenticated { auth_failures: 0 };
 
         self.write_bytes(
@@ -231,6 +369,27 @@ impl<T: SessionStream> Session<T> {
     }
 }
 
+pub fn sasl_mechanisms(mechanisms: &[SaslMechanism], is_tls: bool) -> Vec<Mechanism> {
+    mechanisms
+        .iter()
+        .filter_map(|mechanism| match mechanism {
+            SaslMechanism::ScramSha256 => Some(Mechanism::ScramSha256),
+            SaslMechanism::ScramSha256Plus if is_tls => Some(Mechanism::ScramSha256Plus),
+            SaslMechanism::CramMd5 => Some(Mechanism::CramMd5),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn sasl_mechanism(mechanism: &Mechanism) -> Option<SaslMechanism> {
+    match mechanism {
+        Mechanism::ScramSha256 => Some(SaslMechanism::ScramSha256),
+        Mechanism::ScramSha256Plus => Some(SaslMechanism::ScramSha256Plus),
+        Mechanism::CramMd5 => Some(SaslMechanism::CramMd5),
+        _ => None,
+    }
+}
+
 pub fn decode_challenge_plain(challenge: &[u8]) -> Result<Credentials<String>, &'static str> {
     let mut username = Vec::new();
     let mut secret = Vec::new();
@@ -255,6 +414,43 @@ pub fn decode_challenge_plain(challenge: &[u8]) -> Result<Credentials<String>, &
     }
 }
 
+pub fn decode_challenge_plain_authzid(
+    challenge: &[u8],
+) -> Result<(Option<String>, Credentials<String>), &'static str> {
+    let mut authz_id = Vec::new();
+    let mut username = Vec::new();
+    let mut secret = Vec::new();
+    let mut arg_num = 0;
+    for &ch in challenge {
+        if ch != 0 {
+            match arg_num {
+                0 => authz_id.push(ch),
+                1 => username.push(ch),
+                2 => secret.push(ch),
+                _ => (),
+            }
+        } else {
+            arg_num += 1;
+        }
+    }
+
+    match (
+        String::from_utf8(authz_id),
+        String::from_utf8(username),
+        String::from_utf8(secret),
+    ) {
+        (Ok(authz_id), Ok(username), Ok(secret))
+            if arg_num == 2 && !username.is_empty() && !secret.is_empty() =>
+        {
+            Ok((
+                Some(authz_id).filter(|authz_id| !authz_id.is_empty()),
+                (username, secret).into(),
+            ))
+        }
+        _ => Err("Invalid AUTH=PLAIN challenge."),
+    }
+}
+
 pub fn decode_challenge_oauth(challenge: &[u8]) -> Result<Credentials<String>, &'static str> {
     let mut saw_marker = true;
     for (pos, &ch) in challenge.iter().enumerate() {
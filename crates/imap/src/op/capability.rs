@@ -32,6 +32,8 @@ use imap_proto::{
     Command, StatusResponse,
 };
 
+use super::authenticate::sasl_mechanisms;
+
 impl<T: SessionStream> Session<T> {
     pub async fn handle_capability(&mut self, request: Request<Command>) -> crate::OpResult {
         self.write_bytes(
@@ -42,6 +44,9 @@ impl<T: SessionStream> Session<T> {
                         capabilities: Capability::all_capabilities(
                             self.state.is_authenticated(),
                             self.is_tls,
+                            self.jmap.core.imap.max_non_sync_literal.is_some(),
+                            Some(self.jmap.core.jmap.mail_max_size as u64),
+                            &sasl_mechanisms(&self.jmap.core.imap.sasl_mechanisms, self.is_tls),
                         ),
                     }
                     .serialize(),
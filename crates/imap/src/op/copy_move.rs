@@ -30,7 +30,10 @@ use imap_proto::{
 
 use crate::core::{MailboxId, SelectedMailbox, Session, SessionData};
 use common::listener::SessionStream;
-use jmap::{email::set::TagManager, mailbox::UidMailbox};
+use jmap::{
+    email::set::TagManager,
+    mailbox::{SavedDate, UidMailbox, JUNK_ID, TRASH_ID},
+};
 use jmap_proto::{
     error::{method::MethodError, set::SetErrorType},
     types::{
@@ -40,7 +43,7 @@ use jmap_proto::{
 };
 use store::{
     roaring::RoaringBitmap,
-    write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder, F_VALUE},
+    write::{assert::HashedValue, log::ChangeLogBuilder, now, BatchBuilder, F_VALUE},
 };
 
 impl<T: SessionStream> Session<T> {
@@ -186,6 +189,20 @@ impl<T: SessionStream> SessionData<T> {
         if src_mailbox.id.account_id == dest_mailbox.account_id {
             // Mailboxes are in the same account
             let account_id = src_mailbox.id.account_id;
+
+            // Moving messages into or out of Junk trains the account's spam model
+            let train_spam = if !self.jmap.has_spam_training() {
+                None
+            } else if dest_mailbox_id == JUNK_ID {
+                Some(true)
+            } else if is_move && src_mailbox.id.mailbox_id == JUNK_ID && dest_mailbox_id != TRASH_ID
+            {
+                Some(false)
+            } else {
+                None
+            };
+            let mut train_ids = Vec::new();
+
             let dest_mailbox_id = UidMailbox::new_unassigned(dest_mailbox_id);
             for (id, imap_id) in ids {
                 // Obtain mailbox tags
@@ -249,6 +266,25 @@ impl<T: SessionStream> SessionData<T> {
                     .with_collection(Collection::Email)
                     .update_document(id);
                 mailboxes.update_batch(&mut batch, Property::MailboxIds);
+
+                // Record the save date for the destination mailbox only
+                let mut saved_dates = self
+                    .jmap
+                    .get_property::<Vec<SavedDate>>(
+                        account_id,
+                        Collection::Email,
+                        id,
+                        Property::SavedAt,
+                    )
+                    .await
+                    .map_err(|_| StatusResponse::database_failure().with_tag(&arguments.tag))?
+                    .unwrap_or_default();
+                saved_dates.retain(|saved_date| {
+                    saved_date.mailbox_id != dest_mailbox_id.mailbox_id
+                        && (!is_move || saved_date.mailbox_id != src_mailbox.id.mailbox_id)
+                });
+                saved_dates.push(SavedDate::new(dest_mailbox_id.mailbox_id, now()));
+                batch.value(Property::SavedAt, saved_dates, F_VALUE);
                 if changelog.change_id == u64::MAX {
                     changelog.change_id =
                         self.jmap.assign_change_id(account_id).await.map_err(|_| {
@@ -265,6 +301,9 @@ impl<T: SessionStream> SessionData<T> {
                                 .log_child_update(Collection::Mailbox, src_mailbox.id.mailbox_id);
                             did_move = true;
                         }
+                        if train_spam.is_some() {
+                            train_ids.push(id);
+                        }
                     }
                     Err(MethodError::ServerUnavailable) => {
                         response.rtype = ResponseType::No;
@@ -275,6 +314,14 @@ impl<T: SessionStream> SessionData<T> {
                     }
                 }
             }
+
+            if let Some(is_spam) = train_spam.filter(|_| !train_ids.is_empty()) {
+                let jmap = self.jmap.clone();
+                tokio::spawn(async move {
+                    jmap.bayes_train_account(account_id, train_ids, is_spam)
+                        .await;
+                });
+            }
         } else {
             // Obtain quota for target account
             let src_account_id = src_mailbox.id.account_id;
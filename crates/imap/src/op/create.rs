@@ -149,7 +149,7 @@ impl<T: SessionStream> SessionData<T> {
         // Build response
         StatusResponse::ok("Mailbox created.")
             .with_code(ResponseCode::MailboxId {
-                mailbox_id: Id::from_parts(params.account_id, parent_id - 1).to_string(),
+                mailbox_id: Id::from(parent_id - 1).to_string(),
             })
             .with_tag(arguments.tag)
     }
@@ -39,7 +39,7 @@ use imap_proto::{
     receiver::Request,
     Command, ResponseCode, StatusResponse,
 };
-use jmap::email::metadata::MessageMetadata;
+use jmap::{email::metadata::MessageMetadata, mailbox::SavedDate};
 use jmap_proto::{
     error::method::MethodError,
     types::{
@@ -85,6 +85,7 @@ impl<T: SessionStream> Session<T> {
                             is_qresync,
                             is_rev2,
                             enabled_condstore,
+                            0,
                         )
                         .await
                         .into_bytes(),
@@ -107,6 +108,7 @@ impl<T: SessionStream> SessionData<T> {
         is_qresync: bool,
         _is_rev2: bool,
         enabled_condstore: bool,
+        verified_uid: u32,
     ) -> StatusResponse {
         // Validate VANISHED parameter
         if arguments.include_vanished {
@@ -176,10 +178,14 @@ impl<T: SessionStream> SessionData<T> {
 
             // Send vanished UIDs
             if arguments.include_vanished && has_vanished {
-                // Add to vanished all known destroyed Ids
-                let vanished = mailbox
+                // Add to vanished all known destroyed Ids, skipping those below
+                // the UID the client proved to be in sync through seq-match.
+                let mut vanished = mailbox
                     .sequence_expand_missing(&arguments.sequence_set, true)
                     .await;
+                if verified_uid > 0 {
+                    vanished.retain(|&uid| uid > verified_uid);
+                }
 
                 if !vanished.is_empty() {
                     let mut buf = Vec::with_capacity(vanished.len() * 3);
@@ -242,7 +248,7 @@ impl<T: SessionStream> SessionData<T> {
                     }
                     needs_blobs = true;
                 }
-                Attribute::ThreadId => {
+                Attribute::ThreadId | Attribute::EmailId => {
                     needs_thread_id = true;
                 }
                 _ => (),
@@ -477,12 +483,31 @@ impl<T: SessionStream> SessionData<T> {
                     }
                     Attribute::EmailId => {
                         items.push(DataItem::EmailId {
-                            email_id: Id::from_parts(account_id, id).to_string(),
+                            email_id: Id::from_parts(thread_id, id).to_string(),
                         });
                     }
                     Attribute::ThreadId => {
                         items.push(DataItem::ThreadId {
-                            thread_id: Id::from_parts(account_id, thread_id).to_string(),
+                            thread_id: Id::from(thread_id).to_string(),
+                        });
+                    }
+                    Attribute::SaveDate => {
+                        // Messages saved before save dates were recorded return NIL
+                        let saved_at = self
+                            .jmap
+                            .get_property::<Vec<SavedDate>>(
+                                account_id,
+                                Collection::Email,
+                                id,
+                                Property::SavedAt,
+                            )
+                            .await
+                            .unwrap_or_default()
+                            .and_then(|saved_dates| {
+                                SavedDate::find(&saved_dates, mailbox.id.mailbox_id)
+                            });
+                        items.push(DataItem::SaveDate {
+                            date: saved_at.map(|saved_at| saved_at as i64),
                         });
                     }
                 }
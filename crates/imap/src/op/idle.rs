@@ -43,8 +43,15 @@ use utils::map::bitmap::Bitmap;
 
 use crate::core::{SelectedMailbox, Session, SessionData, State};
 
+use super::notify::next_notify_change;
+
 impl<T: SessionStream> Session<T> {
     pub async fn handle_idle(&mut self, request: Request<Command>) -> crate::OpResult {
+        // When NOTIFY is active, its queued events are sent instead (RFC 5465)
+        if self.notify.is_some() {
+            return self.handle_idle_notify(request).await;
+        }
+
         let (data, mailbox, types) = match &self.state {
             State::Authenticated { data, .. } => {
                 (data.clone(), None, Bitmap::from_iter([DataType::Mailbox]))
@@ -141,6 +148,58 @@ impl<T: SessionStream> Session<T> {
     }
 }
 
+impl<T: SessionStream> Session<T> {
+    async fn handle_idle_notify(&mut self, request: Request<Command>) -> crate::OpResult {
+        // Send any events queued while the previous commands were running
+        self.flush_notify().await?;
+
+        self.write_bytes(b"+ Idling, send 'DONE' to stop.\r\n".to_vec())
+            .await?;
+        tracing::debug!(parent: &self.span, event = "start", context = "idle", "Starting IDLE.");
+        let mut buf = vec![0; 1024];
+        loop {
+            tokio::select! {
+                result = tokio::time::timeout(self.jmap.core.imap.timeout_idle, self.stream_rx.read(&mut buf)) => {
+                    match result {
+                        Ok(Ok(bytes_read)) => {
+                            if bytes_read > 0 {
+                                if (buf[..bytes_read]).windows(4).any(|w| w == b"DONE") {
+                                    tracing::debug!(parent: &self.span, event = "stop", context = "idle", "Stopping IDLE.");
+                                    return self.write_bytes(StatusResponse::completed(Command::Idle)
+                                                                    .with_tag(request.tag)
+                                                                    .into_bytes()).await;
+                                }
+                            } else {
+                                tracing::debug!(parent: &self.span, event = "close", "IMAP connection closed by client.");
+                                return Err(());
+                            }
+                        },
+                        Ok(Err(err)) => {
+                            tracing::debug!(parent: &self.span, event = "error", reason = %err, "IMAP connection error.");
+                            return Err(());
+                        },
+                        Err(_) => {
+                            self.write_bytes(&b"* BYE IDLE timed out.\r\n"[..]).await.ok();
+                            tracing::debug!(parent: &self.span, "IDLE timed out.");
+                            return Err(());
+                        }
+                    }
+                }
+                state_change = next_notify_change(&mut self.notify) => {
+                    if let (Some(state_change), Some(notify)) = (state_change, &mut self.notify) {
+                        notify.queue(state_change);
+                        self.flush_notify().await?;
+                    } else {
+                        self.write_bytes(&b"* BYE Server shutting down.\r\n"[..]).await.ok();
+                        tracing::debug!(parent: &self.span, "NOTIFY channel closed.");
+                        return Err(());
+                    }
+                }
+            }
+        }
+    }
+}
+
 impl<T: SessionStream> SessionData<T> {
     pub async fn write_changes(
         &self,
@@ -271,6 +330,7 @@ impl<T: SessionStream> SessionData<T> {
                         is_qresync,
                         is_rev2,
                         false,
+                        0,
                     )
                     .await;
                 }
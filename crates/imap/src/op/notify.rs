@@ -0,0 +1,357 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::sync::Arc;
+
+use common::listener::SessionStream;
+use imap_proto::{
+    protocol::{
+        list::{Attribute, ListItem},
+        notify::{Arguments, Event, EventGroup, MailboxFilter},
+        status::Status,
+    },
+    receiver::Request,
+    Command, ResponseCode, StatusResponse,
+};
+use jmap_proto::types::{state::StateChange, type_state::DataType};
+use utils::map::bitmap::Bitmap;
+
+use crate::core::{Notifier, SelectedMailbox, Session, SessionData, State};
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_notify(&mut self, request: Request<Command>) -> crate::OpResult {
+        let arguments = match request.parse_notify(self.version) {
+            Ok(arguments) => arguments,
+            Err(response) => return self.write_bytes(response.into_bytes()).await,
+        };
+
+        // Replace any previous registration
+        self.notify = None;
+        if arguments.groups.is_empty() {
+            return self
+                .write_bytes(
+                    StatusResponse::completed(Command::Notify)
+                        .with_tag(arguments.tag)
+                        .into_bytes(),
+                )
+                .await;
+        }
+
+        if arguments
+            .groups
+            .iter()
+            .any(|group| group.events.iter().any(|event| !event.is_supported()))
+        {
+            return self
+                .write_bytes(
+                    StatusResponse::no("Unsupported event requested.")
+                        .with_tag(arguments.tag)
+                        .with_code(ResponseCode::BadEvent)
+                        .into_bytes(),
+                )
+                .await;
+        }
+
+        let (data, mailbox) = match &self.state {
+            State::Authenticated { data } => (data.clone(), None),
+            State::Selected { data, mailbox } => (data.clone(), mailbox.clone().into()),
+            _ => unreachable!(),
+        };
+
+        // Register with state manager
+        let change_rx = if let Some(change_rx) = self
+            .jmap
+            .subscribe_state_manager(
+                data.account_id,
+                Bitmap::from_iter([DataType::Email, DataType::Mailbox, DataType::EmailDelivery]),
+            )
+            .await
+        {
+            change_rx
+        } else {
+            return self
+                .write_bytes(
+                    StatusResponse::no("It was not possible to start NOTIFY.")
+                        .with_tag(arguments.tag)
+                        .with_code(ResponseCode::ContactAdmin)
+                        .into_bytes(),
+                )
+                .await;
+        };
+
+        // Send the initial status of all monitored mailboxes
+        if arguments.status {
+            data.write_notify_status(&arguments, mailbox.as_ref(), self.version.is_rev2())
+                .await;
+        }
+
+        tracing::debug!(parent: &self.span, event = "start", context = "notify", "Starting NOTIFY.");
+        self.notify = Some(Notifier {
+            groups: arguments.groups,
+            change_rx,
+            has_mailbox_changes: false,
+            has_email_changes: false,
+        });
+
+        self.write_bytes(
+            StatusResponse::completed(Command::Notify)
+                .with_tag(arguments.tag)
+                .into_bytes(),
+        )
+        .await
+    }
+
+    // Changes are queued as they arrive and only written out once no command
+    // is in progress, as required by RFC 5465 and RFC 3501 section 7.4.1.
+    pub async fn flush_notify(&mut self) -> crate::OpResult {
+        let notify = if let Some(notify) = &mut self.notify {
+            notify
+        } else {
+            return Ok(());
+        };
+        while let Ok(state_change) = notify.change_rx.try_recv() {
+            notify.queue(state_change);
+        }
+        if !notify.has_changes() {
+            return Ok(());
+        }
+
+        // Commands running in the background hold a reference to the session data
+        let (data, mailbox) = match &self.state {
+            State::Authenticated { data } => (data, None),
+            State::Selected { data, mailbox } => (data, Some(mailbox.clone())),
+            _ => return Ok(()),
+        };
+        if Arc::strong_count(data) > 1 {
+            return Ok(());
+        }
+
+        data.write_notify_changes(
+            &notify.groups,
+            mailbox,
+            notify.has_mailbox_changes,
+            notify.has_email_changes,
+            self.is_qresync,
+            self.version.is_rev2(),
+        )
+        .await;
+        notify.has_mailbox_changes = false;
+        notify.has_email_changes = false;
+
+        Ok(())
+    }
+}
+
+impl Notifier {
+    pub fn queue(&mut self, state_change: StateChange) {
+        for (type_state, _) in state_change.types {
+            match type_state {
+                DataType::Email | DataType::EmailDelivery => {
+                    self.has_email_changes = true;
+                }
+                DataType::Mailbox => {
+                    self.has_mailbox_changes = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn has_changes(&self) -> bool {
+        self.has_mailbox_changes || self.has_email_changes
+    }
+}
+
+// Resolves to the next state change, or never when NOTIFY is not active
+pub async fn next_notify_change(notify: &mut Option<Notifier>) -> Option<StateChange> {
+    if let Some(notify) = notify {
+        notify.change_rx.recv().await
+    } else {
+        std::future::pending().await
+    }
+}
+
+impl<T: SessionStream> SessionData<T> {
+    async fn write_notify_status(
+        &self,
+        arguments: &Arguments,
+        selected: Option<&Arc<SelectedMailbox>>,
+        is_rev2: bool,
+    ) {
+        let has_selected = arguments
+            .groups
+            .iter()
+            .any(|group| group.filter.is_selected());
+        let mailbox_names = self
+            .mailboxes
+            .lock()
+            .iter()
+            .flat_map(|account| account.mailbox_names.keys().cloned())
+            .collect::<Vec<_>>();
+
+        let mut buf = Vec::with_capacity(64);
+        for mailbox_name in mailbox_names {
+            if self.is_notify_target(
+                &arguments.groups,
+                &mailbox_name,
+                has_selected.then_some(selected).flatten(),
+                |group| group.has_message_events(),
+            ) {
+                if let Ok(status) = self.status(mailbox_name, NOTIFY_STATUS).await {
+                    status.serialize(&mut buf, is_rev2);
+                }
+            }
+        }
+
+        if !buf.is_empty() {
+            self.write_bytes(buf).await;
+        }
+    }
+
+    async fn write_notify_changes(
+        &self,
+        groups: &[EventGroup],
+        selected: Option<Arc<SelectedMailbox>>,
+        check_mailboxes: bool,
+        check_emails: bool,
+        is_qresync: bool,
+        is_rev2: bool,
+    ) {
+        // Changes to the selected mailbox are reported as untagged EXISTS, EXPUNGE and FETCH
+        let has_selected = groups
+            .iter()
+            .any(|group| group.filter.is_selected() && group.has_message_events());
+        if has_selected && check_emails && selected.is_some() {
+            self.write_changes(&selected, false, true, is_qresync, is_rev2)
+                .await;
+        }
+
+        if !check_mailboxes {
+            return;
+        }
+
+        // Other mailboxes are reported with LIST and STATUS responses
+        let changes = match self.synchronize_mailboxes(true).await {
+            Ok(Some(changes)) => changes,
+            Ok(None) => return,
+            Err(_) => {
+                tracing::debug!(parent: &self.span, "Failed to refresh mailboxes.");
+                return;
+            }
+        };
+        let selected = if has_selected {
+            selected.as_ref()
+        } else {
+            None
+        };
+        let mut buf = Vec::with_capacity(64);
+
+        for (mailbox_names, attributes) in [
+            (changes.deleted, vec![Attribute::NonExistent]),
+            (changes.added, vec![]),
+        ] {
+            for mailbox_name in mailbox_names {
+                if self.is_notify_target(groups, &mailbox_name, None, |group| {
+                    group.has_event(Event::MailboxName)
+                }) {
+                    ListItem {
+                        mailbox_name,
+                        attributes: attributes.clone(),
+                        tags: vec![],
+                    }
+                    .serialize(&mut buf, is_rev2, false);
+                }
+            }
+        }
+
+        for mailbox_name in changes.changed {
+            if self.is_notify_target(groups, &mailbox_name, selected, |group| {
+                group.has_message_events()
+            }) {
+                if let Ok(status) = self.status(mailbox_name, NOTIFY_STATUS).await {
+                    status.serialize(&mut buf, is_rev2);
+                }
+            }
+        }
+
+        if !buf.is_empty() {
+            self.write_bytes(buf).await;
+        }
+    }
+
+    fn is_notify_target(
+        &self,
+        groups: &[EventGroup],
+        mailbox_name: &str,
+        selected: Option<&Arc<SelectedMailbox>>,
+        wants_event: impl Fn(&EventGroup) -> bool,
+    ) -> bool {
+        let mailboxes = self.mailboxes.lock();
+        let (account, mailbox_id) = if let Some(result) = mailboxes.iter().find_map(|account| {
+            account
+                .mailbox_names
+                .get(mailbox_name)
+                .map(|mailbox_id| (Some(account), Some(*mailbox_id)))
+        }) {
+            result
+        } else {
+            (None, None)
+        };
+
+        // The selected mailbox is covered by the SELECTED filter
+        if let (Some(selected), Some(account), Some(mailbox_id)) = (selected, account, mailbox_id) {
+            if selected.id.account_id == account.account_id && selected.id.mailbox_id == mailbox_id
+            {
+                return false;
+            }
+        }
+
+        // Use the first matching filter, as event groups are evaluated in order
+        groups
+            .iter()
+            .find(|group| match &group.filter {
+                MailboxFilter::Selected | MailboxFilter::SelectedDelayed => false,
+                MailboxFilter::Personal => account.map_or(true, |account| account.prefix.is_none()),
+                MailboxFilter::Inboxes => mailbox_name.eq_ignore_ascii_case("INBOX"),
+                MailboxFilter::Subscribed => account
+                    .zip(mailbox_id)
+                    .and_then(|(account, mailbox_id)| account.mailbox_state.get(&mailbox_id))
+                    .map_or(false, |mailbox| mailbox.is_subscribed),
+                MailboxFilter::Subtree(names) => names.iter().any(|name| {
+                    mailbox_name == name
+                        || mailbox_name
+                            .strip_prefix(name.as_str())
+                            .map_or(false, |suffix| suffix.starts_with('/'))
+                }),
+                MailboxFilter::Mailboxes(names) => names.iter().any(|name| name == mailbox_name),
+            })
+            .map_or(false, wants_event)
+    }
+}
+
+const NOTIFY_STATUS: &[Status] = &[
+    Status::Messages,
+    Status::Unseen,
+    Status::UidNext,
+    Status::UidValidity,
+];
@@ -0,0 +1,267 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use common::listener::SessionStream;
+use directory::{
+    backend::internal::{manage::ManageDirectory, PrincipalField, PrincipalUpdate, PrincipalValue},
+    DirectoryInner, QueryBy,
+};
+use imap_proto::{
+    protocol::quota::{QuotaResource, QuotaResponse, QuotaRootResponse},
+    receiver::Request,
+    Command, ResponseCode, StatusResponse,
+};
+
+use crate::core::{Session, SessionData};
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_get_quota(&mut self, request: Request<Command>) -> crate::OpResult {
+        match request.parse_get_quota(self.version) {
+            Ok(arguments) => {
+                let data = self.state.session_data();
+                let is_rev2 = self.version.is_rev2();
+
+                tokio::spawn(async move {
+                    let response = match data.get_quota_root_account(&arguments.name) {
+                        Some(account_id) => {
+                            match data.get_quota(account_id, arguments.name).await {
+                                Ok(Some(quota)) => StatusResponse::completed(Command::GetQuota)
+                                    .with_tag(arguments.tag)
+                                    .serialize(quota.into_bytes(is_rev2)),
+                                Ok(None) => StatusResponse::no("Quota root has no limits.")
+                                    .with_tag(arguments.tag)
+                                    .with_code(ResponseCode::NonExistent)
+                                    .into_bytes(),
+                                Err(response) => response.with_tag(arguments.tag).into_bytes(),
+                            }
+                        }
+                        None => StatusResponse::no("Quota root does not exist.")
+                            .with_tag(arguments.tag)
+                            .with_code(ResponseCode::NonExistent)
+                            .into_bytes(),
+                    };
+                    data.write_bytes(response).await;
+                });
+                Ok(())
+            }
+            Err(response) => self.write_bytes(response.into_bytes()).await,
+        }
+    }
+
+    pub async fn handle_get_quota_root(&mut self, request: Request<Command>) -> crate::OpResult {
+        match request.parse_get_quota(self.version) {
+            Ok(arguments) => {
+                let data = self.state.session_data();
+                let is_rev2 = self.version.is_rev2();
+
+                tokio::spawn(async move {
+                    let response = match data.get_mailbox_by_name(&arguments.name) {
+                        Some(mailbox) => {
+                            let root = data.get_quota_root_name(mailbox.account_id);
+                            match data.get_quota(mailbox.account_id, root).await {
+                                Ok(quota) => StatusResponse::completed(Command::GetQuotaRoot)
+                                    .with_tag(arguments.tag)
+                                    .serialize(
+                                        QuotaRootResponse {
+                                            mailbox_name: arguments.name,
+                                            roots: quota.into_iter().collect(),
+                                        }
+                                        .into_bytes(is_rev2),
+                                    ),
+                                Err(response) => response.with_tag(arguments.tag).into_bytes(),
+                            }
+                        }
+                        None => StatusResponse::no("Mailbox does not exist.")
+                            .with_tag(arguments.tag)
+                            .with_code(ResponseCode::NonExistent)
+                            .into_bytes(),
+                    };
+                    data.write_bytes(response).await;
+                });
+                Ok(())
+            }
+            Err(response) => self.write_bytes(response.into_bytes()).await,
+        }
+    }
+
+    pub async fn handle_set_quota(&mut self, request: Request<Command>) -> crate::OpResult {
+        match request.parse_set_quota(self.version) {
+            Ok(arguments) => {
+                let data = self.state.session_data();
+                let is_rev2 = self.version.is_rev2();
+
+                tokio::spawn(async move {
+                    let response = match data.set_quota(&arguments.root, arguments.limits).await {
+                        Ok(account_id) => match data.get_quota(account_id, arguments.root).await {
+                            Ok(quota) => {
+                                let response = StatusResponse::completed(Command::SetQuota)
+                                    .with_tag(arguments.tag);
+                                if let Some(quota) = quota {
+                                    response.serialize(quota.into_bytes(is_rev2))
+                                } else {
+                                    response.into_bytes()
+                                }
+                            }
+                            Err(response) => response.with_tag(arguments.tag).into_bytes(),
+                        },
+                        Err(response) => response.with_tag(arguments.tag).into_bytes(),
+                    };
+                    data.write_bytes(response).await;
+                });
+                Ok(())
+            }
+            Err(response) => self.write_bytes(response.into_bytes()).await,
+        }
+    }
+}
+
+impl<T: SessionStream> SessionData<T> {
+    pub fn get_quota_root_name(&self, account_id: u32) -> String {
+        self.mailboxes
+            .lock()
+            .iter()
+            .find(|account| account.account_id == account_id)
+            .and_then(|account| account.prefix.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn get_quota_root_account(&self, root: &str) -> Option<u32> {
+        self.mailboxes
+            .lock()
+            .iter()
+            .find(|account| account.prefix.as_deref().unwrap_or_default() == root)
+            .map(|account| account.account_id)
+    }
+
+    pub async fn get_quota(
+        &self,
+        account_id: u32,
+        root: String,
+    ) -> crate::op::Result<Option<QuotaResponse>> {
+        let access_token = self.get_access_token().await?;
+        let mut resources = Vec::with_capacity(2);
+
+        // Storage is reported in units of 1024 octets
+        let quota = self
+            .jmap
+            .get_quota(&access_token, account_id)
+            .await
+            .map_err(|_| StatusResponse::database_failure())?;
+        if quota > 0 {
+            let used_quota = self
+                .jmap
+                .get_used_quota(account_id)
+                .await
+                .map_err(|_| StatusResponse::database_failure())?;
+            resources.push((
+                QuotaResource::Storage,
+                (used_quota.max(0) as u64 + 1023) / 1024,
+                quota as u64 / 1024,
+            ));
+        }
+
+        let max_messages = self.jmap.core.jmap.mail_max_messages;
+        if max_messages > 0 {
+            let used_messages = self
+                .jmap
+                .get_used_messages(account_id)
+                .await
+                .map_err(|_| StatusResponse::database_failure())?;
+            resources.push((QuotaResource::Message, used_messages, max_messages));
+        }
+
+        Ok(if !resources.is_empty() {
+            Some(QuotaResponse { root, resources })
+        } else {
+            None
+        })
+    }
+
+    pub async fn set_quota(
+        &self,
+        root: &str,
+        limits: Vec<(QuotaResource, u64)>,
+    ) -> crate::op::Result<u32> {
+        if !self.get_access_token().await?.is_super_user() {
+            return Err(StatusResponse::no("Only administrators can set quotas.")
+                .with_code(ResponseCode::NoPerm));
+        }
+        if !matches!(
+            self.jmap.core.storage.directory.store,
+            DirectoryInner::Internal(_)
+        ) {
+            return Err(
+                StatusResponse::no("Quotas are managed by an external directory.")
+                    .with_code(ResponseCode::Cannot),
+            );
+        }
+        let account_id = self.get_quota_root_account(root).ok_or_else(|| {
+            StatusResponse::no("Quota root does not exist.").with_code(ResponseCode::NonExistent)
+        })?;
+
+        // Omitted resources have their limits removed
+        let mut storage_limit = 0;
+        for (resource, limit) in limits {
+            match resource {
+                QuotaResource::Storage => {
+                    storage_limit = limit.saturating_mul(1024);
+                }
+                QuotaResource::Message => {
+                    return Err(StatusResponse::no(
+                        "The MESSAGE limit is set server-wide and cannot be changed per account.",
+                    )
+                    .with_code(ResponseCode::Cannot));
+                }
+            }
+        }
+
+        self.jmap
+            .core
+            .storage
+            .data
+            .update_account(
+                QueryBy::Id(account_id),
+                vec![PrincipalUpdate::set(
+                    PrincipalField::Quota,
+                    PrincipalValue::Integer(storage_limit),
+                )],
+            )
+            .await
+            .map_err(|err| {
+                tracing::warn!(
+                    parent: &self.span,
+                    event = "error",
+                    context = "set_quota",
+                    account_id = account_id,
+                    error = ?err,
+                    "Failed to update account quota."
+                );
+                StatusResponse::no("Failed to update quota.").with_code(ResponseCode::ContactAdmin)
+            })?;
+
+        // Make sure the new limits are used on the next access token lookup
+        self.jmap.invalidate_access_tokens(account_id);
+
+        Ok(account_id)
+    }
+}
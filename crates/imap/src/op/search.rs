@@ -23,6 +23,7 @@
 
 use std::sync::Arc;
 
+use ahash::AHashMap;
 use common::listener::SessionStream;
 use imap_proto::{
     protocol::{
@@ -32,6 +33,7 @@ use imap_proto::{
     receiver::Request,
     Command, StatusResponse,
 };
+use jmap::mailbox::SavedDate;
 use jmap_proto::types::{collection::Collection, id::Id, keyword::Keyword, property::Property};
 use mail_parser::HeaderName;
 use nlp::language::Language;
@@ -124,10 +126,29 @@ impl<T: SessionStream> SessionData<T> {
         is_uid: bool,
     ) -> Result<search::Response, StatusResponse> {
         // Run query
+        let fuzzy_terms = fuzzy_terms(&arguments.filter);
         let (result_set, include_highest_modseq) = self
             .query(arguments.filter, &mailbox, &prev_saved_search)
             .await?;
 
+        // Score fuzzy matches
+        let sort_by_relevancy = arguments
+            .sort
+            .as_ref()
+            .and_then(|sort| sort.first())
+            .filter(|item| item.sort == search::Sort::Relevancy)
+            .map(|item| item.ascending);
+        let scores = if sort_by_relevancy.is_some()
+            || arguments.result_options.contains(&ResultOption::Relevancy)
+        {
+            Some(
+                self.relevancy(mailbox.id.account_id, fuzzy_terms, &result_set.results)
+                    .await?,
+            )
+        } else {
+            None
+        };
+
         // Obtain modseq
         let highest_modseq = if include_highest_modseq {
             self.synchronize_messages(&mailbox)
@@ -150,7 +171,20 @@ impl<T: SessionStream> SessionData<T> {
         };
         let mut imap_ids = Vec::with_capacity(results_len);
         let is_sort = if let Some(sort) = arguments.sort {
-            mailbox.map_search_results(
+            let ids = if let (Some(ascending), Some(scores)) = (sort_by_relevancy, &scores) {
+                // RELEVANCY sorts by decreasing score unless reversed
+                let mut ids = result_set.results.iter().collect::<Vec<_>>();
+                ids.sort_unstable_by(|a, b| {
+                    let (score_a, score_b) = (scores.get(a), scores.get(b));
+                    let order = if ascending {
+                        score_b.cmp(&score_a)
+                    } else {
+                        score_a.cmp(&score_b)
+                    };
+                    order.then(a.cmp(b))
+                });
+                ids
+            } else {
                 self.jmap
                     .core
                     .storage
@@ -158,28 +192,32 @@ impl<T: SessionStream> SessionData<T> {
                     .sort(
                         result_set,
                         sort.into_iter()
-                            .map(|item| match item.sort {
+                            .filter_map(|item| match item.sort {
                                 search::Sort::Arrival => {
                                     query::Comparator::field(Property::ReceivedAt, item.ascending)
+                                        .into()
                                 }
                                 search::Sort::Cc => {
-                                    query::Comparator::field(Property::Cc, item.ascending)
+                                    query::Comparator::field(Property::Cc, item.ascending).into()
                                 }
                                 search::Sort::Date => {
                                     query::Comparator::field(Property::SentAt, item.ascending)
+                                        .into()
                                 }
                                 search::Sort::From | search::Sort::DisplayFrom => {
-                                    query::Comparator::field(Property::From, item.ascending)
+                                    query::Comparator::field(Property::From, item.ascending).into()
                                 }
                                 search::Sort::Size => {
-                                    query::Comparator::field(Property::Size, item.ascending)
+                                    query::Comparator::field(Property::Size, item.ascending).into()
                                 }
                                 search::Sort::Subject => {
                                     query::Comparator::field(Property::Subject, item.ascending)
+                                        .into()
                                 }
                                 search::Sort::To | search::Sort::DisplayTo => {
-                                    query::Comparator::field(Property::To, item.ascending)
+                                    query::Comparator::field(Property::To, item.ascending).into()
                                 }
+                                search::Sort::Relevancy => None,
                             })
                             .collect::<Vec<_>>(),
                         Pagination::new(results_len, 0, None, 0),
@@ -188,7 +226,11 @@ impl<T: SessionStream> SessionData<T> {
                     .map_err(|_| StatusResponse::database_failure())?
                     .ids
                     .into_iter()
-                    .map(|id| id as u32),
+                    .map(|id| id as u32)
+                    .collect::<Vec<_>>()
+            };
+            mailbox.map_search_results(
+                ids.into_iter(),
                 is_uid,
                 arguments.result_options.contains(&ResultOption::Min),
                 arguments.result_options.contains(&ResultOption::Max),
@@ -242,6 +284,11 @@ impl<T: SessionStream> SessionData<T> {
                 vec![]
             },
             is_sort,
+            relevancy: if arguments.result_options.contains(&ResultOption::Relevancy) {
+                scores.map(|scores| mailbox.map_relevancy(scores, &imap_ids, is_uid))
+            } else {
+                None
+            },
             is_esearch: arguments.is_esearch,
             highest_modseq,
         })
@@ -393,6 +440,56 @@ impl<T: SessionStream> SessionData<T> {
                                     Language::None,
                                 ));
                             }
+                            search::Filter::Fuzzy(filter) => {
+                                let (fields, text) = match *filter {
+                                    search::Filter::Bcc(text) => {
+                                        (vec![Field::Header(HeaderName::Bcc)], text)
+                                    }
+                                    search::Filter::Body(text) => (vec![Field::Body], text),
+                                    search::Filter::Cc(text) => {
+                                        (vec![Field::Header(HeaderName::Cc)], text)
+                                    }
+                                    search::Filter::From(text) => {
+                                        (vec![Field::Header(HeaderName::From)], text)
+                                    }
+                                    search::Filter::Subject(text) => {
+                                        (vec![Field::Header(HeaderName::Subject)], text)
+                                    }
+                                    search::Filter::To(text) => {
+                                        (vec![Field::Header(HeaderName::To)], text)
+                                    }
+                                    search::Filter::Text(text) => (
+                                        vec![
+                                            Field::Header(HeaderName::From),
+                                            Field::Header(HeaderName::To),
+                                            Field::Header(HeaderName::Cc),
+                                            Field::Header(HeaderName::Bcc),
+                                            Field::Header(HeaderName::Subject),
+                                            Field::Body,
+                                            Field::Attachment,
+                                        ],
+                                        text,
+                                    ),
+                                    _ => continue,
+                                };
+
+                                // Fuzzy matching never requests exact phrases, so
+                                // terms are stemmed using the detected language
+                                let text = text.trim_matches(|c| c == '"' || c == '\'');
+                                if fields.len() > 1 {
+                                    fts_filters.push(FtsFilter::Or);
+                                }
+                                for field in &fields {
+                                    fts_filters.push(FtsFilter::has_text_detect(
+                                        field.clone(),
+                                        text,
+                                        self.jmap.core.jmap.default_language,
+                                    ));
+                                }
+                                if fields.len() > 1 {
+                                    fts_filters.push(FtsFilter::End);
+                                }
+                            }
                             search::Filter::And => {
                                 fts_filters.push(FtsFilter::And);
                             }
@@ -595,6 +692,31 @@ impl<T: SessionStream> SessionData<T> {
                             now().saturating_sub(secs as u64),
                         ));
                     }
+                    search::Filter::SavedBefore(date) => {
+                        filters.push(query::Filter::is_in_set(
+                            self.saved_in(mailbox, &message_ids, |saved_at| saved_at < date as u64)
+                                .await?,
+                        ));
+                    }
+                    search::Filter::SavedOn(date) => {
+                        filters.push(query::Filter::is_in_set(
+                            self.saved_in(mailbox, &message_ids, |saved_at| {
+                                saved_at >= date as u64 && saved_at < (date + 86400) as u64
+                            })
+                            .await?,
+                        ));
+                    }
+                    search::Filter::SavedSince(date) => {
+                        filters.push(query::Filter::is_in_set(
+                            self.saved_in(mailbox, &message_ids, |saved_at| {
+                                saved_at >= date as u64
+                            })
+                            .await?,
+                        ));
+                    }
+                    search::Filter::SaveDateSupported => {
+                        filters.push(query::Filter::is_in_set(message_ids.clone()));
+                    }
                     search::Filter::Younger(secs) => {
                         filters.push(query::Filter::ge(
                             Property::ReceivedAt,
@@ -656,6 +778,85 @@ impl<T: SessionStream> SessionData<T> {
             .map(|res| (res, include_highest_modseq))
             .map_err(|err| err.into())
     }
+
+    async fn saved_in(
+        &self,
+        mailbox: &SelectedMailbox,
+        message_ids: &RoaringBitmap,
+        matches: impl Fn(u64) -> bool,
+    ) -> Result<RoaringBitmap, StatusResponse> {
+        // Save dates are kept per mailbox, so they are matched in memory
+        let mut set = RoaringBitmap::new();
+        for (document_id, saved_dates) in self
+            .jmap
+            .get_properties::<Vec<SavedDate>, _, _>(
+                mailbox.id.account_id,
+                Collection::Email,
+                message_ids,
+                Property::SavedAt,
+            )
+            .await?
+        {
+            if SavedDate::find(&saved_dates, mailbox.id.mailbox_id).map_or(false, &matches) {
+                set.insert(document_id);
+            }
+        }
+
+        Ok(set)
+    }
+
+    async fn relevancy(
+        &self,
+        account_id: u32,
+        terms: Vec<String>,
+        results: &RoaringBitmap,
+    ) -> Result<AHashMap<u32, u8>, StatusResponse> {
+        // Score each message by the share of fuzzy terms it matches
+        let mut matches: AHashMap<u32, u32> = AHashMap::with_capacity(results.len() as usize);
+        for term in &terms {
+            let mut fts_filters = Vec::with_capacity(9);
+            fts_filters.push(FtsFilter::Or);
+            for field in [
+                Field::Header(HeaderName::From),
+                Field::Header(HeaderName::To),
+                Field::Header(HeaderName::Cc),
+                Field::Header(HeaderName::Bcc),
+                Field::Header(HeaderName::Subject),
+                Field::Body,
+                Field::Attachment,
+            ] {
+                fts_filters.push(FtsFilter::has_text(
+                    field,
+                    term.as_str(),
+                    self.jmap.core.jmap.default_language,
+                ));
+            }
+            fts_filters.push(FtsFilter::End);
+
+            for document_id in self
+                .jmap
+                .fts_filter(account_id, Collection::Email, fts_filters)
+                .await?
+                & results
+            {
+                *matches.entry(document_id).or_default() += 1;
+            }
+        }
+
+        Ok(results
+            .iter()
+            .map(|document_id| {
+                let score = if !terms.is_empty() {
+                    (matches.get(&document_id).copied().unwrap_or_default() * 100
+                        / terms.len() as u32)
+                        .max(1)
+                } else {
+                    100
+                };
+                (document_id, score as u8)
+            })
+            .collect())
+    }
 }
 
 impl SelectedMailbox {
@@ -730,6 +931,29 @@ impl SelectedMailbox {
     }
 }
 
+impl SelectedMailbox {
+    pub fn map_relevancy(
+        &self,
+        scores: AHashMap<u32, u8>,
+        imap_ids: &[u32],
+        is_uid: bool,
+    ) -> Vec<u8> {
+        let state = self.state.lock();
+        let scores = scores
+            .into_iter()
+            .filter_map(|(document_id, score)| {
+                state
+                    .map_result_id(document_id, is_uid)
+                    .map(|(id, _)| (id, score))
+            })
+            .collect::<AHashMap<_, _>>();
+        imap_ids
+            .iter()
+            .map(|id| scores.get(id).copied().unwrap_or(1))
+            .collect()
+    }
+}
+
 impl MailboxState {
     pub fn map_result_id(&self, document_id: u32, is_uid: bool) -> Option<(u32, ImapId)> {
         if let Some(imap_id) = self.id_to_imap.get(&document_id) {
@@ -747,6 +971,32 @@ impl MailboxState {
     }
 }
 
+fn fuzzy_terms(filters: &[Filter]) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for filter in filters {
+        if let Filter::Fuzzy(filter) = filter {
+            if let Filter::Bcc(text)
+            | Filter::Body(text)
+            | Filter::Cc(text)
+            | Filter::From(text)
+            | Filter::Subject(text)
+            | Filter::Text(text)
+            | Filter::To(text) = filter.as_ref()
+            {
+                for term in text.split(|c: char| !c.is_alphanumeric()) {
+                    if !term.is_empty() {
+                        let term = term.to_lowercase();
+                        if !terms.contains(&term) {
+                            terms.push(term);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    terms
+}
+
 impl SavedSearch {
     pub async fn unwrap(&self) -> Option<Arc<Vec<ImapId>>> {
         match self {
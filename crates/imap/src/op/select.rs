@@ -57,6 +57,32 @@ impl<T: SessionStream> Session<T> {
                 }
 
                 if let Some(mailbox) = data.get_mailbox_by_name(&arguments.mailbox_name) {
+                    // Shared mailboxes are opened read-only unless the user is
+                    // allowed to modify or remove messages
+                    let is_select = match data
+                        .check_mailbox_select(mailbox.account_id, mailbox.mailbox_id)
+                        .await
+                    {
+                        Ok(Some(is_writable)) => is_select && is_writable,
+                        Ok(None) => {
+                            return self
+                                .write_bytes(
+                                    StatusResponse::no(
+                                        "You do not have the required permissions to read messages in this mailbox.",
+                                    )
+                                    .with_tag(arguments.tag)
+                                    .with_code(ResponseCode::NoPerm)
+                                    .into_bytes(),
+                                )
+                                .await;
+                        }
+                        Err(response) => {
+                            return self
+                                .write_bytes(response.with_tag(arguments.tag).into_bytes())
+                                .await;
+                        }
+                    };
+
                     // Try obtaining the mailbox from the cache
                     let state = {
                         let modseq = match data.get_modseq(mailbox.account_id).await {
@@ -129,17 +155,22 @@ impl<T: SessionStream> Session<T> {
                                 .await;
                         }
                         if qresync.uid_validity == uid_validity {
+                            // Messages whose sequence numbers still match the
+                            // client's view do not need to be reported as vanished
+                            let verified_uid = qresync
+                                .seq_match
+                                .as_ref()
+                                .map(|(seqnums, uids)| mailbox.verify_seq_match(seqnums, uids))
+                                .unwrap_or(0);
+
                             // Send flags for changed messages
                             data.fetch(
                                 fetch::Arguments {
                                     tag: String::new(),
-                                    sequence_set: qresync
-                                        .known_uids
-                                        .or_else(|| qresync.seq_match.map(|(_, s)| s))
-                                        .unwrap_or(Sequence::Range {
-                                            start: 1.into(),
-                                            end: None,
-                                        }),
+                                    sequence_set: qresync.known_uids.unwrap_or(Sequence::Range {
+                                        start: 1.into(),
+                                        end: None,
+                                    }),
                                     attributes: vec![fetch::Attribute::Flags],
                                     changed_since: qresync.modseq.into(),
                                     include_vanished: true,
@@ -149,6 +180,7 @@ impl<T: SessionStream> Session<T> {
                                 true,
                                 is_rev2,
                                 false,
+                                verified_uid,
                             )
                             .await;
                         }
@@ -165,8 +197,7 @@ impl<T: SessionStream> Session<T> {
                         closed_previous,
                         is_rev2,
                         highest_modseq,
-                        mailbox_id: Id::from_parts(mailbox.id.account_id, mailbox.id.mailbox_id)
-                            .to_string(),
+                        mailbox_id: Id::from(mailbox.id.mailbox_id).to_string(),
                     };
 
                     // Update state
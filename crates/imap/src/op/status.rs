@@ -194,10 +194,7 @@ impl<T: SessionStream> SessionData<T> {
                         Status::MailboxId => {
                             items_response.push((
                                 *item,
-                                StatusItemType::String(
-                                    Id::from_parts(mailbox.account_id, mailbox.mailbox_id)
-                                        .to_string(),
-                                ),
+                                StatusItemType::String(Id::from(mailbox.mailbox_id).to_string()),
                             ));
                         }
                         Status::Recent => {
@@ -61,6 +61,8 @@ pub struct RequestError {
     pub detail: Cow<'static, str>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limit: Option<RequestLimitError>,
+    #[serde(skip)]
+    pub retry_after: Option<u64>,
 }
 
 impl RequestError {
@@ -75,6 +77,7 @@ impl RequestError {
             title: Some(title.into()),
             detail: detail.into(),
             limit: None,
+            retry_after: None,
         }
     }
 
@@ -135,6 +138,11 @@ impl RequestError {
         )
     }
 
+    pub fn with_retry_after(mut self, retry_after: u64) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
     pub fn too_many_auth_attempts() -> Self {
         RequestError::blank(
             429,
@@ -172,6 +180,7 @@ impl RequestError {
             }
             .into(),
             limit: Some(limit_type),
+            retry_after: None,
         }
     }
 
@@ -191,6 +200,7 @@ impl RequestError {
         RequestError {
             p_type: RequestErrorType::UnknownCapability,
             limit: None,
+            retry_after: None,
             title: None,
             status: 400,
             detail: format!(
@@ -209,6 +219,7 @@ impl RequestError {
         RequestError {
             p_type: RequestErrorType::NotJSON,
             limit: None,
+            retry_after: None,
             title: None,
             status: 400,
             detail: format!("Failed to parse JSON: {detail}").into(),
@@ -219,6 +230,7 @@ impl RequestError {
         RequestError {
             p_type: RequestErrorType::NotRequest,
             limit: None,
+            retry_after: None,
             title: None,
             status: 400,
             detail: detail.into(),
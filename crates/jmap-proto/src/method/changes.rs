@@ -69,6 +69,9 @@ pub enum RequestArguments {
     Identity,
     EmailSubmission,
     Quota,
+    SavedSearch,
+    CalendarEvent,
+    ContactCard,
 }
 
 impl JsonObjectParser for ChangesRequest {
@@ -84,6 +87,9 @@ impl JsonObjectParser for ChangesRequest {
                 MethodObject::Identity => RequestArguments::Identity,
                 MethodObject::EmailSubmission => RequestArguments::EmailSubmission,
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::SavedSearch => RequestArguments::SavedSearch,
+                MethodObject::CalendarEvent => RequestArguments::CalendarEvent,
+                MethodObject::ContactCard => RequestArguments::ContactCard,
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
                         "{}/changes",
@@ -91,6 +91,10 @@ pub enum Filter {
     After(UTCDate),
     InMailbox(Id),
     InMailboxOtherThan(Vec<Id>),
+    InSavedSearch(Id),
+    InCalendar(Id),
+    InAddressBook(Id),
+    Uid(String),
     MinSize(u32),
     MaxSize(u32),
     AllInThreadHaveKeyword(Keyword),
@@ -165,6 +169,8 @@ pub enum RequestArguments {
     SieveScript,
     Principal,
     Quota,
+    CalendarEvent,
+    ContactCard,
 }
 
 impl JsonObjectParser for QueryRequest<RequestArguments> {
@@ -180,6 +186,8 @@ impl JsonObjectParser for QueryRequest<RequestArguments> {
                 MethodObject::SieveScript => RequestArguments::SieveScript,
                 MethodObject::Principal => RequestArguments::Principal,
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::CalendarEvent => RequestArguments::CalendarEvent,
+                MethodObject::ContactCard => RequestArguments::ContactCard,
                 _ => {
                     return Err(Error::Method(MethodError::UnknownMethod(format!(
                         "{}/query",
@@ -337,6 +345,18 @@ pub fn parse_filter(parser: &mut Parser) -> crate::parser::Result<Vec<Filter>> {
                         (0x6854_7265_6874_4f78_6f62_6c69_614d_6e69, 0x6e61) => {
                             Filter::InMailboxOtherThan(<Vec<Id>>::parse(parser)?)
                         }
+                        (0x0068_6372_6165_5364_6576_6153_6e69, _) => Filter::InSavedSearch(
+                            parser.next_token::<Id>()?.unwrap_string("inSavedSearch")?,
+                        ),
+                        (0x7261_646e_656c_6143_6e69, _) => Filter::InCalendar(
+                            parser.next_token::<Id>()?.unwrap_string("inCalendar")?,
+                        ),
+                        (0x006b_6f6f_4273_7365_7264_6441_6e69, _) => Filter::InAddressBook(
+                            parser.next_token::<Id>()?.unwrap_string("inAddressBook")?,
+                        ),
+                        (0x0064_6975, _) => {
+                            Filter::Uid(parser.next_token::<String>()?.unwrap_string("uid")?)
+                        }
                         (0x0065_7a69_536e_696d, _) => Filter::MinSize(
                             parser
                                 .next_token::<String>()?
@@ -621,6 +641,10 @@ impl Display for Filter {
             Filter::After(_) => "after",
             Filter::InMailbox(_) => "inMailbox",
             Filter::InMailboxOtherThan(_) => "inMailboxOtherThan",
+            Filter::InSavedSearch(_) => "inSavedSearch",
+            Filter::InCalendar(_) => "inCalendar",
+            Filter::InAddressBook(_) => "inAddressBook",
+            Filter::Uid(_) => "uid",
             Filter::MinSize(_) => "minSize",
             Filter::MaxSize(_) => "maxSize",
             Filter::AllInThreadHaveKeyword(_) => "allInThreadHaveKeyword",
@@ -123,6 +123,12 @@ pub struct CoreCapabilities {
     pub max_objects_in_set: usize,
     #[serde(rename(serialize = "collationAlgorithms"))]
     pub collation_algorithms: Vec<String>,
+    #[serde(rename(serialize = "maxRequestCost"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_request_cost: Option<u64>,
+    #[serde(rename(serialize = "requestCostPeriod"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_cost_period: Option<u64>,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -46,6 +46,9 @@ pub enum MethodObject {
     SieveScript,
     Principal,
     Quota,
+    SavedSearch,
+    CalendarEvent,
+    ContactCard,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -113,6 +116,9 @@ impl JsonObjectParser for MethodName {
                 0x0074_7069_7263_5365_7665_6953 => MethodObject::SieveScript,
                 0x006c_6170_6963_6e69_7250 => MethodObject::Principal,
                 0x0061_746f_7551 => MethodObject::Quota,
+                0x0068_6372_6165_5364_6576_6153 => MethodObject::SavedSearch,
+                0x0074_6e65_7645_7261_646e_656c_6143 => MethodObject::CalendarEvent,
+                0x0064_7261_4374_6361_746e_6f43 => MethodObject::ContactCard,
                 0x6572_6f43 => MethodObject::Core,
                 _ => return Err(parser.error_value()),
             },
@@ -207,6 +213,20 @@ impl MethodName {
             (MethodFunction::Query, MethodObject::Quota) => "Quota/query",
             (MethodFunction::QueryChanges, MethodObject::Quota) => "Quota/queryChanges",
 
+            (MethodFunction::Get, MethodObject::SavedSearch) => "SavedSearch/get",
+            (MethodFunction::Changes, MethodObject::SavedSearch) => "SavedSearch/changes",
+            (MethodFunction::Set, MethodObject::SavedSearch) => "SavedSearch/set",
+
+            (MethodFunction::Get, MethodObject::CalendarEvent) => "CalendarEvent/get",
+            (MethodFunction::Changes, MethodObject::CalendarEvent) => "CalendarEvent/changes",
+            (MethodFunction::Set, MethodObject::CalendarEvent) => "CalendarEvent/set",
+            (MethodFunction::Query, MethodObject::CalendarEvent) => "CalendarEvent/query",
+
+            (MethodFunction::Get, MethodObject::ContactCard) => "ContactCard/get",
+            (MethodFunction::Changes, MethodObject::ContactCard) => "ContactCard/changes",
+            (MethodFunction::Set, MethodObject::ContactCard) => "ContactCard/set",
+            (MethodFunction::Query, MethodObject::ContactCard) => "ContactCard/query",
+
             (MethodFunction::Get, MethodObject::Blob) => "Blob/get",
             (MethodFunction::Copy, MethodObject::Blob) => "Blob/copy",
             (MethodFunction::Lookup, MethodObject::Blob) => "Blob/lookup",
@@ -234,6 +254,9 @@ impl Display for MethodObject {
             MethodObject::Thread => "Thread",
             MethodObject::Email => "Email",
             MethodObject::Quota => "Quota",
+            MethodObject::SavedSearch => "SavedSearch",
+            MethodObject::CalendarEvent => "CalendarEvent",
+            MethodObject::ContactCard => "ContactCard",
         })
     }
 }
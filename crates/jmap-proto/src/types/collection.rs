@@ -38,7 +38,12 @@ pub enum Collection {
     SieveScript = 5,
     PushSubscription = 6,
     Principal = 7,
-    None = 8,
+    SavedSearch = 8,
+    Calendar = 9,
+    CalendarEvent = 10,
+    AddressBook = 11,
+    ContactCard = 12,
+    None = 13,
 }
 
 impl From<u8> for Collection {
@@ -52,6 +57,11 @@ impl From<u8> for Collection {
             5 => Collection::SieveScript,
             6 => Collection::PushSubscription,
             7 => Collection::Principal,
+            8 => Collection::SavedSearch,
+            9 => Collection::Calendar,
+            10 => Collection::CalendarEvent,
+            11 => Collection::AddressBook,
+            12 => Collection::ContactCard,
             _ => Collection::None,
         }
     }
@@ -68,6 +78,11 @@ impl From<u64> for Collection {
             5 => Collection::SieveScript,
             6 => Collection::PushSubscription,
             7 => Collection::Principal,
+            8 => Collection::SavedSearch,
+            9 => Collection::Calendar,
+            10 => Collection::CalendarEvent,
+            11 => Collection::AddressBook,
+            12 => Collection::ContactCard,
             _ => Collection::None,
         }
     }
@@ -97,6 +112,9 @@ impl TryFrom<Collection> for DataType {
             Collection::EmailSubmission => Ok(DataType::EmailSubmission),
             Collection::SieveScript => Ok(DataType::SieveScript),
             Collection::PushSubscription => Ok(DataType::PushSubscription),
+            Collection::SavedSearch => Ok(DataType::SavedSearch),
+            Collection::CalendarEvent => Ok(DataType::CalendarEvent),
+            Collection::ContactCard => Ok(DataType::ContactCard),
             _ => Err(()),
         }
     }
@@ -113,6 +131,11 @@ impl Display for Collection {
             Collection::EmailSubmission => write!(f, "emailSubmission"),
             Collection::SieveScript => write!(f, "sieveScript"),
             Collection::Principal => write!(f, "principal"),
+            Collection::SavedSearch => write!(f, "savedSearch"),
+            Collection::Calendar => write!(f, "calendar"),
+            Collection::CalendarEvent => write!(f, "calendarEvent"),
+            Collection::AddressBook => write!(f, "addressBook"),
+            Collection::ContactCard => write!(f, "contactCard"),
             Collection::None => write!(f, ""),
         }
     }
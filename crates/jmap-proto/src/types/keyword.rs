@@ -129,13 +129,14 @@ impl JsonObjectParser for Keyword {
 
 impl From<String> for Keyword {
     fn from(value: String) -> Self {
-        if value.starts_with('$') {
+        // Accept both JMAP ($seen) and IMAP/Sieve (\Seen) system flag names
+        if let Some(name) = value.strip_prefix('$').or_else(|| value.strip_prefix('\\')) {
             let mut hash = 0;
             let mut shift = 0;
 
-            for &ch in value.as_bytes() {
+            for &ch in name.as_bytes() {
                 if shift < 128 {
-                    hash |= (ch as u128) << shift;
+                    hash |= (ch.to_ascii_lowercase() as u128) << shift;
                     shift += 8;
                 } else {
                     break;
@@ -360,3 +361,24 @@ impl From<&Keyword> for TagValue<MaybeDynamicId> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Keyword;
+
+    #[test]
+    fn keyword_from_string() {
+        for (value, expected) in [
+            ("$seen", Keyword::Seen),
+            ("$Flagged", Keyword::Flagged),
+            ("\\Seen", Keyword::Seen),
+            ("\\Answered", Keyword::Answered),
+            ("$Important", Keyword::Important),
+            ("$label1", Keyword::Other("$label1".to_string())),
+            ("\\Unknown", Keyword::Other("\\Unknown".to_string())),
+            ("seen", Keyword::Other("seen".to_string())),
+        ] {
+            assert_eq!(Keyword::from(value.to_string()), expected, "{value}");
+        }
+    }
+}
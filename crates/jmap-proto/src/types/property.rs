@@ -34,12 +34,14 @@ use super::{acl::Acl, id::Id, keyword::Keyword, value::Value};
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Property {
     Acl,
+    AddressBookIds,
     Aliases,
     Attachments,
     Bcc,
     BlobId,
     BodyStructure,
     BodyValues,
+    CalendarIds,
     Capabilities,
     Cc,
     Charset,
@@ -54,6 +56,7 @@ pub enum Property {
     EmailIds,
     Envelope,
     Expires,
+    Filter,
     From,
     FromDate,
     HasAttachment,
@@ -92,6 +95,9 @@ pub enum Property {
     Sender,
     SentAt,
     Size,
+    SnoozedUntil,
+    Versions,
+    SavedAt,
     SortOrder,
     Subject,
     SubParts,
@@ -105,6 +111,7 @@ pub enum Property {
     TotalThreads,
     Type,
     Types,
+    Uid,
     UndoStatus,
     UnreadEmails,
     UnreadThreads,
@@ -136,6 +143,7 @@ pub enum Property {
     WarnLimit,
     SoftLimit,
     Scope,
+    Encryption,
     Digest(DigestProperty),
     Data(DataProperty),
     _T(String),
@@ -351,6 +359,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
         b'a' => match hash {
             0x6c63 => Property::Acl,
             0x7365_7361_696c => Property::Aliases,
+            0x0073_6449_6b6f_6f42_7373_6572_6464 => Property::AddressBookIds,
             0x7374_6e65_6d68_6361_7474 => Property::Attachments,
             _ => return None,
         },
@@ -363,6 +372,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
         },
         b'c' => match hash {
             0x0073_6569_7469_6c69_6261_7061 => Property::Capabilities,
+            0x7364_4972_6164_6e65_6c61 => Property::CalendarIds,
             0x63 => Property::Cc,
             0x7465_7372_6168 => Property::Charset,
             0x6469 => Property::Cid,
@@ -382,10 +392,12 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x6449_6c69_616d => Property::EmailId,
             0x0073_6449_6c69_616d => Property::EmailIds,
             0x0065_706f_6c65_766e => Property::Envelope,
+            0x006e_6f69_7470_7972_636e => Property::Encryption,
             0x7365_7269_7078 => Property::Expires,
             _ => return None,
         },
         b'f' => match hash {
+            0x0072_6574_6c69 => Property::Filter,
             0x006d_6f72 => Property::From,
             0x0065_7461_446d_6f72 => Property::FromDate,
             _ => return None,
@@ -453,6 +465,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0072_6564_6e65 => Property::Sender,
             0x0074_4174_6e65 => Property::SentAt,
             0x0065_7a69 => Property::Size,
+            0x006c_6974_6e55_6465_7a6f_6f6e => Property::SnoozedUntil,
             0x7265_6472_4f74_726f => Property::SortOrder,
             0x7463_656a_6275 => Property::Subject,
             0x7374_7261_5062_7573 => Property::SubParts,
@@ -472,6 +485,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             _ => return None,
         },
         b'u' => match hash {
+            0x6469 => Property::Uid,
             0x0073_7574_6174_536f_646e => Property::UndoStatus,
             0x0073_6c69_616d_4564_6165_726e => Property::UnreadEmails,
             0x7364_6165_7268_5464_6165_726e => Property::UnreadThreads,
@@ -480,6 +494,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
         },
         b'v' => match hash {
             0x0065_646f_436e_6f69_7461_6369_6669_7265 => Property::VerificationCode,
+            0x0073_6e6f_6973_7265 => Property::Versions,
             _ => return None,
         },
         _ => return None,
@@ -859,6 +874,12 @@ impl Display for Property {
             Property::TotalThreads => write!(f, "totalThreads"),
             Property::Type => write!(f, "type"),
             Property::Types => write!(f, "types"),
+            Property::Uid => write!(f, "uid"),
+            Property::CalendarIds => write!(f, "calendarIds"),
+            Property::AddressBookIds => write!(f, "addressBookIds"),
+            Property::SnoozedUntil => write!(f, "snoozedUntil"),
+            Property::Versions => write!(f, "versions"),
+            Property::SavedAt => write!(f, "savedAt"),
             Property::UndoStatus => write!(f, "undoStatus"),
             Property::UnreadEmails => write!(f, "unreadEmails"),
             Property::UnreadThreads => write!(f, "unreadThreads"),
@@ -898,6 +919,8 @@ impl Display for Property {
             Property::Used => write!(f, "used"),
             Property::HardLimit => write!(f, "hardLimit"),
             Property::Scope => write!(f, "scope"),
+            Property::Encryption => write!(f, "encryption"),
+            Property::Filter => write!(f, "filter"),
             Property::WarnLimit => write!(f, "warnLimit"),
             Property::SoftLimit => write!(f, "softLimit"),
             Property::_T(s) => write!(f, "{s}"),
@@ -1080,6 +1103,14 @@ impl From<&Property> for u8 {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::Encryption => 104,
+            Property::Filter => 105,
+            Property::Uid => 106,
+            Property::CalendarIds => 107,
+            Property::AddressBookIds => 108,
+            Property::SnoozedUntil => 109,
+            Property::Versions => 110,
+            Property::SavedAt => 111,
             Property::Digest(_) | Property::Data(_) => unreachable!("invalid property"),
         }
     }
@@ -1222,6 +1253,14 @@ impl SerializeInto for Property {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::Encryption => 104,
+            Property::Filter => 105,
+            Property::Uid => 106,
+            Property::CalendarIds => 107,
+            Property::AddressBookIds => 108,
+            Property::SnoozedUntil => 109,
+            Property::Versions => 110,
+            Property::SavedAt => 111,
             Property::Digest(_) | Property::Data(_) => {
                 unreachable!("Property::Digest and Property::Data are not serializable")
             }
@@ -1340,6 +1379,14 @@ impl DeserializeFrom for Property {
             101 => Some(Property::WarnLimit),
             102 => Some(Property::SoftLimit),
             103 => Some(Property::Scope),
+            104 => Some(Property::Encryption),
+            105 => Some(Property::Filter),
+            106 => Some(Property::Uid),
+            107 => Some(Property::CalendarIds),
+            108 => Some(Property::AddressBookIds),
+            109 => Some(Property::SnoozedUntil),
+            110 => Some(Property::Versions),
+            111 => Some(Property::SavedAt),
             _ => None,
         }
     }
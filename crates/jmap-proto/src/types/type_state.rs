@@ -58,7 +58,13 @@ pub enum DataType {
     Quota = 11,
     #[serde(rename = "SieveScript")]
     SieveScript = 12,
-    None = 13,
+    #[serde(rename = "SavedSearch")]
+    SavedSearch = 13,
+    #[serde(rename = "CalendarEvent")]
+    CalendarEvent = 14,
+    #[serde(rename = "ContactCard")]
+    ContactCard = 15,
+    None = 16,
 }
 
 impl BitmapItem for DataType {
@@ -87,6 +93,9 @@ impl From<u64> for DataType {
             10 => DataType::Mdn,
             11 => DataType::Quota,
             12 => DataType::SieveScript,
+            13 => DataType::SavedSearch,
+            14 => DataType::CalendarEvent,
+            15 => DataType::ContactCard,
             _ => {
                 debug_assert!(false, "Invalid type_state value: {}", value);
                 DataType::None
@@ -132,6 +141,9 @@ impl JsonObjectParser for DataType {
             0x004e_444d => Ok(DataType::Mdn),
             0x0061_746f_7551 => Ok(DataType::Quota),
             0x0074_7069_7263_5365_7665_6953 => Ok(DataType::SieveScript),
+            0x0068_6372_6165_5364_6576_6153 => Ok(DataType::SavedSearch),
+            0x0074_6e65_7645_7261_646e_656c_6143 => Ok(DataType::CalendarEvent),
+            0x0064_7261_4374_6361_746e_6f43 => Ok(DataType::ContactCard),
             _ => Err(parser.error_value()),
         }
     }
@@ -167,6 +179,9 @@ impl TryFrom<&str> for DataType {
             0x004e_444d => Ok(DataType::Mdn),
             0x0061_746f_7551 => Ok(DataType::Quota),
             0x0074_7069_7263_5365_7665_6953 => Ok(DataType::SieveScript),
+            0x0068_6372_6165_5364_6576_6153 => Ok(DataType::SavedSearch),
+            0x0074_6e65_7645_7261_646e_656c_6143 => Ok(DataType::CalendarEvent),
+            0x0064_7261_4374_6361_746e_6f43 => Ok(DataType::ContactCard),
             _ => Err(()),
         }
     }
@@ -188,6 +203,9 @@ impl DataType {
             DataType::Mdn => "MDN",
             DataType::Quota => "Quota",
             DataType::SieveScript => "SieveScript",
+            DataType::SavedSearch => "SavedSearch",
+            DataType::CalendarEvent => "CalendarEvent",
+            DataType::ContactCard => "ContactCard",
             DataType::None => "",
         }
     }
@@ -221,6 +239,9 @@ impl DeserializeFrom for DataType {
             10 => Some(DataType::Mdn),
             11 => Some(DataType::Quota),
             12 => Some(DataType::SieveScript),
+            13 => Some(DataType::SavedSearch),
+            14 => Some(DataType::CalendarEvent),
+            15 => Some(DataType::ContactCard),
             _ => None,
         }
     }
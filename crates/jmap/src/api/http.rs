@@ -48,6 +48,7 @@ use jmap_proto::{
 use crate::{
     auth::oauth::OAuthMetadata,
     blob::{DownloadResponse, UploadResponse},
+    dav::DavResponse,
     services::state,
     JmapInstance, JMAP,
 };
@@ -75,18 +76,20 @@ impl JMAP {
         match path.next().unwrap_or_default() {
             "jmap" => {
                 // Authenticate request
-                let (_in_flight, access_token) =
-                    match self.authenticate_headers(&req, session.remote_ip).await {
-                        Ok(Some(session)) => session,
-                        Ok(None) => {
-                            return if req.method() != Method::OPTIONS {
-                                RequestError::unauthorized().into_http_response()
-                            } else {
-                                ().into_http_response()
-                            }
+                let (_in_flight, access_token) = match self
+                    .authenticate_headers(&req, session.remote_ip, "jmap")
+                    .await
+                {
+                    Ok(Some(session)) => session,
+                    Ok(None) => {
+                        return if req.method() != Method::OPTIONS {
+                            RequestError::unauthorized().into_http_response()
+                        } else {
+                            ().into_http_response()
                         }
-                        Err(err) => return err.into_http_response(),
-                    };
+                    }
+                    Err(err) => return err.into_http_response(),
+                };
 
                 match (path.next().unwrap_or_default(), req.method()) {
                     ("", &Method::POST) => {
@@ -210,15 +213,20 @@ impl JMAP {
                     _ => (),
                 }
             }
+            "dav" => {
+                return self.handle_dav_request(req, &session).await;
+            }
             ".well-known" => match (path.next().unwrap_or_default(), req.method()) {
                 ("jmap", &Method::GET) => {
                     // Authenticate request
-                    let (_in_flight, access_token) =
-                        match self.authenticate_headers(&req, session.remote_ip).await {
-                            Ok(Some(session)) => session,
-                            Ok(None) => return RequestError::unauthorized().into_http_response(),
-                            Err(err) => return err.into_http_response(),
-                        };
+                    let (_in_flight, access_token) = match self
+                        .authenticate_headers(&req, session.remote_ip, "jmap")
+                        .await
+                    {
+                        Ok(Some(session)) => session,
+                        Ok(None) => return RequestError::unauthorized().into_http_response(),
+                        Err(err) => return err.into_http_response(),
+                    };
 
                     return match self
                         .handle_session_resource(
@@ -281,6 +289,11 @@ impl JMAP {
                         return self.handle_autoconfig_request(&req).await;
                     }
                 }
+                ("caldav" | "carddav", _) if self.core.dav.enable => {
+                    return DavResponse::new(StatusCode::MOVED_PERMANENTLY)
+                        .with_header("Location", "/dav/")
+                        .into_http_response();
+                }
                 (_, &Method::OPTIONS) => {
                     return ().into_http_response();
                 }
@@ -314,7 +327,10 @@ impl JMAP {
                 }
 
                 // Authenticate user
-                return match self.authenticate_headers(&req, session.remote_ip).await {
+                return match self
+                    .authenticate_headers(&req, session.remote_ip, "jmap")
+                    .await
+                {
                     Ok(Some((_, access_token))) => {
                         let body = fetch_body(&mut req, 1024 * 1024).await;
                         self.handle_api_manage_request(&req, body, access_token)
@@ -324,6 +340,24 @@ impl JMAP {
                     Err(err) => err.into_http_response(),
                 };
             }
+            "metrics" => {
+                if self.core.jmap.metrics_prometheus
+                    && req.method() == Method::GET
+                    && path.next().unwrap_or_default() == "prometheus"
+                {
+                    return match self
+                        .authenticate_headers(&req, session.remote_ip, "jmap")
+                        .await
+                    {
+                        Ok(Some((_, access_token))) if access_token.is_super_user() => {
+                            self.handle_prometheus_metrics().await
+                        }
+                        Ok(Some(_)) => RequestError::forbidden().into_http_response(),
+                        Ok(None) => RequestError::unauthorized().into_http_response(),
+                        Err(err) => err.into_http_response(),
+                    };
+                }
+            }
             "mail" => {
                 if req.method() == Method::GET
                     && path.next().unwrap_or_default() == "config-v1.1.xml"
@@ -362,6 +396,7 @@ impl JmapInstance {
     async fn handle_session<T: SessionStream>(self, session: SessionData<T>) {
         let span = session.span;
         let _in_flight = session.in_flight;
+        let _qos = session.qos;
         let is_tls = session.stream.is_tls();
 
         if let Err(http_err) = http1::Builder::new()
@@ -637,9 +672,13 @@ impl ToHttpResponse for UploadResponse {
 
 impl ToHttpResponse for RequestError {
     fn into_http_response(self) -> HttpResponse {
-        hyper::Response::builder()
+        let mut response = hyper::Response::builder()
             .status(StatusCode::from_u16(self.status).unwrap())
-            .header(header::CONTENT_TYPE, "application/problem+json")
+            .header(header::CONTENT_TYPE, "application/problem+json");
+        if let Some(retry_after) = self.retry_after {
+            response = response.header(header::RETRY_AFTER, retry_after);
+        }
+        response
             .body(
                 Full::new(Bytes::from(serde_json::to_string(&self).unwrap()))
                     .map_err(|never| match never {})
@@ -0,0 +1,258 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use hyper::{Method, StatusCode};
+use jmap_proto::error::request::RequestError;
+use mail_parser::DateTime;
+use serde_json::{json, Value};
+use store::{
+    write::{now, BatchBuilder, Bincode, ReportClass, ValueClass},
+    Deserialize, IterateParams, Serialize, ValueKey,
+};
+use utils::url_params::UrlParams;
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    JMAP,
+};
+
+use super::decode_path_element;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct AuditEvent {
+    pub timestamp: u64,
+    pub actor: String,
+    pub action: String,
+    pub target: String,
+    pub method: String,
+    pub path: String,
+    pub changes: String,
+    pub status: u16,
+}
+
+const REDACTED: &str = "[redacted]";
+
+impl JMAP {
+    pub async fn audit_log(&self, actor: &str, req: &HttpRequest, body: &[u8], status: StatusCode) {
+        if self.core.jmap.audit_retention.is_none() {
+            return;
+        }
+        let path = req.uri().path();
+        let now = now();
+        let event = AuditEvent {
+            timestamp: now,
+            actor: actor.to_string(),
+            action: path.split('/').nth(2).unwrap_or_default().to_string(),
+            target: path
+                .split('/')
+                .skip(3)
+                .map(decode_path_element)
+                .collect::<Vec<_>>()
+                .join("/"),
+            method: req.method().to_string(),
+            path: path.to_string(),
+            changes: if !body.is_empty() {
+                audit_changes(body)
+            } else {
+                req.uri().query().unwrap_or_default().to_string()
+            },
+            status: status.as_u16(),
+        };
+
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            ValueClass::Report(ReportClass::Audit {
+                id: self.inner.snowflake_id.generate().unwrap_or(now),
+                timestamp: now,
+            }),
+            Bincode::new(event).serialize(),
+        );
+        if let Err(err) = self.core.storage.data.write(batch.build()).await {
+            tracing::warn!(
+                context = "audit",
+                event = "error",
+                actor = actor,
+                path = path,
+                reason = %err,
+                "Failed to store audit event."
+            );
+        }
+    }
+
+    pub async fn purge_audit_log(&self) {
+        let retention = if let Some(retention) = self.core.jmap.audit_retention {
+            retention.as_secs()
+        } else {
+            return;
+        };
+
+        if let Err(err) = self
+            .core
+            .storage
+            .data
+            .delete_range(
+                ValueKey::from(ValueClass::Report(ReportClass::Audit {
+                    id: 0,
+                    timestamp: 0,
+                })),
+                ValueKey::from(ValueClass::Report(ReportClass::Audit {
+                    id: u64::MAX,
+                    timestamp: now().saturating_sub(retention),
+                })),
+            )
+            .await
+        {
+            tracing::error!(
+                context = "audit",
+                event = "error",
+                reason = %err,
+                "Failed to purge audit log."
+            );
+        }
+    }
+
+    pub async fn handle_manage_audit(&self, req: &HttpRequest) -> HttpResponse {
+        if req.method() != Method::GET {
+            return RequestError::not_found().into_http_response();
+        }
+
+        let params = UrlParams::new(req.uri().query());
+        let actor = params.get("actor");
+        let action = params.get("action");
+        let after = params
+            .get("after")
+            .and_then(DateTime::parse_rfc3339)
+            .map(|dt| dt.to_timestamp() as u64);
+        let before = params
+            .get("before")
+            .and_then(DateTime::parse_rfc3339)
+            .map(|dt| dt.to_timestamp() as u64);
+        let page: usize = params.parse::<usize>("page").unwrap_or_default();
+        let limit: usize = params.parse::<usize>("limit").unwrap_or_default();
+
+        let mut results = Vec::new();
+        let mut offset = page.saturating_sub(1) * limit;
+        let mut total = 0;
+        let result = self
+            .core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Report(ReportClass::Audit {
+                        id: 0,
+                        timestamp: after.unwrap_or_default(),
+                    })),
+                    ValueKey::from(ValueClass::Report(ReportClass::Audit {
+                        id: u64::MAX,
+                        timestamp: before.unwrap_or(u64::MAX),
+                    })),
+                )
+                .descending(),
+                |_, value| {
+                    let event = Bincode::<AuditEvent>::deserialize(value)?.inner;
+                    if actor.map_or(true, |actor| event.actor == actor)
+                        && action.map_or(true, |action| event.action == action)
+                    {
+                        if offset == 0 {
+                            if limit == 0 || results.len() < limit {
+                                results.push(json!({
+                                    "timestamp": DateTime::from_timestamp(event.timestamp as i64).to_rfc3339(),
+                                    "actor": event.actor,
+                                    "action": event.action,
+                                    "target": event.target,
+                                    "method": event.method,
+                                    "path": event.path,
+                                    "changes": event.changes,
+                                    "status": event.status,
+                                }));
+                            }
+                        } else {
+                            offset -= 1;
+                        }
+                        total += 1;
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await;
+
+        match result {
+            Ok(_) => JsonResponse::new(json!({
+                    "data": {
+                        "items": results,
+                        "total": total,
+                    },
+            }))
+            .into_http_response(),
+            Err(err) => err.into_http_response(),
+        }
+    }
+}
+
+// JSON change sets are stored with their secrets redacted, other bodies
+// such as Sieve scripts are stored as text
+fn audit_changes(body: &[u8]) -> String {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(mut changes) => {
+            redact_secrets(&mut changes);
+            changes.to_string()
+        }
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    }
+}
+
+fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            // Principal updates are sent as {"action", "field", "value"}
+            let is_secret_update = map
+                .get("field")
+                .and_then(|field| field.as_str())
+                .map_or(false, is_secret_key);
+            for (key, value) in map.iter_mut() {
+                if is_secret_key(key) || (is_secret_update && key == "value") {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_secrets(value);
+                }
+            }
+        }
+        Value::Array(items) => {
+            // Settings are sent as [key, value] pairs
+            if let [Value::String(key), value] = items.as_mut_slice() {
+                if is_secret_key(key) {
+                    *value = Value::String(REDACTED.to_string());
+                    return;
+                }
+            }
+            items.iter_mut().for_each(redact_secrets);
+        }
+        _ => (),
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    key.contains("secret") || key.contains("password")
+}
@@ -0,0 +1,110 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::net::IpAddr;
+
+use hyper::Method;
+use jmap_proto::error::request::RequestError;
+use serde::Serialize;
+use serde_json::json;
+use utils::url_params::UrlParams;
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    JMAP,
+};
+
+use super::{decode_path_element, ManagementApiError};
+
+#[derive(Debug, Serialize)]
+struct BlockedIp {
+    ip: IpAddr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires: Option<u64>,
+}
+
+impl JMAP {
+    pub async fn handle_manage_blocked_ip(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+    ) -> HttpResponse {
+        match (path.get(1), req.method()) {
+            (None, &Method::GET) => {
+                // List blocked IP addresses
+                let params = UrlParams::new(req.uri().query());
+                let page: usize = params.parse("page").unwrap_or(0);
+                let limit: usize = params.parse("limit").unwrap_or(0);
+
+                let mut blocked = self
+                    .core
+                    .list_blocked_ips()
+                    .into_iter()
+                    .map(|(ip, expires)| BlockedIp { ip, expires })
+                    .collect::<Vec<_>>();
+                blocked.sort_unstable_by_key(|blocked| blocked.ip);
+                let total = blocked.len();
+                let items = if limit > 0 {
+                    let offset = page.saturating_sub(1) * limit;
+                    blocked.into_iter().skip(offset).take(limit).collect()
+                } else {
+                    blocked
+                };
+
+                JsonResponse::new(json!({
+                        "data": {
+                            "items": items,
+                            "total": total,
+                        },
+                }))
+                .into_http_response()
+            }
+            (Some(ip), &Method::DELETE) => {
+                // Lift ban
+                let ip = decode_path_element(ip);
+                let ip = match ip.parse::<IpAddr>() {
+                    Ok(ip) => ip,
+                    Err(_) => {
+                        return ManagementApiError::Other {
+                            details: format!("Invalid IP address {ip:?}").into(),
+                        }
+                        .into_http_response();
+                    }
+                };
+
+                match self.core.unblock_ip(ip).await {
+                    Ok(true) => JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response(),
+                    Ok(false) => ManagementApiError::NotFound {
+                        item: ip.to_string().into(),
+                    }
+                    .into_http_response(),
+                    Err(err) => err.into_http_response(),
+                }
+            }
+            _ => RequestError::not_found().into_http_response(),
+        }
+    }
+}
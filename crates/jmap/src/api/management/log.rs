@@ -5,14 +5,17 @@ use std::{
 };
 
 use chrono::DateTime;
+use common::history::HistoryEvent;
 use rev_lines::RevLines;
 use serde::Serialize;
 use serde_json::json;
+use store::write::Bincode;
 use tokio::sync::oneshot;
 use utils::url_params::UrlParams;
 
 use crate::{
     api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    services::history::{history_chunk_key, HistoryChunk},
     JMAP,
 };
 
@@ -73,6 +76,122 @@ impl JMAP {
     }
 }
 
+impl JMAP {
+    pub async fn handle_event_history(&self, req: &HttpRequest) -> HttpResponse {
+        let params = UrlParams::new(req.uri().query());
+        let filter = HistoryFilter {
+            account: params.get("account"),
+            remote_ip: params.get("remote-ip"),
+            protocol: params.get("protocol"),
+            cause: params.get("cause"),
+            context: params.get("context"),
+            text: params.get("filter"),
+        };
+        let page: usize = params.parse("page").unwrap_or(0);
+        let limit: usize = params.parse("limit").unwrap_or(100);
+        let mut offset = page.saturating_sub(1) * limit;
+
+        // Walk the ring buffer backwards, starting from the most recent chunk
+        let mut items = Vec::new();
+        let mut total = 0;
+        let last_seq = self.history_seq().await;
+        let mut seq = last_seq;
+        loop {
+            let chunk = match self
+                .core
+                .storage
+                .lookup
+                .key_get::<Bincode<HistoryChunk>>(history_chunk_key(seq))
+                .await
+            {
+                Ok(Some(chunk)) => chunk.inner,
+                Ok(None) if seq == last_seq => HistoryChunk::default(),
+                Ok(None) => break,
+                Err(err) => return err.into_http_response(),
+            };
+
+            for event in chunk.events.iter().rev() {
+                if filter.matches(event) {
+                    total += 1;
+                    if offset == 0 {
+                        if items.len() < limit {
+                            items.push(json!({
+                                "timestamp": DateTime::from_timestamp(event.timestamp as i64, 0)
+                                    .unwrap_or_default()
+                                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                                "level": event.level,
+                                "context": event.context,
+                                "event": event.event,
+                                "message": event.message,
+                                "fields": event
+                                    .fields
+                                    .iter()
+                                    .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+                                    .collect::<serde_json::Map<_, _>>(),
+                            }));
+                        }
+                    } else {
+                        offset -= 1;
+                    }
+                }
+            }
+
+            if seq == 0 {
+                break;
+            }
+            seq -= 1;
+        }
+
+        JsonResponse::new(json!({
+            "data": {
+                "items": items,
+                "total": total,
+            },
+        }))
+        .into_http_response()
+    }
+}
+
+struct HistoryFilter<'x> {
+    account: Option<&'x str>,
+    remote_ip: Option<&'x str>,
+    protocol: Option<&'x str>,
+    cause: Option<&'x str>,
+    context: Option<&'x str>,
+    text: Option<&'x str>,
+}
+
+impl HistoryFilter<'_> {
+    fn matches(&self, event: &HistoryEvent) -> bool {
+        self.account.map_or(true, |account| {
+            ["account_id", "account", "login", "user", "name"]
+                .iter()
+                .any(|field| event.field(field) == Some(account))
+        }) && self.remote_ip.map_or(true, |ip| {
+            ["remote.ip", "remote_ip"]
+                .iter()
+                .any(|field| event.field(field) == Some(ip))
+        }) && self.protocol.map_or(true, |protocol| {
+            event
+                .field("protocol")
+                .map_or(false, |value| value.eq_ignore_ascii_case(protocol))
+        }) && self.cause.map_or(true, |cause| {
+            event.event == cause
+                || ["reason", "details", "error"].iter().any(|field| {
+                    event
+                        .field(field)
+                        .map_or(false, |value| value.contains(cause))
+                })
+        }) && self
+            .context
+            .map_or(true, |context| event.context == context)
+            && self.text.map_or(true, |text| {
+                event.message.contains(text)
+                    || event.fields.iter().any(|(_, value)| value.contains(text))
+            })
+    }
+}
+
 fn read_log_files(
     path: impl AsRef<Path>,
     filter: &str,
@@ -0,0 +1,122 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use hyper::{Method, StatusCode};
+use jmap_proto::error::request::RequestError;
+use serde_json::json;
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    services::migrate::MigrationRequest,
+    JMAP,
+};
+
+use super::{decode_path_element, ManagementApiError};
+
+impl JMAP {
+    pub async fn handle_manage_migrate(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+    ) -> HttpResponse {
+        // Obtain account id
+        let account_id = match path.get(1) {
+            Some(name) => {
+                match self
+                    .core
+                    .storage
+                    .data
+                    .get_account_id(decode_path_element(name).as_ref())
+                    .await
+                {
+                    Ok(Some(account_id)) => account_id,
+                    Ok(None) => {
+                        return RequestError::blank(
+                            StatusCode::NOT_FOUND.as_u16(),
+                            "Not found",
+                            "Account not found.",
+                        )
+                        .into_http_response();
+                    }
+                    Err(err) => {
+                        return err.into_http_response();
+                    }
+                }
+            }
+            None => return RequestError::not_found().into_http_response(),
+        };
+
+        match *req.method() {
+            Method::GET => match self.migration_status(account_id).await {
+                Ok(Some(status)) => JsonResponse::new(json!({
+                    "data": status,
+                }))
+                .into_http_response(),
+                Ok(None) => RequestError::blank(
+                    StatusCode::NOT_FOUND.as_u16(),
+                    "Not found",
+                    "No migration found for this account.",
+                )
+                .into_http_response(),
+                Err(_) => RequestError::internal_server_error().into_http_response(),
+            },
+            Method::POST => {
+                match serde_json::from_slice::<MigrationRequest>(
+                    body.as_deref().unwrap_or_default(),
+                ) {
+                    Ok(request) => match self.migration_start(account_id, request).await {
+                        Ok(true) => JsonResponse::new(json!({
+                            "data": (),
+                        }))
+                        .into_http_response(),
+                        Ok(false) => ManagementApiError::Other {
+                            details: "A migration is already running for this account.".into(),
+                        }
+                        .into_http_response(),
+                        Err(_) => RequestError::internal_server_error().into_http_response(),
+                    },
+                    Err(err) => err.into_http_response(),
+                }
+            }
+            Method::DELETE => {
+                // Cancel a running migration, otherwise discard its saved progress
+                if self.migration_cancel(account_id) {
+                    JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response()
+                } else {
+                    match self.migration_delete(account_id).await {
+                        Ok(_) => JsonResponse::new(json!({
+                            "data": (),
+                        }))
+                        .into_http_response(),
+                        Err(_) => RequestError::internal_server_error().into_http_response(),
+                    }
+                }
+            }
+            _ => RequestError::not_found().into_http_response(),
+        }
+    }
+}
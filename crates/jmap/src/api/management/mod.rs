@@ -21,14 +21,18 @@
  * for more details.
 */
 
+pub mod audit;
+pub mod blocked;
 pub mod dkim;
 pub mod domain;
 pub mod log;
+pub mod migrate;
 pub mod principal;
 pub mod queue;
 pub mod reload;
 pub mod report;
 pub mod settings;
+pub mod sieve;
 pub mod stores;
 
 use std::{borrow::Cow, sync::Arc};
@@ -75,19 +79,37 @@ impl JMAP {
     ) -> HttpResponse {
         let path = req.uri().path().split('/').skip(2).collect::<Vec<_>>();
         let is_superuser = access_token.is_super_user();
+        let actor = access_token.name.clone();
 
-        match path.first().copied().unwrap_or_default() {
+        // Only administrative mutations are recorded in the audit log
+        let audit_body = (is_superuser
+            && !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+            && !matches!(
+                path.first().copied().unwrap_or_default(),
+                "oauth" | "crypto" | "undo" | "password" | "app-password"
+            ))
+        .then(|| body.clone().unwrap_or_default());
+
+        let response = match path.first().copied().unwrap_or_default() {
             "queue" if is_superuser => self.handle_manage_queue(req, path).await,
             "settings" if is_superuser => self.handle_manage_settings(req, path, body).await,
             "reports" if is_superuser => self.handle_manage_reports(req, path).await,
             "principal" if is_superuser => self.handle_manage_principal(req, path, body).await,
             "domain" if is_superuser => self.handle_manage_domain(req, path).await,
             "store" if is_superuser => self.handle_manage_store(req, path).await,
+            "sieve" if is_superuser => self.handle_manage_sieve(req, path, body).await,
+            "migrate" if is_superuser => self.handle_manage_migrate(req, path, body).await,
+            "audit" if is_superuser => self.handle_manage_audit(req).await,
             "reload" if is_superuser => self.handle_manage_reload(req, path).await,
+            "blocked-ip" if is_superuser => self.handle_manage_blocked_ip(req, path).await,
             "dkim" if is_superuser => self.handle_manage_dkim(req, path, body).await,
             "update" if is_superuser => self.handle_manage_update(req, path).await,
             "logs" if is_superuser && req.method() == Method::GET => {
-                self.handle_view_logs(req).await
+                if path.get(1) == Some(&"history") {
+                    self.handle_event_history(req).await
+                } else {
+                    self.handle_view_logs(req).await
+                }
             }
             "restart" if is_superuser && req.method() == Method::GET => {
                 ManagementApiError::Unsupported {
@@ -101,11 +123,25 @@ impl JMAP {
                 Method::GET => self.handle_crypto_get(access_token).await,
                 _ => RequestError::not_found().into_http_response(),
             },
+            "undo" if req.method() == Method::DELETE => {
+                self.handle_undo_send(path, access_token).await
+            }
             "password" if req.method() == Method::POST => {
                 self.handle_change_password(req, access_token, body).await
             }
+            "app-password" => {
+                self.handle_app_password(req, path, access_token, body)
+                    .await
+            }
             _ => RequestError::not_found().into_http_response(),
+        };
+
+        if let Some(audit_body) = audit_body {
+            self.audit_log(&actor, req, &audit_body, response.status())
+                .await;
         }
+
+        response
     }
 }
 
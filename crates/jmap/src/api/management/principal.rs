@@ -26,8 +26,8 @@ use std::sync::Arc;
 use directory::{
     backend::internal::{
         lookup::DirectoryStore, manage::ManageDirectory, PrincipalField, PrincipalUpdate,
-        PrincipalValue,
     },
+    core::app_password::AppPassword,
     DirectoryError, DirectoryInner, ManagementError, Principal, QueryBy, Type,
 };
 
@@ -68,6 +68,15 @@ pub struct PrincipalResponse {
     pub members: Vec<String>,
     #[serde(default)]
     pub description: Option<String>,
+    #[serde(default)]
+    pub protocols: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct AppPasswordRequest {
+    pub name: String,
+    #[serde(default)]
+    pub protocols: Vec<String>,
 }
 
 impl JMAP {
@@ -103,6 +112,7 @@ impl JMAP {
                                     emails: principal.emails,
                                     member_of: principal.member_of,
                                     description: principal.description,
+                                    protocols: principal.protocols,
                                 },
                                 principal.members,
                             )
@@ -346,13 +356,7 @@ impl JMAP {
             .core
             .storage
             .data
-            .update_account(
-                QueryBy::Id(access_token.primary_id()),
-                vec![PrincipalUpdate::set(
-                    PrincipalField::Secrets,
-                    PrincipalValue::StringList(vec![new_password]),
-                )],
-            )
+            .change_password(access_token.primary_id(), &new_password)
             .await
         {
             Ok(_) => JsonResponse::new(json!({
@@ -363,6 +367,113 @@ impl JMAP {
         }
     }
 
+    pub async fn handle_app_password(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> HttpResponse {
+        // Make sure the current directory supports updates
+        if let Some(response) = self.assert_supported_directory() {
+            return response;
+        } else if access_token.primary_id() == u32::MAX {
+            return ManagementApiError::Unsupported {
+                details: "Application passwords are not available for the fallback administrator"
+                    .into(),
+            }
+            .into_http_response();
+        }
+        let account_id = access_token.primary_id();
+
+        match (path.get(1), req.method()) {
+            (None, &Method::GET) => {
+                // List application passwords, their secrets are never returned
+                match self
+                    .core
+                    .storage
+                    .data
+                    .query(QueryBy::Id(account_id), false)
+                    .await
+                {
+                    Ok(Some(principal)) => {
+                        let app_passwords = principal
+                            .app_passwords()
+                            .map(|app_password| {
+                                json!({
+                                    "name": app_password.name,
+                                    "protocols": app_password.protocols,
+                                    "lastUsed": Some(app_password.last_used)
+                                        .filter(|last_used| *last_used > 0),
+                                })
+                            })
+                            .collect::<Vec<_>>();
+
+                        JsonResponse::new(json!({
+                            "data": app_passwords,
+                        }))
+                        .into_http_response()
+                    }
+                    Ok(None) => RequestError::not_found().into_http_response(),
+                    Err(err) => err.into_http_response(),
+                }
+            }
+            (None, &Method::POST) => {
+                let request = match serde_json::from_slice::<AppPasswordRequest>(
+                    body.as_deref().unwrap_or_default(),
+                ) {
+                    Ok(request) => request,
+                    Err(err) => return err.into_http_response(),
+                };
+                let (app_password, secret) =
+                    match AppPassword::generate(&request.name, &request.protocols) {
+                        Ok(app_password) => app_password,
+                        Err(err) => {
+                            return ManagementApiError::Other {
+                                details: err.into(),
+                            }
+                            .into_http_response()
+                        }
+                    };
+
+                match self
+                    .core
+                    .storage
+                    .data
+                    .add_app_password(account_id, &app_password)
+                    .await
+                {
+                    Ok(_) => JsonResponse::new(json!({
+                        "data": {
+                            "name": app_password.name,
+                            "protocols": app_password.protocols,
+                            "password": secret,
+                        },
+                    }))
+                    .into_http_response(),
+                    Err(err) => err.into_http_response(),
+                }
+            }
+            (Some(name), &Method::DELETE) => {
+                let name = decode_path_element(name);
+                match self
+                    .core
+                    .storage
+                    .data
+                    .remove_app_password(account_id, name.as_ref())
+                    .await
+                {
+                    Ok(_) => JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response(),
+                    Err(err) => err.into_http_response(),
+                }
+            }
+            _ => RequestError::not_found().into_http_response(),
+        }
+    }
+
     pub fn assert_supported_directory(&self) -> Option<HttpResponse> {
         ManagementApiError::UnsupportedDirectoryOperation {
             class: match &self.core.storage.directory.store {
@@ -371,6 +482,8 @@ impl JMAP {
                 DirectoryInner::Sql(_) => "SQL",
                 DirectoryInner::Imap(_) => "IMAP",
                 DirectoryInner::Smtp(_) => "SMTP",
+                DirectoryInner::Oidc(_) => "OIDC",
+                DirectoryInner::Http(_) => "HTTP",
                 DirectoryInner::Memory(_) => "In-Memory",
             }
             .into(),
@@ -393,6 +506,7 @@ impl From<Principal<String>> for PrincipalResponse {
             secrets: principal.secrets,
             used_quota: 0,
             members: Vec::new(),
+            protocols: principal.protocols,
         }
     }
 }
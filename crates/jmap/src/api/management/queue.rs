@@ -21,9 +21,11 @@
  * for more details.
 */
 
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use common::manager::webadmin::Resource;
+use directory::QueryBy;
 use hyper::Method;
 use jmap_proto::error::request::RequestError;
 use mail_auth::{
@@ -34,7 +36,7 @@ use mail_auth::{
 use mail_parser::DateTime;
 use serde::{Deserializer, Serializer};
 use serde_json::json;
-use smtp::queue::{self, ErrorDetails, HostResponse, QueueId, Status};
+use smtp::queue::{self, timeline::Disposition, ErrorDetails, HostResponse, QueueId, Status};
 use store::{
     write::{key::DeserializeBigEndian, now, Bincode, QueueClass, ReportEvent, ValueClass},
     Deserialize, IterateParams, ValueKey,
@@ -43,10 +45,11 @@ use utils::url_params::UrlParams;
 
 use crate::{
     api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    auth::AccessToken,
     JMAP,
 };
 
-use super::decode_path_element;
+use super::{decode_path_element, ManagementApiError};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub struct Message {
@@ -63,6 +66,30 @@ pub struct Message {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub env_id: Option<String>,
     pub blob_hash: String,
+    #[serde(skip_serializing_if = "is_false")]
+    #[serde(default)]
+    pub on_hold: bool,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct DeadLetter {
+    #[serde(flatten)]
+    pub message: Message,
+    #[serde(deserialize_with = "deserialize_datetime")]
+    #[serde(serialize_with = "serialize_datetime")]
+    pub archived: DateTime,
+    pub failures: Vec<Failure>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+pub struct Failure {
+    #[serde(deserialize_with = "deserialize_datetime")]
+    #[serde(serialize_with = "serialize_datetime")]
+    pub timestamp: DateTime,
+    pub domain: String,
+    pub retry_num: u32,
+    pub status: Status<String, String>,
+    pub recipients: Vec<Recipient>,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
@@ -135,6 +162,10 @@ impl JMAP {
                 let to = params.get("to");
                 let before = params.parse::<Timestamp>("before").map(|t| t.into_inner());
                 let after = params.parse::<Timestamp>("after").map(|t| t.into_inner());
+                let domain = params.get("domain").map(|d| d.to_lowercase());
+                let status = params.get("status");
+                let min_age = params.parse::<u64>("min-age");
+                let max_age = params.parse::<u64>("max-age");
                 let page = params.parse::<usize>("page").unwrap_or_default();
                 let limit = params.parse::<usize>("limit").unwrap_or_default();
                 let values = params.has_key("values");
@@ -151,7 +182,12 @@ impl JMAP {
                     || from.is_some()
                     || to.is_some()
                     || before.is_some()
-                    || after.is_some();
+                    || after.is_some()
+                    || domain.is_some()
+                    || status.is_some()
+                    || min_age.is_some()
+                    || max_age.is_some();
+                let now = now();
                 let mut offset = page.saturating_sub(1) * limit;
                 let mut total = 0;
                 let mut total_returned = 0;
@@ -188,6 +224,18 @@ impl JMAP {
                                     })
                                     && after.as_ref().map_or(true, |after| {
                                         message.next_delivery_event() > *after
+                                    })
+                                    && domain.as_ref().map_or(true, |domain| {
+                                        message.domains.iter().any(|d| &d.domain == domain)
+                                    })
+                                    && status.map_or(true, |status| {
+                                        message_has_status(&message, status)
+                                    })
+                                    && min_age.map_or(true, |min_age| {
+                                        message.created.saturating_add(min_age) <= now
+                                    })
+                                    && max_age.map_or(true, |max_age| {
+                                        message.created.saturating_add(max_age) >= now
                                     }));
 
                             if matches {
@@ -243,44 +291,108 @@ impl JMAP {
                     RequestError::not_found().into_http_response()
                 }
             }
-            ("messages", Some(queue_id), &Method::PATCH) => {
+            ("messages", None, &Method::PATCH | &Method::DELETE) => {
+                // Bulk operations on all messages queued for a destination domain
+                let domain = if let Some(domain) = params.get("domain") {
+                    domain.to_lowercase()
+                } else {
+                    return RequestError::invalid_parameters().into_http_response();
+                };
+                let is_cancel = *req.method() == Method::DELETE;
+                let action = if let Some(action) = QueueAction::parse(params.get("action")) {
+                    action
+                } else {
+                    return RequestError::invalid_parameters().into_http_response();
+                };
                 let time = params
                     .parse::<Timestamp>("at")
                     .map(|t| t.into_inner())
                     .unwrap_or_else(now);
-                let item = params.get("filter");
 
-                if let Some(mut message) = self
+                // Obtain the ids of the messages queued for this domain
+                let mut queue_ids = Vec::new();
+                let from_key = ValueKey::from(ValueClass::Queue(QueueClass::Message(0)));
+                let to_key = ValueKey::from(ValueClass::Queue(QueueClass::Message(u64::MAX)));
+                let _ = self
+                    .core
+                    .storage
+                    .data
+                    .iterate(
+                        IterateParams::new(from_key, to_key).ascending(),
+                        |key, value| {
+                            let message = Bincode::<queue::Message>::deserialize(value)?.inner;
+                            if message.domains.iter().any(|d| {
+                                d.domain == domain
+                                    && matches!(
+                                        d.status,
+                                        Status::Scheduled | Status::TemporaryFailure(_)
+                                    )
+                            }) {
+                                queue_ids.push(key.deserialize_be_u64(0)?);
+                            }
+
+                            Ok(true)
+                        },
+                    )
+                    .await;
+
+                let mut total = 0;
+                for queue_id in queue_ids {
+                    if let Some(message) = self.smtp.read_message(queue_id).await {
+                        let found = if is_cancel {
+                            let domain_idx =
+                                message.domains.iter().position(|d| d.domain == domain);
+                            self.queue_cancel(message, |rcpt| Some(rcpt.domain_idx) == domain_idx)
+                                .await
+                        } else {
+                            match action {
+                                QueueAction::Retry => {
+                                    self.queue_retry(message, time, |d| d == domain).await
+                                }
+                                QueueAction::Hold => self.queue_hold(message).await,
+                                QueueAction::Release => self.queue_release(message).await,
+                            }
+                        };
+
+                        if found {
+                            total += 1;
+                        }
+                    }
+                }
+
+                JsonResponse::new(json!({
+                        "data": total,
+                }))
+                .into_http_response()
+            }
+            ("messages", Some(queue_id), &Method::PATCH) => {
+                let action = if let Some(action) = QueueAction::parse(params.get("action")) {
+                    action
+                } else {
+                    return RequestError::invalid_parameters().into_http_response();
+                };
+
+                if let Some(message) = self
                     .smtp
                     .read_message(queue_id.parse().unwrap_or_default())
                     .await
                 {
-                    let prev_event = message.next_event().unwrap_or_default();
-                    let mut found = false;
-
-                    for domain in &mut message.domains {
-                        if matches!(
-                            domain.status,
-                            Status::Scheduled | Status::TemporaryFailure(_)
-                        ) && item
-                            .as_ref()
-                            .map_or(true, |item| domain.domain.contains(item))
-                        {
-                            domain.retry.due = time;
-                            if domain.expires > time {
-                                domain.expires = time + 10;
-                            }
-                            found = true;
-                        }
-                    }
+                    let found = match action {
+                        QueueAction::Retry => {
+                            let time = params
+                                .parse::<Timestamp>("at")
+                                .map(|t| t.into_inner())
+                                .unwrap_or_else(now);
+                            let item = params.get("filter");
 
-                    if found {
-                        let next_event = message.next_event().unwrap_or_default();
-                        message
-                            .save_changes(&self.smtp, prev_event.into(), next_event.into())
-                            .await;
-                        let _ = self.smtp.inner.queue_tx.send(queue::Event::Reload).await;
-                    }
+                            self.queue_retry(message, time, |domain| {
+                                item.as_ref().map_or(true, |item| domain.contains(item))
+                            })
+                            .await
+                        }
+                        QueueAction::Hold => self.queue_hold(message).await,
+                        QueueAction::Release => self.queue_release(message).await,
+                    };
 
                     JsonResponse::new(json!({
                             "data": found,
@@ -291,76 +403,24 @@ impl JMAP {
                 }
             }
             ("messages", Some(queue_id), &Method::DELETE) => {
-                if let Some(mut message) = self
+                if let Some(message) = self
                     .smtp
                     .read_message(queue_id.parse().unwrap_or_default())
                     .await
                 {
-                    let mut found = false;
-                    let prev_event = message.next_event().unwrap_or_default();
-
-                    if let Some(item) = params.get("filter") {
+                    let found = if let Some(item) = params.get("filter") {
                         // Cancel delivery for all recipients that match
-                        for rcpt in &mut message.recipients {
-                            if rcpt.address_lcase.contains(item) {
-                                rcpt.status = Status::PermanentFailure(HostResponse {
-                                    hostname: ErrorDetails::default(),
-                                    response: smtp_proto::Response {
-                                        code: 0,
-                                        esc: [0, 0, 0],
-                                        message: "Delivery canceled.".to_string(),
-                                    },
-                                });
-                                found = true;
-                            }
-                        }
-                        if found {
-                            // Mark as completed domains without any pending deliveries
-                            for (domain_idx, domain) in message.domains.iter_mut().enumerate() {
-                                if matches!(
-                                    domain.status,
-                                    Status::TemporaryFailure(_) | Status::Scheduled
-                                ) {
-                                    let mut total_rcpt = 0;
-                                    let mut total_completed = 0;
-
-                                    for rcpt in &message.recipients {
-                                        if rcpt.domain_idx == domain_idx {
-                                            total_rcpt += 1;
-                                            if matches!(
-                                                rcpt.status,
-                                                Status::PermanentFailure(_) | Status::Completed(_)
-                                            ) {
-                                                total_completed += 1;
-                                            }
-                                        }
-                                    }
-
-                                    if total_rcpt == total_completed {
-                                        domain.status = Status::Completed(());
-                                    }
-                                }
-                            }
-
-                            // Delete message if there are no pending deliveries
-                            if message.domains.iter().any(|domain| {
-                                matches!(
-                                    domain.status,
-                                    Status::TemporaryFailure(_) | Status::Scheduled
-                                )
-                            }) {
-                                let next_event = message.next_event().unwrap_or_default();
-                                message
-                                    .save_changes(&self.smtp, next_event.into(), prev_event.into())
-                                    .await;
-                            } else {
-                                message.remove(&self.smtp, prev_event).await;
-                            }
-                        }
+                        self.queue_cancel(message, |rcpt| rcpt.address_lcase.contains(item))
+                            .await
                     } else {
+                        let queue_id = message.id;
+                        let prev_event = message.next_event().unwrap_or_default();
                         message.remove(&self.smtp, prev_event).await;
-                        found = true;
-                    }
+                        self.smtp
+                            .timeline_complete(queue_id, Disposition::Removed)
+                            .await;
+                        true
+                    };
 
                     JsonResponse::new(json!({
                             "data": found,
@@ -506,9 +566,431 @@ impl JMAP {
                     RequestError::not_found().into_http_response()
                 }
             }
+            ("timeline", None, &Method::GET) => {
+                if let Some(message_id) = params.get("message-id") {
+                    let message_id = message_id
+                        .trim()
+                        .trim_start_matches('<')
+                        .trim_end_matches('>');
+                    let mut results = Vec::new();
+                    for queue_id in self.smtp.timeline_find(message_id).await {
+                        results.push(json!({
+                            "id": queue_id,
+                            "events": self.smtp.timeline_read(queue_id).await,
+                        }));
+                    }
+
+                    JsonResponse::new(json!({
+                            "data": results,
+                    }))
+                    .into_http_response()
+                } else {
+                    RequestError::invalid_parameters().into_http_response()
+                }
+            }
+            ("timeline", Some(queue_id), &Method::GET) => {
+                let events = self
+                    .smtp
+                    .timeline_read(queue_id.parse().unwrap_or_default())
+                    .await;
+                if !events.is_empty() {
+                    JsonResponse::new(json!({
+                            "data": events,
+                    }))
+                    .into_http_response()
+                } else {
+                    RequestError::not_found().into_http_response()
+                }
+            }
+            ("dead-letters", None, &Method::GET) => {
+                let text = params.get("text");
+                let page = params.parse::<usize>("page").unwrap_or_default();
+                let limit = params.parse::<usize>("limit").unwrap_or_default();
+                let values = params.has_key("values");
+
+                let mut result_ids = Vec::new();
+                let mut result_values = Vec::new();
+                let from_key = ValueKey::from(ValueClass::Queue(QueueClass::DeadLetter(0)));
+                let to_key = ValueKey::from(ValueClass::Queue(QueueClass::DeadLetter(u64::MAX)));
+                let mut offset = page.saturating_sub(1) * limit;
+                let mut total = 0;
+                let mut total_returned = 0;
+                let _ = self
+                    .core
+                    .storage
+                    .data
+                    .iterate(
+                        IterateParams::new(from_key, to_key).descending(),
+                        |key, value| {
+                            let dead_letter =
+                                Bincode::<queue::DeadLetter>::deserialize(value)?.inner;
+                            let matches = text.map_or(true, |text| {
+                                dead_letter.message.return_path.contains(text)
+                                    || dead_letter
+                                        .message
+                                        .recipients
+                                        .iter()
+                                        .any(|r| r.address_lcase.contains(text))
+                            });
+
+                            if matches {
+                                if offset == 0 {
+                                    if limit == 0 || total_returned < limit {
+                                        if values {
+                                            result_values.push(DeadLetter::from(&dead_letter));
+                                        } else {
+                                            result_ids.push(key.deserialize_be_u64(1)?);
+                                        }
+                                        total_returned += 1;
+                                    }
+                                } else {
+                                    offset -= 1;
+                                }
+
+                                total += 1;
+                            }
+
+                            Ok(true)
+                        },
+                    )
+                    .await;
+
+                if values {
+                    JsonResponse::new(json!({
+                            "data":{
+                                "items": result_values,
+                                "total": total,
+                            },
+                    }))
+                } else {
+                    JsonResponse::new(json!({
+                            "data": {
+                                "items": result_ids,
+                                "total": total,
+                            },
+                    }))
+                }
+                .into_http_response()
+            }
+            ("dead-letters", Some(queue_id), &Method::GET) => {
+                if let Some(dead_letter) = self
+                    .smtp
+                    .read_dead_letter(queue_id.parse().unwrap_or_default())
+                    .await
+                {
+                    if path.get(3).copied() == Some("contents") {
+                        // Download the raw message
+                        match self
+                            .core
+                            .storage
+                            .blob
+                            .get_blob(dead_letter.message.blob_hash.as_slice(), 0..usize::MAX)
+                            .await
+                        {
+                            Ok(Some(contents)) => Resource {
+                                content_type: "message/rfc822",
+                                contents,
+                            }
+                            .into_http_response(),
+                            Ok(None) => RequestError::not_found().into_http_response(),
+                            Err(err) => err.into_http_response(),
+                        }
+                    } else {
+                        JsonResponse::new(json!({
+                                "data": DeadLetter::from(&dead_letter),
+                        }))
+                        .into_http_response()
+                    }
+                } else {
+                    RequestError::not_found().into_http_response()
+                }
+            }
+            ("dead-letters", Some(queue_id), &Method::PATCH) => {
+                if let Some(dead_letter) = self
+                    .smtp
+                    .read_dead_letter(queue_id.parse().unwrap_or_default())
+                    .await
+                {
+                    JsonResponse::new(json!({
+                            "data": dead_letter.requeue(&self.smtp).await,
+                    }))
+                    .into_http_response()
+                } else {
+                    RequestError::not_found().into_http_response()
+                }
+            }
+            ("dead-letters", Some(queue_id), &Method::DELETE) => {
+                if let Some(dead_letter) = self
+                    .smtp
+                    .read_dead_letter(queue_id.parse().unwrap_or_default())
+                    .await
+                {
+                    JsonResponse::new(json!({
+                            "data": dead_letter.purge(&self.smtp).await,
+                    }))
+                    .into_http_response()
+                } else {
+                    RequestError::not_found().into_http_response()
+                }
+            }
+            ("dead-letters", None, &Method::DELETE) => {
+                // Purge all dead letters
+                let mut dead_letters = Vec::new();
+                let from_key = ValueKey::from(ValueClass::Queue(QueueClass::DeadLetter(0)));
+                let to_key = ValueKey::from(ValueClass::Queue(QueueClass::DeadLetter(u64::MAX)));
+                let _ = self
+                    .core
+                    .storage
+                    .data
+                    .iterate(
+                        IterateParams::new(from_key, to_key).ascending(),
+                        |_, value| {
+                            dead_letters
+                                .push(Bincode::<queue::DeadLetter>::deserialize(value)?.inner);
+
+                            Ok(true)
+                        },
+                    )
+                    .await;
+
+                let mut total = 0;
+                for dead_letter in dead_letters {
+                    if dead_letter.purge(&self.smtp).await {
+                        total += 1;
+                    }
+                }
+
+                JsonResponse::new(json!({
+                        "data": total,
+                }))
+                .into_http_response()
+            }
             _ => RequestError::not_found().into_http_response(),
         }
     }
+
+    async fn queue_retry(
+        &self,
+        mut message: queue::Message,
+        time: u64,
+        filter: impl Fn(&str) -> bool,
+    ) -> bool {
+        let prev_event = message.next_event().unwrap_or_default();
+        let mut found = false;
+
+        for domain in &mut message.domains {
+            if matches!(
+                domain.status,
+                Status::Scheduled | Status::TemporaryFailure(_)
+            ) && filter(&domain.domain)
+            {
+                domain.retry.due = time;
+                if domain.expires > time {
+                    domain.expires = time + 10;
+                }
+                found = true;
+            }
+        }
+
+        if found {
+            // Retrying a message on hold also releases it
+            message.flags &= !queue::MESSAGE_ON_HOLD;
+            let next_event = message.next_event().unwrap_or_default();
+            message
+                .save_changes(&self.smtp, prev_event.into(), next_event.into())
+                .await;
+            let _ = self.smtp.inner.queue_tx.send(queue::Event::Reload).await;
+        }
+
+        found
+    }
+
+    pub async fn handle_undo_send(
+        &self,
+        path: Vec<&str>,
+        access_token: Arc<AccessToken>,
+    ) -> HttpResponse {
+        let message = match self
+            .smtp
+            .read_message(
+                path.get(1)
+                    .and_then(|queue_id| queue_id.parse().ok())
+                    .unwrap_or(u64::MAX),
+            )
+            .await
+        {
+            Some(message) => message,
+            None => return RequestError::not_found().into_http_response(),
+        };
+
+        // Users can only cancel messages sent from one of their addresses
+        let emails = match self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(access_token.primary_id), false)
+            .await
+        {
+            Ok(Some(principal)) => principal.emails,
+            Ok(None) => Vec::new(),
+            Err(err) => return err.into_http_response(),
+        };
+        if !emails
+            .iter()
+            .any(|email| email.eq_ignore_ascii_case(&message.return_path_lcase))
+        {
+            return RequestError::not_found().into_http_response();
+        }
+
+        // Messages can only be cancelled before their first delivery attempt
+        let now = now();
+        if !message
+            .domains
+            .iter()
+            .all(|domain| matches!(domain.status, Status::Scheduled) && domain.retry.due > now)
+        {
+            return ManagementApiError::Other {
+                details: "The message has already been released for delivery".into(),
+            }
+            .into_http_response();
+        }
+
+        let queue_id = message.id;
+        let prev_event = message.next_event().unwrap_or_default();
+        message.remove(&self.smtp, prev_event).await;
+        self.smtp
+            .timeline_complete(queue_id, Disposition::Removed)
+            .await;
+
+        JsonResponse::new(json!({
+            "data": (),
+        }))
+        .into_http_response()
+    }
+
+    async fn queue_hold(&self, message: queue::Message) -> bool {
+        if !message.is_on_hold() {
+            let prev_event = message.next_event().unwrap_or_default();
+            message.hold(&self.smtp, prev_event).await
+        } else {
+            false
+        }
+    }
+
+    async fn queue_release(&self, message: queue::Message) -> bool {
+        if message.is_on_hold() {
+            message.release(&self.smtp).await
+        } else {
+            false
+        }
+    }
+
+    async fn queue_cancel(
+        &self,
+        mut message: queue::Message,
+        filter: impl Fn(&queue::Recipient) -> bool,
+    ) -> bool {
+        let prev_event = message.next_event().unwrap_or_default();
+        let mut found = false;
+
+        for rcpt in &mut message.recipients {
+            if filter(rcpt) {
+                rcpt.status = Status::PermanentFailure(HostResponse {
+                    hostname: ErrorDetails::default(),
+                    response: smtp_proto::Response {
+                        code: 0,
+                        esc: [0, 0, 0],
+                        message: "Delivery canceled.".to_string(),
+                    },
+                });
+                found = true;
+            }
+        }
+
+        if found {
+            // Mark as completed domains without any pending deliveries
+            for (domain_idx, domain) in message.domains.iter_mut().enumerate() {
+                if matches!(
+                    domain.status,
+                    Status::TemporaryFailure(_) | Status::Scheduled
+                ) {
+                    let mut total_rcpt = 0;
+                    let mut total_completed = 0;
+
+                    for rcpt in &message.recipients {
+                        if rcpt.domain_idx == domain_idx {
+                            total_rcpt += 1;
+                            if matches!(
+                                rcpt.status,
+                                Status::PermanentFailure(_) | Status::Completed(_)
+                            ) {
+                                total_completed += 1;
+                            }
+                        }
+                    }
+
+                    if total_rcpt == total_completed {
+                        domain.status = Status::Completed(());
+                    }
+                }
+            }
+
+            // Delete message if there are no pending deliveries
+            if message.domains.iter().any(|domain| {
+                matches!(
+                    domain.status,
+                    Status::TemporaryFailure(_) | Status::Scheduled
+                )
+            }) {
+                if message.is_on_hold() {
+                    message.save_changes(&self.smtp, None, None).await;
+                } else {
+                    let next_event = message.next_event().unwrap_or_default();
+                    message
+                        .save_changes(&self.smtp, prev_event.into(), next_event.into())
+                        .await;
+                }
+            } else {
+                message.remove(&self.smtp, prev_event).await;
+            }
+        }
+
+        found
+    }
+}
+
+#[derive(Clone, Copy)]
+enum QueueAction {
+    Retry,
+    Hold,
+    Release,
+}
+
+impl QueueAction {
+    fn parse(action: Option<&str>) -> Option<Self> {
+        match action.unwrap_or("retry") {
+            "retry" => Some(QueueAction::Retry),
+            "hold" => Some(QueueAction::Hold),
+            "release" => Some(QueueAction::Release),
+            _ => None,
+        }
+    }
+}
+
+fn message_has_status(message: &queue::Message, status: &str) -> bool {
+    if status == "on_hold" {
+        message.is_on_hold()
+    } else {
+        message.domains.iter().any(|domain| {
+            matches!(
+                (&domain.status, status),
+                (Status::Scheduled, "scheduled")
+                    | (Status::Completed(_), "completed")
+                    | (Status::TemporaryFailure(_), "temp_fail")
+                    | (Status::PermanentFailure(_), "perm_fail")
+            )
+        })
+    }
 }
 
 impl From<&queue::Message> for Message {
@@ -570,6 +1052,56 @@ impl From<&queue::Message> for Message {
                 })
                 .collect(),
             blob_hash: URL_SAFE_NO_PAD.encode::<&[u8]>(message.blob_hash.as_ref()),
+            on_hold: message.is_on_hold(),
+        }
+    }
+}
+
+impl From<&queue::DeadLetter> for DeadLetter {
+    fn from(dead_letter: &queue::DeadLetter) -> Self {
+        DeadLetter {
+            message: Message::from(&dead_letter.message),
+            archived: DateTime::from_timestamp(dead_letter.archived as i64),
+            failures: dead_letter
+                .message
+                .failures
+                .iter()
+                .map(|failure| Failure {
+                    timestamp: DateTime::from_timestamp(failure.timestamp as i64),
+                    domain: failure.domain.clone(),
+                    retry_num: failure.attempt,
+                    status: match &failure.status {
+                        Status::Scheduled => Status::Scheduled,
+                        Status::Completed(_) => Status::Completed(String::new()),
+                        Status::TemporaryFailure(status) => {
+                            Status::TemporaryFailure(status.to_string())
+                        }
+                        Status::PermanentFailure(status) => {
+                            Status::PermanentFailure(status.to_string())
+                        }
+                    },
+                    recipients: failure
+                        .recipients
+                        .iter()
+                        .map(|rcpt| Recipient {
+                            address: rcpt.address.clone(),
+                            status: match &rcpt.status {
+                                Status::Scheduled => Status::Scheduled,
+                                Status::Completed(status) => {
+                                    Status::Completed(status.response.to_string())
+                                }
+                                Status::TemporaryFailure(status) => {
+                                    Status::TemporaryFailure(status.response.to_string())
+                                }
+                                Status::PermanentFailure(status) => {
+                                    Status::PermanentFailure(status.response.to_string())
+                                }
+                            },
+                            orcpt: None,
+                        })
+                        .collect(),
+                })
+                .collect(),
         }
     }
 }
@@ -707,3 +1239,7 @@ where
 fn is_zero(num: &i16) -> bool {
     *num == 0
 }
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
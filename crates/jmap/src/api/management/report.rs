@@ -221,6 +221,7 @@ impl JMAP {
                             Ok(None) => RequestError::not_found().into_http_response(),
                             Err(err) => err.into_http_response(),
                         },
+                        ReportClass::Audit { .. } => RequestError::not_found().into_http_response(),
                     }
                 } else {
                     RequestError::not_found().into_http_response()
@@ -0,0 +1,447 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use hyper::{Method, StatusCode};
+use jmap_proto::{
+    error::{method::MethodError, request::RequestError},
+    object::Object,
+    types::{collection::Collection, property::Property, value::Value},
+};
+use mail_parser::DateTime;
+use serde::Serialize;
+use serde_json::json;
+use store::{query::Filter, write::log::ChangeLogBuilder};
+
+use crate::{
+    api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse},
+    sieve::set::ObjectBlobId,
+    JMAP,
+};
+
+use super::{decode_path_element, ManagementApiError};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SieveScriptResponse {
+    name: String,
+    is_active: bool,
+    size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    script: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SieveVersionResponse {
+    version: usize,
+    size: usize,
+    replaced_at: String,
+}
+
+impl JMAP {
+    pub async fn handle_manage_sieve(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+    ) -> HttpResponse {
+        // Obtain account id
+        let account_id = match path.get(1) {
+            Some(name) => {
+                match self
+                    .core
+                    .storage
+                    .data
+                    .get_account_id(decode_path_element(name).as_ref())
+                    .await
+                {
+                    Ok(Some(account_id)) => account_id,
+                    Ok(None) => {
+                        return RequestError::blank(
+                            StatusCode::NOT_FOUND.as_u16(),
+                            "Not found",
+                            "Account not found.",
+                        )
+                        .into_http_response();
+                    }
+                    Err(err) => {
+                        return err.into_http_response();
+                    }
+                }
+            }
+            None => return RequestError::not_found().into_http_response(),
+        };
+
+        let result = match (path.get(2).copied(), path.get(3).copied(), req.method()) {
+            (None, _, &Method::GET) => self.sieve_api_list(account_id).await,
+            (Some(name), None, &Method::GET) => {
+                self.sieve_api_get(account_id, decode_path_element(name).as_ref())
+                    .await
+            }
+            (Some(name), None, &Method::PUT) => {
+                self.sieve_api_put(
+                    account_id,
+                    decode_path_element(name).into_owned(),
+                    body.unwrap_or_default(),
+                )
+                .await
+            }
+            (Some(name), None, &Method::DELETE) => {
+                self.sieve_api_delete(account_id, decode_path_element(name).as_ref())
+                    .await
+            }
+            (Some("activate"), Some(name), &Method::POST) => {
+                self.sieve_api_activate(account_id, Some(decode_path_element(name).as_ref()))
+                    .await
+            }
+            (Some("deactivate"), None, &Method::POST) => {
+                self.sieve_api_activate(account_id, None).await
+            }
+            (Some("versions"), Some(name), &Method::GET) => {
+                self.sieve_api_versions(account_id, decode_path_element(name).as_ref())
+                    .await
+            }
+            (Some("restore"), Some(name), &Method::POST) => {
+                match path
+                    .get(4)
+                    .and_then(|version| version.parse::<usize>().ok())
+                {
+                    Some(version) if version > 0 => {
+                        self.sieve_api_restore(
+                            account_id,
+                            decode_path_element(name).as_ref(),
+                            version,
+                        )
+                        .await
+                    }
+                    _ => return RequestError::not_found().into_http_response(),
+                }
+            }
+            (Some("validate"), None, &Method::POST) => Ok(
+                match self
+                    .core
+                    .sieve
+                    .untrusted_compiler
+                    .compile(body.as_deref().unwrap_or_default())
+                {
+                    Ok(_) => JsonResponse::new(json!({
+                        "data": (),
+                    }))
+                    .into_http_response(),
+                    Err(err) => ManagementApiError::Other {
+                        details: err.to_string().into(),
+                    }
+                    .into_http_response(),
+                },
+            ),
+            _ => return RequestError::not_found().into_http_response(),
+        };
+
+        match result {
+            Ok(response) => response,
+            Err(MethodError::NotFound) => RequestError::blank(
+                StatusCode::NOT_FOUND.as_u16(),
+                "Not found",
+                "Script not found.",
+            )
+            .into_http_response(),
+            Err(_) => RequestError::internal_server_error().into_http_response(),
+        }
+    }
+
+    async fn sieve_api_list(&self, account_id: u32) -> Result<HttpResponse, MethodError> {
+        let mut scripts = Vec::new();
+        for document_id in self
+            .get_document_ids(account_id, Collection::SieveScript)
+            .await?
+            .unwrap_or_default()
+        {
+            if let Some(script) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::SieveScript,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                scripts.push(SieveScriptResponse::from(script));
+            }
+        }
+
+        Ok(JsonResponse::new(json!({
+            "data": scripts,
+        }))
+        .into_http_response())
+    }
+
+    async fn sieve_api_get(
+        &self,
+        account_id: u32,
+        name: &str,
+    ) -> Result<HttpResponse, MethodError> {
+        let document_id = self
+            .sieve_api_script_id(account_id, name)
+            .await?
+            .ok_or(MethodError::NotFound)?;
+        let script = self
+            .get_property::<Object<Value>>(
+                account_id,
+                Collection::SieveScript,
+                document_id,
+                Property::Value,
+            )
+            .await?
+            .ok_or(MethodError::NotFound)?;
+        let blob_id = script.blob_id().ok_or(MethodError::ServerPartialFail)?;
+        let contents = self
+            .get_blob_section(
+                &blob_id.hash,
+                blob_id
+                    .section
+                    .as_ref()
+                    .ok_or(MethodError::ServerPartialFail)?,
+            )
+            .await?
+            .ok_or(MethodError::NotFound)?;
+        let mut response = SieveScriptResponse::from(script);
+        response.script = String::from_utf8_lossy(&contents).into_owned().into();
+
+        Ok(JsonResponse::new(json!({
+            "data": response,
+        }))
+        .into_http_response())
+    }
+
+    async fn sieve_api_put(
+        &self,
+        account_id: u32,
+        name: String,
+        mut script: Vec<u8>,
+    ) -> Result<HttpResponse, MethodError> {
+        // Validate name and size
+        let name = name.trim().to_string();
+        let details = if name.is_empty() {
+            "Script name cannot be empty."
+        } else if name.len() > self.core.jmap.sieve_max_script_name {
+            "Script name is too long."
+        } else if name.eq_ignore_ascii_case("vacation") {
+            "The 'vacation' name is reserved, please use a different name."
+        } else if script.len() > self.core.managesieve.max_script_size {
+            "Script is too large."
+        } else {
+            ""
+        };
+        if !details.is_empty() {
+            return Ok(ManagementApiError::Other {
+                details: details.into(),
+            }
+            .into_http_response());
+        }
+
+        // Compile script
+        let script_size = script.len();
+        match self.core.sieve.untrusted_compiler.compile(&script) {
+            Ok(compiled_script) => {
+                script.extend(bincode::serialize(&compiled_script).unwrap_or_default());
+            }
+            Err(err) => {
+                return Ok(ManagementApiError::Other {
+                    details: err.to_string().into(),
+                }
+                .into_http_response());
+            }
+        }
+
+        // Make sure the account does not exceed the maximum number of scripts
+        let document_id = self.sieve_api_script_id(account_id, &name).await?;
+        if document_id.is_none()
+            && self
+                .get_document_ids(account_id, Collection::SieveScript)
+                .await?
+                .map_or(0, |ids| ids.len() as usize)
+                >= self.core.jmap.sieve_max_scripts
+        {
+            return Ok(ManagementApiError::Other {
+                details: "Too many scripts.".into(),
+            }
+            .into_http_response());
+        }
+
+        self.sieve_script_put(account_id, document_id, name, script, script_size)
+            .await?;
+
+        Ok(JsonResponse::new(json!({
+            "data": (),
+        }))
+        .into_http_response())
+    }
+
+    async fn sieve_api_delete(
+        &self,
+        account_id: u32,
+        name: &str,
+    ) -> Result<HttpResponse, MethodError> {
+        let document_id = self
+            .sieve_api_script_id(account_id, name)
+            .await?
+            .ok_or(MethodError::NotFound)?;
+
+        if self
+            .sieve_script_delete(account_id, document_id, true)
+            .await?
+        {
+            let mut changelog = ChangeLogBuilder::new();
+            changelog.log_delete(Collection::SieveScript, document_id);
+            self.commit_changes(account_id, changelog).await?;
+
+            Ok(JsonResponse::new(json!({
+                "data": (),
+            }))
+            .into_http_response())
+        } else {
+            Ok(ManagementApiError::Other {
+                details: "Active scripts cannot be deleted.".into(),
+            }
+            .into_http_response())
+        }
+    }
+
+    async fn sieve_api_activate(
+        &self,
+        account_id: u32,
+        name: Option<&str>,
+    ) -> Result<HttpResponse, MethodError> {
+        let document_id = if let Some(name) = name {
+            Some(
+                self.sieve_api_script_id(account_id, name)
+                    .await?
+                    .ok_or(MethodError::NotFound)?,
+            )
+        } else {
+            None
+        };
+
+        let changes = self.sieve_activate_script(account_id, document_id).await?;
+        if !changes.is_empty() {
+            let mut changelog = ChangeLogBuilder::new();
+            for (document_id, _) in changes {
+                changelog.log_update(Collection::SieveScript, document_id);
+            }
+            self.commit_changes(account_id, changelog).await?;
+        }
+
+        Ok(JsonResponse::new(json!({
+            "data": (),
+        }))
+        .into_http_response())
+    }
+
+    async fn sieve_api_versions(
+        &self,
+        account_id: u32,
+        name: &str,
+    ) -> Result<HttpResponse, MethodError> {
+        let document_id = self
+            .sieve_api_script_id(account_id, name)
+            .await?
+            .ok_or(MethodError::NotFound)?;
+        let versions = self
+            .sieve_script_versions(account_id, document_id)
+            .await?
+            .into_iter()
+            .enumerate()
+            .map(|(pos, version)| SieveVersionResponse {
+                version: pos + 1,
+                size: version.size,
+                replaced_at: DateTime::from_timestamp(version.replaced_at as i64).to_rfc3339(),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(JsonResponse::new(json!({
+            "data": versions,
+        }))
+        .into_http_response())
+    }
+
+    async fn sieve_api_restore(
+        &self,
+        account_id: u32,
+        name: &str,
+        version: usize,
+    ) -> Result<HttpResponse, MethodError> {
+        let document_id = self
+            .sieve_api_script_id(account_id, name)
+            .await?
+            .ok_or(MethodError::NotFound)?;
+
+        match self
+            .sieve_restore_version(account_id, document_id, version - 1)
+            .await?
+        {
+            Ok(_) => Ok(JsonResponse::new(json!({
+                "data": (),
+            }))
+            .into_http_response()),
+            Err(details) => Ok(ManagementApiError::Other {
+                details: details.into(),
+            }
+            .into_http_response()),
+        }
+    }
+
+    async fn sieve_api_script_id(
+        &self,
+        account_id: u32,
+        name: &str,
+    ) -> Result<Option<u32>, MethodError> {
+        self.filter(
+            account_id,
+            Collection::SieveScript,
+            vec![Filter::eq(Property::Name, name)],
+        )
+        .await
+        .map(|r| r.results.min())
+    }
+}
+
+impl From<Object<Value>> for SieveScriptResponse {
+    fn from(mut script: Object<Value>) -> Self {
+        SieveScriptResponse {
+            size: script
+                .blob_id()
+                .and_then(|blob_id| blob_id.section.as_ref())
+                .map_or(0, |section| section.size),
+            name: script
+                .properties
+                .remove(&Property::Name)
+                .and_then(|name| name.try_unwrap_string())
+                .unwrap_or_default(),
+            is_active: matches!(
+                script.properties.get(&Property::IsActive),
+                Some(Value::Bool(true))
+            ),
+            script: None,
+        }
+    }
+}
@@ -88,6 +88,13 @@ impl JMAP {
                 }))
                 .await
             }
+            (Some("purge"), Some("tiering"), _, &Method::GET) => {
+                self.housekeeper_request(Event::Purge(PurgeType::BlobTiering {
+                    store: self.core.storage.data.clone(),
+                    blob_store: self.core.storage.blob.clone(),
+                }))
+                .await
+            }
             (Some("purge"), Some("data"), id, &Method::GET) => {
                 let store = if let Some(id) = id {
                     if let Some(store) = self.core.storage.stores.get(id) {
@@ -130,6 +137,25 @@ impl JMAP {
                 self.housekeeper_request(Event::Purge(PurgeType::Account(account_id)))
                     .await
             }
+            (Some("repair"), id, _, &Method::GET) => {
+                let account_id = if let Some(id) = id {
+                    if let Ok(account_id) = id.parse::<u32>() {
+                        account_id.into()
+                    } else {
+                        return RequestError::invalid_parameters().into_http_response();
+                    }
+                } else {
+                    None
+                };
+
+                match self.repair_accounts(account_id).await {
+                    Ok(results) => JsonResponse::new(json!({
+                        "data": results,
+                    }))
+                    .into_http_response(),
+                    Err(_) => RequestError::internal_server_error().into_http_response(),
+                }
+            }
             _ => RequestError::not_found().into_http_response(),
         }
     }
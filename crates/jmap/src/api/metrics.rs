@@ -0,0 +1,82 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use common::manager::webadmin::Resource;
+use store::{
+    write::{QueueClass, ValueClass},
+    IterateParams, ValueKey,
+};
+use utils::metrics::{render_gauge, render_prometheus};
+
+use crate::{api::http::ToHttpResponse, JMAP};
+
+use super::HttpResponse;
+
+impl JMAP {
+    pub async fn handle_prometheus_metrics(&self) -> HttpResponse {
+        let mut metrics = render_prometheus();
+
+        // Queue size is obtained from the store on each scrape
+        let mut queue_size = 0;
+        match self
+            .core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Queue(QueueClass::Message(0))),
+                    ValueKey::from(ValueClass::Queue(QueueClass::Message(u64::MAX))),
+                )
+                .no_values(),
+                |_, _| {
+                    queue_size += 1;
+                    Ok(true)
+                },
+            )
+            .await
+        {
+            Ok(_) => {
+                render_gauge(
+                    &mut metrics,
+                    "stalwart_queue_messages",
+                    "Number of messages in the delivery queue.",
+                    queue_size,
+                );
+            }
+            Err(err) => {
+                tracing::warn!(
+                    context = "metrics",
+                    event = "error",
+                    reason = %err,
+                    "Failed to obtain queue size."
+                );
+            }
+        }
+
+        Resource {
+            content_type: "text/plain; version=0.0.4",
+            contents: metrics.into_bytes(),
+        }
+        .into_http_response()
+    }
+}
@@ -32,6 +32,7 @@ pub mod autoconfig;
 pub mod event_source;
 pub mod http;
 pub mod management;
+pub mod metrics;
 pub mod request;
 pub mod session;
 
@@ -24,6 +24,7 @@
 use std::sync::Arc;
 
 use common::listener::ServerInstance;
+use dav::DavResourceType;
 use jmap_proto::{
     error::{method::MethodError, request::RequestError},
     method::{
@@ -44,6 +45,9 @@ impl JMAP {
         access_token: Arc<AccessToken>,
         instance: &Arc<ServerInstance>,
     ) -> Result<Response, RequestError> {
+        self.is_request_cost_allowed(&access_token, &request)
+            .await?;
+
         let mut response = Response::new(
             access_token.state(),
             request.created_ids.unwrap_or_default(),
@@ -193,6 +197,25 @@ impl JMAP {
                         .await?
                         .into()
                 }
+                get::RequestArguments::SavedSearch => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.saved_search_get(req).await?.into()
+                }
+                get::RequestArguments::CalendarEvent => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.dav_object_get(req, DavResourceType::Calendar)
+                        .await?
+                        .into()
+                }
+                get::RequestArguments::ContactCard => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.dav_object_get(req, DavResourceType::AddressBook)
+                        .await?
+                        .into()
+                }
             },
             RequestMethod::Query(mut req) => match req.take_arguments() {
                 query::RequestArguments::Email(arguments) => {
@@ -233,6 +256,20 @@ impl JMAP {
 
                     self.quota_query(req, access_token).await?.into()
                 }
+                query::RequestArguments::CalendarEvent => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.dav_object_query(req, DavResourceType::Calendar)
+                        .await?
+                        .into()
+                }
+                query::RequestArguments::ContactCard => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.dav_object_query(req, DavResourceType::AddressBook)
+                        .await?
+                        .into()
+                }
             },
             RequestMethod::Set(mut req) => match req.take_arguments() {
                 set::RequestArguments::Email => {
@@ -274,6 +311,25 @@ impl JMAP {
 
                     self.vacation_response_set(req).await?.into()
                 }
+                set::RequestArguments::SavedSearch => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.saved_search_set(req).await?.into()
+                }
+                set::RequestArguments::CalendarEvent => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.dav_object_set(req, access_token, DavResourceType::Calendar)
+                        .await?
+                        .into()
+                }
+                set::RequestArguments::ContactCard => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.dav_object_set(req, access_token, DavResourceType::AddressBook)
+                        .await?
+                        .into()
+                }
             },
             RequestMethod::Changes(req) => self.changes(req, access_token).await?.into(),
             RequestMethod::Copy(req) => {
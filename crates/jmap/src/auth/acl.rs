@@ -384,7 +384,6 @@ impl JMAP {
         current: &Option<HashedValue<Object<Value>>>,
     ) {
         if let Value::Acl(acl_changes) = changes.get(&Property::Acl) {
-            let access_tokens = &self.inner.access_tokens;
             if let Some(Value::Acl(acl_current)) = current
                 .as_ref()
                 .and_then(|current| current.inner.properties.get(&Property::Acl))
@@ -398,7 +397,7 @@ impl JMAP {
                         }
                     }
                     if invalidate {
-                        access_tokens.remove(&current_item.account_id);
+                        self.invalidate_access_tokens(current_item.account_id);
                     }
                 }
 
@@ -411,12 +410,12 @@ impl JMAP {
                         }
                     }
                     if invalidate {
-                        access_tokens.remove(&change_item.account_id);
+                        self.invalidate_access_tokens(change_item.account_id);
                     }
                 }
             } else {
                 for value in acl_changes {
-                    access_tokens.remove(&value.account_id);
+                    self.invalidate_access_tokens(value.account_id);
                 }
             }
         }
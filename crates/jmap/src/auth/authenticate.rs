@@ -23,7 +23,11 @@
 
 use std::{net::IpAddr, sync::Arc, time::Instant};
 
-use common::{listener::limiter::InFlight, AuthResult};
+use common::{
+    listener::limiter::InFlight,
+    sasl::{SaslExchange, SaslStep},
+    AuthResult,
+};
 use directory::{Principal, QueryBy};
 use hyper::header;
 use jmap_proto::error::request::RequestError;
@@ -40,6 +44,7 @@ impl JMAP {
         &self,
         req: &hyper::Request<hyper::body::Incoming>,
         remote_ip: IpAddr,
+        protocol: &str,
     ) -> Result<Option<(InFlight, Arc<AccessToken>)>, RequestError> {
         if let Some((mechanism, token)) = req
             .headers()
@@ -47,8 +52,17 @@ impl JMAP {
             .and_then(|h| h.to_str().ok())
             .and_then(|h| h.split_once(' ').map(|(l, t)| (l, t.trim().to_string())))
         {
-            let session = if let Some(account_id) = self.inner.sessions.get_with_ttl(&token) {
-                self.get_cached_access_token(account_id).await
+            // Sessions are cached per protocol, application passwords are limited to some of them
+            let session_id = format!("{protocol}:{token}");
+            let session = match self.inner.sessions.get_with_ttl(&session_id) {
+                Some((account_id, None)) => self.get_cached_access_token(account_id).await,
+                // Scoped tokens can't be refreshed without the application password,
+                // the request is authenticated again once they expire.
+                Some(key) => self.inner.access_tokens.get_with_ttl(&key),
+                None => None,
+            };
+            let session = if session.is_some() {
+                session
             } else {
                 if mechanism.eq_ignore_ascii_case("basic") {
                     // Enforce rate limit for authentication requests
@@ -98,9 +112,22 @@ impl JMAP {
                     self.is_anonymous_allowed(&remote_ip).await?;
                     None
                 }
+                .filter(|access_token| {
+                    if access_token.is_protocol_allowed(protocol) {
+                        true
+                    } else {
+                        tracing::debug!(
+                            context = "authenticate_headers",
+                            account_id = access_token.primary_id(),
+                            protocol = protocol,
+                            "Account is not allowed to access this protocol."
+                        );
+                        false
+                    }
+                })
                 .map(|access_token| {
                     let access_token = Arc::new(access_token);
-                    self.cache_session(token, &access_token);
+                    self.cache_session(session_id, &access_token);
                     self.cache_access_token(access_token.clone());
                     access_token
                 })
@@ -123,21 +150,27 @@ impl JMAP {
     pub fn cache_session(&self, session_id: String, access_token: &AccessToken) {
         self.inner.sessions.insert_with_ttl(
             session_id,
-            access_token.primary_id(),
+            access_token.cache_key(),
             Instant::now() + self.core.jmap.session_cache_ttl,
         );
     }
 
     pub fn cache_access_token(&self, access_token: Arc<AccessToken>) {
         self.inner.access_tokens.insert_with_ttl(
-            access_token.primary_id(),
+            access_token.cache_key(),
             access_token,
             Instant::now() + self.core.jmap.session_cache_ttl,
         );
     }
 
+    pub fn invalidate_access_tokens(&self, primary_id: u32) {
+        self.inner
+            .access_tokens
+            .retain(|(account_id, _), _| *account_id != primary_id);
+    }
+
     pub async fn get_cached_access_token(&self, primary_id: u32) -> Option<Arc<AccessToken>> {
-        if let Some(access_token) = self.inner.access_tokens.get_with_ttl(&primary_id) {
+        if let Some(access_token) = self.inner.access_tokens.get_with_ttl(&(primary_id, None)) {
             access_token.into()
         } else {
             // Refresh ACL token
@@ -168,7 +201,12 @@ impl JMAP {
             )
             .await
         {
-            Ok(AuthResult::Success(principal)) => AuthResult::Success(AccessToken::new(principal)),
+            Ok(AuthResult::Success(principal)) => {
+                let scope = principal
+                    .app_password(secret)
+                    .map(|app_password| app_password.name);
+                AuthResult::Success(AccessToken::new(principal).with_scope(scope))
+            }
             Ok(AuthResult::Failure) => {
                 let _ = self.is_auth_allowed_hard(&remote_ip).await;
                 AuthResult::Failure
@@ -178,6 +216,59 @@ impl JMAP {
         }
     }
 
+    pub async fn authenticate_apop(
+        &self,
+        username: &str,
+        timestamp: &str,
+        digest: &str,
+        remote_ip: IpAddr,
+    ) -> AuthResult<AccessToken> {
+        match self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Name(username), true)
+            .await
+        {
+            Ok(Some(principal)) if principal.verify_apop(timestamp, digest) => {
+                AuthResult::Success(AccessToken::new(principal))
+            }
+            Ok(_) => match self.core.auth_failure(remote_ip, username).await {
+                Ok(AuthResult::Banned) => AuthResult::Banned,
+                _ => {
+                    let _ = self.is_auth_allowed_hard(&remote_ip).await;
+                    AuthResult::Failure
+                }
+            },
+            Err(_) => AuthResult::Failure,
+        }
+    }
+
+    pub async fn authenticate_sasl(
+        &self,
+        exchange: &mut SaslExchange,
+        response: Option<&[u8]>,
+        remote_ip: IpAddr,
+    ) -> SaslStep<AuthResult<AccessToken>> {
+        match self
+            .core
+            .sasl_step(&self.core.storage.directory, exchange, response, remote_ip)
+            .await
+        {
+            SaslStep::Challenge(challenge) => SaslStep::Challenge(challenge),
+            SaslStep::Done(Ok(AuthResult::Success(principal))) => {
+                SaslStep::Done(AuthResult::Success(AccessToken::new(principal)))
+            }
+            SaslStep::Done(Ok(AuthResult::Failure)) => {
+                let _ = self.is_auth_allowed_hard(&remote_ip).await;
+                SaslStep::Done(AuthResult::Failure)
+            }
+            SaslStep::Done(Ok(AuthResult::Banned)) => SaslStep::Done(AuthResult::Banned),
+            SaslStep::Done(Err(_)) => SaslStep::Done(AuthResult::Failure),
+            SaslStep::Invalid(err) => SaslStep::Invalid(err),
+        }
+    }
+
     pub async fn get_access_token(&self, account_id: u32) -> Option<AccessToken> {
         match self
             .core
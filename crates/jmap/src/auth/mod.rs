@@ -52,9 +52,15 @@ pub struct AccessToken {
     pub name: String,
     pub description: Option<String>,
     pub quota: u64,
+    pub protocols: Vec<String>,
     pub is_superuser: bool,
+    // Application password the token was issued for, if any
+    pub scope: Option<String>,
 }
 
+// Tokens restricted to an application password are cached apart from full logins
+pub type AccessTokenKey = (u32, Option<String>);
+
 impl AccessToken {
     pub fn new(principal: Principal<u32>) -> Self {
         Self {
@@ -64,10 +70,16 @@ impl AccessToken {
             name: principal.name,
             description: principal.description,
             quota: principal.quota,
+            protocols: principal.protocols,
             is_superuser: principal.typ == Type::Superuser,
+            scope: None,
         }
     }
 
+    pub fn with_scope(self, scope: Option<String>) -> Self {
+        Self { scope, ..self }
+    }
+
     pub fn with_access_to(self, access_to: Vec<(u32, Bitmap<Collection>)>) -> Self {
         Self { access_to, ..self }
     }
@@ -84,6 +96,10 @@ impl AccessToken {
         self.primary_id
     }
 
+    pub fn cache_key(&self) -> AccessTokenKey {
+        (self.primary_id, self.scope.clone())
+    }
+
     pub fn secondary_ids(&self) -> impl Iterator<Item = &u32> {
         self.member_of
             .iter()
@@ -102,6 +118,14 @@ impl AccessToken {
         self.is_superuser
     }
 
+    pub fn is_protocol_allowed(&self, protocol: &str) -> bool {
+        self.protocols.is_empty()
+            || self
+                .protocols
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(protocol))
+    }
+
     pub fn is_shared(&self, account_id: u32) -> bool {
         !self.is_member(account_id) && self.access_to.iter().any(|(id, _)| *id == account_id)
     }
@@ -23,8 +23,16 @@
 
 use std::{net::IpAddr, sync::Arc};
 
-use common::listener::limiter::{ConcurrencyLimiter, InFlight};
-use jmap_proto::error::request::{RequestError, RequestLimitError};
+use common::{
+    config::jmap::settings::RequestCost,
+    listener::limiter::{ConcurrencyLimiter, InFlight},
+};
+use jmap_proto::{
+    error::request::{RequestError, RequestLimitError},
+    method::query::Filter,
+    request::{Request, RequestMethod},
+};
+use store::fts::{FilterItem, FilterType};
 
 use crate::JMAP;
 
@@ -62,7 +70,7 @@ impl JMAP {
         access_token: &AccessToken,
     ) -> Result<InFlight, RequestError> {
         let limiter = self.get_concurrency_limiter(access_token.primary_id());
-        let is_rate_allowed = if let Some(rate) = &self.core.jmap.rate_authenticated {
+        let retry_after = if let Some(rate) = &self.core.jmap.rate_authenticated {
             self.core
                 .storage
                 .lookup
@@ -73,38 +81,85 @@ impl JMAP {
                 )
                 .await
                 .map_err(|_| RequestError::internal_server_error())?
-                .is_none()
         } else {
-            true
+            None
         };
 
-        if is_rate_allowed {
-            if let Some(in_flight_request) = limiter.concurrent_requests.is_allowed() {
-                Ok(in_flight_request)
-            } else if access_token.is_super_user() {
+        if let Some(retry_after) = retry_after {
+            if access_token.is_super_user() {
                 Ok(InFlight::default())
             } else {
-                Err(RequestError::limit(RequestLimitError::ConcurrentRequest))
+                Err(RequestError::too_many_requests().with_retry_after(retry_after))
             }
+        } else if let Some(in_flight_request) = limiter.concurrent_requests.is_allowed() {
+            Ok(in_flight_request)
         } else if access_token.is_super_user() {
             Ok(InFlight::default())
         } else {
-            Err(RequestError::too_many_requests())
+            Err(RequestError::limit(RequestLimitError::ConcurrentRequest))
+        }
+    }
+
+    /// Charges the cost of a request against the account's budget, rejecting
+    /// it when the budget for the current period has been exhausted.
+    pub async fn is_request_cost_allowed(
+        &self,
+        access_token: &AccessToken,
+        request: &Request,
+    ) -> Result<(), RequestError> {
+        let config = match &self.core.jmap.request_cost {
+            Some(config) if !access_token.is_super_user() => config,
+            _ => return Ok(()),
+        };
+
+        // Requests costing more than the whole budget are charged the full
+        // budget so they can still go through once per period
+        let cost = request
+            .method_calls
+            .iter()
+            .map(|call| method_cost(config, &call.method))
+            .sum::<u64>()
+            .min(config.budget.requests);
+
+        if let Some(retry_after) = self
+            .core
+            .storage
+            .lookup
+            .is_cost_allowed(
+                format!("jc:{}", access_token.primary_id).as_bytes(),
+                &config.budget,
+                cost,
+                false,
+            )
+            .await
+            .map_err(|_| RequestError::internal_server_error())?
+        {
+            tracing::debug!(
+                context = "jmap",
+                event = "rate-limit",
+                account_id = access_token.primary_id,
+                cost = cost,
+                retry_after = retry_after,
+                "Request cost budget exceeded."
+            );
+
+            Err(RequestError::too_many_requests().with_retry_after(retry_after))
+        } else {
+            Ok(())
         }
     }
 
     pub async fn is_anonymous_allowed(&self, addr: &IpAddr) -> Result<(), RequestError> {
         if let Some(rate) = &self.core.jmap.rate_anonymous {
-            if self
+            if let Some(retry_after) = self
                 .core
                 .storage
                 .lookup
                 .is_rate_allowed(format!("jreq:{}", addr).as_bytes(), rate, false)
                 .await
                 .map_err(|_| RequestError::internal_server_error())?
-                .is_some()
             {
-                return Err(RequestError::too_many_requests());
+                return Err(RequestError::too_many_requests().with_retry_after(retry_after));
             }
         }
         Ok(())
@@ -159,6 +214,37 @@ impl JMAP {
     }
 }
 
+fn method_cost(config: &RequestCost, method: &RequestMethod) -> u64 {
+    match method {
+        RequestMethod::Get(_) => config.get,
+        RequestMethod::Set(_) => config.set,
+        RequestMethod::Changes(_) => config.changes,
+        RequestMethod::Copy(_) | RequestMethod::CopyBlob(_) => config.copy,
+        RequestMethod::ImportEmail(_) => config.import,
+        RequestMethod::ParseEmail(_) => config.parse,
+        RequestMethod::QueryChanges(req) => config.query_changes + filter_cost(config, &req.filter),
+        RequestMethod::Query(req) => config.query + filter_cost(config, &req.filter),
+        RequestMethod::SearchSnippet(req) => {
+            config.search_snippet + filter_cost(config, &req.filter)
+        }
+        RequestMethod::ValidateScript(_) => config.validate,
+        RequestMethod::LookupBlob(_) | RequestMethod::UploadBlob(_) => config.blob,
+        RequestMethod::Echo(_) => config.echo,
+        RequestMethod::Error(_) => 0,
+    }
+}
+
+fn filter_cost(config: &RequestCost, filter: &[Filter]) -> u64 {
+    filter
+        .iter()
+        .map(|item| match item.filter_type() {
+            FilterType::Fts => config.filter_full_text,
+            FilterType::Store => config.filter_condition,
+            FilterType::And | FilterType::Or | FilterType::Not | FilterType::End => 0,
+        })
+        .sum()
+}
+
 impl ConcurrencyLimiters {
     pub fn is_active(&self) -> bool {
         self.concurrent_requests.is_active() || self.concurrent_uploads.is_active()
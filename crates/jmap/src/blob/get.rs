@@ -279,6 +279,11 @@ impl JMAP {
                         }
                     }
 
+                    // Every requested type is listed, even if no objects reference the blob
+                    for type_name in &type_names {
+                        matched_ids.get_mut_or_insert(*type_name);
+                    }
+
                     response.list.push(BlobInfo { id, matched_ids });
                 }
                 _ => response.not_found.push(id),
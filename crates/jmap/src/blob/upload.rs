@@ -317,7 +317,7 @@ impl JMAP {
 
             // Commit blob
             let mut batch = BatchBuilder::new();
-            batch.set(BlobOp::Commit { hash: hash.clone() }, Vec::new());
+            batch.set(BlobOp::Commit { hash: hash.clone() }, now().serialize());
             self.write_batch(batch).await?;
         }
 
@@ -62,10 +62,25 @@ impl JMAP {
 
                 Collection::EmailSubmission
             }
+            RequestArguments::SavedSearch => {
+                access_token.assert_is_member(request.account_id)?;
+
+                Collection::SavedSearch
+            }
+            RequestArguments::CalendarEvent => {
+                access_token.assert_is_member(request.account_id)?;
+
+                Collection::CalendarEvent
+            }
+            RequestArguments::ContactCard => {
+                access_token.assert_is_member(request.account_id)?;
+
+                Collection::ContactCard
+            }
             RequestArguments::Quota => {
                 access_token.assert_is_member(request.account_id)?;
 
-                return Err(MethodError::CannotCalculateChanges);
+                return self.quota_changes(request, access_token).await;
             }
         };
 
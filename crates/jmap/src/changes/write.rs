@@ -93,6 +93,11 @@ impl JMAP {
             Collection::Thread,
             Collection::Identity,
             Collection::EmailSubmission,
+            Collection::SavedSearch,
+            Collection::Calendar,
+            Collection::CalendarEvent,
+            Collection::AddressBook,
+            Collection::ContactCard,
         ] {
             self.core
                 .storage
@@ -0,0 +1,475 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use dav::{
+    request::{Ace, DavProperty, MkCol, PropPatch},
+    response::{Condition, MultiStatus, PropStat, PropValue, Response},
+    DavPath, DavResourceType,
+};
+use directory::QueryBy;
+use hyper::StatusCode;
+use jmap_proto::{
+    error::method::MethodError,
+    object::{
+        index::{IndexAs, IndexProperty, ObjectIndexBuilder},
+        Object,
+    },
+    types::{
+        acl::Acl,
+        property::Property,
+        value::{AclGrant, Value},
+    },
+};
+use store::{
+    query::Filter,
+    roaring::RoaringBitmap,
+    write::{
+        assert::HashedValue,
+        log::{Changes, LogInsert},
+        BatchBuilder, F_VALUE,
+    },
+};
+use utils::map::bitmap::Bitmap;
+
+use crate::{auth::AccessToken, JMAP};
+
+use super::{DavAccount, DavResponse};
+
+pub static SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::Name)
+        .index_as(IndexAs::Text {
+            tokenize: false,
+            index: true,
+        })
+        .max_size(255)
+        .required(),
+    IndexProperty::new(Property::Acl).index_as(IndexAs::Acl),
+];
+
+impl JMAP {
+    pub async fn dav_collection(
+        &self,
+        account: &DavAccount,
+        name: &str,
+    ) -> Result<Option<(u32, HashedValue<Object<Value>>)>, MethodError> {
+        if let Some(document_id) = self
+            .filter(
+                account.account_id,
+                account.container(),
+                vec![Filter::eq(Property::Name, name)],
+            )
+            .await?
+            .results
+            .min()
+        {
+            Ok(self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account.account_id,
+                    account.container(),
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+                .map(|collection| (document_id, collection)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the collections in the account, creating a default calendar or
+    /// address book the first time the owner accesses their home.
+    pub async fn dav_collection_ids(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+    ) -> Result<RoaringBitmap, MethodError> {
+        let document_ids = self
+            .get_document_ids(account.account_id, account.container())
+            .await?
+            .unwrap_or_default();
+        if document_ids.is_empty() && access_token.is_primary_id(account.account_id) {
+            let display_name = match account.resource_type {
+                DavResourceType::Calendar => &self.core.dav.default_calendar_name,
+                DavResourceType::AddressBook => &self.core.dav.default_addressbook_name,
+            };
+            let document_id = self
+                .dav_collection_create(account, "default", Some(display_name.clone()))
+                .await?;
+            Ok(RoaringBitmap::from_iter([document_id]))
+        } else {
+            Ok(document_ids)
+        }
+    }
+
+    pub async fn dav_mkcol(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        name: &str,
+        request: MkCol,
+    ) -> Result<DavResponse, MethodError> {
+        // Only members of the account can create collections
+        if !access_token.is_member(account.account_id) {
+            return Ok(DavResponse::new(StatusCode::FORBIDDEN));
+        } else if name.len() > 255 {
+            return Ok(
+                DavResponse::new(StatusCode::FORBIDDEN).with_text("Collection name is too long.")
+            );
+        } else if self.dav_collection(account, name).await?.is_some() {
+            return Ok(DavResponse::method_not_allowed());
+        }
+
+        self.dav_collection_create(account, name, request.display_name)
+            .await?;
+
+        Ok(DavResponse::new(StatusCode::CREATED))
+    }
+
+    pub async fn dav_proppatch(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        name: &str,
+        request: PropPatch,
+    ) -> Result<DavResponse, MethodError> {
+        let (document_id, collection) = match self.dav_collection(account, name).await? {
+            Some(collection) => collection,
+            None => return Ok(DavResponse::new(StatusCode::NOT_FOUND)),
+        };
+        if !self
+            .dav_privileges(access_token, account.account_id, &collection.inner)
+            .contains(Acl::Modify)
+        {
+            return Ok(DavResponse::new(StatusCode::FORBIDDEN));
+        }
+
+        // Only the display name can be modified, and property updates are
+        // atomic: if any property fails, all other changes are rejected.
+        let mut display_name = None;
+        let mut applied = Vec::new();
+        let mut failed = Vec::new();
+        for (property, value) in request
+            .set
+            .into_iter()
+            .map(|(property, value)| (property, Value::Text(value)))
+            .chain(request.remove.into_iter().map(|p| (p, Value::Null)))
+        {
+            if property == DavProperty::DisplayName {
+                display_name = Some(value);
+                applied.push((property, PropValue::Empty));
+            } else {
+                failed.push((property, PropValue::Empty));
+            }
+        }
+
+        let propstat = if failed.is_empty() {
+            if let Some(display_name) = display_name {
+                let change_id = self.assign_change_id(account.account_id).await?;
+                let mut batch = BatchBuilder::new();
+                batch
+                    .with_change_id(change_id)
+                    .with_account_id(account.account_id)
+                    .with_collection(account.container())
+                    .update_document(document_id)
+                    .log(Changes::update([document_id]))
+                    .value(Property::Cid, change_id, F_VALUE)
+                    .custom(
+                        ObjectIndexBuilder::new(SCHEMA)
+                            .with_current(collection)
+                            .with_changes(
+                                Object::with_capacity(1)
+                                    .with_property(Property::Description, display_name),
+                            ),
+                    );
+                self.write_batch(batch).await?;
+            }
+
+            vec![PropStat {
+                status: 200,
+                properties: applied,
+            }]
+        } else {
+            let mut propstat = vec![PropStat {
+                status: 403,
+                properties: failed,
+            }];
+            if !applied.is_empty() {
+                propstat.push(PropStat {
+                    status: 424,
+                    properties: applied,
+                });
+            }
+            propstat
+        };
+
+        Ok(DavResponse::multi_status(MultiStatus::new(vec![
+            Response::with_propstat(account.collection_href(name), propstat),
+        ])))
+    }
+
+    pub async fn dav_delete_collection(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        name: &str,
+    ) -> Result<DavResponse, MethodError> {
+        let (document_id, collection) = match self.dav_collection(account, name).await? {
+            Some(collection) => collection,
+            None => return Ok(DavResponse::new(StatusCode::NOT_FOUND)),
+        };
+        if !self
+            .dav_privileges(access_token, account.account_id, &collection.inner)
+            .contains(Acl::Delete)
+        {
+            return Ok(DavResponse::new(StatusCode::FORBIDDEN));
+        }
+
+        // Delete resources in the collection
+        for resource_id in self
+            .filter(
+                account.account_id,
+                account.items(),
+                vec![Filter::eq(Property::ParentId, document_id)],
+            )
+            .await?
+            .results
+        {
+            if let Some(resource) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account.account_id,
+                    account.items(),
+                    resource_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                self.dav_resource_destroy(account, document_id, resource_id, resource)
+                    .await?;
+            }
+        }
+
+        self.dav_resource_tombstones_purge(account, document_id)
+            .await?;
+
+        // Invalidate the access tokens of the principals the collection was shared with
+        let current = Some(collection);
+        self.refresh_acls(
+            &Object::with_capacity(1).with_property(Property::Acl, Value::Acl(Vec::new())),
+            &current,
+        );
+
+        // Delete collection
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_change_id(self.assign_change_id(account.account_id).await?)
+            .with_account_id(account.account_id)
+            .with_collection(account.container())
+            .delete_document(document_id)
+            .log(Changes::delete([document_id]))
+            .clear(Property::Cid)
+            .custom(ObjectIndexBuilder::new(SCHEMA).with_current_opt(current));
+        self.write_batch(batch).await?;
+
+        Ok(DavResponse::new(StatusCode::NO_CONTENT))
+    }
+
+    pub async fn dav_acl(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        name: &str,
+        aces: Vec<Ace>,
+    ) -> Result<DavResponse, MethodError> {
+        let (document_id, collection) = match self.dav_collection(account, name).await? {
+            Some(collection) => collection,
+            None => return Ok(DavResponse::new(StatusCode::NOT_FOUND)),
+        };
+        if !self
+            .dav_privileges(access_token, account.account_id, &collection.inner)
+            .contains(Acl::Administer)
+        {
+            return Ok(DavResponse::new(StatusCode::FORBIDDEN));
+        }
+
+        // Map privileges to grants on directory principals
+        let mut grants: Vec<AclGrant> = Vec::with_capacity(aces.len());
+        for ace in aces {
+            let principal = match DavPath::parse(&ace.principal) {
+                Some(DavPath::Principal(principal)) => self
+                    .core
+                    .storage
+                    .directory
+                    .query(QueryBy::Name(&principal), false)
+                    .await
+                    .map_err(|err| {
+                        tracing::error!(
+                            event = "error",
+                            context = "dav_acl",
+                            account_id = account.account_id,
+                            error = ?err,
+                            "Failed to query directory.");
+                        MethodError::ServerPartialFail
+                    })?,
+                _ => None,
+            };
+            let account_id = match principal {
+                Some(principal) => principal.id,
+                None => {
+                    return Ok(DavResponse::condition(
+                        StatusCode::FORBIDDEN,
+                        Condition::RecognizedPrincipal,
+                    ))
+                }
+            };
+
+            let mut acls = Bitmap::new();
+            for privilege in &ace.privileges {
+                match privilege_acls(privilege) {
+                    Some(privilege_acls) => {
+                        for acl in privilege_acls {
+                            acls.insert(*acl);
+                        }
+                    }
+                    None => {
+                        return Ok(DavResponse::condition(
+                            StatusCode::FORBIDDEN,
+                            Condition::NotSupportedPrivilege,
+                        ))
+                    }
+                }
+            }
+
+            if account_id == account.account_id || acls.is_empty() {
+                continue;
+            } else if let Some(grant) = grants.iter_mut().find(|g| g.account_id == account_id) {
+                grant.grants.union(&acls);
+            } else {
+                grants.push(AclGrant {
+                    account_id,
+                    grants: acls,
+                });
+            }
+        }
+
+        // Update ACLs
+        let changes = Object::with_capacity(1).with_property(Property::Acl, Value::Acl(grants));
+        let current = Some(collection);
+        self.refresh_acls(&changes, &current);
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_change_id(self.assign_change_id(account.account_id).await?)
+            .with_account_id(account.account_id)
+            .with_collection(account.container())
+            .update_document(document_id)
+            .log(Changes::update([document_id]))
+            .custom(
+                ObjectIndexBuilder::new(SCHEMA)
+                    .with_current_opt(current)
+                    .with_changes(changes),
+            );
+        self.write_batch(batch).await?;
+
+        Ok(DavResponse::new(StatusCode::OK))
+    }
+
+    async fn dav_collection_create(
+        &self,
+        account: &DavAccount,
+        name: &str,
+        display_name: Option<String>,
+    ) -> Result<u32, MethodError> {
+        let mut collection = Object::with_capacity(2).with_property(Property::Name, name);
+        if let Some(display_name) = display_name {
+            collection.set(Property::Description, display_name);
+        }
+
+        let change_id = self.assign_change_id(account.account_id).await?;
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_change_id(change_id)
+            .with_account_id(account.account_id)
+            .with_collection(account.container())
+            .create_document()
+            .log(LogInsert())
+            .value(Property::Cid, change_id, F_VALUE)
+            .custom(ObjectIndexBuilder::new(SCHEMA).with_changes(collection));
+        self.write_batch_expect_id(batch).await
+    }
+}
+
+/// Maps a WebDAV privilege (RFC 3744) to the ACLs it grants.
+pub fn privilege_acls(privilege: &str) -> Option<&'static [Acl]> {
+    match privilege {
+        "read" => Some(&[Acl::Read, Acl::ReadItems]),
+        "write" => Some(&[
+            Acl::Modify,
+            Acl::AddItems,
+            Acl::ModifyItems,
+            Acl::RemoveItems,
+        ]),
+        "write-properties" => Some(&[Acl::Modify]),
+        "write-content" => Some(&[Acl::ModifyItems]),
+        "bind" => Some(&[Acl::AddItems]),
+        "unbind" => Some(&[Acl::RemoveItems]),
+        "write-acl" => Some(&[Acl::Administer]),
+        "read-acl" | "read-current-user-privilege-set" => Some(&[]),
+        "all" => Some(&[
+            Acl::Read,
+            Acl::ReadItems,
+            Acl::Modify,
+            Acl::Delete,
+            Acl::AddItems,
+            Acl::ModifyItems,
+            Acl::RemoveItems,
+            Acl::Administer,
+        ]),
+        _ => None,
+    }
+}
+
+/// Returns the WebDAV privileges equivalent to a set of ACLs.
+pub fn privilege_names(acls: &Bitmap<Acl>) -> Vec<&'static str> {
+    let mut names = Vec::new();
+    for privilege in [
+        "all",
+        "read",
+        "write",
+        "write-properties",
+        "write-content",
+        "bind",
+        "unbind",
+        "write-acl",
+    ] {
+        if privilege_acls(privilege)
+            .unwrap_or_default()
+            .iter()
+            .all(|acl| acls.contains(*acl))
+        {
+            names.push(privilege);
+        }
+    }
+    if acls.contains(Acl::Read) || acls.contains(Acl::ReadItems) {
+        names.push("read-current-user-privilege-set");
+    }
+    names
+}
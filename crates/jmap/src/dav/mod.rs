@@ -0,0 +1,479 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use dav::{
+    encode_path_segment,
+    request::{Ace, MkCol, PropFind, PropPatch, Report},
+    response::Condition,
+    DavPath, DavResourceType, Depth, Preconditions, XML_CONTENT_TYPE,
+};
+use directory::QueryBy;
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Bytes, header, Method, StatusCode};
+use jmap_proto::{
+    error::method::MethodError,
+    object::Object,
+    types::{acl::Acl, collection::Collection, property::Property, value::Value},
+};
+use utils::map::bitmap::Bitmap;
+
+use crate::{
+    api::{
+        http::{fetch_body, HttpSessionData, ToHttpResponse},
+        HttpRequest, HttpResponse,
+    },
+    auth::AccessToken,
+    JMAP,
+};
+
+pub mod collection;
+pub mod object;
+pub mod propfind;
+pub mod report;
+pub mod resource;
+
+const DAV_CAPABILITIES: &str = "1, 3, access-control, calendar-access, addressbook, extended-mkcol";
+const DAV_METHODS: &str =
+    "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, PROPPATCH, MKCOL, MKCALENDAR, REPORT, ACL";
+
+pub struct DavResponse {
+    status: StatusCode,
+    headers: Vec<(&'static str, String)>,
+    body: Option<(&'static str, Vec<u8>)>,
+}
+
+/// Account owning the requested home, collection or resource.
+pub struct DavAccount {
+    pub resource_type: DavResourceType,
+    pub account_id: u32,
+    pub name: String,
+}
+
+impl JMAP {
+    pub async fn handle_dav_request(
+        &self,
+        mut req: HttpRequest,
+        session: &HttpSessionData,
+    ) -> HttpResponse {
+        if !self.core.dav.enable {
+            return DavResponse::new(StatusCode::NOT_FOUND).into_http_response();
+        } else if req.method() == Method::OPTIONS {
+            return DavResponse::new(StatusCode::OK)
+                .with_header("DAV", DAV_CAPABILITIES)
+                .with_header("Allow", DAV_METHODS)
+                .into_http_response();
+        }
+
+        // Authenticate request
+        let (_in_flight, access_token) = match self
+            .authenticate_headers(&req, session.remote_ip, "dav")
+            .await
+        {
+            Ok(Some(session)) => session,
+            Ok(None) => {
+                return DavResponse::new(StatusCode::UNAUTHORIZED)
+                    .with_header("WWW-Authenticate", "Basic realm=\"Stalwart DAV\"")
+                    .into_http_response()
+            }
+            Err(err) => return err.into_http_response(),
+        };
+
+        // Parse request
+        let path = match DavPath::parse(req.uri().path()) {
+            Some(path) => path,
+            None => return DavResponse::new(StatusCode::NOT_FOUND).into_http_response(),
+        };
+        let depth = Depth::parse(req.headers().get("Depth").and_then(|h| h.to_str().ok()));
+        let preconditions = Preconditions {
+            if_match: header_value(&req, header::IF_MATCH),
+            if_none_match: header_value(&req, header::IF_NONE_MATCH),
+        };
+        let method = req.method().clone();
+        let body = match fetch_body(&mut req, self.core.dav.max_request_size).await {
+            Some(body) => body,
+            None => return DavResponse::new(StatusCode::PAYLOAD_TOO_LARGE).into_http_response(),
+        };
+
+        match self
+            .handle_dav_method(&access_token, method, path, depth, preconditions, body)
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => DavResponse::from(err),
+        }
+        .into_http_response()
+    }
+
+    async fn handle_dav_method(
+        &self,
+        access_token: &AccessToken,
+        method: Method,
+        path: DavPath,
+        depth: Depth,
+        preconditions: Preconditions,
+        body: Vec<u8>,
+    ) -> Result<DavResponse, MethodError> {
+        match path {
+            DavPath::Root | DavPath::Principal(_) => match method.as_str() {
+                "PROPFIND" => match PropFind::parse(&body) {
+                    Ok(request) => {
+                        self.dav_propfind_principal(access_token, path, request)
+                            .await
+                    }
+                    Err(err) => Ok(DavResponse::bad_request(err)),
+                },
+                _ => Ok(DavResponse::method_not_allowed()),
+            },
+            DavPath::Home {
+                resource_type,
+                account,
+            } => {
+                let account = match self.dav_account(resource_type, &account).await? {
+                    Some(account) => account,
+                    None => return Ok(DavResponse::new(StatusCode::NOT_FOUND)),
+                };
+                match method.as_str() {
+                    "PROPFIND" => match PropFind::parse(&body) {
+                        Ok(request) => {
+                            self.dav_propfind_home(access_token, &account, depth, request)
+                                .await
+                        }
+                        Err(err) => Ok(DavResponse::bad_request(err)),
+                    },
+                    _ => Ok(DavResponse::method_not_allowed()),
+                }
+            }
+            DavPath::Collection {
+                resource_type,
+                account,
+                name,
+            } => {
+                let account = match self.dav_account(resource_type, &account).await? {
+                    Some(account) => account,
+                    None => return Ok(DavResponse::new(StatusCode::NOT_FOUND)),
+                };
+                match method.as_str() {
+                    "PROPFIND" => match PropFind::parse(&body) {
+                        Ok(request) => {
+                            self.dav_propfind_collection(
+                                access_token,
+                                &account,
+                                &name,
+                                depth,
+                                request,
+                            )
+                            .await
+                        }
+                        Err(err) => Ok(DavResponse::bad_request(err)),
+                    },
+                    "REPORT" => match Report::parse(&body) {
+                        Ok(request) => {
+                            self.dav_report(access_token, &account, &name, request)
+                                .await
+                        }
+                        Err(err) => Ok(DavResponse::bad_request(err)),
+                    },
+                    "PROPPATCH" => match PropPatch::parse(&body) {
+                        Ok(request) => {
+                            self.dav_proppatch(access_token, &account, &name, request)
+                                .await
+                        }
+                        Err(err) => Ok(DavResponse::bad_request(err)),
+                    },
+                    "MKCOL" | "MKCALENDAR" => match MkCol::parse(&body) {
+                        Ok(request)
+                            if (method.as_str() == "MKCOL"
+                                || resource_type == DavResourceType::Calendar)
+                                && request
+                                    .resource_type
+                                    .map_or(true, |typ| typ == resource_type) =>
+                        {
+                            self.dav_mkcol(access_token, &account, &name, request).await
+                        }
+                        Ok(_) => Ok(DavResponse::new(StatusCode::FORBIDDEN)),
+                        Err(err) => Ok(DavResponse::bad_request(err)),
+                    },
+                    "ACL" => match Ace::parse(&body) {
+                        Ok(request) => self.dav_acl(access_token, &account, &name, request).await,
+                        Err(err) => Ok(DavResponse::new(StatusCode::FORBIDDEN).with_text(err)),
+                    },
+                    "DELETE" => {
+                        self.dav_delete_collection(access_token, &account, &name)
+                            .await
+                    }
+                    _ => Ok(DavResponse::method_not_allowed()),
+                }
+            }
+            DavPath::Resource {
+                resource_type,
+                account,
+                collection,
+                name,
+            } => {
+                let account = match self.dav_account(resource_type, &account).await? {
+                    Some(account) => account,
+                    None => return Ok(DavResponse::new(StatusCode::NOT_FOUND)),
+                };
+                match method.as_str() {
+                    "PROPFIND" => match PropFind::parse(&body) {
+                        Ok(request) => {
+                            self.dav_propfind_resource(
+                                access_token,
+                                &account,
+                                &collection,
+                                &name,
+                                request,
+                            )
+                            .await
+                        }
+                        Err(err) => Ok(DavResponse::bad_request(err)),
+                    },
+                    "GET" | "HEAD" => {
+                        self.dav_get(
+                            access_token,
+                            &account,
+                            &collection,
+                            &name,
+                            method == Method::HEAD,
+                        )
+                        .await
+                    }
+                    "PUT" => {
+                        self.dav_put(
+                            access_token,
+                            &account,
+                            &collection,
+                            name,
+                            preconditions,
+                            body,
+                        )
+                        .await
+                    }
+                    "DELETE" => {
+                        self.dav_delete(access_token, &account, &collection, &name, preconditions)
+                            .await
+                    }
+                    _ => Ok(DavResponse::method_not_allowed()),
+                }
+            }
+        }
+    }
+
+    async fn dav_account(
+        &self,
+        resource_type: DavResourceType,
+        name: &str,
+    ) -> Result<Option<DavAccount>, MethodError> {
+        Ok(self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Name(name), false)
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    event = "error",
+                    context = "dav",
+                    account = name,
+                    error = ?err,
+                    "Failed to query directory.");
+                MethodError::ServerPartialFail
+            })?
+            .map(|principal| DavAccount {
+                resource_type,
+                account_id: principal.id,
+                name: principal.name,
+            }))
+    }
+
+    /// Returns the effective privileges of the user on a collection. Members
+    /// of the account have full access, other principals obtain the rights
+    /// granted to them or their groups in the collection's ACL.
+    fn dav_privileges(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        collection: &Object<Value>,
+    ) -> Bitmap<Acl> {
+        if access_token.is_member(account_id) {
+            return Bitmap::all();
+        }
+
+        let mut privileges = Bitmap::new();
+        if let Value::Acl(grants) = collection.get(&Property::Acl) {
+            for grant in grants {
+                if grant.account_id == access_token.primary_id
+                    || access_token.member_of.contains(&grant.account_id)
+                {
+                    privileges.union(&grant.grants);
+                }
+            }
+        }
+        privileges
+    }
+}
+
+impl DavAccount {
+    pub fn home_href(&self) -> String {
+        home_href(self.resource_type, &self.name)
+    }
+
+    pub fn collection_href(&self, collection: &str) -> String {
+        format!(
+            "/dav/{}/{}/{}/",
+            self.resource_type.as_path(),
+            encode_path_segment(&self.name),
+            encode_path_segment(collection)
+        )
+    }
+
+    pub fn resource_href(&self, collection: &str, name: &str) -> String {
+        format!(
+            "/dav/{}/{}/{}/{}",
+            self.resource_type.as_path(),
+            encode_path_segment(&self.name),
+            encode_path_segment(collection),
+            encode_path_segment(name)
+        )
+    }
+
+    /// Collection holding the calendars or address books of the account.
+    pub fn container(&self) -> Collection {
+        match self.resource_type {
+            DavResourceType::Calendar => Collection::Calendar,
+            DavResourceType::AddressBook => Collection::AddressBook,
+        }
+    }
+
+    /// Collection holding the calendar objects or vCards of the account.
+    pub fn items(&self) -> Collection {
+        match self.resource_type {
+            DavResourceType::Calendar => Collection::CalendarEvent,
+            DavResourceType::AddressBook => Collection::ContactCard,
+        }
+    }
+}
+
+pub fn home_href(resource_type: DavResourceType, name: &str) -> String {
+    format!(
+        "/dav/{}/{}/",
+        resource_type.as_path(),
+        encode_path_segment(name)
+    )
+}
+
+pub fn principal_href(name: &str) -> String {
+    format!("/dav/principal/{}/", encode_path_segment(name))
+}
+
+pub fn sync_token(change_id: u64) -> String {
+    format!("urn:stalwart:sync:{change_id}")
+}
+
+pub fn etag(change_id: u64) -> String {
+    format!("\"{change_id}\"")
+}
+
+fn header_value(req: &HttpRequest, name: header::HeaderName) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|h| h.to_str().ok())
+        .map(|h| h.to_string())
+}
+
+impl DavResponse {
+    pub fn new(status: StatusCode) -> Self {
+        DavResponse {
+            status,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    pub fn multi_status(body: impl ToString) -> Self {
+        DavResponse::new(StatusCode::MULTI_STATUS).with_xml(body)
+    }
+
+    pub fn condition(status: StatusCode, condition: Condition) -> Self {
+        DavResponse::new(status).with_xml(condition)
+    }
+
+    pub fn bad_request(reason: String) -> Self {
+        DavResponse::new(StatusCode::BAD_REQUEST).with_text(reason)
+    }
+
+    pub fn method_not_allowed() -> Self {
+        DavResponse::new(StatusCode::METHOD_NOT_ALLOWED).with_header("Allow", DAV_METHODS)
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    pub fn with_xml(mut self, body: impl ToString) -> Self {
+        self.body = Some((XML_CONTENT_TYPE, body.to_string().into_bytes()));
+        self
+    }
+
+    pub fn with_text(mut self, body: impl Into<String>) -> Self {
+        self.body = Some(("text/plain; charset=utf-8", body.into().into_bytes()));
+        self
+    }
+
+    pub fn with_body(mut self, content_type: &'static str, body: Vec<u8>) -> Self {
+        self.body = Some((content_type, body));
+        self
+    }
+}
+
+impl From<MethodError> for DavResponse {
+    fn from(err: MethodError) -> Self {
+        match err {
+            MethodError::Forbidden(reason) => {
+                DavResponse::new(StatusCode::FORBIDDEN).with_text(reason)
+            }
+            MethodError::NotFound => DavResponse::new(StatusCode::NOT_FOUND),
+            MethodError::ServerUnavailable => DavResponse::new(StatusCode::SERVICE_UNAVAILABLE),
+            _ => DavResponse::new(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+}
+
+impl ToHttpResponse for DavResponse {
+    fn into_http_response(self) -> HttpResponse {
+        let mut response = hyper::Response::builder().status(self.status);
+        for (name, value) in self.headers {
+            response = response.header(name, value);
+        }
+        let body = if let Some((content_type, body)) = self.body {
+            response = response.header(header::CONTENT_TYPE, content_type);
+            Bytes::from(body)
+        } else {
+            Bytes::new()
+        };
+        response
+            .body(Full::new(body).map_err(|never| match never {}).boxed())
+            .unwrap()
+    }
+}
@@ -0,0 +1,154 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use dav::DavResourceType;
+use jmap_proto::{
+    error::method::MethodError,
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    object::Object,
+    types::{id::Id, property::Property, value::Value},
+};
+use store::{roaring::RoaringBitmap, BlobClass};
+
+use crate::{dav::DavAccount, JMAP};
+
+use super::parent_id;
+
+impl JMAP {
+    pub async fn dav_object_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+        resource_type: DavResourceType,
+    ) -> Result<GetResponse, MethodError> {
+        let account = DavAccount::jmap(resource_type, request.account_id.document_id());
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = request.unwrap_properties(&[
+            Property::Id,
+            account.parent_property(),
+            Property::Uid,
+            Property::BlobId,
+            Property::Size,
+        ]);
+        let document_ids = self
+            .get_document_ids(account.account_id, account.items())
+            .await?
+            .unwrap_or_default();
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            self.get_properties::<u32, _, _>(
+                account.account_id,
+                account.items(),
+                &document_ids
+                    .iter()
+                    .take(self.core.jmap.get_max_objects)
+                    .collect::<RoaringBitmap>(),
+                Property::ParentId,
+            )
+            .await?
+            .into_iter()
+            .map(|(document_id, collection_id)| Id::from_parts(collection_id, document_id))
+            .collect()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: self
+                .get_state(account.account_id, account.items())
+                .await?
+                .into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            // Obtain the resource, making sure the id matches its collection
+            let document_id = id.document_id();
+            if !document_ids.contains(document_id) {
+                response.not_found.push(id.into());
+                continue;
+            }
+            let mut resource = match self
+                .get_property::<Object<Value>>(
+                    account.account_id,
+                    account.items(),
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                Some(resource) if parent_id(&resource) == Some(id.prefix_id()) => resource,
+                _ => {
+                    response.not_found.push(id.into());
+                    continue;
+                }
+            };
+
+            let mut result = Object::with_capacity(properties.len());
+            for property in &properties {
+                match property {
+                    Property::Id => {
+                        result.append(Property::Id, Value::Id(id));
+                    }
+                    Property::CalendarIds | Property::AddressBookIds
+                        if property == &account.parent_property() =>
+                    {
+                        result.append(
+                            property.clone(),
+                            Value::Object(Object::with_capacity(1).with_property(
+                                Property::_T(Id::from(id.prefix_id()).to_string()),
+                                true,
+                            )),
+                        );
+                    }
+                    Property::Uid => {
+                        result.append(Property::Uid, resource.remove(&Property::Uid));
+                    }
+                    Property::BlobId | Property::Size => {
+                        let value = match resource.properties.get(&Property::BlobId) {
+                            Some(Value::BlobId(blob_id)) if property == &Property::BlobId => {
+                                let mut blob_id = blob_id.clone();
+                                blob_id.class = BlobClass::Linked {
+                                    account_id: account.account_id,
+                                    collection: account.items().into(),
+                                    document_id,
+                                };
+                                Value::BlobId(blob_id)
+                            }
+                            Some(Value::BlobId(blob_id)) => Value::UnsignedInt(
+                                blob_id.section.as_ref().map_or(0, |section| section.size) as u64,
+                            ),
+                            _ => Value::Null,
+                        };
+                        result.append(property.clone(), value);
+                    }
+                    property => {
+                        result.append(property.clone(), Value::Null);
+                    }
+                }
+            }
+            response.list.push(result);
+        }
+
+        Ok(response)
+    }
+}
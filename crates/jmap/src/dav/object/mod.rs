@@ -0,0 +1,70 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use dav::DavResourceType;
+use jmap_proto::{
+    object::Object,
+    types::{property::Property, value::Value},
+};
+
+use super::DavAccount;
+
+pub mod get;
+pub mod query;
+pub mod set;
+
+impl DavAccount {
+    /// Account used by the CalendarEvent and ContactCard methods. JMAP
+    /// methods never build hrefs, so the account name is not needed.
+    pub fn jmap(resource_type: DavResourceType, account_id: u32) -> Self {
+        DavAccount {
+            resource_type,
+            account_id,
+            name: String::new(),
+        }
+    }
+
+    /// Property listing the calendar or address book of an object.
+    pub fn parent_property(&self) -> Property {
+        match self.resource_type {
+            DavResourceType::Calendar => Property::CalendarIds,
+            DavResourceType::AddressBook => Property::AddressBookIds,
+        }
+    }
+
+    /// File extension used when naming resources created over JMAP.
+    pub fn extension(&self) -> &'static str {
+        match self.resource_type {
+            DavResourceType::Calendar => "ics",
+            DavResourceType::AddressBook => "vcf",
+        }
+    }
+}
+
+/// Returns the document id of the collection containing a resource.
+pub fn parent_id(resource: &Object<Value>) -> Option<u32> {
+    match resource.properties.get(&Property::ParentId)? {
+        Value::Id(id) => Some(id.document_id()),
+        _ => None,
+    }
+}
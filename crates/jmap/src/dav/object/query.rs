@@ -0,0 +1,94 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use dav::DavResourceType;
+use jmap_proto::{
+    error::method::MethodError,
+    method::query::{Filter, QueryRequest, QueryResponse, RequestArguments},
+    types::property::Property,
+};
+use store::{
+    query::{self},
+    write::ValueClass,
+    ValueKey,
+};
+
+use crate::{dav::DavAccount, JMAP};
+
+impl JMAP {
+    pub async fn dav_object_query(
+        &self,
+        mut request: QueryRequest<RequestArguments>,
+        resource_type: DavResourceType,
+    ) -> Result<QueryResponse, MethodError> {
+        let account = DavAccount::jmap(resource_type, request.account_id.document_id());
+        let mut filters = Vec::with_capacity(request.filter.len());
+
+        for cond in std::mem::take(&mut request.filter) {
+            match cond {
+                Filter::InCalendar(id) if resource_type == DavResourceType::Calendar => {
+                    filters.push(query::Filter::eq(Property::ParentId, id.document_id()))
+                }
+                Filter::InAddressBook(id) if resource_type == DavResourceType::AddressBook => {
+                    filters.push(query::Filter::eq(Property::ParentId, id.document_id()))
+                }
+                Filter::Uid(uid) => filters.push(query::Filter::eq(Property::Uid, uid.as_str())),
+                Filter::And | Filter::Or | Filter::Not | Filter::Close => {
+                    filters.push(cond.into());
+                }
+                other => return Err(MethodError::UnsupportedFilter(other.to_string())),
+            }
+        }
+
+        // Objects are returned in creation order
+        if let Some(comparator) = request.sort.as_ref().and_then(|sort| sort.first()) {
+            return Err(MethodError::UnsupportedSort(
+                comparator.property.to_string(),
+            ));
+        }
+
+        let result_set = self
+            .filter(account.account_id, account.items(), filters)
+            .await?;
+
+        let (response, paginate) = self.build_query_response(&result_set, &request).await?;
+
+        if let Some(paginate) = paginate {
+            // Ids are prefixed with the id of the calendar or address book
+            self.sort(
+                result_set,
+                Vec::new(),
+                paginate.with_prefix_key(ValueKey {
+                    account_id: account.account_id,
+                    collection: account.items().into(),
+                    document_id: 0,
+                    class: ValueClass::Property(Property::ParentId.into()),
+                }),
+                response,
+            )
+            .await
+        } else {
+            Ok(response)
+        }
+    }
+}
@@ -0,0 +1,359 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use dav::DavResourceType;
+use jmap_proto::{
+    error::{
+        method::MethodError,
+        set::{SetError, SetErrorType},
+    },
+    method::set::{RequestArguments, SetRequest, SetResponse},
+    object::Object,
+    response::references::EvalObjectReferences,
+    types::{
+        blob::BlobId,
+        id::Id,
+        property::Property,
+        value::{MaybePatchValue, SetValue, Value},
+    },
+};
+use store::write::assert::HashedValue;
+
+use crate::{
+    auth::AccessToken,
+    dav::{
+        resource::{DavItem, DavResourceError},
+        DavAccount,
+    },
+    JMAP,
+};
+
+use super::parent_id;
+
+impl JMAP {
+    pub async fn dav_object_set(
+        &self,
+        mut request: SetRequest<RequestArguments>,
+        access_token: &AccessToken,
+        resource_type: DavResourceType,
+    ) -> Result<SetResponse, MethodError> {
+        let account = DavAccount::jmap(resource_type, request.account_id.document_id());
+        let collection_ids = self.dav_collection_ids(access_token, &account).await?;
+        let mut response = self.prepare_set_response(&request, account.items()).await?;
+        let will_destroy = request.unwrap_destroy();
+        let mut last_change_id = None;
+
+        // Process creates
+        'create: for (id, object) in request.unwrap_create() {
+            // New objects are added to the first collection unless specified
+            let mut collection_id = collection_ids.min();
+            let mut blob_id = None;
+            for (property, value) in object.properties {
+                match self.dav_object_set_property(&account, &response, property, value) {
+                    Ok(DavObjectChange::BlobId(value)) => blob_id = Some(value),
+                    Ok(DavObjectChange::CollectionId(value)) if collection_ids.contains(value) => {
+                        collection_id = Some(value);
+                    }
+                    Ok(DavObjectChange::CollectionId(_)) => {
+                        response.not_created.append(
+                            id,
+                            SetError::invalid_properties()
+                                .with_property(account.parent_property())
+                                .with_description("Collection does not exist."),
+                        );
+                        continue 'create;
+                    }
+                    Err(err) => {
+                        response.not_created.append(id, err);
+                        continue 'create;
+                    }
+                }
+            }
+            let (collection_id, blob_id) = match (collection_id, blob_id) {
+                (Some(collection_id), Some(blob_id)) => (collection_id, blob_id),
+                (_, None) => {
+                    response.not_created.append(
+                        id,
+                        SetError::invalid_properties()
+                            .with_property(Property::BlobId)
+                            .with_description("Missing blobId."),
+                    );
+                    continue 'create;
+                }
+                (None, _) => {
+                    response.not_created.append(
+                        id,
+                        SetError::invalid_properties()
+                            .with_property(account.parent_property())
+                            .with_description("Collection does not exist."),
+                    );
+                    continue 'create;
+                }
+            };
+
+            // Validate and store the object
+            let name = format!(
+                "{}.{}",
+                Id::from(self.generate_snowflake_id()?),
+                account.extension()
+            );
+            match self
+                .dav_object_write(access_token, &account, collection_id, None, name, &blob_id)
+                .await?
+            {
+                Ok((document_id, change_id, result)) => {
+                    last_change_id = Some(change_id);
+                    response.created.insert(
+                        id,
+                        result.with_property(
+                            Property::Id,
+                            Value::Id(Id::from_parts(collection_id, document_id)),
+                        ),
+                    );
+                }
+                Err(err) => {
+                    response.not_created.append(id, err);
+                }
+            }
+        }
+
+        // Process updates
+        'update: for (id, object) in request.unwrap_update() {
+            // Make sure id won't be destroyed
+            if will_destroy.contains(&id) {
+                response.not_updated.append(id, SetError::will_destroy());
+                continue 'update;
+            }
+
+            // Obtain the object
+            let (collection_id, resource, item) = match self.dav_object_fetch(&account, id).await? {
+                Some(current) => current,
+                None => {
+                    response.not_updated.append(id, SetError::not_found());
+                    continue 'update;
+                }
+            };
+
+            // Objects cannot be moved to a different collection
+            let mut blob_id = None;
+            for (property, value) in object.properties {
+                match self.dav_object_set_property(&account, &response, property, value) {
+                    Ok(DavObjectChange::BlobId(value)) => blob_id = Some(value),
+                    Ok(DavObjectChange::CollectionId(value)) if value == collection_id => {}
+                    Ok(DavObjectChange::CollectionId(_)) => {
+                        response.not_updated.append(
+                            id,
+                            SetError::invalid_properties()
+                                .with_property(account.parent_property())
+                                .with_description("Objects cannot be moved between collections."),
+                        );
+                        continue 'update;
+                    }
+                    Err(err) => {
+                        response.not_updated.append(id, err);
+                        continue 'update;
+                    }
+                }
+            }
+
+            // Replace the object contents
+            let blob_id = match blob_id {
+                Some(blob_id) => blob_id,
+                None => {
+                    response.updated.append(id, None);
+                    continue 'update;
+                }
+            };
+            let name = item.name.clone();
+            match self
+                .dav_object_write(
+                    access_token,
+                    &account,
+                    collection_id,
+                    Some((id.document_id(), resource, item)),
+                    name,
+                    &blob_id,
+                )
+                .await?
+            {
+                Ok((_, change_id, result)) => {
+                    last_change_id = Some(change_id);
+                    response.updated.append(id, Some(result));
+                }
+                Err(err) => {
+                    response.not_updated.append(id, err);
+                }
+            }
+        }
+
+        // Process deletions
+        for id in will_destroy {
+            if let Some((collection_id, resource, item)) =
+                self.dav_object_fetch(&account, id).await?
+            {
+                last_change_id = Some(
+                    self.dav_resource_destroy(&account, collection_id, id.document_id(), resource)
+                        .await?,
+                );
+                self.dav_resource_tombstone_set(&account, id, &item.name)
+                    .await;
+                response.destroyed.push(id);
+            } else {
+                response.not_destroyed.append(id, SetError::not_found());
+            }
+        }
+
+        // Each change is logged as it is written, the new state is the last one
+        if let Some(change_id) = last_change_id {
+            response.new_state = Some(change_id.into());
+        }
+
+        Ok(response)
+    }
+
+    async fn dav_object_write(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        collection_id: u32,
+        current: Option<(u32, HashedValue<Object<Value>>, DavItem)>,
+        name: String,
+        blob_id: &BlobId,
+    ) -> Result<Result<(u32, u64, Object<Value>), SetError>, MethodError> {
+        let bytes = match self.blob_download(blob_id, access_token).await? {
+            Some(bytes) => bytes,
+            None => {
+                return Ok(Err(SetError::new(SetErrorType::BlobNotFound)
+                    .with_property(Property::BlobId)
+                    .with_description("Blob does not exist.")))
+            }
+        };
+        let object = match self.dav_resource_parse(account.resource_type, &bytes) {
+            Ok(object) => object,
+            Err(err) => return Ok(Err(err.into())),
+        };
+        if let Err(err) = self
+            .dav_resource_check(
+                access_token,
+                account,
+                collection_id,
+                current
+                    .as_ref()
+                    .map(|(document_id, _, item)| (*document_id, item.size)),
+                &object.uid,
+                bytes.len(),
+            )
+            .await?
+        {
+            return Ok(Err(err.into()));
+        }
+
+        let uid = object.uid.clone();
+        let (document_id, change_id, blob_id) = self
+            .dav_resource_store(account, collection_id, current, name, object, &bytes)
+            .await?;
+
+        Ok(Ok((
+            document_id,
+            change_id,
+            Object::with_capacity(3)
+                .with_property(Property::Uid, uid)
+                .with_property(Property::BlobId, Value::BlobId(blob_id))
+                .with_property(Property::Size, Value::UnsignedInt(bytes.len() as u64)),
+        )))
+    }
+
+    /// Obtains an object, making sure the id matches its collection.
+    async fn dav_object_fetch(
+        &self,
+        account: &DavAccount,
+        id: Id,
+    ) -> Result<Option<(u32, HashedValue<Object<Value>>, DavItem)>, MethodError> {
+        let document_id = id.document_id();
+        let resource = match self
+            .get_property::<HashedValue<Object<Value>>>(
+                account.account_id,
+                account.items(),
+                document_id,
+                Property::Value,
+            )
+            .await?
+        {
+            Some(resource) if parent_id(&resource.inner) == Some(id.prefix_id()) => resource,
+            _ => return Ok(None),
+        };
+
+        Ok(DavItem::new(document_id, 0, &resource.inner)
+            .map(|item| (id.prefix_id(), resource, item)))
+    }
+
+    fn dav_object_set_property(
+        &self,
+        account: &DavAccount,
+        response: &SetResponse,
+        property: Property,
+        value: SetValue,
+    ) -> Result<DavObjectChange, SetError> {
+        match (&property, response.eval_object_references(value)?) {
+            (Property::BlobId, MaybePatchValue::Value(Value::BlobId(blob_id))) => {
+                Ok(DavObjectChange::BlobId(blob_id))
+            }
+            (
+                Property::CalendarIds | Property::AddressBookIds,
+                MaybePatchValue::Value(Value::List(ids)),
+            ) if property == account.parent_property() => {
+                // Objects belong to exactly one calendar or address book
+                match ids.as_slice() {
+                    [Value::Id(id)] => Ok(DavObjectChange::CollectionId(id.document_id())),
+                    _ => Err(SetError::invalid_properties()
+                        .with_property(property)
+                        .with_description("Objects must belong to exactly one collection.")),
+                }
+            }
+            _ => Err(SetError::invalid_properties()
+                .with_property(property)
+                .with_description("Invalid property or value.")),
+        }
+    }
+}
+
+enum DavObjectChange {
+    BlobId(BlobId),
+    CollectionId(u32),
+}
+
+impl From<DavResourceError> for SetError {
+    fn from(err: DavResourceError) -> Self {
+        match err {
+            DavResourceError::TooLarge => SetError::new(SetErrorType::TooLarge)
+                .with_property(Property::BlobId)
+                .with_description("Object exceeds the maximum size."),
+            DavResourceError::InvalidData => SetError::invalid_properties()
+                .with_property(Property::BlobId)
+                .with_description("Blob does not contain a valid object."),
+            DavResourceError::UidConflict => SetError::already_exists()
+                .with_description("An object with the same UID already exists."),
+            DavResourceError::OverQuota => SetError::over_quota(),
+        }
+    }
+}
@@ -0,0 +1,454 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use dav::{
+    request::{DavProperty, PropFind},
+    response::{MultiStatus, PropStat, PropValue, Response},
+    DavPath, DavResourceType, Depth,
+};
+use directory::QueryBy;
+use hyper::StatusCode;
+use jmap_proto::{
+    error::method::MethodError,
+    object::Object,
+    types::{acl::Acl, property::Property, value::Value},
+};
+use store::write::assert::HashedValue;
+use utils::map::bitmap::Bitmap;
+
+use crate::{auth::AccessToken, JMAP};
+
+use super::{
+    collection::privilege_names, home_href, principal_href, resource::DavItem, sync_token,
+    DavAccount, DavResponse,
+};
+
+const ROOT_PROPERTIES: &[DavProperty] =
+    &[DavProperty::ResourceType, DavProperty::CurrentUserPrincipal];
+const PRINCIPAL_PROPERTIES: &[DavProperty] = &[
+    DavProperty::ResourceType,
+    DavProperty::DisplayName,
+    DavProperty::PrincipalUrl,
+    DavProperty::CurrentUserPrincipal,
+    DavProperty::CalendarHomeSet,
+    DavProperty::AddressbookHomeSet,
+];
+const HOME_PROPERTIES: &[DavProperty] = &[
+    DavProperty::ResourceType,
+    DavProperty::DisplayName,
+    DavProperty::Owner,
+    DavProperty::CurrentUserPrincipal,
+    DavProperty::CurrentUserPrivilegeSet,
+];
+const COLLECTION_PROPERTIES: &[DavProperty] = &[
+    DavProperty::ResourceType,
+    DavProperty::DisplayName,
+    DavProperty::Owner,
+    DavProperty::CurrentUserPrincipal,
+    DavProperty::CurrentUserPrivilegeSet,
+    DavProperty::SupportedReportSet,
+    DavProperty::SyncToken,
+    DavProperty::GetCTag,
+    DavProperty::SupportedCalendarComponentSet,
+];
+const RESOURCE_PROPERTIES: &[DavProperty] = &[
+    DavProperty::ResourceType,
+    DavProperty::GetETag,
+    DavProperty::GetContentType,
+    DavProperty::GetContentLength,
+];
+
+impl JMAP {
+    pub async fn dav_propfind_principal(
+        &self,
+        access_token: &AccessToken,
+        path: DavPath,
+        request: PropFind,
+    ) -> Result<DavResponse, MethodError> {
+        let current_user = principal_href(&access_token.name);
+        let response = match path {
+            DavPath::Principal(name) => {
+                let principal = self
+                    .core
+                    .storage
+                    .directory
+                    .query(QueryBy::Name(&name), false)
+                    .await
+                    .map_err(|err| {
+                        tracing::error!(
+                            event = "error",
+                            context = "dav_propfind",
+                            account = name,
+                            error = ?err,
+                            "Failed to query directory.");
+                        MethodError::ServerPartialFail
+                    })?;
+                let principal = match principal {
+                    Some(principal) => principal,
+                    None => return Ok(DavResponse::new(StatusCode::NOT_FOUND)),
+                };
+                let href = principal_href(&principal.name);
+                Response::with_propstat(
+                    href.clone(),
+                    build_propstat(&request, PRINCIPAL_PROPERTIES, |property| {
+                        match property {
+                            DavProperty::ResourceType => PropValue::Principal,
+                            DavProperty::DisplayName => PropValue::text(
+                                principal.description.as_deref().unwrap_or(&principal.name),
+                            ),
+                            DavProperty::PrincipalUrl => PropValue::Href(href.clone()),
+                            DavProperty::CurrentUserPrincipal => {
+                                PropValue::Href(current_user.clone())
+                            }
+                            DavProperty::CalendarHomeSet => PropValue::Href(home_href(
+                                DavResourceType::Calendar,
+                                &principal.name,
+                            )),
+                            DavProperty::AddressbookHomeSet => PropValue::Href(home_href(
+                                DavResourceType::AddressBook,
+                                &principal.name,
+                            )),
+                            _ => return None,
+                        }
+                        .into()
+                    }),
+                )
+            }
+            _ => Response::with_propstat(
+                "/dav/",
+                build_propstat(&request, ROOT_PROPERTIES, |property| match property {
+                    DavProperty::ResourceType => PropValue::ResourceType(None).into(),
+                    DavProperty::CurrentUserPrincipal => {
+                        PropValue::Href(current_user.clone()).into()
+                    }
+                    _ => None,
+                }),
+            ),
+        };
+
+        Ok(DavResponse::multi_status(MultiStatus::new(vec![response])))
+    }
+
+    pub async fn dav_propfind_home(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        depth: Depth,
+        request: PropFind,
+    ) -> Result<DavResponse, MethodError> {
+        // Members of the account can list all collections, other users only
+        // the ones shared with them
+        let mut collections = Vec::new();
+        for document_id in self.dav_collection_ids(access_token, account).await? {
+            if let Some(collection) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account.account_id,
+                    account.container(),
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                let privileges =
+                    self.dav_privileges(access_token, account.account_id, &collection.inner);
+                if privileges.contains(Acl::Read) {
+                    collections.push((document_id, collection.inner, privileges));
+                }
+            }
+        }
+        if collections.is_empty() && !access_token.is_member(account.account_id) {
+            return Ok(DavResponse::new(StatusCode::FORBIDDEN));
+        }
+
+        let current_user = principal_href(&access_token.name);
+        let privileges = if access_token.is_member(account.account_id) {
+            Bitmap::all()
+        } else {
+            Bitmap::from_iter([Acl::Read])
+        };
+        let mut responses = vec![Response::with_propstat(
+            account.home_href(),
+            build_propstat(&request, HOME_PROPERTIES, |property| {
+                match property {
+                    DavProperty::ResourceType => PropValue::ResourceType(None),
+                    DavProperty::DisplayName => PropValue::text(&account.name),
+                    DavProperty::Owner => PropValue::Href(principal_href(&account.name)),
+                    DavProperty::CurrentUserPrincipal => PropValue::Href(current_user.clone()),
+                    DavProperty::CurrentUserPrivilegeSet => {
+                        PropValue::Privileges(privilege_names(&privileges))
+                    }
+                    _ => return None,
+                }
+                .into()
+            }),
+        )];
+
+        if depth != Depth::Zero {
+            for (document_id, collection, privileges) in collections {
+                responses.push(
+                    self.dav_collection_response(
+                        access_token,
+                        account,
+                        document_id,
+                        &collection,
+                        &privileges,
+                        &request,
+                    )
+                    .await?,
+                );
+            }
+        }
+
+        Ok(DavResponse::multi_status(MultiStatus::new(responses)))
+    }
+
+    pub async fn dav_propfind_collection(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        name: &str,
+        depth: Depth,
+        request: PropFind,
+    ) -> Result<DavResponse, MethodError> {
+        let (document_id, collection) = match self.dav_collection(account, name).await? {
+            Some(collection) => collection,
+            None => return Ok(DavResponse::new(StatusCode::NOT_FOUND)),
+        };
+        let privileges = self.dav_privileges(access_token, account.account_id, &collection.inner);
+        if !privileges.contains(Acl::Read) {
+            return Ok(DavResponse::new(StatusCode::FORBIDDEN));
+        }
+
+        let mut responses = vec![
+            self.dav_collection_response(
+                access_token,
+                account,
+                document_id,
+                &collection.inner,
+                &privileges,
+                &request,
+            )
+            .await?,
+        ];
+        if depth != Depth::Zero && privileges.contains(Acl::ReadItems) {
+            let items = self
+                .dav_items(
+                    account,
+                    &self.dav_collection_items(account, document_id).await?,
+                )
+                .await?;
+            responses.extend(
+                self.dav_item_responses(account, name, items, &request)
+                    .await?,
+            );
+        }
+
+        Ok(DavResponse::multi_status(MultiStatus::new(responses)))
+    }
+
+    pub async fn dav_propfind_resource(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        collection: &str,
+        name: &str,
+        request: PropFind,
+    ) -> Result<DavResponse, MethodError> {
+        let item = match self
+            .dav_resource(access_token, account, collection, name, Acl::ReadItems)
+            .await?
+        {
+            Ok((_, _, _, item)) => item,
+            Err(response) => return Ok(response),
+        };
+
+        Ok(DavResponse::multi_status(MultiStatus::new(
+            self.dav_item_responses(account, collection, vec![item], &request)
+                .await?,
+        )))
+    }
+
+    /// Builds the responses for a list of resources, fetching their contents
+    /// only when calendar-data or address-data is explicitly requested.
+    pub async fn dav_item_responses(
+        &self,
+        account: &DavAccount,
+        collection: &str,
+        items: Vec<DavItem>,
+        request: &PropFind,
+    ) -> Result<Vec<Response>, MethodError> {
+        let data_property = match account.resource_type {
+            DavResourceType::Calendar => DavProperty::CalendarData,
+            DavResourceType::AddressBook => DavProperty::AddressData,
+        };
+        let fetch_data =
+            matches!(request, PropFind::Prop(properties) if properties.contains(&data_property));
+
+        let mut responses = Vec::with_capacity(items.len());
+        for item in items {
+            let data = if fetch_data {
+                self.get_blob(&item.blob_hash, 0..usize::MAX)
+                    .await?
+                    .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            } else {
+                None
+            };
+
+            responses.push(Response::with_propstat(
+                account.resource_href(collection, &item.name),
+                build_propstat(request, RESOURCE_PROPERTIES, |property| {
+                    match property {
+                        DavProperty::ResourceType => PropValue::Empty,
+                        DavProperty::GetETag => PropValue::text(&item.etag),
+                        DavProperty::GetContentType => {
+                            PropValue::text(account.resource_type.content_type())
+                        }
+                        DavProperty::GetContentLength => PropValue::text(item.size),
+                        property if property == &data_property => PropValue::text(data.as_deref()?),
+                        _ => return None,
+                    }
+                    .into()
+                }),
+            ));
+        }
+
+        Ok(responses)
+    }
+
+    async fn dav_collection_response(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        document_id: u32,
+        collection: &Object<Value>,
+        privileges: &Bitmap<Acl>,
+        request: &PropFind,
+    ) -> Result<Response, MethodError> {
+        let name = collection
+            .properties
+            .get(&Property::Name)
+            .and_then(|name| name.as_string())
+            .unwrap_or_default();
+        let change_id = self
+            .get_property::<u64>(
+                account.account_id,
+                account.container(),
+                document_id,
+                Property::Cid,
+            )
+            .await?
+            .unwrap_or_default();
+        let current_user = principal_href(&access_token.name);
+
+        Ok(Response::with_propstat(
+            account.collection_href(name),
+            build_propstat(request, COLLECTION_PROPERTIES, |property| {
+                match property {
+                    DavProperty::ResourceType => {
+                        PropValue::ResourceType(Some(account.resource_type))
+                    }
+                    DavProperty::DisplayName => PropValue::text(
+                        collection
+                            .properties
+                            .get(&Property::Description)
+                            .and_then(|name| name.as_string())
+                            .unwrap_or(name),
+                    ),
+                    DavProperty::Owner => PropValue::Href(principal_href(&account.name)),
+                    DavProperty::CurrentUserPrincipal => PropValue::Href(current_user.clone()),
+                    DavProperty::CurrentUserPrivilegeSet => {
+                        PropValue::Privileges(privilege_names(privileges))
+                    }
+                    DavProperty::SupportedReportSet => {
+                        PropValue::Reports(match account.resource_type {
+                            DavResourceType::Calendar => vec![
+                                ("D", "sync-collection"),
+                                ("C", "calendar-multiget"),
+                                ("C", "calendar-query"),
+                            ],
+                            DavResourceType::AddressBook => vec![
+                                ("D", "sync-collection"),
+                                ("CR", "addressbook-multiget"),
+                                ("CR", "addressbook-query"),
+                            ],
+                        })
+                    }
+                    DavProperty::SyncToken => PropValue::Text(sync_token(change_id)),
+                    DavProperty::GetCTag => PropValue::text(change_id),
+                    DavProperty::SupportedCalendarComponentSet
+                        if account.resource_type == DavResourceType::Calendar =>
+                    {
+                        PropValue::Components(vec!["VEVENT", "VTODO", "VJOURNAL"])
+                    }
+                    _ => return None,
+                }
+                .into()
+            }),
+        ))
+    }
+}
+
+/// Groups the requested properties into found (200) and not found (404)
+/// property sets. Properties not listed as supported are only returned when
+/// explicitly requested.
+pub fn build_propstat(
+    request: &PropFind,
+    supported: &[DavProperty],
+    mut value: impl FnMut(&DavProperty) -> Option<PropValue>,
+) -> Vec<PropStat> {
+    let mut found = PropStat::new(200);
+    let mut not_found = PropStat::new(404);
+
+    match request {
+        PropFind::AllProp => {
+            for property in supported {
+                if let Some(value) = value(property) {
+                    found.properties.push((property.clone(), value));
+                }
+            }
+        }
+        PropFind::PropName => {
+            for property in supported {
+                if value(property).is_some() {
+                    found.properties.push((property.clone(), PropValue::Empty));
+                }
+            }
+        }
+        PropFind::Prop(properties) => {
+            for property in properties {
+                if let Some(value) = value(property) {
+                    found.properties.push((property.clone(), value));
+                } else {
+                    not_found
+                        .properties
+                        .push((property.clone(), PropValue::Empty));
+                }
+            }
+        }
+    }
+
+    [found, not_found]
+        .into_iter()
+        .filter(|propstat| !propstat.properties.is_empty())
+        .collect()
+}
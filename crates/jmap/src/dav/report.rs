@@ -0,0 +1,255 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use dav::{
+    request::{PropFind, Report},
+    response::{Condition, MultiStatus, Response},
+    DavPath,
+};
+use hyper::StatusCode;
+use jmap_proto::{
+    error::method::MethodError,
+    types::{acl::Acl, id::Id, property::Property},
+};
+use store::{query::log::Query, roaring::RoaringBitmap};
+
+use crate::{auth::AccessToken, JMAP};
+
+use super::{sync_token, DavAccount, DavResponse};
+
+impl JMAP {
+    pub async fn dav_report(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        name: &str,
+        request: Report,
+    ) -> Result<DavResponse, MethodError> {
+        let (collection_id, collection) = match self.dav_collection(account, name).await? {
+            Some(collection) => collection,
+            None => return Ok(DavResponse::new(StatusCode::NOT_FOUND)),
+        };
+        let privileges = self.dav_privileges(access_token, account.account_id, &collection.inner);
+        if !privileges.contains(Acl::Read) || !privileges.contains(Acl::ReadItems) {
+            return Ok(DavResponse::new(StatusCode::FORBIDDEN));
+        }
+
+        match request {
+            Report::SyncCollection {
+                sync_token,
+                properties,
+            } => {
+                self.dav_sync_collection(account, name, collection_id, sync_token, properties)
+                    .await
+            }
+            Report::Multiget { hrefs, properties } => {
+                let mut items = Vec::with_capacity(hrefs.len());
+                let mut not_found = Vec::new();
+                for href in hrefs {
+                    let item = match DavPath::parse(&href) {
+                        Some(DavPath::Resource {
+                            resource_type,
+                            account: account_name,
+                            collection,
+                            name: resource_name,
+                        }) if resource_type == account.resource_type
+                            && account_name == account.name
+                            && collection == name =>
+                        {
+                            self.dav_resource_by_name(account, collection_id, &resource_name)
+                                .await?
+                        }
+                        _ => None,
+                    };
+                    if let Some((_, _, item)) = item {
+                        items.push(item);
+                    } else {
+                        not_found.push(Response::with_status(href, 404));
+                    }
+                }
+
+                let mut responses = self
+                    .dav_item_responses(account, name, items, &properties)
+                    .await?;
+                responses.extend(not_found);
+                Ok(DavResponse::multi_status(MultiStatus::new(responses)))
+            }
+            Report::Query {
+                component,
+                properties,
+            } => {
+                let items = self
+                    .dav_items(
+                        account,
+                        &self.dav_collection_items(account, collection_id).await?,
+                    )
+                    .await?
+                    .into_iter()
+                    .filter(|item| {
+                        component.as_ref().map_or(true, |component| {
+                            item.component
+                                .as_ref()
+                                .map_or(false, |c| c.eq_ignore_ascii_case(component))
+                        })
+                    })
+                    .collect();
+
+                Ok(DavResponse::multi_status(MultiStatus::new(
+                    self.dav_item_responses(account, name, items, &properties)
+                        .await?,
+                )))
+            }
+        }
+    }
+
+    /// Implements the sync-collection report (RFC 6578). Sync tokens are the
+    /// change ids of the collection, removed resources are reported using the
+    /// tombstones written on deletion.
+    async fn dav_sync_collection(
+        &self,
+        account: &DavAccount,
+        name: &str,
+        collection_id: u32,
+        token: Option<String>,
+        properties: PropFind,
+    ) -> Result<DavResponse, MethodError> {
+        let current_token = self
+            .get_property::<u64>(
+                account.account_id,
+                account.container(),
+                collection_id,
+                Property::Cid,
+            )
+            .await?
+            .unwrap_or_default();
+        let document_ids = self.dav_collection_items(account, collection_id).await?;
+
+        // Initial synchronization
+        let token = match token.filter(|token| !token.is_empty()) {
+            Some(token) => token,
+            None => {
+                let items = self.dav_items(account, &document_ids).await?;
+                return Ok(DavResponse::multi_status(
+                    MultiStatus::new(
+                        self.dav_item_responses(account, name, items, &properties)
+                            .await?,
+                    )
+                    .with_sync_token(sync_token(current_token)),
+                ));
+            }
+        };
+
+        // Make sure the token is valid and within the change history period
+        let change_id = match token
+            .strip_prefix("urn:stalwart:sync:")
+            .and_then(|id| id.parse::<u64>().ok())
+        {
+            Some(change_id)
+                if change_id <= current_token
+                    && self
+                        .core
+                        .jmap
+                        .changes_max_history
+                        .and_then(|history| self.inner.snowflake_id.past_id(history))
+                        .map_or(true, |oldest| change_id >= oldest) =>
+            {
+                change_id
+            }
+            _ => {
+                return Ok(DavResponse::condition(
+                    StatusCode::FORBIDDEN,
+                    Condition::ValidSyncToken,
+                ))
+            }
+        };
+
+        // Obtain changes since the token
+        let mut changed_ids = RoaringBitmap::new();
+        for change in self
+            .changes_(account.account_id, account.items(), Query::Since(change_id))
+            .await?
+            .changes
+        {
+            let id = Id::from(change.id());
+            if id.prefix_id() == collection_id {
+                changed_ids.insert(id.document_id());
+            }
+        }
+
+        let mut existing_ids = changed_ids.clone();
+        existing_ids &= &document_ids;
+        let items = self.dav_items(account, &existing_ids).await?;
+        let mut removed = Vec::new();
+        for document_id in &changed_ids {
+            // Document ids can be reused, report the previous resource as
+            // removed when its name differs from the current one
+            let tombstone = self
+                .dav_resource_tombstone(account, Id::from_parts(collection_id, document_id))
+                .await?;
+            match (
+                tombstone,
+                items.iter().find(|item| item.document_id == document_id),
+            ) {
+                (Some(tombstone), Some(item)) if tombstone != item.name => {
+                    removed.push(tombstone);
+                }
+                (Some(tombstone), None) => {
+                    removed.push(tombstone);
+                }
+                (None, None) => {
+                    return Ok(DavResponse::condition(
+                        StatusCode::FORBIDDEN,
+                        Condition::ValidSyncToken,
+                    ));
+                }
+                _ => (),
+            }
+        }
+
+        // Skip names that were reused by a resource created later on
+        let mut removed_ = Vec::with_capacity(removed.len());
+        for resource in removed {
+            if !removed_.contains(&resource)
+                && self
+                    .dav_resource_by_name(account, collection_id, &resource)
+                    .await?
+                    .is_none()
+            {
+                removed_.push(resource);
+            }
+        }
+
+        let mut responses = self
+            .dav_item_responses(account, name, items, &properties)
+            .await?;
+        responses.extend(
+            removed_
+                .into_iter()
+                .map(|resource| Response::with_status(account.resource_href(name, &resource), 404)),
+        );
+
+        Ok(DavResponse::multi_status(
+            MultiStatus::new(responses).with_sync_token(sync_token(current_token)),
+        ))
+    }
+}
@@ -0,0 +1,731 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use dav::{object::DavObject, response::Condition, DavResourceType, Preconditions};
+use hyper::StatusCode;
+use jmap_proto::{
+    error::method::MethodError,
+    object::{
+        index::{IndexAs, IndexProperty, ObjectIndexBuilder},
+        Object,
+    },
+    types::{
+        acl::Acl, blob::BlobId, id::Id, property::Property, state::StateChange,
+        type_state::DataType, value::Value,
+    },
+};
+use store::{
+    ahash::AHashMap,
+    query::{
+        log::{Change, Query},
+        Filter,
+    },
+    roaring::RoaringBitmap,
+    write::{
+        assert::HashedValue, log::Changes, AssignedIds, BatchBuilder, BlobOp, DirectoryClass,
+        MaybeDynamicValue, SerializeWithId, F_VALUE,
+    },
+    BlobClass, Serialize,
+};
+use utils::BlobHash;
+
+use crate::{auth::AccessToken, JMAP};
+
+use super::{etag, DavAccount, DavResponse};
+
+pub static SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::Name)
+        .index_as(IndexAs::Text {
+            tokenize: false,
+            index: true,
+        })
+        .required(),
+    IndexProperty::new(Property::ParentId).index_as(IndexAs::Integer),
+    IndexProperty::new(Property::Uid).index_as(IndexAs::Text {
+        tokenize: false,
+        index: true,
+    }),
+];
+
+/// Calendar object or vCard stored in a collection.
+pub struct DavItem {
+    pub document_id: u32,
+    pub name: String,
+    pub etag: String,
+    pub size: usize,
+    pub blob_hash: BlobHash,
+    pub component: Option<String>,
+}
+
+/// Reasons a calendar object or vCard is rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DavResourceError {
+    TooLarge,
+    InvalidData,
+    UidConflict,
+    OverQuota,
+}
+
+/// Logs the insertion of a resource, using the collection id as the prefix
+/// so sync reports can tell which collection the change belongs to.
+pub struct LogDavInsert(u32);
+
+impl JMAP {
+    pub async fn dav_get(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        collection: &str,
+        name: &str,
+        is_head: bool,
+    ) -> Result<DavResponse, MethodError> {
+        let item = match self
+            .dav_resource(access_token, account, collection, name, Acl::ReadItems)
+            .await?
+        {
+            Ok((_, _, _, item)) => item,
+            Err(response) => return Ok(response),
+        };
+
+        let response = DavResponse::new(StatusCode::OK).with_header("ETag", item.etag);
+        if is_head {
+            return Ok(response
+                .with_header("Content-Type", account.resource_type.content_type())
+                .with_header("Content-Length", item.size.to_string()));
+        }
+
+        match self.get_blob(&item.blob_hash, 0..usize::MAX).await? {
+            Some(bytes) => Ok(response.with_body(account.resource_type.content_type(), bytes)),
+            None => {
+                tracing::warn!(
+                    event = "error",
+                    context = "dav_get",
+                    account_id = account.account_id,
+                    document_id = item.document_id,
+                    "Blob not found."
+                );
+                Ok(DavResponse::new(StatusCode::NOT_FOUND))
+            }
+        }
+    }
+
+    pub async fn dav_put(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        collection: &str,
+        name: String,
+        preconditions: Preconditions,
+        bytes: Vec<u8>,
+    ) -> Result<DavResponse, MethodError> {
+        let (collection_id, collection) = match self.dav_collection(account, collection).await? {
+            Some(collection) => collection,
+            None => return Ok(DavResponse::new(StatusCode::CONFLICT)),
+        };
+
+        // Validate the object
+        if name.len() > 255 {
+            return Ok(
+                DavResponse::new(StatusCode::FORBIDDEN).with_text("Resource name is too long.")
+            );
+        }
+        let object = match self.dav_resource_parse(account.resource_type, &bytes) {
+            Ok(object) => object,
+            Err(err) => return Ok(err.into_response(account.resource_type)),
+        };
+
+        // Evaluate preconditions and privileges
+        let current = self
+            .dav_resource_by_name(account, collection_id, &name)
+            .await?;
+        if !preconditions.is_satisfied(current.as_ref().map(|(_, _, item)| item.etag.as_str())) {
+            return Ok(DavResponse::new(StatusCode::PRECONDITION_FAILED));
+        }
+        if !self
+            .dav_privileges(access_token, account.account_id, &collection.inner)
+            .contains(if current.is_some() {
+                Acl::ModifyItems
+            } else {
+                Acl::AddItems
+            })
+        {
+            return Ok(DavResponse::new(StatusCode::FORBIDDEN));
+        }
+        if let Err(err) = self
+            .dav_resource_check(
+                access_token,
+                account,
+                collection_id,
+                current
+                    .as_ref()
+                    .map(|(document_id, _, item)| (*document_id, item.size)),
+                &object.uid,
+                bytes.len(),
+            )
+            .await?
+        {
+            return Ok(err.into_response(account.resource_type));
+        }
+
+        // Write resource
+        let status = if current.is_some() {
+            StatusCode::NO_CONTENT
+        } else {
+            StatusCode::CREATED
+        };
+        let (_, change_id, _) = self
+            .dav_resource_store(account, collection_id, current, name, object, &bytes)
+            .await?;
+
+        Ok(DavResponse::new(status).with_header("ETag", etag(change_id)))
+    }
+
+    /// Parses a calendar object or vCard, making sure it does not exceed the
+    /// maximum resource size.
+    pub fn dav_resource_parse(
+        &self,
+        resource_type: DavResourceType,
+        bytes: &[u8],
+    ) -> Result<DavObject, DavResourceError> {
+        if bytes.len() > self.core.dav.max_resource_size {
+            return Err(DavResourceError::TooLarge);
+        }
+        match DavObject::parse(resource_type, bytes) {
+            Ok(object) if object.uid.len() <= 255 => Ok(object),
+            _ => Err(DavResourceError::InvalidData),
+        }
+    }
+
+    /// Makes sure the UID is unique within the collection and that the
+    /// account has enough quota left to store the resource.
+    pub async fn dav_resource_check(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        collection_id: u32,
+        current: Option<(u32, usize)>,
+        uid: &str,
+        size: usize,
+    ) -> Result<Result<(), DavResourceError>, MethodError> {
+        if self
+            .filter(
+                account.account_id,
+                account.items(),
+                vec![
+                    Filter::eq(Property::Uid, uid),
+                    Filter::eq(Property::ParentId, collection_id),
+                ],
+            )
+            .await?
+            .results
+            .iter()
+            .any(|document_id| current.map_or(true, |(current_id, _)| current_id != document_id))
+        {
+            return Ok(Err(DavResourceError::UidConflict));
+        }
+
+        let quota = self.get_quota(access_token, account.account_id).await?;
+        if quota > 0
+            && size as i64 - current.map_or(0, |(_, current_size)| current_size as i64)
+                + self.get_used_quota(account.account_id).await?
+                > quota
+        {
+            return Ok(Err(DavResourceError::OverQuota));
+        }
+
+        Ok(Ok(()))
+    }
+
+    /// Creates or replaces a resource, returning its document id, change id
+    /// and blob id. The name is only stored when the resource is created.
+    pub async fn dav_resource_store(
+        &self,
+        account: &DavAccount,
+        collection_id: u32,
+        current: Option<(u32, HashedValue<Object<Value>>, DavItem)>,
+        name: String,
+        object: DavObject,
+        bytes: &[u8],
+    ) -> Result<(u32, u64, BlobId), MethodError> {
+        // Write blob
+        let size = bytes.len() as i64;
+        let mut blob_id = BlobId::new(
+            self.put_blob(account.account_id, bytes, false).await?.hash,
+            BlobClass::Linked {
+                account_id: account.account_id,
+                collection: account.items().into(),
+                document_id: current
+                    .as_ref()
+                    .map_or(0, |(document_id, _, _)| *document_id),
+            },
+        )
+        .with_section_size(bytes.len());
+        let mut changes = Object::with_capacity(5)
+            .with_property(Property::Uid, object.uid)
+            .with_property(Property::BlobId, Value::BlobId(blob_id.clone()));
+        if let Some(component) = object.component {
+            changes.set(Property::Type, component);
+        } else if current.is_some() {
+            changes.set(Property::Type, Value::Null);
+        }
+
+        // Write record
+        let change_id = self.assign_change_id(account.account_id).await?;
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_change_id(change_id)
+            .with_account_id(account.account_id)
+            .with_collection(account.items());
+        let document_id = if let Some((document_id, resource, item)) = current {
+            batch
+                .update_document(document_id)
+                .log(Changes::update([Id::from_parts(
+                    collection_id,
+                    document_id,
+                )]))
+                .clear(BlobOp::Link {
+                    hash: item.blob_hash,
+                })
+                .add(
+                    DirectoryClass::UsedQuota(account.account_id),
+                    size - item.size as i64,
+                )
+                .custom(
+                    ObjectIndexBuilder::new(SCHEMA)
+                        .with_current(resource)
+                        .with_changes(changes),
+                );
+            Some(document_id)
+        } else {
+            changes.set(Property::Name, name);
+            changes.set(Property::ParentId, Value::Id(Id::from(collection_id)));
+            batch
+                .create_document()
+                .log(LogDavInsert(collection_id))
+                .add(DirectoryClass::UsedQuota(account.account_id), size)
+                .value(Property::ParentId, collection_id, F_VALUE)
+                .custom(ObjectIndexBuilder::new(SCHEMA).with_changes(changes));
+            None
+        };
+        batch
+            .set(
+                BlobOp::Link {
+                    hash: blob_id.hash.clone(),
+                },
+                Vec::new(),
+            )
+            .value(Property::Cid, change_id, F_VALUE)
+            .with_collection(account.container())
+            .update_document(collection_id)
+            .value(Property::Cid, change_id, F_VALUE);
+        let document_id = if let Some(document_id) = document_id {
+            self.write_batch(batch).await?;
+            document_id
+        } else {
+            let document_id = self.write_batch_expect_id(batch).await?;
+            blob_id.class = BlobClass::Linked {
+                account_id: account.account_id,
+                collection: account.items().into(),
+                document_id,
+            };
+            document_id
+        };
+        self.dav_broadcast_change(account, change_id).await;
+
+        Ok((document_id, change_id, blob_id))
+    }
+
+    pub async fn dav_delete(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        collection: &str,
+        name: &str,
+        preconditions: Preconditions,
+    ) -> Result<DavResponse, MethodError> {
+        let (collection_id, document_id, resource, item) = match self
+            .dav_resource(access_token, account, collection, name, Acl::RemoveItems)
+            .await?
+        {
+            Ok(resource) => resource,
+            Err(response) => return Ok(response),
+        };
+        if !preconditions.is_satisfied(Some(&item.etag)) {
+            return Ok(DavResponse::new(StatusCode::PRECONDITION_FAILED));
+        }
+
+        self.dav_resource_destroy(account, collection_id, document_id, resource)
+            .await?;
+        self.dav_resource_tombstone_set(account, Id::from_parts(collection_id, document_id), name)
+            .await;
+
+        Ok(DavResponse::new(StatusCode::NO_CONTENT))
+    }
+
+    pub async fn dav_resource_destroy(
+        &self,
+        account: &DavAccount,
+        collection_id: u32,
+        document_id: u32,
+        resource: HashedValue<Object<Value>>,
+    ) -> Result<u64, MethodError> {
+        let blob_id = match resource.inner.properties.get(&Property::BlobId) {
+            Some(Value::BlobId(blob_id)) => blob_id.clone(),
+            _ => {
+                tracing::warn!(
+                    event = "error",
+                    context = "dav_resource_destroy",
+                    account_id = account.account_id,
+                    document_id = document_id,
+                    "Resource does not contain a blobId."
+                );
+                return Err(MethodError::ServerPartialFail);
+            }
+        };
+        let change_id = self.assign_change_id(account.account_id).await?;
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_change_id(change_id)
+            .with_account_id(account.account_id)
+            .with_collection(account.items())
+            .delete_document(document_id)
+            .log(Changes::delete([Id::from_parts(
+                collection_id,
+                document_id,
+            )]))
+            .clear(BlobOp::Link { hash: blob_id.hash })
+            .add(
+                DirectoryClass::UsedQuota(account.account_id),
+                -(blob_id.section.map_or(0, |section| section.size) as i64),
+            )
+            .clear(Property::Cid)
+            .clear(Property::ParentId)
+            .custom(ObjectIndexBuilder::new(SCHEMA).with_current(resource))
+            .with_collection(account.container())
+            .update_document(collection_id)
+            .value(Property::Cid, change_id, F_VALUE);
+        self.write_batch(batch).await?;
+        self.dav_broadcast_change(account, change_id).await;
+
+        Ok(change_id)
+    }
+
+    /// Notifies JMAP push subscribers about changes made over either protocol.
+    async fn dav_broadcast_change(&self, account: &DavAccount, change_id: u64) {
+        let data_type = match account.resource_type {
+            DavResourceType::Calendar => DataType::CalendarEvent,
+            DavResourceType::AddressBook => DataType::ContactCard,
+        };
+        self.broadcast_state_change(
+            StateChange::new(account.account_id).with_change(data_type, change_id),
+        )
+        .await;
+    }
+
+    /// Keeps the name of a deleted resource for the change history period,
+    /// so sync reports can include the removed href.
+    pub async fn dav_resource_tombstone_set(&self, account: &DavAccount, id: Id, name: &str) {
+        if let Err(err) = self
+            .core
+            .storage
+            .lookup
+            .key_set(
+                tombstone_key(account, id),
+                name.as_bytes().to_vec(),
+                self.core
+                    .jmap
+                    .changes_max_history
+                    .map(|history| history.as_secs()),
+            )
+            .await
+        {
+            tracing::warn!(
+                event = "error",
+                context = "dav_resource_tombstone",
+                account_id = account.account_id,
+                document_id = id.document_id(),
+                reason = ?err,
+                "Failed to store resource tombstone."
+            );
+        }
+    }
+
+    /// Returns the name of a deleted resource, if it is still known.
+    pub async fn dav_resource_tombstone(
+        &self,
+        account: &DavAccount,
+        id: Id,
+    ) -> Result<Option<String>, MethodError> {
+        self.core
+            .storage
+            .lookup
+            .key_get::<String>(tombstone_key(account, id))
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    event = "error",
+                    context = "dav_resource_tombstone",
+                    account_id = account.account_id,
+                    error = ?err,
+                    "Failed to retrieve resource tombstone.");
+                MethodError::ServerPartialFail
+            })
+    }
+
+    /// Removes the tombstones of the resources deleted from a collection.
+    pub async fn dav_resource_tombstones_purge(
+        &self,
+        account: &DavAccount,
+        collection_id: u32,
+    ) -> Result<(), MethodError> {
+        for change in self
+            .changes_(account.account_id, account.items(), Query::All)
+            .await?
+            .changes
+        {
+            let id = match change {
+                Change::Delete(id) => Id::from(id),
+                _ => continue,
+            };
+            if id.prefix_id() == collection_id {
+                if let Err(err) = self
+                    .core
+                    .storage
+                    .lookup
+                    .key_delete(tombstone_key(account, id))
+                    .await
+                {
+                    tracing::warn!(
+                        event = "error",
+                        context = "dav_resource_tombstones_purge",
+                        account_id = account.account_id,
+                        reason = ?err,
+                        "Failed to delete resource tombstone."
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn dav_collection_items(
+        &self,
+        account: &DavAccount,
+        collection_id: u32,
+    ) -> Result<RoaringBitmap, MethodError> {
+        self.filter(
+            account.account_id,
+            account.items(),
+            vec![Filter::eq(Property::ParentId, collection_id)],
+        )
+        .await
+        .map(|result| result.results)
+    }
+
+    pub async fn dav_items(
+        &self,
+        account: &DavAccount,
+        document_ids: &RoaringBitmap,
+    ) -> Result<Vec<DavItem>, MethodError> {
+        let change_ids = self
+            .get_properties::<u64, _, _>(
+                account.account_id,
+                account.items(),
+                document_ids,
+                Property::Cid,
+            )
+            .await?
+            .into_iter()
+            .collect::<AHashMap<_, _>>();
+
+        Ok(self
+            .get_properties::<Object<Value>, _, _>(
+                account.account_id,
+                account.items(),
+                document_ids,
+                Property::Value,
+            )
+            .await?
+            .into_iter()
+            .filter_map(|(document_id, resource)| {
+                DavItem::new(
+                    document_id,
+                    change_ids.get(&document_id).copied().unwrap_or_default(),
+                    &resource,
+                )
+            })
+            .collect())
+    }
+
+    /// Obtains a resource after verifying that the user holds the requested
+    /// privilege on its collection.
+    #[allow(clippy::type_complexity)]
+    pub async fn dav_resource(
+        &self,
+        access_token: &AccessToken,
+        account: &DavAccount,
+        collection: &str,
+        name: &str,
+        acl: Acl,
+    ) -> Result<Result<(u32, u32, HashedValue<Object<Value>>, DavItem), DavResponse>, MethodError>
+    {
+        let (collection_id, collection) = match self.dav_collection(account, collection).await? {
+            Some(collection) => collection,
+            None => return Ok(Err(DavResponse::new(StatusCode::NOT_FOUND))),
+        };
+        if !self
+            .dav_privileges(access_token, account.account_id, &collection.inner)
+            .contains(acl)
+        {
+            return Ok(Err(DavResponse::new(StatusCode::FORBIDDEN)));
+        }
+
+        Ok(
+            match self
+                .dav_resource_by_name(account, collection_id, name)
+                .await?
+            {
+                Some((document_id, resource, item)) => {
+                    Ok((collection_id, document_id, resource, item))
+                }
+                None => Err(DavResponse::new(StatusCode::NOT_FOUND)),
+            },
+        )
+    }
+
+    pub async fn dav_resource_by_name(
+        &self,
+        account: &DavAccount,
+        collection_id: u32,
+        name: &str,
+    ) -> Result<Option<(u32, HashedValue<Object<Value>>, DavItem)>, MethodError> {
+        let document_id = match self
+            .filter(
+                account.account_id,
+                account.items(),
+                vec![
+                    Filter::eq(Property::Name, name),
+                    Filter::eq(Property::ParentId, collection_id),
+                ],
+            )
+            .await?
+            .results
+            .min()
+        {
+            Some(document_id) => document_id,
+            None => return Ok(None),
+        };
+        let resource = match self
+            .get_property::<HashedValue<Object<Value>>>(
+                account.account_id,
+                account.items(),
+                document_id,
+                Property::Value,
+            )
+            .await?
+        {
+            Some(resource) => resource,
+            None => return Ok(None),
+        };
+        let change_id = self
+            .get_property::<u64>(
+                account.account_id,
+                account.items(),
+                document_id,
+                Property::Cid,
+            )
+            .await?
+            .unwrap_or_default();
+
+        Ok(DavItem::new(document_id, change_id, &resource.inner)
+            .map(|item| (document_id, resource, item)))
+    }
+}
+
+impl DavItem {
+    pub fn new(document_id: u32, change_id: u64, resource: &Object<Value>) -> Option<Self> {
+        let blob_id = match resource.properties.get(&Property::BlobId)? {
+            Value::BlobId(blob_id) => blob_id,
+            _ => return None,
+        };
+
+        Some(DavItem {
+            document_id,
+            name: resource
+                .properties
+                .get(&Property::Name)?
+                .as_string()?
+                .to_string(),
+            etag: etag(change_id),
+            size: blob_id.section.as_ref().map_or(0, |section| section.size),
+            blob_hash: blob_id.hash.clone(),
+            component: resource
+                .properties
+                .get(&Property::Type)
+                .and_then(|component| component.as_string())
+                .map(|component| component.to_string()),
+        })
+    }
+}
+
+impl DavResourceError {
+    pub fn into_response(self, resource_type: DavResourceType) -> DavResponse {
+        match self {
+            DavResourceError::TooLarge => DavResponse::condition(
+                StatusCode::FORBIDDEN,
+                Condition::MaxResourceSize(resource_type),
+            ),
+            DavResourceError::InvalidData => {
+                DavResponse::condition(StatusCode::FORBIDDEN, Condition::ValidData(resource_type))
+            }
+            DavResourceError::UidConflict => DavResponse::condition(
+                StatusCode::FORBIDDEN,
+                Condition::NoUidConflict(resource_type),
+            ),
+            DavResourceError::OverQuota => DavResponse::new(StatusCode::INSUFFICIENT_STORAGE),
+        }
+    }
+}
+
+impl SerializeWithId for LogDavInsert {
+    fn serialize_with_id(&self, ids: &AssignedIds) -> store::Result<Vec<u8>> {
+        Ok(Changes::insert([Id::from_parts(self.0, ids.last_document_id()?)]).serialize())
+    }
+}
+
+impl From<LogDavInsert> for MaybeDynamicValue {
+    fn from(log: LogDavInsert) -> Self {
+        MaybeDynamicValue::Dynamic(Box::new(log))
+    }
+}
+
+fn tombstone_key(account: &DavAccount, id: Id) -> Vec<u8> {
+    format!(
+        "dav:{}:{}:{}",
+        account.account_id,
+        u8::from(account.items()),
+        u64::from(id)
+    )
+    .into_bytes()
+}
@@ -0,0 +1,310 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use common::config::jmap::settings::AccountBayes;
+use jmap_proto::types::{collection::Collection, property::Property};
+use mail_parser::{parsers::fields::thread::thread_name, Message, MessageParser};
+use nlp::{
+    bayes::{tokenize::BayesTokenizer, BayesClassifier, BayesModel, TokenHash, Weights},
+    tokenizers::osb::{OsbToken, OsbTokenizer},
+};
+use smtp::inbound::rspamd::rspamd_learn;
+use store::{
+    write::{key::KeySerializer, Bincode},
+    LookupStore, U32_LEN, U64_LEN,
+};
+
+use crate::JMAP;
+
+use super::metadata::MessageMetadata;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BayesVerdict {
+    Spam,
+    Ham,
+}
+
+impl JMAP {
+    pub fn has_spam_training(&self) -> bool {
+        self.core.jmap.spam_bayes_account.is_some()
+            || self
+                .core
+                .smtp
+                .session
+                .data
+                .rspamd
+                .as_ref()
+                .map_or(false, |rspamd| rspamd.learn)
+    }
+
+    /// Trains the account's Bayes model and rspamd with messages the user
+    /// moved into (spam) or out of (ham) the Junk folder.
+    pub async fn bayes_train_account(
+        &self,
+        account_id: u32,
+        document_ids: Vec<u32>,
+        is_spam: bool,
+    ) {
+        let store = self
+            .core
+            .jmap
+            .spam_bayes_account
+            .as_ref()
+            .map(|config| self.bayes_lookup(config));
+        let rspamd = self
+            .core
+            .smtp
+            .session
+            .data
+            .rspamd
+            .as_ref()
+            .filter(|rspamd| rspamd.learn);
+        if store.is_none() && rspamd.is_none() {
+            return;
+        }
+
+        for document_id in document_ids {
+            // Fetch message
+            let metadata = match self
+                .get_property::<Bincode<MessageMetadata>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    &Property::BodyStructure,
+                )
+                .await
+            {
+                Ok(Some(metadata)) => metadata.inner,
+                _ => continue,
+            };
+            let raw_message = match self.get_blob(&metadata.blob_hash, 0..usize::MAX).await {
+                Ok(Some(raw_message)) => raw_message,
+                _ => continue,
+            };
+
+            // Train rspamd
+            if let Some(rspamd) = rspamd {
+                if let Err(err) = rspamd_learn(rspamd, &raw_message, is_spam).await {
+                    tracing::warn!(
+                        context = "rspamd",
+                        event = "error",
+                        account_id = account_id,
+                        reason = err,
+                        "Failed to train rspamd."
+                    );
+                }
+            }
+
+            // Train the account model
+            let store = match store {
+                Some(store) => store,
+                None => continue,
+            };
+            let text = match MessageParser::new().parse(&raw_message) {
+                Some(message) => bayes_text(&message),
+                None => continue,
+            };
+            let mut model = BayesModel::default();
+            model.train(
+                OsbTokenizer::new(
+                    BayesTokenizer::new(text.as_ref(), &self.core.smtp.resolvers.psl),
+                    5,
+                ),
+                is_spam,
+            );
+            if model.weights.is_empty() {
+                continue;
+            }
+            let num_tokens = model.weights.len();
+
+            let learns = if is_spam {
+                Weights { spam: 1, ham: 0 }
+            } else {
+                Weights { spam: 0, ham: 1 }
+            };
+            for (hash, weights) in model
+                .weights
+                .into_iter()
+                .chain([(TokenHash::default(), learns)])
+            {
+                if let Err(err) = store
+                    .counter_incr(
+                        account_token_key(account_id, hash),
+                        weights.into(),
+                        None,
+                        false,
+                    )
+                    .await
+                {
+                    tracing::warn!(
+                        context = "bayes",
+                        event = "error",
+                        account_id = account_id,
+                        reason = ?err,
+                        "Failed to train account Bayes model."
+                    );
+                    return;
+                }
+            }
+
+            tracing::debug!(
+                context = "bayes",
+                event = "train",
+                account_id = account_id,
+                document_id = document_id,
+                is_spam = is_spam,
+                num_tokens = num_tokens,
+            );
+        }
+    }
+
+    /// Classifies a message using the account's Bayes model blended with the
+    /// global model. Returns `None` when the account model is not trained
+    /// enough or the result falls between the ham and spam thresholds.
+    pub async fn bayes_classify_account(
+        &self,
+        account_id: u32,
+        message: &Message<'_>,
+    ) -> Option<BayesVerdict> {
+        let config = self.core.jmap.spam_bayes_account.as_ref()?;
+        let store = self.bayes_lookup(config);
+
+        // Make sure the account model has enough training data
+        let learns = Weights::from(
+            store
+                .counter_get(account_token_key(account_id, TokenHash::default()))
+                .await
+                .ok()?,
+        );
+        if learns.spam < config.classifier.min_learns || learns.ham < config.classifier.min_learns {
+            return None;
+        }
+        let global_learns = self.bayes_global_weights(store, TokenHash::default()).await;
+
+        // Obtain token weights from both models
+        let text = bayes_text(message);
+        let tokens = OsbTokenizer::<_, TokenHash>::new(
+            BayesTokenizer::new(text.as_ref(), &self.core.smtp.resolvers.psl),
+            5,
+        )
+        .collect::<Vec<_>>();
+        let mut account_tokens = Vec::with_capacity(tokens.len());
+        let mut global_tokens = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let weights = store
+                .counter_get(account_token_key(account_id, token.inner))
+                .await
+                .ok()?;
+            account_tokens.push(OsbToken {
+                inner: Weights::from(weights),
+                idx: token.idx,
+            });
+            if global_learns.is_some() {
+                global_tokens.push(OsbToken {
+                    inner: self
+                        .bayes_global_weights(store, token.inner)
+                        .await
+                        .unwrap_or_default(),
+                    idx: token.idx,
+                });
+            }
+        }
+
+        let account_prob =
+            config
+                .classifier
+                .classify(account_tokens.into_iter(), learns.ham, learns.spam)?;
+        let prob = match global_learns.and_then(|learns| {
+            BayesClassifier::default().classify(global_tokens.into_iter(), learns.ham, learns.spam)
+        }) {
+            Some(global_prob) => config.weight * account_prob + (1.0 - config.weight) * global_prob,
+            None => account_prob,
+        };
+
+        tracing::debug!(
+            context = "bayes",
+            event = "classify",
+            account_id = account_id,
+            account_prob = account_prob,
+            prob = prob,
+        );
+
+        if prob >= config.spam_threshold {
+            Some(BayesVerdict::Spam)
+        } else if prob <= config.ham_threshold {
+            Some(BayesVerdict::Ham)
+        } else {
+            None
+        }
+    }
+
+    async fn bayes_global_weights(&self, store: &LookupStore, hash: TokenHash) -> Option<Weights> {
+        let bayes_cache = &self.core.sieve.bayes_cache;
+        if let Some(weights) = bayes_cache.get(&hash) {
+            return weights.unwrap_or_default().into();
+        }
+
+        let num = store
+            .counter_get(
+                KeySerializer::new(U64_LEN)
+                    .write(hash.h1)
+                    .write(hash.h2)
+                    .finalize(),
+            )
+            .await
+            .ok()?;
+        if num != 0 {
+            let weights = Weights::from(num);
+            bayes_cache.insert_positive(hash, weights);
+            Some(weights)
+        } else {
+            bayes_cache.insert_negative(hash);
+            Some(Weights::default())
+        }
+    }
+
+    fn bayes_lookup(&self, config: &AccountBayes) -> &LookupStore {
+        config
+            .lookup
+            .as_ref()
+            .and_then(|id| self.core.storage.lookups.get(id))
+            .unwrap_or(&self.core.storage.lookup)
+    }
+}
+
+fn account_token_key(account_id: u32, hash: TokenHash) -> Vec<u8> {
+    KeySerializer::new(U32_LEN + 2 * U64_LEN)
+        .write(account_id)
+        .write(hash.h1)
+        .write(hash.h2)
+        .finalize()
+}
+
+fn bayes_text(message: &Message<'_>) -> String {
+    let mut text = thread_name(message.subject().unwrap_or_default()).to_string();
+    if let Some(body) = message.body_text(0) {
+        text.push(' ');
+        text.push_str(body.as_ref());
+    }
+    text
+}
@@ -50,14 +50,19 @@ use mail_parser::{parsers::fields::thread::thread_name, HeaderName, HeaderValue}
 use store::{
     write::{
         log::{Changes, LogInsert},
-        BatchBuilder, Bincode, FtsQueueClass, MaybeDynamicId, TagValue, ValueClass, F_BITMAP,
+        now, BatchBuilder, Bincode, FtsQueueClass, MaybeDynamicId, TagValue, ValueClass, F_BITMAP,
         F_VALUE,
     },
     BlobClass, Serialize,
 };
 use utils::map::vec_map::VecMap;
 
-use crate::{auth::AccessToken, mailbox::UidMailbox, services::housekeeper::Event, JMAP};
+use crate::{
+    auth::AccessToken,
+    mailbox::{SavedDate, UidMailbox},
+    services::housekeeper::Event,
+    JMAP,
+};
 
 use super::{
     index::{EmailIndexBuilder, TrimTextValue, VisitValues, MAX_ID_LENGTH, MAX_SORT_FIELD_LENGTH},
@@ -319,6 +324,10 @@ impl JMAP {
         {
             return Ok(Err(SetError::over_quota()));
         }
+        let max_messages = self.core.jmap.mail_max_messages;
+        if max_messages > 0 && self.get_used_messages(account_id).await? >= max_messages {
+            return Ok(Err(SetError::over_quota()));
+        }
 
         // Set receivedAt
         if let Some(received_at) = received_at {
@@ -371,6 +380,8 @@ impl JMAP {
 
         // Assign IMAP UIDs
         let mut mailbox_ids = Vec::with_capacity(mailboxes.len());
+        let mut saved_dates = Vec::with_capacity(mailboxes.len());
+        let saved_at = now();
         email.imap_uids = Vec::with_capacity(mailboxes.len());
         for mailbox_id in &mailboxes {
             let uid = self
@@ -385,6 +396,7 @@ impl JMAP {
                     MethodError::ServerPartialFail
                 })?;
             mailbox_ids.push(UidMailbox::new(*mailbox_id, uid));
+            saved_dates.push(SavedDate::new(*mailbox_id, saved_at));
             email.imap_uids.push(uid);
         }
 
@@ -415,6 +427,7 @@ impl JMAP {
             .tag(Property::ThreadId, TagValue::Id(maybe_thread_id), 0)
             .value(Property::MailboxIds, mailbox_ids, F_VALUE | F_BITMAP)
             .value(Property::Keywords, keywords, F_VALUE | F_BITMAP)
+            .value(Property::SavedAt, saved_dates, F_VALUE)
             .value(Property::Cid, change_id, F_VALUE)
             .set(
                 ValueClass::FtsQueue(FtsQueueClass {
@@ -23,6 +23,8 @@
 
 use std::time::Duration;
 
+use common::config::jmap::retention::{RetentionAction, RetentionPolicy};
+use directory::QueryBy;
 use jmap_proto::{
     error::method::MethodError,
     types::{
@@ -37,7 +39,7 @@ use store::{
         log::ChangeLogBuilder, BatchBuilder, Bincode, BitmapClass, MaybeDynamicId, TagValue,
         ValueClass, F_BITMAP, F_CLEAR, F_VALUE,
     },
-    BitmapKey, IterateParams, ValueKey, U32_LEN,
+    BitmapKey, BlobBackend, IterateParams, TieredBlobStore, ValueKey, U32_LEN,
 };
 use utils::codec::leb128::Leb128Reader;
 
@@ -302,14 +304,24 @@ impl JMAP {
             }
         }
 
-        // Auto-expunge deleted and junk messages
-        if let Some(period) = self.core.jmap.mail_autoexpunge_after {
-            if self.emails_auto_expunge(account_id, period).await.is_err() {
+        // Apply retention policies, which include auto-expunging deleted and junk messages
+        if self.emails_apply_retention(account_id).await.is_err() {
+            tracing::error!(
+                event = "error",
+                context = "email_retention",
+                account_id = account_id,
+                "Failed to apply retention policies."
+            );
+        }
+
+        // Move archived messages to cold storage
+        if let BlobBackend::Tiered(store) = &self.core.storage.blob.backend {
+            if store.archive && self.emails_tier_archived(account_id, store).await.is_err() {
                 tracing::error!(
                     event = "error",
-                    context = "email_auto_expunge",
+                    context = "email_tier_archived",
                     account_id = account_id,
-                    "Failed to auto-expunge messages."
+                    "Failed to migrate archived messages."
                 );
             }
         }
@@ -339,56 +351,101 @@ impl JMAP {
         }
     }
 
-    pub async fn emails_auto_expunge(
-        &self,
-        account_id: u32,
-        period: Duration,
-    ) -> Result<(), MethodError> {
-        let deletion_candidates = self
-            .get_tag(
-                account_id,
-                Collection::Email,
-                Property::MailboxIds,
-                TagValue::Id(TRASH_ID),
-            )
-            .await?
-            .unwrap_or_default()
-            | self
+    pub async fn emails_apply_retention(&self, account_id: u32) -> Result<(), MethodError> {
+        // Account names are only needed to match domain and account policies
+        let policies = &self.core.jmap.mail_retention;
+        let mut names = Vec::new();
+        if policies.iter().any(|policy| policy.is_scoped()) {
+            if let Some(principal) = self
+                .core
+                .storage
+                .directory
+                .query(QueryBy::Id(account_id), false)
+                .await
+                .map_err(|err| {
+                    tracing::error!(
+                        event = "error",
+                        context = "email_retention",
+                        account_id = account_id,
+                        error = ?err,
+                        "Failed to obtain principal."
+                    );
+                    MethodError::ServerPartialFail
+                })?
+            {
+                names.push(principal.name);
+                names.extend(principal.emails);
+            }
+        }
+        let names = names.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+        let mut effective = RetentionPolicy::effective(policies, &names)
+            .into_iter()
+            .map(|policy| (policy.role.as_str(), policy.action, policy.after))
+            .collect::<Vec<_>>();
+
+        // Trash and junk fall back to the auto-expunge period
+        if let Some(period) = self.core.jmap.mail_autoexpunge_after {
+            for role in ["trash", "junk"] {
+                if !effective
+                    .iter()
+                    .any(|(policy_role, _, _)| *policy_role == role)
+                {
+                    effective.push((role, RetentionAction::Delete, period));
+                }
+            }
+        }
+
+        let mut destroy_ids = RoaringBitmap::new();
+        for (role, action, period) in effective {
+            if action == RetentionAction::Keep {
+                continue;
+            }
+            let mailbox_id = match role {
+                "trash" => TRASH_ID,
+                "junk" => JUNK_ID,
+                role => match self.mailbox_get_by_role(account_id, role).await? {
+                    Some(mailbox_id) => mailbox_id,
+                    None => continue,
+                },
+            };
+            let candidates = self
                 .get_tag(
                     account_id,
                     Collection::Email,
                     Property::MailboxIds,
-                    TagValue::Id(JUNK_ID),
+                    TagValue::Id(mailbox_id),
                 )
                 .await?
                 .unwrap_or_default();
+            if candidates.is_empty() {
+                continue;
+            }
+            let expired_ids = self
+                .emails_older_than(account_id, &candidates, period)
+                .await?;
+            if expired_ids.is_empty() {
+                continue;
+            }
 
-        if deletion_candidates.is_empty() {
-            return Ok(());
-        }
-        let reference_cid = self.inner.snowflake_id.past_id(period).ok_or_else(|| {
-            tracing::error!(
-                event = "error",
-                context = "email_auto_expunge",
-                account_id = account_id,
-                "Failed to generate reference cid."
-            );
-            MethodError::ServerPartialFail
-        })?;
-
-        // Find messages to destroy
-        let mut destroy_ids = RoaringBitmap::new();
-        for (document_id, cid) in self
-            .get_properties::<u64, _, _>(
-                account_id,
-                Collection::Email,
-                &deletion_candidates,
-                Property::Cid,
-            )
-            .await?
-        {
-            if cid < reference_cid {
-                destroy_ids.insert(document_id);
+            match action {
+                RetentionAction::Delete => {
+                    destroy_ids |= expired_ids;
+                }
+                RetentionAction::Archive => {
+                    if let BlobBackend::Tiered(store) = &self.core.storage.blob.backend {
+                        self.emails_tier_migrate(account_id, store, expired_ids)
+                            .await?;
+                    } else {
+                        tracing::debug!(
+                            event = "skipped",
+                            context = "email_retention",
+                            account_id = account_id,
+                            role = role,
+                            "Archiving requires a tiered blob store."
+                        );
+                    }
+                }
+                RetentionAction::Keep => (),
             }
         }
 
@@ -398,10 +455,10 @@ impl JMAP {
 
         tracing::debug!(
             event = "info",
-            context = "email_auto_expunge",
+            context = "email_retention",
             account_id = account_id,
             count = destroy_ids.len(),
-            "Auto-expunging messages."
+            "Expunging expired messages."
         );
 
         // Tombstone messages
@@ -422,6 +479,110 @@ impl JMAP {
         Ok(())
     }
 
+    async fn emails_older_than(
+        &self,
+        account_id: u32,
+        document_ids: &RoaringBitmap,
+        period: Duration,
+    ) -> Result<RoaringBitmap, MethodError> {
+        let reference_cid = self.inner.snowflake_id.past_id(period).ok_or_else(|| {
+            tracing::error!(
+                event = "error",
+                context = "email_retention",
+                account_id = account_id,
+                "Failed to generate reference cid."
+            );
+            MethodError::ServerPartialFail
+        })?;
+
+        let mut expired_ids = RoaringBitmap::new();
+        for (document_id, cid) in self
+            .get_properties::<u64, _, _>(account_id, Collection::Email, document_ids, Property::Cid)
+            .await?
+        {
+            if cid < reference_cid {
+                expired_ids.insert(document_id);
+            }
+        }
+
+        Ok(expired_ids)
+    }
+
+    pub async fn emails_tier_archived(
+        &self,
+        account_id: u32,
+        store: &TieredBlobStore,
+    ) -> Result<(), MethodError> {
+        let archive_id = match self.mailbox_get_by_role(account_id, "archive").await? {
+            Some(archive_id) => archive_id,
+            None => return Ok(()),
+        };
+
+        let document_ids = self
+            .get_tag(
+                account_id,
+                Collection::Email,
+                Property::MailboxIds,
+                TagValue::Id(archive_id),
+            )
+            .await?
+            .unwrap_or_default();
+        self.emails_tier_migrate(account_id, store, document_ids)
+            .await
+    }
+
+    async fn emails_tier_migrate(
+        &self,
+        account_id: u32,
+        store: &TieredBlobStore,
+        document_ids: RoaringBitmap,
+    ) -> Result<(), MethodError> {
+        let mut migrated = 0;
+        for document_id in document_ids {
+            if let Some(metadata) = self
+                .get_property::<Bincode<MessageMetadata>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    &Property::BodyStructure,
+                )
+                .await?
+            {
+                if self
+                    .core
+                    .storage
+                    .data
+                    .migrate_blob(store, &metadata.inner.blob_hash)
+                    .await
+                    .map_err(|err| {
+                        tracing::error!(
+                            event = "error",
+                            context = "email_tier_archived",
+                            account_id = account_id,
+                            error = ?err,
+                            "Failed to migrate blob."
+                        );
+                        MethodError::ServerPartialFail
+                    })?
+                {
+                    migrated += 1;
+                }
+            }
+        }
+
+        if migrated > 0 {
+            tracing::debug!(
+                event = "info",
+                context = "email_tier_archived",
+                account_id = account_id,
+                count = migrated,
+                "Migrated archived messages to cold storage."
+            );
+        }
+
+        Ok(())
+    }
+
     pub async fn emails_purge_tombstoned(&self, account_id: u32) -> store::Result<()> {
         // Obtain tombstoned messages
         let tombstoned_ids = self
@@ -460,6 +621,7 @@ impl JMAP {
             .await?;
 
         // Delete messages
+        let mut freed = 0;
         for document_id in tombstoned_ids {
             let mut batch = BatchBuilder::new();
             batch
@@ -497,6 +659,27 @@ impl JMAP {
                 );
             }
 
+            // Cancel any pending snooze wake-up
+            if let Some(snoozed_until) = self
+                .core
+                .storage
+                .data
+                .get_value::<u64>(ValueKey {
+                    account_id,
+                    collection: Collection::Email.into(),
+                    document_id,
+                    class: ValueClass::Property(Property::SnoozedUntil.into()),
+                })
+                .await?
+            {
+                batch
+                    .value(Property::SnoozedUntil, (), F_VALUE | F_CLEAR)
+                    .clear(ValueClass::Snooze(snoozed_until));
+            }
+
+            // Remove save dates
+            batch.value(Property::SavedAt, (), F_VALUE | F_CLEAR);
+
             // Remove message metadata
             if let Some(metadata) = self
                 .core
@@ -510,6 +693,7 @@ impl JMAP {
                 })
                 .await?
             {
+                freed += metadata.inner.size as i64;
                 batch.custom(EmailIndexBuilder::clear(metadata.inner));
                 // Commit batch
                 self.core.storage.data.write(batch.build()).await?;
@@ -524,6 +708,11 @@ impl JMAP {
             }
         }
 
+        // Notify quota threshold changes
+        if freed > 0 {
+            self.notify_quota_change(account_id, -freed).await;
+        }
+
         Ok(())
     }
 }
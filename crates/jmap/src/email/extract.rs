@@ -0,0 +1,293 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    io::{Cursor, Read},
+    process::Stdio,
+};
+
+use common::config::jmap::attachments::{AttachmentExtraction, BuiltinExtractor, ExtractCommand};
+use mail_parser::{Message, MimeHeaders, PartType};
+use quick_xml::{events::Event, Reader};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::JMAP;
+
+use super::index::MAX_MESSAGE_PARTS;
+
+const MAX_UNCOMPRESSED_SIZE: u64 = 50 * 1024 * 1024;
+
+impl JMAP {
+    pub async fn extract_attachments(&self, message: &Message<'_>) -> Vec<String> {
+        let config = if let Some(config) = &self.core.jmap.fts_attachments {
+            config
+        } else {
+            return vec![];
+        };
+        let mut texts = Vec::new();
+
+        for part in message.parts.iter().take(MAX_MESSAGE_PARTS) {
+            let contents = match &part.body {
+                PartType::Binary(contents) | PartType::InlineBinary(contents)
+                    if !contents.is_empty() && contents.len() <= config.max_size =>
+                {
+                    contents.as_ref()
+                }
+                _ => continue,
+            };
+            let content_type = part
+                .content_type()
+                .map(|ct| {
+                    if let Some(subtype) = ct.subtype() {
+                        format!("{}/{}", ct.ctype(), subtype)
+                    } else {
+                        ct.ctype().to_string()
+                    }
+                    .to_ascii_lowercase()
+                })
+                .unwrap_or_default();
+            let name = part
+                .attachment_name()
+                .map(|name| name.to_ascii_lowercase())
+                .unwrap_or_default();
+
+            let text = if let Some(extractor) = config
+                .extractors
+                .iter()
+                .find(|extractor| extractor.matches(&content_type, &name))
+            {
+                extract_builtin(config, *extractor, contents.to_vec()).await
+            } else if let Some(command) = config
+                .command
+                .as_ref()
+                .filter(|command| command.matches(&content_type))
+            {
+                extract_command(config, command, contents).await
+            } else {
+                continue;
+            };
+
+            if let Some(mut text) = text.filter(|text| !text.trim().is_empty()) {
+                if text.len() > config.max_text {
+                    let mut pos = config.max_text;
+                    while !text.is_char_boundary(pos) {
+                        pos -= 1;
+                    }
+                    text.truncate(pos);
+                }
+                texts.push(text);
+            }
+        }
+
+        texts
+    }
+}
+
+async fn extract_builtin(
+    config: &AttachmentExtraction,
+    extractor: BuiltinExtractor,
+    contents: Vec<u8>,
+) -> Option<String> {
+    // Parsing is CPU bound, run it outside of the async executor
+    let task = tokio::task::spawn_blocking(move || match extractor {
+        BuiltinExtractor::Pdf => extract_pdf(&contents),
+        BuiltinExtractor::Docx => {
+            extract_zip_xml(&contents, "word/document.xml", Some("w:t"), &["w:p"])
+        }
+        BuiltinExtractor::Odf => {
+            extract_zip_xml(&contents, "content.xml", None, &["text:p", "text:h"])
+        }
+    });
+
+    match tokio::time::timeout(config.timeout, task).await {
+        Ok(Ok(text)) => text,
+        Ok(Err(err)) => {
+            tracing::warn!(
+                context = "fts_extract",
+                event = "error",
+                extractor = ?extractor,
+                reason = %err,
+                "Attachment text extraction failed"
+            );
+            None
+        }
+        Err(_) => {
+            tracing::warn!(
+                context = "fts_extract",
+                event = "timeout",
+                extractor = ?extractor,
+                "Attachment text extraction timed out"
+            );
+            None
+        }
+    }
+}
+
+async fn extract_command(
+    config: &AttachmentExtraction,
+    command_: &ExtractCommand,
+    contents: &[u8],
+) -> Option<String> {
+    let mut command = Command::new(&command_.command);
+    for argument in &command_.arguments {
+        command.arg(argument);
+    }
+    match command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(mut child) => {
+            let mut stdin = child.stdin.take()?;
+            match tokio::time::timeout(config.timeout, stdin.write_all(contents)).await {
+                Ok(Ok(_)) => {
+                    drop(stdin);
+                }
+                Ok(Err(err)) => {
+                    tracing::warn!(
+                        context = "fts_extract",
+                        event = "write-error",
+                        command = command_.command,
+                        reason = %err
+                    );
+                    return None;
+                }
+                Err(_) => {
+                    tracing::warn!(
+                        context = "fts_extract",
+                        event = "stdin-timeout",
+                        command = command_.command
+                    );
+                    return None;
+                }
+            }
+            match tokio::time::timeout(config.timeout, child.wait_with_output()).await {
+                Ok(Ok(output)) if output.status.success() => {
+                    String::from_utf8_lossy(&output.stdout).into_owned().into()
+                }
+                Ok(Ok(output)) => {
+                    tracing::debug!(
+                        context = "fts_extract",
+                        event = "failed",
+                        command = command_.command,
+                        status = output.status.to_string()
+                    );
+                    None
+                }
+                Ok(Err(err)) => {
+                    tracing::warn!(
+                        context = "fts_extract",
+                        event = "exec-error",
+                        command = command_.command,
+                        reason = %err
+                    );
+                    None
+                }
+                Err(_) => {
+                    tracing::warn!(
+                        context = "fts_extract",
+                        event = "timeout",
+                        command = command_.command
+                    );
+                    None
+                }
+            }
+        }
+        Err(err) => {
+            tracing::warn!(
+                context = "fts_extract",
+                event = "spawn-error",
+                command = command_.command,
+                reason = %err
+            );
+            None
+        }
+    }
+}
+
+fn extract_pdf(contents: &[u8]) -> Option<String> {
+    // The PDF parser panics on some malformed documents
+    std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(contents))
+        .ok()?
+        .ok()
+}
+
+fn extract_zip_xml(
+    contents: &[u8],
+    file_name: &str,
+    text_element: Option<&str>,
+    paragraph_elements: &[&str],
+) -> Option<String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(contents)).ok()?;
+    let mut xml = Vec::new();
+    archive
+        .by_name(file_name)
+        .ok()?
+        .take(MAX_UNCOMPRESSED_SIZE)
+        .read_to_end(&mut xml)
+        .ok()?;
+
+    extract_xml_text(&xml, text_element, paragraph_elements)
+}
+
+fn extract_xml_text(
+    xml: &[u8],
+    text_element: Option<&str>,
+    paragraph_elements: &[&str],
+) -> Option<String> {
+    let text_element = text_element.map(str::as_bytes);
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+    let mut text = String::new();
+    let mut in_text = text_element.is_none();
+
+    loop {
+        match reader.read_event_into(&mut buf).ok()? {
+            Event::Start(element) if Some(element.name().as_ref()) == text_element => {
+                in_text = true;
+            }
+            Event::End(element) => {
+                let name = element.name();
+                if Some(name.as_ref()) == text_element {
+                    in_text = false;
+                } else if paragraph_elements
+                    .iter()
+                    .any(|p| p.as_bytes() == name.as_ref())
+                    && !text.ends_with('\n')
+                {
+                    text.push('\n');
+                }
+            }
+            Event::Text(value) if in_text => {
+                text.push_str(&value.unescape().ok()?);
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+
+    Some(text)
+}
@@ -289,6 +289,21 @@ impl JMAP {
                     Property::HasAttachment => {
                         email.append(Property::HasAttachment, metadata.has_attachments);
                     }
+                    Property::SnoozedUntil => {
+                        email.append(
+                            Property::SnoozedUntil,
+                            self.get_property::<u64>(
+                                account_id,
+                                Collection::Email,
+                                id.document_id(),
+                                Property::SnoozedUntil,
+                            )
+                            .await?
+                            .map_or(Value::Null, |until| {
+                                Value::Date(UTCDate::from_timestamp(until as i64))
+                            }),
+                        );
+                    }
                     Property::Subject => {
                         email.append(
                             Property::Subject,
@@ -39,7 +39,7 @@ use store::{
     ahash::AHashSet,
     query::Filter,
     write::{
-        log::{ChangeLogBuilder, Changes, LogInsert},
+        log::{ChangeLogBuilder, Changes},
         now, AssignedIds, BatchBuilder, BitmapClass, FtsQueueClass, MaybeDynamicId,
         MaybeDynamicValue, SerializeWithId, TagValue, ValueClass, F_BITMAP, F_CLEAR, F_VALUE,
     },
@@ -49,12 +49,13 @@ use utils::map::vec_map::VecMap;
 
 use crate::{
     email::index::{IndexMessage, VisitValues, MAX_ID_LENGTH},
-    mailbox::{UidMailbox, INBOX_ID, JUNK_ID},
+    mailbox::{SavedDate, UidMailbox, INBOX_ID, JUNK_ID},
     services::housekeeper::Event,
     IngestError, JMAP,
 };
 
 use super::{
+    bayes::BayesVerdict,
     crypto::{EncryptMessage, EncryptMessageError, EncryptionParams},
     index::{TrimTextValue, MAX_SORT_FIELD_LENGTH},
 };
@@ -80,18 +81,289 @@ pub struct IngestEmail<'x> {
     pub encrypt: bool,
 }
 
+// Messages staged for a single write, threads are tracked per message and
+// merged when staged messages reference each other
+#[derive(Default)]
+struct IngestBatch {
+    change_id: Option<u64>,
+    size: i64,
+    messages: u64,
+    threads: Vec<StagedThread>,
+}
+
+struct StagedThread {
+    parent: usize,
+    thread_id: Option<u32>,
+    subject: String,
+    references: AHashSet<String>,
+}
+
+struct StagedEmail<'x> {
+    message: StagedMessage<'x>,
+    keywords: Vec<Keyword>,
+    mailbox_ids: Vec<u32>,
+    uid_mailboxes: Vec<UidMailbox>,
+    received_at: Option<u64>,
+    thread_idx: usize,
+    blob_id: BlobId,
+    size: i64,
+    imap_uids: Vec<u32>,
+}
+
+enum StagedMessage<'x> {
+    Plain(Message<'x>),
+    Encrypted(Vec<u8>),
+}
+
 const MAX_RETRIES: u32 = 10;
 
+impl IngestBatch {
+    fn thread_root(&self, mut idx: usize) -> usize {
+        while self.threads[idx].parent != idx {
+            idx = self.threads[idx].parent;
+        }
+        idx
+    }
+
+    fn stage_thread(
+        &mut self,
+        thread_id: Option<u32>,
+        subject: &str,
+        references: &[&str],
+    ) -> usize {
+        let idx = self.threads.len();
+        self.threads.push(StagedThread {
+            parent: idx,
+            thread_id,
+            subject: subject.to_string(),
+            references: references.iter().map(|id| id.to_string()).collect(),
+        });
+
+        // Merge with the threads of staged messages sharing a reference
+        for other_idx in 0..idx {
+            let other = &self.threads[other_idx];
+            if other.subject == subject
+                && references.iter().any(|id| other.references.contains(*id))
+            {
+                let root = self.thread_root(idx);
+                let other_root = self.thread_root(other_idx);
+                if root != other_root {
+                    self.threads[other_root].parent = root;
+                    if self.threads[root].thread_id.is_none() {
+                        self.threads[root].thread_id = self.threads[other_root].thread_id;
+                    }
+                }
+            }
+        }
+
+        idx
+    }
+}
+
+impl IngestedEmail {
+    fn duplicate() -> Self {
+        IngestedEmail {
+            change_id: u64::MAX,
+            ..Default::default()
+        }
+    }
+}
+
 impl JMAP {
-    #[allow(clippy::blocks_in_conditions)]
     pub async fn email_ingest(
         &self,
-        mut params: IngestEmail<'_>,
+        params: IngestEmail<'_>,
     ) -> Result<IngestedEmail, IngestError> {
-        // Check quota
+        self.email_ingest_many(vec![params])
+            .await
+            .map(|mut emails| emails.pop().unwrap_or_else(IngestedEmail::duplicate))
+    }
+
+    /// Ingests several messages into the same account in a single write,
+    /// either all of them are stored or none of them are.
+    pub async fn email_ingest_many(
+        &self,
+        messages: Vec<IngestEmail<'_>>,
+    ) -> Result<Vec<IngestedEmail>, IngestError> {
+        let account_id = match messages.first() {
+            Some(message) => message.account_id,
+            None => return Ok(Vec::new()),
+        };
+        let mut ingest = IngestBatch::default();
+        let mut staged = Vec::with_capacity(messages.len());
+        for params in messages {
+            staged.push(self.email_stage(params, &mut ingest).await?);
+        }
+
+        // Nothing to write if all messages were duplicates
+        let change_id = if let Some(change_id) = ingest.change_id {
+            change_id
+        } else {
+            return Ok(staged
+                .into_iter()
+                .map(|_| IngestedEmail::duplicate())
+                .collect());
+        };
+
+        // Create or update threads
+        let mut batch = BatchBuilder::new();
+        let mut document_count = 0;
+        let mut thread_ids = vec![MaybeDynamicId::Static(u32::MAX); ingest.threads.len()];
+        let mut thread_changes = LogThreadChanges::default();
+        batch
+            .with_change_id(change_id)
+            .with_account_id(account_id)
+            .with_collection(Collection::Thread);
+        for idx in 0..ingest.threads.len() {
+            let root = ingest.thread_root(idx);
+            if root != idx {
+                continue;
+            }
+            thread_ids[idx] = if let Some(thread_id) = ingest.threads[idx].thread_id {
+                thread_changes.updates.push(thread_id);
+                MaybeDynamicId::Static(thread_id)
+            } else {
+                batch.create_document();
+                thread_changes.inserts.push(document_count);
+                document_count += 1;
+                MaybeDynamicId::Dynamic(document_count - 1)
+            };
+        }
+        batch.log(thread_changes);
+
+        // Build write batch
+        let mut email_inserts = Vec::with_capacity(staged.len());
+        let mut mailbox_ids = AHashSet::new();
+        batch.with_collection(Collection::Email);
+        for email in staged.iter_mut().flatten() {
+            let thread_id = thread_ids[ingest.thread_root(email.thread_idx)];
+            let raw_message;
+            let message =
+                match std::mem::replace(&mut email.message, StagedMessage::Encrypted(Vec::new())) {
+                    StagedMessage::Plain(message) => message,
+                    StagedMessage::Encrypted(encrypted_message) => {
+                        raw_message = encrypted_message;
+                        parse_encrypted(&raw_message)?
+                    }
+                };
+            let saved_at = now();
+            let saved_dates = email
+                .uid_mailboxes
+                .iter()
+                .map(|uid_mailbox| SavedDate::new(uid_mailbox.mailbox_id, saved_at))
+                .collect::<Vec<_>>();
+            batch
+                .create_document()
+                .index_message(
+                    message,
+                    email.blob_id.hash.clone(),
+                    std::mem::take(&mut email.keywords),
+                    std::mem::take(&mut email.uid_mailboxes),
+                    email.received_at.unwrap_or_else(now),
+                )
+                .value(Property::SavedAt, saved_dates, F_VALUE)
+                .value(Property::Cid, change_id, F_VALUE)
+                .set(Property::ThreadId, thread_id)
+                .tag(Property::ThreadId, TagValue::Id(thread_id), 0)
+                .set(
+                    ValueClass::FtsQueue(FtsQueueClass {
+                        seq: self
+                            .generate_snowflake_id()
+                            .map_err(|_| IngestError::Temporary)?,
+                        hash: email.blob_id.hash.clone(),
+                    }),
+                    0u64.serialize(),
+                );
+            email_inserts.push((thread_id, document_count));
+            document_count += 1;
+            mailbox_ids.extend(email.mailbox_ids.iter().copied());
+        }
+
+        // Each collection is logged once per change id
+        batch
+            .log(LogEmailInserts(email_inserts.clone()))
+            .with_collection(Collection::Mailbox)
+            .log(Changes::child_update(mailbox_ids));
+
+        // Insert and obtain ids
+        let ids = self
+            .core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                event = "error",
+                context = "email_ingest",
+                error = ?err,
+                "Failed to write message to database.");
+                IngestError::Temporary
+            })?;
+
+        // Request FTS index
+        let _ = self.inner.housekeeper_tx.send(Event::IndexStart).await;
+
+        // Notify quota threshold changes
+        self.notify_quota_change(account_id, ingest.size).await;
+
+        let mut emails = Vec::with_capacity(staged.len());
+        let mut email_inserts = email_inserts.into_iter();
+        for email in staged {
+            let Some(email) = email else {
+                emails.push(IngestedEmail::duplicate());
+                continue;
+            };
+            let (thread_id, document_idx) = email_inserts.next().ok_or(IngestError::Temporary)?;
+            let thread_id = thread_id
+                .resolve(&ids)
+                .map_err(|_| IngestError::Temporary)?;
+            let document_id = ids
+                .get_document_id(document_idx)
+                .map_err(|_| IngestError::Temporary)?;
+
+            tracing::debug!(
+                context = "email_ingest",
+                event = "success",
+                account_id = ?account_id,
+                document_id = ?document_id,
+                mailbox_ids = ?email.mailbox_ids,
+                change_id = ?change_id,
+                blob_id = ?email.blob_id.hash,
+                size = email.size,
+                "Ingested e-mail.");
+
+            emails.push(IngestedEmail {
+                id: Id::from_parts(thread_id, document_id),
+                change_id,
+                blob_id: BlobId {
+                    hash: email.blob_id.hash,
+                    class: BlobClass::Linked {
+                        account_id,
+                        collection: Collection::Email.into(),
+                        document_id,
+                    },
+                    section: email.blob_id.section,
+                },
+                size: email.size as usize,
+                imap_uids: email.imap_uids,
+            });
+        }
+
+        Ok(emails)
+    }
+
+    #[allow(clippy::blocks_in_conditions)]
+    async fn email_stage<'x>(
+        &self,
+        mut params: IngestEmail<'x>,
+        ingest: &mut IngestBatch,
+    ) -> Result<Option<StagedEmail<'x>>, IngestError> {
+        // Check quota, including the messages already staged in this batch
         let mut raw_message_len = params.raw_message.len() as i64;
         if params.account_quota > 0
             && raw_message_len
+                + ingest.size
                 + self
                     .get_used_quota(params.account_id)
                     .await
@@ -100,17 +372,28 @@ impl JMAP {
         {
             return Err(IngestError::OverQuota);
         }
+        let max_messages = self.core.jmap.mail_max_messages;
+        if max_messages > 0
+            && ingest.messages
+                + self
+                    .get_used_messages(params.account_id)
+                    .await
+                    .map_err(|_| IngestError::Temporary)?
+                >= max_messages
+        {
+            return Err(IngestError::OverQuota);
+        }
 
         // Parse message
-        let mut raw_message = Cow::from(params.raw_message);
-        let mut message = params.message.ok_or_else(|| IngestError::Permanent {
+        let message = params.message.ok_or_else(|| IngestError::Permanent {
             code: [5, 5, 0],
             reason: "Failed to parse e-mail message.".to_string(),
         })?;
 
         // Check for Spam headers
+        let is_inbox = params.mailbox_ids == [INBOX_ID];
         if let Some((header_name, header_value)) = &self.core.jmap.spam_header {
-            if params.mailbox_ids == [INBOX_ID]
+            if is_inbox
                 && message.root_part().headers().iter().any(|header| {
                     &header.name == header_name
                         && header
@@ -123,8 +406,20 @@ impl JMAP {
             }
         }
 
+        // Apply the account's own spam model
+        if is_inbox {
+            match self
+                .bayes_classify_account(params.account_id, &message)
+                .await
+            {
+                Some(BayesVerdict::Spam) => params.mailbox_ids[0] = JUNK_ID,
+                Some(BayesVerdict::Ham) => params.mailbox_ids[0] = INBOX_ID,
+                None => (),
+            }
+        }
+
         // Obtain message references and thread name
-        let thread_id = {
+        let thread_idx = {
             let mut references = Vec::with_capacity(5);
             let mut subject = "";
             let mut message_id = "";
@@ -193,24 +488,21 @@ impl JMAP {
                     message_id = message_id,
                     "Duplicate message skipped.");
 
-                return Ok(IngestedEmail {
-                    id: Id::default(),
-                    change_id: u64::MAX,
-                    blob_id: BlobId::default(),
-                    imap_uids: Vec::new(),
-                    size: 0,
-                });
+                return Ok(None);
             }
 
-            if !references.is_empty() {
+            let thread_id = if !references.is_empty() {
                 self.find_or_merge_thread(params.account_id, subject, &references)
                     .await?
             } else {
                 None
-            }
+            };
+
+            ingest.stage_thread(thread_id, subject, &references)
         };
 
         // Encrypt message
+        let mut encrypted_message = None;
         if params.encrypt && !message.is_encrypted() {
             if let Some(encrypt_params) = self
                 .get_property::<EncryptionParams>(
@@ -224,30 +516,8 @@ impl JMAP {
             {
                 match message.encrypt(&encrypt_params).await {
                     Ok(new_raw_message) => {
-                        raw_message = Cow::from(new_raw_message);
-                        raw_message_len = raw_message.len() as i64;
-                        message = MessageParser::default()
-                            .parse(raw_message.as_ref())
-                            .ok_or_else(|| IngestError::Permanent {
-                                code: [5, 5, 0],
-                                reason: "Failed to parse encrypted e-mail message.".to_string(),
-                            })?;
-
-                        // Remove contents from parsed message
-                        for part in &mut message.parts {
-                            match &mut part.body {
-                                PartType::Text(txt) | PartType::Html(txt) => {
-                                    *txt = Cow::from("");
-                                }
-                                PartType::Binary(bin) | PartType::InlineBinary(bin) => {
-                                    *bin = Cow::from(&[][..]);
-                                }
-                                PartType::Message(_) => {
-                                    part.body = PartType::Binary(Cow::from(&[][..]));
-                                }
-                                PartType::Multipart(_) => (),
-                            }
-                        }
+                        raw_message_len = new_raw_message.len() as i64;
+                        encrypted_message = Some(new_raw_message);
                     }
                     Err(EncryptMessageError::Error(err)) => {
                         tracing::error!(
@@ -261,23 +531,37 @@ impl JMAP {
                 }
             }
         }
+        let staged_message = match encrypted_message {
+            Some(raw_message) => StagedMessage::Encrypted(raw_message),
+            None => StagedMessage::Plain(message),
+        };
 
-        // Obtain a documentId and changeId
-        let change_id = self
-            .assign_change_id(params.account_id)
-            .await
-            .map_err(|_| {
-                tracing::error!(
-                    event = "error",
-                    context = "email_ingest",
-                    "Failed to assign changeId."
-                );
-                IngestError::Temporary
-            })?;
+        // Obtain a changeId, shared by all messages in the batch
+        if ingest.change_id.is_none() {
+            ingest.change_id = self
+                .assign_change_id(params.account_id)
+                .await
+                .map_err(|_| {
+                    tracing::error!(
+                        event = "error",
+                        context = "email_ingest",
+                        "Failed to assign changeId."
+                    );
+                    IngestError::Temporary
+                })?
+                .into();
+        }
 
         // Store blob
         let blob_id = self
-            .put_blob(params.account_id, raw_message.as_ref(), false)
+            .put_blob(
+                params.account_id,
+                match &staged_message {
+                    StagedMessage::Plain(_) => params.raw_message,
+                    StagedMessage::Encrypted(raw_message) => raw_message,
+                },
+                false,
+            )
             .await
             .map_err(|err| {
                 tracing::error!(
@@ -289,7 +573,7 @@ impl JMAP {
             })?;
 
         // Assign IMAP UIDs
-        let mut mailbox_ids = Vec::with_capacity(params.mailbox_ids.len());
+        let mut uid_mailboxes = Vec::with_capacity(params.mailbox_ids.len());
         let mut imap_uids = Vec::with_capacity(params.mailbox_ids.len());
         for mailbox_id in &params.mailbox_ids {
             let uid = self
@@ -303,105 +587,24 @@ impl JMAP {
                     "Failed to assign IMAP UID.");
                     IngestError::Temporary
                 })?;
-            mailbox_ids.push(UidMailbox::new(*mailbox_id, uid));
+            uid_mailboxes.push(UidMailbox::new(*mailbox_id, uid));
             imap_uids.push(uid);
         }
 
-        // Prepare batch
-        let mut batch = BatchBuilder::new();
-        batch
-            .with_change_id(change_id)
-            .with_account_id(params.account_id)
-            .with_collection(Collection::Thread);
-        if let Some(thread_id) = thread_id {
-            batch.log(Changes::update([thread_id]));
-        } else {
-            batch.create_document().log(LogInsert());
-        }
-
-        // Build write batch
-        let maybe_thread_id = thread_id
-            .map(MaybeDynamicId::Static)
-            .unwrap_or(MaybeDynamicId::Dynamic(0));
-        batch
-            .with_collection(Collection::Mailbox)
-            .log(Changes::child_update(params.mailbox_ids.iter().copied()))
-            .with_collection(Collection::Email)
-            .create_document()
-            .log(LogEmailInsert(thread_id))
-            .index_message(
-                message,
-                blob_id.hash.clone(),
-                params.keywords,
-                mailbox_ids,
-                params.received_at.unwrap_or_else(now),
-            )
-            .value(Property::Cid, change_id, F_VALUE)
-            .set(Property::ThreadId, maybe_thread_id)
-            .tag(Property::ThreadId, TagValue::Id(maybe_thread_id), 0)
-            .set(
-                ValueClass::FtsQueue(FtsQueueClass {
-                    seq: self
-                        .generate_snowflake_id()
-                        .map_err(|_| IngestError::Temporary)?,
-                    hash: blob_id.hash.clone(),
-                }),
-                0u64.serialize(),
-            );
-
-        // Insert and obtain ids
-        let ids = self
-            .core
-            .storage
-            .data
-            .write(batch.build())
-            .await
-            .map_err(|err| {
-                tracing::error!(
-                event = "error",
-                context = "email_ingest",
-                error = ?err,
-                "Failed to write message to database.");
-                IngestError::Temporary
-            })?;
-        let thread_id = match thread_id {
-            Some(thread_id) => thread_id,
-            None => ids
-                .first_document_id()
-                .map_err(|_| IngestError::Temporary)?,
-        };
-        let document_id = ids.last_document_id().map_err(|_| IngestError::Temporary)?;
-        let id = Id::from_parts(thread_id, document_id);
-
-        // Request FTS index
-        let _ = self.inner.housekeeper_tx.send(Event::IndexStart).await;
-
-        tracing::debug!(
-            context = "email_ingest",
-            event = "success",
-            account_id = ?params.account_id,
-            document_id = ?document_id,
-            mailbox_ids = ?params.mailbox_ids,
-            change_id = ?change_id,
-            blob_id = ?blob_id.hash,
-            size = raw_message_len,
-            "Ingested e-mail.");
-
-        Ok(IngestedEmail {
-            id,
-            change_id,
-            blob_id: BlobId {
-                hash: blob_id.hash,
-                class: BlobClass::Linked {
-                    account_id: params.account_id,
-                    collection: Collection::Email.into(),
-                    document_id,
-                },
-                section: blob_id.section,
-            },
-            size: raw_message_len as usize,
+        ingest.size += raw_message_len;
+        ingest.messages += 1;
+
+        Ok(Some(StagedEmail {
+            message: staged_message,
+            keywords: params.keywords,
+            mailbox_ids: params.mailbox_ids,
+            uid_mailboxes,
+            received_at: params.received_at,
+            thread_idx,
+            blob_id,
+            size: raw_message_len,
             imap_uids,
-        })
+        }))
     }
 
     pub async fn find_or_merge_thread(
@@ -591,6 +794,35 @@ impl JMAP {
     }
 }
 
+// Encrypted messages are indexed without their contents
+fn parse_encrypted(raw_message: &[u8]) -> Result<Message<'_>, IngestError> {
+    let mut message =
+        MessageParser::default()
+            .parse(raw_message)
+            .ok_or_else(|| IngestError::Permanent {
+                code: [5, 5, 0],
+                reason: "Failed to parse encrypted e-mail message.".to_string(),
+            })?;
+
+    // Remove contents from parsed message
+    for part in &mut message.parts {
+        match &mut part.body {
+            PartType::Text(txt) | PartType::Html(txt) => {
+                *txt = Cow::from("");
+            }
+            PartType::Binary(bin) | PartType::InlineBinary(bin) => {
+                *bin = Cow::from(&[][..]);
+            }
+            PartType::Message(_) => {
+                part.body = PartType::Binary(Cow::from(&[][..]));
+            }
+            PartType::Multipart(_) => (),
+        }
+    }
+
+    Ok(message)
+}
+
 pub struct LogEmailInsert(Option<u32>);
 
 impl LogEmailInsert {
@@ -617,6 +849,50 @@ impl From<LogEmailInsert> for MaybeDynamicValue {
     }
 }
 
+#[derive(Default)]
+struct LogThreadChanges {
+    updates: Vec<u32>,
+    inserts: Vec<usize>,
+}
+
+impl SerializeWithId for LogThreadChanges {
+    fn serialize_with_id(&self, ids: &AssignedIds) -> store::Result<Vec<u8>> {
+        let mut changes = Changes::update(self.updates.iter().copied());
+        for idx in &self.inserts {
+            changes.inserts.insert(ids.get_document_id(*idx)? as u64);
+        }
+
+        Ok(changes.serialize())
+    }
+}
+
+impl From<LogThreadChanges> for MaybeDynamicValue {
+    fn from(log: LogThreadChanges) -> Self {
+        MaybeDynamicValue::Dynamic(Box::new(log))
+    }
+}
+
+struct LogEmailInserts(Vec<(MaybeDynamicId, usize)>);
+
+impl SerializeWithId for LogEmailInserts {
+    fn serialize_with_id(&self, ids: &AssignedIds) -> store::Result<Vec<u8>> {
+        let mut changes = Changes::default();
+        for (thread_id, document_idx) in &self.0 {
+            changes.inserts.insert(
+                Id::from_parts(thread_id.resolve(ids)?, ids.get_document_id(*document_idx)?).into(),
+            );
+        }
+
+        Ok(changes.serialize())
+    }
+}
+
+impl From<LogEmailInserts> for MaybeDynamicValue {
+    fn from(log: LogEmailInserts) -> Self {
+        MaybeDynamicValue::Dynamic(Box::new(log))
+    }
+}
+
 impl From<IngestedEmail> for Object<Value> {
     fn from(email: IngestedEmail) -> Self {
         Object::with_capacity(3)
@@ -21,11 +21,13 @@
  * for more details.
 */
 
+pub mod bayes;
 pub mod body;
 pub mod cache;
 pub mod copy;
 pub mod crypto;
 pub mod delete;
+pub mod extract;
 pub mod get;
 pub mod headers;
 pub mod import;
@@ -36,3 +38,4 @@ pub mod parse;
 pub mod query;
 pub mod set;
 pub mod snippet;
+pub mod snooze;
@@ -28,7 +28,7 @@ use jmap_proto::{
     types::{property::Property, value::Value},
 };
 use mail_parser::{
-    decoders::html::html_to_text, parsers::preview::preview_text, MessageParser, PartType,
+    decoders::html::html_to_text, parsers::preview::preview_text, Message, MessageParser, PartType,
 };
 use utils::map::vec_map::VecMap;
 
@@ -243,6 +243,9 @@ impl JMAP {
                         }
                         email.append(Property::BodyValues, body_values);
                     }
+                    Property::Encryption => {
+                        email.append(Property::Encryption, message.encryption());
+                    }
                     Property::Id
                     | Property::ThreadId
                     | Property::Keywords
@@ -264,3 +267,86 @@ impl JMAP {
         Ok(response)
     }
 }
+
+trait EncryptionInfo {
+    fn encryption(&self) -> Value;
+}
+
+impl EncryptionInfo for Message<'_> {
+    fn encryption(&self) -> Value {
+        let mut encryption = None;
+        let mut is_signed = false;
+        let mut part_id = 0;
+
+        // Walk down the signature wrappers until the encrypted or clear-text content is found
+        while let Some(part) = self.parts.get(part_id) {
+            let (ctype, subtype, protocol) = match part.content_type() {
+                Some(ct) => (
+                    ct.ctype().to_ascii_lowercase(),
+                    ct.subtype().unwrap_or_default().to_ascii_lowercase(),
+                    ct.attribute("protocol")
+                        .unwrap_or_default()
+                        .to_ascii_lowercase(),
+                ),
+                None => break,
+            };
+
+            match (ctype.as_str(), subtype.as_str()) {
+                ("multipart", "signed") => {
+                    is_signed = true;
+                    if encryption.is_none() {
+                        encryption = match protocol.as_str() {
+                            "application/pgp-signature" => Some("pgp"),
+                            "application/pkcs7-signature" | "application/x-pkcs7-signature" => {
+                                Some("smime")
+                            }
+                            _ => None,
+                        }
+                        .map(|protocol| (protocol, false));
+                    }
+                    match &part.body {
+                        PartType::Multipart(parts) if !parts.is_empty() => {
+                            part_id = parts[0];
+                        }
+                        _ => break,
+                    }
+                }
+                ("multipart", "encrypted") => {
+                    if protocol == "application/pgp-encrypted" {
+                        encryption = Some(("pgp", true));
+                    }
+                    break;
+                }
+                ("application", "pkcs7-mime" | "x-pkcs7-mime") => {
+                    match part
+                        .content_type()
+                        .and_then(|ct| ct.attribute("smime-type"))
+                        .unwrap_or_default()
+                        .to_ascii_lowercase()
+                        .as_str()
+                    {
+                        "signed-data" => {
+                            is_signed = true;
+                            encryption = Some(("smime", false));
+                        }
+                        "certs-only" | "compressed-data" => (),
+                        _ => {
+                            encryption = Some(("smime", true));
+                        }
+                    }
+                    break;
+                }
+                _ => break,
+            }
+        }
+
+        match encryption {
+            Some((protocol, is_encrypted)) => Object::with_capacity(3)
+                .with_property(Property::Type, protocol.to_string())
+                .with_property(Property::_T("isEncrypted".to_string()), is_encrypted)
+                .with_property(Property::_T("isSigned".to_string()), is_signed)
+                .into(),
+            None => Value::Null,
+        }
+    }
+}
@@ -48,7 +48,15 @@ impl JMAP {
         let account_id = request.account_id.document_id();
         let mut filters = Vec::with_capacity(request.filter.len());
 
-        for cond_group in std::mem::take(&mut request.filter).into_filter_group() {
+        for cond_group in self
+            .expand_saved_searches(
+                account_id,
+                std::mem::take(&mut request.filter),
+                access_token,
+            )
+            .await?
+            .into_filter_group()
+        {
             match cond_group {
                 FilterGroup::Fts(conds) => {
                     let mut fts_filters = Vec::with_capacity(filters.len());
@@ -271,7 +279,9 @@ impl JMAP {
             }
         }
 
-        let mut result_set = self.filter(account_id, Collection::Email, filters).await?;
+        let mut result_set = self
+            .query_filter(account_id, Collection::Email, filters)
+            .await?;
         if access_token.is_shared(account_id) {
             result_set.apply_mask(
                 self.shared_messages(access_token, account_id, Acl::ReadItems)
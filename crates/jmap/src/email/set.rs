@@ -59,7 +59,11 @@ use store::{
     Serialize,
 };
 
-use crate::{auth::AccessToken, mailbox::UidMailbox, IngestError, JMAP};
+use crate::{
+    auth::AccessToken,
+    mailbox::{UidMailbox, JUNK_ID, TRASH_ID},
+    IngestError, JMAP,
+};
 
 use super::{
     headers::{BuildHeader, ValueToHeader},
@@ -758,6 +762,8 @@ impl JMAP {
 
         // Process updates
         let mut changes = ChangeLogBuilder::new();
+        let mut train_spam = Vec::new();
+        let mut train_ham = Vec::new();
         'update: for (id, object) in request.unwrap_update() {
             // Make sure id won't be destroyed
             if will_destroy.contains(&id) {
@@ -794,6 +800,8 @@ impl JMAP {
             batch
                 .with_account_id(account_id)
                 .with_collection(Collection::Email);
+            let mut is_spam = None;
+            let mut snoozed_until = None;
 
             for (property, value) in object.properties {
                 let value = match response.eval_object_references(value) {
@@ -840,6 +848,12 @@ impl JMAP {
                             );
                         }
                     }
+                    (Property::SnoozedUntil, MaybePatchValue::Value(Value::Date(until))) => {
+                        snoozed_until = Some(Some(until.timestamp() as u64));
+                    }
+                    (Property::SnoozedUntil, MaybePatchValue::Value(Value::Null)) => {
+                        snoozed_until = Some(None);
+                    }
                     (property, _) => {
                         response.invalid_property_update(id, property);
                         continue 'update;
@@ -847,7 +861,7 @@ impl JMAP {
                 }
             }
 
-            if !mailboxes.has_changes() && !keywords.has_changes() {
+            if !mailboxes.has_changes() && !keywords.has_changes() && snoozed_until.is_none() {
                 response.not_updated.append(
                     id,
                     SetError::invalid_properties()
@@ -972,10 +986,50 @@ impl JMAP {
                     }
                 }
 
+                // Moving messages into or out of Junk trains the account's spam model
+                if mailboxes.added().iter().any(|m| m.mailbox_id == JUNK_ID) {
+                    is_spam = Some(true);
+                } else if mailboxes.removed().iter().any(|m| m.mailbox_id == JUNK_ID)
+                    && !mailboxes.added().iter().any(|m| m.mailbox_id == TRASH_ID)
+                {
+                    is_spam = Some(false);
+                }
+
                 // Update mailboxIds property
                 mailboxes.update_batch(&mut batch, Property::MailboxIds);
             }
 
+            // Schedule or cancel the snooze wake-up
+            if let Some(snoozed_until) = snoozed_until {
+                if matches!(&can_modify_message_ids, Some(ids) if !ids.contains(document_id)) {
+                    response.not_updated.append(
+                        id,
+                        SetError::forbidden()
+                            .with_description("You are not allowed to snooze this message."),
+                    );
+                    continue 'update;
+                }
+
+                if let Some(prev_until) = self
+                    .get_property::<u64>(
+                        account_id,
+                        Collection::Email,
+                        document_id,
+                        Property::SnoozedUntil,
+                    )
+                    .await?
+                {
+                    batch.clear(ValueClass::Snooze(prev_until));
+                }
+                if let Some(until) = snoozed_until {
+                    batch
+                        .value(Property::SnoozedUntil, until, F_VALUE)
+                        .set(ValueClass::Snooze(until), vec![]);
+                } else {
+                    batch.value(Property::SnoozedUntil, (), F_VALUE | F_CLEAR);
+                }
+            }
+
             // Log mailbox changes
             for mailbox_id in changed_mailboxes {
                 changes.log_child_update(Collection::Mailbox, mailbox_id);
@@ -987,6 +1041,12 @@ impl JMAP {
                     Ok(_) => {
                         // Add to updated list
                         response.updated.append(id, None);
+
+                        match is_spam {
+                            Some(true) => train_spam.push(document_id),
+                            Some(false) => train_ham.push(document_id),
+                            None => (),
+                        }
                     }
                     Err(store::Error::AssertValueFailed) => {
                         response.not_updated.append(
@@ -1008,6 +1068,19 @@ impl JMAP {
             }
         }
 
+        // Train the account's spam model in the background
+        if self.has_spam_training() {
+            for (document_ids, is_spam) in [(train_spam, true), (train_ham, false)] {
+                if !document_ids.is_empty() {
+                    let jmap = self.clone();
+                    tokio::spawn(async move {
+                        jmap.bayes_train_account(account_id, document_ids, is_spam)
+                            .await;
+                    });
+                }
+            }
+        }
+
         // Process deletions
         if !will_destroy.is_empty() {
             let email_ids = self
@@ -48,8 +48,12 @@ impl JMAP {
         let mut terms = vec![];
         let mut is_exact = false;
         let mut language = self.core.jmap.default_language;
+        let account_id = request.account_id.document_id();
 
-        for cond in request.filter {
+        for cond in self
+            .expand_saved_searches(account_id, request.filter, access_token)
+            .await?
+        {
             match cond {
                 Filter::Text(text) | Filter::Subject(text) | Filter::Body(text) => {
                     if include_term {
@@ -88,7 +92,6 @@ impl JMAP {
                 _ => (),
             }
         }
-        let account_id = request.account_id.document_id();
         let document_ids = self
             .owned_or_shared_messages(access_token, account_id, Acl::ReadItems)
             .await?;
@@ -0,0 +1,221 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    types::{
+        collection::Collection, id::Id, keyword::Keyword, property::Property, state::StateChange,
+        type_state::DataType,
+    },
+};
+use store::{
+    write::{
+        assert::HashedValue, key::DeserializeBigEndian, log::ChangeLogBuilder, now, BatchBuilder,
+        ValueClass, F_CLEAR, F_VALUE,
+    },
+    IterateParams, ValueKey, U32_LEN, U64_LEN,
+};
+
+use crate::{
+    mailbox::{UidMailbox, INBOX_ID},
+    JMAP,
+};
+
+use super::set::TagManager;
+
+impl JMAP {
+    pub async fn email_wake_snoozed(&self) {
+        let from_key = ValueKey::<ValueClass<u32>> {
+            account_id: 0,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Snooze(0),
+        };
+        let to_key = ValueKey::<ValueClass<u32>> {
+            account_id: u32::MAX,
+            collection: 0,
+            document_id: u32::MAX,
+            class: ValueClass::Snooze(now()),
+        };
+
+        // Retrieve messages that are due to be woken up
+        let mut due = Vec::new();
+        if let Err(err) = self
+            .core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(from_key, to_key).ascending().no_values(),
+                |key, _| {
+                    due.push((
+                        key.deserialize_be_u64(1)?,
+                        key.deserialize_be_u32(U64_LEN + 1)?,
+                        key.deserialize_be_u32(U64_LEN + U32_LEN + 1)?,
+                    ));
+
+                    Ok(true)
+                },
+            )
+            .await
+        {
+            tracing::error!(
+                context = "email_snooze",
+                event = "error",
+                reason = ?err,
+                "Failed to iterate over snoozed emails"
+            );
+            return;
+        }
+
+        for (snoozed_until, account_id, document_id) in due {
+            if let Err(err) = self
+                .email_wake(account_id, document_id, snoozed_until)
+                .await
+            {
+                tracing::error!(
+                    context = "email_snooze",
+                    event = "error",
+                    account_id = account_id,
+                    document_id = document_id,
+                    reason = ?err,
+                    "Failed to wake up snoozed email"
+                );
+            }
+        }
+    }
+
+    async fn email_wake(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        snoozed_until: u64,
+    ) -> Result<(), MethodError> {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Email)
+            .update_document(document_id)
+            .clear(ValueClass::Snooze(snoozed_until));
+
+        // Entries left behind by a rescheduled or cancelled snooze are just removed
+        let mut changes = ChangeLogBuilder::new();
+        if self
+            .get_property::<u64>(
+                account_id,
+                Collection::Email,
+                document_id,
+                Property::SnoozedUntil,
+            )
+            .await?
+            == Some(snoozed_until)
+        {
+            batch.value(Property::SnoozedUntil, (), F_VALUE | F_CLEAR);
+
+            // Move the message back to the Inbox if it is still in the snoozed mailbox
+            if let (Some(snoozed_id), Some(thread_id), Some(mailboxes), Some(keywords)) = (
+                self.mailbox_get_by_role(account_id, "snoozed").await?,
+                self.get_property::<u32>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::ThreadId,
+                )
+                .await?,
+                self.get_property::<HashedValue<Vec<UidMailbox>>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::MailboxIds,
+                )
+                .await?,
+                self.get_property::<HashedValue<Vec<Keyword>>>(
+                    account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::Keywords,
+                )
+                .await?,
+            ) {
+                let mut mailboxes = TagManager::new(mailboxes);
+                let mut keywords = TagManager::new(keywords);
+
+                if mailboxes
+                    .current()
+                    .contains(&UidMailbox::new_unassigned(snoozed_id))
+                {
+                    mailboxes.update(UidMailbox::new_unassigned(snoozed_id), false);
+                    mailboxes.update(UidMailbox::new_unassigned(INBOX_ID), true);
+                    keywords.update(Keyword::Seen, false);
+
+                    // Obtain an IMAP UID for the Inbox
+                    for uid_mailbox in mailboxes.inner_tags_mut() {
+                        if uid_mailbox.uid == 0 {
+                            uid_mailbox.uid = self
+                                .assign_imap_uid(account_id, uid_mailbox.mailbox_id)
+                                .await
+                                .map_err(|_| MethodError::ServerPartialFail)?;
+                        }
+                    }
+
+                    changes.change_id = self.assign_change_id(account_id).await?;
+                    changes.log_update(Collection::Email, Id::from_parts(thread_id, document_id));
+                    changes.log_child_update(Collection::Mailbox, snoozed_id);
+                    changes.log_child_update(Collection::Mailbox, INBOX_ID);
+                    batch.value(Property::Cid, changes.change_id, F_VALUE);
+                    mailboxes.update_batch(&mut batch, Property::MailboxIds);
+                    if keywords.has_changes() {
+                        keywords.update_batch(&mut batch, Property::Keywords);
+                    }
+                }
+            }
+        }
+
+        self.core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    event = "error",
+                    context = "email_snooze",
+                    error = ?err,
+                    "Failed to write message changes to database.");
+                MethodError::ServerPartialFail
+            })?;
+
+        // Write and broadcast changes
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            self.broadcast_state_change(
+                StateChange::new(account_id)
+                    .with_change(DataType::Email, change_id)
+                    .with_change(DataType::Mailbox, change_id)
+                    .with_change(DataType::Thread, change_id),
+            )
+            .await;
+        }
+
+        Ok(())
+    }
+}
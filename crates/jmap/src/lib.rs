@@ -28,8 +28,10 @@ use std::{
     time::Duration,
 };
 
-use auth::{rate_limit::ConcurrencyLimiters, AccessToken};
-use common::{manager::webadmin::WebAdminManager, Core, DeliveryEvent, SharedCore};
+use auth::{rate_limit::ConcurrencyLimiters, AccessToken, AccessTokenKey};
+use common::{
+    history::HistoryLayer, manager::webadmin::WebAdminManager, Core, DeliveryEvent, SharedCore,
+};
 use dashmap::DashMap;
 use directory::QueryBy;
 use email::cache::Threads;
@@ -43,7 +45,10 @@ use jmap_proto::{
 };
 use services::{
     delivery::spawn_delivery_manager,
+    event_bus::{init_event_bus, spawn_event_bus},
+    history::spawn_event_history,
     housekeeper::{self, init_housekeeper, spawn_housekeeper},
+    migrate::MigrationJob,
     state::{self, init_state_manager, spawn_state_manager},
 };
 
@@ -71,12 +76,14 @@ pub mod api;
 pub mod auth;
 pub mod blob;
 pub mod changes;
+pub mod dav;
 pub mod email;
 pub mod identity;
 pub mod mailbox;
 pub mod principal;
 pub mod push;
 pub mod quota;
+pub mod saved_search;
 pub mod services;
 pub mod sieve;
 pub mod submission;
@@ -102,16 +109,18 @@ pub struct JmapInstance {
 }
 
 pub struct Inner {
-    pub sessions: TtlDashMap<String, u32>,
-    pub access_tokens: TtlDashMap<u32, Arc<AccessToken>>,
+    pub sessions: TtlDashMap<String, AccessTokenKey>,
+    pub access_tokens: TtlDashMap<AccessTokenKey, Arc<AccessToken>>,
     pub snowflake_id: SnowflakeIdGenerator,
     pub webadmin: WebAdminManager,
     pub config_version: AtomicU8,
 
     pub concurrency_limiter: DashMap<u32, Arc<ConcurrencyLimiters>>,
+    pub migrations: DashMap<u32, Arc<MigrationJob>>,
 
     pub state_tx: mpsc::Sender<state::Event>,
     pub housekeeper_tx: mpsc::Sender<housekeeper::Event>,
+    pub event_bus_tx: mpsc::Sender<Vec<u8>>,
 
     pub cache_threads: LruCache<u32, Arc<Threads>>,
 }
@@ -133,6 +142,7 @@ impl JMAP {
         // Init state manager and housekeeper
         let (state_tx, state_rx) = init_state_manager();
         let (housekeeper_tx, housekeeper_rx) = init_housekeeper();
+        let (event_bus_tx, event_bus_rx) = init_event_bus();
         let shard_amount = config
             .property::<u64>("cache.shard")
             .unwrap_or(32)
@@ -152,8 +162,10 @@ impl JMAP {
                 RandomState::default(),
                 shard_amount,
             ),
+            migrations: DashMap::default(),
             state_tx,
             housekeeper_tx,
+            event_bus_tx,
             cache_threads: LruCache::with_capacity(
                 config.property("cache.thread.size").unwrap_or(2048),
             ),
@@ -177,9 +189,17 @@ impl JMAP {
         // Spawn state manager
         spawn_state_manager(jmap_instance.clone(), state_rx);
 
+        // Spawn cluster event bus subscriber
+        spawn_event_bus(jmap_instance.clone(), event_bus_rx);
+
         // Spawn housekeeper
         spawn_housekeeper(jmap_instance.clone(), housekeeper_rx);
 
+        // Spawn event history writer
+        if let Some(history_rx) = HistoryLayer::take_receiver() {
+            spawn_event_history(jmap_instance.clone(), history_rx);
+        }
+
         jmap_instance
     }
 
@@ -404,6 +424,12 @@ impl JMAP {
             })
     }
 
+    pub async fn get_used_messages(&self, account_id: u32) -> Result<u64, MethodError> {
+        self.get_document_ids(account_id, Collection::Email)
+            .await
+            .map(|ids| ids.map_or(0, |ids| ids.len()))
+    }
+
     pub async fn filter(
         &self,
         account_id: u32,
@@ -427,6 +453,30 @@ impl JMAP {
             })
     }
 
+    // Client queries may be served by a read replica
+    pub async fn query_filter(
+        &self,
+        account_id: u32,
+        collection: Collection,
+        filters: Vec<Filter>,
+    ) -> Result<ResultSet, MethodError> {
+        self.core
+            .storage
+            .data
+            .query_filter(account_id, collection, filters)
+            .await
+            .map_err(|err| {
+                tracing::error!(event = "error",
+                                context = "filter",
+                                account_id = account_id,
+                                collection = ?collection,
+                                error = ?err,
+                                "Failed to execute filter.");
+
+                MethodError::ServerPartialFail
+            })
+    }
+
     pub async fn fts_filter<T: Into<u8> + Display + Clone + std::fmt::Debug>(
         &self,
         account_id: u32,
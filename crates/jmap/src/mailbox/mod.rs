@@ -40,6 +40,7 @@ pub const TRASH_ID: u32 = 1;
 pub const JUNK_ID: u32 = 2;
 pub const DRAFTS_ID: u32 = 3;
 pub const SENT_ID: u32 = 4;
+pub const ARCHIVE_ID: u32 = 5;
 pub const TOMBSTONE_ID: u32 = u32::MAX - 1;
 
 #[derive(Debug, Clone, Copy)]
@@ -101,3 +102,41 @@ impl UidMailbox {
         UidMailbox { mailbox_id, uid: 0 }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavedDate {
+    pub mailbox_id: u32,
+    pub saved_at: u64,
+}
+
+impl SerializeInto for SavedDate {
+    fn serialize_into(&self, buf: &mut Vec<u8>) {
+        buf.push_leb128(self.mailbox_id);
+        buf.push_leb128(self.saved_at);
+    }
+}
+
+impl DeserializeFrom for SavedDate {
+    fn deserialize_from(bytes: &mut Iter<'_, u8>) -> Option<Self> {
+        Some(SavedDate {
+            mailbox_id: bytes.next_leb128()?,
+            saved_at: bytes.next_leb128()?,
+        })
+    }
+}
+
+impl SavedDate {
+    pub fn new(mailbox_id: u32, saved_at: u64) -> Self {
+        SavedDate {
+            mailbox_id,
+            saved_at,
+        }
+    }
+
+    pub fn find(saved_dates: &[SavedDate], mailbox_id: u32) -> Option<u64> {
+        saved_dates
+            .iter()
+            .find(|saved_date| saved_date.mailbox_id == mailbox_id)
+            .map(|saved_date| saved_date.saved_at)
+    }
+}
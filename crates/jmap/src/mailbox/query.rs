@@ -102,7 +102,7 @@ impl JMAP {
         }
 
         let mut result_set = self
-            .filter(account_id, Collection::Mailbox, filters)
+            .query_filter(account_id, Collection::Mailbox, filters)
             .await?;
         if access_token.is_shared(account_id) {
             result_set.apply_mask(
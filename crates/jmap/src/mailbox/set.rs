@@ -60,7 +60,7 @@ use crate::{
 
 #[allow(unused_imports)]
 use super::{UidMailbox, INBOX_ID, JUNK_ID, TRASH_ID};
-use super::{DRAFTS_ID, SENT_ID};
+use super::{ARCHIVE_ID, DRAFTS_ID, SENT_ID};
 
 struct SetContext<'x> {
     account_id: u32,
@@ -594,7 +594,15 @@ impl JMAP {
                 (Property::Role, MaybePatchValue::Value(Value::Text(value))) => {
                     let role = value.trim().to_lowercase();
                     if [
-                        "inbox", "trash", "spam", "junk", "drafts", "archive", "sent",
+                        "inbox",
+                        "trash",
+                        "spam",
+                        "junk",
+                        "drafts",
+                        "archive",
+                        "sent",
+                        "important",
+                        "snoozed",
                     ]
                     .contains(&role.as_str())
                     {
@@ -837,14 +845,24 @@ impl JMAP {
             .with_account_id(account_id)
             .with_collection(Collection::Mailbox);
 
-        // Create mailboxes
-        for (name, role, document_id) in [
-            ("Inbox", "inbox", INBOX_ID),
-            ("Deleted Items", "trash", TRASH_ID),
-            ("Junk Mail", "junk", JUNK_ID),
-            ("Drafts", "drafts", DRAFTS_ID),
-            ("Sent Items", "sent", SENT_ID),
-        ] {
+        // Create the inbox and any configured special-use mailboxes
+        for (name, role, document_id) in [("Inbox", "inbox", INBOX_ID)].into_iter().chain(
+            self.core
+                .jmap
+                .mailbox_defaults
+                .iter()
+                .filter_map(|(role, name)| {
+                    let document_id = match *role {
+                        "trash" => TRASH_ID,
+                        "junk" => JUNK_ID,
+                        "drafts" => DRAFTS_ID,
+                        "sent" => SENT_ID,
+                        "archive" => ARCHIVE_ID,
+                        _ => return None,
+                    };
+                    Some((name.as_str(), *role, document_id))
+                }),
+        ) {
             batch.create_document_with_id(document_id).custom(
                 ObjectIndexBuilder::new(SCHEMA).with_changes(
                     Object::with_capacity(4)
@@ -22,15 +22,23 @@
 */
 
 use base64::{engine::general_purpose, Engine};
+use common::config::jmap::settings::VapidKey;
 use jmap_proto::types::id::Id;
 use store::ahash::{AHashMap, AHashSet};
 use tokio::sync::mpsc;
 
-use crate::{api::StateChangeResponse, services::IPC_CHANNEL_BUFFER, JmapInstance, LONG_SLUMBER};
+use crate::{
+    api::StateChangeResponse, services::IPC_CHANNEL_BUFFER, JmapInstance, JMAP, LONG_SLUMBER,
+};
 
-use super::{ece::ece_encrypt, EncryptionKeys, Event, PushServer, PushUpdate};
+use super::{
+    ece::ece_encrypt, vapid::vapid_authorization, EncryptionKeys, Event, PushServer, PushUpdate,
+};
 
-use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::{
+    header::{AUTHORIZATION, CONTENT_ENCODING, CONTENT_TYPE},
+    StatusCode,
+};
 use std::{
     collections::hash_map::Entry,
     time::{Duration, Instant},
@@ -56,6 +64,7 @@ pub fn spawn_push_manager(core: JmapInstance) -> mpsc::Sender<Event> {
             let push_timeout = core_.jmap.push_timeout;
             let push_verify_timeout = core_.jmap.push_verify_timeout;
             let push_throttle = core_.jmap.push_throttle;
+            let push_vapid = core_.jmap.push_vapid.clone();
 
             match tokio::time::timeout(retry_timeout, push_rx.recv()).await {
                 Ok(Some(event)) => match event {
@@ -100,6 +109,7 @@ pub fn spawn_push_manager(core: JmapInstance) -> mpsc::Sender<Event> {
                                                     code
                                                 ),
                                                 keys,
+                                                push_vapid,
                                                 push_timeout,
                                             )
                                             .await;
@@ -149,7 +159,12 @@ pub fn spawn_push_manager(core: JmapInstance) -> mpsc::Sender<Event> {
                                             .contains(&subscription.num_attempts)
                                             && last_request > push_attempt_interval))
                                 {
-                                    subscription.send(id, push_tx.clone(), push_timeout);
+                                    subscription.send(
+                                        id,
+                                        push_tx.clone(),
+                                        push_vapid.clone(),
+                                        push_timeout,
+                                    );
                                     retry_ids.remove(&id);
                                 } else {
                                     retry_ids.insert(id);
@@ -178,6 +193,17 @@ pub fn spawn_push_manager(core: JmapInstance) -> mpsc::Sender<Event> {
                             retry_ids.insert(id);
                         }
                     }
+                    Event::SubscriptionExpired { id } => {
+                        // The push service no longer accepts messages for this
+                        // subscription, destroy it (RFC 8030, section 7.3)
+                        subscriptions.remove(&id);
+                        retry_ids.remove(&id);
+                        let jmap = JMAP::from(core.clone());
+                        tokio::spawn(async move {
+                            jmap.push_subscription_expire(id.prefix_id(), id.document_id())
+                                .await;
+                        });
+                    }
                 },
                 Ok(None) => {
                     break;
@@ -202,7 +228,12 @@ pub fn spawn_push_manager(core: JmapInstance) -> mpsc::Sender<Event> {
                                         && last_request >= push_attempt_interval))
                             {
                                 if subscription.num_attempts < push_attempts_max {
-                                    subscription.send(*retry_id, push_tx.clone(), push_timeout);
+                                    subscription.send(
+                                        *retry_id,
+                                        push_tx.clone(),
+                                        push_vapid.clone(),
+                                        push_timeout,
+                                    );
                                 } else {
                                     tracing::debug!(
                                         concat!(
@@ -244,7 +275,13 @@ pub fn spawn_push_manager(core: JmapInstance) -> mpsc::Sender<Event> {
 }
 
 impl PushServer {
-    fn send(&mut self, id: Id, push_tx: mpsc::Sender<Event>, push_timeout: Duration) {
+    fn send(
+        &mut self,
+        id: Id,
+        push_tx: mpsc::Sender<Event>,
+        push_vapid: Option<VapidKey>,
+        push_timeout: Duration,
+    ) {
         let url = self.url.clone();
         let keys = self.keys.clone();
         let state_changes = std::mem::take(&mut self.state_changes);
@@ -265,17 +302,18 @@ impl PushServer {
 
             push_tx
                 .send(
-                    if http_request(
+                    match http_request(
                         url,
                         serde_json::to_string(&response).unwrap(),
                         keys,
+                        push_vapid,
                         push_timeout,
                     )
                     .await
                     {
-                        Event::DeliverySuccess { id }
-                    } else {
-                        Event::DeliveryFailure { id, state_changes }
+                        PushResult::Success => Event::DeliverySuccess { id },
+                        PushResult::Failure => Event::DeliveryFailure { id, state_changes },
+                        PushResult::Expired => Event::SubscriptionExpired { id },
                     },
                 )
                 .await
@@ -284,12 +322,19 @@ impl PushServer {
     }
 }
 
+enum PushResult {
+    Success,
+    Failure,
+    Expired,
+}
+
 async fn http_request(
     url: String,
     mut body: String,
     keys: Option<EncryptionKeys>,
+    push_vapid: Option<VapidKey>,
     push_timeout: Duration,
-) -> bool {
+) -> PushResult {
     let client_builder = reqwest::Client::builder().timeout(push_timeout);
 
     #[cfg(feature = "test_mode")]
@@ -313,16 +358,36 @@ async fn http_request(
             Err(err) => {
                 // Do not reattempt if encryption fails.
                 tracing::debug!("Failed to encrypt push subscription to {}: {}", url, err);
-                return true;
+                return PushResult::Success;
+            }
+        }
+    }
+
+    // Identify the application server to the push service (RFC 8292)
+    if let Some(vapid) = push_vapid {
+        match vapid_authorization(&vapid, &url) {
+            Ok(authorization) => {
+                client = client.header(AUTHORIZATION, authorization);
+            }
+            Err(err) => {
+                tracing::debug!("Failed to sign VAPID token for {}: {}", url, err);
             }
         }
     }
 
     match client.body(body).send().await {
-        Ok(response) => response.status().is_success(),
+        Ok(response) => match response.status() {
+            status if status.is_success() => PushResult::Success,
+            StatusCode::NOT_FOUND | StatusCode::GONE => {
+                // The push subscription expired or was removed, do not reattempt.
+                tracing::debug!("Push subscription to {} is no longer valid.", url);
+                PushResult::Expired
+            }
+            _ => PushResult::Failure,
+        },
         Err(err) => {
             tracing::debug!("HTTP post to {} failed with: {}", url, err);
-            false
+            PushResult::Failure
         }
     }
 }
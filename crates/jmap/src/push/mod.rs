@@ -25,6 +25,7 @@ pub mod ece;
 pub mod get;
 pub mod manager;
 pub mod set;
+pub mod vapid;
 
 use std::time::Instant;
 
@@ -73,6 +74,9 @@ pub enum Event {
         id: Id,
         state_changes: Vec<StateChange>,
     },
+    SubscriptionExpired {
+        id: Id,
+    },
     Reset,
 }
 
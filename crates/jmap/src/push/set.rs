@@ -216,6 +216,36 @@ impl JMAP {
 
         Ok(response)
     }
+
+    pub async fn push_subscription_expire(&self, account_id: u32, document_id: u32) {
+        // The subscription may have been destroyed in the meantime
+        if !matches!(
+            self.get_document_ids(account_id, Collection::PushSubscription)
+                .await,
+            Ok(Some(push_ids)) if push_ids.contains(document_id)
+        ) {
+            return;
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::PushSubscription)
+            .delete_document(document_id)
+            .value(Property::Value, (), F_VALUE | F_CLEAR);
+        if let Err(err) = self.write_batch(batch).await {
+            tracing::warn!(
+                context = "push_subscription",
+                event = "error",
+                account_id = account_id,
+                document_id = document_id,
+                reason = ?err,
+                "Failed to destroy expired push subscription."
+            );
+        } else {
+            self.update_push_subscriptions(account_id).await;
+        }
+    }
 }
 
 fn validate_push_value(
@@ -0,0 +1,67 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use base64::{engine::general_purpose, Engine};
+use common::config::jmap::settings::VapidKey;
+use p256::{
+    ecdsa::{signature::Signer, Signature, SigningKey},
+    elliptic_curve::sec1::ToEncodedPoint,
+};
+use store::write::now;
+
+// VAPID tokens are valid for 12 hours, the maximum allowed is 24 hours (RFC 8292, section 2)
+const VAPID_TOKEN_EXPIRY: u64 = 12 * 60 * 60;
+
+pub fn vapid_authorization(vapid: &VapidKey, url: &str) -> Result<String, String> {
+    let signing_key = SigningKey::from_slice(&vapid.private_key).map_err(|e| e.to_string())?;
+    let audience = reqwest::Url::parse(url)
+        .map_err(|e| e.to_string())?
+        .origin()
+        .ascii_serialization();
+
+    let token = format!(
+        "{}.{}",
+        general_purpose::URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"ES256"}"#),
+        general_purpose::URL_SAFE_NO_PAD.encode(
+            serde_json::json!({
+                "aud": audience,
+                "exp": now() + VAPID_TOKEN_EXPIRY,
+                "sub": vapid.subject,
+            })
+            .to_string()
+        )
+    );
+    let signature: Signature = signing_key.sign(token.as_bytes());
+
+    Ok(format!(
+        "vapid t={}.{}, k={}",
+        token,
+        general_purpose::URL_SAFE_NO_PAD.encode(signature.to_bytes()),
+        general_purpose::URL_SAFE_NO_PAD.encode(
+            signing_key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+        )
+    ))
+}
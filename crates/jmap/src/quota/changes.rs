@@ -0,0 +1,70 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::changes::{ChangesRequest, ChangesResponse},
+    types::{id::Id, state::State},
+};
+
+use crate::{auth::AccessToken, JMAP};
+
+impl JMAP {
+    pub async fn quota_changes(
+        &self,
+        request: ChangesRequest,
+        access_token: &AccessToken,
+    ) -> Result<ChangesResponse, MethodError> {
+        let quota = access_token.quota as i64;
+        let state = if quota > 0 {
+            self.quota_state(
+                quota,
+                self.get_used_quota(request.account_id.document_id())
+                    .await?,
+            )
+        } else {
+            0
+        };
+        let mut response = ChangesResponse {
+            account_id: request.account_id,
+            old_state: request.since_state.clone(),
+            new_state: State::Exact(state),
+            has_more_changes: false,
+            created: vec![],
+            updated: vec![],
+            destroyed: vec![],
+            updated_properties: None,
+        };
+
+        // The quota state only changes when usage crosses a configured threshold
+        if quota > 0 {
+            match request.since_state {
+                State::Initial => response.created.push(Id::from(0u32)),
+                State::Exact(since_state) if since_state == state => (),
+                _ => response.updated.push(Id::from(0u32)),
+            }
+        }
+
+        Ok(response)
+    }
+}
@@ -60,15 +60,19 @@ impl JMAP {
         } else {
             quota_ids.iter().map(|id| Id::from(*id)).collect()
         };
+        let used_quota = if !quota_ids.is_empty() {
+            self.get_used_quota(account_id).await?
+        } else {
+            0
+        };
         let mut response = GetResponse {
             account_id: request.account_id.into(),
-            state: State::Initial.into(),
+            state: State::Exact(self.quota_state(access_token.quota as i64, used_quota)).into(),
             list: Vec::with_capacity(ids.len()),
             not_found: vec![],
         };
 
         for id in ids {
-            // Obtain the sieve script object
             let document_id = id.document_id();
             if !quota_ids.contains(&document_id) {
                 response.not_found.push(id.into());
@@ -80,7 +84,7 @@ impl JMAP {
                 let value = match property {
                     Property::Id => Value::Id(id),
                     Property::ResourceType => "octets".to_string().into(),
-                    Property::Used => (self.get_used_quota(account_id).await? as u64).into(),
+                    Property::Used => (used_quota as u64).into(),
                     Property::HardLimit => access_token.quota.into(),
                     Property::Scope => "account".to_string().into(),
                     Property::Name => access_token.name.clone().into(),
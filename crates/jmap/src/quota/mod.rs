@@ -21,5 +21,60 @@
  * for more details.
 */
 
+pub mod changes;
 pub mod get;
 pub mod query;
+
+use directory::QueryBy;
+use jmap_proto::types::{state::StateChange, type_state::DataType};
+
+use crate::JMAP;
+
+impl JMAP {
+    // Returns the number of configured usage thresholds reached by the account
+    pub fn quota_state(&self, quota: i64, used: i64) -> u64 {
+        if quota > 0 {
+            let used = (used.max(0) as u128 * 100 / quota as u128) as u64;
+            self.core
+                .jmap
+                .quota_thresholds
+                .iter()
+                .filter(|threshold| used >= **threshold)
+                .count() as u64
+        } else {
+            0
+        }
+    }
+
+    pub async fn notify_quota_change(&self, account_id: u32, delta: i64) {
+        if let Ok(Some(principal)) = self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+        {
+            let quota = principal.quota as i64;
+            if quota > 0 {
+                if let Ok(used) = self.get_used_quota(account_id).await {
+                    let state = self.quota_state(quota, used);
+                    if state != self.quota_state(quota, used - delta) {
+                        tracing::debug!(
+                            context = "quota",
+                            event = "threshold",
+                            account_id = account_id,
+                            used = used,
+                            quota = quota,
+                            "Account usage crossed a quota threshold."
+                        );
+
+                        self.broadcast_state_change(
+                            StateChange::new(account_id).with_change(DataType::Quota, state),
+                        )
+                        .await;
+                    }
+                }
+            }
+        }
+    }
+}
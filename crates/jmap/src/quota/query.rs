@@ -85,7 +85,7 @@ impl JMAP {
         }
 
         let result_set = self
-            .filter(account_id, Collection::Quota, filters)
+            .query_filter(account_id, Collection::Quota, filters)
             .await?;
 
         let (response, paginate) = self.build_query_response(&result_set, &request).await?;
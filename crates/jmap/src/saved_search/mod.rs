@@ -0,0 +1,110 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    method::query::{parse_filter, Filter},
+    object::Object,
+    parser::{json::Parser, Ignore, Token},
+    types::{collection::Collection, property::Property, value::Value},
+};
+
+use crate::{auth::AccessToken, JMAP};
+
+pub mod get;
+pub mod set;
+
+impl JMAP {
+    /// Replaces `inSavedSearch` conditions with the filter stored in the
+    /// referenced saved search.
+    pub async fn expand_saved_searches(
+        &self,
+        account_id: u32,
+        filter: Vec<Filter>,
+        access_token: &AccessToken,
+    ) -> Result<Vec<Filter>, MethodError> {
+        if !filter
+            .iter()
+            .any(|cond| matches!(cond, Filter::InSavedSearch(_)))
+        {
+            return Ok(filter);
+        }
+
+        let mut expanded = Vec::with_capacity(filter.len());
+        for cond in filter {
+            if let Filter::InSavedSearch(id) = cond {
+                // Saved searches are private to the account owner
+                let saved_search = if access_token.is_member(account_id) {
+                    self.get_property::<Object<Value>>(
+                        account_id,
+                        Collection::SavedSearch,
+                        id.document_id(),
+                        Property::Value,
+                    )
+                    .await?
+                } else {
+                    None
+                };
+                let saved_filter = saved_search
+                    .and_then(|mut saved_search| {
+                        parse_saved_filter(&saved_search.remove(&Property::Filter))
+                    })
+                    .ok_or_else(|| {
+                        MethodError::InvalidArguments(format!("Saved search {id} not found."))
+                    })?;
+
+                expanded.push(Filter::And);
+                expanded.extend(saved_filter);
+                expanded.push(Filter::Close);
+            } else {
+                expanded.push(cond);
+            }
+        }
+
+        Ok(expanded)
+    }
+}
+
+/// Parses a filter stored in a saved search, returning `None` if it is not a
+/// valid Email/query filter. Saved searches cannot reference other saved
+/// searches.
+pub fn parse_saved_filter(filter: &Value) -> Option<Vec<Filter>> {
+    if !matches!(filter, Value::Object(_)) {
+        return None;
+    }
+    let bytes = serde_json::to_vec(filter).ok()?;
+    let mut parser = Parser::new(&bytes);
+    if !matches!(parser.next_token::<Ignore>().ok()?, Token::DictStart) {
+        return None;
+    }
+    let filter = parse_filter(&mut parser).ok()?;
+
+    if filter
+        .iter()
+        .all(|cond| !matches!(cond, Filter::InSavedSearch(_) | Filter::_T(_)))
+    {
+        Some(filter)
+    } else {
+        None
+    }
+}
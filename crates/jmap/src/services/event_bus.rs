@@ -0,0 +1,166 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use common::manager::node_id;
+use jmap_proto::types::{state::StateChange, type_state::DataType};
+use tokio::sync::mpsc;
+
+use crate::JmapInstance;
+
+use super::{state::Event, IPC_CHANNEL_BUFFER};
+
+pub const STATE_CHANNEL: &str = "stalwart.state";
+const RESUBSCRIBE_AFTER: Duration = Duration::from_secs(5);
+
+pub fn init_event_bus() -> (mpsc::Sender<Vec<u8>>, mpsc::Receiver<Vec<u8>>) {
+    mpsc::channel::<Vec<u8>>(IPC_CHANNEL_BUFFER)
+}
+
+pub fn spawn_event_bus(core: JmapInstance, mut publish_rx: mpsc::Receiver<Vec<u8>>) {
+    // State changes are published from a single task so they reach other nodes in order
+    let publisher = core.clone();
+    tokio::spawn(async move {
+        while let Some(message) = publish_rx.recv().await {
+            let pubsub = publisher.core.load().storage.pubsub.clone();
+            if let Err(err) = pubsub.publish(STATE_CHANNEL, message).await {
+                tracing::debug!(
+                    context = "event_bus",
+                    event = "error",
+                    reason = %err,
+                    "Failed to publish state change."
+                );
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let node_id = node_id();
+
+        loop {
+            let pubsub = core.core.load().storage.pubsub.clone();
+            if pubsub.is_none() {
+                break;
+            }
+
+            match pubsub.subscribe(STATE_CHANNEL).await {
+                Ok(mut stream) => {
+                    tracing::debug!(
+                        context = "event_bus",
+                        event = "subscribe",
+                        channel = STATE_CHANNEL,
+                        "Subscribed to cluster event bus."
+                    );
+
+                    while let Some(message) = stream.next().await {
+                        match decode_state_change(&message) {
+                            Some((origin, state_change)) if origin != node_id => {
+                                if core
+                                    .jmap_inner
+                                    .state_tx
+                                    .send(Event::Publish {
+                                        state_change,
+                                        is_remote: true,
+                                    })
+                                    .await
+                                    .is_err()
+                                {
+                                    // State manager is gone, the server is shutting down
+                                    return;
+                                }
+                            }
+                            Some(_) => {}
+                            None => {
+                                tracing::debug!(
+                                    context = "event_bus",
+                                    event = "error",
+                                    reason = "invalid-message",
+                                    "Received invalid state change from event bus."
+                                );
+                            }
+                        }
+                    }
+
+                    tracing::warn!(
+                        context = "event_bus",
+                        event = "disconnect",
+                        channel = STATE_CHANNEL,
+                        "Lost connection to cluster event bus."
+                    );
+                }
+                Err(err) => {
+                    tracing::error!(
+                        context = "event_bus",
+                        event = "error",
+                        reason = %err,
+                        "Failed to subscribe to cluster event bus."
+                    );
+                }
+            }
+
+            tokio::time::sleep(RESUBSCRIBE_AFTER).await;
+        }
+    });
+}
+
+pub fn publish_state_change(publish_tx: &mpsc::Sender<Vec<u8>>, state_change: &StateChange) {
+    if let Err(err) = publish_tx.try_send(encode_state_change(&node_id(), state_change)) {
+        tracing::debug!(
+            context = "event_bus",
+            event = "error",
+            reason = %err,
+            "Failed to queue state change for publishing."
+        );
+    }
+}
+
+// State changes are sent as "<node-id>\n<account-id>\n<type>:<change-id>,..."
+pub fn encode_state_change(node_id: &str, state_change: &StateChange) -> Vec<u8> {
+    let mut message = format!("{node_id}\n{}\n", state_change.account_id);
+    for (pos, (data_type, change_id)) in state_change.types.iter().enumerate() {
+        if pos > 0 {
+            message.push(',');
+        }
+        message.push_str(&format!("{}:{change_id}", u64::from(*data_type)));
+    }
+    message.into_bytes()
+}
+
+pub fn decode_state_change(message: &[u8]) -> Option<(&str, StateChange)> {
+    let mut lines = std::str::from_utf8(message).ok()?.splitn(3, '\n');
+    let node_id = lines.next()?;
+    let mut state_change = StateChange::new(lines.next()?.parse().ok()?);
+    for item in lines.next()?.split(',').filter(|item| !item.is_empty()) {
+        let (data_type, change_id) = item.split_once(':')?;
+        let data_type = data_type
+            .parse::<u64>()
+            .ok()
+            .filter(|id| *id < DataType::None as u64)?;
+        state_change
+            .types
+            .push((DataType::from(data_type), change_id.parse().ok()?));
+    }
+
+    (!state_change.types.is_empty()).then_some((node_id, state_change))
+}
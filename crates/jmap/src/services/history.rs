@@ -0,0 +1,150 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use common::history::{HistoryEvent, HistoryReceiver};
+use store::{write::Bincode, Serialize};
+
+use crate::{JmapInstance, JMAP};
+
+pub const HISTORY_CHUNK_SIZE: usize = 100;
+const HISTORY_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const HISTORY_SEQ_KEY: &[u8] = b"history:seq";
+
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HistoryChunk {
+    pub events: Vec<HistoryEvent>,
+}
+
+pub fn spawn_event_history(core: JmapInstance, receiver: HistoryReceiver) {
+    tokio::spawn(async move {
+        tracing::debug!("Event history task started.");
+
+        let HistoryReceiver {
+            mut rx,
+            capacity,
+            retention,
+        } = receiver;
+        let num_chunks = capacity.div_ceil(HISTORY_CHUNK_SIZE).max(1) as u64;
+        let retention = retention.map(|r| r.as_secs());
+
+        // Resume appending to the last chunk
+        let jmap = JMAP::from(core.clone());
+        let mut seq = jmap.history_seq().await;
+        let mut chunk = jmap
+            .core
+            .storage
+            .lookup
+            .key_get::<Bincode<HistoryChunk>>(history_chunk_key(seq))
+            .await
+            .ok()
+            .flatten()
+            .map(|chunk| chunk.inner)
+            .unwrap_or_default();
+        let mut is_dirty = false;
+
+        loop {
+            match tokio::time::timeout(HISTORY_FLUSH_INTERVAL, rx.recv()).await {
+                Ok(Some(event)) => {
+                    chunk.events.push(event);
+                    is_dirty = true;
+                    if chunk.events.len() < HISTORY_CHUNK_SIZE {
+                        continue;
+                    }
+                }
+                Ok(None) => {
+                    break;
+                }
+                Err(_) => {
+                    if !is_dirty {
+                        continue;
+                    }
+                }
+            }
+
+            // Write the current chunk
+            let jmap = JMAP::from(core.clone());
+            let lookup = &jmap.core.storage.lookup;
+            if let Err(err) = lookup
+                .key_set(
+                    history_chunk_key(seq),
+                    Bincode::new(chunk.clone()).serialize(),
+                    retention,
+                )
+                .await
+            {
+                tracing::warn!(
+                    context = "history",
+                    event = "error",
+                    reason = %err,
+                    "Failed to store event history."
+                );
+            }
+            is_dirty = false;
+
+            // Move on to the next chunk once the current one is full,
+            // dropping the oldest chunk from the ring
+            if chunk.events.len() >= HISTORY_CHUNK_SIZE {
+                chunk.events.clear();
+                match lookup
+                    .counter_incr(HISTORY_SEQ_KEY.to_vec(), 1, None, true)
+                    .await
+                {
+                    Ok(next_seq) => {
+                        seq = next_seq as u64;
+                    }
+                    Err(err) => {
+                        seq += 1;
+                        tracing::warn!(
+                            context = "history",
+                            event = "error",
+                            reason = %err,
+                            "Failed to increment event history sequence."
+                        );
+                    }
+                }
+                if seq >= num_chunks {
+                    let _ = lookup.key_delete(history_chunk_key(seq - num_chunks)).await;
+                }
+            }
+        }
+
+        tracing::debug!("Event history task stopped.");
+    });
+}
+
+impl JMAP {
+    pub async fn history_seq(&self) -> u64 {
+        self.core
+            .storage
+            .lookup
+            .counter_get(HISTORY_SEQ_KEY.to_vec())
+            .await
+            .unwrap_or_default() as u64
+    }
+}
+
+pub fn history_chunk_key(seq: u64) -> Vec<u8> {
+    format!("history:{seq}").into_bytes()
+}
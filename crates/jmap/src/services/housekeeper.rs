@@ -23,9 +23,11 @@
 
 use std::{
     collections::BinaryHeap,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
+use common::{listener::tls::CertificateDirectory, Core};
 use store::{write::purge::PurgeStore, BlobStore, LookupStore, Store};
 use tokio::sync::mpsc;
 use utils::map::ttl_dashmap::TtlMap;
@@ -34,10 +36,14 @@ use crate::{Inner, JmapInstance, JMAP, LONG_SLUMBER};
 
 use super::IPC_CHANNEL_BUFFER;
 
+/// Leases ensure that singleton tasks only run on one node of the cluster.
+const TASK_LEASE: Duration = Duration::from_secs(300);
+
 pub enum Event {
     IndexStart,
     IndexDone,
     AcmeReload,
+    CertificateReload,
     AcmeReschedule {
         provider_id: String,
         renew_at: Instant,
@@ -51,6 +57,7 @@ pub enum Event {
 pub enum PurgeType {
     Data(Store),
     Blobs { store: Store, blob_store: BlobStore },
+    BlobTiering { store: Store, blob_store: BlobStore },
     Lookup(LookupStore),
     Account(Option<u32>),
 }
@@ -67,6 +74,8 @@ enum ActionClass {
     Account,
     Store(usize),
     Acme(String),
+    CertificateDir,
+    Snooze,
 }
 
 #[derive(Default)]
@@ -98,6 +107,10 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
             Instant::now() + core_.jmap.account_purge_frequency.time_to_next(),
             ActionClass::Account,
         );
+        queue.schedule(
+            Instant::now() + core_.jmap.mail_snooze_interval,
+            ActionClass::Snooze,
+        );
         for (idx, schedule) in core_.storage.purge_schedules.iter().enumerate() {
             queue.schedule(
                 Instant::now() + schedule.cron.time_to_next(),
@@ -124,6 +137,36 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
             };
         }
 
+        // Watch the certificate directory for changes
+        let mut cert_fingerprint = None;
+        if let Some(cert_dir) = &core_.tls.certificate_dir {
+            cert_fingerprint = CertificateDirectory::fingerprint(&cert_dir.path).into();
+            queue.schedule(
+                Instant::now() + cert_dir.poll_interval,
+                ActionClass::CertificateDir,
+            );
+        }
+
+        // Reload certificates on SIGHUP
+        #[cfg(not(target_env = "msvc"))]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let inner = core.jmap_inner.clone();
+            tokio::spawn(async move {
+                if let Ok(mut h_hup) = signal(SignalKind::hangup()) {
+                    while h_hup.recv().await.is_some() {
+                        tracing::debug!("Received SIGHUP.");
+                        inner
+                            .housekeeper_tx
+                            .send(Event::CertificateReload)
+                            .await
+                            .ok();
+                    }
+                }
+            });
+        }
+
         loop {
             match tokio::time::timeout(queue.wake_up_time(), rx.recv()).await {
                 Ok(Some(event)) => match event {
@@ -155,6 +198,9 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
                             }
                         });
                     }
+                    Event::CertificateReload => {
+                        tokio::spawn(reload_certificates(core.core.load().clone()));
+                    }
                     Event::AcmeReschedule {
                         provider_id,
                         renew_at,
@@ -200,6 +246,13 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
                                 }
                             });
                         }
+                        PurgeType::BlobTiering { store, blob_store } => {
+                            tokio::spawn(async move {
+                                if let Err(err) = store.migrate_blobs(blob_store).await {
+                                    tracing::error!("Failed to migrate blobs: {err}",);
+                                }
+                            });
+                        }
                         PurgeType::Lookup(store) => {
                             tokio::spawn(async move {
                                 if let Err(err) = store.purge_lookup_store().await {
@@ -243,6 +296,29 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
                                     if let Some(provider) =
                                         core.tls.acme_providers.get(&provider_id)
                                     {
+                                        // Only one node orders certificates, the others
+                                        // load them from the store once renewed
+                                        if !core
+                                            .try_lease(&format!("acme.{provider_id}"), TASK_LEASE)
+                                            .await
+                                        {
+                                            let renew_at = core
+                                                .init_acme(provider)
+                                                .await
+                                                .map_or(TASK_LEASE, |renew_at| {
+                                                    renew_at.max(TASK_LEASE)
+                                                });
+                                            inner
+                                                .housekeeper_tx
+                                                .send(Event::AcmeReschedule {
+                                                    provider_id: provider_id.clone(),
+                                                    renew_at: Instant::now() + renew_at,
+                                                })
+                                                .await
+                                                .ok();
+                                            return;
+                                        }
+
                                         tracing::info!(
                                             context = "acme",
                                             event = "order",
@@ -283,11 +359,28 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
                                     }
                                 });
                             }
+                            ActionClass::CertificateDir => {
+                                if let Some(cert_dir) = &core_.tls.certificate_dir {
+                                    let fingerprint =
+                                        CertificateDirectory::fingerprint(&cert_dir.path);
+                                    if cert_fingerprint != Some(fingerprint) {
+                                        cert_fingerprint = Some(fingerprint);
+                                        tokio::spawn(reload_certificates(core_.clone()));
+                                    }
+                                    queue.schedule(
+                                        Instant::now() + cert_dir.poll_interval,
+                                        ActionClass::CertificateDir,
+                                    );
+                                }
+                            }
                             ActionClass::Account => {
                                 let jmap = JMAP::from(core.clone());
                                 tokio::spawn(async move {
-                                    tracing::debug!("Purging accounts.");
-                                    jmap.purge_accounts().await;
+                                    if jmap.core.try_lease("purge.account", TASK_LEASE).await {
+                                        tracing::debug!("Purging accounts.");
+                                        jmap.purge_accounts().await;
+                                        jmap.purge_audit_log().await;
+                                    }
                                 });
                                 queue.schedule(
                                     Instant::now()
@@ -295,11 +388,27 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
                                     ActionClass::Account,
                                 );
                             }
+                            ActionClass::Snooze => {
+                                let jmap = JMAP::from(core.clone());
+                                tokio::spawn(async move {
+                                    if jmap.core.try_lease("email.snooze", TASK_LEASE).await {
+                                        jmap.email_wake_snoozed().await;
+                                    }
+                                });
+                                queue.schedule(
+                                    Instant::now() + core_.jmap.mail_snooze_interval,
+                                    ActionClass::Snooze,
+                                );
+                            }
                             ActionClass::Session => {
                                 let inner = core.jmap_inner.clone();
+                                let jmap = JMAP::from(core.clone());
                                 tokio::spawn(async move {
                                     tracing::debug!("Purging session cache.");
                                     inner.purge();
+                                    if let Err(err) = jmap.core.purge_expired_bans().await {
+                                        tracing::error!("Failed to purge expired IP bans: {err}");
+                                    }
                                 });
                                 queue.schedule(
                                     Instant::now()
@@ -315,7 +424,18 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
                                         Instant::now() + schedule.cron.time_to_next(),
                                         ActionClass::Store(idx),
                                     );
+                                    let core = core_.clone();
                                     tokio::spawn(async move {
+                                        if !core
+                                            .try_lease(
+                                                &format!("purge.store.{}", schedule.store_id),
+                                                TASK_LEASE,
+                                            )
+                                            .await
+                                        {
+                                            return;
+                                        }
+
                                         let (class, result) = match schedule.store {
                                             PurgeStore::Data(store) => {
                                                 ("data", store.purge_store().await)
@@ -323,6 +443,9 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
                                             PurgeStore::Blobs { store, blob_store } => {
                                                 ("blob", store.purge_blobs(blob_store).await)
                                             }
+                                            PurgeStore::BlobTiering { store, blob_store } => {
+                                                ("tiering", store.migrate_blobs(blob_store).await)
+                                            }
                                             PurgeStore::Lookup(lookup_store) => {
                                                 ("lookup", lookup_store.purge_lookup_store().await)
                                             }
@@ -353,6 +476,27 @@ pub fn spawn_housekeeper(core: JmapInstance, mut rx: mpsc::Receiver<Event>) {
     });
 }
 
+async fn reload_certificates(core: Arc<Core>) {
+    match core.reload_certificates().await {
+        Ok(result) => {
+            result.config.log_errors(false);
+            tracing::info!(
+                context = "tls",
+                event = "reload",
+                "Reloaded TLS certificates."
+            );
+        }
+        Err(err) => {
+            tracing::error!(
+                context = "tls",
+                event = "error",
+                error = ?err,
+                "Failed to reload TLS certificates."
+            );
+        }
+    }
+}
+
 impl Queue {
     pub fn schedule(&mut self, due: Instant, event: ActionClass) {
         tracing::debug!(due_in = due.saturating_duration_since(Instant::now()).as_secs(), event = ?event, "Scheduling housekeeper event.");
@@ -21,9 +21,11 @@
  * for more details.
 */
 
+use directory::QueryBy;
 use jmap_proto::types::{collection::Collection, property::Property};
+use nlp::language::Language;
 use store::{
-    fts::index::FtsDocument,
+    fts::{index::FtsDocument, Field},
     write::{
         key::DeserializeBigEndian, now, BatchBuilder, Bincode, FtsQueueClass, MaybeDynamicId,
         ValueClass,
@@ -35,6 +37,7 @@ use utils::{BlobHash, BLOB_HASH_LEN};
 
 use crate::{
     email::{index::IndexMessageText, metadata::MessageMetadata},
+    mailbox::UidMailbox,
     JMAP,
 };
 
@@ -149,12 +152,21 @@ impl JMAP {
                     let message = metadata.inner.contents.into_message(&raw_message);
 
                     // Index message
-                    let document =
+                    let language = self
+                        .fts_language_override(event.account_id, event.document_id)
+                        .await;
+                    let mut document =
                         FtsDocument::with_default_language(self.core.jmap.default_language)
+                            .with_language_override(language)
                             .with_account_id(event.account_id)
                             .with_collection(Collection::Email)
                             .with_document_id(event.document_id)
                             .index_message(&message);
+
+                    // Index text extracted from binary attachments
+                    for text in self.extract_attachments(&message).await {
+                        document.index(Field::Attachment, text, Language::Unknown);
+                    }
                     if let Err(err) = self.core.storage.fts.index(document).await {
                         tracing::error!(
                             context = "fts_index_queued",
@@ -229,6 +241,63 @@ impl JMAP {
         }
     }
 
+    // Mailbox overrides take precedence over account overrides
+    async fn fts_language_override(&self, account_id: u32, document_id: u32) -> Option<Language> {
+        let overrides = &self.core.jmap.language_overrides;
+        if overrides.is_empty() {
+            return None;
+        }
+
+        let name = match self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+        {
+            Ok(Some(principal)) => principal.name,
+            _ => return None,
+        };
+
+        let mut account_language = None;
+        let mut mailbox_ids: Option<Vec<UidMailbox>> = None;
+        for (key, language) in overrides {
+            if key == &name {
+                account_language = Some(*language);
+            } else if let Some(path) = key
+                .strip_prefix(name.as_str())
+                .and_then(|path| path.strip_prefix('/'))
+            {
+                if mailbox_ids.is_none() {
+                    mailbox_ids = self
+                        .get_property::<Vec<UidMailbox>>(
+                            account_id,
+                            Collection::Email,
+                            document_id,
+                            Property::MailboxIds,
+                        )
+                        .await
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default()
+                        .into();
+                }
+
+                if let Ok(Some(mailbox_id)) = self.mailbox_get_by_name(account_id, path).await {
+                    if mailbox_ids
+                        .iter()
+                        .flatten()
+                        .any(|mailbox| mailbox.mailbox_id == mailbox_id)
+                    {
+                        return Some(*language);
+                    }
+                }
+            }
+        }
+
+        account_language
+    }
+
     async fn try_lock_index(&self, event: &IndexEmail) -> bool {
         let mut batch = BatchBuilder::new();
         batch
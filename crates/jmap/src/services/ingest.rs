@@ -56,6 +56,9 @@ impl JMAP {
             }
         };
 
+        // Obtain the addresses this message was already delivered to
+        let delivery_chain = delivery_chain(&raw_message);
+
         // Obtain the UIDs for each recipient
         let mut recipients = Vec::with_capacity(message.recipients.len());
         let mut deliver_names = AHashMap::with_capacity(message.recipients.len());
@@ -85,6 +88,32 @@ impl JMAP {
 
         // Deliver to each recipient
         for (uid, (status, rcpt)) in &mut deliver_names {
+            // Break forwarding loops
+            if delivery_chain.len() >= self.core.jmap.mail_max_hops
+                || delivery_chain
+                    .iter()
+                    .any(|addr| addr.eq_ignore_ascii_case(rcpt.as_str()))
+            {
+                let path = delivery_chain
+                    .iter()
+                    .map(|addr| addr.as_str())
+                    .chain([rcpt.as_str()])
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                tracing::info!(
+                    context = "ingest",
+                    event = "loop-detected",
+                    rcpt = rcpt.as_str(),
+                    path = path,
+                    "Mail forwarding loop detected."
+                );
+                *status = DeliveryResult::PermanentFailure {
+                    code: [5, 4, 6],
+                    reason: format!("Mail forwarding loop detected: {path}").into(),
+                };
+                continue;
+            }
+
             // Check if there is an active sieve script
             let result = match self.sieve_script_get_active(*uid).await {
                 Ok(Some(active_script)) => {
@@ -93,7 +122,17 @@ impl JMAP {
                         &message.sender_address,
                         rcpt,
                         *uid,
-                        active_script,
+                        active_script.into(),
+                    )
+                    .await
+                }
+                Ok(None) if self.has_global_scripts(rcpt) => {
+                    self.sieve_script_ingest(
+                        &raw_message,
+                        &message.sender_address,
+                        rcpt,
+                        *uid,
+                        None,
                     )
                     .await
                 }
@@ -216,3 +255,27 @@ impl JMAP {
             .collect()
     }
 }
+
+/// Returns the addresses found in the Delivered-To headers, oldest first.
+fn delivery_chain(message: &[u8]) -> Vec<String> {
+    let mut chain = Vec::new();
+    for line in message.split(|&ch| ch == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            break;
+        } else if let Some(value) = line
+            .get(..13)
+            .filter(|name| name.eq_ignore_ascii_case(b"delivered-to:"))
+            .and_then(|_| std::str::from_utf8(&line[13..]).ok())
+        {
+            let value = value.trim().trim_start_matches('<').trim_end_matches('>');
+            if !value.is_empty() {
+                chain.push(value.to_lowercase());
+            }
+        }
+    }
+
+    // Headers are prepended on each hop
+    chain.reverse();
+    chain
+}
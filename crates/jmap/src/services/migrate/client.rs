@@ -0,0 +1,540 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{fmt::Display, time::Duration};
+
+use imap_proto::{protocol::Flag, utf7::utf7_decode};
+use jmap_proto::types::keyword::Keyword;
+use rustls_pki_types::ServerName;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+use tokio_rustls::{client::TlsStream, TlsConnector};
+
+pub struct ImapClient<T: AsyncRead + AsyncWrite> {
+    stream: T,
+    buf: Vec<u8>,
+    tag: u32,
+    timeout: Duration,
+    max_literal_size: usize,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Timeout,
+    TLSInvalidName,
+    Disconnected,
+    LiteralTooLarge(usize),
+    Rejected(String),
+    Invalid(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Default)]
+struct Response {
+    text: Vec<u8>,
+    literals: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Atom(String),
+    String(Vec<u8>),
+    ListStart,
+    ListEnd,
+}
+
+#[derive(Debug)]
+pub struct Folder {
+    pub name: String,
+    pub path: String,
+    pub role: Option<&'static str>,
+    pub is_selectable: bool,
+}
+
+#[derive(Debug)]
+pub struct FetchedMessage {
+    pub uid: u32,
+    pub keywords: Vec<Keyword>,
+    pub received_at: Option<u64>,
+    pub contents: Vec<u8>,
+}
+
+impl ImapClient<TcpStream> {
+    pub async fn connect(
+        host: &str,
+        port: u16,
+        timeout: Duration,
+        max_literal_size: usize,
+    ) -> Result<Self> {
+        tokio::time::timeout(timeout, async {
+            Ok(ImapClient {
+                stream: TcpStream::connect((host, port)).await?,
+                buf: Vec::with_capacity(1024),
+                tag: 0,
+                timeout,
+                max_literal_size,
+            })
+        })
+        .await
+        .map_err(|_| Error::Timeout)?
+    }
+
+    pub async fn into_tls(
+        self,
+        tls_connector: &TlsConnector,
+        tls_hostname: &str,
+    ) -> Result<ImapClient<TlsStream<TcpStream>>> {
+        tokio::time::timeout(self.timeout, async {
+            Ok(ImapClient {
+                stream: tls_connector
+                    .connect(
+                        ServerName::try_from(tls_hostname)
+                            .map_err(|_| Error::TLSInvalidName)?
+                            .to_owned(),
+                        self.stream,
+                    )
+                    .await?,
+                buf: self.buf,
+                tag: self.tag,
+                timeout: self.timeout,
+                max_literal_size: self.max_literal_size,
+            })
+        })
+        .await
+        .map_err(|_| Error::Timeout)?
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> ImapClient<T> {
+    pub async fn greeting(&mut self) -> Result<()> {
+        let response = self.read_response().await?;
+        if response.text.starts_with(b"* OK") || response.text.starts_with(b"* PREAUTH") {
+            Ok(())
+        } else {
+            Err(Error::Rejected(
+                String::from_utf8_lossy(&response.text).into_owned(),
+            ))
+        }
+    }
+
+    pub async fn login(&mut self, username: &str, secret: &str) -> Result<()> {
+        self.command(&format!("LOGIN {} {}", quote(username)?, quote(secret)?))
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn list(&mut self) -> Result<Vec<Folder>> {
+        let mut folders = Vec::new();
+
+        for response in self.command("LIST \"\" \"*\"").await? {
+            let mut tokens = response.tokenize().into_iter();
+            if !matches!((tokens.next(), tokens.next()), (Some(Token::Atom(star)), Some(Token::Atom(list))) if star == "*" && list.eq_ignore_ascii_case("LIST"))
+            {
+                continue;
+            }
+
+            // Parse attributes
+            let mut role = None;
+            let mut is_selectable = true;
+            if tokens.next() != Some(Token::ListStart) {
+                return Err(Error::Invalid("Expected LIST attributes".to_string()));
+            }
+            for token in tokens.by_ref() {
+                match token {
+                    Token::Atom(attr) => {
+                        let attr = attr.to_ascii_lowercase();
+                        match attr.as_str() {
+                            "\\noselect" | "\\nonexistent" => is_selectable = false,
+                            "\\sent" => role = Some("sent"),
+                            "\\trash" => role = Some("trash"),
+                            "\\junk" => role = Some("junk"),
+                            "\\drafts" => role = Some("drafts"),
+                            "\\archive" => role = Some("archive"),
+                            _ => (),
+                        }
+                    }
+                    Token::ListEnd => break,
+                    _ => (),
+                }
+            }
+
+            // Parse delimiter and name
+            let delimiter = match tokens.next() {
+                Some(Token::String(delimiter)) => delimiter.first().copied(),
+                _ => None,
+            };
+            let name = match tokens.next() {
+                Some(Token::String(name)) => String::from_utf8(name),
+                Some(Token::Atom(name)) => Ok(name),
+                _ => return Err(Error::Invalid("Expected mailbox name".to_string())),
+            }
+            .map_err(|_| Error::Invalid("Invalid mailbox name".to_string()))?;
+            let decoded_name = utf7_decode(name.as_bytes()).unwrap_or_else(|| name.clone());
+            let path = if name.eq_ignore_ascii_case("INBOX") {
+                role = Some("inbox");
+                "INBOX".to_string()
+            } else if let Some(delimiter) = delimiter.filter(|d| *d != b'/') {
+                decoded_name
+                    .replace('/', "_")
+                    .replace(char::from(delimiter), "/")
+            } else {
+                decoded_name
+            };
+
+            folders.push(Folder {
+                name,
+                path,
+                role,
+                is_selectable,
+            });
+        }
+
+        Ok(folders)
+    }
+
+    pub async fn examine(&mut self, folder: &str) -> Result<u32> {
+        let mut uid_validity = None;
+
+        for response in self.command(&format!("EXAMINE {}", quote(folder)?)).await? {
+            let mut tokens = response.tokenize().into_iter();
+            while let Some(token) = tokens.next() {
+                if matches!(&token, Token::Atom(code) if code.eq_ignore_ascii_case("[UIDVALIDITY"))
+                {
+                    if let Some(Token::Atom(value)) = tokens.next() {
+                        uid_validity = value.trim_end_matches(']').parse::<u32>().ok();
+                    }
+                }
+            }
+        }
+
+        uid_validity.ok_or_else(|| Error::Invalid("Missing UIDVALIDITY".to_string()))
+    }
+
+    pub async fn uid_search(&mut self, from_uid: u32) -> Result<Vec<u32>> {
+        let mut uids = Vec::new();
+
+        for response in self
+            .command(&format!("UID SEARCH UID {}:*", from_uid.max(1)))
+            .await?
+        {
+            let mut tokens = response.tokenize().into_iter().skip(1);
+            if matches!(tokens.next(), Some(Token::Atom(search)) if search.eq_ignore_ascii_case("SEARCH"))
+            {
+                for token in tokens {
+                    if let Token::Atom(uid) = token {
+                        if let Ok(uid) = uid.parse::<u32>() {
+                            // A range ending in '*' always includes the highest UID
+                            if uid >= from_uid {
+                                uids.push(uid);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        uids.sort_unstable();
+        Ok(uids)
+    }
+
+    pub async fn uid_fetch(&mut self, uid: u32) -> Result<Option<FetchedMessage>> {
+        for response in self
+            .command(&format!(
+                "UID FETCH {uid} (UID FLAGS INTERNALDATE BODY.PEEK[])"
+            ))
+            .await?
+        {
+            let mut tokens = response.tokenize().into_iter().skip(2);
+            if !matches!(tokens.next(), Some(Token::Atom(fetch)) if fetch.eq_ignore_ascii_case("FETCH"))
+                || tokens.next() != Some(Token::ListStart)
+            {
+                continue;
+            }
+
+            let mut message = FetchedMessage {
+                uid: 0,
+                keywords: Vec::new(),
+                received_at: None,
+                contents: Vec::new(),
+            };
+            while let Some(Token::Atom(item)) = tokens.next() {
+                match item.to_ascii_uppercase().as_str() {
+                    "UID" => {
+                        if let Some(Token::Atom(value)) = tokens.next() {
+                            message.uid = value.parse().unwrap_or_default();
+                        }
+                    }
+                    "FLAGS" => {
+                        if tokens.next() == Some(Token::ListStart) {
+                            for token in tokens.by_ref() {
+                                match token {
+                                    Token::Atom(flag) => {
+                                        if let Ok(flag) = Flag::parse_imap(flag.into_bytes()) {
+                                            if !matches!(flag, Flag::Recent) {
+                                                message.keywords.push(Keyword::from(flag));
+                                            }
+                                        }
+                                    }
+                                    _ => break,
+                                }
+                            }
+                        }
+                    }
+                    "INTERNALDATE" => {
+                        if let Some(Token::String(value)) = tokens.next() {
+                            message.received_at = chrono::DateTime::parse_from_str(
+                                String::from_utf8_lossy(&value).trim(),
+                                "%d-%b-%Y %H:%M:%S %z",
+                            )
+                            .ok()
+                            .map(|dt| dt.timestamp() as u64);
+                        }
+                    }
+                    "BODY[]" => {
+                        if let Some(Token::String(value)) = tokens.next() {
+                            message.contents = value;
+                        }
+                    }
+                    _ => {
+                        // Skip unrequested items
+                        if tokens.next() == Some(Token::ListStart) {
+                            for token in tokens.by_ref() {
+                                if token == Token::ListEnd {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            if message.uid == uid && !message.contents.is_empty() {
+                return Ok(Some(message));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub async fn logout(&mut self) {
+        let _ = self.command("LOGOUT").await;
+    }
+
+    async fn command(&mut self, command: &str) -> Result<Vec<Response>> {
+        self.tag += 1;
+        let tag = format!("M{}", self.tag);
+        tokio::time::timeout(self.timeout, async {
+            self.stream
+                .write_all(format!("{tag} {command}\r\n").as_bytes())
+                .await?;
+            self.stream.flush().await
+        })
+        .await
+        .map_err(|_| Error::Timeout)??;
+
+        let mut responses = Vec::new();
+        loop {
+            let response = self.read_response().await?;
+            if let Some(status) = response
+                .text
+                .strip_prefix(tag.as_bytes())
+                .and_then(|status| status.strip_prefix(b" "))
+            {
+                return if status
+                    .get(0..2)
+                    .map_or(false, |s| s.eq_ignore_ascii_case(b"OK"))
+                {
+                    Ok(responses)
+                } else {
+                    Err(Error::Rejected(
+                        String::from_utf8_lossy(status).into_owned(),
+                    ))
+                };
+            } else if response.text.starts_with(b"*") {
+                responses.push(response);
+            }
+        }
+    }
+
+    async fn read_response(&mut self) -> Result<Response> {
+        let mut response = Response::default();
+
+        loop {
+            let line = self.read_line().await?;
+            let literal_size = line
+                .strip_suffix(b"}")
+                .and_then(|line| {
+                    line.iter()
+                        .rposition(|ch| *ch == b'{')
+                        .map(|pos| &line[pos + 1..])
+                })
+                .and_then(|size| {
+                    std::str::from_utf8(size.strip_suffix(b"+").unwrap_or(size))
+                        .ok()?
+                        .parse::<usize>()
+                        .ok()
+                });
+            response.text.extend_from_slice(&line);
+
+            if let Some(literal_size) = literal_size {
+                if literal_size > self.max_literal_size {
+                    return Err(Error::LiteralTooLarge(literal_size));
+                }
+                let literal = self.read_bytes(literal_size).await?;
+                response.literals.push(literal);
+            } else {
+                return Ok(response);
+            }
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<Vec<u8>> {
+        loop {
+            if let Some(pos) = self.buf.windows(2).position(|w| w == b"\r\n") {
+                let line = self.buf[..pos].to_vec();
+                self.buf.drain(..pos + 2);
+                return Ok(line);
+            } else if self.buf.len() > self.max_literal_size {
+                return Err(Error::LiteralTooLarge(self.buf.len()));
+            }
+            self.fill_buf().await?;
+        }
+    }
+
+    async fn read_bytes(&mut self, size: usize) -> Result<Vec<u8>> {
+        while self.buf.len() < size {
+            self.fill_buf().await?;
+        }
+        Ok(self.buf.drain(..size).collect())
+    }
+
+    async fn fill_buf(&mut self) -> Result<()> {
+        let mut buf = [0u8; 8192];
+        match tokio::time::timeout(self.timeout, self.stream.read(&mut buf)).await {
+            Ok(Ok(0)) => Err(Error::Disconnected),
+            Ok(Ok(bytes_read)) => {
+                self.buf.extend_from_slice(&buf[..bytes_read]);
+                Ok(())
+            }
+            Ok(Err(err)) => Err(Error::Io(err)),
+            Err(_) => Err(Error::Timeout),
+        }
+    }
+}
+
+impl Response {
+    fn tokenize(self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut literals = self.literals.into_iter();
+        let mut iter = self.text.iter().copied().peekable();
+
+        while let Some(ch) = iter.next() {
+            match ch {
+                b' ' => (),
+                b'(' => tokens.push(Token::ListStart),
+                b')' => tokens.push(Token::ListEnd),
+                b'"' => {
+                    let mut value = Vec::new();
+                    while let Some(ch) = iter.next() {
+                        match ch {
+                            b'\\' => {
+                                if let Some(ch) = iter.next() {
+                                    value.push(ch);
+                                }
+                            }
+                            b'"' => break,
+                            _ => value.push(ch),
+                        }
+                    }
+                    tokens.push(Token::String(value));
+                }
+                b'{' => {
+                    for ch in iter.by_ref() {
+                        if ch == b'}' {
+                            break;
+                        }
+                    }
+                    tokens.push(Token::String(literals.next().unwrap_or_default()));
+                }
+                _ => {
+                    let mut value = vec![ch];
+                    while let Some(&ch) = iter.peek() {
+                        if matches!(ch, b' ' | b'(' | b')') {
+                            break;
+                        }
+                        value.push(ch);
+                        iter.next();
+                    }
+                    tokens.push(Token::Atom(String::from_utf8_lossy(&value).into_owned()));
+                }
+            }
+        }
+
+        tokens
+    }
+}
+
+fn quote(value: &str) -> Result<String> {
+    if value.contains(['\r', '\n']) {
+        return Err(Error::Invalid(
+            "Line breaks are not allowed in arguments".to_string(),
+        ));
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        if matches!(ch, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(ch);
+    }
+    quoted.push('"');
+    Ok(quoted)
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "IO error: {}", err),
+            Error::Timeout => write!(f, "Connection timed out"),
+            Error::TLSInvalidName => write!(f, "Invalid TLS name"),
+            Error::Disconnected => write!(f, "Disconnected unexpectedly"),
+            Error::LiteralTooLarge(size) => {
+                write!(f, "IMAP literal of {} bytes is too large", size)
+            }
+            Error::Rejected(response) => write!(f, "Command rejected: {}", response),
+            Error::Invalid(details) => write!(f, "Invalid IMAP response: {}", details),
+        }
+    }
+}
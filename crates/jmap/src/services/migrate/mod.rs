@@ -0,0 +1,453 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use directory::QueryBy;
+use jmap_proto::error::method::MethodError;
+use mail_parser::MessageParser;
+use store::{
+    write::{BatchBuilder, Bincode},
+    Serialize,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{email::ingest::IngestEmail, mailbox::INBOX_ID, IngestError, JMAP};
+
+use self::client::ImapClient;
+
+pub mod client;
+
+const MIGRATION_TIMEOUT: Duration = Duration::from_secs(120);
+const MIGRATION_CHECKPOINT: u64 = 100;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationRequest {
+    pub host: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default = "default_tls")]
+    pub tls: bool,
+    #[serde(default)]
+    pub allow_invalid_certs: bool,
+    pub username: String,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    pub host: String,
+    pub username: String,
+    pub state: MigrationState,
+    pub error: Option<String>,
+    pub folders: Vec<FolderStatus>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderStatus {
+    pub name: String,
+    pub uid_validity: u32,
+    pub last_uid: u32,
+    pub total: u64,
+    pub imported: u64,
+    pub skipped: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationState {
+    #[default]
+    Running,
+    Completed,
+    Failed,
+    Canceled,
+    Interrupted,
+}
+
+#[derive(Debug, Default)]
+pub struct MigrationJob {
+    status: Mutex<MigrationStatus>,
+    cancel: AtomicBool,
+}
+
+impl JMAP {
+    pub async fn migration_status(
+        &self,
+        account_id: u32,
+    ) -> Result<Option<MigrationStatus>, MethodError> {
+        if let Some(job) = self.inner.migrations.get(&account_id) {
+            return Ok(Some(job.status()));
+        }
+
+        // Jobs that were running when the server stopped are reported as interrupted
+        self.migration_load(account_id).await.map(|status| {
+            status.map(|mut status| {
+                if status.state == MigrationState::Running {
+                    status.state = MigrationState::Interrupted;
+                }
+                status
+            })
+        })
+    }
+
+    pub async fn migration_start(
+        &self,
+        account_id: u32,
+        request: MigrationRequest,
+    ) -> Result<bool, MethodError> {
+        if self.inner.migrations.contains_key(&account_id) {
+            return Ok(false);
+        }
+
+        // Resume from the last checkpoint when migrating from the same remote account
+        let mut status = self
+            .migration_load(account_id)
+            .await?
+            .filter(|status| {
+                status.host.eq_ignore_ascii_case(&request.host)
+                    && status.username == request.username
+            })
+            .unwrap_or_else(|| MigrationStatus {
+                host: request.host.clone(),
+                username: request.username.clone(),
+                ..Default::default()
+            });
+        status.state = MigrationState::Running;
+        status.error = None;
+
+        let job = Arc::new(MigrationJob {
+            status: Mutex::new(status),
+            cancel: AtomicBool::new(false),
+        });
+        match self.inner.migrations.entry(account_id) {
+            dashmap::mapref::entry::Entry::Occupied(_) => return Ok(false),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(job.clone());
+            }
+        }
+
+        let jmap = self.clone();
+        tokio::spawn(async move {
+            let result = jmap.migration_run(account_id, &request, &job).await;
+            job.update(|status| match result {
+                Ok(true) => {
+                    status.state = MigrationState::Completed;
+                }
+                Ok(false) => {
+                    status.state = MigrationState::Canceled;
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        context = "migrate",
+                        event = "error",
+                        account_id = account_id,
+                        host = request.host,
+                        reason = %err,
+                        "Account migration failed."
+                    );
+                    status.state = MigrationState::Failed;
+                    status.error = err.into();
+                }
+            });
+            let _ = jmap.migration_save(account_id, &job).await;
+            jmap.inner.migrations.remove(&account_id);
+        });
+
+        Ok(true)
+    }
+
+    pub fn migration_cancel(&self, account_id: u32) -> bool {
+        if let Some(job) = self.inner.migrations.get(&account_id) {
+            job.cancel.store(true, Ordering::Relaxed);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub async fn migration_delete(&self, account_id: u32) -> Result<(), MethodError> {
+        self.core
+            .storage
+            .lookup
+            .key_delete(format!("migrate:{account_id}").into_bytes())
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    event = "error",
+                    context = "migrate",
+                    error = ?err,
+                    "Failed to delete migration status."
+                );
+                MethodError::ServerPartialFail
+            })
+    }
+
+    async fn migration_run(
+        &self,
+        account_id: u32,
+        request: &MigrationRequest,
+        job: &MigrationJob,
+    ) -> Result<bool, String> {
+        let client = ImapClient::connect(
+            &request.host,
+            request.port.unwrap_or(if request.tls { 993 } else { 143 }),
+            MIGRATION_TIMEOUT,
+            self.core.jmap.mail_max_size,
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+
+        if request.tls {
+            let tls_connector = if !request.allow_invalid_certs {
+                &self.smtp.inner.connectors.pki_verify
+            } else {
+                &self.smtp.inner.connectors.dummy_verify
+            };
+            let client = client
+                .into_tls(tls_connector, &request.host)
+                .await
+                .map_err(|err| err.to_string())?;
+            self.migration_session(account_id, request, job, client)
+                .await
+        } else {
+            self.migration_session(account_id, request, job, client)
+                .await
+        }
+    }
+
+    async fn migration_session<T: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        account_id: u32,
+        request: &MigrationRequest,
+        job: &MigrationJob,
+        mut client: ImapClient<T>,
+    ) -> Result<bool, String> {
+        client.greeting().await.map_err(|err| err.to_string())?;
+        client
+            .login(&request.username, &request.secret)
+            .await
+            .map_err(|err| err.to_string())?;
+        let folders = client.list().await.map_err(|err| err.to_string())?;
+
+        // Obtain account quota and make sure the default mailboxes exist
+        let account_quota = match self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+        {
+            Ok(Some(principal)) => principal.quota as i64,
+            Ok(None) => return Err("Account not found".to_string()),
+            Err(_) => return Err("Failed to obtain account quota".to_string()),
+        };
+        self.mailbox_get_or_create(account_id)
+            .await
+            .map_err(|_| "Failed to create default mailboxes".to_string())?;
+
+        for folder in folders.into_iter().filter(|f| f.is_selectable) {
+            // Obtain the local mailbox
+            let mailbox_id = if folder.role == Some("inbox") {
+                Some(INBOX_ID)
+            } else if let Some(role) = folder.role {
+                self.mailbox_get_by_role(account_id, role)
+                    .await
+                    .map_err(|_| "Failed to obtain mailbox".to_string())?
+            } else {
+                None
+            };
+            let mailbox_id = if let Some(mailbox_id) = mailbox_id {
+                mailbox_id
+            } else {
+                self.mailbox_create_path(account_id, &folder.path)
+                    .await
+                    .map_err(|_| "Failed to create mailbox".to_string())?
+                    .ok_or_else(|| format!("Invalid mailbox name {:?}", folder.path))?
+                    .0
+            };
+
+            // Resume from the last imported UID unless the folder's UIDVALIDITY changed
+            let uid_validity = client
+                .examine(&folder.name)
+                .await
+                .map_err(|err| err.to_string())?;
+            let last_uid = job.update(|status| {
+                let pos =
+                    if let Some(pos) = status.folders.iter().position(|f| f.name == folder.name) {
+                        pos
+                    } else {
+                        status.folders.push(FolderStatus {
+                            name: folder.name.clone(),
+                            ..Default::default()
+                        });
+                        status.folders.len() - 1
+                    };
+                let folder_status = &mut status.folders[pos];
+                if folder_status.uid_validity != uid_validity {
+                    *folder_status = FolderStatus {
+                        name: folder.name.clone(),
+                        uid_validity,
+                        ..Default::default()
+                    };
+                }
+                folder_status.last_uid
+            });
+            let uids = client
+                .uid_search(last_uid + 1)
+                .await
+                .map_err(|err| err.to_string())?;
+            job.update_folder(&folder.name, |folder| {
+                folder.total = folder.imported + folder.skipped + uids.len() as u64;
+            });
+
+            for (pos, uid) in uids.into_iter().enumerate() {
+                if job.cancel.load(Ordering::Relaxed) {
+                    client.logout().await;
+                    return Ok(false);
+                }
+
+                let mut imported = false;
+                if let Some(message) = client.uid_fetch(uid).await.map_err(|err| err.to_string())? {
+                    match self
+                        .email_ingest(IngestEmail {
+                            raw_message: &message.contents,
+                            message: MessageParser::new().parse(&message.contents),
+                            account_id,
+                            account_quota,
+                            mailbox_ids: vec![mailbox_id],
+                            keywords: message.keywords,
+                            received_at: message.received_at,
+                            skip_duplicates: true,
+                            encrypt: self.core.jmap.encrypt && self.core.jmap.encrypt_append,
+                        })
+                        .await
+                    {
+                        Ok(_) => {
+                            imported = true;
+                        }
+                        Err(IngestError::OverQuota) => {
+                            return Err("Account is over quota".to_string());
+                        }
+                        Err(IngestError::Temporary) => {
+                            return Err("Temporary failure while importing message".to_string());
+                        }
+                        Err(IngestError::Permanent { reason, .. }) => {
+                            tracing::debug!(
+                                context = "migrate",
+                                event = "skip",
+                                account_id = account_id,
+                                folder = folder.path,
+                                uid = uid,
+                                reason = reason,
+                                "Skipping message."
+                            );
+                        }
+                    }
+                }
+
+                job.update_folder(&folder.name, |folder| {
+                    folder.last_uid = uid;
+                    if imported {
+                        folder.imported += 1;
+                    } else {
+                        folder.skipped += 1;
+                    }
+                });
+                if (pos as u64 + 1) % MIGRATION_CHECKPOINT == 0 {
+                    self.migration_save(account_id, job).await?;
+                }
+            }
+
+            self.migration_save(account_id, job).await?;
+        }
+
+        client.logout().await;
+
+        Ok(true)
+    }
+
+    async fn migration_load(
+        &self,
+        account_id: u32,
+    ) -> Result<Option<MigrationStatus>, MethodError> {
+        self.core
+            .storage
+            .lookup
+            .key_get::<Bincode<MigrationStatus>>(format!("migrate:{account_id}").into_bytes())
+            .await
+            .map(|status| status.map(|status| status.inner))
+            .map_err(|err| {
+                tracing::error!(
+                    event = "error",
+                    context = "migrate",
+                    error = ?err,
+                    "Failed to obtain migration status."
+                );
+                MethodError::ServerPartialFail
+            })
+    }
+
+    async fn migration_save(&self, account_id: u32, job: &MigrationJob) -> Result<(), String> {
+        self.core
+            .storage
+            .lookup
+            .key_set(
+                format!("migrate:{account_id}").into_bytes(),
+                Bincode::new(job.status()).serialize(),
+                None,
+            )
+            .await
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl MigrationJob {
+    pub fn status(&self) -> MigrationStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut MigrationStatus) -> T) -> T {
+        f(&mut self.status.lock().unwrap())
+    }
+
+    fn update_folder(&self, name: &str, f: impl FnOnce(&mut FolderStatus)) {
+        self.update(|status| {
+            if let Some(folder) = status.folders.iter_mut().find(|f| f.name == name) {
+                f(folder);
+            }
+        })
+    }
+}
+
+fn default_tls() -> bool {
+    true
+}
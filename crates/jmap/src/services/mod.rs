@@ -22,10 +22,14 @@
 */
 
 pub mod delivery;
+pub mod event_bus;
 pub mod gossip;
+pub mod history;
 pub mod housekeeper;
 pub mod index;
 pub mod ingest;
+pub mod migrate;
+pub mod repair;
 pub mod state;
 
 pub const IPC_CHANNEL_BUFFER: usize = 1024;
@@ -0,0 +1,308 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    object::Object,
+    types::{collection::Collection, property::Property, value::Value},
+};
+use store::{
+    ahash::AHashMap,
+    write::{key::DeserializeBigEndian, BatchBuilder, BlobOp, DirectoryClass, ValueClass},
+    IndexKey, IterateParams, Serialize, ValueKey, U32_LEN,
+};
+use utils::{BlobHash, BLOB_HASH_LEN};
+
+use crate::{sieve::set::ObjectBlobId, JMAP};
+
+#[derive(Debug, Default, serde::Serialize)]
+pub struct AccountRepair {
+    #[serde(rename = "accountId")]
+    pub account_id: u32,
+    #[serde(rename = "quotaStored")]
+    pub quota_stored: i64,
+    #[serde(rename = "quotaComputed")]
+    pub quota_computed: i64,
+    pub documents: AHashMap<String, u64>,
+    #[serde(rename = "orphanedBlobs")]
+    pub orphaned_blobs: u64,
+}
+
+struct BlobLink {
+    account_id: u32,
+    collection: u8,
+    document_id: u32,
+    hash: BlobHash,
+}
+
+impl JMAP {
+    // Recomputes the storage usage of one or all accounts and repairs any drift
+    pub async fn repair_accounts(
+        &self,
+        account_id: Option<u32>,
+    ) -> Result<Vec<AccountRepair>, MethodError> {
+        let account_ids = if let Some(account_id) = account_id {
+            vec![account_id]
+        } else {
+            self.get_document_ids(u32::MAX, Collection::Principal)
+                .await?
+                .map(|ids| ids.into_iter().collect())
+                .unwrap_or_default()
+        };
+
+        let mut links = self.blob_links(account_id).await?;
+        let mut results = Vec::with_capacity(account_ids.len());
+        for account_id in account_ids {
+            results.push(
+                self.repair_account(account_id, links.remove(&account_id).unwrap_or_default())
+                    .await?,
+            );
+        }
+
+        Ok(results)
+    }
+
+    async fn repair_account(
+        &self,
+        account_id: u32,
+        links: Vec<BlobLink>,
+    ) -> Result<AccountRepair, MethodError> {
+        let mut result = AccountRepair {
+            account_id,
+            quota_stored: self.get_used_quota(account_id).await?,
+            ..Default::default()
+        };
+
+        // Count documents
+        let mut document_ids = AHashMap::new();
+        for collection in [
+            Collection::Email,
+            Collection::Mailbox,
+            Collection::Thread,
+            Collection::Identity,
+            Collection::EmailSubmission,
+            Collection::SieveScript,
+            Collection::PushSubscription,
+            Collection::SavedSearch,
+            Collection::Calendar,
+            Collection::CalendarEvent,
+            Collection::AddressBook,
+            Collection::ContactCard,
+        ] {
+            let ids = self
+                .get_document_ids(account_id, collection)
+                .await?
+                .unwrap_or_default();
+            result.documents.insert(collection.to_string(), ids.len());
+            document_ids.insert(u8::from(collection), ids);
+        }
+
+        // Add message sizes, including messages pending deletion
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    IndexKey {
+                        account_id,
+                        collection: Collection::Email.into(),
+                        document_id: 0,
+                        field: Property::Size.into(),
+                        key: 0u32.serialize(),
+                    },
+                    IndexKey {
+                        account_id,
+                        collection: Collection::Email.into(),
+                        document_id: u32::MAX,
+                        field: Property::Size.into(),
+                        key: u32::MAX.serialize(),
+                    },
+                )
+                .no_values(),
+                |key, _| {
+                    result.quota_computed +=
+                        key.deserialize_be_u32(key.len() - (U32_LEN * 2))? as i64;
+                    Ok(true)
+                },
+            )
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    event = "error",
+                    context = "repair_account",
+                    account_id = account_id,
+                    error = ?err,
+                    "Failed to iterate message sizes."
+                );
+                MethodError::ServerPartialFail
+            })?;
+
+        // Add sieve script, calendar object and vCard sizes
+        for collection in [
+            Collection::SieveScript,
+            Collection::CalendarEvent,
+            Collection::ContactCard,
+        ] {
+            for document_id in document_ids
+                .get(&u8::from(collection))
+                .into_iter()
+                .flatten()
+            {
+                if let Some(size) = self
+                    .get_property::<Object<Value>>(
+                        account_id,
+                        collection,
+                        document_id,
+                        Property::Value,
+                    )
+                    .await?
+                    .and_then(|obj| {
+                        obj.blob_id()
+                            .and_then(|b| b.section.as_ref())
+                            .map(|s| s.size)
+                    })
+                {
+                    result.quota_computed += size as i64;
+                }
+            }
+        }
+
+        // Remove links to documents that no longer exist
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(account_id);
+        for link in links {
+            if document_ids
+                .get(&link.collection)
+                .map_or(true, |ids| !ids.contains(link.document_id))
+            {
+                tracing::debug!(
+                    context = "repair_account",
+                    event = "orphaned-blob",
+                    account_id = link.account_id,
+                    collection = link.collection,
+                    document_id = link.document_id,
+                    "Removing orphaned blob link."
+                );
+                batch
+                    .with_collection(link.collection)
+                    .update_document(link.document_id)
+                    .clear(BlobOp::Link { hash: link.hash });
+                result.orphaned_blobs += 1;
+            }
+        }
+
+        if result.quota_computed != result.quota_stored {
+            tracing::info!(
+                context = "repair_account",
+                event = "quota-drift",
+                account_id = account_id,
+                stored = result.quota_stored,
+                computed = result.quota_computed,
+                "Correcting used quota."
+            );
+            batch.add(
+                DirectoryClass::UsedQuota(account_id),
+                result.quota_computed - result.quota_stored,
+            );
+        }
+
+        if !batch.is_empty() {
+            self.write_batch(batch).await?;
+        }
+
+        Ok(result)
+    }
+
+    // Only emails, sieve scripts, calendar objects and vCards hold blob links
+    // bound to a document
+    async fn blob_links(
+        &self,
+        account_id: Option<u32>,
+    ) -> Result<AHashMap<u32, Vec<BlobLink>>, MethodError> {
+        let collections = [
+            u8::from(Collection::Email),
+            u8::from(Collection::SieveScript),
+            u8::from(Collection::CalendarEvent),
+            u8::from(Collection::ContactCard),
+        ];
+        let mut links: AHashMap<u32, Vec<BlobLink>> = AHashMap::new();
+
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey {
+                        account_id: 0,
+                        collection: 0,
+                        document_id: 0,
+                        class: ValueClass::Blob(BlobOp::Link {
+                            hash: BlobHash::default(),
+                        }),
+                    },
+                    ValueKey {
+                        account_id: u32::MAX,
+                        collection: u8::MAX,
+                        document_id: u32::MAX,
+                        class: ValueClass::Blob(BlobOp::Link {
+                            hash: BlobHash::new_max(),
+                        }),
+                    },
+                )
+                .no_values(),
+                |key, _| {
+                    let link_account_id = key.deserialize_be_u32(BLOB_HASH_LEN)?;
+                    let collection = *key.get(BLOB_HASH_LEN + U32_LEN).ok_or_else(|| {
+                        store::Error::InternalError("Invalid blob link key".to_string())
+                    })?;
+                    let document_id = key.deserialize_be_u32(BLOB_HASH_LEN + U32_LEN + 1)?;
+
+                    if collections.contains(&collection)
+                        && account_id
+                            .map_or(link_account_id != u32::MAX, |id| id == link_account_id)
+                    {
+                        links.entry(link_account_id).or_default().push(BlobLink {
+                            account_id: link_account_id,
+                            collection,
+                            document_id,
+                            hash: BlobHash::try_from_hash_slice(&key[..BLOB_HASH_LEN]).unwrap(),
+                        });
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    event = "error",
+                    context = "repair_account",
+                    error = ?err,
+                    "Failed to iterate blob links."
+                );
+                MethodError::ServerPartialFail
+            })?;
+
+        Ok(links)
+    }
+}
@@ -33,7 +33,7 @@ use crate::{
     JmapInstance, JMAP,
 };
 
-use super::IPC_CHANNEL_BUFFER;
+use super::{event_bus::publish_state_change, IPC_CHANNEL_BUFFER};
 
 #[derive(Debug)]
 pub enum Event {
@@ -44,6 +44,7 @@ pub enum Event {
     },
     Publish {
         state_change: StateChange,
+        is_remote: bool,
     },
     UpdateSharedAccounts {
         account_id: u32,
@@ -190,7 +191,10 @@ pub fn spawn_state_manager(core: JmapInstance, mut change_rx: mpsc::Receiver<Eve
                             },
                         );
                 }
-                Event::Publish { state_change } => {
+                Event::Publish {
+                    state_change,
+                    is_remote,
+                } => {
                     if let Some(shared_accounts) = shared_accounts_map.get(&state_change.account_id)
                     {
                         let current_time = SystemTime::now()
@@ -238,10 +242,13 @@ pub fn spawn_state_manager(core: JmapInstance, mut change_rx: mpsc::Receiver<Eve
                                             SubscriberType::Push { expires }
                                                 if expires > &current_time =>
                                             {
-                                                push_ids.push(Id::from_parts(
-                                                    *owner_account_id,
-                                                    (*subscriber_id).into(),
-                                                ));
+                                                // Push notifications are sent by the node where the change originated
+                                                if !is_remote {
+                                                    push_ids.push(Id::from_parts(
+                                                        *owner_account_id,
+                                                        (*subscriber_id).into(),
+                                                    ));
+                                                }
                                             }
                                             _ => {
                                                 purge_needed = true;
@@ -413,11 +420,19 @@ impl JMAP {
     }
 
     pub async fn broadcast_state_change(&self, state_change: StateChange) -> bool {
+        // Notify subscribers on other nodes
+        if !self.core.storage.pubsub.is_none() {
+            publish_state_change(&self.inner.event_bus_tx, &state_change);
+        }
+
         match self
             .inner
             .state_tx
             .clone()
-            .send(Event::Publish { state_change })
+            .send(Event::Publish {
+                state_change,
+                is_remote: false,
+            })
             .await
         {
             Ok(_) => true,
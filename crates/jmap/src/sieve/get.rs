@@ -46,8 +46,12 @@ impl JMAP {
         mut request: GetRequest<RequestArguments>,
     ) -> Result<GetResponse, MethodError> {
         let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
-        let properties =
-            request.unwrap_properties(&[Property::Id, Property::Name, Property::BlobId]);
+        let properties = request.unwrap_properties(&[
+            Property::Id,
+            Property::Name,
+            Property::BlobId,
+            Property::IsActive,
+        ]);
         let account_id = request.account_id.document_id();
         let push_ids = self
             .get_document_ids(account_id, Collection::SieveScript)
@@ -134,42 +138,85 @@ impl JMAP {
         account_id: u32,
     ) -> Result<Option<ActiveScript>, MethodError> {
         // Find the currently active script
-        if let Some(document_id) = self
+        let mut active_ids = self
             .filter(
                 account_id,
                 Collection::SieveScript,
                 vec![Filter::eq(Property::IsActive, 1u32)],
             )
             .await?
-            .results
-            .min()
-        {
-            let (script, mut script_object) =
-                self.sieve_script_compile(account_id, document_id).await?;
-            Ok(Some(ActiveScript {
-                document_id,
-                script: Arc::new(script),
-                script_name: script_object
-                    .properties
-                    .remove(&Property::Name)
-                    .and_then(|name| name.try_unwrap_string())
-                    .unwrap_or_else(|| account_id.to_string()),
-                seen_ids: self
-                    .get_property::<Bincode<SeenIds>>(
-                        account_id,
-                        Collection::SieveScript,
-                        document_id,
-                        Property::EmailIds,
-                    )
-                    .await?
-                    .map(|seen_ids| seen_ids.inner)
-                    .unwrap_or_default(),
-            }))
+            .results;
+
+        // User scripts take precedence, the vacation response is evaluated separately
+        if active_ids.len() > 1 {
+            if let Some(vacation_id) = self.get_vacation_sieve_script_id(account_id).await? {
+                active_ids.remove(vacation_id);
+            }
+        }
+
+        if let Some(document_id) = active_ids.min() {
+            self.sieve_script_load(account_id, document_id)
+                .await
+                .map(Some)
         } else {
             Ok(None)
         }
     }
 
+    pub async fn sieve_vacation_get_active(
+        &self,
+        account_id: u32,
+    ) -> Result<Option<ActiveScript>, MethodError> {
+        // Find the vacation response script, if enabled
+        if let Some(document_id) = self.get_vacation_sieve_script_id(account_id).await? {
+            if self
+                .filter(
+                    account_id,
+                    Collection::SieveScript,
+                    vec![Filter::eq(Property::IsActive, 1u32)],
+                )
+                .await?
+                .results
+                .contains(document_id)
+            {
+                return self
+                    .sieve_script_load(account_id, document_id)
+                    .await
+                    .map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn sieve_script_load(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> Result<ActiveScript, MethodError> {
+        let (script, mut script_object) =
+            self.sieve_script_compile(account_id, document_id).await?;
+        Ok(ActiveScript {
+            document_id,
+            script: Arc::new(script),
+            script_name: script_object
+                .properties
+                .remove(&Property::Name)
+                .and_then(|name| name.try_unwrap_string())
+                .unwrap_or_else(|| account_id.to_string()),
+            seen_ids: self
+                .get_property::<Bincode<SeenIds>>(
+                    account_id,
+                    Collection::SieveScript,
+                    document_id,
+                    Property::EmailIds,
+                )
+                .await?
+                .map(|seen_ids| seen_ids.inner)
+                .unwrap_or_default(),
+        })
+    }
+
     pub async fn sieve_script_get_by_name(
         &self,
         account_id: u32,
@@ -0,0 +1,185 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use jmap_proto::{
+    error::method::MethodError,
+    types::{
+        blob::{BlobId, BlobSection},
+        collection::Collection,
+        property::Property,
+    },
+};
+use store::write::{now, BatchBuilder, Bincode, BlobOp, F_CLEAR, F_VALUE};
+use utils::BlobHash;
+
+use crate::JMAP;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SieveVersion {
+    pub hash: BlobHash,
+    pub size: usize,
+    pub replaced_at: u64,
+}
+
+impl JMAP {
+    pub async fn sieve_script_versions(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> Result<Vec<SieveVersion>, MethodError> {
+        self.get_property::<Bincode<Vec<SieveVersion>>>(
+            account_id,
+            Collection::SieveScript,
+            document_id,
+            Property::Versions,
+        )
+        .await
+        .map(|versions| versions.map(|versions| versions.inner).unwrap_or_default())
+    }
+
+    // Replaces the blob of a script, keeping the previous one linked as an older version.
+    // The batch is expected to point to the script document being updated.
+    pub async fn sieve_archive_version(
+        &self,
+        batch: &mut BatchBuilder,
+        account_id: u32,
+        document_id: u32,
+        prev_blob_id: &BlobId,
+        blob_hash: &BlobHash,
+    ) -> Result<(), MethodError> {
+        let max_versions = self.core.jmap.sieve_max_versions;
+        let prev_versions = self.sieve_script_versions(account_id, document_id).await?;
+
+        // Add the replaced script to the top of the history
+        let mut versions = Vec::with_capacity(max_versions);
+        if max_versions > 0 && &prev_blob_id.hash != blob_hash {
+            versions.push(SieveVersion {
+                hash: prev_blob_id.hash.clone(),
+                size: prev_blob_id
+                    .section
+                    .as_ref()
+                    .map_or(0, |section| section.size),
+                replaced_at: now(),
+            });
+        }
+        for version in &prev_versions {
+            if versions.len() == max_versions {
+                break;
+            } else if &version.hash != blob_hash && !versions.iter().any(|v| v.hash == version.hash)
+            {
+                versions.push(version.clone());
+            }
+        }
+
+        // Unlink blobs that are no longer referenced
+        for hash in prev_versions
+            .iter()
+            .map(|version| &version.hash)
+            .chain([&prev_blob_id.hash])
+        {
+            if hash != blob_hash && !versions.iter().any(|v| &v.hash == hash) {
+                batch.clear(BlobOp::Link { hash: hash.clone() });
+            }
+        }
+        batch.set(
+            BlobOp::Link {
+                hash: blob_hash.clone(),
+            },
+            Vec::new(),
+        );
+
+        if !versions.is_empty() {
+            batch.value(Property::Versions, Bincode::new(versions), F_VALUE);
+        } else if !prev_versions.is_empty() {
+            batch.value(Property::Versions, (), F_VALUE | F_CLEAR);
+        }
+
+        Ok(())
+    }
+
+    // Unlinks all older versions of a script that is about to be deleted.
+    pub async fn sieve_purge_versions(
+        &self,
+        batch: &mut BatchBuilder,
+        account_id: u32,
+        document_id: u32,
+    ) -> Result<(), MethodError> {
+        let versions = self.sieve_script_versions(account_id, document_id).await?;
+        if !versions.is_empty() {
+            for version in versions {
+                batch.clear(BlobOp::Link { hash: version.hash });
+            }
+            batch.value(Property::Versions, (), F_VALUE | F_CLEAR);
+        }
+
+        Ok(())
+    }
+
+    pub async fn sieve_restore_version(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        version: usize,
+    ) -> Result<Result<(), String>, MethodError> {
+        let version = if let Some(version) = self
+            .sieve_script_versions(account_id, document_id)
+            .await?
+            .into_iter()
+            .nth(version)
+        {
+            version
+        } else {
+            return Ok(Err("Version not found.".to_string()));
+        };
+        let mut script = self
+            .get_blob_section(
+                &version.hash,
+                &BlobSection {
+                    size: version.size,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .ok_or(MethodError::NotFound)?;
+
+        // Recompile the script, the compiler might have changed since it was stored
+        match self.core.sieve.untrusted_compiler.compile(&script) {
+            Ok(compiled_script) => {
+                script.extend(bincode::serialize(&compiled_script).unwrap_or_default());
+            }
+            Err(err) => {
+                return Ok(Err(err.to_string()));
+            }
+        }
+
+        self.sieve_script_put(
+            account_id,
+            Some(document_id),
+            String::new(),
+            script,
+            version.size,
+        )
+        .await
+        .map(|_| Ok(()))
+    }
+}
@@ -21,13 +21,14 @@
  * for more details.
 */
 
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 
-use common::listener::stream::NullIo;
+use common::{config::scripts::ADDRESS_BOOK_LIST, listener::stream::NullIo};
 use directory::QueryBy;
 use jmap_proto::types::{collection::Collection, id::Id, keyword::Keyword, property::Property};
 use mail_parser::MessageParser;
-use sieve::{Envelope, Event, Input, Mailbox, Recipient};
+use reqwest::header::CONTENT_TYPE;
+use sieve::{Envelope, Event, Input, Mailbox, MatchAs, Recipient, Script, Sieve};
 use smtp::core::{Session, SessionAddress};
 use store::{
     ahash::AHashSet,
@@ -57,7 +58,7 @@ impl JMAP {
         envelope_from: &str,
         envelope_to: &str,
         account_id: u32,
-        mut active_script: ActiveScript,
+        active_script: Option<ActiveScript>,
     ) -> Result<IngestedEmail, IngestError> {
         // Parse message
         let message = if let Some(message) = MessageParser::new().parse(raw_message) {
@@ -79,7 +80,7 @@ impl JMAP {
         let mut instance = self.core.sieve.untrusted_runtime.filter_parsed(message);
 
         // Set account name and obtain quota
-        let (account_quota, mail_from) = match self
+        let (account_quota, mail_from, user_name) = match self
             .core
             .storage
             .directory
@@ -87,10 +88,11 @@ impl JMAP {
             .await
         {
             Ok(Some(p)) => {
-                instance.set_user_full_name(p.description().unwrap_or_else(|| p.name()));
-                (p.quota as i64, p.emails.into_iter().next())
+                let user_name = p.description().unwrap_or_else(|| p.name()).to_string();
+                instance.set_user_full_name(&user_name);
+                (p.quota as i64, p.emails.into_iter().next(), Some(user_name))
             }
-            Ok(None) => (0, None),
+            Ok(None) => (0, None, None),
             Err(_) => {
                 return Err(IngestError::Temporary);
             }
@@ -104,7 +106,45 @@ impl JMAP {
         instance.set_envelope(Envelope::From, envelope_from);
         instance.set_envelope(Envelope::To, envelope_to);
 
-        let mut input = Input::script(active_script.script_name, active_script.script.clone());
+        // The vacation response is the canonical source for auto-replies,
+        // run it alongside the active user script when enabled
+        let mut has_vacation = false;
+        if active_script.as_ref().map_or(true, |active_script| {
+            active_script.script_name != "vacation"
+        }) {
+            match self.sieve_vacation_get_active(account_id).await {
+                Ok(Some(vacation_script)) => {
+                    has_vacation = true;
+                    self.sieve_vacation_reply(
+                        raw_message,
+                        envelope_from,
+                        envelope_to,
+                        account_id,
+                        &mail_from,
+                        user_name.as_deref(),
+                        vacation_script,
+                    )
+                    .await;
+                }
+                Ok(None) => (),
+                Err(_) => {
+                    return Err(IngestError::Temporary);
+                }
+            }
+        }
+
+        // Surround the active script with the admin scripts configured for the recipient
+        let mut input = match (
+            self.sieve_global_wrapper(envelope_to, active_script.as_ref()),
+            &active_script,
+        ) {
+            (Some(wrapper), _) => Input::script("__global", wrapper),
+            (None, Some(active_script)) => Input::script(
+                active_script.script_name.clone(),
+                active_script.script.clone(),
+            ),
+            (None, None) => return Err(IngestError::Temporary),
+        };
 
         let mut do_discard = false;
         let mut do_deliver = false;
@@ -129,7 +169,23 @@ impl JMAP {
             match event {
                 Ok(event) => match event {
                     Event::IncludeScript { name, .. } => {
-                        if let Ok(Some(script)) =
+                        let script = match &name {
+                            Script::Global(global_name) => self
+                                .core
+                                .sieve
+                                .untrusted_global
+                                .iter()
+                                .find(|script| &script.id == global_name)
+                                .map(|script| script.script.clone()),
+                            Script::Personal(personal_name) => active_script
+                                .as_ref()
+                                .filter(|active_script| &active_script.script_name == personal_name)
+                                .map(|active_script| active_script.script.clone()),
+                        };
+
+                        if let Some(script) = script {
+                            input = Input::script(name, script);
+                        } else if let Ok(Some(script)) =
                             self.sieve_script_get_by_name(account_id, &name).await
                         {
                             input = Input::script(name, script);
@@ -214,7 +270,9 @@ impl JMAP {
                     }
                     Event::DuplicateId { id, expiry, last } => {
                         let id_hash = SeenIdHash::new(&id, expiry + now);
-                        let seen_id = active_script.seen_ids.ids.contains(&id_hash);
+                        let seen_id = active_script.as_ref().map_or(false, |active_script| {
+                            active_script.seen_ids.ids.contains(&id_hash)
+                        });
                         if !seen_id || last {
                             new_ids.insert(id_hash);
                         }
@@ -333,7 +391,21 @@ impl JMAP {
                     } => {
                         input = true.into();
                         if let Some(message) = messages.get(message_id) {
-                            if message.raw_message.len() <= self.core.jmap.mail_max_size {
+                            if has_vacation
+                                && is_auto_submitted(&message.raw_message, "auto-replied")
+                            {
+                                // Auto-replies are sent by the vacation response
+                                tracing::debug!(
+                                    context = "sieve_script_ingest",
+                                    event = "skip_auto_reply",
+                                    account_id = account_id,
+                                    "Vacation response is enabled, skipping auto-reply."
+                                );
+                            } else if is_auto_submitted(&message.raw_message, "auto-notified")
+                                && !self.sieve_notify_allowed(account_id).await
+                            {
+                                input = false.into();
+                            } else if message.raw_message.len() <= self.core.jmap.mail_max_size {
                                 let result = Session::<NullIo>::sieve(
                                     self.smtp.clone(),
                                     SessionAddress::new(mail_from.clone()),
@@ -347,7 +419,7 @@ impl JMAP {
                                             continue;
                                         }
                                     },
-                                    message.raw_message.to_vec(),
+                                    with_delivered_to(&message.raw_message, envelope_to),
                                 )
                                 .queue_message()
                                 .await;
@@ -376,10 +448,95 @@ impl JMAP {
                             continue;
                         }
                     }
-                    Event::ListContains { .. }
-                    | Event::Function { .. }
-                    | Event::Notify { .. }
-                    | Event::SetEnvelope { .. } => {
+                    Event::ListContains {
+                        lists,
+                        values,
+                        match_as,
+                    } => {
+                        input = false.into();
+                        'outer: for list in lists {
+                            if list == ADDRESS_BOOK_LIST {
+                                for value in &values {
+                                    if let Ok(true) = self
+                                        .core
+                                        .storage
+                                        .directory
+                                        .rcpt(&value.to_lowercase())
+                                        .await
+                                    {
+                                        input = true.into();
+                                        break 'outer;
+                                    }
+                                }
+                            } else if let Some(store) = self
+                                .core
+                                .sieve
+                                .untrusted_lists
+                                .contains(&list)
+                                .then(|| self.core.storage.lookups.get(&list))
+                                .flatten()
+                            {
+                                for value in &values {
+                                    if let Ok(true) = store
+                                        .key_exists(
+                                            if !matches!(match_as, MatchAs::Lowercase) {
+                                                value.clone()
+                                            } else {
+                                                value.to_lowercase()
+                                            }
+                                            .into_bytes(),
+                                        )
+                                        .await
+                                    {
+                                        input = true.into();
+                                        break 'outer;
+                                    }
+                                }
+                            } else {
+                                tracing::debug!(
+                                    context = "sieve_script_ingest",
+                                    event = "list-not-found",
+                                    account_id = account_id,
+                                    list = list,
+                                );
+                            }
+                        }
+                    }
+                    Event::Notify {
+                        from,
+                        options,
+                        message,
+                        method,
+                        ..
+                    } => {
+                        input = false.into();
+                        if method
+                            .get(..8)
+                            .map_or(false, |m| m.eq_ignore_ascii_case("https://"))
+                        {
+                            if self.sieve_notify_allowed(account_id).await {
+                                self.sieve_notify_webhook(
+                                    method,
+                                    serde_json::json!({
+                                        "from": from.as_deref().unwrap_or(mail_from.as_str()),
+                                        "sender": envelope_from,
+                                        "recipient": envelope_to,
+                                        "message": message,
+                                        "options": options,
+                                    }),
+                                );
+                                input = true.into();
+                            }
+                        } else {
+                            tracing::debug!(
+                                context = "sieve_script_ingest",
+                                event = "notify_method_unsupported",
+                                account_id = account_id,
+                                method = method,
+                            );
+                        }
+                    }
+                    Event::Function { .. } | Event::SetEnvelope { .. } => {
                         // Not allowed
                         input = false.into();
                     }
@@ -462,20 +619,22 @@ impl JMAP {
             }
         }
 
-        // Save new ids script changes
-        if !new_ids.is_empty() || active_script.seen_ids.has_changes {
-            active_script.seen_ids.ids.extend(new_ids);
-            let mut batch = BatchBuilder::new();
-            batch
-                .with_account_id(account_id)
-                .with_collection(Collection::SieveScript)
-                .update_document(active_script.document_id)
-                .value(
-                    Property::EmailIds,
-                    Bincode::new(active_script.seen_ids),
-                    F_VALUE,
-                );
-            let _ = self.write_batch(batch).await;
+        // Save new ids script changes, duplicate tracking requires an active user script
+        if let Some(mut active_script) = active_script {
+            if !new_ids.is_empty() || active_script.seen_ids.has_changes {
+                active_script.seen_ids.ids.extend(new_ids);
+                let mut batch = BatchBuilder::new();
+                batch
+                    .with_account_id(account_id)
+                    .with_collection(Collection::SieveScript)
+                    .update_document(active_script.document_id)
+                    .value(
+                        Property::EmailIds,
+                        Bincode::new(active_script.seen_ids),
+                        F_VALUE,
+                    );
+                let _ = self.write_batch(batch).await;
+            }
         }
 
         if let Some(reject_reason) = reject_reason {
@@ -491,6 +650,277 @@ impl JMAP {
             Err(last_temp_error.unwrap())
         }
     }
+
+    pub fn has_global_scripts(&self, envelope_to: &str) -> bool {
+        let domain = envelope_to
+            .rsplit_once('@')
+            .map_or("", |(_, domain)| domain);
+        self.core.sieve.untrusted_global.iter().any(|script| {
+            script
+                .domain
+                .as_ref()
+                .map_or(true, |d| d.eq_ignore_ascii_case(domain))
+        })
+    }
+
+    // Builds a script that includes the admin "before" scripts, the user's active script and
+    // the admin "after" scripts, so that implicit keep and "stop" apply to the whole chain.
+    fn sieve_global_wrapper(
+        &self,
+        envelope_to: &str,
+        active_script: Option<&ActiveScript>,
+    ) -> Option<Arc<Sieve>> {
+        let domain = envelope_to
+            .rsplit_once('@')
+            .map_or("", |(_, domain)| domain);
+        let (before, after) = self.core.sieve.global_scripts(domain);
+        if before.is_empty() && after.is_empty() && active_script.is_some() {
+            return None;
+        }
+
+        let mut wrapper = String::from("require \"include\";\r\n");
+        for script in before {
+            wrapper.push_str(&format!("include :global \"{}\";\r\n", script.id));
+        }
+        if let Some(active_script) = active_script {
+            wrapper.push_str("include :personal \"");
+            for ch in active_script.script_name.chars() {
+                if matches!(ch, '"' | '\\') {
+                    wrapper.push('\\');
+                }
+                wrapper.push(ch);
+            }
+            wrapper.push_str("\";\r\n");
+        }
+        for script in after {
+            wrapper.push_str(&format!("include :global \"{}\";\r\n", script.id));
+        }
+
+        match self
+            .core
+            .sieve
+            .untrusted_compiler
+            .compile(wrapper.as_bytes())
+        {
+            Ok(wrapper) => Some(wrapper.into()),
+            Err(err) => {
+                tracing::warn!(
+                    context = "sieve_script_ingest",
+                    event = "error",
+                    reason = %err,
+                    "Failed to compile global Sieve scripts wrapper."
+                );
+                None
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn sieve_vacation_reply(
+        &self,
+        raw_message: &[u8],
+        envelope_from: &str,
+        envelope_to: &str,
+        account_id: u32,
+        mail_from: &str,
+        user_name: Option<&str>,
+        mut vacation_script: ActiveScript,
+    ) {
+        let mut instance = self.core.sieve.untrusted_runtime.filter(raw_message);
+        if let Some(user_name) = user_name {
+            instance.set_user_full_name(user_name);
+        }
+        instance.set_user_address(mail_from);
+        instance.set_envelope(Envelope::From, envelope_from);
+        instance.set_envelope(Envelope::To, envelope_to);
+
+        let mut input = Input::script(
+            vacation_script.script_name.clone(),
+            vacation_script.script.clone(),
+        );
+        let mut messages: Vec<Cow<[u8]>> = vec![raw_message.into()];
+        let mut new_ids = AHashSet::new();
+        let now = now();
+
+        while let Some(event) = instance.run(input) {
+            input = match event {
+                Ok(Event::DuplicateId { id, expiry, last }) => {
+                    let id_hash = SeenIdHash::new(&id, expiry + now);
+                    let seen_id = vacation_script.seen_ids.ids.contains(&id_hash);
+                    if !seen_id || last {
+                        new_ids.insert(id_hash);
+                    }
+                    seen_id.into()
+                }
+                Ok(Event::CreatedMessage { message, .. }) => {
+                    messages.push(message.into());
+                    true.into()
+                }
+                Ok(Event::SendMessage {
+                    recipient: Recipient::Address(rcpt),
+                    message_id,
+                    ..
+                }) => {
+                    if let Some(message) = messages.get(message_id) {
+                        let result = Session::<NullIo>::sieve(
+                            self.smtp.clone(),
+                            SessionAddress::new(mail_from.to_string()),
+                            vec![SessionAddress::new(rcpt)],
+                            message.to_vec(),
+                        )
+                        .queue_message()
+                        .await;
+
+                        tracing::debug!(
+                            context = "sieve_vacation_reply",
+                            event = "send_message",
+                            account_id = account_id,
+                            smtp_response = std::str::from_utf8(&result).unwrap()
+                        );
+                    }
+                    true.into()
+                }
+                Ok(
+                    Event::Keep { .. }
+                    | Event::FileInto { .. }
+                    | Event::Discard
+                    | Event::Reject { .. },
+                ) => {
+                    // Delivery actions are handled by the user script
+                    true.into()
+                }
+                Ok(_) => false.into(),
+                Err(err) => {
+                    tracing::debug!(
+                        context = "sieve_vacation_reply",
+                        event = "error",
+                        account_id = account_id,
+                        reason = %err,
+                        "Runtime error",
+                    );
+                    true.into()
+                }
+            };
+        }
+
+        // Save responded addresses
+        if !new_ids.is_empty() || vacation_script.seen_ids.has_changes {
+            vacation_script.seen_ids.ids.extend(new_ids);
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::SieveScript)
+                .update_document(vacation_script.document_id)
+                .value(
+                    Property::EmailIds,
+                    Bincode::new(vacation_script.seen_ids),
+                    F_VALUE,
+                );
+            let _ = self.write_batch(batch).await;
+        }
+    }
+
+    async fn sieve_notify_allowed(&self, account_id: u32) -> bool {
+        if let Some(rate) = &self.core.sieve.untrusted_notify_rate {
+            match self
+                .core
+                .storage
+                .lookup
+                .is_rate_allowed(format!("snot:{account_id}").as_bytes(), rate, false)
+                .await
+            {
+                Ok(None) => true,
+                Ok(Some(_)) => {
+                    tracing::debug!(
+                        context = "sieve_script_ingest",
+                        event = "notify_rate_limited",
+                        account_id = account_id,
+                        "Notification rate limit exceeded."
+                    );
+                    false
+                }
+                Err(_) => false,
+            }
+        } else {
+            true
+        }
+    }
+
+    fn sieve_notify_webhook(&self, url: String, payload: serde_json::Value) {
+        let timeout = self.core.sieve.untrusted_notify_timeout;
+        tokio::spawn(async move {
+            let client_builder = reqwest::Client::builder().timeout(timeout);
+
+            #[cfg(feature = "test_mode")]
+            let client_builder = client_builder.danger_accept_invalid_certs(true);
+
+            match client_builder
+                .build()
+                .unwrap_or_default()
+                .post(&url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(payload.to_string())
+                .send()
+                .await
+            {
+                Ok(response) if response.status().is_success() => {
+                    tracing::debug!(
+                        context = "sieve_notify",
+                        event = "success",
+                        url = url,
+                        "Webhook notification sent."
+                    );
+                }
+                Ok(response) => {
+                    tracing::debug!(
+                        context = "sieve_notify",
+                        event = "failed",
+                        url = url,
+                        status = %response.status(),
+                        "Webhook notification failed."
+                    );
+                }
+                Err(err) => {
+                    tracing::debug!(
+                        context = "sieve_notify",
+                        event = "error",
+                        url = url,
+                        reason = %err,
+                        "Webhook notification failed."
+                    );
+                }
+            }
+        });
+    }
+}
+
+// Record this hop so that forwarding loops can be detected on delivery
+fn with_delivered_to(message: &[u8], envelope_to: &str) -> Vec<u8> {
+    let mut new_message = Vec::with_capacity(message.len() + envelope_to.len() + 16);
+    new_message.extend_from_slice(b"Delivered-To: ");
+    new_message.extend_from_slice(envelope_to.as_bytes());
+    new_message.extend_from_slice(b"\r\n");
+    new_message.extend_from_slice(message);
+    new_message
+}
+
+fn is_auto_submitted(message: &[u8], auto_submitted: &str) -> bool {
+    for line in message.split(|&ch| ch == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            break;
+        } else if let Some(value) = line
+            .get(..15)
+            .filter(|name| name.eq_ignore_ascii_case(b"auto-submitted:"))
+            .map(|_| &line[15..])
+        {
+            return std::str::from_utf8(value).map_or(false, |value| {
+                value.trim().eq_ignore_ascii_case(auto_submitted)
+            });
+        }
+    }
+
+    false
 }
 
 #[inline(always)]
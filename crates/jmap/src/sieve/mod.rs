@@ -28,6 +28,7 @@ use sieve::Sieve;
 use store::{ahash::AHashSet, blake3, write::now};
 
 pub mod get;
+pub mod history;
 pub mod ingest;
 pub mod query;
 pub mod set;
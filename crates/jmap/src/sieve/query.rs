@@ -54,7 +54,7 @@ impl JMAP {
         }
 
         let result_set = self
-            .filter(account_id, Collection::SieveScript, filters)
+            .query_filter(account_id, Collection::SieveScript, filters)
             .await?;
 
         let (response, paginate) = self.build_query_response(&result_set, &request).await?;
@@ -47,8 +47,9 @@ use store::{
     query::Filter,
     rand::{distributions::Alphanumeric, thread_rng, Rng},
     write::{
-        assert::HashedValue, log::ChangeLogBuilder, BatchBuilder, BlobOp, DirectoryClass, F_CLEAR,
-        F_VALUE,
+        assert::HashedValue,
+        log::{ChangeLogBuilder, Changes, LogInsert},
+        BatchBuilder, BlobOp, DirectoryClass, F_CLEAR, F_VALUE,
     },
     BlobClass,
 };
@@ -222,17 +223,15 @@ impl JMAP {
                                 batch.add(DirectoryClass::UsedQuota(account_id), update_quota);
                             }
 
-                            // Update blobId
-                            batch
-                                .clear(BlobOp::Link {
-                                    hash: prev_blob_id.hash,
-                                })
-                                .set(
-                                    BlobOp::Link {
-                                        hash: blob_id.hash.clone(),
-                                    },
-                                    Vec::new(),
-                                );
+                            // Update blobId, keeping the previous script as an older version
+                            self.sieve_archive_version(
+                                &mut batch,
+                                account_id,
+                                document_id,
+                                &prev_blob_id,
+                                &blob_id.hash,
+                            )
+                            .await?;
 
                             blob_id.into()
                         } else {
@@ -406,12 +405,133 @@ impl JMAP {
             .add(
                 DirectoryClass::UsedQuota(account_id),
                 -(blob_id.section.as_ref().unwrap().size as i64),
-            )
-            .custom(ObjectIndexBuilder::new(SCHEMA).with_current(obj));
+            );
+        self.sieve_purge_versions(&mut batch, account_id, document_id)
+            .await?;
+        batch.custom(ObjectIndexBuilder::new(SCHEMA).with_current(obj));
         self.write_batch(batch).await?;
         Ok(true)
     }
 
+    pub async fn sieve_script_put(
+        &self,
+        account_id: u32,
+        document_id: Option<u32>,
+        name: String,
+        script_bytes: Vec<u8>,
+        script_size: usize,
+    ) -> Result<u32, MethodError> {
+        let script_size_ = script_size as i64;
+        let change_id = self.assign_change_id(account_id).await?;
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_change_id(change_id)
+            .with_account_id(account_id)
+            .with_collection(Collection::SieveScript);
+
+        if let Some(document_id) = document_id {
+            // Obtain script values
+            let script = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    Collection::SieveScript,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+                .ok_or(MethodError::NotFound)?;
+            let prev_blob_id = script.inner.blob_id().ok_or_else(|| {
+                tracing::warn!(
+                    event = "error",
+                    context = "sieve_script_put",
+                    account_id = account_id,
+                    document_id = document_id,
+                    "Sieve does not contain a blobId."
+                );
+                MethodError::ServerPartialFail
+            })?;
+
+            // Write script blob
+            let blob_id = BlobId::new(
+                self.put_blob(account_id, &script_bytes, false).await?.hash,
+                BlobClass::Linked {
+                    account_id,
+                    collection: Collection::SieveScript.into(),
+                    document_id,
+                },
+            )
+            .with_section_size(script_size);
+
+            // Write record
+            batch
+                .update_document(document_id)
+                .log(Changes::update([document_id]));
+            self.sieve_archive_version(
+                &mut batch,
+                account_id,
+                document_id,
+                prev_blob_id,
+                &blob_id.hash,
+            )
+            .await?;
+
+            // Update quota
+            let prev_script_size = prev_blob_id.section.as_ref().unwrap().size as i64;
+            let update_quota = match script_size_.cmp(&prev_script_size) {
+                std::cmp::Ordering::Greater => script_size_ - prev_script_size,
+                std::cmp::Ordering::Less => -prev_script_size + script_size_,
+                std::cmp::Ordering::Equal => 0,
+            };
+            if update_quota != 0 {
+                batch.add(DirectoryClass::UsedQuota(account_id), update_quota);
+            }
+
+            batch.custom(
+                ObjectIndexBuilder::new(SCHEMA)
+                    .with_current(script)
+                    .with_changes(
+                        Object::with_capacity(1)
+                            .with_property(Property::BlobId, Value::BlobId(blob_id)),
+                    ),
+            );
+            self.write_batch(batch).await?;
+
+            Ok(document_id)
+        } else {
+            // Write script blob
+            let blob_id = BlobId::new(
+                self.put_blob(account_id, &script_bytes, false).await?.hash,
+                BlobClass::Linked {
+                    account_id,
+                    collection: Collection::SieveScript.into(),
+                    document_id: 0,
+                },
+            )
+            .with_section_size(script_size);
+
+            // Write record
+            batch
+                .create_document()
+                .log(LogInsert())
+                .add(DirectoryClass::UsedQuota(account_id), script_size_)
+                .set(
+                    BlobOp::Link {
+                        hash: blob_id.hash.clone(),
+                    },
+                    Vec::new(),
+                )
+                .custom(
+                    ObjectIndexBuilder::new(SCHEMA).with_changes(
+                        Object::with_capacity(3)
+                            .with_property(Property::Name, name)
+                            .with_property(Property::IsActive, Value::Bool(false))
+                            .with_property(Property::BlobId, Value::BlobId(blob_id)),
+                    ),
+                );
+            self.write_batch_expect_id(batch).await
+        }
+    }
+
     #[allow(clippy::blocks_in_conditions)]
     async fn sieve_set_item(
         &self,
@@ -586,6 +706,19 @@ impl JMAP {
             .await?
             .results;
 
+        // The vacation response is enabled independently from user scripts
+        if let Some(vacation_id) = self.get_vacation_sieve_script_id(account_id).await? {
+            if activate_id == Some(vacation_id) {
+                let is_active = active_ids.contains(vacation_id);
+                active_ids.clear();
+                if is_active {
+                    active_ids.insert(vacation_id);
+                }
+            } else {
+                active_ids.remove(vacation_id);
+            }
+        }
+
         // Check if script is already active
         if activate_id.map_or(false, |id| active_ids.remove(id)) {
             if active_ids.is_empty() {
@@ -82,7 +82,7 @@ impl JMAP {
         }
 
         let result_set = self
-            .filter(account_id, Collection::EmailSubmission, filters)
+            .query_filter(account_id, Collection::EmailSubmission, filters)
             .await?;
 
         let (response, paginate) = self.build_query_response(&result_set, &request).await?;
@@ -228,6 +228,19 @@ impl JMAP {
                 )
                 .await?
             {
+                // Cancel sends held with FUTURERELEASE that have not been released yet
+                if let (Value::UnsignedInt(queue_id), Value::Date(send_at)) = (
+                    submission.inner.get(&Property::MessageId),
+                    submission.inner.get(&Property::SendAt),
+                ) {
+                    if send_at.timestamp() > now() as i64 {
+                        if let Some(queue_message) = self.smtp.read_message(*queue_id).await {
+                            let message_due = queue_message.next_event().unwrap_or_default();
+                            queue_message.remove(&self.smtp, message_due).await;
+                        }
+                    }
+                }
+
                 // Update record
                 let mut batch = BatchBuilder::new();
                 batch
@@ -604,6 +617,14 @@ impl JMAP {
             let response = session.queue_message().await;
             if let State::Accepted(queue_id) = session.state {
                 submission.append(Property::MessageId, queue_id);
+
+                // Messages held during the undo window are released later
+                if session.data.future_release > 0 {
+                    submission.set(
+                        Property::SendAt,
+                        UTCDate::from_timestamp((now() + session.data.future_release) as i64),
+                    );
+                }
             } else {
                 return Ok(Err(SetError::new(SetErrorType::ForbiddenToSend)
                     .with_description(format!(
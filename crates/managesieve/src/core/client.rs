@@ -21,6 +21,8 @@
  * for more details.
 */
 
+use std::time::Instant;
+
 use common::listener::SessionStream;
 use imap_proto::receiver::{self, Request};
 use jmap_proto::types::{collection::Collection, property::Property};
@@ -58,6 +60,17 @@ impl<T: SessionStream> Session<T> {
                     break;
                 }
                 Err(receiver::Error::Error { response }) => {
+                    if self
+                        .jmap
+                        .core
+                        .is_abuse_banned(self.remote_addr)
+                        .await
+                        .unwrap_or_default()
+                    {
+                        self.write(&StatusResponse::bye("Too many invalid commands.").into_bytes())
+                            .await?;
+                        return Err(());
+                    }
                     self.write(&StatusResponse::no(response.message).into_bytes())
                         .await?;
                     break;
@@ -65,8 +78,24 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        let mut is_reset = false;
         for request in requests {
-            match match request.command {
+            // Requests pipelined after UNAUTHENTICATE are validated again
+            let request = if is_reset {
+                match self.validate_request(request).await {
+                    Ok(request) => request,
+                    Err(response) => {
+                        self.write(&response.into_bytes()).await?;
+                        continue;
+                    }
+                }
+            } else {
+                request
+            };
+
+            let command = request.command;
+            let op_start = Instant::now();
+            let result = match command {
                 Command::ListScripts => self.handle_listscripts().await,
                 Command::PutScript => self.handle_putscript(request).await,
                 Command::SetActive => self.handle_setactive(request).await,
@@ -83,8 +112,27 @@ impl<T: SessionStream> Session<T> {
                 }
                 Command::Logout => self.handle_logout().await,
                 Command::Noop => self.handle_noop(request).await,
-                Command::Unauthenticate => self.handle_unauthenticate().await,
-            } {
+                Command::Unauthenticate => {
+                    is_reset = true;
+                    needs_literal = None;
+                    self.handle_unauthenticate().await
+                }
+            };
+
+            tracing::debug!(
+                parent: &self.span,
+                event = "command",
+                command = command.as_str(),
+                account_id = ?self.state.account_id(),
+                result = match &result {
+                    Ok(_) => ResponseType::Ok.as_str(),
+                    Err(err) => err.rtype.as_str(),
+                },
+                elapsed = op_start.elapsed().as_millis() as u64,
+                "ManageSieve command completed."
+            );
+
+            match result {
                 Ok(response) => {
                     self.write(&response).await?;
                 }
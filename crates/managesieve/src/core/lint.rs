@@ -0,0 +1,286 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::fmt::Display;
+
+static DEPRECATED_EXTENSIONS: &[(&str, &str)] =
+    &[("imapflags", "imap4flags"), ("notify", "enotify")];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub warnings: Vec<Warning>,
+    pub fileinto: Vec<(usize, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Identifier(String),
+    Tag(String),
+    String(String),
+    Semicolon,
+    BlockStart,
+    BlockEnd,
+    Other,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Diagnostics {
+    // Collects non-fatal diagnostics from a script that already compiled successfully
+    pub fn collect(script: &[u8]) -> Self {
+        let mut diagnostics = Diagnostics::default();
+        let mut tokens = tokenize(script).into_iter();
+        let mut stopped = vec![false];
+        let mut reported = vec![false];
+
+        while let Some((token, line)) = tokens.next() {
+            match token {
+                Token::Identifier(command) => {
+                    if *stopped.last().unwrap() && !*reported.last().unwrap() {
+                        *reported.last_mut().unwrap() = true;
+                        diagnostics.warnings.push(Warning {
+                            line,
+                            message: format!("command {command:?} is unreachable after \"stop\""),
+                        });
+                    }
+
+                    // Collect arguments up to the end of the command
+                    let mut args = Vec::new();
+                    let mut has_block = false;
+                    for (token, line) in tokens.by_ref() {
+                        match token {
+                            Token::Semicolon => break,
+                            Token::BlockStart => {
+                                has_block = true;
+                                break;
+                            }
+                            token => args.push((token, line)),
+                        }
+                    }
+
+                    match command.as_str() {
+                        "require" => {
+                            for (arg, line) in &args {
+                                if let Token::String(name) = arg {
+                                    if let Some((_, replacement)) = DEPRECATED_EXTENSIONS
+                                        .iter()
+                                        .find(|(ext, _)| name.eq_ignore_ascii_case(ext))
+                                    {
+                                        diagnostics.warnings.push(Warning {
+                                            line: *line,
+                                            message: format!(
+                                                "extension {name:?} is deprecated, use {replacement:?} instead"
+                                            ),
+                                        });
+                                    }
+                                }
+                            }
+                        }
+                        "fileinto" => {
+                            let mut args = args.into_iter();
+                            let mut mailbox = None;
+                            let mut has_fallback = false;
+                            while let Some((arg, line)) = args.next() {
+                                match arg {
+                                    Token::Tag(tag) => match tag.as_str() {
+                                        "flags" => {
+                                            args.next();
+                                        }
+                                        "mailboxid" | "specialuse" => {
+                                            has_fallback = true;
+                                            args.next();
+                                        }
+                                        "create" => {
+                                            has_fallback = true;
+                                        }
+                                        _ => (),
+                                    },
+                                    Token::String(name) => {
+                                        mailbox = Some((line, name));
+                                    }
+                                    _ => (),
+                                }
+                            }
+                            if let Some((line, name)) = mailbox {
+                                if !has_fallback && !name.contains("${") {
+                                    diagnostics.fileinto.push((line, name));
+                                }
+                            }
+                        }
+                        "stop" => {
+                            *stopped.last_mut().unwrap() = true;
+                        }
+                        _ => (),
+                    }
+
+                    if has_block {
+                        stopped.push(false);
+                        reported.push(false);
+                    }
+                }
+                Token::BlockEnd if stopped.len() > 1 => {
+                    stopped.pop();
+                    reported.pop();
+                }
+                _ => (),
+            }
+        }
+
+        diagnostics
+    }
+}
+
+fn tokenize(script: &[u8]) -> Vec<(Token, usize)> {
+    let mut tokens = Vec::new();
+    let mut iter = script.iter().peekable();
+    let mut line = 1;
+
+    while let Some(&ch) = iter.next() {
+        match ch {
+            b'\n' => {
+                line += 1;
+            }
+            b'#' => {
+                for &ch in iter.by_ref() {
+                    if ch == b'\n' {
+                        line += 1;
+                        break;
+                    }
+                }
+            }
+            b'/' if iter.peek() == Some(&&b'*') => {
+                iter.next();
+                let mut last_ch = 0;
+                for &ch in iter.by_ref() {
+                    if ch == b'\n' {
+                        line += 1;
+                    } else if ch == b'/' && last_ch == b'*' {
+                        break;
+                    }
+                    last_ch = ch;
+                }
+            }
+            b'"' => {
+                let start_line = line;
+                let mut value = Vec::new();
+                while let Some(&ch) = iter.next() {
+                    match ch {
+                        b'\\' => {
+                            if let Some(&ch) = iter.next() {
+                                value.push(ch);
+                            }
+                        }
+                        b'"' => break,
+                        _ => {
+                            if ch == b'\n' {
+                                line += 1;
+                            }
+                            value.push(ch);
+                        }
+                    }
+                }
+                tokens.push((
+                    Token::String(String::from_utf8_lossy(&value).into_owned()),
+                    start_line,
+                ));
+            }
+            b':' => {
+                let mut tag = Vec::new();
+                while let Some(&&ch) = iter.peek() {
+                    if ch.is_ascii_alphanumeric() || ch == b'_' {
+                        tag.push(ch.to_ascii_lowercase());
+                        iter.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push((Token::Tag(String::from_utf8(tag).unwrap_or_default()), line));
+            }
+            b';' => tokens.push((Token::Semicolon, line)),
+            b'{' => tokens.push((Token::BlockStart, line)),
+            b'}' => tokens.push((Token::BlockEnd, line)),
+            _ if ch.is_ascii_alphabetic() || ch == b'_' => {
+                let mut identifier = vec![ch.to_ascii_lowercase()];
+                while let Some(&&ch) = iter.peek() {
+                    if ch.is_ascii_alphanumeric() || ch == b'_' {
+                        identifier.push(ch.to_ascii_lowercase());
+                        iter.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                if identifier == b"text" && iter.peek() == Some(&&b':') {
+                    // Multi-line string, terminated by a line containing a single dot
+                    iter.next();
+                    let start_line = line;
+                    for &ch in iter.by_ref() {
+                        if ch == b'\n' {
+                            line += 1;
+                            break;
+                        }
+                    }
+                    let mut value = Vec::new();
+                    let mut current_line = Vec::new();
+                    for &ch in iter.by_ref() {
+                        if ch == b'\n' {
+                            line += 1;
+                            let text = current_line.strip_suffix(b"\r").unwrap_or(&current_line);
+                            if text == b"." {
+                                break;
+                            }
+                            value.extend_from_slice(text.strip_prefix(b".").unwrap_or(text));
+                            value.push(b'\n');
+                            current_line.clear();
+                        } else {
+                            current_line.push(ch);
+                        }
+                    }
+                    tokens.push((
+                        Token::String(String::from_utf8_lossy(&value).into_owned()),
+                        start_line,
+                    ));
+                } else {
+                    tokens.push((
+                        Token::Identifier(String::from_utf8(identifier).unwrap_or_default()),
+                        line,
+                    ));
+                }
+            }
+            _ if ch.is_ascii_whitespace() => (),
+            _ => tokens.push((Token::Other, line)),
+        }
+    }
+
+    tokens
+}
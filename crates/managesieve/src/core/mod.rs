@@ -22,11 +22,15 @@
 */
 
 pub mod client;
+pub mod lint;
 pub mod session;
 
 use std::{borrow::Cow, net::IpAddr, sync::Arc};
 
-use common::listener::{limiter::InFlight, ServerInstance};
+use common::{
+    listener::{limiter::InFlight, qos::QosPermit, ServerInstance},
+    sasl::SaslExchange,
+};
 use imap::core::{ImapInstance, Inner};
 use imap_proto::receiver::{CommandParser, Receiver};
 use jmap::{auth::AccessToken, JMAP};
@@ -40,8 +44,10 @@ pub struct Session<T: AsyncRead + AsyncWrite> {
     pub state: State,
     pub remote_addr: IpAddr,
     pub stream: T,
+    pub sasl: Option<SaslExchange>,
     pub span: tracing::Span,
     pub in_flight: InFlight,
+    pub qos: QosPermit,
 }
 
 pub enum State {
@@ -61,6 +67,13 @@ impl State {
             State::NotAuthenticated { .. } => unreachable!("Not authenticated"),
         }
     }
+
+    pub fn account_id(&self) -> Option<u32> {
+        match self {
+            State::Authenticated { access_token, .. } => Some(access_token.primary_id()),
+            State::NotAuthenticated { .. } => None,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -93,6 +106,27 @@ pub enum Command {
     Unauthenticate,
 }
 
+impl Command {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Command::Authenticate => "AUTHENTICATE",
+            Command::StartTls => "STARTTLS",
+            Command::Logout => "LOGOUT",
+            Command::Capability => "CAPABILITY",
+            Command::HaveSpace => "HAVESPACE",
+            Command::PutScript => "PUTSCRIPT",
+            Command::ListScripts => "LISTSCRIPTS",
+            Command::SetActive => "SETACTIVE",
+            Command::GetScript => "GETSCRIPT",
+            Command::DeleteScript => "DELETESCRIPT",
+            Command::RenameScript => "RENAMESCRIPT",
+            Command::CheckScript => "CHECKSCRIPT",
+            Command::Noop => "NOOP",
+            Command::Unauthenticate => "UNAUTHENTICATE",
+        }
+    }
+}
+
 impl CommandParser for Command {
     fn parse(value: &[u8], _is_uid: bool) -> Option<Self> {
         match value {
@@ -180,11 +214,15 @@ impl ResponseCode {
 
 impl ResponseType {
     pub fn serialize(&self, buf: &mut Vec<u8>) {
-        buf.extend_from_slice(match self {
-            ResponseType::Ok => b"OK",
-            ResponseType::No => b"NO",
-            ResponseType::Bye => b"BYE",
-        });
+        buf.extend_from_slice(self.as_str().as_bytes());
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseType::Ok => "OK",
+            ResponseType::No => "NO",
+            ResponseType::Bye => "BYE",
+        }
     }
 }
 
@@ -48,7 +48,9 @@ impl SessionManager for ManageSieveSessionManager {
                 state: State::NotAuthenticated { auth_failures: 0 },
                 span: session.span,
                 stream: session.stream,
+                sasl: None,
                 in_flight: session.in_flight,
+                qos: session.qos,
                 remote_addr: session.remote_ip,
             };
 
@@ -81,7 +83,7 @@ impl<T: SessionStream> Session<T> {
             tokio::select! {
                 result = tokio::time::timeout(
                     if !matches!(self.state, State::NotAuthenticated {..}) {
-                        self.jmap.core.imap.timeout_auth
+                        self.jmap.core.managesieve.timeout
                     } else {
                         self.jmap.core.imap.timeout_unauth
                     },
@@ -119,7 +121,7 @@ impl<T: SessionStream> Session<T> {
                                     "Connection timed out."
                                 );
                                 self
-                                    .write(b"BYE \"Connection timed out.\"\r\n")
+                                    .write(b"BYE \"Idle timeout\"\r\n")
                                     .await
                                     .ok();
                                 break;
@@ -146,9 +148,11 @@ impl<T: SessionStream> Session<T> {
         let span = self.span;
         Ok(Session {
             stream: self.instance.tls_accept(self.stream, &span).await?,
+            sasl: None,
             state: self.state,
             instance: self.instance,
             in_flight: self.in_flight,
+            qos: self.qos,
             span,
             jmap: self.jmap,
             imap: self.imap,
@@ -28,9 +28,19 @@ static SERVER_GREETING: &str = "Stalwart ManageSieve at your service.";
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
     use imap_proto::receiver::{Error, Receiver, Request, State, Token};
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
 
-    use crate::core::Command;
+    use crate::{
+        core::{lint::Diagnostics, Command},
+        op::authenticate::record_identity,
+    };
 
     #[test]
     fn receiver_parse_managesieve() {
@@ -156,4 +166,101 @@ mod tests {
             assert_eq!(requests, expected_requests, "{:#?}", frames);
         }
     }
+
+    #[test]
+    fn record_auth_identities() {
+        struct Recorder(Mutex<Vec<(String, String)>>);
+
+        impl Visit for &Recorder {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((field.name().to_string(), format!("{value:?}")));
+            }
+
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((field.name().to_string(), value.to_string()));
+            }
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+
+            fn record(&self, _: &Id, values: &Record<'_>) {
+                values.record(&mut &*self);
+            }
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, _: &Event<'_>) {}
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let span = tracing::info_span!(
+                "session",
+                authcid = tracing::field::Empty,
+                authzid = tracing::field::Empty,
+            );
+            record_identity(&span, "admin", "jdoe@example.com");
+        });
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                ("authcid".to_string(), "admin".to_string()),
+                ("authzid".to_string(), "jdoe@example.com".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_script() {
+        let diagnostics = Diagnostics::collect(
+            br#"require ["fileinto", "imapflags"];
+# fileinto "Commented";
+if header :contains "subject" "stop" {
+    fileinto :flags "\\Seen" "Lists/Rust";
+    stop;
+}
+fileinto :create "Created";
+fileinto :specialuse "\\Junk" "Spam";
+vacation :subject "Away" text:
+I am away;
+stop;
+.
+;
+stop;
+keep;
+discard;
+"#,
+        );
+
+        assert_eq!(
+            diagnostics
+                .warnings
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "line 1: extension \"imapflags\" is deprecated, use \"imap4flags\" instead",
+                "line 15: command \"keep\" is unreachable after \"stop\"",
+            ]
+        );
+        assert_eq!(diagnostics.fileinto, vec![(4, "Lists/Rust".to_string())]);
+    }
 }
@@ -23,14 +23,18 @@
 
 use common::{
     listener::{limiter::ConcurrencyLimiter, SessionStream},
+    sasl::{SaslExchange, SaslStep},
     AuthResult,
 };
-use imap::op::authenticate::{decode_challenge_oauth, decode_challenge_plain};
+use directory::QueryBy;
+use imap::op::authenticate::{
+    decode_challenge_oauth, decode_challenge_plain_authzid, sasl_mechanism,
+};
 use imap_proto::{
     protocol::authenticate::Mechanism,
     receiver::{self, Request},
 };
-use jmap::auth::rate_limit::ConcurrencyLimiters;
+use jmap::auth::{rate_limit::ConcurrencyLimiters, AccessToken};
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use std::sync::Arc;
@@ -50,15 +54,15 @@ impl<T: SessionStream> Session<T> {
             .filter_map(|token| token.unwrap_string().ok())
             .collect();
 
-        let credentials = match mechanism {
+        let (authz_id, credentials) = match mechanism {
             Mechanism::Plain | Mechanism::OAuthBearer => {
                 if !params.is_empty() {
                     let challenge = base64_decode(params.pop().unwrap().as_bytes())
                         .ok_or_else(|| StatusResponse::no("Failed to decode challenge."))?;
                     (if mechanism == Mechanism::Plain {
-                        decode_challenge_plain(&challenge)
+                        decode_challenge_plain_authzid(&challenge)
                     } else {
-                        decode_challenge_oauth(&challenge)
+                        decode_challenge_oauth(&challenge).map(|credentials| (None, credentials))
                     }
                     .map_err(StatusResponse::no))?
                 } else {
@@ -71,6 +75,9 @@ impl<T: SessionStream> Session<T> {
                     return Ok(b"{0}\r\n".to_vec());
                 }
             }
+            Mechanism::ScramSha256 | Mechanism::ScramSha256Plus | Mechanism::CramMd5 => {
+                return self.handle_sasl_exchange(mechanism, params.pop()).await;
+            }
             _ => {
                 return Err(StatusResponse::no(
                     "Authentication mechanism not supported.",
@@ -79,20 +86,7 @@ impl<T: SessionStream> Session<T> {
         };
 
         // Throttle authentication requests
-        if self
-            .jmap
-            .is_auth_allowed_soft(&self.remote_addr)
-            .await
-            .is_err()
-        {
-            tracing::debug!(parent: &self.span,
-                event = "disconnect",
-                "Too many authentication attempts, disconnecting.",
-            );
-            return Err(StatusResponse::bye(
-                "Too many authentication requests from this IP address.",
-            ));
-        }
+        self.is_auth_allowed().await?;
 
         // Authenticate
         let access_token = match credentials {
@@ -131,7 +125,115 @@ impl<T: SessionStream> Session<T> {
             }
         };
 
-        if let Some(access_token) = access_token {
+        self.finish_authentication(access_token, authz_id).await
+    }
+
+    async fn handle_sasl_exchange(
+        &mut self,
+        mechanism: Mechanism,
+        response: Option<String>,
+    ) -> crate::op::OpResult {
+        // Continue the exchange when this is a response to a previous challenge
+        let mut exchange = match self.sasl.take() {
+            Some(exchange) if Some(exchange.mechanism()) == sasl_mechanism(&mechanism) => exchange,
+            _ => {
+                let sasl_mechanism = sasl_mechanism(&mechanism)
+                    .filter(|mechanism| self.jmap.core.imap.sasl_mechanisms.contains(mechanism))
+                    .ok_or_else(|| StatusResponse::no("Authentication mechanism not supported."))?;
+                self.is_auth_allowed().await?;
+                SaslExchange::new(sasl_mechanism, self.stream.tls_exporter())
+            }
+        };
+
+        let response = match response {
+            Some(response) => Some(
+                base64_decode(response.as_bytes())
+                    .or_else(|| response.is_empty().then(Vec::new))
+                    .ok_or_else(|| StatusResponse::no("Failed to decode challenge."))?,
+            ),
+            None => None,
+        };
+
+        match self
+            .jmap
+            .authenticate_sasl(&mut exchange, response.as_deref(), self.remote_addr)
+            .await
+        {
+            SaslStep::Challenge(challenge) => {
+                self.sasl = Some(exchange);
+                self.receiver.request = receiver::Request {
+                    tag: String::new(),
+                    command: Command::Authenticate,
+                    tokens: vec![receiver::Token::Argument(mechanism.into_bytes())],
+                };
+                self.receiver.state = receiver::State::Argument { last_ch: b' ' };
+                Ok(format!("\"{challenge}\"\r\n").into_bytes())
+            }
+            SaslStep::Done(AuthResult::Success(access_token)) => {
+                self.finish_authentication(Some(access_token), None).await
+            }
+            SaslStep::Done(AuthResult::Failure) => self.finish_authentication(None, None).await,
+            SaslStep::Done(AuthResult::Banned) => Err(StatusResponse::bye(
+                "Too many authentication requests from this IP address.",
+            )),
+            SaslStep::Invalid(err) => Err(StatusResponse::no(err)),
+        }
+    }
+
+    async fn is_auth_allowed(&self) -> Result<(), StatusResponse> {
+        if self
+            .jmap
+            .is_auth_allowed_soft(&self.remote_addr)
+            .await
+            .is_err()
+        {
+            tracing::debug!(parent: &self.span,
+                event = "disconnect",
+                "Too many authentication attempts, disconnecting.",
+            );
+            Err(StatusResponse::bye(
+                "Too many authentication requests from this IP address.",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn finish_authentication(
+        &mut self,
+        access_token: Option<AccessToken>,
+        authz_id: Option<String>,
+    ) -> crate::op::OpResult {
+        // Make sure the account is allowed to use this protocol
+        let access_token = access_token.filter(|access_token| {
+            if access_token.is_protocol_allowed("managesieve") {
+                true
+            } else {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "authenticate",
+                    account_id = access_token.primary_id(),
+                    "Account is not allowed to access ManageSieve."
+                );
+                false
+            }
+        });
+
+        // Obtain the authorization identity
+        let authcid = access_token
+            .as_ref()
+            .map(|access_token| access_token.name.clone());
+        let access_token = match (access_token, authz_id) {
+            (Some(access_token), Some(authz_id)) => {
+                Some(self.authorize_as(access_token, &authz_id).await?)
+            }
+            (access_token, _) => access_token,
+        };
+
+        if let (Some(access_token), Some(authcid)) = (access_token, authcid) {
+            // Record both identities on the session
+            record_identity(&self.span, &authcid, &access_token.name);
+
             // Enforce concurrency limits
             let in_flight = match self
                 .get_concurrency_limiter(access_token.primary_id())
@@ -152,6 +254,9 @@ impl<T: SessionStream> Session<T> {
             let access_token = Arc::new(access_token);
             self.jmap.cache_access_token(access_token.clone());
 
+            // Move the connection to the authenticated class
+            self.qos.promote();
+
             // Create session
             self.state = State::Authenticated {
                 access_token,
@@ -181,8 +286,86 @@ impl<T: SessionStream> Session<T> {
         }
     }
 
+    async fn authorize_as(
+        &self,
+        access_token: AccessToken,
+        authz_id: &str,
+    ) -> Result<AccessToken, StatusResponse> {
+        if access_token.name == authz_id {
+            return Ok(access_token);
+        }
+
+        // Map the authorization identity to an account
+        let account_id = match self
+            .jmap
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Name(authz_id), false)
+            .await
+        {
+            Ok(Some(principal)) => principal.id,
+            Ok(None) => {
+                return Err(StatusResponse::no("Authorization identity does not exist."));
+            }
+            Err(_) => return Err(StatusResponse::database_failure()),
+        };
+
+        // Only superusers and members of the target account may act on its behalf
+        if !access_token.is_member(account_id) {
+            tracing::debug!(
+                parent: &self.span,
+                context = "authenticate",
+                event = "authz-denied",
+                authcid = access_token.name.as_str(),
+                authzid = authz_id,
+                "Authorization identity denied."
+            );
+            return Err(StatusResponse::no(
+                "Not authorized to act on behalf of this account.",
+            ));
+        }
+
+        let target_token = self
+            .jmap
+            .get_access_token(account_id)
+            .await
+            .ok_or_else(StatusResponse::database_failure)?;
+
+        tracing::info!(
+            parent: &self.span,
+            context = "authenticate",
+            event = "authz",
+            authcid = access_token.name.as_str(),
+            authzid = target_token.name.as_str(),
+            "Authenticated on behalf of another account."
+        );
+
+        Ok(target_token)
+    }
+
     pub async fn handle_unauthenticate(&mut self) -> super::OpResult {
-        self.state = State::NotAuthenticated { auth_failures: 0 };
+        if let State::Authenticated { access_token, .. } = std::mem::replace(
+            &mut self.state,
+            State::NotAuthenticated { auth_failures: 0 },
+        ) {
+            // Only session-local state is reset: dropping the previous state releases
+            // the session's access token and in-flight slot, while the shared
+            // access token cache and request rate counters are left alone
+            let account_id = access_token.primary_id();
+            self.sasl = None;
+
+            tracing::debug!(
+                parent: &self.span,
+                context = "unauthenticate",
+                event = "success",
+                account_id = account_id,
+                "Session returned to non-authenticated state."
+            );
+        }
+
+        // Discard any pending literals
+        self.receiver.reset();
 
         Ok(StatusResponse::ok("Unauthenticate successful.").into_bytes())
     }
@@ -204,3 +387,8 @@ impl<T: SessionStream> Session<T> {
             .into()
     }
 }
+
+pub fn record_identity(span: &tracing::Span, authcid: &str, authzid: &str) {
+    span.record("authcid", authcid);
+    span.record("authzid", authzid);
+}
@@ -22,6 +22,7 @@
 */
 
 use common::listener::SessionStream;
+use imap::op::authenticate::sasl_mechanisms;
 use jmap_proto::request::capability::Capabilities;
 
 use crate::core::{Session, StatusResponse};
@@ -34,11 +35,18 @@ impl<T: SessionStream> Session<T> {
         if !self.stream.is_tls() {
             response.extend_from_slice(b"\"STARTTLS\"\r\n");
         }
+        response.extend_from_slice(b"\"SASL\" \"");
         if self.stream.is_tls() || self.jmap.core.imap.allow_plain_auth {
-            response.extend_from_slice(b"\"SASL\" \"PLAIN OAUTHBEARER\"\r\n");
-        } else {
-            response.extend_from_slice(b"\"SASL\" \"OAUTHBEARER\"\r\n");
-        };
+            response.extend_from_slice(b"PLAIN ");
+        }
+        for mechanism in sasl_mechanisms(
+            &self.jmap.core.imap.sasl_mechanisms,
+            self.stream.tls_exporter().is_some(),
+        ) {
+            mechanism.serialize(&mut response);
+            response.push(b' ');
+        }
+        response.extend_from_slice(b"OAUTHBEARER\"\r\n");
         if let Some(sieve) =
             self.jmap
                 .core
@@ -70,6 +78,12 @@ impl<T: SessionStream> Session<T> {
         } else {
             response.extend_from_slice(b"\"SIEVE\" \"\"\r\n");
         }
+        let max_versions = self.jmap.core.jmap.sieve_max_versions;
+        if max_versions > 0 {
+            response.extend_from_slice(b"\"X-VERSIONS\" \"");
+            response.extend_from_slice(max_versions.to_string().as_bytes());
+            response.extend_from_slice(b"\"\r\n");
+        }
 
         Ok(StatusResponse::ok(message).serialize(response))
     }
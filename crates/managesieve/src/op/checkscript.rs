@@ -24,7 +24,10 @@
 use imap_proto::receiver::Request;
 use tokio::io::{AsyncRead, AsyncWrite};
 
-use crate::core::{Command, Session, StatusResponse};
+use crate::core::{
+    lint::{Diagnostics, Warning},
+    Command, ResponseCode, Session, StatusResponse,
+};
 
 impl<T: AsyncRead + AsyncWrite> Session<T> {
     pub async fn handle_checkscript(&mut self, request: Request<Command>) -> super::OpResult {
@@ -32,12 +35,46 @@ impl<T: AsyncRead + AsyncWrite> Session<T> {
             return Err(StatusResponse::no("Expected script as a parameter."));
         }
 
+        let script = request.tokens.into_iter().next().unwrap().unwrap_bytes();
         self.jmap
             .core
             .sieve
             .untrusted_compiler
-            .compile(&request.tokens.into_iter().next().unwrap().unwrap_bytes())
-            .map(|_| StatusResponse::ok("Script is valid.").into_bytes())
-            .map_err(|err| StatusResponse::no(err.to_string()))
+            .compile(&script)
+            .map_err(|err| StatusResponse::no(err.to_string()))?;
+
+        // Collect warnings
+        let diagnostics = Diagnostics::collect(&script);
+        let mut warnings = diagnostics.warnings;
+        let account_id = self.state.access_token().primary_id();
+        for (line, mailbox) in diagnostics.fileinto {
+            if !mailbox.eq_ignore_ascii_case("INBOX")
+                && self
+                    .jmap
+                    .mailbox_get_by_name(account_id, &mailbox)
+                    .await?
+                    .is_none()
+            {
+                warnings.push(Warning {
+                    line,
+                    message: format!("mailbox {mailbox:?} does not exist"),
+                });
+            }
+        }
+
+        if warnings.is_empty() {
+            Ok(StatusResponse::ok("Script is valid.").into_bytes())
+        } else {
+            warnings.sort_by_key(|warning| warning.line);
+            Ok(StatusResponse::ok(
+                warnings
+                    .iter()
+                    .map(|warning| warning.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            )
+            .with_code(ResponseCode::Warnings)
+            .into_bytes())
+        }
     }
 }
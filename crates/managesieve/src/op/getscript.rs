@@ -21,17 +21,19 @@
  * for more details.
 */
 
+use common::listener::SessionStream;
 use imap_proto::receiver::Request;
 use jmap::sieve::set::ObjectBlobId;
 use jmap_proto::{
     object::Object,
     types::{collection::Collection, property::Property, value::Value},
 };
-use tokio::io::{AsyncRead, AsyncWrite};
 
 use crate::core::{Command, ResponseCode, Session, StatusResponse};
 
-impl<T: AsyncRead + AsyncWrite> Session<T> {
+const CHUNK_SIZE: usize = 128 * 1024;
+
+impl<T: SessionStream> Session<T> {
     pub async fn handle_getscript(&mut self, request: Request<Command>) -> super::OpResult {
         let name = request
             .tokens
@@ -58,20 +60,40 @@ impl<T: AsyncRead + AsyncWrite> Session<T> {
             .ok_or_else(|| {
                 StatusResponse::no("Filed to retrieve blobId").with_code(ResponseCode::TryLater)
             })?;
-        let script = self
+
+        // Stream script contents in chunks
+        let mut offset = blob_section.offset_start;
+        let end = blob_section.offset_start + blob_section.size;
+        let chunk = self
             .jmap
-            .get_blob_section(&blob_hash, &blob_section)
+            .get_blob(&blob_hash, offset..std::cmp::min(offset + CHUNK_SIZE, end))
             .await?
             .ok_or_else(|| {
                 StatusResponse::no("Script blob not found").with_code(ResponseCode::NonExistent)
             })?;
-        debug_assert_eq!(script.len(), blob_section.size);
-
-        let mut response = Vec::with_capacity(script.len() + 30);
+        let mut response = Vec::with_capacity(chunk.len() + 30);
         response.push(b'{');
         response.extend_from_slice(blob_section.size.to_string().as_bytes());
         response.extend_from_slice(b"}\r\n");
-        response.extend(script);
+        response.extend(chunk);
+
+        loop {
+            offset += CHUNK_SIZE;
+            if offset >= end {
+                break;
+            }
+
+            self.write(&response)
+                .await
+                .map_err(|_| StatusResponse::bye("Failed to write script."))?;
+            response = self
+                .jmap
+                .get_blob(&blob_hash, offset..std::cmp::min(offset + CHUNK_SIZE, end))
+                .await
+                .ok()
+                .flatten()
+                .ok_or_else(|| StatusResponse::bye("Failed to retrieve script."))?;
+        }
 
         Ok(StatusResponse::ok("").serialize(response))
     }
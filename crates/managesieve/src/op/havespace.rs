@@ -46,7 +46,9 @@ impl<T: AsyncRead + AsyncWrite> Session<T> {
         self.validate_name(account_id, &name).await?;
 
         // Validate quota
-        if access_token.quota == 0
+        if size > self.jmap.core.managesieve.max_script_size {
+            Err(StatusResponse::no("Script is too large.").with_code(ResponseCode::QuotaMaxSize))
+        } else if access_token.quota == 0
             || size as i64 + self.jmap.get_used_quota(account_id).await?
                 <= access_token.quota as i64
         {
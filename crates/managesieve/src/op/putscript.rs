@@ -22,17 +22,12 @@
 */
 
 use imap_proto::receiver::Request;
-use jmap::sieve::set::{ObjectBlobId, SCHEMA};
 use jmap_proto::{
-    object::{index::ObjectIndexBuilder, Object},
-    types::{blob::BlobId, collection::Collection, property::Property, value::Value},
+    error::method::MethodError,
+    types::{collection::Collection, property::Property},
 };
 use sieve::compiler::ErrorType;
-use store::{
-    query::Filter,
-    write::{assert::HashedValue, log::LogInsert, BatchBuilder, BlobOp, DirectoryClass},
-    BlobClass,
-};
+use store::query::Filter;
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use crate::core::{Command, ResponseCode, Session, StatusResponse};
@@ -50,13 +45,20 @@ impl<T: AsyncRead + AsyncWrite> Session<T> {
             .next()
             .ok_or_else(|| StatusResponse::no("Expected script as a parameter."))?
             .unwrap_bytes();
-        let script_size = script_bytes.len() as i64;
+        let script_size = script_bytes.len();
+
+        // Check script size
+        if script_size > self.jmap.core.managesieve.max_script_size {
+            return Err(
+                StatusResponse::no("Script is too large.").with_code(ResponseCode::QuotaMaxSize)
+            );
+        }
 
         // Check quota
         let access_token = self.state.access_token();
         let account_id = access_token.primary_id();
         if access_token.quota > 0
-            && script_bytes.len() as i64 + self.jmap.get_used_quota(account_id).await?
+            && script_size as i64 + self.jmap.get_used_quota(account_id).await?
                 > access_token.quota as i64
         {
             return Err(StatusResponse::no("Quota exceeded.").with_code(ResponseCode::Quota));
@@ -94,116 +96,18 @@ impl<T: AsyncRead + AsyncWrite> Session<T> {
             }
         }
 
-        // Validate name
-        if let Some(document_id) = self.validate_name(account_id, &name).await? {
-            // Obtain script values
-            let script = self
-                .jmap
-                .get_property::<HashedValue<Object<Value>>>(
-                    account_id,
-                    Collection::SieveScript,
-                    document_id,
-                    Property::Value,
-                )
-                .await?
-                .ok_or_else(|| {
+        // Validate name and write script
+        let document_id = self.validate_name(account_id, &name).await?;
+        self.jmap
+            .sieve_script_put(account_id, document_id, name, script_bytes, script_size)
+            .await
+            .map_err(|err| match err {
+                MethodError::NotFound => {
                     StatusResponse::no("Script not found").with_code(ResponseCode::NonExistent)
-                })?;
-            let prev_blob_id = script.inner.blob_id().ok_or_else(|| {
-                StatusResponse::no("Internal error while obtaining blobId")
-                    .with_code(ResponseCode::TryLater)
+                }
+                err => err.into(),
             })?;
 
-            // Write script blob
-            let blob_id = BlobId::new(
-                self.jmap
-                    .put_blob(account_id, &script_bytes, false)
-                    .await?
-                    .hash,
-                BlobClass::Linked {
-                    account_id,
-                    collection: Collection::SieveScript.into(),
-                    document_id,
-                },
-            )
-            .with_section_size(script_size as usize);
-
-            // Write record
-            let mut batch = BatchBuilder::new();
-            batch
-                .with_account_id(account_id)
-                .with_collection(Collection::SieveScript)
-                .update_document(document_id)
-                .clear(BlobOp::Link {
-                    hash: prev_blob_id.hash.clone(),
-                })
-                .set(
-                    BlobOp::Link {
-                        hash: blob_id.hash.clone(),
-                    },
-                    Vec::new(),
-                );
-
-            // Update quota
-            let prev_script_size = prev_blob_id.section.as_ref().unwrap().size as i64;
-            let update_quota = match script_size.cmp(&prev_script_size) {
-                std::cmp::Ordering::Greater => script_size - prev_script_size,
-                std::cmp::Ordering::Less => -prev_script_size + script_size,
-                std::cmp::Ordering::Equal => 0,
-            };
-            if update_quota != 0 {
-                batch.add(DirectoryClass::UsedQuota(account_id), update_quota);
-            }
-
-            batch.custom(
-                ObjectIndexBuilder::new(SCHEMA)
-                    .with_current(script)
-                    .with_changes(
-                        Object::with_capacity(1)
-                            .with_property(Property::BlobId, Value::BlobId(blob_id)),
-                    ),
-            );
-            self.jmap.write_batch(batch).await?;
-        } else {
-            // Write script blob
-            let blob_id = BlobId::new(
-                self.jmap
-                    .put_blob(account_id, &script_bytes, false)
-                    .await?
-                    .hash,
-                BlobClass::Linked {
-                    account_id,
-                    collection: Collection::SieveScript.into(),
-                    document_id: 0,
-                },
-            )
-            .with_section_size(script_size as usize);
-
-            // Write record
-            let mut batch = BatchBuilder::new();
-            batch
-                .with_account_id(account_id)
-                .with_collection(Collection::SieveScript)
-                .create_document()
-                .log(LogInsert())
-                .add(DirectoryClass::UsedQuota(account_id), script_size)
-                .set(
-                    BlobOp::Link {
-                        hash: blob_id.hash.clone(),
-                    },
-                    Vec::new(),
-                )
-                .custom(
-                    ObjectIndexBuilder::new(SCHEMA).with_changes(
-                        Object::with_capacity(3)
-                            .with_property(Property::Name, name)
-                            .with_property(Property::IsActive, Value::Bool(false))
-                            .with_property(Property::BlobId, Value::BlobId(blob_id)),
-                    ),
-                );
-            self.jmap.write_batch(batch).await?;
-        }
-
         Ok(StatusResponse::ok("Success.").into_bytes())
     }
 
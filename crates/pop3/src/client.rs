@@ -143,8 +143,8 @@ impl<T: SessionStream> Session<T> {
                         Command::Auth { mechanism, params } => {
                             self.handle_sasl(mechanism, params).await?;
                         }
-                        Command::Apop { .. } => {
-                            self.write_err("APOP not supported.").await?;
+                        Command::Apop { name, digest } => {
+                            self.handle_apop(name, digest).await?;
                         }
                     },
                     Err(err) => {
@@ -152,6 +152,16 @@ impl<T: SessionStream> Session<T> {
                     }
                 },
                 Err(err) => {
+                    if self
+                        .jmap
+                        .core
+                        .is_abuse_banned(self.remote_addr)
+                        .await
+                        .unwrap_or_default()
+                    {
+                        self.write_err("Too many invalid commands.").await?;
+                        return Err(());
+                    }
                     self.write_err(err).await?;
                 }
             }
@@ -23,7 +23,7 @@
 
 use std::{net::IpAddr, sync::Arc};
 
-use common::listener::{limiter::InFlight, ServerInstance, SessionStream};
+use common::listener::{limiter::InFlight, qos::QosPermit, ServerInstance, SessionStream};
 use imap::core::{ImapInstance, Inner};
 use jmap::JMAP;
 use mailbox::Mailbox;
@@ -35,7 +35,7 @@ pub mod op;
 pub mod protocol;
 pub mod session;
 
-static SERVER_GREETING: &str = "+OK Stalwart POP3 at your service.\r\n";
+static SERVER_GREETING: &str = "Stalwart POP3 at your service.";
 
 #[derive(Clone)]
 pub struct Pop3SessionManager {
@@ -56,7 +56,9 @@ pub struct Session<T: SessionStream> {
     pub state: State,
     pub stream: T,
     pub in_flight: InFlight,
+    pub qos: QosPermit,
     pub remote_addr: IpAddr,
+    pub apop_timestamp: String,
     pub span: tracing::Span,
 }
 
@@ -26,7 +26,7 @@ use common::{
     AuthResult,
 };
 use imap::op::authenticate::{decode_challenge_oauth, decode_challenge_plain};
-use jmap::auth::rate_limit::ConcurrencyLimiters;
+use jmap::auth::{rate_limit::ConcurrencyLimiters, AccessToken};
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
 use std::sync::Arc;
@@ -80,23 +80,28 @@ impl<T: SessionStream> Session<T> {
         }
     }
 
-    pub async fn handle_auth(&mut self, credentials: Credentials<String>) -> Result<(), ()> {
-        // Throttle authentication requests
-        if self
+    pub async fn handle_apop(&mut self, username: String, digest: String) -> Result<(), ()> {
+        self.is_auth_allowed().await?;
+
+        let access_token = match self
             .jmap
-            .is_auth_allowed_soft(&self.remote_addr)
+            .authenticate_apop(&username, &self.apop_timestamp, &digest, self.remote_addr)
             .await
-            .is_err()
         {
-            tracing::debug!(parent: &self.span,
-                event = "disconnect",
-                "Too many authentication attempts, disconnecting.",
-            );
+            AuthResult::Success(token) => Some(token),
+            AuthResult::Failure => None,
+            AuthResult::Banned => {
+                self.write_err("Too many authentication requests from this IP address.")
+                    .await?;
+                return Err(());
+            }
+        };
 
-            self.write_err("Too many authentication requests from this IP address.")
-                .await?;
-            return Err(());
-        }
+        self.handle_access_token(access_token).await
+    }
+
+    pub async fn handle_auth(&mut self, credentials: Credentials<String>) -> Result<(), ()> {
+        self.is_auth_allowed().await?;
 
         // Authenticate
         let access_token = match credentials {
@@ -135,6 +140,46 @@ impl<T: SessionStream> Session<T> {
             }
         };
 
+        self.handle_access_token(access_token).await
+    }
+
+    async fn is_auth_allowed(&mut self) -> Result<(), ()> {
+        // Throttle authentication requests
+        if self
+            .jmap
+            .is_auth_allowed_soft(&self.remote_addr)
+            .await
+            .is_err()
+        {
+            tracing::debug!(parent: &self.span,
+                event = "disconnect",
+                "Too many authentication attempts, disconnecting.",
+            );
+
+            self.write_err("Too many authentication requests from this IP address.")
+                .await?;
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn handle_access_token(&mut self, access_token: Option<AccessToken>) -> Result<(), ()> {
+        // Make sure the account is allowed to use this protocol
+        let access_token = access_token.filter(|access_token| {
+            if access_token.is_protocol_allowed("pop3") {
+                true
+            } else {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "authenticate",
+                    account_id = access_token.primary_id(),
+                    "Account is not allowed to access POP3."
+                );
+                false
+            }
+        });
+
         if let Some(access_token) = access_token {
             // Enforce concurrency limits
             let in_flight = match self
@@ -157,6 +202,9 @@ impl<T: SessionStream> Session<T> {
             let access_token = Arc::new(access_token);
             self.jmap.cache_access_token(access_token.clone());
 
+            // Move the connection to the authenticated class
+            self.qos.promote();
+
             // Fetch mailbox
             match self.fetch_mailbox(access_token.primary_id()).await {
                 Ok(mailbox) => {
@@ -52,14 +52,20 @@ impl SessionManager for Pop3SessionManager {
                 },
                 stream: session.stream,
                 in_flight: session.in_flight,
+                qos: session.qos,
                 remote_addr: session.remote_ip,
+                apop_timestamp: format!(
+                    "<{}.{}@stalwart>",
+                    std::process::id(),
+                    store::rand::random::<u64>()
+                ),
                 span: session.span,
             };
 
-            if session
-                .write_bytes(SERVER_GREETING.as_bytes())
-                .await
-                .is_ok()
+            let greeting =
+                Response::Ok::<u32>(format!("{SERVER_GREETING} {}", session.apop_timestamp).into())
+                    .serialize();
+            if session.write_bytes(greeting).await.is_ok()
                 && session.handle_conn().await
                 && session.instance.acceptor.is_tls()
             {
@@ -140,7 +146,9 @@ impl<T: SessionStream> Session<T> {
             state: self.state,
             span: self.span,
             in_flight: self.in_flight,
+            qos: self.qos,
             remote_addr: self.remote_addr,
+            apop_timestamp: self.apop_timestamp,
         })
     }
 }
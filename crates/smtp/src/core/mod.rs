@@ -29,9 +29,13 @@ use std::{
 };
 
 use common::{
-    config::smtp::auth::VerifyStrategy,
+    config::smtp::{
+        auth::VerifyStrategy,
+        dnsbl::{DnsblResult, UrlReputation},
+    },
     listener::{
         limiter::{ConcurrencyLimiter, InFlight},
+        qos::QosPermit,
         ServerInstance,
     },
     Core, DeliveryEvent, SharedCore,
@@ -52,6 +56,7 @@ use utils::snowflake::SnowflakeIdGenerator;
 
 use crate::{
     inbound::auth::SaslToken,
+    outbound::pool::{ConnectionPool, PoolKey, PooledClient},
     queue::{self, DomainPart, QueueId},
     reporting,
 };
@@ -102,6 +107,7 @@ pub struct Inner {
     pub report_tx: mpsc::Sender<reporting::Event>,
     pub snowflake_id: SnowflakeIdGenerator,
     pub connectors: TlsConnectors,
+    pub connection_pool: ConnectionPool<PoolKey, PooledClient>,
     #[cfg(feature = "local_delivery")]
     pub delivery_tx: mpsc::Sender<DeliveryEvent>,
 }
@@ -132,6 +138,7 @@ pub struct Session<T: AsyncWrite + AsyncRead> {
     pub data: SessionData,
     pub params: SessionParameters,
     pub in_flight: Vec<InFlight>,
+    pub qos: QosPermit,
 }
 
 pub struct SessionData {
@@ -147,6 +154,7 @@ pub struct SessionData {
     pub rcpt_to: Vec<SessionAddress>,
     pub rcpt_errors: usize,
     pub message: Vec<u8>,
+    pub bdat_failed: bool,
 
     pub authenticated_as: String,
     pub authenticated_emails: Vec<String>,
@@ -163,7 +171,9 @@ pub struct SessionData {
     pub iprev: Option<IprevOutput>,
     pub spf_ehlo: Option<SpfOutput>,
     pub spf_mail_from: Option<SpfOutput>,
-    pub dnsbl_error: Option<Vec<u8>>,
+    pub dnsbl_ip: Option<Arc<DnsblResult>>,
+    pub dnsbl_domain: Option<Arc<DnsblResult>>,
+    pub url_reputation: Option<UrlReputation>,
 }
 
 #[derive(Clone)]
@@ -199,6 +209,7 @@ pub struct SessionParameters {
     pub can_expn: bool,
     pub can_vrfy: bool,
     pub max_message_size: usize,
+    pub max_header_size: usize,
 
     // Mail authentication parameters
     pub iprev: VerifyStrategy,
@@ -224,6 +235,7 @@ impl SessionData {
             valid_until: Instant::now(),
             rcpt_errors: 0,
             message: Vec::with_capacity(0),
+            bdat_failed: false,
             auth_errors: 0,
             messages_sent: 0,
             bytes_left: 0,
@@ -232,7 +244,9 @@ impl SessionData {
             iprev: None,
             spf_ehlo: None,
             spf_mail_from: None,
-            dnsbl_error: None,
+            dnsbl_ip: None,
+            dnsbl_domain: None,
+            url_reputation: None,
         }
     }
 }
@@ -288,6 +302,7 @@ static ref SIEVE: Arc<ServerInstance> = Arc::new(ServerInstance {
     protocol: common::config::server::ServerProtocol::Lmtp,
     acceptor: common::listener::TcpAcceptor::Plain,
     limiter: ConcurrencyLimiter::new(0),
+    qos: Default::default(),
     shutdown_rx: tokio::sync::watch::channel(false).1,
     proxy_networks: vec![],
 });
@@ -331,6 +346,7 @@ impl Session<common::listener::stream::NullIo> {
                 rcpt_max: Default::default(),
                 rcpt_dsn: Default::default(),
                 max_message_size: Default::default(),
+                max_header_size: Default::default(),
                 auth_match_sender: false,
                 iprev: VerifyStrategy::Disable,
                 spf_ehlo: VerifyStrategy::Disable,
@@ -339,6 +355,7 @@ impl Session<common::listener::stream::NullIo> {
                 can_vrfy: false,
             },
             in_flight: vec![],
+            qos: Default::default(),
         }
     }
 
@@ -389,6 +406,7 @@ impl SessionData {
             rcpt_to,
             rcpt_errors: 0,
             message,
+            bdat_failed: false,
             authenticated_as: "local".into(),
             authenticated_emails: vec![],
             auth_errors: 0,
@@ -401,7 +419,9 @@ impl SessionData {
             iprev: None,
             spf_ehlo: None,
             spf_mail_from: None,
-            dnsbl_error: None,
+            dnsbl_ip: None,
+            dnsbl_domain: None,
+            url_reputation: None,
         }
     }
 }
@@ -444,6 +464,7 @@ impl Default for Inner {
                 pki_verify: mail_send::smtp::tls::build_tls_connector(false),
                 dummy_verify: mail_send::smtp::tls::build_tls_connector(true),
             },
+            connection_pool: Default::default(),
             delivery_tx: mpsc::channel(1).0,
         }
     }
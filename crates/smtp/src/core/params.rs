@@ -189,5 +189,11 @@ impl<T: SessionStream> Session<T> {
             .eval_if(&self.core.core.smtp.session.data.max_message_size, self)
             .await
             .unwrap_or(25 * 1024 * 1024);
+        self.params.max_header_size = self
+            .core
+            .core
+            .eval_if(&self.core.core.smtp.session.data.max_header_size, self)
+            .await
+            .unwrap_or(512 * 1024);
     }
 }
@@ -22,12 +22,12 @@
 */
 
 use common::{
-    config::smtp::{queue::QueueQuota, *},
+    config::smtp::{queue::QueueQuota, session::SendingLimit, *},
     expr::{functions::ResolveVariable, *},
     listener::{limiter::ConcurrencyLimiter, SessionStream},
 };
 use dashmap::mapref::entry::Entry;
-use utils::config::Rate;
+use utils::{config::Rate, metrics::SENDING_LIMITS_EXCEEDED};
 
 use std::hash::{BuildHasher, Hash, Hasher};
 
@@ -317,4 +317,64 @@ impl<T: SessionStream> Session<T> {
             .unwrap_or_default()
             .is_none()
     }
+
+    pub async fn is_sending_allowed(&self) -> bool {
+        let limits = SendingLimit::applicable(
+            &self.core.core.smtp.session.data.sending_limits,
+            &self.data.authenticated_as,
+        );
+        if limits.is_empty() {
+            return true;
+        }
+
+        let account = self.data.authenticated_as.to_lowercase();
+        let num_rcpts = self.data.rcpt_to.len() as u64;
+        let mut checks = Vec::with_capacity(limits.len() * 2);
+        for limit in limits {
+            for (name, rate, cost) in [
+                ("messages", &limit.messages, 1),
+                ("recipients", &limit.recipients, num_rcpts),
+            ] {
+                if let Some(rate) = rate.as_ref().filter(|rate| rate.requests > 0) {
+                    let mut hasher = blake3::Hasher::new();
+                    hasher.update(account.as_bytes());
+                    hasher.update(limit.id.as_bytes());
+                    hasher.update(name.as_bytes());
+                    hasher.update(&rate.period.as_secs().to_ne_bytes()[..]);
+                    checks.push((name, rate, cost, hasher.finalize()));
+                }
+            }
+        }
+
+        // Verify all limits before consuming any of them
+        let lookup = &self.core.core.storage.lookup;
+        for (name, rate, cost, key) in &checks {
+            if lookup
+                .is_cost_allowed(key.as_bytes(), rate, *cost, true)
+                .await
+                .unwrap_or_default()
+                .is_some()
+            {
+                SENDING_LIMITS_EXCEEDED.increment(name);
+                tracing::warn!(
+                    parent: &self.span,
+                    context = "sending-limit",
+                    event = "exceeded",
+                    account = self.data.authenticated_as,
+                    limit = name,
+                    max_requests = rate.requests,
+                    max_interval = rate.period.as_secs(),
+                    "Account exceeded its sending limit, it might be compromised."
+                );
+                return false;
+            }
+        }
+        for (_, rate, cost, key) in &checks {
+            let _ = lookup
+                .is_cost_allowed(key.as_bytes(), rate, *cost, false)
+                .await;
+        }
+
+        true
+    }
 }
@@ -0,0 +1,114 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::borrow::Cow;
+
+use common::{
+    config::smtp::auth::{ArcSealer, VerifyStrategy},
+    listener::SessionStream,
+};
+use mail_auth::{
+    common::headers::HeaderWriter, ArcOutput, AuthenticatedMessage, AuthenticationResults,
+    DkimResult,
+};
+
+use crate::core::Session;
+
+use super::ArcSeal;
+
+impl<T: SessionStream> Session<T> {
+    pub async fn verify_arc_chain<'x>(
+        &self,
+        auth_message: &'x AuthenticatedMessage<'x>,
+        strategy: VerifyStrategy,
+    ) -> Result<ArcOutput<'x>, Cow<'static, [u8]>> {
+        let arc_output = self
+            .core
+            .core
+            .smtp
+            .resolvers
+            .dns
+            .verify_arc(auth_message)
+            .await;
+
+        if strategy.is_strict()
+            && !matches!(arc_output.result(), DkimResult::Pass | DkimResult::None)
+        {
+            tracing::info!(parent: &self.span,
+                context = "arc",
+                event = "auth-failed",
+                return_path = self.data.mail_from.as_ref().unwrap().address,
+                from = auth_message.from(),
+                result = %arc_output.result(),
+                "ARC validation failed.");
+
+            Err(if matches!(arc_output.result(), DkimResult::TempError(_)) {
+                (&b"451 4.7.29 ARC validation failed.\r\n"[..]).into()
+            } else {
+                (&b"550 5.7.29 ARC validation failed.\r\n"[..]).into()
+            })
+        } else {
+            tracing::debug!(parent: &self.span,
+                context = "arc",
+                event = "verify",
+                return_path = self.data.mail_from.as_ref().unwrap().address,
+                from = auth_message.from(),
+                result = %arc_output.result());
+
+            Ok(arc_output)
+        }
+    }
+
+    pub fn seal_arc_chain(
+        &self,
+        arc_sealer: &ArcSealer,
+        auth_message: &AuthenticatedMessage<'_>,
+        auth_results: &AuthenticationResults<'_>,
+        arc_output: &ArcOutput<'_>,
+        headers: &mut Vec<u8>,
+    ) {
+        // Chains that already failed validation must not be extended (RFC 8617 section 5.1.2)
+        if !arc_output.can_be_sealed() {
+            return;
+        }
+
+        match arc_sealer.seal(auth_message, auth_results, arc_output) {
+            Ok(set) => {
+                set.write_header(headers);
+                tracing::debug!(parent: &self.span,
+                    context = "arc",
+                    event = "seal",
+                    return_path = self.data.mail_from.as_ref().unwrap().address_lcase,
+                    from = auth_message.from());
+            }
+            Err(err) => {
+                tracing::info!(parent: &self.span,
+                    context = "arc",
+                    event = "seal-failed",
+                    return_path = self.data.mail_from.as_ref().unwrap().address_lcase,
+                    from = auth_message.from(),
+                    "Failed to seal message: {}", err);
+            }
+        }
+    }
+}
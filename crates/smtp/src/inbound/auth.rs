@@ -21,16 +21,25 @@
  * for more details.
 */
 
-use common::{listener::SessionStream, AuthResult};
+use common::{
+    listener::SessionStream,
+    sasl::{SaslExchange, SaslMechanism, SaslStep},
+    AuthResult,
+};
+use directory::Principal;
 use mail_parser::decoders::base64::base64_decode;
 use mail_send::Credentials;
-use smtp_proto::{IntoString, AUTH_LOGIN, AUTH_OAUTHBEARER, AUTH_PLAIN, AUTH_XOAUTH2};
+use smtp_proto::{
+    IntoString, AUTH_CRAM_MD5, AUTH_LOGIN, AUTH_OAUTHBEARER, AUTH_PLAIN, AUTH_SCRAM_SHA_256,
+    AUTH_SCRAM_SHA_256_PLUS, AUTH_XOAUTH2,
+};
 
 use crate::core::Session;
 
 pub struct SaslToken {
     mechanism: u64,
     credentials: Credentials<String>,
+    exchange: Option<SaslExchange>,
 }
 
 impl SaslToken {
@@ -42,6 +51,8 @@ impl SaslToken {
                     username: String::new(),
                     secret: String::new(),
                 },
+
+                exchange: None,
             }
             .into(),
             AUTH_OAUTHBEARER => SaslToken {
@@ -49,6 +60,8 @@ impl SaslToken {
                 credentials: Credentials::OAuthBearer {
                     token: String::new(),
                 },
+
+                exchange: None,
             }
             .into(),
             AUTH_XOAUTH2 => SaslToken {
@@ -57,6 +70,17 @@ impl SaslToken {
                     username: String::new(),
                     secret: String::new(),
                 },
+
+                exchange: None,
+            }
+            .into(),
+            AUTH_SCRAM_SHA_256 | AUTH_SCRAM_SHA_256_PLUS | AUTH_CRAM_MD5 => SaslToken {
+                mechanism,
+                credentials: Credentials::Plain {
+                    username: String::new(),
+                    secret: String::new(),
+                },
+                exchange: None,
             }
             .into(),
             _ => None,
@@ -70,6 +94,10 @@ impl<T: SessionStream> Session<T> {
         token: &mut SaslToken,
         response: &[u8],
     ) -> Result<bool, ()> {
+        if let Some(mechanism) = sasl_mechanism(token.mechanism) {
+            return self.handle_sasl_exchange(token, mechanism, response).await;
+        }
+
         if response.is_empty() {
             match (token.mechanism, &token.credentials) {
                 (AUTH_PLAIN | AUTH_XOAUTH2 | AUTH_OAUTHBEARER, _) => {
@@ -173,6 +201,61 @@ impl<T: SessionStream> Session<T> {
         self.auth_error(b"500 5.5.6 Invalid challenge.\r\n").await
     }
 
+    async fn handle_sasl_exchange(
+        &mut self,
+        token: &mut SaslToken,
+        mechanism: SaslMechanism,
+        response: &[u8],
+    ) -> Result<bool, ()> {
+        let directory = if let Some(directory) = &self.params.auth_directory {
+            directory.clone()
+        } else {
+            return self.authenticate(Credentials::default()).await;
+        };
+
+        // An empty line is only an empty response once the exchange started
+        let response = if response.is_empty() {
+            token.exchange.as_ref().map(|_| vec![])
+        } else if let Some(response) = base64_decode(response) {
+            Some(response)
+        } else {
+            return self.auth_error(b"500 5.5.6 Invalid challenge.\r\n").await;
+        };
+        let exchange = token
+            .exchange
+            .get_or_insert_with(|| SaslExchange::new(mechanism, self.stream.tls_exporter()));
+
+        match self
+            .core
+            .core
+            .sasl_step(
+                &directory,
+                exchange,
+                response.as_deref(),
+                self.data.remote_ip,
+            )
+            .await
+        {
+            SaslStep::Challenge(challenge) => {
+                self.write(format!("334 {challenge}\r\n").as_bytes())
+                    .await?;
+                Ok(true)
+            }
+            SaslStep::Done(result) => self.handle_auth_result(None, result).await,
+            SaslStep::Invalid(err) => {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "auth",
+                    event = "invalid",
+                    mechanism = mechanism.as_str(),
+                    reason = err,
+                );
+
+                self.auth_error(b"500 5.5.6 Invalid challenge.\r\n").await
+            }
+        }
+    }
+
     pub async fn authenticate(&mut self, credentials: Credentials<String>) -> Result<bool, ()> {
         if let Some(directory) = &self.params.auth_directory {
             let authenticated_as = match &credentials {
@@ -180,55 +263,13 @@ impl<T: SessionStream> Session<T> {
                 | Credentials::XOauth2 { username, .. }
                 | Credentials::OAuthBearer { token: username } => username.to_string(),
             };
-            match self
+            let result = self
                 .core
                 .core
                 .authenticate(directory, &credentials, self.data.remote_ip, false)
+                .await;
+            self.handle_auth_result(authenticated_as.into(), result)
                 .await
-            {
-                Ok(AuthResult::Success(principal)) => {
-                    tracing::debug!(
-                        parent: &self.span,
-                        context = "auth",
-                        event = "authenticate",
-                        result = "success"
-                    );
-
-                    self.data.authenticated_as = authenticated_as.to_lowercase();
-                    self.data.authenticated_emails = principal
-                        .emails
-                        .into_iter()
-                        .map(|e| e.trim().to_lowercase())
-                        .collect();
-                    self.eval_post_auth_params().await;
-                    self.write(b"235 2.7.0 Authentication succeeded.\r\n")
-                        .await?;
-                    return Ok(false);
-                }
-                Ok(AuthResult::Failure) => {
-                    tracing::debug!(
-                        parent: &self.span,
-                        context = "auth",
-                        event = "authenticate",
-                        result = "failed"
-                    );
-
-                    return self
-                        .auth_error(b"535 5.7.8 Authentication credentials invalid.\r\n")
-                        .await;
-                }
-                Ok(AuthResult::Banned) => {
-                    tracing::debug!(
-                        parent: &self.span,
-                        context = "auth",
-                        event = "authenticate",
-                        result = "banned"
-                    );
-
-                    return Err(());
-                }
-                _ => (),
-            }
         } else {
             tracing::warn!(
                 parent: &self.span,
@@ -236,6 +277,76 @@ impl<T: SessionStream> Session<T> {
                 event = "error",
                 "No lookup list configured for authentication."
             );
+            self.write(b"454 4.7.0 Temporary authentication failure\r\n")
+                .await?;
+
+            Ok(false)
+        }
+    }
+
+    async fn handle_auth_result(
+        &mut self,
+        authenticated_as: Option<String>,
+        result: directory::Result<AuthResult<Principal<u32>>>,
+    ) -> Result<bool, ()> {
+        match result {
+            Ok(AuthResult::Success(principal)) if !principal.is_protocol_allowed("smtp") => {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "auth",
+                    event = "authenticate",
+                    result = "protocol-not-allowed"
+                );
+
+                return self
+                    .auth_error(b"535 5.7.8 Authentication credentials invalid.\r\n")
+                    .await;
+            }
+            Ok(AuthResult::Success(principal)) => {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "auth",
+                    event = "authenticate",
+                    result = "success"
+                );
+
+                self.data.authenticated_as = authenticated_as
+                    .unwrap_or_else(|| principal.name.clone())
+                    .to_lowercase();
+                self.qos.promote();
+                self.data.authenticated_emails = principal
+                    .emails
+                    .into_iter()
+                    .map(|e| e.trim().to_lowercase())
+                    .collect();
+                self.eval_post_auth_params().await;
+                self.write(b"235 2.7.0 Authentication succeeded.\r\n")
+                    .await?;
+                return Ok(false);
+            }
+            Ok(AuthResult::Failure) => {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "auth",
+                    event = "authenticate",
+                    result = "failed"
+                );
+
+                return self
+                    .auth_error(b"535 5.7.8 Authentication credentials invalid.\r\n")
+                    .await;
+            }
+            Ok(AuthResult::Banned) => {
+                tracing::debug!(
+                    parent: &self.span,
+                    context = "auth",
+                    event = "authenticate",
+                    result = "banned"
+                );
+
+                return Err(());
+            }
+            _ => (),
         }
         self.write(b"454 4.7.0 Temporary authentication failure\r\n")
             .await?;
@@ -262,3 +373,12 @@ impl<T: SessionStream> Session<T> {
         }
     }
 }
+
+fn sasl_mechanism(mechanism: u64) -> Option<SaslMechanism> {
+    match mechanism {
+        AUTH_SCRAM_SHA_256 => Some(SaslMechanism::ScramSha256),
+        AUTH_SCRAM_SHA_256_PLUS => Some(SaslMechanism::ScramSha256Plus),
+        AUTH_CRAM_MD5 => Some(SaslMechanism::CramMd5),
+        _ => None,
+    }
+}
@@ -36,6 +36,7 @@ use mail_auth::{
     dmarc, AuthenticatedMessage, AuthenticationResults, DkimResult, DmarcResult, ReceivedSpf,
 };
 use mail_builder::headers::{date::Date, message_id::generate_message_id_header};
+use mail_parser::MessageParser;
 use sieve::runtime::Variable;
 use smtp_proto::{
     MAIL_BY_RETURN, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
@@ -46,14 +47,23 @@ use utils::config::Rate;
 
 use crate::{
     core::{Session, SessionAddress, State},
-    queue::{self, Message, QueueEnvelope, Schedule},
+    queue::{
+        self,
+        timeline::{Disposition, TimelineEventType},
+        DomainPart, Message, QueueEnvelope, Schedule,
+    },
     scripts::ScriptResult,
 };
 
-use super::{ArcSeal, AuthResult, DkimSign};
+use super::{rspamd::RspamdAction, AuthResult, DkimSign};
 
 impl<T: SessionStream> Session<T> {
     pub async fn queue_message(&mut self) -> Cow<'static, [u8]> {
+        // Enforce per-account sending limits
+        if !self.data.authenticated_as.is_empty() && !self.is_sending_allowed().await {
+            return (&b"451 4.7.1 Sending limit exceeded, try again later.\r\n"[..]).into();
+        }
+
         // Authenticate message
         let raw_message = Arc::new(std::mem::take(&mut self.data.message));
         let auth_message = if let Some(auth_message) = AuthenticatedMessage::parse_with_opts(
@@ -88,8 +98,7 @@ impl<T: SessionStream> Session<T> {
                 return_path = self.data.mail_from.as_ref().unwrap().address,
                 from = auth_message.from(),
                 received_headers = auth_message.received_headers_count());
-            return (&b"450 4.4.6 Too many Received headers. Possible loop detected.\r\n"[..])
-                .into();
+            return (&b"554 5.4.6 Too many Received headers. Mail loop detected.\r\n"[..]).into();
         }
 
         // Verify DKIM
@@ -174,40 +183,10 @@ impl<T: SessionStream> Session<T> {
             .await
             .and_then(|name| self.core.core.get_arc_sealer(&name));
         let arc_output = if arc.verify() || arc_sealer.is_some() {
-            let arc_output = self
-                .core
-                .core
-                .smtp
-                .resolvers
-                .dns
-                .verify_arc(&auth_message)
-                .await;
-
-            if arc.is_strict()
-                && !matches!(arc_output.result(), DkimResult::Pass | DkimResult::None)
-            {
-                tracing::info!(parent: &self.span,
-                    context = "arc",
-                    event = "auth-failed",
-                    return_path = self.data.mail_from.as_ref().unwrap().address,
-                    from = auth_message.from(),
-                    result = %arc_output.result(),
-                    "ARC validation failed.");
-
-                return if matches!(arc_output.result(), DkimResult::TempError(_)) {
-                    (&b"451 4.7.29 ARC validation failed.\r\n"[..]).into()
-                } else {
-                    (&b"550 5.7.29 ARC validation failed.\r\n"[..]).into()
-                };
-            } else {
-                tracing::debug!(parent: &self.span,
-                    context = "arc",
-                    event = "verify",
-                    return_path = self.data.mail_from.as_ref().unwrap().address,
-                    from = auth_message.from(),
-                    result = %arc_output.result());
+            match self.verify_arc_chain(&auth_message, arc).await {
+                Ok(arc_output) => arc_output.into(),
+                Err(response) => return response,
             }
-            arc_output.into()
         } else {
             None
         };
@@ -236,6 +215,9 @@ impl<T: SessionStream> Session<T> {
         if let Some(iprev) = &self.data.iprev {
             auth_results = auth_results.with_iprev_result(iprev, self.data.remote_ip);
         }
+        if let Some(arc_output) = &arc_output {
+            auth_results = auth_results.with_arc_result(arc_output, self.data.remote_ip);
+        }
 
         // Verify DMARC
         let (dmarc_result, dmarc_policy) = match &self.data.spf_mail_from {
@@ -378,27 +360,47 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
-        // ARC Seal
+        // ARC Seal, also applies to messages forwarded by Sieve scripts which
+        // usually skip DKIM and DMARC verification
         if let (Some(arc_sealer), Some(arc_output)) = (arc_sealer, &arc_output) {
-            if !dkim_output.is_empty() && arc_output.can_be_sealed() {
-                match arc_sealer.seal(&auth_message, &auth_results, arc_output) {
-                    Ok(set) => {
-                        set.write_header(&mut headers);
-                    }
-                    Err(err) => {
-                        tracing::info!(parent: &self.span,
-                            context = "arc",
-                            event = "seal-failed",
-                            return_path = mail_from.address_lcase,
-                            from = auth_message.from(),
-                            "Failed to seal message: {}", err);
+            self.seal_arc_chain(
+                arc_sealer,
+                &auth_message,
+                &auth_results,
+                arc_output,
+                &mut headers,
+            );
+        }
+
+        // Check the reputation of the URLs in the message
+        if self.core.core.smtp.dnsbl.has_url_checks() {
+            let result = self.core.core.url_reputation(&raw_message).await;
+            if !result.hits.is_empty() {
+                tracing::info!(parent: &self.span,
+                    context = "dnsbl",
+                    event = "url-listed",
+                    hits = ?result.hits,
+                    score = result.score);
+
+                if self.core.core.smtp.dnsbl.url_add_header {
+                    headers.extend_from_slice(b"X-Spam-URL: ");
+                    for (pos, hit) in result.hits.iter().enumerate() {
+                        if pos > 0 {
+                            headers.extend_from_slice(b",\r\n\t");
+                        }
+                        headers.extend_from_slice(hit.host.as_bytes());
+                        headers.extend_from_slice(b" (");
+                        headers.extend_from_slice(hit.list.as_bytes());
+                        headers.extend_from_slice(b")");
                     }
+                    headers.extend_from_slice(b"\r\n");
                 }
             }
+            self.data.url_reputation = result.into();
         }
 
         // Run Milter filters
-        let mut edited_message = match self.run_milters(&auth_message).await {
+        let mut modifications = match self.run_milters(&auth_message).await {
             Ok(modifications) => {
                 if !modifications.is_empty() {
                     tracing::debug!(
@@ -414,14 +416,37 @@ impl<T: SessionStream> Session<T> {
                         s
                     }),
                     "Milter filter(s) accepted message.");
+                }
+                modifications
+            }
+            Err(response) => return response,
+        };
 
-                    self.data
-                        .apply_milter_modifications(modifications, &auth_message)
-                } else {
-                    None
+        // Scan message with rspamd
+        match self.run_rspamd(&raw_message).await {
+            Ok(Some(result)) => {
+                if result.action == RspamdAction::AddHeader {
+                    headers.extend_from_slice(b"X-Spam: Yes\r\n");
+                }
+                for (name, value) in &result.add_headers {
+                    headers.extend_from_slice(name.as_bytes());
+                    headers.extend_from_slice(b": ");
+                    headers.extend_from_slice(value.as_bytes());
+                    if !value.ends_with('\n') {
+                        headers.extend_from_slice(b"\r\n");
+                    }
                 }
+                modifications.extend(result.modifications());
             }
+            Ok(None) => (),
             Err(response) => return response,
+        }
+
+        let mut edited_message = if !modifications.is_empty() {
+            self.data
+                .apply_milter_modifications(modifications, &auth_message)
+        } else {
+            None
         };
 
         // Pipe message
@@ -624,9 +649,47 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Hold messages submitted by authenticated users during the undo window
+        let mut undo_window = 0;
+        if self.data.future_release == 0 && !self.data.authenticated_as.is_empty() {
+            if let Some(window) = self
+                .core
+                .core
+                .eval_if::<Duration, _>(&dc.undo_window, self)
+                .await
+                .filter(|window| !window.is_zero())
+            {
+                undo_window = window.as_secs();
+                self.data.future_release = undo_window;
+            }
+        }
+
         // Build message
-        let mail_from = self.data.mail_from.clone().unwrap();
+        let mut mail_from = self.data.mail_from.clone().unwrap();
         let rcpt_to = std::mem::take(&mut self.data.rcpt_to);
+
+        // Rewrite the envelope sender of messages forwarded off-host
+        if self.data.authenticated_as.is_empty() {
+            if let Some(srs_address) = self
+                .core
+                .core
+                .srs_forward(
+                    &mail_from.address,
+                    rcpt_to.iter().map(|rcpt| rcpt.domain.as_str()),
+                )
+                .await
+            {
+                tracing::debug!(parent: &self.span,
+                    context = "srs",
+                    event = "rewrite",
+                    from = mail_from.address,
+                    to = srs_address);
+
+                mail_from.address_lcase = srs_address.to_lowercase();
+                mail_from.domain = mail_from.address_lcase.domain_part().to_string();
+                mail_from.address = srs_address;
+            }
+        }
         let mut message = self.build_message(mail_from, rcpt_to, message_id).await;
 
         // Add Return-Path
@@ -701,14 +764,49 @@ impl<T: SessionStream> Session<T> {
         // Verify queue quota
         if self.core.has_quota(&mut message).await {
             let queue_id = message.id;
+
+            // Record the message in the delivery timeline
+            if self.core.has_timeline() {
+                let message_id = [raw_message, headers.as_slice()]
+                    .into_iter()
+                    .find_map(|bytes| {
+                        MessageParser::new()
+                            .parse_headers(bytes)
+                            .and_then(|m| m.message_id().map(|id| id.to_string()))
+                    });
+                self.core
+                    .timeline_add(
+                        queue_id,
+                        TimelineEventType::Accepted {
+                            remote_ip: self.data.remote_ip_str.clone(),
+                            helo: self.data.helo_domain.clone(),
+                            authenticated_as: (!self.data.authenticated_as.is_empty())
+                                .then(|| self.data.authenticated_as.clone()),
+                            message_id,
+                        },
+                    )
+                    .await;
+            }
+
             if message
                 .queue(Some(&headers), raw_message, &self.core, &self.span)
                 .await
             {
                 self.state = State::Accepted(queue_id);
                 self.data.messages_sent += 1;
-                (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
+                if undo_window > 0 {
+                    format!(
+                        "250 2.0.0 Message queued as {queue_id}, can be cancelled for {undo_window} seconds.\r\n"
+                    )
+                    .into_bytes()
+                    .into()
+                } else {
+                    (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
+                }
             } else {
+                self.core
+                    .timeline_complete(queue_id, Disposition::Removed)
+                    .await;
                 (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into()
             }
         } else {
@@ -747,6 +845,7 @@ impl<T: SessionStream> Session<T> {
             env_id: mail_from.dsn_info,
             blob_hash: Default::default(),
             quota_keys: Vec::new(),
+            failures: Vec::new(),
         };
 
         // Add recipients
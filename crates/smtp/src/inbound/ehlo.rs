@@ -199,6 +199,9 @@ impl<T: SessionStream> Session<T> {
                 .await
                 .unwrap_or_default()
                 .into();
+            if self.stream.tls_exporter().is_none() {
+                response.auth_mechanisms &= !AUTH_SCRAM_SHA_256_PLUS;
+            }
             if response.auth_mechanisms != 0 {
                 response.capabilities |= EXT_AUTH;
             }
@@ -0,0 +1,174 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::net::IpAddr;
+
+use common::listener::SessionStream;
+use store::write::now;
+
+use crate::core::Session;
+
+impl<T: SessionStream> Session<T> {
+    pub async fn is_greylisted(&self) -> bool {
+        let config = &self.core.core.smtp.session.rcpt.greylist;
+        if !self
+            .core
+            .core
+            .eval_if(&config.enable, self)
+            .await
+            .unwrap_or(false)
+        {
+            return false;
+        }
+
+        // Triplets are keyed on the client network rather than the exact address,
+        // as large senders often retry from a different host of the same pool
+        let network = match self.data.remote_ip {
+            IpAddr::V4(ip) => ip.octets()[..3].to_vec(),
+            IpAddr::V6(ip) => ip.octets()[..8].to_vec(),
+        };
+        let store = &self.core.core.storage.lookup;
+
+        // Networks that passed greylisting enough times are whitelisted
+        let mut whitelist_key = b"gw:".to_vec();
+        whitelist_key.extend_from_slice(&network);
+        if config.whitelist_retries > 0 {
+            match store.counter_get(whitelist_key.clone()).await {
+                Ok(retries) if retries as u64 >= config.whitelist_retries => {
+                    return false;
+                }
+                Ok(_) => (),
+                Err(err) => {
+                    tracing::debug!(parent: &self.span,
+                        context = "greylist",
+                        event = "error",
+                        reason = %err,
+                        "Failed to obtain greylist whitelist.");
+                    return false;
+                }
+            }
+        }
+
+        let rcpt = self.data.rcpt_to.last().unwrap();
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&network);
+        hasher.update(
+            self.data
+                .mail_from
+                .as_ref()
+                .map(|m| m.domain.as_str())
+                .unwrap_or_default()
+                .as_bytes(),
+        );
+        hasher.update(rcpt.address_lcase.as_bytes());
+        let mut triplet_key = b"gt:".to_vec();
+        triplet_key.extend_from_slice(hasher.finalize().as_bytes());
+
+        let now = now();
+        match store.key_get::<String>(triplet_key.clone()).await {
+            Ok(Some(first_seen)) => {
+                let first_seen = first_seen.parse::<u64>().unwrap_or(now);
+                if first_seen + config.delay.as_secs() > now {
+                    tracing::info!(parent: &self.span,
+                        context = "greylist",
+                        event = "defer",
+                        remote_ip = %self.data.remote_ip,
+                        address = &rcpt.address_lcase,
+                        retry_in = first_seen + config.delay.as_secs() - now,
+                        "Recipient greylisted, retry attempted too soon.");
+                    return true;
+                }
+
+                // Retry after the initial delay, keep the triplet for future deliveries
+                if let Err(err) = store
+                    .key_set(
+                        triplet_key,
+                        first_seen.to_string().into_bytes(),
+                        config.whitelist_expire.as_secs().into(),
+                    )
+                    .await
+                {
+                    tracing::debug!(parent: &self.span,
+                        context = "greylist",
+                        event = "error",
+                        reason = %err,
+                        "Failed to update greylist triplet.");
+                }
+                if config.whitelist_retries > 0 {
+                    if let Err(err) = store
+                        .counter_incr(
+                            whitelist_key,
+                            1,
+                            config.whitelist_expire.as_secs().into(),
+                            false,
+                        )
+                        .await
+                    {
+                        tracing::debug!(parent: &self.span,
+                            context = "greylist",
+                            event = "error",
+                            reason = %err,
+                            "Failed to update greylist whitelist.");
+                    }
+                }
+
+                false
+            }
+            Ok(None) => match store
+                .key_set(
+                    triplet_key,
+                    now.to_string().into_bytes(),
+                    config.expire.as_secs().into(),
+                )
+                .await
+            {
+                Ok(_) => {
+                    tracing::info!(parent: &self.span,
+                        context = "greylist",
+                        event = "defer",
+                        remote_ip = %self.data.remote_ip,
+                        address = &rcpt.address_lcase,
+                        retry_in = config.delay.as_secs(),
+                        "Recipient greylisted, first delivery attempt.");
+                    true
+                }
+                Err(err) => {
+                    tracing::debug!(parent: &self.span,
+                        context = "greylist",
+                        event = "error",
+                        reason = %err,
+                        "Failed to store greylist triplet.");
+                    false
+                }
+            },
+            Err(err) => {
+                tracing::debug!(parent: &self.span,
+                    context = "greylist",
+                    event = "error",
+                    reason = %err,
+                    "Failed to obtain greylist triplet.");
+                false
+            }
+        }
+    }
+}
@@ -23,7 +23,9 @@
 
 use std::time::{Duration, SystemTime};
 
-use common::{listener::SessionStream, scripts::ScriptModification};
+use common::{
+    config::smtp::dnsbl::DnsListTarget, listener::SessionStream, scripts::ScriptModification,
+};
 use mail_auth::{IprevOutput, IprevResult, SpfOutput, SpfResult};
 use smtp_proto::{MailFrom, MtPriority, MAIL_BY_NOTIFY, MAIL_BY_RETURN, MAIL_REQUIRETLS};
 use utils::config::Rate;
@@ -128,6 +130,23 @@ impl<T: SessionStream> Session<T> {
         }
         .into();
 
+        // Query DNS block and allow lists for the sender domain
+        if self.core.core.smtp.dnsbl.has_lists(DnsListTarget::Domain) {
+            let domain = &self.data.mail_from.as_ref().unwrap().domain;
+            if !domain.is_empty() {
+                let result = self.core.core.dnsbl_check_domain(domain).await;
+                if !result.hits.is_empty() {
+                    tracing::info!(parent: &self.span,
+                        context = "dnsbl",
+                        event = "listed",
+                        domain = domain,
+                        lists = ?result.hits,
+                        score = result.score);
+                }
+                self.data.dnsbl_domain = result.into();
+            }
+        }
+
         // Sieve filtering
         if let Some(script) = self
             .core
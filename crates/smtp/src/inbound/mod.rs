@@ -27,12 +27,15 @@ use mail_auth::{
     AuthenticationResults, DkimResult, DmarcResult, IprevResult, SpfResult,
 };
 
+pub mod arc;
 pub mod auth;
 pub mod data;
 pub mod ehlo;
+pub mod greylist;
 pub mod mail;
 pub mod milter;
 pub mod rcpt;
+pub mod rspamd;
 pub mod session;
 pub mod spawn;
 pub mod vrfy;
@@ -21,7 +21,7 @@
  * for more details.
 */
 
-use common::{listener::SessionStream, scripts::ScriptModification};
+use common::{addresses::RcptStatus, listener::SessionStream, scripts::ScriptModification};
 use smtp_proto::{
     RcptTo, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
 };
@@ -144,9 +144,50 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Decode bounces addressed to SRS rewritten senders
+        let rcpt = self.data.rcpt_to.last_mut().unwrap();
+        let srs_result = self
+            .core
+            .core
+            .smtp
+            .session
+            .srs
+            .as_ref()
+            .and_then(|srs| srs.reverse(&rcpt.address_lcase));
+        let is_srs = match srs_result {
+            Some(Ok(address)) => {
+                tracing::debug!(parent: &self.span,
+                    context = "srs",
+                    event = "reverse",
+                    address = &rcpt.address_lcase,
+                    to = address);
+
+                rcpt.address_lcase = address.to_lowercase();
+                rcpt.domain = rcpt.address_lcase.domain_part().to_string();
+                rcpt.address = address;
+                true
+            }
+            Some(Err(err)) => {
+                tracing::debug!(parent: &self.span,
+                    context = "srs",
+                    event = "error",
+                    address = &rcpt.address_lcase,
+                    reason = ?err,
+                    "Invalid SRS address.");
+
+                self.data.rcpt_to.pop();
+                return self
+                    .rcpt_error(b"550 5.1.1 Invalid or expired SRS address.\r\n")
+                    .await;
+            }
+            None => false,
+        };
+
         // Verify address
         let rcpt = self.data.rcpt_to.last().unwrap();
-        if let Some(directory) = self
+        if is_srs {
+            // Decoded SRS addresses are relayed back to the original sender
+        } else if let Some(directory) = self
             .core
             .core
             .eval_if::<String, _>(&self.core.core.smtp.session.rcpt.directory, self)
@@ -155,20 +196,40 @@ impl<T: SessionStream> Session<T> {
         {
             if let Ok(is_local_domain) = directory.is_local_domain(&rcpt.domain).await {
                 if is_local_domain {
-                    if let Ok(is_local_address) =
-                        self.core.core.rcpt(directory, &rcpt.address_lcase).await
+                    if let Ok(status) = self
+                        .core
+                        .core
+                        .rcpt_status(directory, &rcpt.address_lcase)
+                        .await
                     {
-                        if !is_local_address {
-                            tracing::debug!(parent: &self.span,
+                        match status {
+                            RcptStatus::Found => (),
+                            RcptStatus::NotFound => {
+                                tracing::debug!(parent: &self.span,
                                             context = "rcpt", 
                                             event = "error",
                                             address = &rcpt.address_lcase,
                                             "Mailbox does not exist.");
 
-                            self.data.rcpt_to.pop();
-                            return self
-                                .rcpt_error(b"550 5.1.2 Mailbox does not exist.\r\n")
-                                .await;
+                                self.data.rcpt_to.pop();
+                                return self
+                                    .rcpt_error(b"550 5.1.2 Mailbox does not exist.\r\n")
+                                    .await;
+                            }
+                            RcptStatus::Rejected(mut reason) => {
+                                tracing::debug!(parent: &self.span,
+                                            context = "rcpt", 
+                                            event = "error",
+                                            address = &rcpt.address_lcase,
+                                            reason = reason,
+                                            "Recipient rejected by catch-all expression.");
+
+                                self.data.rcpt_to.pop();
+                                if !reason.ends_with('\n') {
+                                    reason.push_str("\r\n");
+                                }
+                                return self.rcpt_error(reason.as_bytes()).await;
+                            }
                         }
                     } else {
                         tracing::debug!(parent: &self.span,
@@ -227,6 +288,14 @@ impl<T: SessionStream> Session<T> {
             return self.rcpt_error(b"550 5.1.2 Relay not allowed.\r\n").await;
         }
 
+        // Greylisting
+        if self.is_greylisted().await {
+            self.data.rcpt_to.pop();
+            return self
+                .write(b"451 4.7.1 Greylisted, please try again later.\r\n")
+                .await;
+        }
+
         if self.is_allowed().await {
             tracing::debug!(parent: &self.span,
                     context = "rcpt",
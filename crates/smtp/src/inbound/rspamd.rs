@@ -0,0 +1,294 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::borrow::Cow;
+
+use common::{config::smtp::session::Rspamd, listener::SessionStream};
+use serde::Deserialize;
+
+use crate::{core::Session, USER_AGENT};
+
+use super::milter::Modification;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RspamdAction {
+    NoAction,
+    Greylist,
+    AddHeader,
+    RewriteSubject,
+    SoftReject,
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RspamdResult {
+    pub action: RspamdAction,
+    pub score: f64,
+    pub required_score: f64,
+    pub subject: Option<String>,
+    pub message: Option<String>,
+    pub add_headers: Vec<(String, String)>,
+    pub remove_headers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckResponse {
+    action: String,
+    #[serde(default)]
+    score: f64,
+    #[serde(default)]
+    required_score: f64,
+    #[serde(default)]
+    subject: Option<String>,
+    #[serde(default)]
+    messages: Option<CheckMessages>,
+    #[serde(default)]
+    milter: Option<CheckMilter>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CheckMessages {
+    #[serde(default)]
+    smtp_message: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CheckMilter {
+    #[serde(default)]
+    add_headers: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    remove_headers: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<T: SessionStream> Session<T> {
+    /// Scans the message with rspamd, returning an SMTP response if the
+    /// message has to be rejected or greylisted.
+    pub async fn run_rspamd(
+        &self,
+        message: &[u8],
+    ) -> Result<Option<RspamdResult>, Cow<'static, [u8]>> {
+        let config = match &self.core.core.smtp.session.data.rspamd {
+            Some(config)
+                if message.len() <= config.max_size
+                    && self
+                        .core
+                        .core
+                        .eval_if(&config.enable, self)
+                        .await
+                        .unwrap_or(false) =>
+            {
+                config
+            }
+            _ => return Ok(None),
+        };
+
+        let result = match self.rspamd_check(config, message).await {
+            Ok(result) => result,
+            Err(err) => {
+                tracing::warn!(
+                    parent: &self.span,
+                    context = "rspamd",
+                    event = "error",
+                    url = config.url,
+                    reason = err,
+                    "Rspamd scan failed");
+                return if config.tempfail_on_error {
+                    Err((b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into())
+                } else {
+                    Ok(None)
+                };
+            }
+        };
+
+        tracing::debug!(
+            parent: &self.span,
+            context = "rspamd",
+            event = "scan",
+            action = ?result.action,
+            score = result.score,
+            required_score = result.required_score);
+
+        match result.action {
+            RspamdAction::Reject => Err(smtp_response(
+                "550 5.7.1",
+                result
+                    .message
+                    .as_deref()
+                    .unwrap_or("Message rejected due to spam content."),
+            )),
+            RspamdAction::SoftReject => Err(smtp_response(
+                "451 4.7.1",
+                result
+                    .message
+                    .as_deref()
+                    .unwrap_or("Message temporarily rejected, try again later."),
+            )),
+            RspamdAction::Greylist => Err(smtp_response(
+                "451 4.7.1",
+                result
+                    .message
+                    .as_deref()
+                    .unwrap_or("Greylisted, please try again later."),
+            )),
+            RspamdAction::NoAction | RspamdAction::AddHeader | RspamdAction::RewriteSubject => {
+                Ok(Some(result))
+            }
+        }
+    }
+
+    async fn rspamd_check(&self, config: &Rspamd, message: &[u8]) -> Result<RspamdResult, String> {
+        let mut request = rspamd_client(config)?
+            .post(format!("{}/checkv2", config.url))
+            .header("IP", self.data.remote_ip.to_string())
+            .header("MTA-Name", self.hostname.as_str())
+            .body(message.to_vec());
+        if !self.data.helo_domain.is_empty() {
+            request = request.header("Helo", self.data.helo_domain.as_str());
+        }
+        if let Some(mail_from) = &self.data.mail_from {
+            request = request.header("From", mail_from.address.as_str());
+        }
+        for rcpt in &self.data.rcpt_to {
+            request = request.header("Rcpt", rcpt.address.as_str());
+        }
+        if !self.data.authenticated_as.is_empty() {
+            request = request.header("User", self.data.authenticated_as.as_str());
+        }
+
+        let response = request.send().await.map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("Unexpected status {}", response.status()));
+        }
+        let response = response.bytes().await.map_err(|err| err.to_string())?;
+
+        RspamdResult::parse(&response)
+    }
+}
+
+impl RspamdResult {
+    /// Returns the message changes requested by rspamd, in the same format
+    /// used for Milter modifications.
+    pub fn modifications(&self) -> Vec<Modification> {
+        let mut modifications = self
+            .remove_headers
+            .iter()
+            .map(|name| Modification::ChangeHeader {
+                index: 1,
+                name: name.clone(),
+                value: String::new(),
+            })
+            .collect::<Vec<_>>();
+        if let (RspamdAction::RewriteSubject, Some(subject)) = (self.action, &self.subject) {
+            modifications.push(Modification::ChangeHeader {
+                index: 1,
+                name: "Subject".to_string(),
+                value: subject.clone(),
+            });
+        }
+        modifications
+    }
+
+    /// Parses a `/checkv2` JSON response.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let response =
+            serde_json::from_slice::<CheckResponse>(bytes).map_err(|err| err.to_string())?;
+        let action = match response.action.as_str() {
+            "no action" => RspamdAction::NoAction,
+            "greylist" => RspamdAction::Greylist,
+            "add header" => RspamdAction::AddHeader,
+            "rewrite subject" => RspamdAction::RewriteSubject,
+            "soft reject" => RspamdAction::SoftReject,
+            "reject" => RspamdAction::Reject,
+            action => return Err(format!("Unknown action {action:?}")),
+        };
+        let milter = response.milter.unwrap_or_default();
+        let mut add_headers = Vec::new();
+        for (name, value) in milter.add_headers {
+            // Header values are either a string, an object with a value and
+            // an order, or a list of those
+            for value in match value {
+                serde_json::Value::Array(values) => values,
+                value => vec![value],
+            } {
+                match value {
+                    serde_json::Value::String(value) => add_headers.push((name.clone(), value)),
+                    serde_json::Value::Object(mut value) => {
+                        if let Some(serde_json::Value::String(value)) = value.remove("value") {
+                            add_headers.push((name.clone(), value));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(RspamdResult {
+            action,
+            score: response.score,
+            required_score: response.required_score,
+            subject: response.subject,
+            message: response.messages.and_then(|m| m.smtp_message),
+            add_headers,
+            remove_headers: milter.remove_headers.keys().cloned().collect(),
+        })
+    }
+}
+
+/// Trains rspamd's Bayes classifier with a message flagged by the user.
+pub async fn rspamd_learn(config: &Rspamd, message: &[u8], is_spam: bool) -> Result<(), String> {
+    let url = format!(
+        "{}/{}",
+        config.learn_url.as_deref().unwrap_or(&config.url),
+        if is_spam { "learnspam" } else { "learnham" }
+    );
+    let mut request = rspamd_client(config)?.post(url).body(message.to_vec());
+    if let Some(password) = &config.password {
+        request = request.header("Password", password.as_str());
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("Unexpected status {}", response.status()))
+    }
+}
+
+fn rspamd_client(config: &Rspamd) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(config.timeout)
+        .danger_accept_invalid_certs(config.tls_allow_invalid_certs)
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+fn smtp_response(code: &str, message: &str) -> Cow<'static, [u8]> {
+    let message = message.trim();
+    let mut response = Vec::with_capacity(code.len() + message.len() + 3);
+    response.extend_from_slice(code.as_bytes());
+    response.push(b' ');
+    response.extend_from_slice(message.replace(['\r', '\n'], " ").as_bytes());
+    response.extend_from_slice(b"\r\n");
+    response.into()
+}
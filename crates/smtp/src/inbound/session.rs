@@ -75,8 +75,9 @@ impl<T: SessionStream> Session<T> {
                                 chunk_size,
                                 is_last,
                             } => {
-                                state = if chunk_size + self.data.message.len()
-                                    < self.params.max_message_size
+                                state = if !self.data.bdat_failed
+                                    && chunk_size + self.data.message.len()
+                                        < self.params.max_message_size
                                 {
                                     if self.data.message.is_empty() {
                                         self.data.message = Vec::with_capacity(chunk_size);
@@ -84,8 +85,17 @@ impl<T: SessionStream> Session<T> {
                                         self.data.message.reserve(chunk_size);
                                     }
                                     State::Bdat(BdatReceiver::new(chunk_size, is_last))
+                                } else if !self.data.bdat_failed {
+                                    // Abort the transfer rather than reading an oversized chunk.
+                                    return self
+                                        .data_too_large(
+                                            b"552 5.3.4 Message too big for system.\r\n",
+                                        )
+                                        .await;
                                 } else {
-                                    // Chunk is too large, ignore.
+                                    // A previous chunk failed, discard the remaining
+                                    // chunks of this transaction.
+                                    self.data.bdat_failed = !is_last;
                                     State::DataTooLarge(DummyDataReceiver::new_bdat(chunk_size))
                                 };
                                 continue 'outer;
@@ -190,7 +200,7 @@ impl<T: SessionStream> Session<T> {
                         Err(err) => match err {
                             Error::NeedsMoreData { .. } => break 'outer,
                             Error::UnknownCommand | Error::InvalidResponse { .. } => {
-                                self.write(b"500 5.5.1 Invalid command.\r\n").await?;
+                                self.bad_command(b"500 5.5.1 Invalid command.\r\n").await?;
                             }
                             Error::InvalidSenderAddress => {
                                 self.write(b"501 5.1.8 Bad sender's system address.\r\n")
@@ -203,7 +213,7 @@ impl<T: SessionStream> Session<T> {
                                 .await?;
                             }
                             Error::SyntaxError { syntax } => {
-                                self.write(
+                                self.bad_command(
                                     format!("501 5.5.2 Syntax error, expected: {syntax}\r\n")
                                         .as_bytes(),
                                 )
@@ -231,33 +241,39 @@ impl<T: SessionStream> Session<T> {
                     }
                 },
                 State::Data(receiver) => {
-                    if self.data.message.len() + bytes.len() < self.params.max_message_size {
-                        if receiver.ingest(&mut iter, &mut self.data.message) {
-                            let num_rcpts = self.data.rcpt_to.len();
-                            let message = self.queue_message().await;
-                            if !message.is_empty() {
-                                if self.instance.protocol == ServerProtocol::Smtp {
+                    let prev_len = self.data.message.len();
+                    let is_done = receiver.ingest(&mut iter, &mut self.data.message);
+                    if let Some(response) = self.exceeds_data_limits(prev_len) {
+                        return self.data_too_large(response).await;
+                    }
+                    if is_done {
+                        let num_rcpts = self.data.rcpt_to.len();
+                        let message = self.queue_message().await;
+                        if !message.is_empty() {
+                            if self.instance.protocol == ServerProtocol::Smtp {
+                                self.write(message.as_ref()).await?;
+                            } else {
+                                for _ in 0..num_rcpts {
                                     self.write(message.as_ref()).await?;
-                                } else {
-                                    for _ in 0..num_rcpts {
-                                        self.write(message.as_ref()).await?;
-                                    }
                                 }
-                                self.reset();
-                                state = State::default();
-                            } else {
-                                // Disconnect requested
-                                return Err(());
                             }
+                            self.reset();
+                            state = State::default();
                         } else {
-                            break 'outer;
+                            // Disconnect requested
+                            return Err(());
                         }
                     } else {
-                        state = State::DataTooLarge(DummyDataReceiver::new_data(receiver));
+                        break 'outer;
                     }
                 }
                 State::Bdat(receiver) => {
-                    if receiver.ingest(&mut iter, &mut self.data.message) {
+                    let prev_len = self.data.message.len();
+                    let is_done = receiver.ingest(&mut iter, &mut self.data.message);
+                    if let Some(response) = self.exceeds_data_limits(prev_len) {
+                        return self.data_too_large(response).await;
+                    }
+                    if is_done {
                         if self.can_send_data().await? {
                             if receiver.is_last {
                                 let num_rcpts = self.data.rcpt_to.len();
@@ -280,6 +296,7 @@ impl<T: SessionStream> Session<T> {
                             }
                         } else {
                             self.data.message = Vec::with_capacity(0);
+                            self.data.bdat_failed = !receiver.is_last;
                         }
                         state = State::default();
                     } else {
@@ -326,7 +343,7 @@ impl<T: SessionStream> Session<T> {
                 }
                 State::RequestTooLarge(receiver) => {
                     if receiver.ingest(&mut iter) {
-                        self.write(b"554 5.3.4 Line is too long.\r\n").await?;
+                        self.bad_command(b"554 5.3.4 Line is too long.\r\n").await?;
                         state = State::default();
                     } else {
                         break 'outer;
@@ -339,19 +356,77 @@ impl<T: SessionStream> Session<T> {
 
         Ok(true)
     }
+
+    async fn bad_command(&mut self, response: &[u8]) -> Result<(), ()> {
+        if self
+            .core
+            .core
+            .is_abuse_banned(self.data.remote_ip)
+            .await
+            .unwrap_or_default()
+        {
+            tracing::info!(
+                parent: &self.span,
+                context = "fail2ban",
+                event = "ban",
+                remote_ip = self.data.remote_ip.to_string(),
+                "IP address banned after too many invalid commands."
+            );
+            self.write(b"421 4.3.0 Too many invalid commands, closing connection.\r\n")
+                .await?;
+            Err(())
+        } else {
+            self.write(response).await
+        }
+    }
 }
 
 impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
     pub fn reset(&mut self) {
         self.data.mail_from = None;
         self.data.spf_mail_from = None;
+        self.data.dnsbl_domain = None;
+        self.data.url_reputation = None;
         self.data.rcpt_to.clear();
         self.data.message = Vec::with_capacity(0);
+        self.data.bdat_failed = false;
         self.data.priority = 0;
         self.data.delivery_by = 0;
         self.data.future_release = 0;
     }
 
+    fn exceeds_data_limits(&self, prev_len: usize) -> Option<&'static [u8]> {
+        let message = &self.data.message;
+        if message.len() > self.params.max_message_size {
+            Some(b"552 5.3.4 Message too big for system.\r\n")
+        } else if prev_len <= self.params.max_header_size
+            && message.len() > self.params.max_header_size
+            && !has_header_end(
+                &message[..std::cmp::min(message.len(), self.params.max_header_size + 4)],
+            )
+        {
+            Some(b"552 5.3.4 Message header section too big for system.\r\n")
+        } else {
+            None
+        }
+    }
+
+    async fn data_too_large(&mut self, response: &[u8]) -> Result<bool, ()> {
+        tracing::debug!(
+            parent: &self.span,
+            context = "data",
+            event = "too-large",
+            size = self.data.message.len(),
+            "Message exceeds size limits, aborting transfer."
+        );
+
+        // The client keeps sending until it reads the reply, so the
+        // connection has to be dropped once the response is written.
+        self.data.message = Vec::with_capacity(0);
+        self.write(response).await?;
+        Err(())
+    }
+
     #[inline(always)]
     pub async fn write(&mut self, bytes: &[u8]) -> Result<(), ()> {
         let err = match self.stream.write_all(bytes).await {
@@ -445,9 +520,23 @@ impl<T: SessionStream> ResolveVariable for Session<T> {
             V_LOCAL_IP => self.data.local_ip_str.as_str().into(),
             V_LOCAL_PORT => self.data.local_port.into(),
             V_TLS => self.stream.is_tls().into(),
+            V_TLS_SNI => self.stream.tls_server_name().unwrap_or_default().into(),
             V_PRIORITY => self.data.priority.to_string().into(),
             V_PROTOCOL => self.instance.protocol.as_str().into(),
             _ => expr::Variable::default(),
         }
     }
 }
+
+fn has_header_end(bytes: &[u8]) -> bool {
+    let mut is_empty_line = true;
+    for &ch in bytes {
+        match ch {
+            b'\n' if is_empty_line => return true,
+            b'\n' => is_empty_line = true,
+            b'\r' => (),
+            _ => is_empty_line = false,
+        }
+    }
+    false
+}
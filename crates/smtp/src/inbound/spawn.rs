@@ -23,7 +23,10 @@
 
 use std::time::Instant;
 
-use common::listener::{self, SessionManager, SessionStream};
+use common::{
+    config::smtp::dnsbl::DnsListTarget,
+    listener::{self, SessionManager, SessionStream},
+};
 use tokio_rustls::server::TlsStream;
 
 use crate::{
@@ -46,6 +49,7 @@ impl SessionManager for SmtpSessionManager {
             span: session.span,
             stream: session.stream,
             in_flight: vec![session.in_flight],
+            qos: session.qos,
             data: SessionData::new(
                 session.local_ip,
                 session.local_port,
@@ -96,6 +100,20 @@ impl<T: SessionStream> Session<T> {
 
         let config = &self.core.core.smtp.session.connect;
 
+        // Query DNS block and allow lists
+        if self.core.core.smtp.dnsbl.has_lists(DnsListTarget::Ip) {
+            let result = self.core.core.dnsbl_check_ip(self.data.remote_ip).await;
+            if !result.hits.is_empty() {
+                tracing::info!(parent: &self.span,
+                    context = "dnsbl",
+                    event = "listed",
+                    ip = %self.data.remote_ip,
+                    lists = ?result.hits,
+                    score = result.score);
+            }
+            self.data.dnsbl_ip = result.into();
+        }
+
         // Sieve filtering
         if let Some(script) = self
             .core
@@ -253,6 +271,7 @@ impl<T: SessionStream> Session<T> {
             instance: self.instance,
             core: self.core,
             in_flight: self.in_flight,
+            qos: self.qos,
             params: self.params,
             span,
         })
@@ -87,6 +87,7 @@ impl SMTP {
                 pki_verify: build_tls_connector(false),
                 dummy_verify: build_tls_connector(true),
             },
+            connection_pool: ConnectionPool::new(capacity, shard),
             #[cfg(feature = "local_delivery")]
             delivery_tx,
         };
@@ -48,11 +48,13 @@ use crate::{
 use super::{
     lookup::ToNextHop,
     mta_sts,
+    pool::PoolKey,
     session::{read_greeting, say_helo, try_start_tls, SessionParams, StartTlsResult},
     NextHop, TlsStrategy,
 };
 use crate::queue::{
-    throttle, DeliveryAttempt, Domain, Error, Event, OnHold, QueueEnvelope, Status,
+    throttle, timeline::AttemptDetails, DeliveryAttempt, Domain, Error, Event, FailedRecipient,
+    Failure, OnHold, QueueEnvelope, Status, RCPT_DSN_SENT,
 };
 
 impl DeliveryAttempt {
@@ -79,6 +81,17 @@ impl DeliveryAttempt {
                 return;
             };
 
+            // Messages on hold are not delivered until released
+            if message.is_on_hold() {
+                let mut batch = BatchBuilder::new();
+                batch.clear(ValueClass::Queue(QueueClass::MessageEvent(QueueEvent {
+                    due: self.event.due,
+                    queue_id: self.event.queue_id,
+                })));
+                let _ = core.core.storage.data.write(batch.build()).await;
+                return;
+            }
+
             let span = tracing::info_span!(
                 "delivery",
                 "id" = message.id,
@@ -112,7 +125,7 @@ impl DeliveryAttempt {
                 }
             } else {
                 // All message recipients expired, do not re-queue. (DSN has been already sent)
-                message.remove(&core, self.event.due).await;
+                message.remove_or_archive(&core, self.event.due).await;
                 if core.inner.queue_tx.send(Event::Reload).await.is_err() {
                     tracing::warn!("Channel closed while trying to notify queue manager.");
                 }
@@ -163,6 +176,7 @@ impl DeliveryAttempt {
             let mut on_hold = Vec::new();
             let no_ip = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
             let mut recipients = std::mem::take(&mut message.recipients);
+            let mut attempts = Vec::new();
             'next_domain: for domain_idx in 0..message.domains.len() {
                 // Only process domains due for delivery
                 let domain = &message.domains[domain_idx];
@@ -171,6 +185,10 @@ impl DeliveryAttempt {
                 {
                     continue;
                 }
+                attempts.push(AttemptDetails {
+                    domain_idx,
+                    ..Default::default()
+                });
 
                 // Create new span for domain
                 let span = tracing::info_span!(
@@ -437,6 +455,10 @@ impl DeliveryAttempt {
                 'next_host: for remote_host in &remote_hosts {
                     // Validate MTA-STS
                     envelope.mx = remote_host.hostname();
+                    if let Some(attempt) = attempts.last_mut() {
+                        attempt.mx = Some(envelope.mx.to_string());
+                        attempt.tls = false;
+                    }
                     if let Some(mta_sts_policy) = &mta_sts_policy {
                         if !mta_sts_policy.verify(envelope.mx) {
                             // Report MTA-STS failed verification
@@ -662,51 +684,11 @@ impl DeliveryAttempt {
                             }
                         }
 
-                        // Connect
-                        let conn_timeout = core
-                            .core
-                            .eval_if(&queue_config.timeout.connect, &envelope)
-                            .await
-                            .unwrap_or_else(|| Duration::from_secs(5 * 60));
-                        let mut smtp_client = match if let Some(ip_addr) = source_ip {
-                            SmtpClient::connect_using(
-                                ip_addr,
-                                SocketAddr::new(remote_ip, remote_host.port()),
-                                conn_timeout,
-                            )
-                            .await
-                        } else {
-                            SmtpClient::connect(
-                                SocketAddr::new(remote_ip, remote_host.port()),
-                                conn_timeout,
-                            )
-                            .await
-                        } {
-                            Ok(smtp_client) => {
-                                tracing::debug!(
-                                    parent: &span,
-                                    context = "connect",
-                                    event = "success",
-                                    mx = envelope.mx,
-                                    source_ip = %source_ip.unwrap_or(no_ip),
-                                    remote_ip = %remote_ip,
-                                    remote_port = remote_host.port(),
-                                );
-
-                                smtp_client
-                            }
-                            Err(err) => {
-                                tracing::info!(
-                                    parent: &span,
-                                    context = "connect",
-                                    event = "failed",
-                                    mx = envelope.mx,
-                                    reason = %err,
-                                );
-                                last_status = Status::from_smtp_error(envelope.mx, "", err);
-                                continue 'next_ip;
-                            }
-                        };
+                        // Throttle source IPs that are being warmed up
+                        if let Err(err) = core.is_warmup_allowed(envelope.local_ip, &span).await {
+                            message.domains[domain_idx].set_throttle_error(err, &mut on_hold);
+                            continue 'next_domain;
+                        }
 
                         // Obtain session parameters
                         let local_hostname = core
@@ -754,7 +736,7 @@ impl DeliveryAttempt {
                         // Prepare TLS connector
                         let is_strict_tls = tls_strategy.is_tls_required()
                             || (message.flags & MAIL_REQUIRETLS) != 0
-                            || mta_sts_policy.is_some()
+                            || mta_sts_policy.as_ref().map_or(false, |p| p.enforce())
                             || dane_policy.is_some();
                         let tls_connector =
                             if allow_invalid_certs || remote_host.allow_invalid_certs() {
@@ -763,6 +745,100 @@ impl DeliveryAttempt {
                                 &core.inner.connectors.pki_verify
                             };
 
+                        // Reuse an idle session to this host, if available
+                        let pool_key = (queue_config.pool.max_idle > 0
+                            && !allow_invalid_certs
+                            && !remote_host.allow_invalid_certs())
+                        .then(|| PoolKey {
+                            mx: envelope.mx.to_string(),
+                            remote_ip,
+                            remote_port: remote_host.port(),
+                            local_ip: source_ip,
+                            is_strict_tls,
+                            is_dane: dane_policy.is_some(),
+                            credentials: remote_host.credentials().cloned(),
+                        });
+                        if let Some(pooled) = match &pool_key {
+                            Some(pool_key) => core.take_pooled_client(pool_key, &params).await,
+                            None => None,
+                        } {
+                            // Sessions are only pooled after a successful TLS handshake
+                            if let Some(tls_report) = &tls_report {
+                                core.schedule_report(TlsEvent {
+                                    policy: (&mta_sts_policy, &dane_policy).into(),
+                                    domain: domain.domain.to_string(),
+                                    failure: None,
+                                    tls_record: tls_report.record.clone(),
+                                    interval: tls_report.interval,
+                                })
+                                .await;
+                            }
+
+                            let delivery_result = message
+                                .deliver_tls(
+                                    pooled.smtp_client,
+                                    Some(pooled.capabilities),
+                                    pool_key,
+                                    recipients.iter_mut().filter(|r| r.domain_idx == domain_idx),
+                                    params,
+                                )
+                                .await;
+
+                            let schedule = core
+                                .core
+                                .eval_if::<Vec<Duration>, _>(&queue_config.retry, &envelope)
+                                .await
+                                .unwrap_or_else(|| vec![Duration::from_secs(60)]);
+                            message.domains[domain_idx].set_status(delivery_result, &schedule);
+                            continue 'next_domain;
+                        }
+
+                        // Connect
+                        let conn_timeout = core
+                            .core
+                            .eval_if(&queue_config.timeout.connect, &envelope)
+                            .await
+                            .unwrap_or_else(|| Duration::from_secs(5 * 60));
+                        let mut smtp_client = match if let Some(ip_addr) = source_ip {
+                            SmtpClient::connect_using(
+                                ip_addr,
+                                SocketAddr::new(remote_ip, remote_host.port()),
+                                conn_timeout,
+                            )
+                            .await
+                        } else {
+                            SmtpClient::connect(
+                                SocketAddr::new(remote_ip, remote_host.port()),
+                                conn_timeout,
+                            )
+                            .await
+                        } {
+                            Ok(smtp_client) => {
+                                tracing::debug!(
+                                    parent: &span,
+                                    context = "connect",
+                                    event = "success",
+                                    mx = envelope.mx,
+                                    source_ip = %source_ip.unwrap_or(no_ip),
+                                    remote_ip = %remote_ip,
+                                    remote_port = remote_host.port(),
+                                );
+
+                                smtp_client
+                            }
+                            Err(err) => {
+                                tracing::info!(
+                                    parent: &span,
+                                    context = "connect",
+                                    event = "failed",
+                                    mx = envelope.mx,
+                                    reason = %err,
+                                );
+                                last_status = Status::from_smtp_error(envelope.mx, "", err);
+                                continue 'next_ip;
+                            }
+                        };
+
                         let delivery_result = if !remote_host.implicit_tls() {
                             // Read greeting
                             smtp_client.timeout = core
@@ -801,8 +877,8 @@ impl DeliveryAttempt {
                                 }
                             };
 
-                            // Try starting TLS
-                            if tls_strategy.try_start_tls() {
+                            // Try starting TLS, policies in enforce mode never fall back to plain-text
+                            if tls_strategy.try_start_tls() || is_strict_tls {
                                 smtp_client.timeout = core
                                     .core
                                     .eval_if(&queue_config.timeout.tls, &envelope)
@@ -871,9 +947,14 @@ impl DeliveryAttempt {
                                         }
 
                                         // Deliver message over TLS
+                                        if let Some(attempt) = attempts.last_mut() {
+                                            attempt.tls = true;
+                                        }
                                         message
-                                            .deliver(
+                                            .deliver_tls(
                                                 smtp_client,
+                                                None,
+                                                pool_key,
                                                 recipients
                                                     .iter_mut()
                                                     .filter(|r| r.domain_idx == domain_idx),
@@ -1038,9 +1119,14 @@ impl DeliveryAttempt {
                             }
 
                             // Deliver message
+                            if let Some(attempt) = attempts.last_mut() {
+                                attempt.tls = true;
+                            }
                             message
-                                .deliver(
+                                .deliver_tls(
                                     smtp_client,
+                                    None,
+                                    pool_key,
                                     recipients.iter_mut().filter(|r| r.domain_idx == domain_idx),
                                     params,
                                 )
@@ -1068,6 +1154,20 @@ impl DeliveryAttempt {
             }
             message.recipients = recipients;
 
+            // Add failed attempts to the message's failure history
+            for attempt in &attempts {
+                message.add_failure(attempt.domain_idx);
+            }
+
+            // Record delivery attempts
+            if core.has_timeline() {
+                for attempt in attempts {
+                    if let Some(event) = message.timeline_attempt(attempt) {
+                        core.timeline_add(message.id, event).await;
+                    }
+                }
+            }
+
             // Send Delivery Status Notifications
             core.send_dsn(&mut message, &span).await;
 
@@ -1108,7 +1208,7 @@ impl DeliveryAttempt {
                 Event::Reload
             } else {
                 // Delete message from queue
-                message.remove(&core, self.event.due).await;
+                message.remove_or_archive(&core, self.event.due).await;
 
                 tracing::info!(
                     parent: &span,
@@ -1183,6 +1283,43 @@ impl Message {
 
         has_pending_delivery
     }
+
+    /// Adds the outcome of a failed delivery attempt to the failure history
+    pub fn add_failure(&mut self, domain_idx: usize) {
+        let domain = &self.domains[domain_idx];
+        let recipients = self
+            .recipients
+            .iter()
+            .filter(|rcpt| {
+                rcpt.domain_idx == domain_idx
+                    && match &rcpt.status {
+                        Status::TemporaryFailure(_) => true,
+                        // Permanent failures are reported only once
+                        Status::PermanentFailure(_) => !rcpt.has_flag(RCPT_DSN_SENT),
+                        _ => false,
+                    }
+            })
+            .map(|rcpt| FailedRecipient {
+                address: rcpt.address.clone(),
+                status: rcpt.status.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        if !recipients.is_empty()
+            || matches!(
+                &domain.status,
+                Status::TemporaryFailure(_) | Status::PermanentFailure(_)
+            )
+        {
+            self.failures.push(Failure {
+                timestamp: now(),
+                domain: domain.domain.clone(),
+                attempt: domain.retry.inner,
+                status: domain.status.clone(),
+                recipients,
+            });
+        }
+    }
 }
 
 impl Domain {
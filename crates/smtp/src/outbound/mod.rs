@@ -40,6 +40,7 @@ pub mod delivery;
 pub mod local;
 pub mod lookup;
 pub mod mta_sts;
+pub mod pool;
 pub mod session;
 
 #[derive(Debug, Clone, Copy, Default)]
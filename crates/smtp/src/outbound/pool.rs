@@ -0,0 +1,241 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    hash::Hash,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use mail_send::{smtp::AssertReply, Credentials, SmtpClient};
+use smtp_proto::EhloResponse;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+
+use crate::{
+    core::SMTP,
+    queue::{Error, Message, Recipient, Status},
+};
+
+use super::session::{quit, start_session, SessionParams};
+
+// Sessions are only shared between deliveries that connect to the same host
+// using the same TLS requirements and relay credentials
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    pub mx: String,
+    pub remote_ip: IpAddr,
+    pub remote_port: u16,
+    pub local_ip: Option<IpAddr>,
+    pub is_strict_tls: bool,
+    pub is_dane: bool,
+    pub credentials: Option<Credentials<String>>,
+}
+
+pub struct PooledClient {
+    pub smtp_client: SmtpClient<TlsStream<TcpStream>>,
+    pub capabilities: EhloResponse<String>,
+}
+
+pub struct ConnectionPool<K, C> {
+    clients: DashMap<K, Vec<IdleClient<C>>>,
+}
+
+struct IdleClient<C> {
+    client: C,
+    idle_since: Instant,
+}
+
+impl<K: Hash + Eq + Clone, C> ConnectionPool<K, C> {
+    pub fn new(capacity: usize, shard_amount: usize) -> Self {
+        ConnectionPool {
+            clients: DashMap::with_capacity_and_shard_amount(capacity, shard_amount),
+        }
+    }
+
+    // Returns the most recently released client that has not expired,
+    // expired clients are dropped which closes their connection.
+    pub fn take(&self, key: &K, idle_timeout: Duration) -> Option<C> {
+        let mut clients = self.clients.get_mut(key)?;
+        while let Some(idle) = clients.pop() {
+            if idle.idle_since.elapsed() < idle_timeout {
+                return Some(idle.client);
+            }
+        }
+        None
+    }
+
+    // Returns the client back when the host or the pool are full
+    pub fn release(&self, key: K, client: C, max_idle: usize, max_connections: usize) -> Option<C> {
+        if self.len() >= max_connections {
+            return Some(client);
+        }
+
+        let mut clients = self.clients.entry(key).or_default();
+        if clients.len() < max_idle {
+            clients.push(IdleClient {
+                client,
+                idle_since: Instant::now(),
+            });
+            None
+        } else {
+            Some(client)
+        }
+    }
+
+    // Removes and returns the expired clients so they can be closed
+    pub fn reap(&self, idle_timeout: Duration) -> Vec<C> {
+        let mut expired = Vec::new();
+        self.clients.retain(|_, clients| {
+            let mut pos = 0;
+            while pos < clients.len() {
+                if clients[pos].idle_since.elapsed() >= idle_timeout {
+                    expired.push(clients.remove(pos).client);
+                } else {
+                    pos += 1;
+                }
+            }
+            !clients.is_empty()
+        });
+        expired
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.iter().map(|clients| clients.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Hash + Eq + Clone, C> Default for ConnectionPool<K, C> {
+    fn default() -> Self {
+        ConnectionPool {
+            clients: DashMap::new(),
+        }
+    }
+}
+
+impl SMTP {
+    pub async fn take_pooled_client(
+        &self,
+        key: &PoolKey,
+        params: &SessionParams<'_>,
+    ) -> Option<PooledClient> {
+        let idle_timeout = self.core.smtp.queue.pool.idle_timeout;
+
+        loop {
+            let mut client = self.inner.connection_pool.take(key, idle_timeout)?;
+
+            // Make sure the remote host did not close the session while it was idle
+            client.smtp_client.timeout = params.timeout_mail;
+            if client
+                .smtp_client
+                .cmd(b"RSET\r\n")
+                .await
+                .and_then(|r| r.assert_positive_completion())
+                .is_ok()
+            {
+                tracing::debug!(
+                    parent: params.span,
+                    context = "pool",
+                    event = "reuse",
+                    mx = params.hostname,
+                    remote_ip = %key.remote_ip,
+                );
+                return Some(client);
+            }
+        }
+    }
+
+    fn release_pooled_client(&self, key: PoolKey, client: PooledClient) -> Option<PooledClient> {
+        let pool = &self.core.smtp.queue.pool;
+        let client =
+            self.inner
+                .connection_pool
+                .release(key, client, pool.max_idle, pool.max_connections);
+
+        if client.is_none() {
+            // Close the session once it expires if it was not reused by then
+            let core = self.clone();
+            let idle_timeout = pool.idle_timeout;
+            tokio::spawn(async move {
+                tokio::time::sleep(idle_timeout).await;
+                for client in core.inner.connection_pool.reap(idle_timeout) {
+                    quit(client.smtp_client).await;
+                }
+            });
+        }
+
+        client
+    }
+}
+
+impl Message {
+    pub async fn deliver_tls(
+        &self,
+        mut smtp_client: SmtpClient<TlsStream<TcpStream>>,
+        capabilities: Option<EhloResponse<String>>,
+        pool_key: Option<PoolKey>,
+        recipients: impl Iterator<Item = &mut Recipient>,
+        params: SessionParams<'_>,
+    ) -> Status<(), Error> {
+        // Pooled sessions are already authenticated
+        let capabilities = match capabilities {
+            Some(capabilities) => capabilities,
+            None => match start_session(&mut smtp_client, &params).await {
+                Ok(capabilities) => capabilities,
+                Err(status) => {
+                    quit(smtp_client).await;
+                    return status;
+                }
+            },
+        };
+
+        match (
+            self.deliver_transaction(&mut smtp_client, &capabilities, recipients, &params)
+                .await,
+            pool_key,
+        ) {
+            (Ok(status), Some(pool_key)) => {
+                // Keep the session open for other messages to the same host
+                if let Some(client) = params.core.release_pooled_client(
+                    pool_key,
+                    PooledClient {
+                        smtp_client,
+                        capabilities,
+                    },
+                ) {
+                    quit(client.smtp_client).await;
+                }
+                status
+            }
+            (Ok(status) | Err(status), _) => {
+                quit(smtp_client).await;
+                status
+            }
+        }
+    }
+}
@@ -65,57 +65,35 @@ impl Message {
         recipients: impl Iterator<Item = &mut Recipient>,
         params: SessionParams<'_>,
     ) -> Status<(), Error> {
-        // Obtain capabilities
-        let capabilities = match say_helo(&mut smtp_client, &params).await {
+        // Obtain capabilities and authenticate
+        let capabilities = match start_session(&mut smtp_client, &params).await {
             Ok(capabilities) => capabilities,
             Err(status) => {
-                tracing::info!(
-                    parent: params.span,
-                    context = "ehlo",
-                    event = "rejected",
-                    mx = &params.hostname,
-                    reason = %status,
-                );
                 quit(smtp_client).await;
                 return status;
             }
         };
 
-        // Authenticate
-        if let Some(credentials) = params.credentials {
-            if let Err(err) = smtp_client.authenticate(credentials, &capabilities).await {
-                tracing::info!(
-                    parent: params.span,
-                    context = "auth",
-                    event = "failed",
-                    mx = &params.hostname,
-                    reason = %err,
-                );
-                quit(smtp_client).await;
-                return Status::from_smtp_error(params.hostname, "AUTH ...", err);
-            }
-
-            // Refresh capabilities
-            // Disabled as some SMTP servers deauthenticate after EHLO
-            /*capabilities = match say_helo(&mut smtp_client, &params).await {
-                Ok(capabilities) => capabilities,
-                Err(status) => {
-                    tracing::info!(
-                        parent: params.span,
-                        context = "ehlo",
-                        event = "rejected",
-                        mx = &params.hostname,
-                        reason = %status,
-                    );
-                    quit(smtp_client).await;
-                    return status;
-                }
-            };*/
-        }
+        let status = self
+            .deliver_transaction(&mut smtp_client, &capabilities, recipients, &params)
+            .await
+            .unwrap_or_else(|status| status);
+        quit(smtp_client).await;
+        status
+    }
 
+    // Runs a single mail transaction over an established session. Returns `Err` when
+    // the transaction was aborted and the connection cannot be reused.
+    pub async fn deliver_transaction<T: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        smtp_client: &mut SmtpClient<T>,
+        capabilities: &EhloResponse<String>,
+        recipients: impl Iterator<Item = &mut Recipient>,
+        params: &SessionParams<'_>,
+    ) -> Result<Status<(), Error>, Status<(), Error>> {
         // MAIL FROM
         smtp_client.timeout = params.timeout_mail;
-        let cmd = self.build_mail_from(&capabilities);
+        let cmd = self.build_mail_from(capabilities);
         if let Err(err) = smtp_client
             .cmd(cmd.as_bytes())
             .await
@@ -128,8 +106,7 @@ impl Message {
                 mx = &params.hostname,
                 reason = %err,
             );
-            quit(smtp_client).await;
-            return Status::from_smtp_error(params.hostname, &cmd, err);
+            return Err(Status::from_smtp_error(params.hostname, &cmd, err));
         }
 
         // RCPT TO
@@ -147,7 +124,7 @@ impl Message {
                 continue;
             }
 
-            let cmd = self.build_rcpt_to(rcpt, &capabilities);
+            let cmd = self.build_rcpt_to(rcpt, capabilities);
             match smtp_client.cmd(cmd.as_bytes()).await {
                 Ok(response) => match response.severity() {
                     Severity::PositiveCompletion => {
@@ -196,21 +173,21 @@ impl Message {
                     );
 
                     // Something went wrong, abort.
-                    quit(smtp_client).await;
-                    return Status::from_smtp_error(params.hostname, "", err);
+                    return Err(Status::from_smtp_error(params.hostname, "", err));
                 }
             }
         }
 
         // Send message
-        if !accepted_rcpts.is_empty() {
+        let has_transaction = !accepted_rcpts.is_empty();
+        if has_transaction {
             let bdat_cmd = if capabilities.has_capability(EXT_CHUNKING) {
                 format!("BDAT {} LAST\r\n", self.size).into()
             } else {
                 None
             };
 
-            if let Err(status) = send_message(&mut smtp_client, self, &bdat_cmd, &params).await {
+            if let Err(status) = send_message(smtp_client, self, &bdat_cmd, params).await {
                 tracing::info!(
                     parent: params.span,
                     context = "message",
@@ -219,13 +196,12 @@ impl Message {
                     reason = %status,
                 );
 
-                quit(smtp_client).await;
-                return status;
+                return Err(status);
             }
 
             if params.is_smtp {
                 // Handle SMTP response
-                match read_smtp_data_response(&mut smtp_client, params.hostname, &bdat_cmd).await {
+                match read_smtp_data_response(smtp_client, params.hostname, &bdat_cmd).await {
                     Ok(response) => {
                         // Mark recipients as delivered
                         if response.code() == 250 {
@@ -252,12 +228,11 @@ impl Message {
                                 reason = %response,
                             );
 
-                            quit(smtp_client).await;
-                            return Status::from_smtp_error(
+                            return Err(Status::from_smtp_error(
                                 params.hostname,
                                 bdat_cmd.as_deref().unwrap_or("DATA"),
                                 mail_send::Error::UnexpectedReply(response),
-                            );
+                            ));
                         }
                     }
                     Err(status) => {
@@ -269,18 +244,13 @@ impl Message {
                             reason = %status,
                         );
 
-                        quit(smtp_client).await;
-                        return status;
+                        return Err(status);
                     }
                 }
             } else {
                 // Handle LMTP responses
-                match read_lmtp_data_respone(
-                    &mut smtp_client,
-                    params.hostname,
-                    accepted_rcpts.len(),
-                )
-                .await
+                match read_lmtp_data_respone(smtp_client, params.hostname, accepted_rcpts.len())
+                    .await
                 {
                     Ok(responses) => {
                         for ((rcpt, _), response) in accepted_rcpts.into_iter().zip(responses) {
@@ -341,18 +311,22 @@ impl Message {
                             reason = %status,
                         );
 
-                        quit(smtp_client).await;
-                        return status;
+                        return Err(status);
                     }
                 }
             }
         }
 
-        quit(smtp_client).await;
-        if total_completed == total_rcpt {
+        let status = if total_completed == total_rcpt {
             Status::Completed(())
         } else {
             Status::Scheduled
+        };
+        if has_transaction {
+            Ok(status)
+        } else {
+            // No recipients were accepted and the transaction is still open
+            Err(status)
         }
     }
 
@@ -474,6 +448,58 @@ pub async fn try_start_tls(
     }
 }
 
+pub async fn start_session<T: AsyncRead + AsyncWrite + Unpin>(
+    smtp_client: &mut SmtpClient<T>,
+    params: &SessionParams<'_>,
+) -> Result<EhloResponse<String>, Status<(), Error>> {
+    // Obtain capabilities
+    let capabilities = match say_helo(smtp_client, params).await {
+        Ok(capabilities) => capabilities,
+        Err(status) => {
+            tracing::info!(
+                parent: params.span,
+                context = "ehlo",
+                event = "rejected",
+                mx = &params.hostname,
+                reason = %status,
+            );
+            return Err(status);
+        }
+    };
+
+    // Authenticate
+    if let Some(credentials) = params.credentials {
+        if let Err(err) = smtp_client.authenticate(credentials, &capabilities).await {
+            tracing::info!(
+                parent: params.span,
+                context = "auth",
+                event = "failed",
+                mx = &params.hostname,
+                reason = %err,
+            );
+            return Err(Status::from_smtp_error(params.hostname, "AUTH ...", err));
+        }
+
+        // Refresh capabilities
+        // Disabled as some SMTP servers deauthenticate after EHLO
+        /*capabilities = match say_helo(smtp_client, params).await {
+            Ok(capabilities) => capabilities,
+            Err(status) => {
+                tracing::info!(
+                    parent: params.span,
+                    context = "ehlo",
+                    event = "rejected",
+                    mx = &params.hostname,
+                    reason = %status,
+                );
+                return Err(status);
+            }
+        };*/
+    }
+
+    Ok(capabilities)
+}
+
 pub async fn read_greeting<T: AsyncRead + AsyncWrite + Unpin>(
     smtp_client: &mut SmtpClient<T>,
     hostname: &str,
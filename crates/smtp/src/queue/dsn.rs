@@ -27,7 +27,8 @@ use mail_builder::mime::{make_boundary, BodyPart, MimePart};
 use mail_builder::MessageBuilder;
 use mail_parser::DateTime;
 use smtp_proto::{
-    Response, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
+    Response, MAIL_RET_FULL, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER,
+    RCPT_NOTIFY_SUCCESS,
 };
 use std::fmt::Write;
 use std::time::Duration;
@@ -275,14 +276,20 @@ impl Message {
         self.write_dsn_headers(&mut dsn_header, &reporting_mta);
         let dsn = dsn_header + &dsn;
 
-        // Fetch up to 1024 bytes of message headers
+        // Failure notifications include the full message when requested with RET=FULL,
+        // otherwise fetch up to 1024 bytes of message headers
+        let return_full = has_failure && self.has_flag(MAIL_RET_FULL);
         let headers = match core
             .core
             .storage
             .blob
-            .get_blob(self.blob_hash.as_slice(), 0..1024)
+            .get_blob(
+                self.blob_hash.as_slice(),
+                if return_full { 0..usize::MAX } else { 0..1024 },
+            )
             .await
         {
+            Ok(Some(buf)) if return_full => String::from_utf8_lossy(&buf).into_owned(),
             Ok(Some(mut buf)) => {
                 let mut prev_ch = 0;
                 let mut last_lf = buf.len();
@@ -347,7 +354,11 @@ impl Message {
                         BodyPart::Text(dsn.into()),
                     ),
                     MimePart::new(
-                        ContentType::new("message/rfc822"),
+                        ContentType::new(if return_full {
+                            "message/rfc822"
+                        } else {
+                            "text/rfc822-headers"
+                        }),
                         BodyPart::Text(headers.into()),
                     ),
                 ]),
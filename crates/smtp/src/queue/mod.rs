@@ -43,6 +43,7 @@ pub mod manager;
 pub mod quota;
 pub mod spool;
 pub mod throttle;
+pub mod timeline;
 
 pub type QueueId = u64;
 
@@ -84,6 +85,29 @@ pub struct Message {
 
     pub size: usize,
     pub quota_keys: Vec<QuotaKey>,
+    pub failures: Vec<Failure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DeadLetter {
+    pub message: Message,
+    pub quota_keys: Vec<QuotaKey>,
+    pub archived: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Failure {
+    pub timestamp: u64,
+    pub domain: String,
+    pub attempt: u32,
+    pub status: Status<(), Error>,
+    pub recipients: Vec<FailedRecipient>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedRecipient {
+    pub address: String,
+    pub status: Status<HostResponse<String>, HostResponse<ErrorDetails>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -114,6 +138,8 @@ pub struct Recipient {
 pub const RCPT_DSN_SENT: u64 = 1 << 32;
 pub const RCPT_STATUS_CHANGED: u64 = 2 << 32;
 
+pub const MESSAGE_ON_HOLD: u64 = 1 << 32;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status<T, E> {
     #[serde(rename = "scheduled")]
@@ -297,6 +323,7 @@ impl<'x> ResolveVariable for QueueEnvelope<'x> {
             V_PRIORITY => self.message.priority.into(),
             V_REMOTE_IP => self.remote_ip.to_string().into(),
             V_LOCAL_IP => self.local_ip.to_string().into(),
+            V_QUEUE_SIZE => self.message.size.into(),
             _ => "".into(),
         }
     }
@@ -314,6 +341,7 @@ impl ResolveVariable for Message {
                 .collect::<Vec<_>>()
                 .into(),
             V_PRIORITY => self.priority.into(),
+            V_QUEUE_SIZE => self.size.into(),
             _ => "".into(),
         }
     }
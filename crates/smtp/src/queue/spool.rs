@@ -31,8 +31,10 @@ use utils::BlobHash;
 
 use crate::core::SMTP;
 
+use super::timeline::Disposition;
 use super::{
-    Domain, Event, Message, QueueEnvelope, QueueId, QuotaKey, Recipient, Schedule, Status,
+    DeadLetter, Domain, Event, Message, QueueEnvelope, QueueId, QuotaKey, Recipient, Schedule,
+    Status, MESSAGE_ON_HOLD, RCPT_DSN_SENT, RCPT_STATUS_CHANGED,
 };
 
 pub const LOCK_EXPIRY: u64 = 300;
@@ -68,6 +70,7 @@ impl SMTP {
             size: 0,
             blob_hash: Default::default(),
             quota_keys: Vec::new(),
+            failures: Vec::new(),
         }
     }
 
@@ -184,6 +187,30 @@ impl SMTP {
             }
         }
     }
+
+    pub async fn read_dead_letter(&self, id: QueueId) -> Option<DeadLetter> {
+        match self
+            .core
+            .storage
+            .data
+            .get_value::<Bincode<DeadLetter>>(ValueKey::from(ValueClass::Queue(
+                QueueClass::DeadLetter(id),
+            )))
+            .await
+        {
+            Ok(Some(dead_letter)) => Some(dead_letter.inner),
+            Ok(None) => None,
+            Err(err) => {
+                tracing::error!(
+                    context = "queue",
+                    event = "error",
+                    "Failed to read dead letter from store: {}",
+                    err
+                );
+                None
+            }
+        }
+    }
 }
 
 impl Message {
@@ -263,6 +290,8 @@ impl Message {
         );
 
         // Write message to queue
+        let queue_id = self.id;
+        let timeline = core.has_timeline().then(|| self.timeline_queued());
         let mut batch = BatchBuilder::new();
 
         // Reserve quotas
@@ -302,7 +331,7 @@ impl Message {
                 BlobOp::Commit {
                     hash: self.blob_hash.clone(),
                 },
-                vec![],
+                now().serialize(),
             )
             .set(
                 ValueClass::Queue(QueueClass::Message(self.id)),
@@ -319,6 +348,9 @@ impl Message {
             );
             return false;
         }
+        if let Some(timeline) = timeline {
+            core.timeline_add(queue_id, timeline).await;
+        }
 
         // Queue the message
         if core.inner.queue_tx.send(Event::Reload).await.is_err() {
@@ -434,6 +466,71 @@ impl Message {
         }
     }
 
+    pub fn is_on_hold(&self) -> bool {
+        (self.flags & MESSAGE_ON_HOLD) != 0
+    }
+
+    pub async fn hold(mut self, core: &SMTP, prev_event: u64) -> bool {
+        self.flags |= MESSAGE_ON_HOLD;
+
+        // Remove the queue event so the message is not picked up for delivery
+        let mut batch = BatchBuilder::new();
+        batch
+            .clear(ValueClass::Queue(QueueClass::MessageEvent(QueueEvent {
+                due: prev_event,
+                queue_id: self.id,
+            })))
+            .set(
+                ValueClass::Queue(QueueClass::Message(self.id)),
+                Bincode::new(self).serialize(),
+            );
+
+        if let Err(err) = core.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "queue",
+                event = "error",
+                "Failed to update queued message: {}",
+                err
+            );
+            false
+        } else {
+            true
+        }
+    }
+
+    pub async fn release(mut self, core: &SMTP) -> bool {
+        self.flags &= !MESSAGE_ON_HOLD;
+
+        // Schedule the message again
+        let mut batch = BatchBuilder::new();
+        if let Some(next_event) = self.next_event() {
+            batch.set(
+                ValueClass::Queue(QueueClass::MessageEvent(QueueEvent {
+                    due: next_event,
+                    queue_id: self.id,
+                })),
+                0u64.serialize(),
+            );
+        }
+        batch.set(
+            ValueClass::Queue(QueueClass::Message(self.id)),
+            Bincode::new(self).serialize(),
+        );
+
+        if let Err(err) = core.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "queue",
+                event = "error",
+                "Failed to update queued message: {}",
+                err
+            );
+            false
+        } else {
+            let _ = core.inner.queue_tx.send(Event::Reload).await;
+            true
+        }
+    }
+
     pub async fn remove(self, core: &SMTP, prev_event: u64) -> bool {
         let mut batch = BatchBuilder::new();
 
@@ -475,4 +572,203 @@ impl Message {
             true
         }
     }
+
+    pub fn has_failed_delivery(&self) -> bool {
+        self.recipients.iter().any(|rcpt| {
+            matches!(
+                rcpt.status,
+                Status::PermanentFailure(_) | Status::TemporaryFailure(_)
+            )
+        })
+    }
+
+    pub async fn remove_or_archive(self, core: &SMTP, prev_event: u64) -> bool {
+        let queue_id = self.id;
+        let (result, disposition) = if self.has_failed_delivery()
+            && core
+                .core
+                .eval_if(
+                    &core.core.smtp.queue.dead_letter,
+                    &QueueEnvelope::new(&self, 0),
+                )
+                .await
+                .unwrap_or(false)
+        {
+            (
+                self.archive(core, prev_event).await,
+                Disposition::DeadLetter,
+            )
+        } else {
+            let disposition = self.disposition();
+            (self.remove(core, prev_event).await, disposition)
+        };
+        core.timeline_complete(queue_id, disposition).await;
+
+        result
+    }
+
+    pub async fn archive(mut self, core: &SMTP, prev_event: u64) -> bool {
+        let mut batch = BatchBuilder::new();
+
+        // Release all quotas, dead letters do not count towards them.
+        // The keys are kept so they can be reserved again on requeue.
+        let quota_keys = std::mem::take(&mut self.quota_keys);
+        for quota_key in &quota_keys {
+            match quota_key {
+                QuotaKey::Count { key, .. } => {
+                    batch.add(ValueClass::Queue(QueueClass::QuotaCount(key.clone())), -1);
+                }
+                QuotaKey::Size { key, .. } => {
+                    batch.add(
+                        ValueClass::Queue(QueueClass::QuotaSize(key.clone())),
+                        -(self.size as i64),
+                    );
+                }
+            }
+        }
+
+        // Move the message to the dead letter store, keeping the blob linked
+        let id = self.id;
+        batch
+            .clear(ValueClass::Queue(QueueClass::MessageEvent(QueueEvent {
+                due: prev_event,
+                queue_id: id,
+            })))
+            .clear(ValueClass::Queue(QueueClass::Message(id)))
+            .set(
+                ValueClass::Queue(QueueClass::DeadLetter(id)),
+                Bincode::new(DeadLetter {
+                    message: self,
+                    quota_keys,
+                    archived: now(),
+                })
+                .serialize(),
+            );
+
+        if let Err(err) = core.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "queue",
+                event = "error",
+                "Failed to move message to dead letter store: {}",
+                err
+            );
+            false
+        } else {
+            tracing::info!(
+                context = "queue",
+                event = "dead-letter",
+                id = id,
+                "Undeliverable message moved to dead letter store."
+            );
+            true
+        }
+    }
+}
+
+impl DeadLetter {
+    pub async fn requeue(self, core: &SMTP) -> bool {
+        let mut message = self.message;
+
+        // Reschedule all failed domains and recipients
+        for idx in 0..message.domains.len() {
+            if !matches!(
+                message.domains[idx].status,
+                Status::PermanentFailure(_) | Status::TemporaryFailure(_)
+            ) {
+                continue;
+            }
+
+            let expires = core
+                .core
+                .eval_if(
+                    &core.core.smtp.queue.expire,
+                    &QueueEnvelope::new(&message, idx),
+                )
+                .await
+                .unwrap_or_else(|| Duration::from_secs(5 * 86400));
+            let domain = &mut message.domains[idx];
+            domain.status = Status::Scheduled;
+            domain.retry = Schedule::now();
+            domain.notify = Schedule::later(expires + Duration::from_secs(10));
+            domain.expires = now() + expires.as_secs();
+
+            for rcpt in &mut message.recipients {
+                if rcpt.domain_idx == idx
+                    && matches!(
+                        rcpt.status,
+                        Status::PermanentFailure(_) | Status::TemporaryFailure(_)
+                    )
+                {
+                    rcpt.status = Status::Scheduled;
+                    rcpt.flags &= !(RCPT_DSN_SENT | RCPT_STATUS_CHANGED);
+                }
+            }
+        }
+
+        // Reserve the quotas released when the message was archived
+        let mut batch = BatchBuilder::new();
+        for quota_key in &self.quota_keys {
+            match quota_key {
+                QuotaKey::Count { key, .. } => {
+                    batch.add(ValueClass::Queue(QueueClass::QuotaCount(key.clone())), 1);
+                }
+                QuotaKey::Size { key, .. } => {
+                    batch.add(
+                        ValueClass::Queue(QueueClass::QuotaSize(key.clone())),
+                        message.size as i64,
+                    );
+                }
+            }
+        }
+        message.quota_keys = self.quota_keys;
+        batch.clear(ValueClass::Queue(QueueClass::DeadLetter(message.id)));
+        if let Some(next_event) = message.next_event() {
+            batch.set(
+                ValueClass::Queue(QueueClass::MessageEvent(QueueEvent {
+                    due: next_event,
+                    queue_id: message.id,
+                })),
+                0u64.serialize(),
+            );
+        }
+        batch.set(
+            ValueClass::Queue(QueueClass::Message(message.id)),
+            Bincode::new(message).serialize(),
+        );
+
+        if let Err(err) = core.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "queue",
+                event = "error",
+                "Failed to requeue dead letter: {}",
+                err
+            );
+            false
+        } else {
+            let _ = core.inner.queue_tx.send(Event::Reload).await;
+            true
+        }
+    }
+
+    pub async fn purge(self, core: &SMTP) -> bool {
+        let mut batch = BatchBuilder::new();
+        batch
+            .clear(BlobOp::LinkId {
+                hash: self.message.blob_hash.clone(),
+                id: self.message.id,
+            })
+            .clear(ValueClass::Queue(QueueClass::DeadLetter(self.message.id)));
+
+        if let Err(err) = core.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "queue",
+                event = "error",
+                "Failed to purge dead letter: {}",
+                err
+            );
+            false
+        } else {
+            true
+        }
+    }
 }
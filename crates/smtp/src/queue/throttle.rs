@@ -21,6 +21,8 @@
  * for more details.
 */
 
+use std::net::IpAddr;
+
 use common::{
     config::smtp::Throttle,
     expr::functions::ResolveVariable,
@@ -110,6 +112,45 @@ impl SMTP {
 
         Ok(())
     }
+
+    pub async fn is_warmup_allowed(
+        &self,
+        source_ip: IpAddr,
+        span: &tracing::Span,
+    ) -> Result<(), Error> {
+        for warmup in &self.core.smtp.queue.throttle.warmup {
+            if !warmup.source_ips.contains(&source_ip) {
+                continue;
+            }
+
+            let now = now();
+            if let Some(rate) = warmup.rate_at(now) {
+                let key = format!("warmup:{}:{}", warmup.id, source_ip).into_bytes();
+                if let Ok(Some(next_refill)) = self
+                    .core
+                    .storage
+                    .lookup
+                    .is_rate_allowed(&key, &rate, false)
+                    .await
+                {
+                    tracing::info!(
+                        parent: span,
+                        context = "throttle",
+                        event = "warmup-limit-exceeded",
+                        source_ip = %source_ip,
+                        max_requests = rate.requests,
+                        max_interval = rate.period.as_secs(),
+                        "Source IP warm-up limit exceeded."
+                    );
+                    return Err(Error::Rate {
+                        retry_at: now + next_refill,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Domain {
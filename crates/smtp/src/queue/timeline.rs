@@ -0,0 +1,304 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use store::{
+    write::{key::DeserializeBigEndian, now, BatchBuilder, Bincode, QueueClass, ValueClass},
+    Deserialize, IterateParams, Serialize, ValueKey, U64_LEN,
+};
+
+use crate::core::SMTP;
+
+use super::{Message, QueueId, Status};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TimelineEvent {
+    pub timestamp: u64,
+    pub event: TimelineEventType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TimelineEventType {
+    #[serde(rename = "accepted")]
+    Accepted {
+        remote_ip: String,
+        helo: String,
+        authenticated_as: Option<String>,
+        message_id: Option<String>,
+    },
+    #[serde(rename = "queued")]
+    Queued {
+        return_path: String,
+        recipients: Vec<String>,
+        size: usize,
+    },
+    #[serde(rename = "attempt")]
+    Attempt {
+        domain: String,
+        attempt: u32,
+        mx: Option<String>,
+        tls: bool,
+        status: Status<String, String>,
+        recipients: Vec<TimelineRecipient>,
+    },
+    #[serde(rename = "completed")]
+    Completed { disposition: Disposition },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TimelineRecipient {
+    pub address: String,
+    pub status: Status<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Disposition {
+    #[serde(rename = "delivered")]
+    Delivered,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "dead-letter")]
+    DeadLetter,
+    #[serde(rename = "removed")]
+    Removed,
+}
+
+/// Per-domain details of a delivery attempt collected while delivering.
+#[derive(Debug, Default)]
+pub struct AttemptDetails {
+    pub domain_idx: usize,
+    pub mx: Option<String>,
+    pub tls: bool,
+}
+
+impl SMTP {
+    pub fn has_timeline(&self) -> bool {
+        self.core.smtp.queue.timeline_retention.is_some()
+    }
+
+    pub async fn timeline_add(&self, queue_id: QueueId, event: TimelineEventType) {
+        if !self.has_timeline() {
+            return;
+        }
+
+        let mut batch = BatchBuilder::new();
+        if let TimelineEventType::Accepted {
+            message_id: Some(message_id),
+            ..
+        } = &event
+        {
+            batch.set(
+                ValueClass::Queue(QueueClass::TimelineIndex {
+                    message_id: message_id.clone(),
+                    queue_id,
+                }),
+                vec![],
+            );
+        }
+        batch.set(
+            ValueClass::Queue(QueueClass::Timeline {
+                queue_id,
+                seq: self.inner.snowflake_id.generate().unwrap_or_else(now),
+            }),
+            Bincode::new(TimelineEvent {
+                timestamp: now(),
+                event,
+            })
+            .serialize(),
+        );
+
+        if let Err(err) = self.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "queue",
+                event = "error",
+                "Failed to write delivery timeline event: {}",
+                err
+            );
+        }
+    }
+
+    pub async fn timeline_complete(&self, queue_id: QueueId, disposition: Disposition) {
+        let retention = if let Some(retention) = self.core.smtp.queue.timeline_retention {
+            retention
+        } else {
+            return;
+        };
+
+        // Record the final disposition and schedule the timeline for removal
+        self.timeline_add(queue_id, TimelineEventType::Completed { disposition })
+            .await;
+        let message_id = self
+            .timeline_read(queue_id)
+            .await
+            .into_iter()
+            .find_map(|event| match event.event {
+                TimelineEventType::Accepted { message_id, .. } => message_id,
+                _ => None,
+            })
+            .unwrap_or_default();
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            ValueClass::Queue(QueueClass::TimelineExpiry {
+                expires: now() + retention.as_secs(),
+                queue_id,
+            }),
+            message_id.into_bytes(),
+        );
+        if let Err(err) = self.core.storage.data.write(batch.build()).await {
+            tracing::error!(
+                context = "queue",
+                event = "error",
+                "Failed to write delivery timeline expiration: {}",
+                err
+            );
+        }
+    }
+
+    pub async fn timeline_read(&self, queue_id: QueueId) -> Vec<TimelineEvent> {
+        let mut events = Vec::new();
+        let result = self
+            .core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Queue(QueueClass::Timeline { queue_id, seq: 0 })),
+                    ValueKey::from(ValueClass::Queue(QueueClass::Timeline {
+                        queue_id,
+                        seq: u64::MAX,
+                    })),
+                )
+                .ascending(),
+                |_, value| {
+                    events.push(Bincode::<TimelineEvent>::deserialize(value)?.inner);
+                    Ok(true)
+                },
+            )
+            .await;
+
+        if let Err(err) = result {
+            tracing::error!(
+                context = "queue",
+                event = "error",
+                "Failed to read delivery timeline: {}",
+                err
+            );
+        }
+
+        events.sort_by_key(|event| event.timestamp);
+        events
+    }
+
+    pub async fn timeline_find(&self, message_id: &str) -> Vec<QueueId> {
+        let mut queue_ids = Vec::new();
+        let key_len = message_id.len() + U64_LEN + 1;
+        let result = self
+            .core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Queue(QueueClass::TimelineIndex {
+                        message_id: message_id.to_string(),
+                        queue_id: 0,
+                    })),
+                    ValueKey::from(ValueClass::Queue(QueueClass::TimelineIndex {
+                        message_id: message_id.to_string(),
+                        queue_id: u64::MAX,
+                    })),
+                )
+                .no_values()
+                .ascending(),
+                |key, _| {
+                    // Skip Message-IDs sharing the same prefix
+                    if key.len() == key_len {
+                        queue_ids.push(key.deserialize_be_u64(key_len - U64_LEN)?);
+                    }
+                    Ok(true)
+                },
+            )
+            .await;
+
+        if let Err(err) = result {
+            tracing::error!(
+                context = "queue",
+                event = "error",
+                "Failed to read delivery timeline index: {}",
+                err
+            );
+        }
+
+        queue_ids
+    }
+}
+
+impl Message {
+    pub fn timeline_queued(&self) -> TimelineEventType {
+        TimelineEventType::Queued {
+            return_path: self.return_path.clone(),
+            recipients: self.recipients.iter().map(|r| r.address.clone()).collect(),
+            size: self.size,
+        }
+    }
+
+    pub fn timeline_attempt(&self, details: AttemptDetails) -> Option<TimelineEventType> {
+        let domain = self.domains.get(details.domain_idx)?;
+        Some(TimelineEventType::Attempt {
+            domain: domain.domain.clone(),
+            attempt: domain.retry.inner,
+            mx: details.mx,
+            tls: details.tls,
+            status: match &domain.status {
+                Status::Scheduled => Status::Scheduled,
+                Status::Completed(_) => Status::Completed(String::new()),
+                Status::TemporaryFailure(err) => Status::TemporaryFailure(err.to_string()),
+                Status::PermanentFailure(err) => Status::PermanentFailure(err.to_string()),
+            },
+            recipients: self
+                .recipients
+                .iter()
+                .filter(|rcpt| rcpt.domain_idx == details.domain_idx)
+                .map(|rcpt| TimelineRecipient {
+                    address: rcpt.address.clone(),
+                    status: match &rcpt.status {
+                        Status::Scheduled => Status::Scheduled,
+                        Status::Completed(status) => Status::Completed(status.response.to_string()),
+                        Status::TemporaryFailure(status) => {
+                            Status::TemporaryFailure(status.response.to_string())
+                        }
+                        Status::PermanentFailure(status) => {
+                            Status::PermanentFailure(status.response.to_string())
+                        }
+                    },
+                })
+                .collect(),
+        })
+    }
+
+    pub fn disposition(&self) -> Disposition {
+        if self.has_failed_delivery() {
+            Disposition::Failed
+        } else {
+            Disposition::Delivered
+        }
+    }
+}
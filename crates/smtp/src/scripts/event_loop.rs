@@ -175,6 +175,17 @@ impl SMTP {
                             }
                         }
 
+                        // Rewrite the envelope sender of redirected messages
+                        if let Some(srs_address) = handle.block_on(self.core.srs_forward(
+                            &message.return_path,
+                            message.domains.iter().map(|d| d.domain.as_str()),
+                        )) {
+                            message.return_path_lcase = srs_address.to_lowercase();
+                            message.return_path_domain =
+                                message.return_path_lcase.domain_part().to_string();
+                            message.return_path = srs_address;
+                        }
+
                         // Set notify flags
                         let mut flags = 0;
                         match notify {
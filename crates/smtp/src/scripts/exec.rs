@@ -66,6 +66,30 @@ impl<T: SessionStream> Session<T> {
             .set_variable("tls.version", tls_version)
             .set_variable("tls.cipher", tls_cipher)
             .set_variable("stage", stage);
+
+        // Combined weight and hits of the DNS block and allow lists and URL checks
+        let mut dnsbl_score = 0.0;
+        let mut dnsbl_lists = Vec::new();
+        for result in [&self.data.dnsbl_ip, &self.data.dnsbl_domain]
+            .into_iter()
+            .flatten()
+        {
+            dnsbl_score += result.score;
+            dnsbl_lists.extend(result.hits.iter().map(|id| Variable::from(id.clone())));
+        }
+        if let Some(result) = &self.data.url_reputation {
+            dnsbl_score += result.score;
+            dnsbl_lists.extend(
+                result
+                    .hits
+                    .iter()
+                    .map(|hit| Variable::from(hit.list.clone())),
+            );
+        }
+        params = params
+            .set_variable("dnsbl.score", dnsbl_score)
+            .set_variable("dnsbl.lists", dnsbl_lists);
+
         if let Some(ip_rev) = &self.data.iprev {
             params = params.set_variable("iprev.result", ip_rev.result().as_str());
             if let Some(ptr) = ip_rev.ptr.as_ref().and_then(|addrs| addrs.first()) {
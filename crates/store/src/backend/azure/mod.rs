@@ -0,0 +1,200 @@
+/*
+ * Copyright (c) 2023, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{io::Write, ops::Range};
+
+use azure_core::{error::ErrorKind, StatusCode};
+use azure_storage::{CloudLocation, StorageCredentials};
+use azure_storage_blobs::{
+    blob::{AccessTier, BlobBlockType, BlockList},
+    prelude::{BlobClient, BlockId, ClientBuilder, ContainerClient},
+};
+use futures::StreamExt;
+use utils::{
+    codec::base32_custom::Base32Writer,
+    config::{utils::AsKey, Config},
+};
+
+pub struct AzureStore {
+    container: ContainerClient,
+    prefix: Option<String>,
+    tier: Option<AccessTier>,
+    block_size: usize,
+}
+
+impl AzureStore {
+    pub async fn open(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
+        let prefix = prefix.as_key();
+        let account = config.value_require((&prefix, "account"))?.to_string();
+        let container = config.value_require((&prefix, "container"))?.to_string();
+
+        // Shared access signatures take precedence over account keys
+        let credentials = if let Some(sas_token) = config.value((&prefix, "sas-token")) {
+            StorageCredentials::sas_token(sas_token)
+                .map_err(|err| {
+                    config.new_build_error(
+                        prefix.as_str(),
+                        format!("Failed to parse SAS token: {err}"),
+                    )
+                })
+                .ok()?
+        } else if let Some(access_key) = config.value((&prefix, "access-key")) {
+            StorageCredentials::access_key(account.clone(), access_key.to_string())
+        } else {
+            config.new_build_error(
+                prefix.as_str(),
+                "Either a SAS token or an access key is required",
+            );
+            return None;
+        };
+
+        let tier = match config.value((&prefix, "tier")) {
+            Some("hot") => Some(AccessTier::Hot),
+            Some("cool") => Some(AccessTier::Cool),
+            Some(tier) => {
+                let err = format!("Invalid access tier {tier:?}, expected 'hot' or 'cool'");
+                config.new_parse_error((&prefix, "tier"), err);
+                return None;
+            }
+            None => None,
+        };
+        let block_size = config
+            .property_or_default::<usize>((&prefix, "block-size"), "4194304")
+            .unwrap_or(4 * 1024 * 1024)
+            .clamp(64 * 1024, 100 * 1024 * 1024);
+
+        // Custom endpoints are used by emulators and sovereign clouds
+        let builder = if let Some(endpoint) = config.value((&prefix, "endpoint")) {
+            ClientBuilder::with_location(
+                CloudLocation::Custom {
+                    account,
+                    uri: endpoint.trim_end_matches('/').to_string(),
+                },
+                credentials,
+            )
+        } else {
+            ClientBuilder::new(account, credentials)
+        };
+
+        Some(AzureStore {
+            container: builder.container_client(container),
+            prefix: config.value((&prefix, "key-prefix")).map(|s| s.to_string()),
+            tier,
+            block_size,
+        })
+    }
+
+    pub(crate) async fn get_blob(
+        &self,
+        key: &[u8],
+        range: Range<usize>,
+    ) -> crate::Result<Option<Vec<u8>>> {
+        let mut request = self.blob_client(key).get();
+        if range.start != 0 || range.end != usize::MAX {
+            request = request.range(range.start as u64..range.end as u64);
+        }
+
+        let mut stream = request.into_stream();
+        let mut data = Vec::new();
+        while let Some(response) = stream.next().await {
+            match response {
+                Ok(response) => {
+                    data.extend_from_slice(&response.data.collect().await?);
+                }
+                Err(err) if is_not_found(&err) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Some(data))
+    }
+
+    pub(crate) async fn put_blob(&self, key: &[u8], data: &[u8]) -> crate::Result<()> {
+        let blob = self.blob_client(key);
+
+        if data.len() <= self.block_size {
+            let mut request = blob.put_block_blob(data.to_vec());
+            if let Some(tier) = self.tier {
+                request = request.access_tier(tier);
+            }
+            request.await?;
+        } else {
+            // Large blobs are uploaded as a list of blocks
+            let mut block_list = BlockList::default();
+            for (block_num, chunk) in data.chunks(self.block_size).enumerate() {
+                let block_id = BlockId::new(format!("{block_num:08}"));
+                blob.put_block(block_id.clone(), chunk.to_vec()).await?;
+                block_list
+                    .blocks
+                    .push(BlobBlockType::new_uncommitted(block_id));
+            }
+            let mut request = blob.put_block_list(block_list);
+            if let Some(tier) = self.tier {
+                request = request.access_tier(tier);
+            }
+            request.await?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn delete_blob(&self, key: &[u8]) -> crate::Result<bool> {
+        match self.blob_client(key).delete().await {
+            Ok(_) => Ok(true),
+            Err(err) if is_not_found(&err) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn blob_client(&self, key: &[u8]) -> BlobClient {
+        self.container.blob_client(self.build_key(key))
+    }
+
+    fn build_key(&self, key: &[u8]) -> String {
+        if let Some(prefix) = &self.prefix {
+            let mut writer =
+                Base32Writer::with_raw_capacity(prefix.len() + ((key.len() + 3) / 4 * 5));
+            writer.push_string(prefix);
+            writer.write_all(key).unwrap();
+            writer.finalize()
+        } else {
+            Base32Writer::from_bytes(key).finalize()
+        }
+    }
+}
+
+fn is_not_found(err: &azure_core::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::HttpResponse {
+            status: StatusCode::NotFound,
+            ..
+        }
+    )
+}
+
+impl From<azure_core::Error> for crate::Error {
+    fn from(err: azure_core::Error) -> Self {
+        Self::InternalError(format!("Azure error: {}", err))
+    }
+}
@@ -31,6 +31,8 @@ use crate::fts::{Field, FtsFilter};
 
 use super::{ElasticSearchStore, INDEX_NAMES};
 
+const PAGE_SIZE: usize = 10000;
+
 impl ElasticSearchStore {
     pub async fn fts_query<T: Into<u8> + Display + Clone + std::fmt::Debug>(
         &self,
@@ -101,34 +103,51 @@ impl ElasticSearchStore {
             }
         }
 
-        // TODO implement pagination
-        let response = self
-            .index
-            .search(SearchParts::Index(&[
-                INDEX_NAMES[collection.into() as usize]
-            ]))
-            .body(json!({
+        // Page through the results, as a single search is capped at the index max result window
+        let index = INDEX_NAMES[collection.into() as usize];
+        let mut results = RoaringBitmap::new();
+        let mut search_after = None;
+
+        loop {
+            let mut query = json!({
                 "query": {
                     "bool": {
                         "must": conditions,
                     }
                 },
-                "size": 10000,
+                "size": PAGE_SIZE,
+                "sort": [{ "document_id": "asc" }],
+                "track_total_hits": false,
                 "_source": ["document_id"]
-            }))
-            .send()
-            .await?
-            .error_for_status_code()?;
+            });
+            if let Some(document_id) = search_after {
+                query["search_after"] = json!([document_id]);
+            }
 
-        let json: Value = response.json().await?;
-        let mut results = RoaringBitmap::new();
+            let response = self
+                .index
+                .search(SearchParts::Index(&[index]))
+                .body(query)
+                .send()
+                .await?
+                .error_for_status_code()?;
 
-        for hit in json["hits"]["hits"].as_array().ok_or_else(|| {
-            crate::Error::InternalError("Invalid response from ElasticSearch".to_string())
-        })? {
-            results.insert(hit["_source"]["document_id"].as_u64().ok_or_else(|| {
+            let json: Value = response.json().await?;
+            let hits = json["hits"]["hits"].as_array().ok_or_else(|| {
                 crate::Error::InternalError("Invalid response from ElasticSearch".to_string())
-            })? as u32);
+            })?;
+
+            for hit in hits {
+                let document_id = hit["_source"]["document_id"].as_u64().ok_or_else(|| {
+                    crate::Error::InternalError("Invalid response from ElasticSearch".to_string())
+                })?;
+                results.insert(document_id as u32);
+                search_after = Some(document_id);
+            }
+
+            if hits.len() < PAGE_SIZE {
+                break;
+            }
         }
 
         Ok(results)
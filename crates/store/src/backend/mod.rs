@@ -21,6 +21,8 @@
  * for more details.
 */
 
+#[cfg(feature = "azure")]
+pub mod azure;
 #[cfg(feature = "elastic")]
 pub mod elastic;
 #[cfg(feature = "foundation")]
@@ -29,10 +31,14 @@ pub mod fs;
 pub mod memory;
 #[cfg(feature = "mysql")]
 pub mod mysql;
+#[cfg(feature = "nats")]
+pub mod nats;
 #[cfg(feature = "postgres")]
 pub mod postgres;
 #[cfg(feature = "redis")]
 pub mod redis;
+#[cfg(any(feature = "postgres", feature = "mysql"))]
+pub mod replica;
 #[cfg(feature = "rocks")]
 pub mod rocksdb;
 #[cfg(feature = "s3")]
@@ -30,6 +30,8 @@ use crate::*;
 
 use super::MysqlStore;
 
+use crate::backend::replica::{split_host, Replicas};
+
 impl MysqlStore {
     pub async fn open(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
         let prefix = prefix.as_key();
@@ -87,8 +89,20 @@ impl MysqlStore {
             PoolOpts::default().with_constraints(PoolConstraints::new(pool_min, pool_max).unwrap()),
         );
 
+        // Replicas share the primary settings except for the host
+        let (hosts, mut replicas) = Replicas::parse(config, prefix.as_str());
+        for host in hosts {
+            let (host, port) = split_host(&host);
+            let mut opts = opts.clone().ip_or_hostname(host.to_string());
+            if let Some(port) = port {
+                opts = opts.tcp_port(port);
+            }
+            replicas.add(Pool::new(opts));
+        }
+
         let db = Self {
             conn_pool: Pool::new(opts),
+            replicas,
         };
 
         if let Err(err) = db.create_tables().await {
@@ -21,7 +21,9 @@
  * for more details.
 */
 
-use mysql_async::Pool;
+use mysql_async::{prelude::Queryable, Conn, Pool, Row};
+
+use super::replica::Replicas;
 
 pub mod blob;
 pub mod lookup;
@@ -31,6 +33,51 @@ pub mod write;
 
 pub struct MysqlStore {
     pub(crate) conn_pool: Pool,
+    pub(crate) replicas: Replicas<Pool>,
+}
+
+impl MysqlStore {
+    // Queries that opt in are served by the first replica within the allowed lag
+    pub(crate) async fn read_conn(&self, from_replica: bool) -> crate::Result<Conn> {
+        if from_replica {
+            for replica in self.replicas.iter() {
+                if self.replicas.needs_check(replica) {
+                    self.replicas
+                        .update_lag(replica, replication_lag(&replica.pool).await);
+                }
+
+                if self.replicas.is_available(replica) {
+                    match replica.pool.get_conn().await {
+                        Ok(conn) => return Ok(conn),
+                        Err(err) => {
+                            self.replicas.update_lag(replica, Err(err.into()));
+                        }
+                    }
+                }
+            }
+        }
+
+        self.conn_pool.get_conn().await.map_err(Into::into)
+    }
+}
+
+async fn replication_lag(pool: &Pool) -> crate::Result<std::time::Duration> {
+    let mut conn = pool.get_conn().await?;
+    match conn.query_first::<Row, _>("SHOW REPLICA STATUS").await? {
+        Some(row) => {
+            // A NULL lag means that replication is not running
+            let lag = row
+                .get_opt::<Option<u64>, _>("Seconds_Behind_Source")
+                .or_else(|| row.get_opt::<Option<u64>, _>("Seconds_Behind_Master"))
+                .transpose()?
+                .flatten()
+                .ok_or_else(|| {
+                    crate::Error::InternalError("Replication is not running".to_string())
+                })?;
+            Ok(std::time::Duration::from_secs(lag))
+        }
+        None => Ok(std::time::Duration::ZERO),
+    }
 }
 
 impl From<mysql_async::Error> for crate::Error {
@@ -60,12 +60,13 @@ impl MysqlStore {
     pub(crate) async fn get_bitmap(
         &self,
         mut key: BitmapKey<BitmapClass<u32>>,
+        from_replica: bool,
     ) -> crate::Result<Option<RoaringBitmap>> {
         let begin = key.serialize(0);
         key.document_id = u32::MAX;
         let key_len = begin.len();
         let end = key.serialize(0);
-        let mut conn = self.conn_pool.get_conn().await?;
+        let mut conn = self.read_conn(from_replica).await?;
         let table = char::from(key.subspace());
 
         let mut bm = RoaringBitmap::new();
@@ -87,7 +88,7 @@ impl MysqlStore {
         params: IterateParams<T>,
         mut cb: impl for<'x> FnMut(&'x [u8], &'x [u8]) -> crate::Result<bool> + Sync + Send,
     ) -> crate::Result<()> {
-        let mut conn = self.conn_pool.get_conn().await?;
+        let mut conn = self.read_conn(params.replica).await?;
         let table = char::from(params.begin.subspace());
         let begin = params.begin.serialize(0);
         let end = params.end.serialize(0);
@@ -0,0 +1,110 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use async_nats::{Client, ConnectOptions};
+use futures::StreamExt;
+use tokio::sync::mpsc;
+use utils::config::{utils::AsKey, Config};
+
+use crate::dispatch::pubsub::{PubSubStream, PUBSUB_BUFFER};
+
+pub struct NatsStore {
+    client: Client,
+}
+
+impl NatsStore {
+    pub async fn open(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
+        let prefix = prefix.as_key();
+        let url = config.value_require((&prefix, "url"))?.to_string();
+        let mut options = ConnectOptions::new()
+            .name("stalwart")
+            .connection_timeout(
+                config
+                    .property_or_default((&prefix, "timeout"), "10s")
+                    .unwrap_or_else(|| Duration::from_secs(10)),
+            )
+            .require_tls(
+                config
+                    .property_or_default((&prefix, "tls.enable"), "false")
+                    .unwrap_or_default(),
+            )
+            .subscription_capacity(PUBSUB_BUFFER)
+            .retry_on_initial_connect();
+        if let (Some(user), Some(password)) = (
+            config.value((&prefix, "user")),
+            config.value((&prefix, "password")),
+        ) {
+            options = options.user_and_password(user.to_string(), password.to_string());
+        }
+
+        match options.connect(url.as_str()).await {
+            Ok(client) => Some(NatsStore { client }),
+            Err(err) => {
+                config.new_build_error(
+                    prefix.as_str(),
+                    format!("Failed to connect to NATS server: {err}"),
+                );
+                None
+            }
+        }
+    }
+
+    pub async fn publish(&self, channel: &str, payload: Vec<u8>) -> crate::Result<()> {
+        self.client
+            .publish(channel.to_string(), payload.into())
+            .await
+            .map_err(|err| crate::Error::InternalError(format!("NATS publish failed: {err}")))
+    }
+
+    pub async fn subscribe(&self, channel: &str) -> crate::Result<PubSubStream> {
+        let mut subscriber = self
+            .client
+            .subscribe(channel.to_string())
+            .await
+            .map_err(|err| crate::Error::InternalError(format!("NATS subscribe failed: {err}")))?;
+
+        let (tx, rx) = mpsc::channel(PUBSUB_BUFFER);
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    message = subscriber.next() => {
+                        match message {
+                            Some(message) => {
+                                if tx.send(message.payload.to_vec()).await.is_err() {
+                                    break;
+                                }
+                            }
+                            None => break,
+                        }
+                    }
+                    _ = tx.closed() => break,
+                }
+            }
+            let _ = subscriber.unsubscribe().await;
+        });
+
+        Ok(PubSubStream::new(rx))
+    }
+}
@@ -27,6 +27,8 @@ use crate::{backend::postgres::tls::MakeRustlsConnect, *};
 
 use super::PostgresStore;
 
+use crate::backend::replica::{split_host, Replicas};
+
 use deadpool_postgres::{
     Config, CreatePoolError, ManagerConfig, PoolConfig, RecyclingMethod, Runtime,
 };
@@ -54,29 +56,65 @@ impl PostgresStore {
         if let Some(max_conn) = config.property::<usize>((&prefix, "pool.max-connections")) {
             cfg.pool = PoolConfig::new(max_conn).into();
         }
-        let db = Self {
-            conn_pool: if config
-                .property_or_default::<bool>((&prefix, "tls.enable"), "false")
-                .unwrap_or_default()
-            {
-                cfg.create_pool(
-                    Some(Runtime::Tokio1),
-                    MakeRustlsConnect::new(rustls_client_config(
-                        config
-                            .property_or_default((&prefix, "tls.allow-invalid-certs"), "false")
-                            .unwrap_or_default(),
-                    )),
+        let tls = config
+            .property_or_default::<bool>((&prefix, "tls.enable"), "false")
+            .unwrap_or_default()
+            .then(|| {
+                rustls_client_config(
+                    config
+                        .property_or_default((&prefix, "tls.allow-invalid-certs"), "false")
+                        .unwrap_or_default(),
                 )
+            });
+        let create_pool = |cfg: &Config| {
+            if let Some(tls) = &tls {
+                cfg.create_pool(Some(Runtime::Tokio1), MakeRustlsConnect::new(tls.clone()))
             } else {
                 cfg.create_pool(Some(Runtime::Tokio1), NoTls)
             }
-            .map_err(|e| {
-                config.new_build_error(
-                    prefix.as_str(),
-                    format!("Failed to create connection pool: {e}"),
-                )
-            })
-            .ok()?,
+        };
+
+        // Replicas share the primary settings except for the host
+        let (hosts, mut replicas) = Replicas::parse(config, prefix.as_str());
+        for host in hosts {
+            let (host, port) = split_host(&host);
+            let mut cfg = cfg.clone();
+            cfg.host = Some(host.to_string());
+            cfg.port = port.or(cfg.port);
+            replicas.add(
+                create_pool(&cfg)
+                    .map_err(|e| {
+                        config.new_build_error(
+                            (&prefix, "read-replicas.hosts"),
+                            format!("Failed to create replica connection pool: {e}"),
+                        )
+                    })
+                    .ok()?,
+            );
+        }
+
+        let listen = (
+            cfg.get_pg_config()
+                .map_err(|e| {
+                    config.new_build_error(
+                        prefix.as_str(),
+                        format!("Failed to build connection settings: {e}"),
+                    )
+                })
+                .ok()?,
+            tls.clone().map(MakeRustlsConnect::new),
+        );
+        let db = Self {
+            conn_pool: create_pool(&cfg)
+                .map_err(|e| {
+                    config.new_build_error(
+                        prefix.as_str(),
+                        format!("Failed to create connection pool: {e}"),
+                    )
+                })
+                .ok()?,
+            replicas,
+            listen,
         };
 
         if let Err(err) = db.create_tables().await {
@@ -21,17 +21,66 @@
  * for more details.
 */
 
-use deadpool_postgres::{Pool, PoolError};
+use deadpool_postgres::{Object, Pool, PoolError};
+
+use self::tls::MakeRustlsConnect;
+
+use super::replica::Replicas;
 
 pub mod blob;
 pub mod lookup;
 pub mod main;
+pub mod pubsub;
 pub mod read;
 pub mod tls;
 pub mod write;
 
 pub struct PostgresStore {
     pub(crate) conn_pool: Pool,
+    pub(crate) replicas: Replicas<Pool>,
+    // LISTEN requires a dedicated connection outside the pool
+    pub(crate) listen: (tokio_postgres::Config, Option<MakeRustlsConnect>),
+}
+
+impl PostgresStore {
+    // Queries that opt in are served by the first replica within the allowed lag
+    pub(crate) async fn read_conn(&self, from_replica: bool) -> crate::Result<Object> {
+        if from_replica {
+            for replica in self.replicas.iter() {
+                if self.replicas.needs_check(replica) {
+                    self.replicas
+                        .update_lag(replica, replication_lag(&replica.pool).await);
+                }
+
+                if self.replicas.is_available(replica) {
+                    match replica.pool.get().await {
+                        Ok(conn) => return Ok(conn),
+                        Err(err) => {
+                            self.replicas.update_lag(replica, Err(err.into()));
+                        }
+                    }
+                }
+            }
+        }
+
+        self.conn_pool.get().await.map_err(Into::into)
+    }
+}
+
+async fn replication_lag(pool: &Pool) -> crate::Result<std::time::Duration> {
+    let conn = pool.get().await?;
+    let lag: f64 = conn
+        .query_one(
+            concat!(
+                "SELECT CASE WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0 ",
+                "ELSE COALESCE(EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp()), 0) ",
+                "END::FLOAT8"
+            ),
+            &[],
+        )
+        .await?
+        .try_get(0)?;
+    Ok(std::time::Duration::from_secs_f64(lag.max(0.0)))
 }
 
 impl From<PoolError> for crate::Error {
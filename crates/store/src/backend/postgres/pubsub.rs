@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use futures::StreamExt;
+use tokio::sync::{mpsc, oneshot};
+use tokio_postgres::{tls::MakeTlsConnect, AsyncMessage, NoTls, Socket};
+
+use crate::dispatch::pubsub::{PubSubStream, PUBSUB_BUFFER};
+
+use super::PostgresStore;
+
+impl PostgresStore {
+    pub async fn publish(&self, channel: &str, payload: Vec<u8>) -> crate::Result<()> {
+        let conn = self.conn_pool.get().await?;
+        let s = conn.prepare_cached("SELECT pg_notify($1, $2)").await?;
+        conn.execute(&s, &[&channel, &String::from_utf8_lossy(&payload).as_ref()])
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    pub async fn subscribe(&self, channel: &str) -> crate::Result<PubSubStream> {
+        let (config, tls) = &self.listen;
+        if let Some(tls) = tls {
+            listen(config, tls.clone(), channel).await
+        } else {
+            listen(config, NoTls, channel).await
+        }
+    }
+}
+
+async fn listen<T>(
+    config: &tokio_postgres::Config,
+    tls: T,
+    channel: &str,
+) -> crate::Result<PubSubStream>
+where
+    T: MakeTlsConnect<Socket> + 'static,
+    T::Stream: Send + 'static,
+{
+    let (client, mut connection) = config.connect(tls).await?;
+    let (tx, rx) = mpsc::channel(PUBSUB_BUFFER);
+    let (done_tx, done_rx) = oneshot::channel::<()>();
+
+    // Notifications are delivered through the connection, which has to be
+    // polled for the LISTEN command to complete
+    tokio::spawn(async move {
+        let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+        loop {
+            tokio::select! {
+                message = messages.next() => {
+                    match message {
+                        Some(Ok(AsyncMessage::Notification(notification))) => {
+                            if tx
+                                .send(notification.payload().as_bytes().to_vec())
+                                .await
+                                .is_err()
+                            {
+                                break;
+                            }
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(err)) => {
+                            tracing::debug!(
+                                context = "pubsub",
+                                event = "error",
+                                reason = %err,
+                                "PostgreSQL notification connection failed."
+                            );
+                            break;
+                        }
+                        None => break,
+                    }
+                }
+                _ = tx.closed() => break,
+            }
+        }
+        drop(done_tx);
+    });
+
+    client
+        .batch_execute(&format!("LISTEN \"{}\"", channel.replace('"', "\"\"")))
+        .await?;
+
+    // Keep the client alive for as long as the connection is being polled
+    tokio::spawn(async move {
+        let _ = done_rx.await;
+        drop(client);
+    });
+
+    Ok(PubSubStream::new(rx))
+}
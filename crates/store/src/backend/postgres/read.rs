@@ -59,12 +59,13 @@ impl PostgresStore {
     pub(crate) async fn get_bitmap(
         &self,
         mut key: BitmapKey<BitmapClass<u32>>,
+        from_replica: bool,
     ) -> crate::Result<Option<RoaringBitmap>> {
         let begin = key.serialize(0);
         key.document_id = u32::MAX;
         let key_len = begin.len();
         let end = key.serialize(0);
-        let conn = self.conn_pool.get().await?;
+        let conn = self.read_conn(from_replica).await?;
         let table = char::from(key.subspace());
 
         let mut bm = RoaringBitmap::new();
@@ -89,7 +90,7 @@ impl PostgresStore {
         params: IterateParams<T>,
         mut cb: impl for<'x> FnMut(&'x [u8], &'x [u8]) -> crate::Result<bool> + Sync + Send,
     ) -> crate::Result<()> {
-        let conn = self.conn_pool.get().await?;
+        let conn = self.read_conn(params.replica).await?;
         let table = char::from(params.begin.subspace());
         let begin = params.begin.serialize(0);
         let end = params.end.serialize(0);
@@ -43,6 +43,10 @@ impl RedisStore {
                 self.key_set_(pool.get().await?.as_mut(), key, value, expires)
                     .await
             }
+            RedisPool::Sentinel(pool) => {
+                self.key_set_(pool.get().await?.as_mut(), key, value, expires)
+                    .await
+            }
         }
     }
 
@@ -61,6 +65,10 @@ impl RedisStore {
                 self.key_incr_(pool.get().await?.as_mut(), key, value, expires)
                     .await
             }
+            RedisPool::Sentinel(pool) => {
+                self.key_incr_(pool.get().await?.as_mut(), key, value, expires)
+                    .await
+            }
         }
     }
 
@@ -68,6 +76,7 @@ impl RedisStore {
         match &self.pool {
             RedisPool::Single(pool) => self.key_delete_(pool.get().await?.as_mut(), key).await,
             RedisPool::Cluster(pool) => self.key_delete_(pool.get().await?.as_mut(), key).await,
+            RedisPool::Sentinel(pool) => self.key_delete_(pool.get().await?.as_mut(), key).await,
         }
     }
 
@@ -78,6 +87,7 @@ impl RedisStore {
         match &self.pool {
             RedisPool::Single(pool) => self.key_get_(pool.get().await?.as_mut(), key).await,
             RedisPool::Cluster(pool) => self.key_get_(pool.get().await?.as_mut(), key).await,
+            RedisPool::Sentinel(pool) => self.key_get_(pool.get().await?.as_mut(), key).await,
         }
     }
 
@@ -85,6 +95,7 @@ impl RedisStore {
         match &self.pool {
             RedisPool::Single(pool) => self.counter_get_(pool.get().await?.as_mut(), key).await,
             RedisPool::Cluster(pool) => self.counter_get_(pool.get().await?.as_mut(), key).await,
+            RedisPool::Sentinel(pool) => self.counter_get_(pool.get().await?.as_mut(), key).await,
         }
     }
 
@@ -92,6 +103,7 @@ impl RedisStore {
         match &self.pool {
             RedisPool::Single(pool) => self.key_exists_(pool.get().await?.as_mut(), key).await,
             RedisPool::Cluster(pool) => self.key_exists_(pool.get().await?.as_mut(), key).await,
+            RedisPool::Sentinel(pool) => self.key_exists_(pool.get().await?.as_mut(), key).await,
         }
     }
 
@@ -29,12 +29,15 @@ use deadpool::{
 };
 use redis::{
     cluster::{ClusterClient, ClusterClientBuilder},
-    Client, RedisError,
+    sentinel::{Sentinel, SentinelNodeConnectionInfo},
+    Client, RedisConnectionInfo, RedisError,
 };
+use tokio::sync::Mutex;
 use utils::config::{utils::AsKey, Config};
 
 pub mod lookup;
 pub mod pool;
+pub mod pubsub;
 
 pub struct RedisStore {
     pool: RedisPool,
@@ -50,9 +53,17 @@ struct RedisClusterConnectionManager {
     timeout: Duration,
 }
 
+struct RedisSentinelConnectionManager {
+    sentinel: Mutex<Sentinel>,
+    master_name: String,
+    node_info: SentinelNodeConnectionInfo,
+    timeout: Duration,
+}
+
 enum RedisPool {
     Single(Pool<RedisConnectionManager>),
     Cluster(Pool<RedisClusterConnectionManager>),
+    Sentinel(Pool<RedisSentinelConnectionManager>),
 }
 
 impl RedisStore {
@@ -152,6 +163,56 @@ impl RedisStore {
                         ),
                     }
                 }
+                "sentinel" => {
+                    // The URLs point to the sentinels, which are asked for the current
+                    // master every time a new connection is opened
+                    let master_name = config.value_require((&prefix, "master-name"))?.to_string();
+                    let sentinel = Sentinel::build(urls)
+                        .map_err(|err| {
+                            config.new_build_error(
+                                prefix.as_str(),
+                                format!("Failed to open Redis sentinel client: {err:?}"),
+                            )
+                        })
+                        .ok()?;
+                    let node_info = SentinelNodeConnectionInfo {
+                        tls_mode: None,
+                        redis_connection_info: RedisConnectionInfo {
+                            db: config
+                                .property_or_default((&prefix, "db"), "0")
+                                .unwrap_or(0),
+                            username: config.value((&prefix, "user")).map(|s| s.to_string()),
+                            password: config.value((&prefix, "password")).map(|s| s.to_string()),
+                            ..Default::default()
+                        }
+                        .into(),
+                    };
+                    let timeout = config
+                        .property_or_default::<Duration>((&prefix, "timeout"), "10s")
+                        .unwrap_or_else(|| Duration::from_secs(10));
+
+                    Self {
+                        pool: RedisPool::Sentinel(
+                            build_pool(
+                                config,
+                                &prefix,
+                                RedisSentinelConnectionManager {
+                                    sentinel: Mutex::new(sentinel),
+                                    master_name,
+                                    node_info,
+                                    timeout,
+                                },
+                            )
+                            .map_err(|err| {
+                                config.new_build_error(
+                                    prefix.as_str(),
+                                    format!("Failed to build Redis pool: {err:?}"),
+                                )
+                            })
+                            .ok()?,
+                        ),
+                    }
+                }
                 invalid => {
                     let err = format!("Invalid Redis type {invalid:?}");
                     config.new_parse_error((&prefix, "redis-type"), err);
@@ -162,6 +223,16 @@ impl RedisStore {
     }
 }
 
+impl RedisSentinelConnectionManager {
+    async fn master_client(&self) -> Result<Client, RedisError> {
+        self.sentinel
+            .lock()
+            .await
+            .async_master_for(&self.master_name, Some(&self.node_info))
+            .await
+    }
+}
+
 fn build_pool<M: Manager>(
     config: &mut Config,
     prefix: &str,
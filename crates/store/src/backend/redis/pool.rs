@@ -28,7 +28,9 @@ use redis::{
     cluster_async::ClusterConnection,
 };
 
-use super::{RedisClusterConnectionManager, RedisConnectionManager};
+use super::{
+    RedisClusterConnectionManager, RedisConnectionManager, RedisSentinelConnectionManager,
+};
 
 #[async_trait]
 impl managed::Manager for RedisConnectionManager {
@@ -83,3 +85,43 @@ impl managed::Manager for RedisClusterConnectionManager {
             .map_err(|err| managed::RecycleError::Backend(err.into()))
     }
 }
+
+#[async_trait]
+impl managed::Manager for RedisSentinelConnectionManager {
+    type Type = MultiplexedConnection;
+    type Error = crate::Error;
+
+    async fn create(&self) -> Result<MultiplexedConnection, crate::Error> {
+        match tokio::time::timeout(self.timeout, async {
+            self.master_client()
+                .await?
+                .get_multiplexed_tokio_connection()
+                .await
+        })
+        .await
+        {
+            Ok(conn) => conn.map_err(Into::into),
+            Err(_) => Err(crate::Error::InternalError(
+                "Redis connection timeout".into(),
+            )),
+        }
+    }
+
+    async fn recycle(
+        &self,
+        conn: &mut MultiplexedConnection,
+        _: &managed::Metrics,
+    ) -> managed::RecycleResult<crate::Error> {
+        // Discard connections to a node that was demoted after a failover
+        let role = redis::cmd("ROLE")
+            .query_async::<_, Vec<redis::Value>>(conn)
+            .await
+            .map_err(|err| managed::RecycleError::Backend(err.into()))?;
+        match role.first().map(redis::from_redis_value::<String>) {
+            Some(Ok(role)) if role == "master" => Ok(()),
+            _ => Err(managed::RecycleError::Message(
+                "Redis node is no longer a master".into(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use futures::StreamExt;
+use redis::AsyncCommands;
+use tokio::sync::mpsc;
+
+use crate::dispatch::pubsub::{PubSubStream, PUBSUB_BUFFER};
+
+use super::{RedisPool, RedisStore};
+
+impl RedisStore {
+    pub async fn publish(&self, channel: &str, payload: Vec<u8>) -> crate::Result<()> {
+        match &self.pool {
+            RedisPool::Single(pool) => pool
+                .get()
+                .await?
+                .as_mut()
+                .publish::<_, _, ()>(channel, payload)
+                .await
+                .map_err(Into::into),
+            RedisPool::Cluster(pool) => pool
+                .get()
+                .await?
+                .as_mut()
+                .publish::<_, _, ()>(channel, payload)
+                .await
+                .map_err(Into::into),
+            RedisPool::Sentinel(pool) => pool
+                .get()
+                .await?
+                .as_mut()
+                .publish::<_, _, ()>(channel, payload)
+                .await
+                .map_err(Into::into),
+        }
+    }
+
+    pub async fn subscribe(&self, channel: &str) -> crate::Result<PubSubStream> {
+        // Subscriptions need a dedicated connection, which the cluster client
+        // does not provide
+        let mut pubsub = match &self.pool {
+            RedisPool::Single(pool) => pool.manager().client.get_async_pubsub().await?,
+            RedisPool::Sentinel(pool) => {
+                pool.manager()
+                    .master_client()
+                    .await?
+                    .get_async_pubsub()
+                    .await?
+            }
+            RedisPool::Cluster(_) => {
+                return Err(crate::Error::InternalError(
+                    "Redis cluster does not support subscriptions".into(),
+                ))
+            }
+        };
+        pubsub.subscribe(channel).await?;
+
+        let (tx, rx) = mpsc::channel(PUBSUB_BUFFER);
+        tokio::spawn(async move {
+            let mut messages = pubsub.into_on_message();
+            loop {
+                tokio::select! {
+                    message = messages.next() => {
+                        match message {
+                            Some(message) => {
+                                if tx.send(message.get_payload_bytes().to_vec()).await.is_err() {
+                                    break;
+                                }
+                            }
+                            None => break,
+                        }
+                    }
+                    _ = tx.closed() => break,
+                }
+            }
+        });
+
+        Ok(PubSubStream::new(rx))
+    }
+}
@@ -0,0 +1,180 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use utils::config::{utils::AsKey, Config};
+
+use crate::write::now;
+
+pub(crate) struct Replicas<P> {
+    replicas: Vec<Replica<P>>,
+    next: AtomicUsize,
+    max_lag: Duration,
+    check_interval: u64,
+}
+
+pub(crate) struct Replica<P> {
+    pub pool: P,
+    checked_at: AtomicU64,
+    is_lagging: AtomicBool,
+}
+
+impl<P> Replicas<P> {
+    pub fn parse(config: &mut Config, prefix: impl AsKey) -> (Vec<String>, Self) {
+        let prefix = prefix.as_key();
+        let hosts = config
+            .values((&prefix, "read-replicas.hosts"))
+            .map(|(_, host)| host.to_string())
+            .collect::<Vec<_>>();
+
+        (
+            hosts,
+            Replicas {
+                replicas: Vec::new(),
+                next: AtomicUsize::new(0),
+                max_lag: config
+                    .property_or_default((&prefix, "read-replicas.max-lag"), "5s")
+                    .unwrap_or_else(|| Duration::from_secs(5)),
+                check_interval: config
+                    .property_or_default::<Duration>(
+                        (&prefix, "read-replicas.check-interval"),
+                        "10s",
+                    )
+                    .unwrap_or_else(|| Duration::from_secs(10))
+                    .as_secs(),
+            },
+        )
+    }
+
+    pub fn add(&mut self, pool: P) {
+        self.replicas.push(Replica {
+            pool,
+            checked_at: AtomicU64::new(0),
+            is_lagging: AtomicBool::new(false),
+        });
+    }
+
+    // Iterates the replicas in round-robin order
+    pub fn iter(&self) -> impl Iterator<Item = &Replica<P>> {
+        let start = if !self.replicas.is_empty() {
+            self.next.fetch_add(1, Ordering::Relaxed) % self.replicas.len()
+        } else {
+            0
+        };
+
+        self.replicas
+            .iter()
+            .skip(start)
+            .chain(self.replicas.iter().take(start))
+    }
+
+    pub fn needs_check(&self, replica: &Replica<P>) -> bool {
+        replica.checked_at.load(Ordering::Relaxed) + self.check_interval <= now()
+    }
+
+    pub fn update_lag(&self, replica: &Replica<P>, lag: crate::Result<Duration>) {
+        let is_lagging = match lag {
+            Ok(lag) => lag > self.max_lag,
+            Err(err) => {
+                tracing::debug!(
+                    context = "store",
+                    event = "replica-error",
+                    reason = %err,
+                    "Failed to obtain replication lag."
+                );
+                true
+            }
+        };
+        replica.is_lagging.store(is_lagging, Ordering::Relaxed);
+        replica.checked_at.store(now(), Ordering::Relaxed);
+    }
+
+    pub fn is_available(&self, replica: &Replica<P>) -> bool {
+        !replica.is_lagging.load(Ordering::Relaxed)
+    }
+}
+
+// Splits an optional port from a replica host
+pub(crate) fn split_host(host: &str) -> (&str, Option<u16>) {
+    host.rsplit_once(':')
+        .and_then(|(host, port)| port.parse().ok().map(|port| (host, Some(port))))
+        .unwrap_or((host, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::AtomicUsize, time::Duration};
+
+    use crate::{IndexKeyPrefix, IterateParams};
+
+    use super::{split_host, Replicas};
+
+    #[test]
+    fn replica_selection() {
+        let mut replicas = Replicas {
+            replicas: Vec::new(),
+            next: AtomicUsize::new(0),
+            max_lag: Duration::from_secs(5),
+            check_interval: 10,
+        };
+        for pool in 0..3u32 {
+            replicas.add(pool);
+        }
+
+        // Replicas are tried in round-robin order
+        for start in 0..6u32 {
+            assert_eq!(
+                replicas.iter().map(|r| r.pool).collect::<Vec<_>>(),
+                (0..3).map(|n| (start + n) % 3).collect::<Vec<_>>()
+            );
+        }
+
+        // Lagging or failed replicas are skipped until the next check
+        let replica = replicas.iter().find(|r| r.pool == 1).unwrap();
+        assert!(replicas.needs_check(replica));
+        replicas.update_lag(replica, Ok(Duration::from_secs(1)));
+        assert!(!replicas.needs_check(replica));
+        assert!(replicas.is_available(replica));
+        replicas.update_lag(replica, Ok(Duration::from_secs(6)));
+        assert!(!replicas.is_available(replica));
+        replicas.update_lag(replica, Err(crate::Error::InternalError("down".into())));
+        assert!(!replicas.is_available(replica));
+
+        // Iterations are served by the primary unless a query opts in
+        let key = IndexKeyPrefix {
+            account_id: 0,
+            collection: 0,
+            field: 0,
+        };
+        let params = IterateParams::new(key, key);
+        assert!(!params.replica);
+        assert!(params.set_replica(true).replica);
+
+        assert_eq!(split_host("db1:5433"), ("db1", Some(5433)));
+        assert_eq!(split_host("db1"), ("db1", None));
+    }
+}
@@ -23,12 +23,14 @@
 
 use std::path::PathBuf;
 
-use rocksdb::{ColumnFamilyDescriptor, MergeOperands, OptimisticTransactionDB, Options};
+use rocksdb::{
+    ColumnFamilyDescriptor, CompactionDecision, MergeOperands, OptimisticTransactionDB, Options,
+};
 
 use tokio::sync::oneshot;
 use utils::config::{utils::AsKey, Config};
 
-use crate::*;
+use crate::{write::now, *};
 
 use super::{RocksDbStore, CF_BLOBS};
 
@@ -86,6 +88,14 @@ impl RocksDbStore {
         );
         cfs.push(ColumnFamilyDescriptor::new(CF_BLOBS, cf_opts));
 
+        // Lookup values, expired keys are dropped during compaction
+        let mut cf_opts = Options::default();
+        cf_opts.set_compaction_filter("lookup_expiry", lookup_expiry_filter);
+        cfs.push(ColumnFamilyDescriptor::new(
+            std::str::from_utf8(&[SUBSPACE_LOOKUP_VALUE]).unwrap(),
+            cf_opts,
+        ));
+
         // Other cfs
         for subspace in [
             SUBSPACE_INDEXES,
@@ -94,7 +104,6 @@ impl RocksDbStore {
             SUBSPACE_FTS_QUEUE,
             SUBSPACE_BLOB_RESERVE,
             SUBSPACE_BLOB_LINK,
-            SUBSPACE_LOOKUP_VALUE,
             SUBSPACE_PROPERTY,
             SUBSPACE_SETTINGS,
             SUBSPACE_QUEUE_MESSAGE,
@@ -179,6 +188,19 @@ impl RocksDbStore {
     }
 }
 
+// Counter expiry markers have a zero expiry and are removed by the purge task,
+// which also clears the counter they refer to
+pub fn lookup_expiry_filter(_level: u32, _key: &[u8], value: &[u8]) -> CompactionDecision {
+    match value
+        .get(..U64_LEN)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_be_bytes)
+    {
+        Some(expiry) if expiry != 0 && expiry <= now() => CompactionDecision::Remove,
+        _ => CompactionDecision::Keep,
+    }
+}
+
 pub fn numeric_value_merge(
     _key: &[u8],
     value: Option<&[u8]>,
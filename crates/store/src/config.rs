@@ -21,19 +21,23 @@
  * for more details.
 */
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use utils::config::{cron::SimpleCron, utils::ParseValue, Config};
 
 use crate::{
     backend::fs::FsStore,
     write::purge::{PurgeSchedule, PurgeStore},
-    BlobStore, CompressionAlgo, FtsStore, LookupStore, QueryStore, Store, Stores,
+    BlobBackend, BlobStore, CompressionAlgo, FtsStore, LookupStore, PubSubStore, QueryStore, Store,
+    Stores, TieredBlobStore,
 };
 
 #[cfg(feature = "s3")]
 use crate::backend::s3::S3Store;
 
+#[cfg(feature = "azure")]
+use crate::backend::azure::AzureStore;
+
 #[cfg(feature = "postgres")]
 use crate::backend::postgres::PostgresStore;
 
@@ -55,6 +59,9 @@ use crate::backend::elastic::ElasticSearchStore;
 #[cfg(feature = "redis")]
 use crate::backend::redis::RedisStore;
 
+#[cfg(feature = "nats")]
+use crate::backend::nats::NatsStore;
+
 impl Stores {
     pub async fn parse_all(config: &mut Config) -> Self {
         let mut stores = Self::parse(config).await;
@@ -70,6 +77,7 @@ impl Stores {
 
     pub async fn parse_stores(&mut self, config: &mut Config) {
         let is_reload = !self.stores.is_empty();
+        let mut tiered_stores = Vec::new();
 
         for id in config
             .sub_keys("store", ".type")
@@ -95,9 +103,20 @@ impl Stores {
             };
             let prefix = ("store", id);
             let store_id = id.to_string();
-            let compression_algo = config
+            let compression_algo = match config
                 .property_or_default::<CompressionAlgo>(("store", id, "compression"), "none")
-                .unwrap_or(CompressionAlgo::None);
+                .unwrap_or(CompressionAlgo::None)
+            {
+                CompressionAlgo::Zstd(level) => CompressionAlgo::Zstd(
+                    config
+                        .property::<i32>(("store", id, "compression-level"))
+                        .unwrap_or(level),
+                ),
+                algo => algo,
+            };
+            let legacy_markers = config
+                .property_or_default(("store", id, "compression-legacy-markers"), "false")
+                .unwrap_or(false);
 
             match protocol.as_str() {
                 #[cfg(feature = "rocks")]
@@ -117,7 +136,9 @@ impl Stores {
                         self.fts_stores.insert(store_id.clone(), db.clone().into());
                         self.blob_stores.insert(
                             store_id.clone(),
-                            BlobStore::from(db.clone()).with_compression(compression_algo),
+                            BlobStore::from(db.clone())
+                                .with_compression(compression_algo)
+                                .with_legacy_markers(legacy_markers),
                         );
                         self.lookup_stores.insert(store_id, db.into());
                     }
@@ -139,7 +160,9 @@ impl Stores {
                         self.fts_stores.insert(store_id.clone(), db.clone().into());
                         self.blob_stores.insert(
                             store_id.clone(),
-                            BlobStore::from(db.clone()).with_compression(compression_algo),
+                            BlobStore::from(db.clone())
+                                .with_compression(compression_algo)
+                                .with_legacy_markers(legacy_markers),
                         );
                         self.lookup_stores.insert(store_id, db.into());
                     }
@@ -147,11 +170,17 @@ impl Stores {
                 #[cfg(feature = "postgres")]
                 "postgresql" => {
                     if let Some(db) = PostgresStore::open(config, prefix).await.map(Store::from) {
+                        if let Store::PostgreSQL(store) = &db {
+                            self.pubsub_stores
+                                .insert(store_id.clone(), PubSubStore::PostgreSQL(store.clone()));
+                        }
                         self.stores.insert(store_id.clone(), db.clone());
                         self.fts_stores.insert(store_id.clone(), db.clone().into());
                         self.blob_stores.insert(
                             store_id.clone(),
-                            BlobStore::from(db.clone()).with_compression(compression_algo),
+                            BlobStore::from(db.clone())
+                                .with_compression(compression_algo)
+                                .with_legacy_markers(legacy_markers),
                         );
                         self.lookup_stores.insert(store_id.clone(), db.into());
                     }
@@ -163,7 +192,9 @@ impl Stores {
                         self.fts_stores.insert(store_id.clone(), db.clone().into());
                         self.blob_stores.insert(
                             store_id.clone(),
-                            BlobStore::from(db.clone()).with_compression(compression_algo),
+                            BlobStore::from(db.clone())
+                                .with_compression(compression_algo)
+                                .with_legacy_markers(legacy_markers),
                         );
                         self.lookup_stores.insert(store_id.clone(), db.into());
                     }
@@ -185,26 +216,48 @@ impl Stores {
                         self.fts_stores.insert(store_id.clone(), db.clone().into());
                         self.blob_stores.insert(
                             store_id.clone(),
-                            BlobStore::from(db.clone()).with_compression(compression_algo),
+                            BlobStore::from(db.clone())
+                                .with_compression(compression_algo)
+                                .with_legacy_markers(legacy_markers),
                         );
                         self.lookup_stores.insert(store_id.clone(), db.into());
                     }
                 }
                 "fs" => {
                     if let Some(db) = FsStore::open(config, prefix).await.map(BlobStore::from) {
-                        self.blob_stores
-                            .insert(store_id, db.with_compression(compression_algo));
+                        self.blob_stores.insert(
+                            store_id,
+                            db.with_compression(compression_algo)
+                                .with_legacy_markers(legacy_markers),
+                        );
                     }
                 }
                 #[cfg(feature = "s3")]
                 "s3" => {
                     if let Some(db) = S3Store::open(config, prefix).await.map(BlobStore::from) {
-                        self.blob_stores
-                            .insert(store_id, db.with_compression(compression_algo));
+                        self.blob_stores.insert(
+                            store_id,
+                            db.with_compression(compression_algo)
+                                .with_legacy_markers(legacy_markers),
+                        );
                     }
                 }
+                #[cfg(feature = "azure")]
+                "azure" => {
+                    if let Some(db) = AzureStore::open(config, prefix).await.map(BlobStore::from) {
+                        self.blob_stores.insert(
+                            store_id,
+                            db.with_compression(compression_algo)
+                                .with_legacy_markers(legacy_markers),
+                        );
+                    }
+                }
+                "tiered" => {
+                    // Tiered stores reference other blob stores
+                    tiered_stores.push(store_id);
+                }
                 #[cfg(feature = "elastic")]
-                "elasticsearch" => {
+                "elasticsearch" | "opensearch" => {
                     if let Some(db) = ElasticSearchStore::open(config, prefix)
                         .await
                         .map(FtsStore::from)
@@ -214,11 +267,16 @@ impl Stores {
                 }
                 #[cfg(feature = "redis")]
                 "redis" => {
-                    if let Some(db) = RedisStore::open(config, prefix)
-                        .await
-                        .map(LookupStore::from)
-                    {
-                        self.lookup_stores.insert(store_id, db);
+                    if let Some(db) = RedisStore::open(config, prefix).await.map(Arc::new) {
+                        self.pubsub_stores
+                            .insert(store_id.clone(), PubSubStore::Redis(db.clone()));
+                        self.lookup_stores.insert(store_id, LookupStore::Redis(db));
+                    }
+                }
+                #[cfg(feature = "nats")]
+                "nats" => {
+                    if let Some(db) = NatsStore::open(config, prefix).await.map(PubSubStore::from) {
+                        self.pubsub_stores.insert(store_id, db);
                     }
                 }
                 unknown => {
@@ -226,6 +284,46 @@ impl Stores {
                 }
             }
         }
+
+        for store_id in tiered_stores {
+            let mut tiers = Vec::with_capacity(2);
+            for tier in ["hot", "cold"] {
+                if let Some(tier_id) = config.value_require(("store", store_id.as_str(), tier)) {
+                    if let Some(blob_store) = self.blob_stores.get(tier_id) {
+                        tiers.push(blob_store.clone());
+                    } else {
+                        config.new_parse_error(
+                            ("store", store_id.as_str(), tier),
+                            "Blob store does not exist",
+                        );
+                    }
+                }
+            }
+
+            if let (Some(cold), Some(hot)) = (tiers.pop(), tiers.pop()) {
+                self.blob_stores.insert(
+                    store_id.clone(),
+                    TieredBlobStore {
+                        hot,
+                        cold,
+                        max_age: config
+                            .property::<Option<Duration>>((
+                                "store",
+                                store_id.as_str(),
+                                "tiering.max-age",
+                            ))
+                            .flatten(),
+                        archive: config
+                            .property_or_default(
+                                ("store", store_id.as_str(), "tiering.archive"),
+                                "false",
+                            )
+                            .unwrap_or_default(),
+                    }
+                    .into(),
+                );
+            }
+        }
     }
 
     pub async fn parse_lookups(&mut self, config: &mut Config) {
@@ -304,6 +402,23 @@ impl Stores {
                         blob_store: blob_store.clone(),
                     },
                 });
+
+                if matches!(blob_store.backend, BlobBackend::Tiered(_)) {
+                    let store_id = config.value("storage.blob").unwrap().to_string();
+                    self.purge_schedules.push(PurgeSchedule {
+                        cron: config
+                            .property_or_default::<SimpleCron>(
+                                ("store", store_id.as_str(), "tiering.frequency"),
+                                "0 2 *",
+                            )
+                            .unwrap_or_else(|| SimpleCron::parse_value("0 2 *").unwrap()),
+                        store_id,
+                        store: PurgeStore::BlobTiering {
+                            store: store.clone(),
+                            blob_store: blob_store.clone(),
+                        },
+                    });
+                }
             }
         }
         for (store_id, store) in &self.lookup_stores {
@@ -33,9 +33,13 @@ impl BlobStore {
         key: &[u8],
         range: Range<usize>,
     ) -> crate::Result<Option<Vec<u8>>> {
-        let read_range = match self.compression {
-            CompressionAlgo::None => range.clone(),
-            CompressionAlgo::Lz4 => 0..usize::MAX,
+        // Compressed blobs carry a marker at the end and have to be read in full,
+        // uncompressed stores push the range down unless legacy markers are enabled
+        let has_markers = self.has_markers();
+        let read_range = if has_markers {
+            0..usize::MAX
+        } else {
+            range.clone()
         };
 
         let result = match &self.backend {
@@ -55,39 +59,54 @@ impl BlobStore {
             BlobBackend::Fs(store) => store.get_blob(key, read_range).await,
             #[cfg(feature = "s3")]
             BlobBackend::S3(store) => store.get_blob(key, read_range).await,
+            #[cfg(feature = "azure")]
+            BlobBackend::Azure(store) => store.get_blob(key, read_range).await,
+            BlobBackend::Tiered(store) => {
+                // Each tier decompresses its own blobs
+                return match Box::pin(store.hot.get_blob(key, range.clone())).await? {
+                    Some(data) => Ok(Some(data)),
+                    None => Box::pin(store.cold.get_blob(key, range)).await,
+                };
+            }
         };
 
-        let decompressed = match self.compression {
-            CompressionAlgo::Lz4 => match result? {
-                Some(data)
-                    if data.last().copied().unwrap_or_default()
-                        == CompressionAlgo::Lz4.marker() =>
-                {
-                    lz4_flex::decompress_size_prepended(
-                        data.get(..data.len() - 1).unwrap_or_default(),
-                    )
+        if !has_markers {
+            return result;
+        }
+
+        // Blobs are decompressed based on their marker rather than the configured
+        // algorithm, so blobs written before a compression change remain readable
+        let data = match result? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        let decompressed = match data.last().copied().unwrap_or_default() {
+            marker if marker == CompressionAlgo::Lz4.marker() => {
+                lz4_flex::decompress_size_prepended(data.get(..data.len() - 1).unwrap_or_default())
                     .map_err(|err| {
                         crate::Error::InternalError(format!(
                             "Failed to decompress LZ4 data: {}",
                             err
                         ))
                     })?
-                }
-                Some(data) => {
-                    tracing::debug!("Warning: Missing LZ4 marker for key: {key:?}");
-                    data
-                }
-                None => return Ok(None),
-            },
-            _ => return result,
+            }
+            marker if marker == CompressionAlgo::Zstd(0).marker() => {
+                zstd::decode_all(data.get(..data.len() - 1).unwrap_or_default()).map_err(|err| {
+                    crate::Error::InternalError(format!("Failed to decompress Zstd data: {}", err))
+                })?
+            }
+            _ => {
+                tracing::debug!("Warning: Missing compression marker for key: {key:?}");
+                data
+            }
         };
 
-        if range.end >= decompressed.len() {
+        if range.start == 0 && range.end >= decompressed.len() {
             Ok(Some(decompressed))
         } else {
             Ok(Some(
                 decompressed
-                    .get(range.start..range.end)
+                    .get(range.start..std::cmp::min(range.end, decompressed.len()))
                     .unwrap_or_default()
                     .to_vec(),
             ))
@@ -102,6 +121,13 @@ impl BlobStore {
                 compressed.push(CompressionAlgo::Lz4.marker());
                 compressed.into()
             }
+            CompressionAlgo::Zstd(level) => {
+                let mut compressed = zstd::encode_all(data, level).map_err(|err| {
+                    crate::Error::InternalError(format!("Failed to compress Zstd data: {}", err))
+                })?;
+                compressed.push(self.compression.marker());
+                compressed.into()
+            }
         };
 
         match &self.backend {
@@ -121,6 +147,9 @@ impl BlobStore {
             BlobBackend::Fs(store) => store.put_blob(key, data.as_ref()).await,
             #[cfg(feature = "s3")]
             BlobBackend::S3(store) => store.put_blob(key, data.as_ref()).await,
+            #[cfg(feature = "azure")]
+            BlobBackend::Azure(store) => store.put_blob(key, data.as_ref()).await,
+            BlobBackend::Tiered(store) => Box::pin(store.hot.put_blob(key, data.as_ref())).await,
         }
     }
 
@@ -142,6 +171,13 @@ impl BlobStore {
             BlobBackend::Fs(store) => store.delete_blob(key).await,
             #[cfg(feature = "s3")]
             BlobBackend::S3(store) => store.delete_blob(key).await,
+            #[cfg(feature = "azure")]
+            BlobBackend::Azure(store) => store.delete_blob(key).await,
+            BlobBackend::Tiered(store) => {
+                let hot = Box::pin(store.hot.delete_blob(key)).await?;
+                let cold = Box::pin(store.cold.delete_blob(key)).await?;
+                Ok(hot || cold)
+            }
         }
     }
 
@@ -149,8 +185,21 @@ impl BlobStore {
         Self {
             backend: self.backend,
             compression,
+            legacy_markers: self.legacy_markers,
         }
     }
+
+    pub fn with_legacy_markers(self, legacy_markers: bool) -> Self {
+        Self {
+            backend: self.backend,
+            compression: self.compression,
+            legacy_markers,
+        }
+    }
+
+    fn has_markers(&self) -> bool {
+        !matches!(self.compression, CompressionAlgo::None) || self.legacy_markers
+    }
 }
 
 const MAGIC_MARKER: u8 = 0xa0;
@@ -159,7 +208,7 @@ impl CompressionAlgo {
     pub fn marker(&self) -> u8 {
         match self {
             CompressionAlgo::Lz4 => MAGIC_MARKER | 0x01,
-            //CompressionAlgo::Zstd => MAGIC_MARKER | 0x02,
+            CompressionAlgo::Zstd(_) => MAGIC_MARKER | 0x02,
             CompressionAlgo::None => 0,
         }
     }
@@ -169,7 +218,7 @@ impl ParseValue for CompressionAlgo {
     fn parse_value(value: &str) -> utils::config::Result<Self> {
         match value {
             "lz4" => Ok(CompressionAlgo::Lz4),
-            //"zstd" => Ok(CompressionAlgo::Zstd),
+            "zstd" => Ok(CompressionAlgo::Zstd(zstd::DEFAULT_COMPRESSION_LEVEL)),
             "none" | "false" | "disable" | "disabled" => Ok(CompressionAlgo::None),
             algo => Err(format!("Invalid compression algorithm: {algo}",)),
         }
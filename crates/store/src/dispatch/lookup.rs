@@ -107,6 +107,14 @@ impl LookupStore {
                 let mut batch = BatchBuilder::new();
 
                 if let Some(expires) = expires {
+                    // Restart counters that expired before being purged
+                    if is_counter_expired(store, &key).await? {
+                        batch.ops.push(Operation::Value {
+                            class: ValueClass::Lookup(LookupClass::Counter(key.clone())),
+                            op: ValueOp::Clear,
+                        });
+                    }
+
                     batch.ops.push(Operation::Value {
                         class: ValueClass::Lookup(LookupClass::Key(key.clone())),
                         op: ValueOp::Set(
@@ -213,11 +221,15 @@ impl LookupStore {
     pub async fn counter_get(&self, key: Vec<u8>) -> crate::Result<i64> {
         match self {
             LookupStore::Store(store) => {
-                store
-                    .get_counter(ValueKey::from(ValueClass::Lookup(LookupClass::Counter(
-                        key,
-                    ))))
-                    .await
+                if !is_counter_expired(store, &key).await? {
+                    store
+                        .get_counter(ValueKey::from(ValueClass::Lookup(LookupClass::Counter(
+                            key,
+                        ))))
+                        .await
+                } else {
+                    Ok(0)
+                }
             }
             #[cfg(feature = "redis")]
             LookupStore::Redis(store) => store.counter_get(key).await,
@@ -256,6 +268,18 @@ impl LookupStore {
         key: &[u8],
         rate: &Rate,
         soft_check: bool,
+    ) -> crate::Result<Option<u64>> {
+        self.is_cost_allowed(key, rate, 1, soft_check).await
+    }
+
+    /// Same as `is_rate_allowed` but consumes `cost` units of the rate
+    /// instead of a single request.
+    pub async fn is_cost_allowed(
+        &self,
+        key: &[u8],
+        rate: &Rate,
+        cost: u64,
+        soft_check: bool,
     ) -> crate::Result<Option<u64>> {
         let now = now();
         let range_start = now / rate.period.as_secs();
@@ -267,10 +291,10 @@ impl LookupStore {
         bucket.extend_from_slice(range_start.to_be_bytes().as_slice());
 
         let requests = if !soft_check {
-            self.counter_incr(bucket, 1, expires_in.into(), true)
+            self.counter_incr(bucket, cost as i64, expires_in.into(), true)
                 .await?
         } else {
-            self.counter_get(bucket).await? + 1
+            self.counter_get(bucket).await? + cost as i64
         };
 
         if requests <= rate.requests as i64 {
@@ -280,6 +304,16 @@ impl LookupStore {
         }
     }
 
+    pub async fn reset_rate(&self, key: &[u8], rate: &Rate) -> crate::Result<()> {
+        let range_start = now() / rate.period.as_secs();
+
+        let mut bucket = Vec::with_capacity(key.len() + U64_LEN);
+        bucket.extend_from_slice(key);
+        bucket.extend_from_slice(range_start.to_be_bytes().as_slice());
+
+        self.counter_delete(bucket).await
+    }
+
     pub async fn purge_lookup_store(&self) -> crate::Result<()> {
         match self {
             LookupStore::Store(store) => {
@@ -364,6 +398,29 @@ enum LookupValue<T> {
     None,
 }
 
+struct CounterExpiry(u64);
+
+// Counters are only purged periodically, so their expiry is also checked on access
+async fn is_counter_expired(store: &Store, key: &[u8]) -> crate::Result<bool> {
+    store
+        .get_value::<CounterExpiry>(ValueKey::from(ValueClass::Lookup(LookupClass::Key(
+            key.to_vec(),
+        ))))
+        .await
+        .map(|expiry| expiry.map_or(false, |expiry| expiry.0 <= now()))
+}
+
+impl Deserialize for CounterExpiry {
+    fn deserialize(bytes: &[u8]) -> crate::Result<Self> {
+        // Counter markers have a zero key expiry followed by the counter expiry
+        if bytes.deserialize_be_u64(0)? == 0 {
+            bytes.deserialize_be_u64(U64_LEN).map(CounterExpiry)
+        } else {
+            Ok(CounterExpiry(u64::MAX))
+        }
+    }
+}
+
 impl<T: Deserialize> Deserialize for LookupValue<T> {
     fn deserialize(bytes: &[u8]) -> crate::Result<Self> {
         bytes.deserialize_be_u64(0).and_then(|expires| {
@@ -28,6 +28,7 @@ use crate::Store;
 pub mod blob;
 pub mod fts;
 pub mod lookup;
+pub mod pubsub;
 pub mod store;
 
 impl Store {
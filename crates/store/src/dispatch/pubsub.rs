@@ -0,0 +1,78 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use tokio::sync::mpsc;
+
+use crate::PubSubStore;
+
+pub const PUBSUB_BUFFER: usize = 1024;
+
+// Messages received on a subscribed channel, the stream ends when the
+// underlying connection is lost.
+pub struct PubSubStream {
+    rx: mpsc::Receiver<Vec<u8>>,
+}
+
+impl PubSubStore {
+    pub async fn publish(&self, channel: &str, payload: Vec<u8>) -> crate::Result<()> {
+        match self {
+            #[cfg(feature = "nats")]
+            PubSubStore::Nats(store) => store.publish(channel, payload).await,
+            #[cfg(feature = "postgres")]
+            PubSubStore::PostgreSQL(store) => store.publish(channel, payload).await,
+            #[cfg(feature = "redis")]
+            PubSubStore::Redis(store) => store.publish(channel, payload).await,
+            PubSubStore::None => Err(crate::Error::InternalError(
+                "No pub/sub store configured".into(),
+            )),
+        }
+    }
+
+    pub async fn subscribe(&self, channel: &str) -> crate::Result<PubSubStream> {
+        match self {
+            #[cfg(feature = "nats")]
+            PubSubStore::Nats(store) => store.subscribe(channel).await,
+            #[cfg(feature = "postgres")]
+            PubSubStore::PostgreSQL(store) => store.subscribe(channel).await,
+            #[cfg(feature = "redis")]
+            PubSubStore::Redis(store) => store.subscribe(channel).await,
+            PubSubStore::None => Err(crate::Error::InternalError(
+                "No pub/sub store configured".into(),
+            )),
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, PubSubStore::None)
+    }
+}
+
+impl PubSubStream {
+    pub fn new(rx: mpsc::Receiver<Vec<u8>>) -> Self {
+        Self { rx }
+    }
+
+    pub async fn next(&mut self) -> Option<Vec<u8>> {
+        self.rx.recv().await
+    }
+}
@@ -21,18 +21,22 @@
  * for more details.
 */
 
-use std::ops::{BitAndAssign, Range};
+use std::{
+    ops::{BitAndAssign, Range},
+    time::Instant,
+};
 
 use roaring::RoaringBitmap;
+use utils::metrics::{STORE_READ_LATENCY, STORE_WRITE_LATENCY};
 
 use crate::{
     write::{
         key::{DeserializeBigEndian, KeySerializer},
         now, AnyClass, AnyKey, AssignedIds, Batch, BatchBuilder, BitmapClass, BitmapHash,
-        Operation, ReportClass, ValueClass, ValueOp,
+        Operation, QueueClass, ReportClass, ValueClass, ValueOp,
     },
     BitmapKey, Deserialize, IterateParams, Key, Store, ValueKey, SUBSPACE_BITMAP_ID,
-    SUBSPACE_BITMAP_TAG, SUBSPACE_BITMAP_TEXT, SUBSPACE_INDEXES, SUBSPACE_LOGS, U32_LEN,
+    SUBSPACE_BITMAP_TAG, SUBSPACE_BITMAP_TEXT, SUBSPACE_INDEXES, SUBSPACE_LOGS, U32_LEN, U64_LEN,
 };
 
 use super::DocumentSet;
@@ -48,7 +52,8 @@ impl Store {
     where
         U: Deserialize + 'static,
     {
-        match self {
+        let start_time = Instant::now();
+        let result = match self {
             #[cfg(feature = "sqlite")]
             Self::SQLite(store) => store.get_value(key).await,
             #[cfg(feature = "foundation")]
@@ -60,7 +65,9 @@ impl Store {
             #[cfg(feature = "rocks")]
             Self::RocksDb(store) => store.get_value(key).await,
             Self::None => Err(crate::Error::InternalError("No store configured".into())),
-        }
+        };
+        STORE_READ_LATENCY.observe(start_time.elapsed());
+        result
     }
 
     pub async fn get_bitmap(
@@ -73,22 +80,45 @@ impl Store {
             #[cfg(feature = "foundation")]
             Self::FoundationDb(store) => store.get_bitmap(key).await,
             #[cfg(feature = "postgres")]
-            Self::PostgreSQL(store) => store.get_bitmap(key).await,
+            Self::PostgreSQL(store) => store.get_bitmap(key, false).await,
             #[cfg(feature = "mysql")]
-            Self::MySQL(store) => store.get_bitmap(key).await,
+            Self::MySQL(store) => store.get_bitmap(key, false).await,
             #[cfg(feature = "rocks")]
             Self::RocksDb(store) => store.get_bitmap(key).await,
             Self::None => Err(crate::Error::InternalError("No store configured".into())),
         }
     }
 
+    // Bitmap reads issued by queries may be served by a read replica
+    pub(crate) async fn get_bitmap_from(
+        &self,
+        key: BitmapKey<BitmapClass<u32>>,
+        from_replica: bool,
+    ) -> crate::Result<Option<RoaringBitmap>> {
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::PostgreSQL(store) => store.get_bitmap(key, from_replica).await,
+            #[cfg(feature = "mysql")]
+            Self::MySQL(store) => store.get_bitmap(key, from_replica).await,
+            _ => self.get_bitmap(key).await,
+        }
+    }
+
     pub async fn get_bitmaps_intersection(
         &self,
         keys: Vec<BitmapKey<BitmapClass<u32>>>,
+    ) -> crate::Result<Option<RoaringBitmap>> {
+        self.get_bitmaps_intersection_from(keys, false).await
+    }
+
+    pub(crate) async fn get_bitmaps_intersection_from(
+        &self,
+        keys: Vec<BitmapKey<BitmapClass<u32>>>,
+        from_replica: bool,
     ) -> crate::Result<Option<RoaringBitmap>> {
         let mut result: Option<RoaringBitmap> = None;
         for key in keys {
-            if let Some(bitmap) = self.get_bitmap(key).await? {
+            if let Some(bitmap) = self.get_bitmap_from(key, from_replica).await? {
                 if let Some(result) = &mut result {
                     result.bitand_assign(&bitmap);
                     if result.is_empty() {
@@ -231,7 +261,8 @@ impl Store {
             return Ok(AssignedIds::default());
         }
 
-        match self {
+        let start_time = Instant::now();
+        let result = match self {
             #[cfg(feature = "sqlite")]
             Self::SQLite(store) => store.write(batch).await,
             #[cfg(feature = "foundation")]
@@ -243,7 +274,9 @@ impl Store {
             #[cfg(feature = "rocks")]
             Self::RocksDb(store) => store.write(batch).await,
             Self::None => Err(crate::Error::InternalError("No store configured".into())),
-        }
+        };
+        STORE_WRITE_LATENCY.observe(start_time.elapsed());
+        result
     }
 
     pub async fn purge_store(&self) -> crate::Result<()> {
@@ -274,6 +307,52 @@ impl Store {
         )
         .await?;
 
+        // Delete expired delivery timelines
+        let mut expired_timelines = Vec::new();
+        self.iterate(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Queue(QueueClass::TimelineExpiry {
+                    expires: 0,
+                    queue_id: 0,
+                })),
+                ValueKey::from(ValueClass::Queue(QueueClass::TimelineExpiry {
+                    expires: now,
+                    queue_id: u64::MAX,
+                })),
+            ),
+            |key, value| {
+                expired_timelines.push((
+                    key.deserialize_be_u64(1)?,
+                    key.deserialize_be_u64(U64_LEN + 1)?,
+                    String::from_utf8_lossy(value).into_owned(),
+                ));
+                Ok(true)
+            },
+        )
+        .await?;
+        for (expires, queue_id, message_id) in expired_timelines {
+            self.delete_range(
+                ValueKey::from(ValueClass::Queue(QueueClass::Timeline { queue_id, seq: 0 })),
+                ValueKey::from(ValueClass::Queue(QueueClass::Timeline {
+                    queue_id,
+                    seq: u64::MAX,
+                })),
+            )
+            .await?;
+            let mut batch = BatchBuilder::new();
+            if !message_id.is_empty() {
+                batch.clear(ValueClass::Queue(QueueClass::TimelineIndex {
+                    message_id,
+                    queue_id,
+                }));
+            }
+            batch.clear(ValueClass::Queue(QueueClass::TimelineExpiry {
+                expires,
+                queue_id,
+            }));
+            self.write(batch.build()).await?;
+        }
+
         match self {
             #[cfg(feature = "sqlite")]
             Self::SQLite(store) => store.purge_store().await,
@@ -668,6 +747,20 @@ impl Store {
         self.purge_blobs(blob_store).await.unwrap();
         self.purge_store().await.unwrap();
 
+        // Audit events are not tied to any account
+        self.delete_range(
+            ValueKey::from(ValueClass::Report(ReportClass::Audit {
+                id: 0,
+                timestamp: 0,
+            })),
+            ValueKey::from(ValueClass::Report(ReportClass::Audit {
+                id: u64::MAX,
+                timestamp: u64::MAX,
+            })),
+        )
+        .await
+        .unwrap();
+
         let store = self.clone();
         let mut failed = false;
 
@@ -64,6 +64,7 @@ pub(crate) enum Type {
 pub struct FtsDocument<'x, T: Into<u8> + Display + Clone + std::fmt::Debug> {
     pub(crate) parts: Vec<Text<'x, T>>,
     pub(crate) default_language: Language,
+    pub(crate) detect_language: bool,
     pub(crate) account_id: u32,
     pub(crate) collection: u8,
     pub(crate) document_id: u32,
@@ -74,12 +75,22 @@ impl<'x, T: Into<u8> + Display + Clone + std::fmt::Debug> FtsDocument<'x, T> {
         FtsDocument {
             parts: vec![],
             default_language,
+            detect_language: true,
             account_id: 0,
             document_id: 0,
             collection: 0,
         }
     }
 
+    // Indexes all text in the specified language, skipping detection
+    pub fn with_language_override(mut self, language: Option<Language>) -> Self {
+        if let Some(language) = language {
+            self.default_language = language;
+            self.detect_language = false;
+        }
+        self
+    }
+
     pub fn with_account_id(mut self, account_id: u32) -> Self {
         self.account_id = account_id;
         self
@@ -144,7 +155,9 @@ impl Store {
         for text in document.parts {
             match text.typ {
                 Type::Text(language) => {
-                    let language = if language == Language::Unknown {
+                    let language = if !document.detect_language {
+                        document.default_language
+                    } else if language == Language::Unknown {
                         detect.detect(&text.text, MIN_LANGUAGE_SCORE)
                     } else {
                         language
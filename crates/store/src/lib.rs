@@ -21,7 +21,7 @@
  * for more details.
 */
 
-use std::{borrow::Cow, fmt::Display, sync::Arc};
+use std::{borrow::Cow, fmt::Display, sync::Arc, time::Duration};
 
 pub mod backend;
 pub mod config;
@@ -42,6 +42,9 @@ use write::{purge::PurgeSchedule, BitmapClass, ValueClass};
 #[cfg(feature = "s3")]
 use backend::s3::S3Store;
 
+#[cfg(feature = "azure")]
+use backend::azure::AzureStore;
+
 #[cfg(feature = "postgres")]
 use backend::postgres::PostgresStore;
 
@@ -63,6 +66,9 @@ use backend::elastic::ElasticSearchStore;
 #[cfg(feature = "redis")]
 use backend::redis::RedisStore;
 
+#[cfg(feature = "nats")]
+use backend::nats::NatsStore;
+
 pub trait Deserialize: Sized + Sync + Send {
     fn deserialize(bytes: &[u8]) -> crate::Result<Self>;
 }
@@ -202,6 +208,7 @@ pub struct IterateParams<T: Key> {
     first: bool,
     ascending: bool,
     values: bool,
+    replica: bool,
 }
 
 #[derive(Clone, Default)]
@@ -210,6 +217,7 @@ pub struct Stores {
     pub blob_stores: AHashMap<String, BlobStore>,
     pub fts_stores: AHashMap<String, FtsStore>,
     pub lookup_stores: AHashMap<String, LookupStore>,
+    pub pubsub_stores: AHashMap<String, PubSubStore>,
     pub purge_schedules: Vec<PurgeSchedule>,
 }
 
@@ -233,12 +241,14 @@ pub enum Store {
 pub struct BlobStore {
     pub backend: BlobBackend,
     pub compression: CompressionAlgo,
+    pub legacy_markers: bool,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub enum CompressionAlgo {
     None,
     Lz4,
+    Zstd(i32),
 }
 
 #[derive(Clone)]
@@ -247,6 +257,16 @@ pub enum BlobBackend {
     Fs(Arc<FsStore>),
     #[cfg(feature = "s3")]
     S3(Arc<S3Store>),
+    #[cfg(feature = "azure")]
+    Azure(Arc<AzureStore>),
+    Tiered(Arc<TieredBlobStore>),
+}
+
+pub struct TieredBlobStore {
+    pub hot: BlobStore,
+    pub cold: BlobStore,
+    pub max_age: Option<Duration>,
+    pub archive: bool,
 }
 
 #[derive(Clone)]
@@ -265,6 +285,18 @@ pub enum LookupStore {
     Memory(Arc<MemoryStore>),
 }
 
+#[derive(Clone, Default)]
+pub enum PubSubStore {
+    #[cfg(feature = "nats")]
+    Nats(Arc<NatsStore>),
+    #[cfg(feature = "postgres")]
+    PostgreSQL(Arc<PostgresStore>),
+    #[cfg(feature = "redis")]
+    Redis(Arc<RedisStore>),
+    #[default]
+    None,
+}
+
 pub struct QueryStore {
     pub store: LookupStore,
     pub query: String,
@@ -310,6 +342,7 @@ impl From<FsStore> for BlobStore {
         BlobStore {
             backend: BlobBackend::Fs(Arc::new(store)),
             compression: CompressionAlgo::None,
+            legacy_markers: false,
         }
     }
 }
@@ -320,6 +353,18 @@ impl From<S3Store> for BlobStore {
         BlobStore {
             backend: BlobBackend::S3(Arc::new(store)),
             compression: CompressionAlgo::None,
+            legacy_markers: false,
+        }
+    }
+}
+
+#[cfg(feature = "azure")]
+impl From<AzureStore> for BlobStore {
+    fn from(store: AzureStore) -> Self {
+        BlobStore {
+            backend: BlobBackend::Azure(Arc::new(store)),
+            compression: CompressionAlgo::None,
+            legacy_markers: false,
         }
     }
 }
@@ -338,6 +383,13 @@ impl From<RedisStore> for LookupStore {
     }
 }
 
+#[cfg(feature = "nats")]
+impl From<NatsStore> for PubSubStore {
+    fn from(store: NatsStore) -> Self {
+        Self::Nats(Arc::new(store))
+    }
+}
+
 impl From<Store> for FtsStore {
     fn from(store: Store) -> Self {
         Self::Store(store)
@@ -349,6 +401,7 @@ impl From<Store> for BlobStore {
         BlobStore {
             backend: BlobBackend::Store(store),
             compression: CompressionAlgo::None,
+            legacy_markers: false,
         }
     }
 }
@@ -359,11 +412,22 @@ impl From<Store> for LookupStore {
     }
 }
 
+impl From<TieredBlobStore> for BlobStore {
+    fn from(store: TieredBlobStore) -> Self {
+        BlobStore {
+            backend: BlobBackend::Tiered(Arc::new(store)),
+            compression: CompressionAlgo::None,
+            legacy_markers: false,
+        }
+    }
+}
+
 impl Default for BlobStore {
     fn default() -> Self {
         Self {
             backend: BlobBackend::Store(Store::None),
             compression: CompressionAlgo::None,
+            legacy_markers: false,
         }
     }
 }
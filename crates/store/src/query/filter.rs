@@ -46,13 +46,38 @@ impl Store {
         collection: impl Into<u8> + Sync + Send,
         filters: Vec<Filter>,
     ) -> crate::Result<ResultSet> {
-        let collection = collection.into();
+        self.filter_(account_id, collection.into(), filters, false)
+            .await
+    }
+
+    // Same as filter but may be served by a read replica, only used by
+    // client queries which can tolerate slightly stale results.
+    pub async fn query_filter(
+        &self,
+        account_id: u32,
+        collection: impl Into<u8> + Sync + Send,
+        filters: Vec<Filter>,
+    ) -> crate::Result<ResultSet> {
+        self.filter_(account_id, collection.into(), filters, true)
+            .await
+    }
+
+    async fn filter_(
+        &self,
+        account_id: u32,
+        collection: u8,
+        filters: Vec<Filter>,
+        from_replica: bool,
+    ) -> crate::Result<ResultSet> {
         if filters.is_empty() {
             return Ok(ResultSet {
                 account_id,
                 collection,
                 results: self
-                    .get_bitmap(BitmapKey::document_ids(account_id, collection))
+                    .get_bitmap_from(
+                        BitmapKey::document_ids(account_id, collection),
+                        from_replica,
+                    )
                     .await?
                     .unwrap_or_else(RoaringBitmap::new),
             });
@@ -68,7 +93,7 @@ impl Store {
         while let Some(filter) = filters.next() {
             let mut result = match filter {
                 Filter::MatchValue { field, op, value } => {
-                    self.range_to_bitmap(account_id, collection, field, &value, op)
+                    self.range_to_bitmap(account_id, collection, field, &value, op, from_replica)
                         .await?
                 }
                 Filter::HasText {
@@ -77,7 +102,7 @@ impl Store {
                     tokenize,
                 } => {
                     if tokenize {
-                        self.get_bitmaps_intersection(
+                        self.get_bitmaps_intersection_from(
                             WordTokenizer::new(&text, MAX_TOKEN_LENGTH)
                                 .map(|token| token.word.into_owned())
                                 .collect::<HashSet<String>>()
@@ -86,20 +111,27 @@ impl Store {
                                     BitmapKey::text_token(account_id, collection, field, word)
                                 })
                                 .collect(),
+                            from_replica,
                         )
                         .await?
                     } else {
-                        self.get_bitmap(BitmapKey::text_token(account_id, collection, field, text))
-                            .await?
+                        self.get_bitmap_from(
+                            BitmapKey::text_token(account_id, collection, field, text),
+                            from_replica,
+                        )
+                        .await?
                     }
                 }
                 Filter::InBitmap(class) => {
-                    self.get_bitmap(BitmapKey {
-                        account_id,
-                        collection,
-                        class,
-                        document_id: 0,
-                    })
+                    self.get_bitmap_from(
+                        BitmapKey {
+                            account_id,
+                            collection,
+                            class,
+                            document_id: 0,
+                        },
+                        from_replica,
+                    )
                     .await?
                 }
                 Filter::DocumentSet(set) => Some(set),
@@ -122,7 +154,10 @@ impl Store {
             // Only fetch not mask if we need it
             if matches!(state.op, Filter::Not) && !not_fetch {
                 not_mask = self
-                    .get_bitmap(BitmapKey::document_ids(account_id, collection))
+                    .get_bitmap_from(
+                        BitmapKey::document_ids(account_id, collection),
+                        from_replica,
+                    )
                     .await?
                     .unwrap_or_else(RoaringBitmap::new);
                 not_fetch = true;
@@ -188,6 +223,7 @@ impl Store {
         field: u8,
         match_value: &[u8],
         op: Operator,
+        from_replica: bool,
     ) -> crate::Result<Option<RoaringBitmap>> {
         let (begin, end) = match op {
             Operator::LowerThan => (
@@ -281,7 +317,10 @@ impl Store {
         .serialize(0);
 
         self.iterate(
-            IterateParams::new(begin, end).no_values().ascending(),
+            IterateParams::new(begin, end)
+                .no_values()
+                .ascending()
+                .set_replica(from_replica),
             |key, _| {
                 if !key.starts_with(&prefix) {
                     return Ok(false);
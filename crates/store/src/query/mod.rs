@@ -252,6 +252,7 @@ impl<T: Key> IterateParams<T> {
             first: false,
             ascending: true,
             values: true,
+            replica: false,
         }
     }
 
@@ -284,4 +285,11 @@ impl<T: Key> IterateParams<T> {
         self.values = false;
         self
     }
+
+    // Allows the read to be served by a lagging read replica, which is
+    // only acceptable for queries and never for values that are asserted.
+    pub fn set_replica(mut self, replica: bool) -> Self {
+        self.replica = replica;
+        self
+    }
 }
@@ -25,11 +25,15 @@ use ahash::AHashSet;
 use utils::{BlobHash, BLOB_HASH_LEN};
 
 use crate::{
-    write::BatchBuilder, BlobClass, BlobStore, Deserialize, IterateParams, Store, ValueKey,
-    U32_LEN, U64_LEN,
+    write::BatchBuilder, BlobBackend, BlobClass, BlobStore, Deserialize, IterateParams, Serialize,
+    Store, TieredBlobStore, ValueKey, U32_LEN, U64_LEN,
 };
 
-use super::{key::DeserializeBigEndian, now, BlobOp, Operation, ValueClass, ValueOp};
+use super::{
+    assert::HashedValue, key::DeserializeBigEndian, now, BlobOp, Operation, ValueClass, ValueOp,
+};
+
+const BLOB_TIER_COLD: u8 = 1;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct BlobQuota {
@@ -37,6 +41,11 @@ pub struct BlobQuota {
     pub count: usize,
 }
 
+struct BlobCommit {
+    created: u64,
+    is_cold: bool,
+}
+
 impl Store {
     pub async fn blob_exists(
         &self,
@@ -253,6 +262,129 @@ impl Store {
         Ok(())
     }
 
+    pub async fn migrate_blobs(&self, blob_store: BlobStore) -> crate::Result<()> {
+        let (store, max_age) = match &blob_store.backend {
+            BlobBackend::Tiered(store) => match store.max_age {
+                Some(max_age) => (store.clone(), max_age.as_secs()),
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+
+        // Find committed blobs that are still in the hot tier
+        let from_key = ValueKey {
+            account_id: 0,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Blob(BlobOp::Commit {
+                hash: BlobHash::default(),
+            }),
+        };
+        let to_key = ValueKey {
+            account_id: 0,
+            collection: 0,
+            document_id: 0,
+            class: ValueClass::Blob(BlobOp::Commit {
+                hash: BlobHash::new_max(),
+            }),
+        };
+        let cutoff = now().saturating_sub(max_age);
+        let mut hashes = Vec::new();
+        self.iterate(
+            IterateParams::new(from_key, to_key).ascending(),
+            |key, value| {
+                if key.deserialize_be_u32(key.len() - U32_LEN)? == u32::MAX
+                    && key.get(BLOB_HASH_LEN + U32_LEN) == Some(&0)
+                {
+                    let commit = BlobCommit::deserialize(value)?;
+                    if !commit.is_cold && commit.created <= cutoff {
+                        hashes.push(
+                            BlobHash::try_from_hash_slice(key.get(0..BLOB_HASH_LEN).ok_or_else(
+                                || {
+                                    crate::Error::InternalError(format!(
+                                        "Invalid key {key:?} in blob hash tables"
+                                    ))
+                                },
+                            )?)
+                            .unwrap(),
+                        );
+                    }
+                }
+
+                Ok(true)
+            },
+        )
+        .await?;
+
+        // Move blobs to the cold tier
+        let mut migrated = 0;
+        for hash in hashes {
+            if self.migrate_blob(&store, &hash).await? {
+                migrated += 1;
+            }
+        }
+
+        tracing::debug!(
+            context = "blob_tiering",
+            event = "migrate",
+            count = migrated,
+            "Migrated blobs to cold storage."
+        );
+
+        Ok(())
+    }
+
+    pub async fn migrate_blob(
+        &self,
+        store: &TieredBlobStore,
+        hash: &BlobHash,
+    ) -> crate::Result<bool> {
+        let commit = match self
+            .get_value::<HashedValue<BlobCommit>>(ValueKey {
+                account_id: 0,
+                collection: 0,
+                document_id: 0,
+                class: ValueClass::Blob(BlobOp::Commit { hash: hash.clone() }),
+            })
+            .await?
+        {
+            Some(commit) if !commit.inner.is_cold => commit,
+            _ => return Ok(false),
+        };
+
+        // Copy the blob to the cold tier before removing it from the hot tier
+        let migrated = if let Some(data) = store.hot.get_blob(hash.as_ref(), 0..usize::MAX).await? {
+            store.cold.put_blob(hash.as_ref(), &data).await?;
+            true
+        } else {
+            false
+        };
+
+        // The commit may have been removed or updated while the blob was copied
+        let mut value = commit.inner.created.serialize();
+        value.push(BLOB_TIER_COLD);
+        let mut batch = BatchBuilder::new();
+        batch
+            .assert_value(BlobOp::Commit { hash: hash.clone() }, &commit)
+            .set(BlobOp::Commit { hash: hash.clone() }, value);
+        match self.write(batch.build()).await {
+            Ok(_) => (),
+            Err(crate::Error::AssertValueFailed) => {
+                if migrated {
+                    store.cold.delete_blob(hash.as_ref()).await?;
+                }
+                return Ok(false);
+            }
+            Err(err) => return Err(err),
+        }
+
+        if migrated {
+            store.hot.delete_blob(hash.as_ref()).await?;
+        }
+
+        Ok(migrated)
+    }
+
     pub async fn blob_hash_unlink_account(&self, account_id: u32) -> crate::Result<()> {
         // Validate linked blobs
         let from_key = ValueKey {
@@ -327,3 +459,13 @@ impl Store {
         Ok(())
     }
 }
+
+impl Deserialize for BlobCommit {
+    fn deserialize(bytes: &[u8]) -> crate::Result<Self> {
+        // Blobs committed before tiering was introduced have no timestamp
+        Ok(BlobCommit {
+            created: bytes.deserialize_be_u64(0).unwrap_or_default(),
+            is_cold: bytes.get(U64_LEN) == Some(&BLOB_TIER_COLD),
+        })
+    }
+}
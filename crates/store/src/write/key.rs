@@ -370,6 +370,20 @@ impl<T: ResolveId> ValueClass<T> {
                     .write(event.seq_id),
                 QueueClass::QuotaCount(key) => serializer.write(0u8).write(key.as_slice()),
                 QueueClass::QuotaSize(key) => serializer.write(1u8).write(key.as_slice()),
+                QueueClass::DeadLetter(queue_id) => serializer.write(7u8).write(*queue_id),
+                QueueClass::Timeline { queue_id, seq } => {
+                    serializer.write(4u8).write(*queue_id).write(*seq)
+                }
+                QueueClass::TimelineIndex {
+                    message_id,
+                    queue_id,
+                } => serializer
+                    .write(5u8)
+                    .write(message_id.as_bytes())
+                    .write(*queue_id),
+                QueueClass::TimelineExpiry { expires, queue_id } => {
+                    serializer.write(6u8).write(*expires).write(*queue_id)
+                }
             },
             ValueClass::Report(report) => match report {
                 ReportClass::Tls { id, expires } => {
@@ -381,7 +395,15 @@ impl<T: ResolveId> ValueClass<T> {
                 ReportClass::Arf { id, expires } => {
                     serializer.write(2u8).write(*expires).write(*id)
                 }
+                ReportClass::Audit { id, timestamp } => {
+                    serializer.write(3u8).write(*timestamp).write(*id)
+                }
             },
+            ValueClass::Snooze(due) => serializer
+                .write(8u8)
+                .write(*due)
+                .write(account_id)
+                .write(document_id),
             ValueClass::Any(any) => serializer.write(any.key.as_slice()),
         }
         .finalize()
@@ -558,8 +580,14 @@ impl<T> ValueClass<T> {
                     event.domain.len() + (U64_LEN * 3) + 1
                 }
                 QueueClass::QuotaCount(v) | QueueClass::QuotaSize(v) => v.len(),
+                QueueClass::DeadLetter(_) => U64_LEN + 1,
+                QueueClass::Timeline { .. } | QueueClass::TimelineExpiry { .. } => {
+                    (U64_LEN * 2) + 1
+                }
+                QueueClass::TimelineIndex { message_id, .. } => message_id.len() + U64_LEN + 1,
             },
             ValueClass::Report(_) => U64_LEN * 2 + 1,
+            ValueClass::Snooze(_) => U64_LEN + U32_LEN * 2 + 1,
             ValueClass::Any(v) => v.key.len(),
         }
     }
@@ -597,10 +625,14 @@ impl<T> ValueClass<T> {
                 QueueClass::DmarcReportHeader(_)
                 | QueueClass::TlsReportHeader(_)
                 | QueueClass::DmarcReportEvent(_)
-                | QueueClass::TlsReportEvent(_) => SUBSPACE_REPORT_OUT,
+                | QueueClass::TlsReportEvent(_)
+                | QueueClass::DeadLetter(_)
+                | QueueClass::Timeline { .. }
+                | QueueClass::TimelineIndex { .. }
+                | QueueClass::TimelineExpiry { .. } => SUBSPACE_REPORT_OUT,
                 QueueClass::QuotaCount(_) | QueueClass::QuotaSize(_) => SUBSPACE_QUOTA,
             },
-            ValueClass::Report(_) => SUBSPACE_REPORT_OUT,
+            ValueClass::Report(_) | ValueClass::Snooze(_) => SUBSPACE_REPORT_OUT,
             ValueClass::Any(any) => any.subspace,
         }
     }
@@ -170,6 +170,7 @@ pub enum ValueClass<T> {
     Config(Vec<u8>),
     Queue(QueueClass),
     Report(ReportClass),
+    Snooze(u64),
     Any(AnyClass),
 }
 
@@ -212,6 +213,10 @@ pub enum QueueClass {
     TlsReportEvent(ReportEvent),
     QuotaCount(Vec<u8>),
     QuotaSize(Vec<u8>),
+    DeadLetter(u64),
+    Timeline { queue_id: u64, seq: u64 },
+    TimelineIndex { message_id: String, queue_id: u64 },
+    TimelineExpiry { expires: u64, queue_id: u64 },
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
@@ -219,6 +224,7 @@ pub enum ReportClass {
     Tls { id: u64, expires: u64 },
     Dmarc { id: u64, expires: u64 },
     Arf { id: u64, expires: u64 },
+    Audit { id: u64, timestamp: u64 },
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
@@ -32,6 +32,7 @@ use crate::{BlobStore, LookupStore, Store};
 pub enum PurgeStore {
     Data(Store),
     Blobs { store: Store, blob_store: BlobStore },
+    BlobTiering { store: Store, blob_store: BlobStore },
     Lookup(LookupStore),
 }
 
@@ -68,6 +69,9 @@ impl PurgeSchedule {
                     PurgeStore::Blobs { store, blob_store } => {
                         store.purge_blobs(blob_store.clone()).await
                     }
+                    PurgeStore::BlobTiering { store, blob_store } => {
+                        store.migrate_blobs(blob_store.clone()).await
+                    }
                     PurgeStore::Lookup(store) => store.purge_lookup_store().await,
                 };
 
@@ -89,6 +93,7 @@ impl Display for PurgeStore {
         match self {
             PurgeStore::Data(_) => write!(f, "bitmaps"),
             PurgeStore::Blobs { .. } => write!(f, "blobs"),
+            PurgeStore::BlobTiering { .. } => write!(f, "blob tiering"),
             PurgeStore::Lookup(_) => write!(f, "expired keys"),
         }
     }
@@ -0,0 +1,242 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+pub static CONNECTIONS_TOTAL: LabeledCounter = LabeledCounter::new(
+    "stalwart_connections_total",
+    "Total number of accepted connections.",
+    "protocol",
+);
+pub static CONNECTIONS_ACTIVE: LabeledGauge = LabeledGauge::new(
+    "stalwart_connections_active",
+    "Number of connections currently open.",
+    "protocol",
+);
+pub static AUTH_TOTAL: LabeledCounter = LabeledCounter::new(
+    "stalwart_authentication_total",
+    "Total number of authentication attempts by outcome.",
+    "result",
+);
+pub static SENDING_LIMITS_EXCEEDED: LabeledCounter = LabeledCounter::new(
+    "stalwart_sending_limits_exceeded_total",
+    "Total number of submissions rejected for exceeding a sending limit.",
+    "limit",
+);
+pub static STORE_READ_LATENCY: Histogram = Histogram::new(
+    "stalwart_store_read_duration_seconds",
+    "Latency of data store reads.",
+);
+pub static STORE_WRITE_LATENCY: Histogram = Histogram::new(
+    "stalwart_store_write_duration_seconds",
+    "Latency of data store writes.",
+);
+
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+pub struct LabeledCounter {
+    name: &'static str,
+    help: &'static str,
+    label: &'static str,
+    values: RwLock<Vec<(String, Arc<AtomicU64>)>>,
+}
+
+pub struct LabeledGauge {
+    name: &'static str,
+    help: &'static str,
+    label: &'static str,
+    values: RwLock<Vec<(String, Arc<AtomicI64>)>>,
+}
+
+pub struct GaugeGuard(Arc<AtomicI64>);
+
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl LabeledCounter {
+    pub const fn new(name: &'static str, help: &'static str, label: &'static str) -> Self {
+        LabeledCounter {
+            name,
+            help,
+            label,
+            values: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn increment(&self, label: &str) {
+        get_or_insert(&self.values, label).fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} counter", self.name);
+        for (label, value) in self.values.read().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "{}{{{}=\"{}\"}} {}",
+                self.name,
+                self.label,
+                label,
+                value.load(Ordering::Relaxed)
+            );
+        }
+    }
+}
+
+impl LabeledGauge {
+    pub const fn new(name: &'static str, help: &'static str, label: &'static str) -> Self {
+        LabeledGauge {
+            name,
+            help,
+            label,
+            values: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Increments the gauge, decrementing it again when the guard is dropped.
+    pub fn guard(&self, label: &str) -> GaugeGuard {
+        let value = get_or_insert(&self.values, label);
+        value.fetch_add(1, Ordering::Relaxed);
+        GaugeGuard(value)
+    }
+
+    pub fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} gauge", self.name);
+        for (label, value) in self.values.read().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "{}{{{}=\"{}\"}} {}",
+                self.name,
+                self.label,
+                label,
+                value.load(Ordering::Relaxed)
+            );
+        }
+    }
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Histogram {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+        Histogram {
+            name,
+            help,
+            buckets: [ZERO; LATENCY_BUCKETS.len()],
+            sum_micros: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(pos) = LATENCY_BUCKETS.iter().position(|bucket| secs <= *bucket) {
+            self.buckets[pos].fetch_add(1, Ordering::Relaxed);
+        }
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} histogram", self.name);
+        let mut cumulative = 0;
+        for (bucket, count) in LATENCY_BUCKETS.iter().zip(self.buckets.iter()) {
+            cumulative += count.load(Ordering::Relaxed);
+            let _ = writeln!(
+                out,
+                "{}_bucket{{le=\"{}\"}} {}",
+                self.name, bucket, cumulative
+            );
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", self.name, count);
+        let _ = writeln!(
+            out,
+            "{}_sum {}",
+            self.name,
+            self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+        );
+        let _ = writeln!(out, "{}_count {}", self.name, count);
+    }
+}
+
+fn get_or_insert<T: Default>(values: &RwLock<Vec<(String, Arc<T>)>>, label: &str) -> Arc<T> {
+    if let Some((_, value)) = values
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(name, _)| name == label)
+    {
+        return value.clone();
+    }
+
+    let mut values = values.write().unwrap();
+    if let Some((_, value)) = values.iter().find(|(name, _)| name == label) {
+        value.clone()
+    } else {
+        let value = Arc::new(T::default());
+        values.push((label.to_string(), value.clone()));
+        value
+    }
+}
+
+/// Renders all registered metrics in the Prometheus text exposition format.
+pub fn render_prometheus() -> String {
+    let mut out = String::with_capacity(2048);
+    CONNECTIONS_TOTAL.render(&mut out);
+    CONNECTIONS_ACTIVE.render(&mut out);
+    AUTH_TOTAL.render(&mut out);
+    SENDING_LIMITS_EXCEEDED.render(&mut out);
+    STORE_READ_LATENCY.render(&mut out);
+    STORE_WRITE_LATENCY.render(&mut out);
+    out
+}
+
+pub fn render_gauge(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    let _ = writeln!(out, "{name} {value}");
+}
@@ -0,0 +1,246 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use directory::QueryBy;
+use mail_send::Credentials;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::watch,
+};
+
+use crate::directory::DirectoryTest;
+
+#[tokio::test]
+async fn http_directory() {
+    // Spawn mock HTTP directory
+    let shutdown = spawn_mock_http_directory();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    // Obtain directory handle
+    let mut config = DirectoryTest::new("sqlite".into()).await;
+    let handle = config.directories.directories.remove("http").unwrap();
+
+    // Authenticate
+    let principal = handle
+        .query(
+            QueryBy::Credentials(&Credentials::Plain {
+                username: "john".to_string(),
+                secret: "ok".to_string(),
+            }),
+            true,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(principal.name, "john");
+    assert_eq!(principal.emails, vec!["john@example.org".to_string()]);
+    assert_eq!(principal.description.as_deref(), Some("John Doe"));
+    assert_eq!(principal.quota, 1024);
+    assert_eq!(principal.member_of.len(), 1);
+    assert_eq!(principal.protocols, vec!["imap".to_string()]);
+    assert!(handle
+        .query(
+            QueryBy::Credentials(&Credentials::Plain {
+                username: "john".to_string(),
+                secret: "bad".to_string(),
+            }),
+            true,
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+    // Lookup by name and id
+    let principal = handle
+        .query(QueryBy::Name("john"), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(principal.member_of.is_empty());
+    assert_eq!(
+        handle
+            .query(QueryBy::Id(principal.id), false)
+            .await
+            .unwrap()
+            .unwrap()
+            .name,
+        "john"
+    );
+    assert!(handle
+        .query(QueryBy::Name("unknown"), false)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Address lookups
+    assert_eq!(
+        handle.email_to_ids("john@example.org").await.unwrap(),
+        vec![principal.id]
+    );
+    assert!(handle.rcpt("john@example.org").await.unwrap());
+    assert!(!handle.rcpt("jane@example.org").await.unwrap());
+    assert!(handle.is_local_domain("example.org").await.unwrap());
+    assert!(!handle.is_local_domain("other.org").await.unwrap());
+    assert_eq!(
+        handle.vrfy("john").await.unwrap(),
+        vec!["john@example.org".to_string()]
+    );
+    assert_eq!(
+        handle.expn("sales@example.org").await.unwrap(),
+        vec![
+            "john@example.org".to_string(),
+            "jane@example.org".to_string()
+        ]
+    );
+
+    // Shutdown
+    shutdown.send(false).ok();
+}
+
+pub fn spawn_mock_http_directory() -> watch::Sender<bool> {
+    let (tx, mut rx) = watch::channel(true);
+
+    tokio::spawn(async move {
+        let listener = TcpListener::bind("127.0.0.1:9196")
+            .await
+            .unwrap_or_else(|e| {
+                panic!("Failed to bind mock HTTP directory to 127.0.0.1:9196: {e}");
+            });
+        loop {
+            tokio::select! {
+                stream = listener.accept() => {
+                    match stream {
+                        Ok((stream, _)) => {
+                            tokio::spawn(accept_http(stream));
+                        }
+                        Err(err) => {
+                            panic!("Something went wrong: {err}" );
+                        }
+                    }
+                },
+                _ = rx.changed() => {
+                    break;
+                }
+            };
+        }
+    });
+
+    tx
+}
+
+async fn accept_http(mut stream: TcpStream) {
+    let mut buf = Vec::with_capacity(1024);
+    let mut buf_u8 = vec![0u8; 1024];
+
+    // Read headers and body
+    let (headers, body) = loop {
+        let br = stream.read(&mut buf_u8).await.unwrap();
+        if br == 0 {
+            return;
+        }
+        buf.extend_from_slice(&buf_u8[0..br]);
+        let request = String::from_utf8_lossy(&buf).into_owned();
+        if let Some((headers, body)) = request.split_once("\r\n\r\n") {
+            let content_length = headers
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    if name.eq_ignore_ascii_case("content-length") {
+                        value.trim().parse::<usize>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or(0);
+            if body.len() >= content_length {
+                break (headers.to_string(), body.to_string());
+            }
+        }
+    };
+    assert!(headers.contains("Bearer s3cr3t"), "{headers}");
+
+    let request = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+    let field = |name: &str| {
+        request
+            .get(name)
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+    };
+    let john = concat!(
+        r#"{"name":"john","type":"individual","description":"John Doe","#,
+        r#""quota":1024,"emails":["john@example.org"],"memberOf":["sales"],"#,
+        r#""protocols":["IMAP"]}"#
+    );
+    let (status, response) = match field("action") {
+        "authenticate" if field("username") == "john" && field("secret") == "ok" => {
+            ("200 OK", john)
+        }
+        "authenticate" => ("401 Unauthorized", "{}"),
+        "lookup" if field("name") == "john" => ("200 OK", john),
+        "lookup" => ("404 Not Found", ""),
+        "email_to_names" if field("address") == "john@example.org" => {
+            ("200 OK", r#"{"names":["john"]}"#)
+        }
+        "rcpt" => (
+            "200 OK",
+            if field("address") == "john@example.org" {
+                r#"{"exists":true}"#
+            } else {
+                r#"{"exists":false}"#
+            },
+        ),
+        "is_local_domain" => (
+            "200 OK",
+            if field("domain") == "example.org" {
+                r#"{"exists":true}"#
+            } else {
+                r#"{"exists":false}"#
+            },
+        ),
+        "vrfy" => ("200 OK", r#"{"addresses":["john@example.org"]}"#),
+        "expn" => (
+            "200 OK",
+            r#"{"addresses":["john@example.org","jane@example.org"]}"#,
+        ),
+        action => panic!("Unknown action: {action}"),
+    };
+
+    stream
+        .write_all(
+            format!(
+                concat!(
+                    "HTTP/1.1 {}\r\n",
+                    "Content-Type: application/json\r\n",
+                    "Content-Length: {}\r\n",
+                    "Connection: close\r\n\r\n{}"
+                ),
+                status,
+                response.len(),
+                response
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+}
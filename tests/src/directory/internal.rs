@@ -27,6 +27,7 @@ use directory::{
         lookup::DirectoryStore, manage::ManageDirectory, PrincipalField, PrincipalUpdate,
         PrincipalValue,
     },
+    core::{app_password::AppPassword, scram::is_scram_verifier, secret::SecretHash},
     DirectoryError, ManagementError, Principal, QueryBy, Type,
 };
 use jmap_proto::types::collection::Collection;
@@ -436,6 +437,13 @@ async fn internal_directory() {
                         PrincipalUpdate::add_item(
                             PrincipalField::Emails,
                             PrincipalValue::String("john.doe@example.org".to_string()),
+                        ),
+                        PrincipalUpdate::set(
+                            PrincipalField::Protocols,
+                            PrincipalValue::StringList(vec![
+                                "IMAP".to_string(),
+                                "smtp".to_string()
+                            ])
                         )
                     ],
                 )
@@ -463,6 +471,7 @@ async fn internal_directory() {
                 quota: 1024,
                 typ: Type::Superuser,
                 member_of: vec!["list".to_string(), "sales".to_string()],
+                protocols: vec!["imap".to_string(), "smtp".to_string()],
             }
         );
         assert_eq!(store.get_account_id("john").await.unwrap(), None);
@@ -692,5 +701,104 @@ async fn internal_directory() {
                 .unwrap(),
             Some("hello".to_string())
         );
+
+        // Weak password hashes should be upgraded
+        let bill_id = store
+            .create_account(
+                Principal {
+                    name: "bill".to_string(),
+                    secrets: vec![
+                        "{SHA}6pdm9DD8ZmaxxilEKOA5HEE4hgs=".to_string(),
+                        "app-password".to_string(),
+                    ],
+                    ..Default::default()
+                },
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert!(!store
+            .update_secret_if_weaker(bill_id, "wrong-secret", SecretHash::Argon2)
+            .await
+            .unwrap());
+        assert!(!store
+            .update_secret_if_weaker(bill_id, "app-password", SecretHash::Argon2)
+            .await
+            .unwrap());
+        assert!(store
+            .update_secret_if_weaker(bill_id, "weak-secret", SecretHash::Argon2)
+            .await
+            .unwrap());
+        assert!(!store
+            .update_secret_if_weaker(bill_id, "weak-secret", SecretHash::Argon2)
+            .await
+            .unwrap());
+        let principal = store
+            .query(
+                QueryBy::Credentials(&Credentials::Plain {
+                    username: "bill".to_string(),
+                    secret: "weak-secret".to_string(),
+                }),
+                false,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(principal.secrets[0].starts_with("$argon2"));
+        assert_eq!(principal.secrets[1], "app-password");
+
+        // Password changes keep application passwords and drop SCRAM verifiers
+        let carol_id = store
+            .create_account(
+                Principal {
+                    name: "carol".to_string(),
+                    secrets: vec!["old-secret".to_string()],
+                    ..Default::default()
+                },
+                vec![],
+            )
+            .await
+            .unwrap();
+        let (app_password, app_secret) = AppPassword::generate("Phone", &[]).unwrap();
+        assert!(store
+            .update_scram_verifier(carol_id, "old-secret")
+            .await
+            .unwrap());
+        store
+            .add_app_password(carol_id, &app_password)
+            .await
+            .unwrap();
+        store.change_password(carol_id, "new-secret").await.unwrap();
+        let principal = store
+            .query(QueryBy::Id(carol_id), false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            principal.secrets,
+            vec!["new-secret".to_string(), app_password.to_string()]
+        );
+        assert!(!principal.secrets.iter().any(|s| is_scram_verifier(s)));
+        for (secret, expected) in [
+            ("old-secret", false),
+            ("new-secret", true),
+            (app_secret.as_str(), true),
+        ] {
+            assert_eq!(
+                store
+                    .query(
+                        QueryBy::Credentials(&Credentials::Plain {
+                            username: "carol".to_string(),
+                            secret: secret.to_string(),
+                        }),
+                        false,
+                    )
+                    .await
+                    .unwrap()
+                    .is_some(),
+                expected,
+                "{secret}"
+            );
+        }
     }
 }
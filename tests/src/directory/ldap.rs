@@ -21,10 +21,25 @@
  * for more details.
 */
 
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use directory::{backend::internal::manage::ManageDirectory, Principal, QueryBy, Type};
+use directory::{
+    backend::{internal::manage::ManageDirectory, ldap::LdapDirectory},
+    Principal, QueryBy, Type,
+};
 use mail_send::Credentials;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use utils::config::Config;
 
 use crate::directory::{map_account_ids, DirectoryTest};
 
@@ -239,6 +254,105 @@ async fn ldap_directory() {
     );
 }
 
+const FAILOVER_CONFIG: &str = r#"
+[directory."ldap"]
+type = "ldap"
+url = ["ldap://127.0.0.1:3895", "ldap://localhost:3893"]
+base-dn = "dc=example,dc=org"
+
+[directory."ldap".pool]
+retry-interval = "1m"
+health-check = "1s"
+
+[directory."ldap".bind]
+dn = "cn=serviceuser,ou=svcaccts,dc=example,dc=org"
+secret = "mysecret"
+
+[directory."ldap".filter]
+name = "(&(|(objectClass=posixAccount)(objectClass=posixGroup))(uid=?))"
+email = "(&(|(objectClass=posixAccount)(objectClass=posixGroup))(|(mail=?)(givenName=?)(sn=?)))"
+
+[directory."ldap".attributes]
+name = "uid"
+secret = "userPassword"
+email = "mail"
+class = "objectClass"
+"#;
+
+#[tokio::test]
+async fn ldap_failover() {
+    // Start a server that accepts binds but drops the connection on any other request
+    let connections = Arc::new(AtomicUsize::new(0));
+    spawn_broken_ldap_server("127.0.0.1:3895", connections.clone()).await;
+
+    let test = DirectoryTest::new("sqlite".into()).await;
+    let base_store = test.stores.stores.get("sqlite").unwrap();
+    let mut config = Config::new(FAILOVER_CONFIG).unwrap();
+    let directory =
+        LdapDirectory::from_config(&mut config, "directory.ldap", base_store.clone()).unwrap();
+
+    // Queries that fail mid-request are retried on the next server
+    let principal = directory
+        .query(QueryBy::Name("john"), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(principal.name, "john");
+    assert_eq!(connections.load(Ordering::Relaxed), 1);
+    assert!(directory.rcpt("john@example.org").await.unwrap());
+    assert_eq!(
+        directory.email_to_ids("john@example.org").await.unwrap(),
+        vec![principal.id]
+    );
+
+    // The failed server is skipped until it recovers
+    assert_eq!(connections.load(Ordering::Relaxed), 1);
+
+    // The health check keeps probing the failed server in the background
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert!(connections.load(Ordering::Relaxed) > 1);
+    assert!(directory.rcpt("john@example.org").await.unwrap());
+}
+
+async fn spawn_broken_ldap_server(addr: &str, connections: Arc<AtomicUsize>) {
+    let listener = TcpListener::bind(addr).await.unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            connections.fetch_add(1, Ordering::Relaxed);
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 1024];
+                while let Ok(len) = stream.read(&mut buf).await {
+                    // LDAPMessage ::= SEQUENCE { messageID INTEGER, protocolOp }
+                    if len < 2 {
+                        break;
+                    }
+                    let pos = if buf[1] & 0x80 != 0 {
+                        2 + (buf[1] & 0x7f) as usize
+                    } else {
+                        2
+                    };
+                    let id_len = buf[pos + 1] as usize;
+                    let id = &buf[pos + 2..pos + 2 + id_len];
+
+                    // Accept binds, drop the connection on anything else
+                    if buf[pos + 2 + id_len] != 0x60 {
+                        break;
+                    }
+                    let mut message = vec![0x02, id_len as u8];
+                    message.extend_from_slice(id);
+                    message
+                        .extend_from_slice(&[0x61, 0x07, 0x0a, 0x01, 0x00, 0x04, 0x00, 0x04, 0x00]);
+                    let mut response = vec![0x30, message.len() as u8];
+                    response.extend(message);
+                    if stream.write_all(&response).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+}
+
 fn compare_sorted<T: Eq + Debug>(v1: Vec<T>, v2: Vec<T>) {
     for val in v1.iter() {
         assert!(v2.contains(val), "{v1:?} != {v2:?}");
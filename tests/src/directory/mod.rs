@@ -21,13 +21,15 @@
  * for more details.
 */
 
+pub mod http;
 pub mod imap;
 pub mod internal;
 pub mod ldap;
+pub mod oidc;
 pub mod smtp;
 pub mod sql;
 
-use common::{config::smtp::session::AddressMapping, Core};
+use common::{addresses::CatchAll, config::smtp::session::AddressMapping, Core};
 use directory::{backend::internal::manage::ManageDirectory, Directories};
 use mail_send::Credentials;
 use rustls::ServerConfig;
@@ -51,6 +53,7 @@ store = "foundationdb"
 [directory."sqlite"]
 type = "sql"
 store = "sqlite"
+member-of.max-depth = 2
 
 [directory."sqlite".columns]
 name = "name"
@@ -88,6 +91,7 @@ lookup = "sqlite"
 [directory."postgresql"]
 type = "sql"
 store = "postgresql"
+member-of.max-depth = 2
 
 [directory."postgresql".columns]
 name = "name"
@@ -119,6 +123,7 @@ domains = "SELECT 1 FROM emails WHERE address LIKE '%@' || $1 LIMIT 1"
 [directory."mysql"]
 type = "sql"
 store = "mysql"
+member-of.max-depth = 2
 
 [directory."mysql".columns]
 name = "name"
@@ -149,9 +154,12 @@ domains = "SELECT 1 FROM emails WHERE address LIKE CONCAT('%@', ?) LIMIT 1"
 
 [directory."ldap"]
 type = "ldap"
-url = "ldap://localhost:3893"
+url = ["ldap://127.0.0.1:3894", "ldap://localhost:3893"]
 base-dn = "dc=example,dc=org"
 
+[directory."ldap".pool]
+retry-interval = "1m"
+
 [directory."ldap".bind]
 dn = "cn=serviceuser,ou=svcaccts,dc=example,dc=org"
 secret = "mysecret"
@@ -218,6 +226,32 @@ ttl = {positive = '10s', negative = '5s'}
 
 ##############################################################################
 
+[directory."oidc"]
+type = "oidc"
+client.id = "stalwart"
+client.secret = "s3cr3t"
+timeout = "5s"
+
+[directory."oidc".endpoint]
+token = "http://127.0.0.1:9197/token"
+userinfo = "http://127.0.0.1:9197/userinfo"
+introspect = "http://127.0.0.1:9197/introspect"
+
+[directory."oidc".lookup]
+domains = ["example.org"]
+
+##############################################################################
+
+[directory."http"]
+type = "http"
+url = "http://127.0.0.1:9196/directory"
+timeout = "5s"
+
+[directory."http".auth]
+token = "s3cr3t"
+
+##############################################################################
+
 [directory."local"]
 type = "memory"
 
@@ -626,6 +660,11 @@ async fn address_mappings() {
     expected-sub = "doe+alias@example.org"
     expected-sub-nomatch = "jane@example.org"
     expected-catch = "info@example.org"
+
+    [wildcard]
+    catch-all = [{if = "domain == 'example.org' && matches_glob('sales-*', local_part)", then = "'sales@' + domain"},
+                 {if = "domain == 'example.net'", then = "'550 5.1.1 No catch-all for this domain.'"},
+                 {else = false}]
     "#;
 
     let mut config = utils::config::Config::new(MAPPINGS).unwrap();
@@ -660,6 +699,29 @@ async fn address_mappings() {
             "failed catch-all for {test:?}"
         );
     }
+
+    // Per-domain wildcard mappings and rejections
+    let catch_all = AddressMapping::parse(&mut config, ("wildcard", "catch-all"));
+    assert_eq!(
+        catch_all
+            .resolve_catch_all(&core, "sales-emea@example.org")
+            .await,
+        Some(CatchAll::Address("sales@example.org".into()))
+    );
+    assert_eq!(
+        catch_all.resolve_catch_all(&core, "john@example.org").await,
+        None
+    );
+    assert_eq!(
+        catch_all.resolve_catch_all(&core, "john@example.net").await,
+        Some(CatchAll::Reject(
+            "550 5.1.1 No catch-all for this domain.".to_string()
+        ))
+    );
+    assert_eq!(
+        catch_all.to_catch_all(&core, "john@example.net").await,
+        None
+    );
 }
 
 async fn map_account_ids(store: &Store, names: Vec<impl AsRef<str>>) -> Vec<u32> {
@@ -0,0 +1,249 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use directory::QueryBy;
+use mail_send::Credentials;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::watch,
+};
+
+use crate::directory::DirectoryTest;
+
+#[tokio::test]
+async fn oidc_directory() {
+    // Enable logging
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::DEBUG)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Spawn mock OIDC provider
+    let shutdown = spawn_mock_oidc_server();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    // Obtain directory handle
+    let mut config = DirectoryTest::new("sqlite".into()).await;
+    let handle = config.directories.directories.remove("oidc").unwrap();
+
+    // Password grant followed by a userinfo request
+    let principal = handle
+        .query(
+            QueryBy::Credentials(&Credentials::Plain {
+                username: "john".to_string(),
+                secret: "ok".to_string(),
+            }),
+            true,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(principal.name, "john");
+    assert_eq!(principal.emails, vec!["john@example.org".to_string()]);
+    assert_eq!(principal.description.as_deref(), Some("John Doe"));
+    assert_eq!(principal.quota, 1024);
+    assert_eq!(principal.member_of.len(), 1);
+    assert!(handle
+        .query(
+            QueryBy::Credentials(&Credentials::Plain {
+                username: "john".to_string(),
+                secret: "bad".to_string(),
+            }),
+            true,
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+    // Token introspection
+    let principal = handle
+        .query(
+            QueryBy::Credentials(&Credentials::OAuthBearer {
+                token: "valid".to_string(),
+            }),
+            false,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(principal.name, "jane");
+    assert_eq!(principal.emails, vec!["jane@example.org".to_string()]);
+    assert!(principal.member_of.is_empty());
+    assert!(handle
+        .query(
+            QueryBy::Credentials(&Credentials::OAuthBearer {
+                token: "expired".to_string(),
+            }),
+            false,
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+    // Lookups by id and name, used to rebuild expired access tokens
+    let john = handle
+        .query(QueryBy::Name("john"), true)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(john.name, "john");
+    assert_eq!(john.emails, vec!["john@example.org".to_string()]);
+    assert_eq!(john.description.as_deref(), Some("John Doe"));
+    assert_eq!(john.quota, 1024);
+    assert_eq!(john.member_of.len(), 1);
+    let principal = handle
+        .query(QueryBy::Id(john.id), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(principal.name, "john");
+    assert_eq!(principal.emails, vec!["john@example.org".to_string()]);
+    assert!(principal.member_of.is_empty());
+    assert_eq!(
+        handle
+            .query(QueryBy::Name("jane"), false)
+            .await
+            .unwrap()
+            .unwrap()
+            .emails,
+        vec!["jane@example.org".to_string()]
+    );
+    assert!(handle
+        .query(QueryBy::Name("unknown"), false)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Domain lookups
+    assert!(handle.is_local_domain("example.org").await.unwrap());
+    assert!(!handle.is_local_domain("other.org").await.unwrap());
+
+    // Shutdown
+    shutdown.send(false).ok();
+}
+
+pub fn spawn_mock_oidc_server() -> watch::Sender<bool> {
+    let (tx, mut rx) = watch::channel(true);
+
+    tokio::spawn(async move {
+        let listener = TcpListener::bind("127.0.0.1:9197")
+            .await
+            .unwrap_or_else(|e| {
+                panic!("Failed to bind mock OIDC server to 127.0.0.1:9197: {e}");
+            });
+        loop {
+            tokio::select! {
+                stream = listener.accept() => {
+                    match stream {
+                        Ok((stream, _)) => {
+                            tokio::spawn(accept_http(stream));
+                        }
+                        Err(err) => {
+                            panic!("Something went wrong: {err}" );
+                        }
+                    }
+                },
+                _ = rx.changed() => {
+                    break;
+                }
+            };
+        }
+    });
+
+    tx
+}
+
+async fn accept_http(mut stream: TcpStream) {
+    let mut buf = Vec::with_capacity(1024);
+    let mut buf_u8 = vec![0u8; 1024];
+
+    // Read headers and body
+    let (headers, body) = loop {
+        let br = stream.read(&mut buf_u8).await.unwrap();
+        if br == 0 {
+            return;
+        }
+        buf.extend_from_slice(&buf_u8[0..br]);
+        let request = String::from_utf8_lossy(&buf).into_owned();
+        if let Some((headers, body)) = request.split_once("\r\n\r\n") {
+            let content_length = headers
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    if name.eq_ignore_ascii_case("content-length") {
+                        value.trim().parse::<usize>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or(0);
+            if body.len() >= content_length {
+                break (headers.to_string(), body.to_string());
+            }
+        }
+    };
+
+    let path = headers.split(' ').nth(1).unwrap_or_default();
+    let (status, response) = match path {
+        "/token" if body.contains("password=ok") => (
+            "200 OK",
+            r#"{"access_token":"tok-john","token_type":"Bearer"}"#,
+        ),
+        "/token" => ("400 Bad Request", r#"{"error":"invalid_grant"}"#),
+        "/userinfo" if headers.contains("Bearer tok-john") => (
+            "200 OK",
+            concat!(
+                r#"{"preferred_username":"john","email":"john@example.org","#,
+                r#""name":"John Doe","quota":1024,"groups":["/sales"]}"#
+            ),
+        ),
+        "/userinfo" => ("401 Unauthorized", "{}"),
+        "/introspect" if body.contains("token=valid") => (
+            "200 OK",
+            r#"{"active":true,"username":"jane","email":["jane@example.org"]}"#,
+        ),
+        "/introspect" => ("200 OK", r#"{"active":false}"#),
+        _ => panic!("Unknown path: {path}"),
+    };
+
+    stream
+        .write_all(
+            format!(
+                concat!(
+                    "HTTP/1.1 {}\r\n",
+                    "Content-Type: application/json\r\n",
+                    "Content-Length: {}\r\n",
+                    "Connection: close\r\n\r\n{}"
+                ),
+                status,
+                response.len(),
+                response
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+}
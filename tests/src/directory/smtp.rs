@@ -198,6 +198,23 @@ async fn lmtp_directory() {
             "Failed for {item:?}"
         );
     }
+
+    // Failed authentication attempts should be served from the cache
+    let cache = handle.cache.as_ref().unwrap();
+    let stats = cache.auth_stats();
+    for n in 0..100 {
+        let (item, expected) = &tests[n % tests.len()];
+        if matches!(item, Item::Authenticate(_)) && *expected == LookupResult::False {
+            assert_eq!(
+                handle
+                    .query(QueryBy::Credentials(item.append(n).as_credentials()), true)
+                    .await
+                    .unwrap(),
+                None
+            );
+        }
+    }
+    assert!(cache.auth_stats().hits > stats.hits);
 }
 
 pub fn spawn_mock_lmtp_server(max_concurrency: u64) -> watch::Sender<bool> {
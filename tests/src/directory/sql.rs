@@ -70,11 +70,13 @@ async fn sql_directory() {
         // Create test groups
         store.create_test_group("sales", "Sales Team").await;
         store.create_test_group("support", "Support Team").await;
+        store.create_test_group("staff", "All Staff").await;
 
         // Link users to groups
         store.add_to_group("john", "sales").await;
         store.add_to_group("jane", "sales").await;
         store.add_to_group("jane", "support").await;
+        store.add_to_group("sales", "staff").await;
 
         // Add email addresses
         store
@@ -134,7 +136,7 @@ async fn sql_directory() {
                 description: "John Doe".to_string().into(),
                 secrets: vec!["12345".to_string()],
                 typ: Type::Individual,
-                member_of: map_account_ids(base_store, vec!["sales"]).await,
+                member_of: map_account_ids(base_store, vec!["sales", "staff"]).await,
                 emails: vec![
                     "john@example.org".to_string(),
                     "jdoe@example.org".to_string(),
@@ -193,7 +195,7 @@ async fn sql_directory() {
                 description: "Jane Doe".to_string().into(),
                 typ: Type::Individual,
                 secrets: vec!["abcde".to_string()],
-                member_of: map_account_ids(base_store, vec!["sales", "support"]).await,
+                member_of: map_account_ids(base_store, vec!["sales", "support", "staff"]).await,
                 emails: vec!["jane@example.org".to_string(),],
                 ..Default::default()
             }
@@ -211,6 +213,7 @@ async fn sql_directory() {
                 name: "sales".to_string(),
                 description: "Sales Team".to_string().into(),
                 typ: Type::Group,
+                member_of: map_account_ids(base_store, vec!["staff"]).await,
                 ..Default::default()
             }
         );
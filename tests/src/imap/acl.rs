@@ -177,11 +177,17 @@ pub async fn test(mut imap_john: &mut ImapConnection, _imap_check: &mut ImapConn
     )
     .await;
 
-    // Only Bill should be allowed to delete messages on Jane's Inbox
-    for imap in [&mut imap_john, &mut imap_bill] {
+    // Only Bill should be allowed to delete messages on Jane's Inbox,
+    // John can only open it read-only
+    for (imap, code) in [
+        (&mut imap_john, "READ-ONLY"),
+        (&mut imap_bill, "READ-WRITE"),
+    ] {
         imap.send("SELECT \"Shared Folders/jane.smith@example.com/Inbox\"")
             .await;
-        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok)
+            .await
+            .assert_contains(code);
     }
     imap_john.send("UID STORE 1 +FLAGS (\\Deleted)").await;
     imap_john.assert_read(Type::Tagged, ResponseType::No).await;
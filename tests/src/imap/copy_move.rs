@@ -21,6 +21,8 @@
  * for more details.
 */
 
+use std::time::Duration;
+
 use imap_proto::ResponseType;
 
 use super::{AssertResult, ImapConnection, Type};
@@ -169,4 +171,84 @@ pub async fn test(_imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
         .assert_contains("\"Burrata al Tartufo\" (UIDNEXT 5 MESSAGES 0 UNSEEN 0 SIZE 0)")
         .assert_contains("\"Scamorza Affumicata\" (UIDNEXT 9 MESSAGES 4 UNSEEN 4 SIZE 5851)")
         .assert_contains("\"INBOX\" (UIDNEXT 11 MESSAGES 10 UNSEEN 10 SIZE 12193)");
+
+    // Save dates are recorded on append and copy, regardless of the internal date
+    imap_check.send("SELECT \"Burrata al Tartufo\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let message = "Subject: Save date\r\n\r\ntest\r\n";
+    imap_check
+        .send(&format!(
+            "APPEND \"Burrata al Tartufo\" \"01-Jan-2000 00:00:00 +0000\" {{{}+}}\r\n{}",
+            message.len(),
+            message
+        ))
+        .await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("UID FETCH 5 (INTERNALDATE SAVEDATE)").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("INTERNALDATE \"01-Jan-2000 00:00:00 +0000\"")
+        .assert_count("SAVEDATE \"01-Jan-2000", 0)
+        .assert_count("SAVEDATE NIL", 0);
+    for (query, expected) in [
+        ("UID SEARCH BEFORE 1-Jan-2001", "* SEARCH 5"),
+        ("UID SEARCH SAVEDBEFORE 1-Jan-2001", "* SEARCH"),
+        ("UID SEARCH SAVEDSINCE 1-Jan-2001", "* SEARCH 5"),
+    ] {
+        imap_check.send(query).await;
+        imap_check
+            .assert_read(Type::Tagged, ResponseType::Ok)
+            .await
+            .assert_equals(expected);
+    }
+
+    // Copies are saved into the destination mailbox without changing the source's save date
+    imap_check.send("UID FETCH 5 SAVEDATE").await;
+    let src_saved_date =
+        into_save_date(imap_check.assert_read(Type::Tagged, ResponseType::Ok).await);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    imap_check.send("UID COPY 5 \"Scamorza Affumicata\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("UID FETCH 5 SAVEDATE").await;
+    assert_eq!(
+        into_save_date(imap_check.assert_read(Type::Tagged, ResponseType::Ok).await),
+        src_saved_date
+    );
+    imap_check.send("SELECT \"Scamorza Affumicata\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("UID FETCH 9 SAVEDATE").await;
+    assert_ne!(
+        into_save_date(imap_check.assert_read(Type::Tagged, ResponseType::Ok).await),
+        src_saved_date
+    );
+    imap_check
+        .send("UID SEARCH SAVEDSINCE 1-Jan-2001 BEFORE 1-Jan-2001")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* SEARCH 9");
+
+    // Remove the test message
+    imap_check.send("UID STORE 9 +FLAGS (\\Deleted)").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("EXPUNGE").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("SELECT \"Burrata al Tartufo\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("UID STORE 5 +FLAGS (\\Deleted)").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("EXPUNGE").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+}
+
+fn into_save_date(lines: Vec<String>) -> String {
+    lines
+        .iter()
+        .find_map(|line| {
+            line.split_once("SAVEDATE ")
+                .map(|(_, date)| date.to_string())
+        })
+        .unwrap()
 }
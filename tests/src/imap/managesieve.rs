@@ -51,7 +51,8 @@ pub async fn test() {
     sieve
         .assert_read(ResponseType::Ok)
         .await
-        .assert_contains("MAXREDIRECTS");
+        .assert_contains("MAXREDIRECTS")
+        .assert_contains("\"X-VERSIONS\" \"5\"");
 
     // CheckScript
     sieve.send("CHECKSCRIPT \"if true { keep; }\"").await;
@@ -90,6 +91,32 @@ pub async fn test() {
     sieve.send("GETSCRIPT \"dummy\"").await;
     sieve.assert_read(ResponseType::No).await;
 
+    // Large scripts should be streamed in chunks
+    let large_script = (0..10000)
+        .map(|n| format!("# Generated rule {n}\r\n"))
+        .collect::<String>()
+        + "keep;\r\n";
+    sieve
+        .send_literal("PUTSCRIPT \"large script\" ", &large_script)
+        .await;
+    sieve.assert_read(ResponseType::Ok).await;
+    sieve.send("GETSCRIPT \"large script\"").await;
+    sieve
+        .assert_read(ResponseType::Ok)
+        .await
+        .assert_contains("Generated rule 0")
+        .assert_contains("Generated rule 9999")
+        .assert_count("Generated rule", 10000);
+    sieve.send("DELETESCRIPT \"large script\"").await;
+    sieve.assert_read(ResponseType::Ok).await;
+
+    // Scripts over the maximum size should be rejected
+    sieve.send("HAVESPACE \"large script\" 999999999").await;
+    sieve
+        .assert_read(ResponseType::No)
+        .await
+        .assert_contains("QUOTA/MAXSIZE");
+
     // ListScripts
     sieve.send("LISTSCRIPTS").await;
     sieve
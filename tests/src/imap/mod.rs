@@ -31,7 +31,9 @@ pub mod fetch;
 pub mod idle;
 pub mod mailbox;
 pub mod managesieve;
+pub mod notify;
 pub mod pop;
+pub mod quota;
 pub mod search;
 pub mod store;
 pub mod thread;
@@ -213,6 +215,9 @@ ttl = "1m"
 files = 3
 size = 50000
 
+[jmap.email]
+max-messages = 100000
+
 [jmap.rate-limit]
 account = "1000/1m"
 authentication = "100/2s"
@@ -442,7 +447,7 @@ pub async fn imap_tests() {
     }
 
     // Delete folders
-    for mailbox in ["Drafts", "Junk Mail", "Sent Items"] {
+    for mailbox in ["Drafts", "Junk Mail", "Sent Items", "Archive"] {
         imap.send(&format!("DELETE \"{}\"", mailbox)).await;
         imap.assert_read(Type::Tagged, ResponseType::Ok).await;
     }
@@ -455,8 +460,10 @@ pub async fn imap_tests() {
     copy_move::test(&mut imap, &mut imap_check).await;
     thread::test(&mut imap, &mut imap_check).await;
     idle::test(&mut imap, &mut imap_check).await;
+    notify::test(&mut imap, &mut imap_check).await;
     condstore::test(&mut imap, &mut imap_check).await;
     acl::test(&mut imap, &mut imap_check).await;
+    quota::test(&mut imap, &mut imap_check).await;
 
     // Logout
     for imap in [&mut imap, &mut imap_check] {
@@ -591,6 +598,10 @@ impl ImapConnection {
         //let c = println!("-> {:?}", text);
         self.writer.write_all(text.as_bytes()).await.unwrap();
     }
+
+    pub fn into_stream(self) -> TcpStream {
+        self.reader.into_inner().into_inner().unsplit(self.writer)
+    }
 }
 
 pub trait AssertResult: Sized {
@@ -0,0 +1,177 @@
+/*
+ * Copyright (c) 2020-2022, Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use common::listener::deflate::DeflateStream;
+use imap_proto::ResponseType;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+    println!("Running NOTIFY tests...");
+
+    // Select a mailbox and register for notifications
+    imap_check.send("CREATE Gouda").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("SELECT Gouda").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .send(concat!(
+            "NOTIFY SET STATUS (SELECTED (MessageNew MessageExpunge FlagChange)) ",
+            "(PERSONAL (MessageNew MessageExpunge MailboxName))"
+        ))
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("STATUS \"INBOX\"");
+
+    // New messages in the selected mailbox are reported once no command is in progress
+    let message = "From: test@domain.com\nSubject: Test\n\nTest message\n";
+    imap.send(&format!("APPEND Gouda {{{}}}", message.len()))
+        .await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged(message).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXISTS");
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 FETCH (FLAGS () UID 1)");
+
+    // Events received while a command is running follow its tagged response
+    imap.send("CREATE Brie").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("UID FETCH 1 (FLAGS)").await;
+    let lines = imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let list = lines
+        .iter()
+        .position(|line| line.contains("LIST () \"/\" \"Brie\""));
+    let fetch = lines
+        .iter()
+        .position(|line| line.contains("FETCH (FLAGS () UID 1)"))
+        .unwrap();
+    if let Some(list) = list {
+        assert!(list < fetch, "{lines:?}");
+    } else {
+        imap_check
+            .assert_read(Type::Status, ResponseType::Ok)
+            .await
+            .assert_contains("LIST () \"/\" \"Brie\"");
+    }
+
+    // Messages added to other mailboxes are reported with STATUS
+    imap.send(&format!("APPEND Brie {{{}}}", message.len()))
+        .await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged(message).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("STATUS \"Brie\"")
+        .assert_contains("MESSAGES 1");
+
+    // IDLE sends the events requested with NOTIFY
+    imap_check.send("IDLE").await;
+    imap_check
+        .assert_read(Type::Continuation, ResponseType::Ok)
+        .await;
+    imap.send("SELECT Gouda").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("STORE 1 +FLAGS (\\Flagged)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 FETCH (FLAGS (\\Flagged) UID 1)");
+    imap.send("DELETE Brie").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("LIST (\\NonExistent) \"/\" \"Brie\"");
+    imap_check.send_raw("DONE").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Disable notifications
+    imap_check.send("NOTIFY NONE").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("CREATE Camembert").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("NOOP").await;
+    let lines = imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    assert!(
+        !lines.iter().any(|line| line.contains("Camembert")),
+        "{lines:?}"
+    );
+
+    // Cleanup
+    for mailbox in ["Gouda", "Camembert"] {
+        imap.send(&format!("DELETE {mailbox}")).await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+    imap_check.send("UNSELECT").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Notifications continue once compression is enabled
+    let mut imap_compress = ImapConnection::connect(b"_z ").await;
+    imap_compress
+        .assert_read(Type::Untagged, ResponseType::Ok)
+        .await;
+    imap_compress
+        .send("AUTHENTICATE PLAIN {32+}\r\nAGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0")
+        .await;
+    imap_compress
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await;
+    imap_compress
+        .send("NOTIFY SET (PERSONAL (MessageNew MessageExpunge MailboxName))")
+        .await;
+    imap_compress
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await;
+    imap_compress.send("COMPRESS DEFLATE").await;
+    imap_compress
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await;
+    let (reader, mut writer) = tokio::io::split(DeflateStream::new(imap_compress.into_stream()));
+    let mut reader = BufReader::new(reader).lines();
+    imap.send("CREATE Roquefort").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    assert_eq!(
+        tokio::time::timeout(std::time::Duration::from_millis(1500), reader.next_line())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap(),
+        "* LIST () \"/\" \"Roquefort\""
+    );
+    writer.write_all(b"_z LOGOUT\r\n").await.unwrap();
+    writer.flush().await.unwrap();
+    imap.send("DELETE Roquefort").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+}
@@ -29,6 +29,7 @@ use std::{
 
 use common::listener::blocked::BLOCKED_IP_KEY;
 use directory::backend::internal::manage::ManageDirectory;
+use hyper::Method;
 use imap_proto::ResponseType;
 use jmap_client::{
     client::{Client, Credentials},
@@ -40,7 +41,7 @@ use store::write::now;
 
 use crate::{
     imap::{ImapConnection, Type},
-    jmap::{assert_is_empty, mailbox::destroy_all_mailboxes},
+    jmap::{assert_is_empty, mailbox::destroy_all_mailboxes, ManagementApi},
 };
 
 use super::JMAPTest;
@@ -144,7 +145,29 @@ pub async fn test(params: &mut JMAPTest) {
         .await;
     imap.assert_disconnect().await;
 
-    // Make sure the IP address is blocked
+    // Make sure the IP address is temporarily blocked
+    let expires = server
+        .core
+        .storage
+        .config
+        .get(format!("{BLOCKED_IP_KEY}.127.0.0.1"))
+        .await
+        .unwrap()
+        .expect("IP address not blocked")
+        .parse::<u64>()
+        .unwrap();
+    assert!(expires > now() + 3500 && expires <= now() + 3600);
+    ImapConnection::connect(b"_y ")
+        .await
+        .assert_disconnect()
+        .await;
+
+    // Lift ban
+    assert!(server
+        .core
+        .unblock_ip(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)))
+        .await
+        .unwrap());
     assert_eq!(
         server
             .core
@@ -153,28 +176,8 @@ pub async fn test(params: &mut JMAPTest) {
             .get(format!("{BLOCKED_IP_KEY}.127.0.0.1"))
             .await
             .unwrap(),
-        Some(String::new())
+        None
     );
-    ImapConnection::connect(b"_y ")
-        .await
-        .assert_disconnect()
-        .await;
-
-    // Lift ban
-    server
-        .core
-        .storage
-        .config
-        .clear(format!("{BLOCKED_IP_KEY}.127.0.0.1"))
-        .await
-        .unwrap();
-    server
-        .core
-        .network
-        .blocked_ips
-        .ip_addresses
-        .write()
-        .remove(&IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
 
     // Valid authentication requests should not be rate limited
     for _ in 0..110 {
@@ -276,6 +279,56 @@ pub async fn test(params: &mut JMAPTest) {
         client.upload(None, b"sleep".to_vec(), None).await,
         Err(jmap_client::Error::Problem(err)) if err.status() == Some(400)));
 
+    // Authentication outcomes, connections and store latencies are exported to Prometheus
+    let metrics = ManagementApi::new(8899, "admin", "secret")
+        .get_text("/metrics/prometheus")
+        .await
+        .unwrap();
+    for metric in [
+        "stalwart_authentication_total{result=\"success\"}",
+        "stalwart_authentication_total{result=\"failure\"}",
+        "stalwart_connections_total{protocol=\"http\"}",
+        "stalwart_connections_active{protocol=\"http\"}",
+        "stalwart_store_write_duration_seconds_count",
+        "stalwart_queue_messages",
+    ] {
+        assert!(metrics.contains(metric), "{metric} not found in {metrics}");
+    }
+
+    // Repeated bans last exponentially longer, up to the maximum duration
+    let api = ManagementApi::new(8899, "admin", "secret");
+    let banned_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    for expected_duration in [3600, 7200, 3 * 3600, 3 * 3600] {
+        server.core.block_ip(banned_ip).await.unwrap();
+        let blocked = api
+            .request::<serde_json::Value>(Method::GET, "/api/blocked-ip")
+            .await
+            .unwrap()
+            .unwrap_data();
+        assert_eq!(blocked["total"], 1, "{blocked}");
+        assert_eq!(blocked["items"][0]["ip"], "10.0.0.1", "{blocked}");
+        let expires = blocked["items"][0]["expires"].as_u64().unwrap();
+        assert!(
+            expires > now() + expected_duration - 100 && expires <= now() + expected_duration,
+            "{blocked}"
+        );
+    }
+    assert!(server.core.is_ip_blocked(&banned_ip));
+
+    // Lift ban using the management API
+    api.request::<serde_json::Value>(Method::DELETE, "/api/blocked-ip/10.0.0.1")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(!server.core.is_ip_blocked(&banned_ip));
+    assert_eq!(
+        api.request::<serde_json::Value>(Method::GET, "/api/blocked-ip")
+            .await
+            .unwrap()
+            .unwrap_data()["total"],
+        0
+    );
+
     // Destroy test accounts
     params.client.set_default_account_id(&account_id);
     destroy_all_mailboxes(params).await;
@@ -463,7 +463,8 @@ pub async fn test(params: &mut JMAPTest) {
                   "typeNames": [
                     "Mailbox",
                     "Thread",
-                    "Email"
+                    "Email",
+                    "SieveScript"
                   ],
                   "ids": [
                     "%%",
@@ -494,6 +495,14 @@ pub async fn test(params: &mut JMAPTest) {
             "Pointer {pointer:?} Response: {response:#?}",
         );
     }
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/list/0/matchedIds/SieveScript")
+            .and_then(|v| v.as_array())
+            .map(|arr| arr.len()),
+        Some(0),
+        "Response: {response:#?}",
+    );
 
     // Remove test data
     params.client.set_default_account_id(account_id.to_string());
@@ -0,0 +1,536 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine};
+use directory::backend::internal::manage::ManageDirectory;
+use reqwest::{header, Method};
+
+use jmap_proto::types::id::Id;
+
+use crate::jmap::{assert_is_empty, jmap_raw_request};
+
+use super::JMAPTest;
+
+const EVENT: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//Test//EN\r
+BEGIN:VEVENT\r
+UID:$UID\r
+DTSTAMP:20240101T000000Z\r
+DTSTART:20240102T100000Z\r
+SUMMARY:Meeting\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+const VCARD: &str = "BEGIN:VCARD\r
+VERSION:4.0\r
+UID:urn:uuid:card-1\r
+FN:Jane Smith\r
+END:VCARD\r
+";
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running CalDAV/CardDAV tests...");
+    let server = params.server.clone();
+    for (email, secret, name) in [
+        ("jdoe@example.com", "12345", "John Doe"),
+        ("jane@example.com", "abcde", "Jane Smith"),
+    ] {
+        params
+            .directory
+            .create_test_user_with_email(email, secret, name)
+            .await;
+        server
+            .core
+            .storage
+            .data
+            .get_or_create_account_id(email)
+            .await
+            .unwrap();
+    }
+    let john = ("jdoe@example.com", "12345");
+    let jane = ("jane@example.com", "abcde");
+    let home = "/dav/cal/jdoe@example.com/";
+
+    // Discovery
+    let (status, headers, _) = dav_request(john, "GET", "/.well-known/caldav", "", &[]).await;
+    assert_eq!(status, 301);
+    assert_eq!(headers.get("location").unwrap(), "/dav/");
+    let (status, _, _) =
+        dav_request(("jdoe@example.com", "wrong"), "PROPFIND", "/dav/", "", &[]).await;
+    assert_eq!(status, 401);
+    let (status, _, body) = dav_request(
+        john,
+        "PROPFIND",
+        "/dav/principal/jdoe@example.com/",
+        "",
+        &[],
+    )
+    .await;
+    assert_eq!(status, 207, "{body}");
+    assert!(
+        body.contains("<C:calendar-home-set><D:href>/dav/cal/jdoe@example.com/</D:href>"),
+        "{body}"
+    );
+
+    // The default calendar is created on first access
+    let (status, _, body) = dav_request(john, "PROPFIND", home, "", &[("Depth", "1")]).await;
+    assert_eq!(status, 207, "{body}");
+    assert!(
+        body.contains("/dav/cal/jdoe@example.com/default/"),
+        "{body}"
+    );
+    assert!(
+        body.contains("<D:displayname>Calendar</D:displayname>"),
+        "{body}"
+    );
+
+    // Create a calendar
+    let calendar = "/dav/cal/jdoe@example.com/work/";
+    let (status, _, _) = dav_request(
+        john,
+        "MKCALENDAR",
+        calendar,
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\" ?>",
+            "<C:mkcalendar xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">",
+            "<D:set><D:prop><D:displayname>Work</D:displayname></D:prop></D:set>",
+            "</C:mkcalendar>"
+        ),
+        &[],
+    )
+    .await;
+    assert_eq!(status, 201);
+    let (status, _, _) = dav_request(john, "MKCALENDAR", calendar, "", &[]).await;
+    assert_eq!(status, 405);
+
+    // Obtain the initial sync token
+    let (status, _, body) = dav_request(john, "REPORT", calendar, &sync_report(""), &[]).await;
+    assert_eq!(status, 207, "{body}");
+    let initial_token = sync_token(&body);
+
+    // Create, read and update an event
+    let event = format!("{calendar}event1.ics");
+    let (status, headers, _) = dav_request(
+        john,
+        "PUT",
+        &event,
+        &EVENT.replace("$UID", "event-1"),
+        &[("If-None-Match", "*")],
+    )
+    .await;
+    assert_eq!(status, 201);
+    let etag = headers.get("etag").unwrap().to_str().unwrap().to_string();
+    let (status, headers, body) = dav_request(john, "GET", &event, "", &[]).await;
+    assert_eq!(status, 200);
+    assert_eq!(headers.get("etag").unwrap().to_str().unwrap(), etag);
+    assert!(body.contains("UID:event-1"), "{body}");
+    let (status, _, _) = dav_request(
+        john,
+        "PUT",
+        &event,
+        &EVENT.replace("$UID", "event-1"),
+        &[("If-None-Match", "*")],
+    )
+    .await;
+    assert_eq!(status, 412);
+    let (status, _, _) = dav_request(
+        john,
+        "PUT",
+        &event,
+        &EVENT.replace("$UID", "event-1").replace("Meeting", "Lunch"),
+        &[("If-Match", etag.as_str())],
+    )
+    .await;
+    assert_eq!(status, 204);
+
+    // UIDs must be unique and objects must be valid
+    let (status, _, body) = dav_request(
+        john,
+        "PUT",
+        &format!("{calendar}event2.ics"),
+        &EVENT.replace("$UID", "event-1"),
+        &[],
+    )
+    .await;
+    assert_eq!(status, 403);
+    assert!(body.contains("no-uid-conflict"), "{body}");
+    let (status, _, body) = dav_request(
+        john,
+        "PUT",
+        &format!("{calendar}event2.ics"),
+        "BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n",
+        &[],
+    )
+    .await;
+    assert_eq!(status, 403);
+    assert!(body.contains("valid-calendar-data"), "{body}");
+    let (status, _, _) = dav_request(
+        john,
+        "PUT",
+        &format!("{calendar}event2.ics"),
+        &EVENT.replace("$UID", "event-2"),
+        &[],
+    )
+    .await;
+    assert_eq!(status, 201);
+
+    // Multiget and query reports
+    let (status, _, body) = dav_request(
+        john,
+        "REPORT",
+        calendar,
+        &format!(
+            concat!(
+                "<C:calendar-multiget xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">",
+                "<D:prop><D:getetag/><C:calendar-data/></D:prop>",
+                "<D:href>{}</D:href><D:href>{}missing.ics</D:href>",
+                "</C:calendar-multiget>"
+            ),
+            event, calendar
+        ),
+        &[],
+    )
+    .await;
+    assert_eq!(status, 207, "{body}");
+    assert!(body.contains("SUMMARY:Lunch"), "{body}");
+    assert!(
+        body.contains("missing.ics</D:href><D:status>HTTP/1.1 404"),
+        "{body}"
+    );
+    let (status, _, body) = dav_request(
+        john,
+        "REPORT",
+        calendar,
+        concat!(
+            "<C:calendar-query xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">",
+            "<D:prop><D:getetag/></D:prop>",
+            "<C:filter><C:comp-filter name=\"VCALENDAR\"><C:comp-filter name=\"VTODO\"/>",
+            "</C:comp-filter></C:filter></C:calendar-query>"
+        ),
+        &[],
+    )
+    .await;
+    assert_eq!(status, 207, "{body}");
+    assert!(!body.contains("event1.ics"), "{body}");
+
+    // Share the calendar with Jane
+    let (status, _, _) = dav_request(jane, "GET", &event, "", &[]).await;
+    assert_eq!(status, 403);
+    let (status, _, body) = dav_request(
+        john,
+        "ACL",
+        calendar,
+        &acl_request("/dav/principal/unknown@example.com/", "read"),
+        &[],
+    )
+    .await;
+    assert_eq!(status, 403);
+    assert!(body.contains("recognized-principal"), "{body}");
+    let (status, _, _) = dav_request(
+        john,
+        "ACL",
+        calendar,
+        &acl_request("/dav/principal/jane@example.com/", "read"),
+        &[],
+    )
+    .await;
+    assert_eq!(status, 200);
+    let (status, _, body) = dav_request(jane, "GET", &event, "", &[]).await;
+    assert_eq!(status, 200);
+    assert!(body.contains("SUMMARY:Lunch"), "{body}");
+    let (status, _, _) = dav_request(jane, "DELETE", &event, "", &[]).await;
+    assert_eq!(status, 403);
+    let (status, _, body) = dav_request(jane, "PROPFIND", home, "", &[("Depth", "1")]).await;
+    assert_eq!(status, 207, "{body}");
+    assert!(body.contains(calendar), "{body}");
+    assert!(!body.contains("/default/"), "{body}");
+
+    // Delete an event and synchronize
+    let (status, _, _) = dav_request(john, "DELETE", &event, "", &[]).await;
+    assert_eq!(status, 204);
+    let (status, _, body) =
+        dav_request(john, "REPORT", calendar, &sync_report(&initial_token), &[]).await;
+    assert_eq!(status, 207, "{body}");
+    assert!(body.contains("event2.ics"), "{body}");
+    assert!(
+        body.contains("event1.ics</D:href><D:status>HTTP/1.1 404"),
+        "{body}"
+    );
+    let token = sync_token(&body);
+    assert_ne!(token, initial_token);
+    let (status, _, body) = dav_request(john, "REPORT", calendar, &sync_report(&token), &[]).await;
+    assert_eq!(status, 207, "{body}");
+    assert!(!body.contains("<D:response>"), "{body}");
+    let (status, _, body) = dav_request(
+        john,
+        "REPORT",
+        calendar,
+        &sync_report("urn:stalwart:sync:invalid"),
+        &[],
+    )
+    .await;
+    assert_eq!(status, 403);
+    assert!(body.contains("valid-sync-token"), "{body}");
+
+    // Address books
+    let contact = "/dav/card/jdoe@example.com/default/jane.vcf";
+    let (status, _, _) = dav_request(
+        john,
+        "PROPFIND",
+        "/dav/card/jdoe@example.com/",
+        "",
+        &[("Depth", "1")],
+    )
+    .await;
+    assert_eq!(status, 207);
+    let (status, _, _) = dav_request(john, "PUT", contact, VCARD, &[]).await;
+    assert_eq!(status, 201);
+    let (status, headers, body) = dav_request(john, "GET", contact, "", &[]).await;
+    assert_eq!(status, 200);
+    assert!(headers
+        .get("content-type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("text/vcard"));
+    assert!(body.contains("FN:Jane Smith"), "{body}");
+    let (status, _, body) =
+        dav_request(john, "PUT", contact, &EVENT.replace("$UID", "event-3"), &[]).await;
+    assert_eq!(status, 403);
+    assert!(body.contains("valid-address-data"), "{body}");
+
+    // JMAP access to the objects stored over CardDAV
+    let account_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .get_or_create_account_id("jdoe@example.com")
+            .await
+            .unwrap(),
+    )
+    .to_string();
+    let response = jmap_json(
+        r##"[[ "ContactCard/query", {
+                "accountId": "$$",
+                "filter": { "uid": "urn:uuid:card-1" }
+              }, "0" ],
+            [ "ContactCard/get", {
+                "accountId": "$$",
+                "#ids": { "resultOf": "0", "name": "ContactCard/query", "path": "/ids" }
+              }, "1" ]]"##
+            .replace("$$", &account_id),
+        john,
+    )
+    .await;
+    let card = &response["methodResponses"][1][1]["list"][0];
+    assert_eq!(card["uid"], "urn:uuid:card-1", "{response}");
+    assert_eq!(card["size"], VCARD.len(), "{response}");
+    assert_eq!(
+        card["addressBookIds"].as_object().map(|ids| ids.len()),
+        Some(1),
+        "{response}"
+    );
+
+    // Create an event over JMAP, duplicate UIDs are rejected
+    let state = jmap_json(
+        r#"[[ "CalendarEvent/get", { "accountId": "$$", "ids": [] }, "0" ]]"#
+            .replace("$$", &account_id),
+        john,
+    )
+    .await["methodResponses"][0][1]["state"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let blob_id = params
+        .client
+        .upload(
+            Some(&account_id),
+            EVENT.replace("$UID", "event-jmap").into_bytes(),
+            None,
+        )
+        .await
+        .unwrap()
+        .take_blob_id();
+    let response = jmap_json(
+        r#"[[ "CalendarEvent/set", {
+                "accountId": "$$",
+                "create": {
+                    "e1": { "blobId": "%%" },
+                    "e2": { "blobId": "%%" },
+                    "e3": { "calendarIds": {} }
+                }
+              }, "0" ]]"#
+            .replace("$$", &account_id)
+            .replace("%%", &blob_id),
+        john,
+    )
+    .await;
+    let set = &response["methodResponses"][0][1];
+    assert_eq!(set["created"]["e1"]["uid"], "event-jmap", "{response}");
+    assert_eq!(
+        set["notCreated"]["e2"]["type"], "alreadyExists",
+        "{response}"
+    );
+    assert_eq!(
+        set["notCreated"]["e3"]["type"], "invalidProperties",
+        "{response}"
+    );
+    let event_id = set["created"]["e1"]["id"].as_str().unwrap().to_string();
+
+    // The event is visible over CalDAV and through the change log
+    let (status, _, body) = dav_request(
+        john,
+        "PROPFIND",
+        "/dav/cal/jdoe@example.com/default/",
+        "",
+        &[("Depth", "1")],
+    )
+    .await;
+    assert_eq!(status, 207, "{body}");
+    assert!(body.contains(".ics</D:href>"), "{body}");
+    let response = jmap_json(
+        r#"[[ "CalendarEvent/changes", { "accountId": "$$", "sinceState": "%%" }, "0" ],
+            [ "CalendarEvent/query", {
+                "accountId": "$$",
+                "filter": { "uid": "event-jmap" }
+              }, "1" ]]"#
+            .replace("$$", &account_id)
+            .replace("%%", &state),
+        john,
+    )
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["created"],
+        serde_json::json!([event_id]),
+        "{response}"
+    );
+    assert_eq!(
+        response["methodResponses"][1][1]["ids"],
+        serde_json::json!([event_id]),
+        "{response}"
+    );
+
+    // Destroy the event
+    let response = jmap_json(
+        r#"[[ "CalendarEvent/set", { "accountId": "$$", "destroy": ["%%"] }, "0" ],
+            [ "CalendarEvent/get", { "accountId": "$$", "ids": ["%%"] }, "1" ]]"#
+            .replace("$$", &account_id)
+            .replace("%%", &event_id),
+        john,
+    )
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["destroyed"],
+        serde_json::json!([event_id]),
+        "{response}"
+    );
+    assert_eq!(
+        response["methodResponses"][1][1]["notFound"],
+        serde_json::json!([event_id]),
+        "{response}"
+    );
+
+    // Clean up
+    for collection in [
+        "/dav/cal/jdoe@example.com/default/",
+        calendar,
+        "/dav/card/jdoe@example.com/default/",
+    ] {
+        let (status, _, _) = dav_request(john, "DELETE", collection, "", &[]).await;
+        assert_eq!(status, 204);
+    }
+    assert_is_empty(server).await;
+}
+
+async fn jmap_json(body: String, (username, secret): (&str, &str)) -> serde_json::Value {
+    let response = jmap_raw_request(body, username, secret).await;
+    serde_json::from_str(&response).unwrap_or_else(|_| panic!("Invalid response: {response}"))
+}
+
+fn sync_report(token: &str) -> String {
+    format!(
+        concat!(
+            "<D:sync-collection xmlns:D=\"DAV:\">",
+            "<D:sync-token>{}</D:sync-token><D:sync-level>1</D:sync-level>",
+            "<D:prop><D:getetag/></D:prop></D:sync-collection>"
+        ),
+        token
+    )
+}
+
+fn acl_request(principal: &str, privilege: &str) -> String {
+    format!(
+        concat!(
+            "<D:acl xmlns:D=\"DAV:\"><D:ace><D:principal><D:href>{}</D:href></D:principal>",
+            "<D:grant><D:privilege><D:{}/></D:privilege></D:grant></D:ace></D:acl>"
+        ),
+        principal, privilege
+    )
+}
+
+fn sync_token(body: &str) -> String {
+    body.split_once("<D:sync-token>")
+        .and_then(|(_, token)| token.split_once("</D:sync-token>"))
+        .map(|(token, _)| token.to_string())
+        .unwrap_or_else(|| panic!("Missing sync token: {body}"))
+}
+
+async fn dav_request(
+    (username, secret): (&str, &str),
+    method: &str,
+    path: &str,
+    body: &str,
+    headers: &[(&str, &str)],
+) -> (u16, header::HeaderMap, String) {
+    let mut request = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_millis(1000))
+        .build()
+        .unwrap()
+        .request(
+            Method::from_bytes(method.as_bytes()).unwrap(),
+            format!("https://127.0.0.1:8899{path}"),
+        )
+        .header(
+            header::AUTHORIZATION,
+            format!(
+                "Basic {}",
+                general_purpose::STANDARD.encode(format!("{username}:{secret}"))
+            ),
+        )
+        .body(body.to_string());
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let response = request.send().await.unwrap();
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+
+    (status, headers, response.text().await.unwrap())
+}
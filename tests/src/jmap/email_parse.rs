@@ -30,7 +30,8 @@ use jmap_client::{
 use jmap_proto::types::id::Id;
 
 use crate::jmap::{
-    assert_is_empty, email_get::all_headers, mailbox::destroy_all_mailboxes, replace_blob_ids,
+    assert_is_empty, email_get::all_headers, jmap_raw_request, mailbox::destroy_all_mailboxes,
+    replace_blob_ids,
 };
 
 use super::JMAPTest;
@@ -252,6 +253,71 @@ pub async fn test(params: &mut JMAPTest) {
         panic!("Test failed, output saved to {}", test_file.display());
     }
 
+    // Test parsing encrypted and signed structures
+    for (message, expected) in [
+        (
+            concat!(
+                "From: jdoe@example.com\r\n",
+                "Subject: Encrypted\r\n",
+                "Content-Type: multipart/encrypted; protocol=\"application/pgp-encrypted\";\r\n",
+                "  boundary=\"bound\"\r\n\r\n",
+                "--bound\r\n",
+                "Content-Type: application/pgp-encrypted\r\n\r\n",
+                "Version: 1\r\n\r\n",
+                "--bound\r\n",
+                "Content-Type: application/octet-stream\r\n\r\n",
+                "-----BEGIN PGP MESSAGE-----\r\n",
+                "-----END PGP MESSAGE-----\r\n",
+                "--bound--\r\n"
+            ),
+            r#"{"type":"pgp","isEncrypted":true,"isSigned":false}"#,
+        ),
+        (
+            concat!(
+                "From: jdoe@example.com\r\n",
+                "Subject: Signed\r\n",
+                "Content-Type: multipart/signed; protocol=\"application/pkcs7-signature\";\r\n",
+                "  micalg=sha-256; boundary=\"bound\"\r\n\r\n",
+                "--bound\r\n",
+                "Content-Type: text/plain\r\n\r\n",
+                "Hello\r\n",
+                "--bound\r\n",
+                "Content-Type: application/pkcs7-signature; name=smime.p7s\r\n",
+                "Content-Transfer-Encoding: base64\r\n\r\n",
+                "AAAA\r\n",
+                "--bound--\r\n"
+            ),
+            r#"{"type":"smime","isEncrypted":false,"isSigned":true}"#,
+        ),
+        (
+            concat!(
+                "From: jdoe@example.com\r\n",
+                "Subject: Plain\r\n\r\n",
+                "Hello\r\n"
+            ),
+            r#""encryption":null"#,
+        ),
+    ] {
+        let blob_id = params
+            .client
+            .upload(None, message.as_bytes().to_vec(), None)
+            .await
+            .unwrap()
+            .take_blob_id();
+        let response = jmap_raw_request(
+            r#"[[ "Email/parse", {
+                "accountId": "b",
+                "blobIds": ["$$"],
+                "properties": ["encryption"]
+              }, "0" ]]"#
+                .replace("$$", &blob_id),
+            "admin",
+            "secret",
+        )
+        .await;
+        assert!(response.contains(expected), "{}", response);
+    }
+
     destroy_all_mailboxes(params).await;
     assert_is_empty(server).await;
 }
@@ -52,6 +52,7 @@ pub async fn test(params: &mut JMAPTest) {
         "mixed",
         "text_plain",
         "text_plain_chinese",
+        "attachments",
     ] {
         let mut file_name = test_dir.clone();
         file_name.push(format!("{}.eml", email_name));
@@ -182,6 +183,27 @@ pub async fn test(params: &mut JMAPTest) {
         );
     }
 
+    // Text extracted from binary attachments should be searchable
+    for (filter, expected) in [
+        (Filter::text("timbuktu"), vec!["attachments"]),
+        (Filter::text("zanzibar"), vec!["attachments"]),
+        (Filter::body("zanzibar"), vec![]),
+        (Filter::text("kathmandu"), vec![]),
+    ] {
+        assert_eq!(
+            params
+                .client
+                .email_query(Some(filter), None::<Vec<_>>)
+                .await
+                .unwrap()
+                .take_ids(),
+            expected
+                .into_iter()
+                .map(|name| email_ids.get(name).unwrap().clone())
+                .collect::<Vec<_>>()
+        );
+    }
+
     // Destroy test data
     destroy_all_mailboxes(params).await;
     assert_is_empty(server).await;
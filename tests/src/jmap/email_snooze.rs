@@ -0,0 +1,198 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use directory::backend::internal::manage::ManageDirectory;
+use jmap::mailbox::INBOX_ID;
+use jmap_proto::types::id::Id;
+
+use crate::jmap::{assert_is_empty, jmap_raw_request, mailbox::destroy_all_mailboxes};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Email snooze tests...");
+
+    // Create test account
+    let server = params.server.clone();
+    params
+        .directory
+        .create_test_user_with_email("jdoe@example.com", "12345", "John Doe")
+        .await;
+    let account_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .get_or_create_account_id("jdoe@example.com")
+            .await
+            .unwrap(),
+    )
+    .to_string();
+    let client = &mut params.client;
+    client.set_default_account_id(&account_id);
+
+    // Create the snoozed mailbox
+    let response = jmap_raw_request(
+        r#"[[ "Mailbox/set", {
+            "accountId": "$$",
+            "create": {
+                "s": {
+                    "name": "Snoozed",
+                    "role": "snoozed"
+                }
+            }
+          }, "0" ]]"#
+            .replace("$$", &account_id),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    let snoozed_id = response
+        .split_once("\"s\":{\"id\":\"")
+        .and_then(|(_, id)| id.split_once('"'))
+        .map(|(id, _)| id.to_string())
+        .unwrap_or_else(|| panic!("Snoozed mailbox not created: {response}"));
+
+    // Import two read messages
+    let inbox_id = Id::new(INBOX_ID as u64).to_string();
+    let mut message_ids = Vec::new();
+    for subject in ["Wake me up", "Later"] {
+        message_ids.push(
+            client
+                .email_import(
+                    format!(
+                        "From: bill@example.com\r\nTo: jdoe@example.com\r\nSubject: {subject}\r\n\r\ntest"
+                    )
+                    .into_bytes(),
+                    vec![&inbox_id],
+                    Some(vec!["$seen"]),
+                    None,
+                )
+                .await
+                .unwrap()
+                .take_id(),
+        );
+    }
+
+    // Snooze both messages, one of them is already due
+    for (message_id, snoozed_until) in [
+        (&message_ids[0], "2020-01-01T00:00:00Z"),
+        (&message_ids[1], "2090-01-01T00:00:00Z"),
+    ] {
+        let response = jmap_raw_request(
+            r#"[[ "Email/set", {
+                "accountId": "$$",
+                "update": {
+                    "&&": {
+                        "mailboxIds": { "%%": true },
+                        "snoozedUntil": "@@"
+                    }
+                }
+              }, "0" ]]"#
+                .replace("$$", &account_id)
+                .replace("&&", message_id)
+                .replace("%%", &snoozed_id)
+                .replace("@@", snoozed_until),
+            "jdoe@example.com",
+            "12345",
+        )
+        .await;
+        assert!(response.contains("\"updated\""), "{}", response);
+    }
+    let response = get_emails(&account_id, &message_ids).await;
+    assert!(
+        response.contains("\"snoozedUntil\":\"2020-01-01T00:00:00Z\""),
+        "{}",
+        response
+    );
+
+    // Wake up due messages, only the first one should be back in the Inbox and unread
+    server.email_wake_snoozed().await;
+    let response = get_emails(&account_id, &message_ids).await;
+    let (woken, snoozed) = response
+        .split_once(&format!("\"id\":\"{}\"", message_ids[1]))
+        .unwrap_or_else(|| panic!("Unexpected response: {response}"));
+    assert!(
+        woken.contains(&format!("\"mailboxIds\":{{\"{inbox_id}\":true}}")),
+        "{}",
+        response
+    );
+    assert!(woken.contains("\"keywords\":{}"), "{}", response);
+    assert!(woken.contains("\"snoozedUntil\":null"), "{}", response);
+    assert!(
+        snoozed.contains(&format!("\"mailboxIds\":{{\"{snoozed_id}\":true}}")),
+        "{}",
+        response
+    );
+    assert!(
+        snoozed.contains("\"snoozedUntil\":\"2090-01-01T00:00:00Z\""),
+        "{}",
+        response
+    );
+
+    // Cancel the remaining snooze
+    let response = jmap_raw_request(
+        r#"[[ "Email/set", {
+            "accountId": "$$",
+            "update": {
+                "&&": {
+                    "snoozedUntil": null
+                }
+            }
+          }, "0" ]]"#
+            .replace("$$", &account_id)
+            .replace("&&", &message_ids[1]),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(response.contains("\"updated\""), "{}", response);
+    let response = get_emails(&account_id, &message_ids[1..]).await;
+    assert!(response.contains("\"snoozedUntil\":null"), "{}", response);
+
+    // Clean up
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn get_emails(account_id: &str, message_ids: &[String]) -> String {
+    jmap_raw_request(
+        r#"[[ "Email/get", {
+            "accountId": "$$",
+            "ids": [&&],
+            "properties": ["id", "mailboxIds", "keywords", "snoozedUntil"]
+          }, "0" ]]"#
+            .replace("$$", account_id)
+            .replace(
+                "&&",
+                &message_ids
+                    .iter()
+                    .map(|id| format!("\"{id}\""))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await
+}
@@ -23,6 +23,7 @@
 
 use ahash::AHashMap;
 use directory::backend::internal::manage::ManageDirectory;
+use jmap::JMAP;
 use jmap_client::{
     core::set::{SetError, SetErrorType, SetObject},
     email_submission::{query::Filter, Address, Delivered, DeliveryStatus, Displayed, UndoStatus},
@@ -35,7 +36,11 @@ use std::{
     sync::Arc,
     time::{Duration, Instant},
 };
-use store::parking_lot::Mutex;
+use store::{
+    parking_lot::Mutex,
+    write::{QueueClass, QueueEvent, ValueClass},
+    IterateParams, ValueKey,
+};
 
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
@@ -451,6 +456,14 @@ pub async fn test(params: &mut JMAPTest) {
         ),])
     );
 
+    // Destroying the submission cancels the held message
+    assert_eq!(count_queued_around(&server, hold_until).await, 1);
+    client
+        .email_submission_destroy(&email_submission_id)
+        .await
+        .unwrap();
+    assert_eq!(count_queued_around(&server, hold_until).await, 0);
+
     // Verify onSuccessUpdateEmail action
     let mut request = client.build();
     let set_request = request.set_email_submission();
@@ -512,6 +525,35 @@ pub async fn test(params: &mut JMAPTest) {
     assert_is_empty(server).await;
 }
 
+async fn count_queued_around(server: &JMAP, due: u64) -> usize {
+    let mut count = 0;
+    server
+        .core
+        .storage
+        .data
+        .iterate(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Queue(QueueClass::MessageEvent(QueueEvent {
+                    due: due - 60,
+                    queue_id: 0,
+                }))),
+                ValueKey::from(ValueClass::Queue(QueueClass::MessageEvent(QueueEvent {
+                    due: due + 60,
+                    queue_id: u64::MAX,
+                }))),
+            )
+            .ascending()
+            .no_values(),
+            |_, _| {
+                count += 1;
+                Ok(true)
+            },
+        )
+        .await
+        .unwrap();
+    count
+}
+
 pub fn spawn_mock_smtp_server() -> (mpsc::Receiver<MockMessage>, Arc<Mutex<MockSMTPSettings>>) {
     // Create channels
     let (event_tx, event_rx) = mpsc::channel::<MockMessage>(100);
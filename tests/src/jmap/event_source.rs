@@ -28,9 +28,15 @@ use crate::jmap::{
 };
 use directory::backend::internal::manage::ManageDirectory;
 use futures::StreamExt;
-use jmap::mailbox::INBOX_ID;
+use jmap::{
+    mailbox::INBOX_ID,
+    services::{
+        event_bus::{decode_state_change, encode_state_change},
+        state::Event,
+    },
+};
 use jmap_client::{event_source::Changes, mailbox::Role, TypeState};
-use jmap_proto::types::id::Id;
+use jmap_proto::types::{id::Id, state::StateChange, type_state::DataType};
 use store::ahash::AHashSet;
 
 use tokio::sync::mpsc;
@@ -146,6 +152,27 @@ pub async fn test(params: &mut JMAPTest) {
     assert_ping(&mut event_rx).await;
     assert_ping(&mut event_rx).await;
 
+    // State changes received from other nodes should be delivered
+    let mut state_change =
+        StateChange::new(Id::from_bytes(account_id.as_bytes()).unwrap().document_id());
+    state_change.types.push((DataType::Mailbox, 1));
+    let message = encode_state_change("remote-node:1", &state_change);
+    let (origin, remote_change) = decode_state_change(&message).unwrap();
+    assert_eq!(origin, "remote-node:1");
+    assert_eq!(remote_change.account_id, state_change.account_id);
+    assert_eq!(remote_change.types, state_change.types);
+    assert!(decode_state_change(b"remote-node:1\n1\n16:1").is_none());
+    server
+        .inner
+        .state_tx
+        .send(Event::Publish {
+            state_change: remote_change,
+            is_remote: true,
+        })
+        .await
+        .unwrap();
+    assert_state(&mut event_rx, &account_id, &[TypeState::Mailbox]).await;
+
     destroy_all_mailboxes(params).await;
     assert_is_empty(server).await;
 }
@@ -0,0 +1,234 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use hyper::Method;
+use jmap::mailbox::INBOX_ID;
+use jmap_client::mailbox::Role;
+use jmap_proto::types::{collection::Collection, id::Id, keyword::Keyword, property::Property};
+use serde_json::json;
+
+use crate::jmap::{assert_is_empty, mailbox::destroy_all_mailboxes, ManagementApi};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running account migration tests...");
+    let server = params.server.clone();
+    params
+        .directory
+        .create_test_user_with_email("jdoe@example.com", "12345", "John Doe")
+        .await;
+    params
+        .directory
+        .create_test_user_with_email("jane.smith@example.com", "abcde", "Jane Smith")
+        .await;
+    let source_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .get_or_create_account_id("jdoe@example.com")
+            .await
+            .unwrap(),
+    );
+    let target_id = server
+        .core
+        .storage
+        .data
+        .get_or_create_account_id("jane.smith@example.com")
+        .await
+        .unwrap();
+
+    // Create test messages on the source account
+    let inbox_id = Id::from(INBOX_ID).to_string();
+    let folder_id = params
+        .client
+        .set_default_account_id(source_id.to_string())
+        .mailbox_create("Projects", None::<String>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+    for (num, mailbox_id, keywords) in [
+        (1, &inbox_id, vec!["$seen"]),
+        (2, &folder_id, vec![]),
+        (3, &folder_id, vec!["$flagged", "$seen"]),
+    ] {
+        import_message(params, num, mailbox_id, keywords).await;
+    }
+
+    // Migrate the messages over IMAP
+    let api = ManagementApi::new(8899, "admin", "secret");
+    let request = json!({
+        "host": "127.0.0.1",
+        "port": 9992,
+        "tls": true,
+        "allowInvalidCerts": true,
+        "username": "jdoe@example.com",
+        "secret": "12345"
+    });
+    api.post::<()>("/api/migrate/jane.smith@example.com", &request)
+        .await
+        .unwrap()
+        .unwrap_data();
+    let status = wait_for_migration(&api).await;
+    assert_eq!(status["state"], "completed", "{status}");
+
+    // Validate folders, messages and flags
+    assert_eq!(
+        server
+            .get_document_ids(target_id, Collection::Email)
+            .await
+            .unwrap()
+            .unwrap_or_default()
+            .len(),
+        3
+    );
+    let projects_id = server
+        .mailbox_get_by_name(target_id, "Projects")
+        .await
+        .unwrap()
+        .expect("Projects mailbox not found");
+    for (mailbox_id, expected) in [(INBOX_ID, 1), (projects_id, 2)] {
+        assert_eq!(
+            server
+                .get_tag(
+                    target_id,
+                    Collection::Email,
+                    Property::MailboxIds,
+                    mailbox_id
+                )
+                .await
+                .unwrap()
+                .unwrap_or_default()
+                .len(),
+            expected
+        );
+    }
+    for (keyword, expected) in [(Keyword::Seen, 2), (Keyword::Flagged, 1)] {
+        assert_eq!(
+            server
+                .get_tag(target_id, Collection::Email, Property::Keywords, keyword)
+                .await
+                .unwrap()
+                .unwrap_or_default()
+                .len(),
+            expected
+        );
+    }
+
+    // Resuming the migration should only import new messages
+    import_message(params, 4, &inbox_id, vec![]).await;
+    api.post::<()>("/api/migrate/jane.smith@example.com", &request)
+        .await
+        .unwrap()
+        .unwrap_data();
+    let status = wait_for_migration(&api).await;
+    assert_eq!(status["state"], "completed", "{status}");
+    assert_eq!(
+        server
+            .get_document_ids(target_id, Collection::Email)
+            .await
+            .unwrap()
+            .unwrap_or_default()
+            .len(),
+        4
+    );
+    let inbox_status = status["folders"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|folder| folder["name"] == "INBOX")
+        .unwrap();
+    assert_eq!(inbox_status["imported"], 2, "{status}");
+    assert_eq!(inbox_status["total"], 2, "{status}");
+
+    // Invalid credentials should fail the migration
+    let mut bad_request = request.clone();
+    bad_request["secret"] = "wrong".into();
+    api.post::<()>("/api/migrate/jane.smith@example.com", &bad_request)
+        .await
+        .unwrap()
+        .unwrap_data();
+    let status = wait_for_migration(&api).await;
+    assert_eq!(status["state"], "failed", "{status}");
+
+    // Remove the migration status
+    api.request::<()>(Method::DELETE, "/api/migrate/jane.smith@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    api.request::<serde_json::Value>(Method::GET, "/api/migrate/jane.smith@example.com")
+        .await
+        .unwrap()
+        .unwrap_error();
+
+    // Empty store
+    destroy_all_mailboxes(params).await;
+    params
+        .client
+        .set_default_account_id(Id::from(target_id).to_string());
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn import_message(params: &mut JMAPTest, num: u32, mailbox_id: &str, keywords: Vec<&str>) {
+    params
+        .client
+        .email_import(
+            format!(
+                concat!(
+                    "From: bill@example.com\r\n",
+                    "To: jdoe@example.com\r\n",
+                    "Message-ID: <migrate-{}@example.com>\r\n",
+                    "Subject: Migration test {}\r\n",
+                    "\r\n",
+                    "Message number {}.\r\n"
+                ),
+                num, num, num
+            )
+            .into_bytes(),
+            [mailbox_id],
+            Some(keywords),
+            None,
+        )
+        .await
+        .unwrap();
+}
+
+async fn wait_for_migration(api: &ManagementApi) -> serde_json::Value {
+    for _ in 0..100 {
+        let status = api
+            .request::<serde_json::Value>(Method::GET, "/api/migrate/jane.smith@example.com")
+            .await
+            .unwrap()
+            .unwrap_data();
+        if status["state"] != "running" {
+            return status;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    panic!("Migration did not finish.");
+}
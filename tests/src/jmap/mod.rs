@@ -62,6 +62,7 @@ pub mod auth_limits;
 pub mod auth_oauth;
 pub mod blob;
 pub mod crypto;
+pub mod dav;
 pub mod delivery;
 pub mod email_changes;
 pub mod email_copy;
@@ -71,12 +72,15 @@ pub mod email_query;
 pub mod email_query_changes;
 pub mod email_search_snippet;
 pub mod email_set;
+pub mod email_snooze;
 pub mod email_submission;
 pub mod event_source;
 pub mod mailbox;
+pub mod migrate;
 pub mod purge;
 pub mod push_subscription;
 pub mod quota;
+pub mod saved_search;
 pub mod sieve_script;
 pub mod stress_test;
 pub mod thread_get;
@@ -100,6 +104,12 @@ bind = ["127.0.0.1:9991"]
 protocol = "imap"
 max-connections = 81920
 
+[server.listener.imaps]
+bind = ["127.0.0.1:9992"]
+protocol = "imap"
+max-connections = 81920
+tls.implicit = true
+
 [server.listener.lmtp-debug]
 bind = ['127.0.0.1:11200']
 greeting = 'Test LMTP instance'
@@ -118,6 +128,10 @@ certificate = "default"
 fail2ban = "101/5s"
 rate-limit = "100/2s"
 
+[server.auto-ban]
+duration = "1h"
+max-duration = "3h"
+
 [session.ehlo]
 reject-non-fqdn = false
 
@@ -142,7 +156,7 @@ hash = 64
 type = "system"
 
 [queue.outbound]
-next-hop = [ { if = "rcpt_domain == 'example.com'", then = "'local'" }, 
+next-hop = [ { if = "contains(['example.com', 'example.net'], rcpt_domain)", then = "'local'" }, 
              { if = "contains(['remote.org', 'foobar.com', 'test.com', 'other_domain.com'], rcpt_domain)", then = "'mock-smtp'" },
              { else = false } ]
 
@@ -205,6 +219,11 @@ blob = "{STORE}"
 lookup = "{STORE}"
 directory = "auth"
 
+[storage.full-text.attachments]
+enable = true
+command.path = "cat"
+command.content-types = ["application/x-minutes"]
+
 [spam.header]
 is-spam  = "X-Spam-Status: Yes"
 
@@ -226,6 +245,9 @@ ttl = "1m"
 files = 3
 size = 50000
 
+[metrics.prometheus]
+enable = true
+
 [jmap.rate-limit]
 account = "1000/1m"
 anonymous = "100/1m"
@@ -233,6 +255,30 @@ anonymous = "100/1m"
 [jmap.event-source]
 throttle = "500ms"
 
+[sieve.untrusted.global."archive"]
+domain = "example.net"
+phase = "before"
+contents = '''
+require ["fileinto", "mailbox"];
+if header :contains "Subject" "Invoice" {
+    fileinto :create "Invoices";
+}
+if header :contains "Subject" "Spam" {
+    discard;
+    stop;
+}
+'''
+
+[sieve.untrusted.global."audit"]
+domain = "example.net"
+phase = "after"
+contents = '''
+require ["fileinto", "mailbox"];
+if header :contains "Subject" "Audit" {
+    fileinto :create "Audit";
+}
+'''
+
 [jmap.web-sockets]
 throttle = "500ms"
 
@@ -243,6 +289,15 @@ attempts.interval = "500ms"
 [jmap.email]
 auto-expunge = "1s"
 
+[jmap.retention."sent"]
+role = "sent"
+after = "1s"
+
+[jmap.retention."sent-other"]
+role = "sent"
+action = "keep"
+domain = "other.org"
+
 [jmap.protocol.changes]
 max-history = "1s"
 
@@ -321,6 +376,8 @@ pub async fn jmap_tests() {
     email_search_snippet::test(&mut params).await;
     email_changes::test(&mut params).await;
     email_query_changes::test(&mut params).await;
+    saved_search::test(&mut params).await;
+    email_snooze::test(&mut params).await;
     email_copy::test(&mut params).await;
     thread_get::test(&mut params).await;
     thread_merge::test(&mut params).await;
@@ -332,12 +389,14 @@ pub async fn jmap_tests() {
     event_source::test(&mut params).await;
     push_subscription::test(&mut params).await;
     sieve_script::test(&mut params).await;
+    dav::test(&mut params).await;
     vacation_response::test(&mut params).await;
     email_submission::test(&mut params).await;
     websocket::test(&mut params).await;
     quota::test(&mut params).await;
     crypto::test(&mut params).await;
     blob::test(&mut params).await;
+    migrate::test(&mut params).await;
     purge::test(&mut params).await;
 
     if delete {
@@ -744,6 +803,24 @@ impl ManagementApi {
         })
     }
 
+    pub async fn request_with_body<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        query: &str,
+        body: &str,
+    ) -> Result<Response<T>, String> {
+        self.request_raw(method, query, Some(body.to_string()))
+            .await
+            .map(|result| {
+                serde_json::from_str::<Response<T>>(&result)
+                    .unwrap_or_else(|err| panic!("{err}: {result}"))
+            })
+    }
+
+    pub async fn get_text(&self, query: &str) -> Result<String, String> {
+        self.request_raw(Method::GET, query, None).await
+    }
+
     async fn request_raw(
         &self,
         method: Method,
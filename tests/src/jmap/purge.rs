@@ -21,14 +21,19 @@
  * for more details.
 */
 
+use std::time::Duration;
+
 use ahash::AHashSet;
 use jmap::{
-    mailbox::{INBOX_ID, JUNK_ID, TRASH_ID},
+    mailbox::{INBOX_ID, JUNK_ID, SENT_ID, TRASH_ID},
     JMAP,
 };
 use jmap_proto::types::{collection::Collection, id::Id, property::Property};
 use store::{
-    write::{key::DeserializeBigEndian, TagValue},
+    write::{
+        key::{DeserializeBigEndian, KeySerializer},
+        now, BatchBuilder, LookupClass, TagValue, ValueClass,
+    },
     IterateParams, LogKey, U32_LEN, U64_LEN,
 };
 
@@ -41,6 +46,7 @@ pub async fn test(params: &mut JMAPTest) {
     let inbox_id = Id::from(INBOX_ID).to_string();
     let trash_id = Id::from(TRASH_ID).to_string();
     let junk_id = Id::from(JUNK_ID).to_string();
+    let sent_id = Id::from(SENT_ID).to_string();
 
     // Create test messages
     client.set_default_account_id(Id::from(1u64));
@@ -50,7 +56,7 @@ pub async fn test(params: &mut JMAPTest) {
 
     loop {
         pass += 1;
-        for folder_id in [&inbox_id, &trash_id, &junk_id] {
+        for folder_id in [&inbox_id, &trash_id, &junk_id, &sent_id] {
             message_ids.push(
                 client
                     .email_import(
@@ -92,13 +98,13 @@ pub async fn test(params: &mut JMAPTest) {
             .unwrap()
             .unwrap()
             .len(),
-        6
+        8
     );
 
-    // Purge junk/trash messages and old changes
+    // Purge junk/trash/sent messages and old changes
     server.purge_account(1).await;
 
-    // Only 4 messages should remain
+    // Only 5 messages should remain
     assert_eq!(
         server
             .get_document_ids(1, Collection::Email)
@@ -106,7 +112,7 @@ pub async fn test(params: &mut JMAPTest) {
             .unwrap()
             .unwrap()
             .len(),
-        4
+        5
     );
     assert_eq!(
         server
@@ -151,6 +157,21 @@ pub async fn test(params: &mut JMAPTest) {
         1
     );
 
+    assert_eq!(
+        server
+            .get_tag(
+                1,
+                Collection::Email,
+                Property::MailboxIds,
+                TagValue::Id(SENT_ID)
+            )
+            .await
+            .unwrap()
+            .unwrap()
+            .len(),
+        1
+    );
+
     // Compare changes
     let new_changes = get_changes(&server).await;
     assert!(!changes.is_empty());
@@ -162,6 +183,35 @@ pub async fn test(params: &mut JMAPTest) {
             change
         );
     }
+
+    // Leases are renewed by their holder
+    let lease = Duration::from_secs(60);
+    assert!(server.core.try_lease("test", lease).await);
+    assert!(server.core.try_lease("test", lease).await);
+
+    // Active leases held by other nodes are respected, expired ones are taken over
+    set_lease(&server, Some((now() + 60, "other-node"))).await;
+    assert!(!server.core.try_lease("test", lease).await);
+    set_lease(&server, Some((now() - 1, "other-node"))).await;
+    assert!(server.core.try_lease("test", lease).await);
+    set_lease(&server, None).await;
+}
+
+async fn set_lease(server: &JMAP, lease: Option<(u64, &str)>) {
+    let class = ValueClass::Lookup(LookupClass::Key(b"lease:test".to_vec()));
+    let mut batch = BatchBuilder::new();
+    if let Some((expires, holder)) = lease {
+        batch.set(
+            class,
+            KeySerializer::new(U64_LEN + holder.len())
+                .write(expires)
+                .write(holder)
+                .finalize(),
+        );
+    } else {
+        batch.clear(class);
+    }
+    server.core.storage.data.write(batch.build()).await.unwrap();
 }
 
 async fn get_changes(server: &JMAP) -> AHashSet<(u64, u8)> {
@@ -110,6 +110,7 @@ pub async fn test(params: &mut JMAPTest) {
         auth_secret: auth_secret.to_vec(),
         tx: event_tx,
         fail_requests: false.into(),
+        expire_requests: false.into(),
     });
 
     // Start mock push server
@@ -229,8 +230,25 @@ pub async fn test(params: &mut JMAPTest) {
     assert_state(&mut event_rx, &account_id, &[DataType::Mailbox]).await;
     expect_nothing(&mut event_rx).await;
 
+    // Subscriptions rejected by the push service as expired are destroyed
+    push_server.expire_requests.store(true, Ordering::Relaxed);
+    client
+        .mailbox_update_sort_order(&mailbox_id, 202)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    push_server.expire_requests.store(false, Ordering::Relaxed);
+    client
+        .mailbox_update_sort_order(&mailbox_id, 203)
+        .await
+        .unwrap();
+    expect_nothing(&mut event_rx).await;
+    client
+        .push_subscription_destroy(&push_id)
+        .await
+        .unwrap_err();
+
     // Destroy mailbox
-    client.push_subscription_destroy(&push_id).await.unwrap();
     client.mailbox_destroy(&mailbox_id, true).await.unwrap();
     expect_nothing(&mut event_rx).await;
 
@@ -253,6 +271,7 @@ pub struct PushServer {
     auth_secret: Vec<u8>,
     tx: mpsc::Sender<PushMessage>,
     fail_requests: AtomicBool,
+    expire_requests: AtomicBool,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -316,6 +335,13 @@ impl common::listener::SessionManager for SessionManager {
                                 )
                                 .into_http_response());
                             }
+                            if push.expire_requests.load(Ordering::Relaxed) {
+                                return Ok(HtmlResponse::with_status(
+                                    StatusCode::GONE,
+                                    "gone".to_string(),
+                                )
+                                .into_http_response());
+                            }
                             let is_encrypted = req
                                 .headers()
                                 .get(CONTENT_ENCODING)
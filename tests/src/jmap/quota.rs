@@ -31,10 +31,31 @@ use jmap_client::{
     core::set::{SetErrorType, SetObject},
     email::EmailBodyPart,
 };
-use jmap_proto::types::{collection::Collection, id::Id};
+use jmap_proto::types::{collection::Collection, id::Id, state::StateChange, type_state::DataType};
+use std::time::Duration;
+use store::write::{BatchBuilder, DirectoryClass};
+use tokio::sync::mpsc;
+use utils::map::bitmap::Bitmap;
 
 use super::JMAPTest;
 
+async fn expect_quota_change(state_rx: &mut mpsc::Receiver<StateChange>) {
+    loop {
+        match tokio::time::timeout(Duration::from_secs(1), state_rx.recv()).await {
+            Ok(Some(change))
+                if change
+                    .types
+                    .iter()
+                    .any(|(data_type, _)| *data_type == DataType::Quota) =>
+            {
+                break;
+            }
+            Ok(Some(_)) => (),
+            result => panic!("Expected quota state change, got {:?}", result),
+        }
+    }
+}
+
 pub async fn test(params: &mut JMAPTest) {
     println!("Running quota tests...");
     let server = params.server.clone();
@@ -139,6 +160,17 @@ pub async fn test(params: &mut JMAPTest) {
         "{}",
         response
     );
+    let quota_state = response
+        .split_once("\"state\":\"")
+        .and_then(|(_, state)| state.split_once('"'))
+        .map(|(state, _)| state.to_string())
+        .unwrap();
+
+    // Subscribe to quota state changes
+    let mut state_rx = server
+        .subscribe_state_manager(account_id.document_id(), Bitmap::from(DataType::Quota))
+        .await
+        .unwrap();
 
     // Test Email/import quota
     let inbox_id = Id::new(INBOX_ID as u64).to_string();
@@ -187,6 +219,28 @@ pub async fn test(params: &mut JMAPTest) {
     assert!(response.contains("\"used\":1024"), "{}", response);
     assert!(response.contains("\"hardLimit\":1024"), "{}", response);
 
+    // Usage crossed the configured thresholds, the quota state must have changed
+    assert!(
+        !response.contains(&format!("\"state\":\"{quota_state}\"")),
+        "{}",
+        response
+    );
+    let response = jmap_raw_request(
+        r#"[[ "Quota/changes", {
+            "accountId": "$$",
+            "sinceState": "%%"
+          }, "0" ]]"#
+            .replace("$$", &account_id.to_string())
+            .replace("%%", &quota_state),
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    assert!(response.contains("\"updated\":[\"a\"]"), "{}", response);
+
+    // Crossing the thresholds on import must notify subscribers
+    expect_quota_change(&mut state_rx).await;
+
     // Delete messages and check available quota
     for message_id in message_ids {
         client.email_destroy(&message_id).await.unwrap();
@@ -200,6 +254,9 @@ pub async fn test(params: &mut JMAPTest) {
         0
     );
 
+    // Freeing space on purge must notify subscribers
+    expect_quota_change(&mut state_rx).await;
+
     // Test Email/set quota
     let mut message_ids = Vec::new();
     for i in 0..2 {
@@ -341,6 +398,26 @@ pub async fn test(params: &mut JMAPTest) {
             .len(),
         1,
     );
+
+    // Introduce drift and make sure it is repaired
+    let mut batch = BatchBuilder::new();
+    batch.add(DirectoryClass::UsedQuota(account_id.document_id()), 1000);
+    server.core.storage.data.write(batch.build()).await.unwrap();
+    let results = server
+        .repair_accounts(account_id.document_id().into())
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].quota_stored, quota + 1000);
+    assert_eq!(results[0].quota_computed, quota);
+    assert_eq!(results[0].orphaned_blobs, 0);
+    assert_eq!(
+        server
+            .get_used_quota(account_id.document_id())
+            .await
+            .unwrap(),
+        quota
+    );
     DISABLE_UPLOAD_QUOTA.store(true, std::sync::atomic::Ordering::Relaxed);
 
     // Remove test data
@@ -0,0 +1,206 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use directory::backend::internal::manage::ManageDirectory;
+use jmap::mailbox::INBOX_ID;
+use jmap_proto::types::id::Id;
+
+use crate::jmap::{assert_is_empty, jmap_raw_request, mailbox::destroy_all_mailboxes};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Saved Search tests...");
+
+    // Create test account
+    let server = params.server.clone();
+    params
+        .directory
+        .create_test_user_with_email("jdoe@example.com", "12345", "John Doe")
+        .await;
+    let account_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .get_or_create_account_id("jdoe@example.com")
+            .await
+            .unwrap(),
+    )
+    .to_string();
+    let client = &mut params.client;
+    client.set_default_account_id(&account_id);
+
+    // Import one read and one unread message
+    let inbox_id = Id::new(INBOX_ID as u64).to_string();
+    let mut message_ids = Vec::new();
+    for (subject, keywords) in [("Read", vec!["$seen"]), ("Unread", vec![])] {
+        message_ids.push(
+            client
+                .email_import(
+                    format!(
+                        "From: bill@example.com\r\nTo: jdoe@example.com\r\nSubject: {subject}\r\n\r\ntest"
+                    )
+                    .into_bytes(),
+                    vec![&inbox_id],
+                    Some(keywords),
+                    None,
+                )
+                .await
+                .unwrap()
+                .take_id(),
+        );
+    }
+
+    // Create a saved search
+    let response = jmap_raw_request(
+        r#"[[ "SavedSearch/set", {
+            "accountId": "$$",
+            "create": {
+                "s1": {
+                    "name": "Unread in Inbox",
+                    "filter": {
+                        "operator": "AND",
+                        "conditions": [
+                            { "inMailbox": "%%" },
+                            { "notKeyword": "$seen" }
+                        ]
+                    }
+                },
+                "s2": {
+                    "name": "Invalid",
+                    "filter": { "inSavedSearch": "a" }
+                },
+                "s3": {
+                    "name": "No filter"
+                }
+            }
+          }, "0" ]]"#
+            .replace("$$", &account_id)
+            .replace("%%", &inbox_id),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(response.contains("\"notCreated\""), "{}", response);
+    assert!(!response.contains("\"s2\":{\"id\""), "{}", response);
+    assert!(!response.contains("\"s3\":{\"id\""), "{}", response);
+    let saved_search_id = response
+        .split_once("\"s1\":{\"id\":\"")
+        .and_then(|(_, id)| id.split_once('"'))
+        .map(|(id, _)| id.to_string())
+        .unwrap_or_else(|| panic!("Saved search not created: {response}"));
+
+    // List saved searches
+    let response = jmap_raw_request(
+        r#"[[ "SavedSearch/get", {
+            "accountId": "$$",
+            "ids": null
+          }, "0" ]]"#
+            .replace("$$", &account_id),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(
+        response.contains("\"name\":\"Unread in Inbox\""),
+        "{}",
+        response
+    );
+    assert!(
+        response.contains("\"notKeyword\":\"$seen\""),
+        "{}",
+        response
+    );
+
+    // Query using the saved search, both alone and combined with other conditions
+    for filter in [
+        r#"{ "inSavedSearch": "&&" }"#,
+        r#"{ "operator": "AND", "conditions": [{ "inSavedSearch": "&&" }, { "maxSize": 10000 }] }"#,
+    ] {
+        let response = jmap_raw_request(
+            r#"[[ "Email/query", {
+                "accountId": "$$",
+                "filter": %%
+              }, "0" ]]"#
+                .replace("%%", &filter.replace("&&", &saved_search_id))
+                .replace("$$", &account_id),
+            "jdoe@example.com",
+            "12345",
+        )
+        .await;
+        assert!(
+            response.contains(&format!("\"ids\":[\"{}\"]", message_ids[1])),
+            "{}",
+            response
+        );
+    }
+
+    // Update the saved search to match read messages instead
+    let response = jmap_raw_request(
+        r#"[[ "SavedSearch/set", {
+            "accountId": "$$",
+            "update": {
+                "&&": {
+                    "filter": { "hasKeyword": "$seen" }
+                }
+            }
+          }, "0" ], [ "Email/query", {
+            "accountId": "$$",
+            "filter": { "inSavedSearch": "&&" }
+          }, "1" ]]"#
+            .replace("&&", &saved_search_id)
+            .replace("$$", &account_id),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(response.contains("\"updated\""), "{}", response);
+    assert!(
+        response.contains(&format!("\"ids\":[\"{}\"]", message_ids[0])),
+        "{}",
+        response
+    );
+
+    // Destroy the saved search, queries referencing it should fail
+    let response = jmap_raw_request(
+        r#"[[ "SavedSearch/set", {
+            "accountId": "$$",
+            "destroy": ["&&"]
+          }, "0" ], [ "Email/query", {
+            "accountId": "$$",
+            "filter": { "inSavedSearch": "&&" }
+          }, "1" ]]"#
+            .replace("&&", &saved_search_id)
+            .replace("$$", &account_id),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert!(response.contains("\"destroyed\""), "{}", response);
+    assert!(response.contains("invalidArguments"), "{}", response);
+
+    // Clean up
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
@@ -22,6 +22,7 @@
 */
 
 use directory::backend::internal::manage::ManageDirectory;
+use hyper::Method;
 use jmap_client::{
     core::set::{SetError, SetErrorType},
     email, mailbox,
@@ -40,6 +41,7 @@ use crate::jmap::{
     delivery::SmtpConnection,
     email_submission::{assert_message_delivery, spawn_mock_smtp_server, MockMessage},
     mailbox::destroy_all_mailboxes,
+    ManagementApi,
 };
 
 use super::JMAPTest;
@@ -406,6 +408,41 @@ pub async fn test(params: &mut JMAPTest) {
         "Redirected message was stored."
     );
 
+    // Forwarding loops are bounced
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "Delivered-To: jdoe@example.com\r\n",
+            "Delivered-To: jane@remote.org\r\n",
+            "From: bill@remote.org\r\n",
+            "To: jane@remote.org\r\n",
+            "Subject: TPS Report\r\n",
+            "\r\n",
+            "I'm going to need those TPS reports ASAP."
+        ),
+    )
+    .await;
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new(
+            "<>",
+            ["<bill@remote.org>"],
+            "@Mail forwarding loop detected",
+        ),
+    )
+    .await;
+    assert_eq!(
+        client
+            .email_query(None::<email::query::Filter>, None::<Vec<_>>)
+            .await
+            .unwrap()
+            .ids()
+            .len(),
+        1,
+        "Looping message was stored."
+    );
+
     // Run notify + editheader + notify + fcc tests
     client
         .sieve_script_create("test_notify_fcc", get_script("test_notify_fcc"), true)
@@ -489,6 +526,248 @@ pub async fn test(params: &mut JMAPTest) {
         panic!("Email {:?} not found in: {:#?}", subject, emails);
     }
 
+    // Run extlists tests
+    client
+        .sieve_script_create("test_extlists", get_script("test_extlists"), true)
+        .await
+        .unwrap();
+    lmtp.ingest(
+        "jdoe@example.com",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Address book test\r\n",
+            "\r\n",
+            "Is this sender in the address book?"
+        ),
+    )
+    .await;
+
+    // Manage scripts using the management API
+    let api = ManagementApi::new(8899, "admin", "secret");
+    api.request_with_body::<()>(
+        Method::PUT,
+        "/api/sieve/jdoe@example.com/api_script",
+        "require \"fileinto\"; fileinto \"API\";",
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    assert!(api
+        .request::<Vec<serde_json::Value>>(Method::GET, "/api/sieve/jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .iter()
+        .any(|script| script["name"] == "api_script" && script["isActive"] == false));
+    assert_eq!(
+        api.request::<serde_json::Value>(Method::GET, "/api/sieve/jdoe@example.com/api_script")
+            .await
+            .unwrap()
+            .unwrap_data()["script"],
+        "require \"fileinto\"; fileinto \"API\";"
+    );
+
+    // Overwriting a script keeps the previous version, which can be restored
+    api.request_with_body::<()>(
+        Method::PUT,
+        "/api/sieve/jdoe@example.com/api_script",
+        "discard;",
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    let versions = api
+        .request::<Vec<serde_json::Value>>(
+            Method::GET,
+            "/api/sieve/jdoe@example.com/versions/api_script",
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(versions.len(), 1, "{versions:?}");
+    assert_eq!(versions[0]["version"], 1);
+    api.request::<()>(
+        Method::POST,
+        "/api/sieve/jdoe@example.com/restore/api_script/2",
+    )
+    .await
+    .unwrap()
+    .unwrap_error();
+    api.request::<()>(
+        Method::POST,
+        "/api/sieve/jdoe@example.com/restore/api_script/1",
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    assert_eq!(
+        api.request::<serde_json::Value>(Method::GET, "/api/sieve/jdoe@example.com/api_script")
+            .await
+            .unwrap()
+            .unwrap_data()["script"],
+        "require \"fileinto\"; fileinto \"API\";"
+    );
+    let versions = api
+        .request::<Vec<serde_json::Value>>(
+            Method::GET,
+            "/api/sieve/jdoe@example.com/versions/api_script",
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(versions.len(), 1, "{versions:?}");
+    assert_eq!(versions[0]["size"], 8);
+
+    api.request_with_body::<()>(
+        Method::POST,
+        "/api/sieve/jdoe@example.com/validate",
+        "keep :invalidtag;",
+    )
+    .await
+    .unwrap()
+    .unwrap_error();
+    api.request::<()>(
+        Method::POST,
+        "/api/sieve/jdoe@example.com/activate/api_script",
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    api.request::<()>(Method::DELETE, "/api/sieve/jdoe@example.com/api_script")
+        .await
+        .unwrap()
+        .unwrap_error();
+    api.request::<()>(Method::POST, "/api/sieve/jdoe@example.com/deactivate")
+        .await
+        .unwrap()
+        .unwrap_data();
+    api.request::<()>(Method::DELETE, "/api/sieve/jdoe@example.com/api_script")
+        .await
+        .unwrap()
+        .unwrap_data();
+
+    // Management API mutations should be recorded in the audit log
+    let audit = api
+        .request::<serde_json::Value>(Method::GET, "/api/audit?action=sieve&actor=admin")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(audit["total"], 9, "{audit}");
+    let last_event = &audit["items"][0];
+    assert_eq!(last_event["method"], "DELETE", "{audit}");
+    assert_eq!(
+        last_event["path"], "/api/sieve/jdoe@example.com/api_script",
+        "{audit}"
+    );
+    assert_eq!(
+        last_event["target"], "jdoe@example.com/api_script",
+        "{audit}"
+    );
+    assert!(
+        audit["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|event| event["method"] == "PUT" && event["changes"] == "discard;"),
+        "{audit}"
+    );
+    assert_eq!(
+        api.request::<serde_json::Value>(
+            Method::GET,
+            "/api/audit?action=sieve&after=2000-01-01T00:00:00Z&before=2000-01-02T00:00:00Z"
+        )
+        .await
+        .unwrap()
+        .unwrap_data()["total"],
+        0
+    );
+    assert_eq!(
+        api.request::<serde_json::Value>(Method::GET, "/api/audit?actor=nobody")
+            .await
+            .unwrap()
+            .unwrap_data()["total"],
+        0
+    );
+
+    // Global admin scripts run before and after the user's script
+    params
+        .directory
+        .create_test_user_with_email("robert@example.net", "aabbcc", "Robert Foobar")
+        .await;
+    let robert_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .get_or_create_account_id("robert@example.net")
+            .await
+            .unwrap(),
+    )
+    .to_string();
+    for subject in [
+        "Invoice 42",
+        "Monthly Audit",
+        "Hello there",
+        "Spam for your Audit",
+    ] {
+        lmtp.ingest(
+            "bill@remote.org",
+            &["robert@example.net"],
+            &format!(
+                concat!(
+                    "From: bill@remote.org\r\n",
+                    "To: robert@example.net\r\n",
+                    "Subject: {}\r\n",
+                    "\r\n",
+                    "Global scripts test"
+                ),
+                subject
+            ),
+        )
+        .await;
+    }
+    client.set_default_account_id(&robert_id);
+    for (folder, expected_subjects) in [
+        ("Invoices", &["Invoice 42"][..]),
+        ("Audit", &["Monthly Audit"][..]),
+        ("Inbox", &["Hello there"][..]),
+    ] {
+        let mailbox_id = client
+            .mailbox_query(mailbox::query::Filter::name(folder).into(), None::<Vec<_>>)
+            .await
+            .unwrap()
+            .take_ids()
+            .pop()
+            .unwrap_or_else(|| panic!("Mailbox {:?} not found", folder));
+        let mut subjects = Vec::new();
+        for id in client
+            .email_query(
+                email::query::Filter::in_mailbox(&mailbox_id).into(),
+                None::<Vec<_>>,
+            )
+            .await
+            .unwrap()
+            .take_ids()
+        {
+            subjects.push(
+                client
+                    .email_get(&id, [email::Property::Subject].into())
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .subject()
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+        assert_eq!(subjects, expected_subjects, "folder {folder:?}");
+    }
+    destroy_all_mailboxes(params).await;
+    let client = &mut params.client;
+    client.set_default_account_id(&account_id);
+
     // Remove test data
     client.sieve_script_deactivate().await.unwrap();
     let mut request = client.build();
@@ -24,6 +24,7 @@
 use chrono::{TimeDelta, Utc};
 
 use directory::backend::internal::manage::ManageDirectory;
+use jmap_client::mailbox;
 use jmap_proto::types::id::Id;
 use std::time::Instant;
 
@@ -138,6 +139,49 @@ pub async fn test(params: &mut JMAPTest) {
 
     expect_nothing(&mut smtp_rx).await;
 
+    // Activating a user script should not disable the vacation response
+    let script_id = client
+        .sieve_script_create(
+            "holidays",
+            b"require [\"fileinto\", \"mailbox\"];\r\nfileinto :create \"Holidays\";\r\n".to_vec(),
+            true,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    lmtp.ingest(
+        "mike@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: mike@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Are you around?\r\n",
+            "\r\n",
+            "Bill is looking for you.",
+        ),
+    )
+    .await;
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new("<jdoe@example.com>", ["<mike@remote.org>"], "@Kokomo"),
+    )
+    .await;
+    assert_eq!(
+        client
+            .mailbox_query(
+                mailbox::query::Filter::name("Holidays").into(),
+                None::<Vec<_>>,
+            )
+            .await
+            .unwrap()
+            .ids()
+            .len(),
+        1,
+        "User script was not executed."
+    );
+    client.sieve_script_deactivate().await.unwrap();
+    client.sieve_script_destroy(&script_id).await.unwrap();
+
     // Vacation responses should honor the configured date ranges
     client
         .vacation_response_set_dates(
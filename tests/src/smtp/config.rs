@@ -29,13 +29,14 @@ use common::{
         smtp::{throttle::parse_throttle, *},
     },
     expr::{functions::ResolveVariable, if_block::*, tokenizer::TokenMap, *},
+    listener::tls::{CertificateDirectory, TlsManager},
     Core,
 };
 use tokio::net::TcpSocket;
 
 use utils::config::{Config, Rate};
 
-use super::add_test_certs;
+use super::{add_test_certs, TempDir};
 
 struct TestEnvelope {
     pub local_ip: IpAddr,
@@ -405,6 +406,61 @@ fn parse_servers() {
     }
 }
 
+#[test]
+fn parse_certificate_directory() {
+    let mut cert_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    cert_path.push("resources");
+    cert_path.push("smtp");
+    cert_path.push("certs");
+    let cert = fs::read_to_string(cert_path.join("tls_cert.pem")).unwrap();
+    let pk = fs::read_to_string(cert_path.join("tls_privatekey.pem")).unwrap();
+
+    // Certificates with a separate key file and with the key in the same file
+    let tmp_dir = TempDir::new("smtp_cert_dir_test", true);
+    fs::write(tmp_dir.temp_dir.join("default.crt"), &cert).unwrap();
+    fs::write(tmp_dir.temp_dir.join("default.key"), &pk).unwrap();
+    fs::write(
+        tmp_dir.temp_dir.join("combined.pem"),
+        format!("{cert}\n{pk}"),
+    )
+    .unwrap();
+    fs::write(tmp_dir.temp_dir.join("readme.txt"), "ignored").unwrap();
+
+    let mut config = Config::new(
+        tmp_dir
+            .update_config("[certificate.directory]\npath = \"{TMP}\"\npoll-interval = \"5s\"\n"),
+    )
+    .unwrap();
+    let tls = TlsManager::parse(&mut config);
+    assert!(config.errors.is_empty(), "{:?}", config.errors);
+    let certificates = tls.certificates.load();
+    let mut names = certificates.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["*".to_string(), "localhost".to_string()]);
+    let cert_dir = tls.certificate_dir.unwrap();
+    assert_eq!(cert_dir.path, tmp_dir.temp_dir);
+    assert_eq!(cert_dir.poll_interval, Duration::from_secs(5));
+
+    // Changes to the directory are detected
+    let fingerprint = CertificateDirectory::fingerprint(&cert_dir.path);
+    assert_eq!(
+        fingerprint,
+        CertificateDirectory::fingerprint(&cert_dir.path)
+    );
+    fs::write(tmp_dir.temp_dir.join("other.pem"), format!("{cert}\n{pk}")).unwrap();
+    assert_ne!(
+        fingerprint,
+        CertificateDirectory::fingerprint(&cert_dir.path)
+    );
+
+    // Invalid certificates are reported
+    fs::write(tmp_dir.temp_dir.join("invalid.pem"), "invalid").unwrap();
+    let mut config =
+        Config::new(tmp_dir.update_config("[certificate.directory]\npath = \"{TMP}\"\n")).unwrap();
+    TlsManager::parse(&mut config);
+    assert_eq!(config.errors.len(), 1);
+}
+
 #[tokio::test]
 async fn eval_if() {
     let mut file = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -0,0 +1,104 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::smtp::{
+    build_smtp,
+    session::{TestSession, VerifyResponse},
+    TempDir,
+};
+use common::Core;
+use smtp::core::{Inner, Session};
+use store::{write::now, Stores};
+use utils::config::Config;
+
+const CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/data.db"
+
+[server.auto-ban]
+duration = "1h"
+max-duration = "3h"
+
+[server.auto-ban.abuse]
+rate = "3/1d"
+"#;
+
+#[tokio::test]
+async fn auto_ban_abuse() {
+    let remote_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5));
+
+    // Abuse banning is disabled unless a rate is configured
+    let mut session = Session::test(build_smtp(Core::default(), Inner::default()));
+    session.data.remote_ip = remote_ip;
+    session.data.remote_ip_str = remote_ip.to_string();
+    for _ in 0..10 {
+        session.cmd("FOOBAR", "500 5.5.1").await;
+    }
+    assert!(!session.core.core.is_ip_blocked(&remote_ip));
+
+    // Invalid commands over the configured rate ban the IP address
+    let tmp_dir = TempDir::new("smtp_auto_ban_abuse", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    let mut session = Session::test(build_smtp(core, Inner::default()));
+    session.data.remote_ip = remote_ip;
+    session.data.remote_ip_str = remote_ip.to_string();
+    for _ in 0..3 {
+        session.cmd("FOOBAR", "500 5.5.1").await;
+    }
+    assert!(!session.core.core.is_ip_blocked(&remote_ip));
+    session.ingest(b"FOOBAR\r\n").await.unwrap_err();
+    session.response().assert_code("421 4.3.0");
+    assert!(session.core.core.is_ip_blocked(&remote_ip));
+
+    // Bans are temporary when a ban duration is configured
+    let expires = session
+        .core
+        .core
+        .network
+        .blocked_ips
+        .ip_bans
+        .read()
+        .get(&remote_ip)
+        .copied()
+        .expect("IP address not temporarily banned");
+    assert!(expires > now() + 3500 && expires <= now() + 3600);
+    assert!(!session
+        .core
+        .core
+        .network
+        .blocked_ips
+        .ip_addresses
+        .read()
+        .contains(&remote_ip));
+}
@@ -22,7 +22,7 @@
 */
 
 use common::Core;
-use store::Stores;
+use store::{write::now, Stores};
 use utils::config::Config;
 
 use crate::{
@@ -78,6 +78,10 @@ email = "mike@test.com"
 [session.rcpt]
 directory = "'local'"
 
+[session.data]
+undo-window = [{if = "!is_empty(authenticated_as)", then = "1h"},
+               {else = false}]
+
 [session.data.limits]
 messages = [{if = "remote_ip = '10.0.0.1'", then = 1},
             {else = 100}]
@@ -164,7 +168,7 @@ async fn data() {
             "john@doe.org",
             &["bill@foobar.org"],
             "test:loop",
-            "450 4.4.6",
+            "554 5.4.6",
         )
         .await;
 
@@ -258,6 +262,24 @@ async fn data() {
         )
         .await;
 
+    // Messages from authenticated users are held during the undo window
+    qr.clear_queue(&core).await;
+    session.data.authenticated_as = "bill".to_string();
+    session
+        .send_message(
+            "bill@foobar.org",
+            &["mike@test.com"],
+            "test:no_dkim",
+            "250 2.0.0 Message queued as",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    assert!(message
+        .domains
+        .iter()
+        .all(|domain| domain.retry.due > now() + 3000));
+    session.data.authenticated_as.clear();
+
     // Make sure store is empty
     qr.clear_queue(&core).await;
     core.core
@@ -0,0 +1,144 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use common::Core;
+use mail_auth::common::lru::DnsCache;
+use store::Stores;
+use utils::config::Config;
+
+const CONFIG: &str = r#"
+[spam.dnsbl.list."block"]
+zone = "bl.test"
+type = "ip"
+weight = 3.5
+
+[spam.dnsbl.list."allow"]
+zone = "wl.test"
+type = "ip"
+weight = -1.0
+
+[spam.dnsbl.list."errors"]
+zone = "err.test"
+type = "ip"
+weight = 10.0
+
+[spam.dnsbl.list."domains"]
+zone = "dbl.test"
+type = "domain"
+weight = 2.0
+
+[spam.dnsbl.list."disabled"]
+zone = "off.test"
+type = "domain"
+enable = false
+
+[spam.dnsbl.list."uribl"]
+zone = "uribl.test"
+type = "url"
+weight = 1.5
+
+[spam.url]
+blocklist = "url-block"
+blocklist-weight = 4.0
+
+[lookup]
+url-block = {"evil.org"}
+
+[resolver]
+public-suffix = "file://{LIST_PATH}/public-suffix.dat"
+"#;
+
+const MESSAGE: &str = "From: john@foobar.org\r
+To: jane@foobar.org\r
+Subject: Account verification\r
+\r
+Please log in at http://www.evil.org/login and then visit\r
+https://login.example.net/verify?id=1 or http://10.0.0.1/.\r
+";
+
+#[tokio::test]
+async fn dnsbl() {
+    let mut config = Config::new(
+        CONFIG.replace(
+            "{LIST_PATH}",
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("resources")
+                .join("smtp")
+                .join("lists")
+                .to_str()
+                .unwrap(),
+        ),
+    )
+    .unwrap();
+    let mut stores = Stores::default();
+    stores.parse_memory_stores(&mut config);
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    assert_eq!(core.smtp.dnsbl.lists.len(), 5);
+
+    let valid_until = Instant::now() + Duration::from_secs(10);
+    for (name, addr) in [
+        ("1.0.0.10.bl.test", "127.0.0.2"),
+        ("1.0.0.10.wl.test", "127.0.10.0"),
+        ("1.0.0.10.err.test", "127.255.255.254"),
+        ("foobar.org.dbl.test", "127.0.1.2"),
+        ("example.net.uribl.test", "127.0.0.4"),
+        ("evil.org.uribl.test", "127.255.255.254"),
+    ] {
+        core.smtp
+            .resolvers
+            .dns
+            .ipv4_add(name, vec![addr.parse().unwrap()], valid_until);
+    }
+
+    // Query error codes should not count as a hit
+    let result = core.dnsbl_check_ip("10.0.0.1".parse().unwrap()).await;
+    assert_eq!(result.score, 2.5);
+    assert_eq!(result.hits, vec!["allow".to_string(), "block".to_string()]);
+
+    let result = core.dnsbl_check_domain("foobar.org").await;
+    assert_eq!(result.score, 2.0);
+    assert_eq!(result.hits, vec!["domains".to_string()]);
+
+    // Results are cached
+    assert!(core.smtp.resolvers.cache.dnsbl.get("foobar.org").is_some());
+
+    // URL domains are checked against the local blocklist and the URL lists
+    let result = core.url_reputation(MESSAGE.as_bytes()).await;
+    assert_eq!(result.score, 5.5);
+    assert_eq!(
+        result
+            .hits
+            .iter()
+            .map(|hit| format!("{} ({})", hit.host, hit.list))
+            .collect::<Vec<_>>(),
+        vec![
+            "evil.org (blocklist)".to_string(),
+            "example.net (uribl)".to_string()
+        ]
+    );
+}
@@ -44,6 +44,8 @@ future-release = [{if = "remote_ip = '10.0.0.1'", then = '1h'},
                   {else = false}]
 mt-priority = [{if = "remote_ip = '10.0.0.1'", then = 'nsep'},
                {else = false}]
+requiretls = [{if = "tls_sni = 'mx.foobar.org'", then = true},
+              {else = false}]
 
 [session.ehlo]
 reject-non-fqdn = true
@@ -112,5 +114,14 @@ async fn ehlo() {
         .assert_contains("SIZE 2048")
         .assert_not_contains("MT-PRIORITY")
         .assert_not_contains("FUTURERELEASE")
+        .assert_not_contains("STARTTLS")
+        .assert_not_contains("REQUIRETLS");
+
+    // Extensions evaluated using the SNI sent by the client
+    session.stream.sni = Some("mx.foobar.org".to_string());
+    session
+        .cmd("EHLO mx2.foobar.org", "250")
+        .await
+        .assert_contains("REQUIRETLS")
         .assert_not_contains("STARTTLS");
 }
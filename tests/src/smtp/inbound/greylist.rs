@@ -0,0 +1,107 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use common::Core;
+
+use store::Stores;
+use utils::config::Config;
+
+use smtp::core::{Inner, Session};
+
+use crate::smtp::{
+    build_smtp,
+    session::{TestSession, VerifyResponse},
+    TempDir,
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/queue.db"
+
+[session.rcpt]
+relay = true
+
+[session.rcpt.greylist]
+enable = [{if = "remote_ip = '10.0.0.2'", then = false},
+          {else = true}]
+delay = "1s"
+expire = "1h"
+
+[session.rcpt.greylist.whitelist]
+retries = 2
+expire = "1h"
+"#;
+
+#[tokio::test]
+async fn greylist() {
+    let tmp_dir = TempDir::new("smtp_greylist_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+
+    // Greylisting is disabled for 10.0.0.2
+    let mut session = Session::test(build_smtp(core, Inner::default()));
+    session.data.remote_ip_str = "10.0.0.2".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx1.foobar.org").await;
+    session.mail_from("john@example.net", "250").await;
+    session.rcpt_to("jane@foobar.org", "250").await;
+
+    // First attempts from 10.0.0.1 are deferred
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.rset().await;
+    session.mail_from("john@example.net", "250").await;
+    session.rcpt_to("jane@foobar.org", "451 4.7.1").await;
+    session.rcpt_to("bill@foobar.org", "451 4.7.1").await;
+    session.rcpt_to("jane@foobar.org", "451 4.7.1").await;
+
+    // Retries after the delay are accepted, also from the same network
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    session.data.remote_ip_str = "10.0.0.100".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.rcpt_to("mike@foobar.org", "451 4.7.1").await;
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.rcpt_to("jane@foobar.org", "250").await;
+    session.rcpt_to("bill@foobar.org", "250").await;
+
+    // The network is now whitelisted
+    session.rcpt_to("mike@foobar.org", "250").await;
+    session.rset().await;
+    session.mail_from("john@otherdomain.net", "250").await;
+    session.rcpt_to("jane@foobar.org", "250").await;
+}
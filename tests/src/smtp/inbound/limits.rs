@@ -26,7 +26,8 @@ use std::time::{Duration, Instant};
 use common::Core;
 use tokio::sync::watch;
 
-use smtp::core::{Inner, Session};
+use smtp::core::{Inner, Session, State};
+use smtp_proto::request::receiver::DataReceiver;
 use utils::config::Config;
 
 use crate::smtp::{
@@ -86,4 +87,34 @@ async fn limits() {
     session.write_rx("MAIL FROM:<this_is_a_long@command_over_10_chars.com>\r\n");
     session.handle_conn().await;
     session.response().assert_code("221 2.0.0");
+
+    // Oversized messages are rejected before the transfer completes
+    session.params.max_message_size = 1024;
+    session.params.max_header_size = 128;
+    session.state = State::Data(DataReceiver::new());
+    session.ingest(b"Subject: test\r\n\r\n").await.unwrap();
+    assert!(session.ingest(&[b'A'; 2048]).await.is_err());
+    session.response().assert_code("552 5.3.4");
+    assert!(session.data.message.is_empty());
+
+    // Header section exceeding its own limit
+    session.state = State::Data(DataReceiver::new());
+    let mut buf = b"X-Header: ".to_vec();
+    buf.extend_from_slice(&[b'A'; 256]);
+    assert!(session.ingest(&buf).await.is_err());
+    session
+        .response()
+        .assert_code("552 5.3.4")
+        .assert_contains("header section");
+
+    // Headers within the limit followed by a larger body
+    session.state = State::Data(DataReceiver::new());
+    session.ingest(b"Subject: test\r\n\r\n").await.unwrap();
+    session.ingest(&[b'A'; 512]).await.unwrap();
+    session.state = State::default();
+    session.reset();
+
+    // Oversized BDAT chunks are refused without reading them
+    assert!(session.ingest(b"BDAT 2048 LAST\r\n").await.is_err());
+    session.response().assert_code("552 5.3.4");
 }
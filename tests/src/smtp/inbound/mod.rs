@@ -39,15 +39,19 @@ use super::{QueueReceiver, ReportReceiver};
 
 pub mod antispam;
 pub mod auth;
+pub mod auto_ban;
 pub mod basic;
 pub mod data;
 pub mod dmarc;
+pub mod dnsbl;
 pub mod ehlo;
+pub mod greylist;
 pub mod limits;
 pub mod mail;
 pub mod milter;
 pub mod rcpt;
 pub mod rewrite;
+pub mod rspamd;
 pub mod scripts;
 pub mod sign;
 pub mod throttle;
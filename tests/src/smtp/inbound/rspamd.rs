@@ -0,0 +1,331 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::Core;
+use smtp::{
+    core::{Inner, Session},
+    inbound::rspamd::{rspamd_learn, RspamdAction, RspamdResult},
+};
+use store::Stores;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::watch,
+};
+use utils::config::Config;
+
+use crate::smtp::{
+    build_smtp,
+    inbound::TestMessage,
+    session::{TestSession, VerifyResponse},
+    TempDir, TestSMTP,
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "sqlite"
+lookup = "sqlite"
+blob = "sqlite"
+fts = "sqlite"
+
+[store."sqlite"]
+type = "sqlite"
+path = "{TMP}/queue.db"
+
+[session.rcpt]
+relay = true
+
+[session.data.rspamd]
+url = "http://127.0.0.1:9333/"
+enable = true
+timeout = "5s"
+learn.enable = true
+"#;
+
+#[tokio::test]
+async fn rspamd_session() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::TRACE)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Configure tests
+    let tmp_dir = TempDir::new("smtp_rspamd_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let _tx = spawn_mock_rspamd_server(requests.clone());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let mut inner = Inner::default();
+    let mut qr = inner.init_test_queue(&core);
+
+    // Build session
+    let rspamd = core.smtp.session.data.rspamd.clone().unwrap();
+    let mut session = Session::test(build_smtp(core, inner));
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.doe.org").await;
+
+    // Test reject
+    session
+        .send_message(
+            "reject@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "550 5.7.1 Spam message rejected",
+        )
+        .await;
+    qr.assert_no_events();
+
+    // Test soft reject and greylist
+    for sender in ["soft_reject@doe.org", "greylist@doe.org"] {
+        session
+            .send_message(sender, &["bill@foobar.org"], "test:no_dkim", "451 4.7.1")
+            .await;
+        qr.assert_no_events();
+    }
+
+    // Test add header
+    session
+        .send_message(
+            "add_header@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250 2.0.0",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("X-Spam: Yes")
+        .assert_contains("X-Spamd-Result: default: True [12.50 / 15.00]")
+        .assert_contains("Subject: Is dinner ready?");
+
+    // Test rewrite subject
+    session
+        .send_message(
+            "rewrite_subject@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250 2.0.0",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Subject: *** SPAM *** Is dinner ready?")
+        .assert_not_contains("Message-ID: <20030712040037.46341.5F8J@football.example.com>")
+        .assert_not_contains("X-Spam: Yes");
+
+    // Test no action
+    session
+        .send_message(
+            "john@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250 2.0.0",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Subject: Is dinner ready?")
+        .assert_not_contains("X-Spam: Yes");
+
+    // Make sure the envelope was sent to rspamd
+    {
+        let requests = requests.lock().unwrap();
+        let request = requests.last().unwrap();
+        assert!(request.starts_with("post /checkv2 "), "{request}");
+        for header in [
+            "from: john@doe.org",
+            "rcpt: bill@foobar.org",
+            "helo: mx.doe.org",
+        ] {
+            assert!(request.contains(header), "{request}");
+        }
+    }
+
+    // Test training
+    rspamd_learn(&rspamd, b"Subject: test\r\n\r\ntest", true)
+        .await
+        .unwrap();
+    rspamd_learn(&rspamd, b"Subject: test\r\n\r\ntest", false)
+        .await
+        .unwrap();
+    let requests = requests.lock().unwrap();
+    assert!(requests[requests.len() - 2].starts_with("post /learnspam "));
+    assert!(requests[requests.len() - 1].starts_with("post /learnham "));
+}
+
+#[test]
+fn rspamd_parse_response() {
+    let result = RspamdResult::parse(
+        br#"{
+            "is_skipped": false,
+            "score": 16.5,
+            "required_score": 15.0,
+            "action": "rewrite subject",
+            "subject": "[SPAM] Hello",
+            "symbols": {},
+            "messages": {"smtp_message": "Go away"},
+            "milter": {
+                "add_headers": {
+                    "X-Spamd-Bar": "++++++",
+                    "X-Spamd-Result": {"value": "default: True", "order": 0},
+                    "X-Rspamd-Server": [{"value": "rspamd1", "order": 0}, "rspamd2"]
+                },
+                "remove_headers": {"X-Spam": 0}
+            }
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(result.action, RspamdAction::RewriteSubject);
+    assert_eq!(result.score, 16.5);
+    assert_eq!(result.required_score, 15.0);
+    assert_eq!(result.message.as_deref(), Some("Go away"));
+    let mut headers = result.add_headers.clone();
+    headers.sort();
+    assert_eq!(
+        headers,
+        [
+            ("X-Rspamd-Server", "rspamd1"),
+            ("X-Rspamd-Server", "rspamd2"),
+            ("X-Spamd-Bar", "++++++"),
+            ("X-Spamd-Result", "default: True"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<Vec<_>>()
+    );
+    assert_eq!(result.remove_headers, vec!["X-Spam".to_string()]);
+    assert_eq!(result.modifications().len(), 2);
+
+    assert!(RspamdResult::parse(br#"{"action": "discard"}"#).is_err());
+}
+
+fn spawn_mock_rspamd_server(requests: Arc<Mutex<Vec<String>>>) -> watch::Sender<bool> {
+    let (tx, mut rx) = watch::channel(true);
+
+    tokio::spawn(async move {
+        let listener = TcpListener::bind("127.0.0.1:9333")
+            .await
+            .unwrap_or_else(|e| {
+                panic!("Failed to bind mock rspamd server to 127.0.0.1:9333: {e}");
+            });
+        loop {
+            tokio::select! {
+                stream = listener.accept() => {
+                    match stream {
+                        Ok((stream, _)) => {
+                            tokio::spawn(accept_rspamd(stream, requests.clone()));
+                        }
+                        Err(err) => {
+                            panic!("Something went wrong: {err}" );
+                        }
+                    }
+                },
+                _ = rx.changed() => {
+                    break;
+                }
+            };
+        }
+    });
+
+    tx
+}
+
+async fn accept_rspamd(mut stream: TcpStream, requests: Arc<Mutex<Vec<String>>>) {
+    // Read headers and body
+    let mut buf = Vec::new();
+    let mut chunk = vec![0u8; 4096];
+    let (headers, body_len) = loop {
+        let br = match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(br) => br,
+        };
+        buf.extend_from_slice(&chunk[..br]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let headers = String::from_utf8_lossy(&buf[..pos]).to_lowercase();
+            let body_len = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|len| len.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            buf.drain(..pos + 4);
+            break (headers, body_len);
+        }
+    };
+    while buf.len() < body_len {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(br) => buf.extend_from_slice(&chunk[..br]),
+        }
+    }
+
+    let sender = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("from:"))
+        .map(|from| from.trim().to_string())
+        .unwrap_or_default();
+    let response = match sender.split_once('@').map(|(local, _)| local) {
+        Some("reject") => {
+            r#"{"action": "reject", "score": 20.0, "required_score": 15.0, "messages": {"smtp_message": "Spam message rejected"}}"#
+        }
+        Some("soft_reject") => r#"{"action": "soft reject", "score": 10.0}"#,
+        Some("greylist") => r#"{"action": "greylist", "score": 5.0}"#,
+        Some("add_header") => {
+            r#"{"action": "add header", "score": 12.5, "required_score": 15.0, "milter": {"add_headers": {"X-Spamd-Result": {"value": "default: True [12.50 / 15.00]", "order": 0}}}}"#
+        }
+        Some("rewrite_subject") => {
+            r#"{"action": "rewrite subject", "score": 13.0, "subject": "*** SPAM *** Is dinner ready?", "milter": {"remove_headers": {"Message-ID": 0}}}"#
+        }
+        _ if headers.starts_with("post /learn") => r#"{"success": true}"#,
+        _ => r#"{"action": "no action", "score": 0.5, "required_score": 15.0}"#,
+    };
+    requests.lock().unwrap().push(headers);
+
+    let _ = stream
+        .write_all(
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .as_bytes(),
+        )
+        .await;
+    let _ = stream.flush().await;
+}
@@ -23,7 +23,7 @@
 
 use std::time::{Duration, Instant};
 
-use common::Core;
+use common::{listener::stream::NullIo, Core};
 
 use mail_auth::{
     common::{parse::TxtRecordParser, verify::DomainKey},
@@ -35,10 +35,10 @@ use utils::config::Config;
 use crate::smtp::{
     build_smtp,
     inbound::TestMessage,
-    session::{TestSession, VerifyResponse},
+    session::{load_test_message, TestSession, VerifyResponse},
     TempDir, TestSMTP,
 };
-use smtp::core::{Inner, Session};
+use smtp::core::{Inner, Session, SessionAddress};
 
 pub const SIGNATURES: &str = "
 [signature.rsa]
@@ -127,7 +127,8 @@ ehlo = "relaxed"
 mail-from = "relaxed"
 
 [auth.dkim]
-verify = "relaxed"
+verify = [{if = "listener = 'sieve'", then = "disable"},
+          {else = "relaxed"}]
 sign = "['rsa']"
 
 [auth.arc]
@@ -135,7 +136,8 @@ verify = "relaxed"
 seal = "'ed'"
 
 [auth.dmarc]
-verify = "relaxed"
+verify = [{if = "listener = 'sieve'", then = "disable"},
+          {else = "relaxed"}]
 
 "#;
 
@@ -217,7 +219,8 @@ async fn sign_and_seal() {
         .await
         .assert_contains(
             "DKIM-Signature: v=1; a=rsa-sha256; s=rsa; d=example.com; c=simple/relaxed;",
-        );
+        )
+        .assert_contains("ARC-Seal: i=1; a=ed25519-sha256; s=ed; d=example.com; cv=none;");
 
     // Test ARC verify and seal
     session
@@ -228,6 +231,7 @@ async fn sign_and_seal() {
         .read_lines(&qr)
         .await
         .assert_contains("ARC-Seal: i=3; a=ed25519-sha256; s=ed; d=example.com; cv=pass;")
+        .assert_contains("arc=pass")
         .assert_contains(
             "ARC-Message-Signature: i=3; a=ed25519-sha256; s=ed; d=example.com; c=relaxed/simple;",
         );
@@ -244,4 +248,25 @@ async fn sign_and_seal() {
         .assert_contains(
             "ARC-Message-Signature: i=1; a=ed25519-sha256; s=ed; d=example.com; c=relaxed/simple;",
         );
+
+    // Test ARC sealing of messages forwarded by Sieve scripts, which skip DKIM and DMARC verification
+    let response = Session::<NullIo>::sieve(
+        session.core.clone(),
+        SessionAddress::new("jdoe@example.com".to_string()),
+        vec![SessionAddress::new("jane@foobar.org".to_string())],
+        load_test_message("no_dkim", "messages")
+            .replace('\n', "\r\n")
+            .into_bytes(),
+    )
+    .queue_message()
+    .await;
+    assert!(response.starts_with(b"250"), "{:?}", response);
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("ARC-Seal: i=1; a=ed25519-sha256; s=ed; d=example.com; cv=none;")
+        .assert_contains(
+            "ARC-Message-Signature: i=1; a=ed25519-sha256; s=ed; d=example.com; c=relaxed/simple;",
+        );
 }
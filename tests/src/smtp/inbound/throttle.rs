@@ -57,6 +57,14 @@ key = ['remote_ip', 'rcpt']
 rate = '2/1s'
 enable = true
 
+[session.data.sending-limit."default"]
+messages = '2/1d'
+recipients = '3/1h'
+
+[session.data.sending-limit."bill"]
+account = 'bill@example.org'
+messages = '5/1d'
+
 "#;
 
 #[tokio::test]
@@ -135,4 +143,46 @@ async fn throttle_inbound() {
     assert!(!session.is_allowed().await, "Rate limiter failed.");
     session.data.remote_ip_str = "10.0.0.2".to_string();
     assert!(session.is_allowed().await, "Rate limiter too strict.");
+
+    // Test per-account message sending limits
+    session.data.authenticated_as = "jane@example.org".to_string();
+    assert!(
+        session.is_sending_allowed().await,
+        "Sending limit too strict."
+    );
+    assert!(
+        session.is_sending_allowed().await,
+        "Sending limit too strict."
+    );
+    assert!(!session.is_sending_allowed().await, "Sending limit failed.");
+
+    // Test per-account recipient sending limits
+    session.data.authenticated_as = "john@example.org".to_string();
+    session.data.rcpt_to.push(SessionAddress {
+        address: "other@example.org".to_string(),
+        address_lcase: "other@example.org".to_string(),
+        domain: "example.org".to_string(),
+        flags: 0,
+        dsn_info: None,
+    });
+    assert!(
+        session.is_sending_allowed().await,
+        "Sending limit too strict."
+    );
+    assert!(!session.is_sending_allowed().await, "Sending limit failed.");
+    session.data.rcpt_to.pop();
+    assert!(
+        session.is_sending_allowed().await,
+        "Sending limit too strict."
+    );
+
+    // Account limits override the server-wide limits
+    session.data.authenticated_as = "bill@example.org".to_string();
+    for _ in 0..5 {
+        assert!(
+            session.is_sending_allowed().await,
+            "Sending limit too strict."
+        );
+    }
+    assert!(!session.is_sending_allowed().await, "Sending limit failed.");
 }
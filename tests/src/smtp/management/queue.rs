@@ -379,6 +379,71 @@ async fn manage_queue() {
         }
     }
 
+    // Hold and release messages
+    let id_c = *id_map.get("c").unwrap();
+    for (action, expected) in [("hold", true), ("hold", false)] {
+        assert_eq!(
+            api.request::<bool>(
+                Method::PATCH,
+                &format!("/api/queue/messages/{id_c}?action={action}")
+            )
+            .await
+            .unwrap()
+            .unwrap_data(),
+            expected
+        );
+    }
+    assert!(api.get_messages(&[id_c]).await[0].as_ref().unwrap().on_hold);
+    for (query, expected_ids) in [
+        ("/api/queue/messages?status=on_hold", vec!["c"]),
+        ("/api/queue/messages?domain=example1.org", vec!["a"]),
+        ("/api/queue/messages?status=temp_fail", vec!["f"]),
+        ("/api/queue/messages?min-age=3600", vec![]),
+        (
+            "/api/queue/messages?max-age=3600",
+            vec!["a", "b", "c", "d", "f"],
+        ),
+    ] {
+        let expected_ids = HashSet::from_iter(expected_ids.into_iter().map(|s| s.to_string()));
+        let ids = api
+            .request::<List<QueueId>>(Method::GET, query)
+            .await
+            .unwrap()
+            .unwrap_data()
+            .items
+            .into_iter()
+            .map(|id| id_map_rev.get(&id).unwrap().clone())
+            .collect::<HashSet<_>>();
+        assert_eq!(ids, expected_ids, "failed for {query}");
+    }
+    assert!(api
+        .request::<bool>(
+            Method::PATCH,
+            &format!("/api/queue/messages/{id_c}?action=release")
+        )
+        .await
+        .unwrap()
+        .unwrap_data());
+    assert!(!api.get_messages(&[id_c]).await[0].as_ref().unwrap().on_hold);
+
+    // Bulk hold and release by destination domain
+    for action in ["hold", "release"] {
+        assert_eq!(
+            api.request::<usize>(
+                Method::PATCH,
+                &format!("/api/queue/messages?domain=example3.com&action={action}")
+            )
+            .await
+            .unwrap()
+            .unwrap_data(),
+            1
+        );
+        assert_eq!(
+            api.get_messages(&[id_c]).await[0].as_ref().unwrap().on_hold,
+            action == "hold"
+        );
+    }
+
     // Cancel deliveries
     for (id, filter) in [
         ("a", "example2.org"),
@@ -464,6 +529,19 @@ async fn manage_queue() {
         }
     }
 
+    // Bulk cancel deliveries by destination domain
+    assert_eq!(
+        api.request::<usize>(Method::DELETE, "/api/queue/messages?domain=example1.org")
+            .await
+            .unwrap()
+            .unwrap_data(),
+        1
+    );
+    assert_eq!(
+        api.get_messages(&[*id_map.get("a").unwrap()]).await,
+        vec![None]
+    );
+
     // Test authentication error
     assert_eq!(
         reqwest::Client::builder()
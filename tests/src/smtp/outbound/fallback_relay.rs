@@ -27,7 +27,9 @@ use common::config::server::ServerProtocol;
 use mail_auth::MX;
 use store::write::now;
 
-use crate::smtp::{outbound::TestServer, session::TestSession};
+use smtp::queue::{Domain, QueueEnvelope, Schedule, Status};
+
+use crate::smtp::{outbound::TestServer, queue::manager::new_message, session::TestSession};
 
 const LOCAL: &str = r#"
 [queue.outbound]
@@ -132,3 +134,81 @@ async fn fallback_relay() {
     tokio::time::sleep(Duration::from_millis(100)).await;
     remote.qr.expect_message().await;
 }
+
+#[tokio::test]
+async fn relay_routing() {
+    let local = TestServer::new(
+        "smtp_relay_routing",
+        r#"
+[queue.outbound]
+next-hop = [{if = "size > 1048576", then = "'bulk'"},
+            {if = "sender_domain = 'foobar.org' && rcpt_domain = 'partner.org'", then = "'partner'"},
+            {else = false}]
+
+[remote.bulk]
+address = bulk.foobar.org
+port = 587
+protocol = 'smtp'
+
+[remote.bulk.tls]
+implicit = false
+
+[remote.partner]
+address = mx.partner.org
+port = 465
+protocol = 'smtp'
+
+[remote.partner.auth]
+username = 'foobar'
+secret = 'secret'
+"#,
+        true,
+    )
+    .await;
+    let core = local.build_smtp();
+
+    let mut message = new_message(0);
+    message.domains.push(Domain {
+        domain: "partner.org".to_string(),
+        retry: Schedule::now(),
+        notify: Schedule::now(),
+        expires: now() + 10,
+        status: Status::Scheduled,
+    });
+    message.domains.push(Domain {
+        domain: "other.org".to_string(),
+        retry: Schedule::now(),
+        notify: Schedule::now(),
+        expires: now() + 10,
+        status: Status::Scheduled,
+    });
+
+    for (size, domain_idx, expected) in [
+        (1024, 0, Some("partner")),
+        (1024, 1, None),
+        (2 * 1048576, 0, Some("bulk")),
+        (2 * 1048576, 1, Some("bulk")),
+    ] {
+        message.size = size;
+        let next_hop = core
+            .core
+            .eval_if::<String, _>(
+                &core.core.smtp.queue.next_hop,
+                &QueueEnvelope::new(&message, domain_idx),
+            )
+            .await;
+        assert_eq!(
+            next_hop.as_deref(),
+            expected,
+            "size {size}, domain {domain_idx}"
+        );
+    }
+
+    let relay = core.core.get_relay_host("partner").unwrap();
+    assert_eq!(relay.port, 465);
+    assert!(relay.auth.is_some());
+    assert!(relay.tls_implicit);
+    let relay = core.core.get_relay_host("bulk").unwrap();
+    assert!(relay.auth.is_none());
+    assert!(!relay.tls_implicit);
+}
@@ -46,6 +46,7 @@ pub mod fallback_relay;
 pub mod ip_lookup;
 pub mod lmtp;
 pub mod mta_sts;
+pub mod pool;
 pub mod smtp;
 pub mod throttle;
 pub mod tls;
@@ -266,4 +266,43 @@ async fn mta_sts_verify() {
         )
     );
     assert!(report.failure.is_none());
+
+    // MTA-STS policies in testing mode do not prevent delivery
+    core.core.smtp.resolvers.dns.txt_add(
+        "_mta-sts.foobar.org",
+        MtaSts::parse(b"v=STSv1; id=policy_testing;").unwrap(),
+        Instant::now() + Duration::from_secs(10),
+    );
+    let policy = concat!(
+        "version: STSv1\n",
+        "mode: testing\n",
+        "mx: mail.foobar.net\n",
+        "max_age: 604800\n"
+    );
+    STS_TEST_POLICY.lock().clear();
+    STS_TEST_POLICY.lock().extend_from_slice(policy.as_bytes());
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    local
+        .qr
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone())
+        .await;
+    local.qr.read_event().await.assert_reload();
+    remote
+        .qr
+        .expect_message()
+        .await
+        .read_lines(&remote.qr)
+        .await
+        .assert_contains("using TLSv1.3 with cipher");
+
+    // Expect TLS failure report
+    let report = local.rr.read_report().await.unwrap_tls();
+    assert_eq!(
+        report.failure.as_ref().unwrap().result_type,
+        ResultType::ValidationFailure
+    );
 }
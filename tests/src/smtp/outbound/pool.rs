@@ -0,0 +1,74 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::Duration,
+};
+
+use mail_send::Credentials;
+use smtp::outbound::pool::{ConnectionPool, PoolKey};
+
+#[test]
+fn connection_pool() {
+    let key = PoolKey {
+        mx: "mx.foobar.org".to_string(),
+        remote_ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        remote_port: 25,
+        local_ip: None,
+        is_strict_tls: false,
+        is_dane: false,
+        credentials: None,
+    };
+    let strict_key = PoolKey {
+        is_strict_tls: true,
+        ..key.clone()
+    };
+    let auth_key = PoolKey {
+        credentials: Some(Credentials::new("john".to_string(), "secret".to_string())),
+        ..key.clone()
+    };
+    let idle_timeout = Duration::from_secs(30);
+    let pool = ConnectionPool::<PoolKey, u32>::default();
+
+    // Sessions are only reused with the same TLS requirements and credentials
+    assert_eq!(pool.release(key.clone(), 1, 2, 10), None);
+    assert_eq!(pool.take(&strict_key, idle_timeout), None);
+    assert_eq!(pool.take(&auth_key, idle_timeout), None);
+    assert_eq!(pool.take(&key, idle_timeout), Some(1));
+    assert_eq!(pool.take(&key, idle_timeout), None);
+
+    // Idle sessions are limited per host and globally
+    assert_eq!(pool.release(key.clone(), 1, 2, 3), None);
+    assert_eq!(pool.release(key.clone(), 2, 2, 3), None);
+    assert_eq!(pool.release(key.clone(), 3, 2, 3), Some(3));
+    assert_eq!(pool.release(auth_key.clone(), 4, 2, 3), None);
+    assert_eq!(pool.release(strict_key.clone(), 5, 2, 3), Some(5));
+    assert_eq!(pool.len(), 3);
+
+    // Expired sessions are reaped and not handed out
+    assert!(pool.reap(idle_timeout).is_empty());
+    assert_eq!(pool.take(&key, Duration::ZERO), None);
+    assert_eq!(pool.reap(Duration::ZERO), vec![4]);
+    assert!(pool.is_empty());
+}
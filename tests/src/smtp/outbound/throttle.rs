@@ -321,6 +321,52 @@ async fn throttle_outbound() {
     assert!(due > 0, "Due: {}", due);
 }
 
+#[tokio::test]
+async fn throttle_warmup() {
+    let local = TestServer::new(
+        "smtp_throttle_warmup",
+        r#"
+[queue.warmup.new-ip]
+source-ip = "10.0.0.10"
+start = "2020-01-01T00:00:00Z"
+rate = "1/1h"
+step = "1d"
+growth = 1.0
+"#,
+        true,
+    )
+    .await;
+    let core = local.build_smtp();
+    let span = tracing::info_span!("test");
+
+    // Check the schedule
+    let warmup = &core.core.smtp.queue.throttle.warmup[0];
+    assert_eq!(warmup.rate_at(now()).unwrap().requests, 1);
+    let mut warmup = warmup.clone();
+    warmup.growth = 2.0;
+    warmup.max_requests = 8;
+    assert_eq!(warmup.rate_at(warmup.start).unwrap().requests, 1);
+    assert_eq!(warmup.rate_at(warmup.start + 86400).unwrap().requests, 2);
+    assert_eq!(
+        warmup.rate_at(warmup.start + 2 * 86400).unwrap().requests,
+        4
+    );
+    assert_eq!(warmup.rate_at(warmup.start + 3 * 86400), None);
+
+    // Only the configured source IP is throttled
+    let source_ip = "10.0.0.10".parse::<IpAddr>().unwrap();
+    let other_ip = "10.0.0.11".parse::<IpAddr>().unwrap();
+    assert!(core.is_warmup_allowed(source_ip, &span).await.is_ok());
+    assert!(core.is_warmup_allowed(other_ip, &span).await.is_ok());
+    assert!(core.is_warmup_allowed(other_ip, &span).await.is_ok());
+    match core.is_warmup_allowed(source_ip, &span).await {
+        Err(smtp::queue::throttle::Error::Rate { retry_at }) => {
+            assert!(retry_at > now(), "Retry at: {}", retry_at)
+        }
+        result => panic!("Unexpected result: {result:?}"),
+    }
+}
+
 pub trait TestQueueEnvelope<'x> {
     fn test(message: &'x Message, current_domain: usize, mx: &'x str) -> Self;
 }
@@ -0,0 +1,146 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    outbound::TestServer,
+    session::{TestSession, VerifyResponse},
+};
+use smtp::queue::{QuotaKey, Status};
+use store::{
+    write::{QueueClass, ValueClass},
+    ValueKey,
+};
+
+const CONFIG: &str = r#"
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.rcpt]
+relay = true
+
+[[queue.quota]]
+match = "sender_domain = 'test.org'"
+key = ['sender']
+messages = 10
+enable = true
+
+[queue.dead-letter]
+enable = [{if = "sender_domain = 'test.org'", then = true},
+          {else = false}]
+"#;
+
+#[tokio::test]
+async fn queue_dead_letter() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::DEBUG)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Create temp dir for queue
+    let mut local = TestServer::new("smtp_queue_dead_letter_test", CONFIG, true).await;
+
+    // Create test message
+    let core = local.build_smtp();
+    let mut session = local.new_session();
+    let qr = &mut local.qr;
+
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    let attempt = qr.expect_message_then_deliver().await;
+    let queue_id = attempt.event.queue_id;
+
+    // Expect a failed DSN and the original message moved to the dead letter store
+    attempt.try_deliver(core.clone()).await;
+    qr.expect_message()
+        .await
+        .read_lines(qr)
+        .await
+        .assert_contains("Final-Recipient: rfc822;bill@foobar.org")
+        .assert_contains("Action: failed");
+    qr.read_event().await.assert_reload();
+    assert!(core.read_message(queue_id).await.is_none());
+    let dead_letter = core.read_dead_letter(queue_id).await.unwrap();
+    assert_eq!(dead_letter.message.return_path, "john@test.org");
+    assert!(matches!(
+        dead_letter.message.recipients[0].status,
+        Status::PermanentFailure(_)
+    ));
+
+    // Quotas are released while the message is in the dead letter store
+    assert!(dead_letter.message.quota_keys.is_empty());
+    let quota_key = match dead_letter.quota_keys.as_slice() {
+        [QuotaKey::Count { key, .. }] => {
+            ValueKey::from(ValueClass::Queue(QueueClass::QuotaCount(key.clone())))
+        }
+        keys => panic!("Unexpected quota keys: {keys:?}"),
+    };
+    assert_eq!(
+        core.core
+            .storage
+            .data
+            .get_counter(quota_key.clone())
+            .await
+            .unwrap(),
+        0
+    );
+
+    // The failure history is kept
+    assert_eq!(dead_letter.message.failures.len(), 1);
+    assert_eq!(dead_letter.message.failures[0].domain, "foobar.org");
+
+    // Requeue the dead letter, quotas are reserved again
+    assert!(dead_letter.requeue(&core).await);
+    qr.read_event().await.assert_reload();
+    assert!(core.read_dead_letter(queue_id).await.is_none());
+    let message = core.read_message(queue_id).await.unwrap();
+    assert_eq!(message.recipients[0].status, Status::Scheduled);
+    assert_eq!(message.domains[0].status, Status::Scheduled);
+    assert!(message.domains[0].expires > store::write::now());
+    assert_eq!(message.quota_keys.len(), 1);
+    assert_eq!(message.failures.len(), 1);
+    assert_eq!(
+        core.core
+            .storage
+            .data
+            .get_counter(quota_key.clone())
+            .await
+            .unwrap(),
+        1
+    );
+
+    // Archive and purge
+    let due = message.next_event().unwrap();
+    assert!(message.archive(&core, due).await);
+    let dead_letter = core.read_dead_letter(queue_id).await.unwrap();
+    assert!(dead_letter.purge(&core).await);
+    assert!(core.read_dead_letter(queue_id).await.is_none());
+    qr.clear_queue(&core).await;
+    qr.assert_queue_is_empty().await;
+}
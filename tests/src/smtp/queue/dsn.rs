@@ -102,6 +102,7 @@ async fn generate_dsn() {
         priority: 0,
         blob_hash: BlobHash::from(dsn_original.as_bytes()),
         quota_keys: vec![],
+        failures: vec![],
     };
     let span = tracing::span!(tracing::Level::INFO, "hi");
 
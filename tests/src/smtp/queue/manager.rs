@@ -154,6 +154,7 @@ pub fn new_message(id: u64) -> Message {
         env_id: None,
         priority: 0,
         quota_keys: vec![],
+        failures: vec![],
         blob_hash: Default::default(),
     }
 }
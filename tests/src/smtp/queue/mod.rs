@@ -22,6 +22,8 @@
 */
 
 pub mod concurrent;
+pub mod dead_letter;
 pub mod dsn;
 pub mod manager;
 pub mod retry;
+pub mod timeline;
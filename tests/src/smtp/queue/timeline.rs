@@ -0,0 +1,127 @@
+/*
+ * Copyright (c) 2023 Stalwart Labs Ltd.
+ *
+ * This file is part of Stalwart Mail Server.
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of
+ * the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ * in the LICENSE file at the top-level directory of this distribution.
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ *
+ * You can be released from the requirements of the AGPLv3 license by
+ * purchasing a commercial license. Please contact licensing@stalw.art
+ * for more details.
+*/
+
+use std::time::Duration;
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    outbound::TestServer,
+    session::{TestSession, VerifyResponse},
+};
+use smtp::queue::{
+    timeline::{Disposition, TimelineEventType},
+    Status,
+};
+
+const CONFIG: &str = r#"
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.rcpt]
+relay = true
+
+[queue.timeline]
+retention = "1s"
+"#;
+
+#[tokio::test]
+async fn queue_timeline() {
+    /*tracing::subscriber::set_global_default(
+        tracing_subscriber::FmtSubscriber::builder()
+            .with_max_level(tracing::Level::DEBUG)
+            .finish(),
+    )
+    .unwrap();*/
+
+    // Create temp dir for queue
+    let mut local = TestServer::new("smtp_queue_timeline_test", CONFIG, true).await;
+
+    // Create test message
+    let core = local.build_smtp();
+    let mut session = local.new_session();
+    let qr = &mut local.qr;
+
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    let attempt = qr.expect_message_then_deliver().await;
+    let queue_id = attempt.event.queue_id;
+    attempt.try_deliver(core.clone()).await;
+    qr.expect_message()
+        .await
+        .read_lines(qr)
+        .await
+        .assert_contains("Action: failed");
+    qr.read_event().await.assert_reload();
+
+    // Look up the message by its Message-ID
+    assert_eq!(
+        core.timeline_find("20030712040037.46341.5F8J@football.example.com")
+            .await,
+        vec![queue_id]
+    );
+    assert!(core.timeline_find("20030712040037.46341").await.is_empty());
+
+    // Verify the recorded events
+    let events = core
+        .timeline_read(queue_id)
+        .await
+        .into_iter()
+        .map(|e| e.event)
+        .collect::<Vec<_>>();
+    assert_eq!(events.len(), 4, "{events:?}");
+    assert!(matches!(
+        &events[0],
+        TimelineEventType::Accepted { remote_ip, helo, message_id, .. }
+        if remote_ip == "10.0.0.1" && helo == "mx.test.org" && message_id.is_some()
+    ));
+    assert!(matches!(
+        &events[1],
+        TimelineEventType::Queued { return_path, recipients, .. }
+        if return_path == "john@test.org" && recipients == &["bill@foobar.org"]
+    ));
+    assert!(matches!(
+        &events[2],
+        TimelineEventType::Attempt { domain, status: Status::PermanentFailure(_), recipients, .. }
+        if domain == "foobar.org" && recipients.len() == 1
+    ));
+    assert_eq!(
+        events[3],
+        TimelineEventType::Completed {
+            disposition: Disposition::Failed
+        }
+    );
+
+    // Expired timelines are removed when the store is purged
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    core.core.storage.data.purge_store().await.unwrap();
+    assert!(core.timeline_read(queue_id).await.is_empty());
+    assert!(core
+        .timeline_find("20030712040037.46341.5F8J@football.example.com")
+        .await
+        .is_empty());
+    qr.clear_queue(&core).await;
+}
@@ -40,6 +40,7 @@ pub struct DummyIo {
     pub tx_buf: Vec<u8>,
     pub rx_buf: Vec<u8>,
     pub tls: bool,
+    pub sni: Option<String>,
 }
 
 impl AsyncRead for DummyIo {
@@ -91,6 +92,10 @@ impl SessionStream for DummyIo {
     fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>) {
         ("".into(), "".into())
     }
+
+    fn tls_server_name(&self) -> Option<&str> {
+        self.sni.as_deref()
+    }
 }
 
 impl Unpin for DummyIo {}
@@ -122,6 +127,7 @@ impl TestSession for Session<DummyIo> {
                 rx_buf: vec![],
                 tx_buf: vec![],
                 tls: false,
+                sni: None,
             },
             data: SessionData::new(
                 "127.0.0.1".parse().unwrap(),
@@ -131,6 +137,7 @@ impl TestSession for Session<DummyIo> {
             ),
             params: SessionParameters::default(),
             in_flight: vec![],
+            qos: Default::default(),
             hostname: "localhost".to_string(),
         }
     }
@@ -374,6 +381,7 @@ impl TestServerInstance for ServerInstance {
                 implicit: false,
             },
             limiter: ConcurrencyLimiter::new(100),
+            qos: Default::default(),
             shutdown_rx,
             proxy_networks: vec![],
         }
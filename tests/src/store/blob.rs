@@ -21,10 +21,12 @@
  * for more details.
 */
 
+use std::time::Duration;
+
 use ahash::AHashMap;
 use store::{
     write::{blob::BlobQuota, now, BatchBuilder, BlobOp},
-    BlobClass, BlobStore, Serialize, Stores,
+    BlobClass, BlobStore, CompressionAlgo, Serialize, Stores, TieredBlobStore,
 };
 use utils::{config::Config, BlobHash};
 
@@ -40,6 +42,61 @@ pub async fn blob_tests() {
     for (store_id, blob_store) in &stores.blob_stores {
         println!("Testing blob store {}...", store_id);
         test_store(blob_store.clone()).await;
+        println!("Testing zstd compression on blob store {}...", store_id);
+        test_store(
+            blob_store
+                .clone()
+                .with_compression(CompressionAlgo::Zstd(3)),
+        )
+        .await;
+
+        // Blobs compressed earlier must remain readable after disabling compression
+        // when legacy markers are enabled
+        println!("Testing compression changes on blob store {}...", store_id);
+        const DATA: &[u8] =
+            b"Compressed blobs are decoded by their marker, not by the configured algorithm.";
+        let hash = BlobHash::from(DATA);
+        for algo in [CompressionAlgo::Lz4, CompressionAlgo::Zstd(3)] {
+            blob_store
+                .clone()
+                .with_compression(algo)
+                .put_blob(hash.as_slice(), DATA)
+                .await
+                .unwrap();
+            let uncompressed = blob_store
+                .clone()
+                .with_compression(CompressionAlgo::None)
+                .with_legacy_markers(true);
+            assert_eq!(
+                uncompressed
+                    .get_blob(hash.as_slice(), 0..usize::MAX)
+                    .await
+                    .unwrap()
+                    .unwrap(),
+                DATA
+            );
+            assert_eq!(
+                uncompressed
+                    .get_blob(hash.as_slice(), 11..usize::MAX)
+                    .await
+                    .unwrap()
+                    .unwrap(),
+                &DATA[11..]
+            );
+
+            // Without legacy markers the raw blob is returned as stored
+            assert_ne!(
+                blob_store
+                    .clone()
+                    .with_compression(CompressionAlgo::None)
+                    .get_blob(hash.as_slice(), 0..usize::MAX)
+                    .await
+                    .unwrap()
+                    .unwrap(),
+                DATA
+            );
+            assert!(uncompressed.delete_blob(hash.as_slice()).await.unwrap());
+        }
     }
 
     for (store_id, store) in stores.stores {
@@ -416,6 +473,69 @@ pub async fn blob_tests() {
                     ^ ct
             );
         }
+
+        // Test blob tiering
+        let hot_store: BlobStore = store.clone().into();
+        let cold_store = stores.blob_stores.get("fs").unwrap().clone();
+        let tiered_store = BlobStore::from(TieredBlobStore {
+            hot: hot_store.clone(),
+            cold: cold_store.clone(),
+            max_age: Some(Duration::from_secs(3600)),
+            archive: false,
+        });
+        let mut hashes = Vec::new();
+        for (blob, created) in [(b"tier-old", now() - 7200), (b"tier-new", now())] {
+            let hash = BlobHash::from(blob.as_slice());
+            tiered_store.put_blob(hash.as_ref(), blob).await.unwrap();
+            store
+                .write(
+                    BatchBuilder::new()
+                        .set(BlobOp::Commit { hash: hash.clone() }, created.serialize())
+                        .build_batch(),
+                )
+                .await
+                .unwrap();
+            hashes.push((hash, blob));
+        }
+        store.migrate_blobs(tiered_store.clone()).await.unwrap();
+
+        // Old blobs are moved to the cold tier, new blobs stay in the hot tier
+        for ((hash, blob), is_cold) in hashes.iter().zip([true, false]) {
+            assert_eq!(
+                hot_store
+                    .get_blob(hash.as_ref(), 0..usize::MAX)
+                    .await
+                    .unwrap()
+                    .is_some(),
+                !is_cold
+            );
+            assert_eq!(
+                cold_store
+                    .get_blob(hash.as_ref(), 0..usize::MAX)
+                    .await
+                    .unwrap()
+                    .is_some(),
+                is_cold
+            );
+            assert_eq!(
+                tiered_store
+                    .get_blob(hash.as_ref(), 0..usize::MAX)
+                    .await
+                    .unwrap()
+                    .unwrap(),
+                blob.to_vec()
+            );
+        }
+
+        // Deleting a blob removes it from both tiers
+        for (hash, _) in &hashes {
+            assert!(tiered_store.delete_blob(hash.as_ref()).await.unwrap());
+            assert!(tiered_store
+                .get_blob(hash.as_ref(), 0..usize::MAX)
+                .await
+                .unwrap()
+                .is_none());
+        }
     }
     temp_dir.delete();
 }
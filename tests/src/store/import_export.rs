@@ -27,8 +27,9 @@ use jmap_proto::types::{collection::Collection, property::Property};
 use store::{
     rand,
     write::{
-        AnyKey, BatchBuilder, BitmapClass, BitmapHash, BlobOp, DirectoryClass, LookupClass,
-        MaybeDynamicId, MaybeDynamicValue, Operation, QueueClass, QueueEvent, TagValue, ValueClass,
+        log::ChangeLogBuilder, AnyKey, BatchBuilder, BitmapClass, BitmapHash, BlobOp,
+        DirectoryClass, LookupClass, MaybeDynamicId, MaybeDynamicValue, Operation, QueueClass,
+        QueueEvent, TagValue, ValueClass,
     },
     *,
 };
@@ -257,7 +258,7 @@ pub async fn test(db: Store) {
     // Export store
     println!("Exporting store...");
     let temp_dir = TempDir::new("art_vandelay_tests", true);
-    core.backup(temp_dir.path.clone()).await;
+    core.backup(temp_dir.path.clone(), None).await;
 
     // Destroy store
     println!("Destroying store...");
@@ -273,9 +274,42 @@ pub async fn test(db: Store) {
     snapshot.assert_is_eq(&Snapshot::new(&db).await);
     println!(" GREAT SUCCESS!");
 
+    // Insert and delete documents after the last change id
+    println!("Creating incremental changes...");
+    let mut changelog = ChangeLogBuilder::with_change_id(1000);
+    changelog.log_insert(Collection::Email, 50u64);
+    changelog.log_delete(Collection::Email, 40u64);
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(0)
+        .with_collection(Collection::Email)
+        .create_document_with_id(50)
+        .set(ValueClass::Property(1), random_bytes(32))
+        .delete_document(40)
+        .custom(changelog);
+    db.write(batch.build()).await.unwrap();
+    let snapshot = Snapshot::new(&db).await;
+
+    // Export changes only
+    println!("Exporting incremental changes...");
+    let incremental_dir = TempDir::new("art_vandelay_incremental_tests", true);
+    core.backup(incremental_dir.path.clone(), Some(100)).await;
+
+    // Restore full and incremental snapshots
+    println!("Restoring incremental changes...");
+    db.destroy().await;
+    db.assert_is_empty(db.clone().into()).await;
+    core.restore(temp_dir.path.clone()).await;
+    core.restore(incremental_dir.path.clone()).await;
+
+    print!("Verifying store hash...");
+    snapshot.assert_is_eq(&Snapshot::new(&db).await);
+    println!(" GREAT SUCCESS!");
+
     // Destroy store
     db.destroy().await;
     temp_dir.delete();
+    incremental_dir.delete();
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -114,6 +114,25 @@ pub async fn lookup_tests() {
         store.purge_lookup_store().await.unwrap();
         assert_eq!(0, store.counter_get(key.clone()).await.unwrap());
 
+        // Test counter expiry without purging
+        let key = "ijk".as_bytes().to_vec();
+        assert_eq!(
+            1,
+            store
+                .counter_incr(key.clone(), 1, 1.into(), true)
+                .await
+                .unwrap()
+        );
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        assert_eq!(0, store.counter_get(key.clone()).await.unwrap());
+        assert_eq!(
+            1,
+            store
+                .counter_incr(key.clone(), 1, 1.into(), true)
+                .await
+                .unwrap()
+        );
+
         // Test rate limiter
         assert!(store
             .is_rate_allowed("rate".as_bytes(), &rate, false)
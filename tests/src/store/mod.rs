@@ -48,6 +48,14 @@ region = "eu-central-1"
 endpoint = "http://localhost:9000"
 bucket = "tmp"
 
+[store."azure"]
+type = "azure"
+account = "devstoreaccount1"
+access-key = "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw=="
+endpoint = "http://127.0.0.1:10000/devstoreaccount1"
+container = "tmp"
+block-size = 1048576
+
 [store."fs"]
 type = "fs"
 path = "{TMP}"